@@ -0,0 +1,73 @@
+#![feature(test)]
+
+extern crate test;
+extern crate vm;
+extern crate ecmascript;
+
+use vm::vm::Vm;
+use ecmascript::compiler::bytecode::{ CodeObject, Instruction, };
+use ecmascript::lexer::span::Loc;
+
+
+// NOTE: 循环 ITERATIONS 次，每次循环执行 UNROLL 次 `o.a = o.a + 1`（各一条 GetNamed 和 SetNamed），
+//       所有指令的缓存都是单态的，测试的是命中缓存时的开销。
+//
+//       cargo +nightly bench --bench inline_cache
+const ITERATIONS: i64 = 10_000;
+const UNROLL: usize = 16;
+
+
+/// `n = ITERATIONS; o = {}; o.a = 0; while (n) { o.a = o.a + 1; ... n--; } return o.a;`
+fn increment() -> CodeObject {
+    let loc = Loc::default();
+    let mut code = CodeObject::new("<script>");
+    code.local_count = 2;
+    let a = code.add_string("a");
+
+    code.emit(Instruction::Int(ITERATIONS), loc);
+    code.emit(Instruction::SetLocal(0), loc);
+    code.emit(Instruction::Pop, loc);
+    code.emit(Instruction::Object, loc);
+    code.emit(Instruction::SetLocal(1), loc);
+    code.emit(Instruction::Int(0), loc);
+    let cache = code.cache();
+    code.emit(Instruction::SetNamed(a, cache), loc);
+    code.emit(Instruction::Pop, loc);
+    let start = code.emit(Instruction::GetLocal(0), loc);
+    let exit = code.emit(Instruction::JumpIfFalse(0), loc);
+    for _ in 0..UNROLL {
+        code.emit(Instruction::GetLocal(1), loc);
+        code.emit(Instruction::GetLocal(1), loc);
+        let cache = code.cache();
+        code.emit(Instruction::GetNamed(a, cache), loc);
+        code.emit(Instruction::Int(1), loc);
+        code.emit(Instruction::Add, loc);
+        let cache = code.cache();
+        code.emit(Instruction::SetNamed(a, cache), loc);
+        code.emit(Instruction::Pop, loc);
+    }
+    code.emit(Instruction::GetLocal(0), loc);
+    code.emit(Instruction::Dec, loc);
+    code.emit(Instruction::SetLocal(0), loc);
+    code.emit(Instruction::Pop, loc);
+    let jump = code.emit(Instruction::Jump(0), loc);
+    let end = code.emit(Instruction::GetLocal(1), loc);
+    let cache = code.cache();
+    code.emit(Instruction::GetNamed(a, cache), loc);
+    code.emit(Instruction::Return, loc);
+    code.patch_jump(exit, end);
+    code.patch_jump(jump, start);
+
+    code
+}
+
+#[bench]
+fn bench_get_set_named(b: &mut test::Bencher) {
+    let code = increment();
+    b.iter(|| {
+        let mut vm = Vm::new();
+        let value = vm.run(code.clone()).unwrap();
+        assert_eq!(value.as_f64(), Some((ITERATIONS * UNROLL as i64) as f64));
+        test::black_box(value)
+    });
+}
@@ -0,0 +1,82 @@
+#![feature(test)]
+
+extern crate test;
+extern crate vm;
+extern crate ecmascript;
+
+use vm::vm::Vm;
+use ecmascript::vm::isolate::Isolate;
+use ecmascript::compiler::scope::Goal;
+use ecmascript::compiler::bytecode::CodeObject;
+
+
+// NOTE: 运行真实的脚本，分别在启用与不启用 nan-boxing 时运行，对比两种值的表示：
+//
+//       cargo +nightly bench --bench scripts
+//       cargo +nightly bench --bench scripts --features nan-boxing
+//
+//       解析器目前还不支持语句和循环，所以脚本都是用递归的箭头函数写的，
+//       调用参数中的第一个参数必须是简单的表达式，所以加了一个占位参数 `_` 。
+const FIB: &str = "fib = (_, n) => (n < 2) ? n : fib(0, n - 1) + fib(0, n - 2); fib(0, 20)";
+
+const PROPERTIES: &str = "o = Object(); o.x = 1; o.y = 2; \
+step = (_, n) => o.x = o.y + o.x * 0.5; \
+walk = (_, n) => (n == 0) ? o.x : step(0, n) * 0 + walk(0, n - 1); \
+walk(0, 2000)";
+
+const OBJECTS: &str = "proto = Object(); proto.y = 2; \
+mk = (_, n, p) => ((p = Object.create(proto)) == null) ? p : (p.x = n) + p.y; \
+sum = (_, n) => (n == 0) ? 0 : mk(0, n) + sum(0, n - 1); \
+sum(0, 2000)";
+
+const CLOSURES: &str = "inc = (n => () => n = n + 1)(0); \
+loop = (_, n) => (n == 0) ? inc() : inc() * 0 + loop(0, n - 1); \
+loop(0, 2000)";
+
+const ARRAYS: &str = "a = []; put = (_, n) => a[n] = n * 0.5; \
+fill = (_, n) => (n == 0) ? a.length : put(0, n) * 0 + fill(0, n - 1); \
+fill(0, 2000)";
+
+
+fn compile(script: &str) -> CodeObject {
+    Isolate::compile(script, "<bench>", Goal::Script).unwrap()
+}
+
+fn bench_script(b: &mut test::Bencher, script: &str) {
+    let code = compile(script);
+    b.iter(|| {
+        let mut vm = Vm::new();
+        test::black_box(vm.run(code.clone()).unwrap())
+    });
+}
+
+#[bench]
+fn bench_new_vm(b: &mut test::Bencher) {
+    // NOTE: 每次运行脚本都会创建新的 Vm ，这是其它测试中的固定开销
+    b.iter(|| test::black_box(Vm::new()));
+}
+
+#[bench]
+fn bench_fib(b: &mut test::Bencher) {
+    bench_script(b, FIB);
+}
+
+#[bench]
+fn bench_properties(b: &mut test::Bencher) {
+    bench_script(b, PROPERTIES);
+}
+
+#[bench]
+fn bench_objects(b: &mut test::Bencher) {
+    bench_script(b, OBJECTS);
+}
+
+#[bench]
+fn bench_closures(b: &mut test::Bencher) {
+    bench_script(b, CLOSURES);
+}
+
+#[bench]
+fn bench_arrays(b: &mut test::Bencher) {
+    bench_script(b, ARRAYS);
+}
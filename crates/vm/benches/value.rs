@@ -0,0 +1,114 @@
+#![feature(test)]
+
+extern crate test;
+extern crate vm;
+
+use vm::vm::Vm;
+use vm::value::Value;
+use vm::nanbox;
+
+use std::mem::size_of;
+
+
+// NOTE: 模拟属性较多的对象：OBJECTS 个对象，每个对象 FIELDS 个属性槽，
+//       数字、字符串与对象引用混合存放。
+//       `b.bytes` 是属性槽占用的内存，`value::Value` 是 16 个字节，`nanbox::Value` 是 8 个字节。
+//
+//       cargo +nightly bench --features nan-boxing --bench value
+const OBJECTS: usize = 10_000;
+const FIELDS: usize = 16;
+
+
+fn slots(vm: &mut Vm) -> Vec<Value> {
+    let string = vm.new_string("name");
+    let object = Value::Object(vm.new_object());
+    (0..OBJECTS * FIELDS)
+        .map(|index| match index % 4 {
+            0 => Value::I64(index as i64),
+            1 => Value::F64(index as f64 + 0.5),
+            2 => string.clone(),
+            _ => object.clone(),
+        })
+        .collect()
+}
+
+#[bench]
+fn bench_read_fields_enum(b: &mut test::Bencher) {
+    let mut vm = Vm::new();
+    let slots = slots(&mut vm);
+    b.bytes = (slots.len() * size_of::<Value>()) as u64;
+    b.iter(|| {
+        let mut sum = 0.0;
+        let mut objects = 0;
+        for value in slots.iter() {
+            match value.as_f64() {
+                Some(n) => sum += n,
+                None => if value.is_object() { objects += 1 },
+            }
+        }
+        test::black_box((sum, objects))
+    });
+}
+
+#[bench]
+fn bench_read_fields_nanbox(b: &mut test::Bencher) {
+    let mut vm = Vm::new();
+    let slots = slots(&mut vm).into_iter().map(nanbox::Value::from).collect::<Vec<nanbox::Value>>();
+    b.bytes = (slots.len() * size_of::<nanbox::Value>()) as u64;
+    b.iter(|| {
+        let mut sum = 0.0;
+        let mut objects = 0;
+        for value in slots.iter() {
+            match value.as_f64() {
+                Some(n) => sum += n,
+                None => if value.is_object() { objects += 1 },
+            }
+        }
+        test::black_box((sum, objects))
+    });
+}
+
+#[bench]
+fn bench_write_fields_enum(b: &mut test::Bencher) {
+    let mut vm = Vm::new();
+    let mut slots = slots(&mut vm);
+    b.bytes = (slots.len() * size_of::<Value>()) as u64;
+    b.iter(|| {
+        // NOTE: 每个对象的第一个属性 `x += 1`
+        for fields in slots.chunks_mut(FIELDS) {
+            let n = fields[0].as_f64().unwrap_or(0.0);
+            fields[0] = Value::number(n + 1.0);
+        }
+        test::black_box(&slots);
+    });
+}
+
+#[bench]
+fn bench_write_fields_nanbox(b: &mut test::Bencher) {
+    let mut vm = Vm::new();
+    let mut slots = slots(&mut vm).into_iter().map(nanbox::Value::from).collect::<Vec<nanbox::Value>>();
+    b.bytes = (slots.len() * size_of::<nanbox::Value>()) as u64;
+    b.iter(|| {
+        for fields in slots.chunks_mut(FIELDS) {
+            let n = fields[0].as_f64().unwrap_or(0.0);
+            fields[0] = nanbox::Value::number(n + 1.0);
+        }
+        test::black_box(&slots);
+    });
+}
+
+#[bench]
+fn bench_copy_objects_enum(b: &mut test::Bencher) {
+    let mut vm = Vm::new();
+    let slots = slots(&mut vm);
+    b.bytes = (slots.len() * size_of::<Value>()) as u64;
+    b.iter(|| test::black_box(slots.clone()));
+}
+
+#[bench]
+fn bench_copy_objects_nanbox(b: &mut test::Bencher) {
+    let mut vm = Vm::new();
+    let slots = slots(&mut vm).into_iter().map(nanbox::Value::from).collect::<Vec<nanbox::Value>>();
+    b.bytes = (slots.len() * size_of::<nanbox::Value>()) as u64;
+    b.iter(|| test::black_box(slots.clone()));
+}
@@ -49,7 +49,8 @@ pub enum EnvironmentKind {
     Module,
     Function,
     Declarative,
-    // Object,   // deprecated
+    // NOTE: `with` 语句，绑定是对象的属性
+    Object,
 
     // VAR 申明需要在上级非 Bloc 作用域进行
     Block,
@@ -60,6 +61,7 @@ pub struct Environment {
     kind: EnvironmentKind,
     records: FxHashMap<String, Record>,
     parent: Option<NonNull<Environment>>,
+    object: Option<NonNull<Object>>,
 }
 
 impl Default for Environment {
@@ -78,6 +80,29 @@ impl Environment {
             kind,
             records,
             parent,
+            object: None,
+        }
+    }
+
+    /// The environment of a `with` statement.
+    #[inline]
+    pub fn with_object(object: NonNull<Object>, parent: NonNull<Environment>) -> Self {
+        Environment {
+            kind: EnvironmentKind::Object,
+            records: FxHashMap::default(),
+            parent: Some(parent),
+            object: Some(object),
+        }
+    }
+
+    /// A new environment whose parent is `parent`.
+    #[inline]
+    pub fn with_parent(kind: EnvironmentKind, parent: NonNull<Environment>) -> Self {
+        Environment {
+            kind,
+            records: FxHashMap::default(),
+            parent: Some(parent),
+            object: None,
         }
     }
 
@@ -105,6 +130,7 @@ impl Environment {
             kind,
             records,
             parent,
+            object: None,
         }
     }
 
@@ -126,6 +152,27 @@ impl Environment {
         }
     }
 
+    #[inline]
+    pub fn parent_ptr(&self) -> Option<NonNull<Environment>> {
+        self.parent
+    }
+
+    /// The binding object of `with` environments.
+    #[inline]
+    pub fn object(&self) -> Option<NonNull<Object>> {
+        self.object
+    }
+
+    #[inline]
+    pub fn record<K: AsRef<str>>(&self, k: K) -> Option<&Record> {
+        self.records.get(k.as_ref())
+    }
+
+    #[inline]
+    pub fn records(&self) -> impl Iterator<Item=&Record> {
+        self.records.values()
+    }
+
     #[inline]
     pub fn is_empty(&self) -> bool {
         self.records.is_empty()
@@ -1,3 +1,5 @@
+use crate::value::Value;
+
 use std::fmt;
 
 
@@ -54,6 +56,8 @@ pub enum NativeError {
 
     // non-standard
     InternalError(String),
+    /// An ECMAScript value thrown by `throw`, or an error object that was not caught.
+    Exception(Value),
 }
 
 impl fmt::Display for NativeError {
@@ -70,6 +74,7 @@ impl fmt::Display for NativeError {
             TypeError(message) => write!(f, "TypeError: {}", message),
             URIError(message) => write!(f, "URIError: {}", message),
             InternalError(message) => write!(f, "InternalError: {}", message),
+            Exception(value) => write!(f, "Uncaught {}", value),
         }
     }
 }
@@ -0,0 +1,177 @@
+// Call frames
+//
+//      function f(a) { return g(a) + 1; }
+//
+//      frames:  [ <script> ] [ f: pc, stack, locals ] [ g: ... ]
+//
+// NOTE: 每次调用字节码函数都会压入一个 `Frame`，解释器只在栈顶的 Frame 上执行，
+//       字节码函数之间的调用不会递归调用解释器本身，调用深度只受 `Vm` 的限制。
+//       生成器暂停时，它的 Frame 从调用栈上移除，保存在生成器对象中。
+
+use crate::value::{ Value, PackedValue, };
+use crate::env::Environment;
+use crate::object::Object;
+use crate::function::Function;
+use crate::inline_cache::{ InlineCache, CacheEntry, };
+
+use ecmascript::compiler::bytecode::{ CodeObject, CodeFlags, Constant, Instruction, };
+
+use std::rc::Rc;
+use std::cell::UnsafeCell;
+use std::ptr::NonNull;
+
+
+/// A `CodeObject` prepared for the interpreter, the nested functions are shared by their closures.
+#[derive(Debug)]
+pub struct Code {
+    pub object: CodeObject,
+    functions: Vec<Option<Rc<Code>>>,
+    /// `arguments` is used by the code or by the arrow functions in it.
+    pub uses_arguments: bool,
+    /// The inline caches of `GetNamed` and `SetNamed`, by the `cache` operand of the instruction.
+    caches: Box<[UnsafeCell<InlineCache>]>,
+}
+
+impl Code {
+    pub fn new(mut object: CodeObject) -> Rc<Code> {
+        // NOTE: 内层函数从常量表中移出，常量表中留下一个空的 CodeObject
+        let functions = object.constants.iter_mut()
+            .map(|constant| match *constant {
+                Constant::Function(ref mut code) => {
+                    Some(Code::new(std::mem::replace(&mut **code, CodeObject::new(""))))
+                },
+                _ => None,
+            })
+            .collect::<Vec<Option<Rc<Code>>>>();
+
+        let uses_arguments = object.instructions()
+            .map(|instructions| instructions.iter().any(|&(_, instruction)| instruction == Instruction::Arguments))
+            .unwrap_or(false)
+            || functions.iter().any(|code| match *code {
+                Some(ref code) => code.object.flags.contains(CodeFlags::ARROW) && code.uses_arguments,
+                None => false,
+            });
+
+        let caches = (0..object.cache_count).map(|_| UnsafeCell::new(InlineCache::Uninitialized)).collect();
+
+        Rc::new(Code { object, functions, uses_arguments, caches })
+    }
+
+    /// The nested function in the constant table.
+    #[inline]
+    pub fn function(&self, index: u32) -> Option<&Rc<Code>> {
+        self.functions.get(index as usize).and_then(|code| code.as_ref())
+    }
+
+    /// The inline cache of a `GetNamed` or `SetNamed` instruction.
+    ///
+    /// # Safety
+    ///
+    /// The reference must be dropped before the cache is updated, that is before running any code
+    /// which may execute the same instruction ( getters, setters, proxies ... ).
+    #[inline]
+    pub unsafe fn cache(&self, index: u32) -> Option<&InlineCache> {
+        // NOTE: 缓存只在解释器的线程上访问，读取时不持有 RefCell 的借用，
+        //       慢路径（可能重新进入解释器）结束之后才通过 `update_cache` 修改缓存。
+        self.caches.get(index as usize).map(|cache| &*cache.get())
+    }
+
+    /// Add an entry to the inline cache.
+    ///
+    /// # Safety
+    ///
+    /// No reference returned by `Code::cache` is alive.
+    #[inline]
+    pub unsafe fn update_cache(&self, index: u32, entry: CacheEntry) {
+        if let Some(cache) = self.caches.get(index as usize) {
+            (*cache.get()).add(entry);
+        }
+    }
+}
+
+
+// NOTE: 缓存是运行时的状态，不参与比较
+impl PartialEq for Code {
+    fn eq(&self, other: &Code) -> bool {
+        self.object == other.object && self.functions == other.functions && self.uses_arguments == other.uses_arguments
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub code: Rc<Code>,
+    /// Offset of the next instruction.
+    pub pc: usize,
+    /// Offset of the running instruction, exception handlers are looked up with it.
+    pub ip: usize,
+    pub stack: Vec<PackedValue>,
+    pub locals: Vec<PackedValue>,
+    pub cells: Vec<Option<NonNull<PackedValue>>>,
+    /// `None` for Scripts and Modules.
+    pub function: Option<NonNull<Function>>,
+    /// `<hole>` before `super()` returns in derived constructors.
+    pub this: Value,
+    pub new_target: Value,
+    pub arguments: Vec<Value>,
+    pub arguments_object: Option<Value>,
+    pub env: NonNull<Environment>,
+    /// The function has its own environment for the variables declared by `DeclareName`.
+    pub has_own_env: bool,
+    pub is_construct: bool,
+    /// The frame of the parent constructor called by `super()`, the result is bound to `this` of the caller.
+    pub is_super_call: bool,
+    /// The generator object which owns this frame.
+    pub generator: Option<NonNull<Object>>,
+}
+
+impl Frame {
+    pub fn new(code: Rc<Code>, function: Option<NonNull<Function>>, this: Value, arguments: Vec<Value>, env: NonNull<Environment>) -> Self {
+        let param_count = code.object.param_count as usize;
+        let local_count = std::cmp::max(code.object.local_count as usize, param_count);
+
+        let mut locals = Vec::with_capacity(local_count);
+        locals.extend(arguments.iter().take(param_count).cloned().map(PackedValue::from));
+        locals.resize(local_count, PackedValue::from(Value::Undefined));
+
+        Frame {
+            code,
+            pc: 0,
+            ip: 0,
+            stack: Vec::new(),
+            locals,
+            cells: vec![ None; local_count ],
+            function,
+            this,
+            new_target: Value::Undefined,
+            arguments,
+            arguments_object: None,
+            env,
+            has_own_env: false,
+            is_construct: false,
+            is_super_call: false,
+            generator: None,
+        }
+    }
+
+    #[inline]
+    pub fn flags(&self) -> CodeFlags {
+        self.code.object.flags
+    }
+}
+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GeneratorState {
+    SuspendedStart,
+    SuspendedYield,
+    Executing,
+    Completed,
+}
+
+#[derive(Debug, Clone)]
+pub struct Generator {
+    pub state: GeneratorState,
+    /// The suspended frame, `None` when the generator is running or completed.
+    pub frame: Option<Frame>,
+}
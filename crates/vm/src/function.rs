@@ -1,33 +1,38 @@
-use crate::value::{ Value, ValueKind, };
+use crate::value::{ Value, ValueKind, PackedValue, };
 use crate::error::NativeError;
 use crate::env::{ Environment, };
 use crate::object::{ Object, Property, PropertyKey, };
+use crate::frame::Code;
 use crate::vm::{ Vm, };
 
+use ecmascript::compiler::bytecode::CodeFlags;
 
 use std::fmt;
+use std::rc::Rc;
 use std::ptr::NonNull;
 
 
-pub type NativeFunction = fn(NonNull<Vm>) -> Result<Value, NativeError>;
+/// `fn(vm, this, arguments)`, `this` is `<hole>` when the function is called by `new`.
+pub type NativeFunction = fn(&mut Vm, Value, &[Value]) -> Result<Value, NativeError>;
 
 
-pub fn isNaN(vm: NonNull<Vm>) -> Result<Value, NativeError> {
-    unimplemented!()
+pub fn isNaN(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let n = vm.to_number(args.first().cloned().unwrap_or(Value::Undefined))?;
+    Ok(Value::Boolean(n.is_nan()))
 }
 
 
 #[derive(Debug, PartialEq)]
 pub enum FunctionCode {
     NativeCode(NativeFunction),
-    ByteCode(NonNull<Vec<u8>>),
+    ByteCode(Rc<Code>),
 }
 
 impl Clone for FunctionCode {
     fn clone(&self) -> Self {
         match *self {
             FunctionCode::NativeCode(f) => FunctionCode::NativeCode(f),
-            FunctionCode::ByteCode(f) => FunctionCode::ByteCode(f),
+            FunctionCode::ByteCode(ref code) => FunctionCode::ByteCode(code.clone()),
         }
     }
 }
@@ -40,6 +45,17 @@ pub struct Function {
     code: FunctionCode,
     // prototype
     object: NonNull<Object>,
+    /// The Cells captured by the closure, see `CodeObject::captures`.
+    pub captures: Vec<NonNull<PackedValue>>,
+    /// The object which the method is defined on, for `super` lookups.
+    pub home_object: Option<NonNull<Object>>,
+    /// The environment of the code that created the closure, for variables looked up by name.
+    pub env: Option<NonNull<Environment>>,
+    // NOTE: 箭头函数在创建时绑定外层函数的 `this`、`new.target` 与 `arguments`
+    pub this_value: Value,
+    pub new_target: Value,
+    pub arguments: Value,
+    pub is_constructor: bool,
 }
 
 impl Function {
@@ -48,29 +64,26 @@ impl Function {
         Self {
             name: name.into().map(|s| s.into()),
             code,
-            object: object.as_raw_non_null()
+            object: object.as_raw_non_null(),
+            captures: Vec::new(),
+            home_object: None,
+            env: None,
+            this_value: Value::Undefined,
+            new_target: Value::Undefined,
+            arguments: Value::Undefined,
+            is_constructor: false,
         }
     }
 
     pub fn set_name<T: Into<String>>(&mut self, name: T) {
-        let name: &String = &name.into();
-        let value = unsafe {
-            Value::String(NonNull::new( std::mem::transmute::<&String, *mut String>(name) ).unwrap())
-        };
-        self.object_mut().insert("name", Property {
-            enumerable: false,
-            configurable: false,
-            value: value,
-            writable: false,
-            getter: Value::Undefined,
-            setter: Value::Undefined,
-        });
+        let name = name.into();
+        self.name = Some(name);
     }
 
     #[inline]
-    pub fn name(&self) -> &Value {
-        match &self.object().get("name") {
-            Some(ref property) => &property.value,
+    pub fn name(&self) -> Value {
+        match self.object().get("name") {
+            Some(property) => property.value(),
             None => unreachable!(),
         }
     }
@@ -80,6 +93,15 @@ impl Function {
         &self.code
     }
 
+    /// Flags of the compiled code, empty for native functions.
+    #[inline]
+    pub fn flags(&self) -> CodeFlags {
+        match self.code {
+            FunctionCode::NativeCode(_) => CodeFlags::empty(),
+            FunctionCode::ByteCode(ref code) => code.object.flags,
+        }
+    }
+
     #[inline]
     pub fn object(&self) -> &Object {
         unsafe { self.object.as_ref() }
@@ -89,6 +111,9 @@ impl Function {
     pub fn object_mut(&mut self) -> &mut Object {
         unsafe { self.object.as_mut() }
     }
-}
-
 
+    #[inline]
+    pub fn object_ptr(&self) -> NonNull<Object> {
+        self.object
+    }
+}
@@ -0,0 +1,549 @@
+// Garbage collection
+//
+//      roots:  global object, global environment, intrinsics, frames, handles, cached constants
+//         |
+//         v
+//      mark:   Value -> Object -> properties, prototype, kind ( function, iterator target, generator frame )
+//                    -> Function -> object, captured Cells, home object, environment, bound `this` ...
+//                    -> Environment -> records, parent, `with` object
+//      sweep:  everything in the `Allocator` that is not marked is finalized and freed
+//
+//      generational mode:
+//          minor:  roots + remembered old values -> young values only, the survivors are promoted
+//          major:  the whole heap, as above
+//
+// NOTE: 标记阶段使用显式的工作栈（gray），不会因为很长的链表或者很深的原型链递归溢出 Rust 的栈，
+//       对象之间的环（`a.b = b; b.a = a;`）没有特殊处理，已经标记过的对象不会再次加入工作栈。
+//
+//       回收只在安全点进行：最外层的 `dispatch` 在两条指令之间（`native_depth == 0`），
+//       这时所有存活的值都在 Frame 中（操作数栈、局部变量、Cell ...）。
+//       Native 函数在 Rust 的栈上持有的值没有登记为根，所以在它们（以及它们嵌套调用的字节码）运行期间不会回收，
+//       需要跨越回收保存值的代码（嵌入者）应该使用 `Handle`。
+//
+//       分代模式下，次要回收不遍历老年代，而是把老年代当作存活的对象。
+//       老年代对象被写入新的引用时（属性、原型、Cell、环境记录、挂起的生成器 Frame ...）
+//       必须调用 `Vm::write_barrier`，把它加入记忆集，次要回收时从记忆集中的对象出发标记新生代。
+
+use rustc_hash::FxHashSet;
+
+use crate::value::Value;
+use crate::object::{ Object, ObjectKind, Property, };
+use crate::function::Function;
+use crate::env::Environment;
+use crate::frame::Frame;
+use crate::intrinsics::Intrinsics;
+use crate::error::NativeError;
+use crate::vm::{ Vm, Allocation, HeapRef, GcMode, Collection, };
+
+use ecmascript::vm::isolate::HeapStats;
+
+use std::ptr::NonNull;
+use std::time::Instant;
+
+
+/// Marks the values reachable from the roots.
+#[derive(Debug, Default)]
+pub struct Tracer {
+    marked: FxHashSet<HeapRef>,
+    /// Marked values whose children are not traced yet.
+    gray: Vec<HeapRef>,
+    /// Only these values are marked by minor collections.
+    young: Option<FxHashSet<HeapRef>>,
+}
+
+impl Tracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark the young values, the others are not traced.
+    pub fn young(young: FxHashSet<HeapRef>) -> Self {
+        Self { young: Some(young), ..Self::default() }
+    }
+
+    #[inline]
+    pub fn is_young(&self, item: &HeapRef) -> bool {
+        self.young.as_ref().map(|young| young.contains(item)).unwrap_or(true)
+    }
+
+    #[inline]
+    pub fn mark<T: Allocation>(&mut self, ptr: NonNull<T>) {
+        let item = T::heap_ref(ptr);
+        if self.is_young(&item) && self.marked.insert(item) {
+            self.gray.push(item);
+        }
+    }
+
+    #[inline]
+    pub fn value(&mut self, value: &Value) {
+        match *value {
+            Value::String(ptr) => self.mark(ptr),
+            Value::BigInt(ptr) => self.mark(ptr),
+            Value::Object(ptr) => self.mark(ptr),
+            Value::Function(ptr) => self.mark(ptr),
+            _ => { },
+        }
+    }
+
+    #[inline]
+    pub fn is_marked(&self, item: &HeapRef) -> bool {
+        self.marked.contains(item)
+    }
+
+    /// Trace the children of the value.
+    pub fn scan(&mut self, item: HeapRef) {
+        unsafe {
+            match item {
+                HeapRef::String(_) | HeapRef::BigInt(_) => { },
+                HeapRef::Object(ptr) => ptr.as_ref().trace(self),
+                HeapRef::Function(ptr) => ptr.as_ref().trace(self),
+                HeapRef::Environment(ptr) => ptr.as_ref().trace(self),
+                HeapRef::Cell(ptr) => ptr.as_ref().trace(self),
+            }
+        }
+    }
+
+    /// Trace the children of the marked values until nothing is left.
+    pub fn finish(mut self) -> FxHashSet<HeapRef> {
+        while let Some(item) = self.gray.pop() {
+            self.scan(item);
+        }
+
+        self.marked
+    }
+}
+
+
+pub trait Trace {
+    fn trace(&self, tracer: &mut Tracer);
+}
+
+impl Trace for Value {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.value(self)
+    }
+}
+
+#[cfg(feature = "nan-boxing")]
+impl Trace for crate::nanbox::Value {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.value(&self.unpack())
+    }
+}
+
+impl Trace for Property {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.value.trace(tracer);
+        self.getter.trace(tracer);
+        self.setter.trace(tracer);
+    }
+}
+
+impl Trace for Object {
+    fn trace(&self, tracer: &mut Tracer) {
+        for property in self.properties.values() {
+            property.trace(tracer);
+        }
+        if let Some(prototype) = self.prototype {
+            tracer.mark(prototype);
+        }
+
+        match self.kind {
+            ObjectKind::Function(function) => tracer.mark(function),
+            ObjectKind::String(ptr) => tracer.mark(ptr),
+            ObjectKind::ArrayIterator { ref target, .. } => tracer.value(target),
+            ObjectKind::StringIterator { target: Some(ptr), .. } => tracer.mark(ptr),
+            ObjectKind::ForInIterator { object: Some(object), .. } => tracer.mark(object),
+            ObjectKind::Generator(ref generator) => {
+                if let Some(ref frame) = generator.frame {
+                    frame.trace(tracer);
+                }
+            },
+            _ => { },
+        }
+    }
+}
+
+impl Trace for Function {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.mark(self.object_ptr());
+        for cell in self.captures.iter() {
+            tracer.mark(*cell);
+        }
+        if let Some(home_object) = self.home_object {
+            tracer.mark(home_object);
+        }
+        if let Some(env) = self.env {
+            tracer.mark(env);
+        }
+        tracer.value(&self.this_value);
+        tracer.value(&self.new_target);
+        tracer.value(&self.arguments);
+    }
+}
+
+impl Trace for Environment {
+    fn trace(&self, tracer: &mut Tracer) {
+        for record in self.records() {
+            tracer.value(&record.value);
+        }
+        if let Some(parent) = self.parent_ptr() {
+            tracer.mark(parent);
+        }
+        if let Some(object) = self.object() {
+            tracer.mark(object);
+        }
+    }
+}
+
+impl Trace for Frame {
+    fn trace(&self, tracer: &mut Tracer) {
+        for value in self.stack.iter().chain(self.locals.iter()) {
+            value.trace(tracer);
+        }
+        for value in self.arguments.iter() {
+            tracer.value(value);
+        }
+        for cell in self.cells.iter().filter_map(|cell| *cell) {
+            tracer.mark(cell);
+        }
+        if let Some(function) = self.function {
+            tracer.mark(function);
+        }
+        tracer.value(&self.this);
+        tracer.value(&self.new_target);
+        if let Some(ref arguments) = self.arguments_object {
+            tracer.value(arguments);
+        }
+        tracer.mark(self.env);
+        if let Some(generator) = self.generator {
+            tracer.mark(generator);
+        }
+    }
+}
+
+impl Trace for Intrinsics {
+    fn trace(&self, tracer: &mut Tracer) {
+        let prototypes = [
+            self.object_prototype, self.function_prototype, self.array_prototype, self.string_prototype,
+            self.number_prototype, self.boolean_prototype, self.symbol_prototype, self.bigint_prototype,
+            self.regexp_prototype, self.error_prototype, self.syntax_error_prototype, self.eval_error_prototype,
+            self.range_error_prototype, self.reference_error_prototype, self.type_error_prototype,
+            self.uri_error_prototype, self.internal_error_prototype, self.iterator_prototype,
+            self.array_iterator_prototype, self.generator_prototype,
+        ];
+        for prototype in prototypes.iter() {
+            tracer.mark(*prototype);
+        }
+        tracer.value(&self.array_values);
+    }
+}
+
+// NOTE: `Vm` 自身就是根集合
+impl Trace for Vm {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.mark(self.global);
+        tracer.mark(self.global_env);
+        self.intrinsics.trace(tracer);
+        for frame in self.frames.iter() {
+            frame.trace(tracer);
+        }
+        for value in self.handles.iter().filter_map(|value| value.as_ref()) {
+            tracer.value(value);
+        }
+        for value in self.constants.values() {
+            tracer.value(value);
+        }
+    }
+}
+
+
+/// A value kept alive by the collector until it is released, see `Vm::root`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Handle(usize);
+
+impl Vm {
+    /// Keep the value alive across collections.
+    pub fn root(&mut self, value: Value) -> Handle {
+        match self.handles.iter().position(|slot| slot.is_none()) {
+            Some(index) => {
+                self.handles[index] = Some(value);
+                Handle(index)
+            },
+            None => {
+                self.handles.push(Some(value));
+                Handle(self.handles.len() - 1)
+            },
+        }
+    }
+
+    pub fn handle(&self, handle: Handle) -> Option<&Value> {
+        self.handles.get(handle.0).and_then(|value| value.as_ref())
+    }
+
+    /// Release the handle, the value can be collected when nothing else refers to it.
+    pub fn unroot(&mut self, handle: Handle) -> Option<Value> {
+        self.handles.get_mut(handle.0).and_then(|value| value.take())
+    }
+
+    /// Collect the values that are not reachable from the roots.
+    ///
+    /// Values held only by Rust code ( other than `Handle`s ) are freed, do not call it while
+    /// native code that holds such values is running.
+    pub fn collect_garbage(&mut self) {
+        self.collect(Collection::Major);
+    }
+
+    /// Run a minor or a major collection, see `collect_garbage`.
+    pub fn collect(&mut self, collection: Collection) {
+        let start = Instant::now();
+        let marked = match collection {
+            Collection::Minor => self.allocator.mark_young(&*self),
+            Collection::Major => self.allocator.mark(&*self),
+        };
+        self.allocator.sweep(&marked, collection);
+        self.allocator.record_pause(start.elapsed());
+    }
+
+    pub fn set_gc_mode(&mut self, mode: GcMode) {
+        self.allocator.set_mode(mode);
+    }
+
+    /// Bytes allocated in the young generation between minor collections.
+    pub fn set_nursery_size(&mut self, size: usize) {
+        self.allocator.set_nursery_size(size);
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        self.allocator.stats()
+    }
+
+    /// Must be called after storing a reference into a value that is already on the heap.
+    #[inline]
+    pub(crate) fn write_barrier<T: Allocation>(&mut self, ptr: NonNull<T>) {
+        self.allocator.remember(T::heap_ref(ptr));
+    }
+
+    /// Called by the interpreter between instructions, collects when the heap has grown past its threshold.
+    pub(crate) fn safe_point(&mut self) -> Result<(), NativeError> {
+        if self.native_depth > 0 {
+            return Ok(());
+        }
+        let collection = match self.allocator.pending() {
+            Some(collection) => collection,
+            None => return Ok(()),
+        };
+
+        self.collect(collection);
+        if self.allocator.is_over_limit() {
+            return Err(NativeError::range_error("Out of memory"));
+        }
+
+        Ok(())
+    }
+}
+
+
+/// `count` times: `a = {}; a.b = {}; a.b.a = a;`, with `keep` the pairs are also linked to the previous ones.
+#[cfg(test)]
+fn cycles(count: i64, keep: bool) -> ecmascript::compiler::bytecode::CodeObject {
+    use ecmascript::compiler::bytecode::{ CodeObject, Instruction, };
+    use ecmascript::lexer::span::Loc;
+
+    let loc = Loc::default();
+    let mut code = CodeObject::new("<script>");
+    code.local_count = 3;
+    let a = code.add_string("a");
+    let b = code.add_string("b");
+    let next = code.add_string("next");
+
+    code.emit(Instruction::Int(count), loc);
+    code.emit(Instruction::SetLocal(0), loc);
+    code.emit(Instruction::Pop, loc);
+    let start = code.emit(Instruction::GetLocal(0), loc);
+    let exit = code.emit(Instruction::JumpIfFalse(0), loc);
+    code.emit(Instruction::Object, loc);
+    code.emit(Instruction::SetLocal(1), loc);
+    code.emit(Instruction::Pop, loc);
+    code.emit(Instruction::GetLocal(1), loc);
+    code.emit(Instruction::Object, loc);
+    let cache = code.cache();
+    code.emit(Instruction::SetNamed(b, cache), loc);
+    code.emit(Instruction::GetLocal(1), loc);
+    let cache = code.cache();
+    code.emit(Instruction::SetNamed(a, cache), loc);
+    code.emit(Instruction::Pop, loc);
+    if keep {
+        code.emit(Instruction::GetLocal(1), loc);
+        code.emit(Instruction::GetLocal(2), loc);
+        let cache = code.cache();
+        code.emit(Instruction::SetNamed(next, cache), loc);
+        code.emit(Instruction::Pop, loc);
+        code.emit(Instruction::GetLocal(1), loc);
+        code.emit(Instruction::SetLocal(2), loc);
+        code.emit(Instruction::Pop, loc);
+    }
+    code.emit(Instruction::GetLocal(0), loc);
+    code.emit(Instruction::Dec, loc);
+    code.emit(Instruction::SetLocal(0), loc);
+    code.emit(Instruction::Pop, loc);
+    let jump = code.emit(Instruction::Jump(0), loc);
+    let end = code.emit(Instruction::Undefined, loc);
+    code.emit(Instruction::Return, loc);
+    code.patch_jump(exit, end);
+    code.patch_jump(jump, start);
+
+    code
+}
+
+/// `list = {}; count times: list.head = { next: list.head }; return list;`
+#[cfg(test)]
+fn linked_list(count: i64) -> ecmascript::compiler::bytecode::CodeObject {
+    use ecmascript::compiler::bytecode::{ CodeObject, Instruction, };
+    use ecmascript::lexer::span::Loc;
+
+    let loc = Loc::default();
+    let mut code = CodeObject::new("<script>");
+    code.local_count = 2;
+    let head = code.add_string("head");
+    let next = code.add_string("next");
+
+    code.emit(Instruction::Int(count), loc);
+    code.emit(Instruction::SetLocal(0), loc);
+    code.emit(Instruction::Pop, loc);
+    code.emit(Instruction::Object, loc);
+    code.emit(Instruction::SetLocal(1), loc);
+    code.emit(Instruction::Pop, loc);
+    let start = code.emit(Instruction::GetLocal(0), loc);
+    let exit = code.emit(Instruction::JumpIfFalse(0), loc);
+    code.emit(Instruction::GetLocal(1), loc);
+    code.emit(Instruction::Object, loc);
+    code.emit(Instruction::GetLocal(1), loc);
+    let cache = code.cache();
+    code.emit(Instruction::GetNamed(head, cache), loc);
+    code.emit(Instruction::DefineNamed(next), loc);
+    let cache = code.cache();
+    code.emit(Instruction::SetNamed(head, cache), loc);
+    code.emit(Instruction::Pop, loc);
+    code.emit(Instruction::GetLocal(0), loc);
+    code.emit(Instruction::Dec, loc);
+    code.emit(Instruction::SetLocal(0), loc);
+    code.emit(Instruction::Pop, loc);
+    let jump = code.emit(Instruction::Jump(0), loc);
+    let end = code.emit(Instruction::GetLocal(1), loc);
+    code.emit(Instruction::Return, loc);
+    code.patch_jump(exit, end);
+    code.patch_jump(jump, start);
+
+    code
+}
+
+#[test]
+fn test_collect_cycles() {
+    use crate::object::Property;
+    use crate::frame::Code;
+    use ecmascript::compiler::bytecode::CodeObject;
+    use ecmascript::compiler::verifier;
+    use std::rc::Rc;
+
+    let mut vm = Vm::new();
+    vm.collect_garbage();
+    let live = vm.allocator().len();
+    let bytes = vm.allocator().bytes();
+    // NOTE: 内置对象都是可达的
+    vm.collect_garbage();
+    assert_eq!(vm.allocator().len(), live);
+
+    // 不可达的环在内存上限之内被反复回收
+    let limit = bytes + 256 * 1024;
+    vm.set_heap_limit(Some(limit));
+    let code = cycles(50_000, false);
+    assert_eq!(verifier::verify(&code), Ok(()));
+    assert!(vm.run(code).is_ok());
+    assert!(vm.allocator().collections() > 0);
+    assert!(vm.allocator().bytes() <= limit);
+    vm.collect_garbage();
+    assert_eq!(vm.allocator().len(), live);
+
+    // Handle 保持对象存活
+    let mut a = vm.new_object();
+    let b = vm.new_object();
+    unsafe { a.as_mut() }.insert("b", Property::data(Value::Object(b)));
+    let handle = vm.root(Value::Object(a));
+    vm.collect_garbage();
+    assert_eq!(vm.allocator().len(), live + 2);
+    assert_eq!(vm.handle(handle), Some(&Value::Object(a)));
+    assert_eq!(vm.unroot(handle), Some(Value::Object(a)));
+    assert_eq!(vm.handle(handle), None);
+    vm.collect_garbage();
+    assert_eq!(vm.allocator().len(), live);
+
+    // 回收函数（与它的 prototype 对象构成环）时释放它的代码
+    let code = Code::new(CodeObject::new("f"));
+    let function = vm.new_function(code.clone());
+    let handle = vm.root(Value::Function(function));
+    vm.collect_garbage();
+    assert_eq!(Rc::strong_count(&code), 2);
+    vm.unroot(handle);
+    vm.collect_garbage();
+    assert_eq!(Rc::strong_count(&code), 1);
+    assert_eq!(vm.allocator().len(), live);
+
+    // 存活的对象超过上限时抛出 RangeError
+    match vm.run(cycles(1_000_000, true)) {
+        Err(NativeError::RangeError(message)) => assert_eq!(message, "Out of memory"),
+        result => panic!("unexpected result: {:?}", result.map(|_| ())),
+    }
+    assert!(vm.frames.is_empty());
+    vm.set_heap_limit(None);
+    vm.collect_garbage();
+    assert_eq!(vm.allocator().len(), live);
+}
+
+#[test]
+fn test_generational() {
+    use ecmascript::vm::isolate::Isolate;
+
+    let mut vm = Vm::new();
+    vm.set_gc_mode(GcMode::Generational);
+    vm.set_nursery_size(16 * 1024);
+    vm.collect_garbage();
+    let live = vm.allocator().len();
+
+    // NOTE: 老年代的 list 不断指向新生代的节点，测试中每次次要回收都会检查写屏障
+    let list = vm.run(linked_list(2_000)).unwrap();
+    let stats = vm.heap_stats();
+    assert!(stats.minor_collections > 0);
+    assert_eq!(stats.values(), vm.allocator().len());
+    assert!(stats.allocated_bytes >= stats.heap_bytes as u64);
+    assert!(stats.last_pause <= stats.max_pause && stats.max_pause <= stats.total_pause);
+
+    let handle = vm.root(list);
+    vm.collect(Collection::Minor);
+    assert_eq!(vm.allocator().young_bytes(), 0);
+    let mut node = vm.handle(handle).cloned().unwrap();
+    let mut length = 0;
+    loop {
+        let object = node.as_object().unwrap();
+        let key = if length == 0 { "head" } else { "next" };
+        node = unsafe { object.as_ref() }.get(key).unwrap().value();
+        if node.is_undefined() {
+            break;
+        }
+        length += 1;
+    }
+    assert_eq!(length, 2_000);
+
+    // 不可达的环在次要回收中释放
+    let minor_collections = vm.heap_stats().minor_collections;
+    vm.run(cycles(20_000, false)).unwrap();
+    assert!(vm.heap_stats().minor_collections > minor_collections);
+    vm.unroot(handle);
+    vm.collect_garbage();
+    assert_eq!(vm.allocator().len(), live);
+
+    let isolate = Isolate::new(Vm::new());
+    let stats = isolate.heap_stats();
+    assert!(stats.objects > 0 && stats.functions > 0);
+    assert_eq!(stats.collections(), 0);
+}
@@ -0,0 +1,434 @@
+// Inline caches
+//
+//      o.x          GetNamed("x")  cache=0  ->  Monomorphic  [ { x, y }: slot 0 ]
+//      o.toString   GetNamed(..)   cache=1  ->  Polymorphic  [ { x, y }: prototype { .. }: slot 3,  { y }: ... ]
+//      o.z = 1      SetNamed("z")  cache=2  ->  Monomorphic  [ { x, y } => { x, y, z } ]
+//
+// NOTE: 每条 GetNamed / SetNamed 指令在编译时分配一个缓存的编号（`cache` 操作数），
+//       缓存按照编号保存在 `Code` 中，执行时直接按编号取得，不需要查找哈希表。
+//       缓存记录接收者的 Shape 与属性所在的槽位，命中时不再查找属性表与原型链。
+//       第一次执行时缓存为空，之后依次变为单态、多态（最多 `MAX_POLYMORPHIC_ENTRIES` 个 Shape），
+//       超过之后变为超态，超态的指令总是走慢路径。
+//
+//       属性的特性保存在对象的槽位中，而不是 Shape 中，所以命中时仍然检查槽位中的属性：
+//       读取要求数据属性，写入要求可写的数据属性，否则回到慢路径（getter、setter、只读属性、严格模式的错误 ...）。
+//       原型上的属性只缓存一层，并且同时检查原型的 Shape；添加属性的缓存检查整条原型链的 Shape，
+//       保证原型链上没有同名的 setter 或者只读属性。
+//       数组的 `length` 与索引需要同步，数组不使用写入缓存；字典模式的对象没有 Shape，不会被缓存。
+
+use crate::value::{ Value, PackedValue, };
+use crate::object::{ Object, Property, PropertyKey, };
+use crate::shape::Shape;
+use crate::error::NativeError;
+use crate::vm::Vm;
+
+use std::rc::Rc;
+use std::ptr::NonNull;
+
+
+/// Instructions that have seen more shapes become megamorphic.
+pub const MAX_POLYMORPHIC_ENTRIES: usize = 4;
+
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum CacheEntry {
+    /// A data property of the receiver, or of its prototype when `prototype` is the shape of the prototype.
+    Load { shape: Rc<Shape>, prototype: Option<Rc<Shape>>, slot: u32 },
+    /// Replaces the value of a writable data property of the receiver.
+    Store { shape: Rc<Shape>, slot: u32 },
+    /// Adds the property to the receiver, `prototypes` are the shapes of the prototype chain that does not have it.
+    Add { shape: Rc<Shape>, target: Rc<Shape>, prototypes: Vec<Rc<Shape>> },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum InlineCache {
+    /// The instruction has not cached any shape yet.
+    Uninitialized,
+    Monomorphic(CacheEntry),
+    Polymorphic(Vec<CacheEntry>),
+    /// Seen too many shapes, always takes the slow path.
+    Megamorphic,
+}
+
+impl CacheEntry {
+    /// The shape of the receiver.
+    #[inline]
+    pub fn shape(&self) -> &Rc<Shape> {
+        match *self {
+            CacheEntry::Load { ref shape, .. } | CacheEntry::Store { ref shape, .. } | CacheEntry::Add { ref shape, .. } => shape,
+        }
+    }
+
+    /// The entry for loading `key` from `object`, only data properties of shaped objects are cached.
+    pub fn load(object: &Object, key: &PropertyKey) -> Option<CacheEntry> {
+        let shape = object.properties.shape()?;
+        if let Some(slot) = shape.slot(key) {
+            return match object.properties.slot(slot) {
+                Some(property) if !property.is_accessor() => Some(CacheEntry::Load { shape: shape.clone(), prototype: None, slot }),
+                _ => None,
+            };
+        }
+
+        let prototype = unsafe { object.prototype?.as_ref() };
+        let prototype_shape = prototype.properties.shape()?;
+        let slot = prototype_shape.slot(key)?;
+        match prototype.properties.slot(slot) {
+            Some(property) if !property.is_accessor() => Some(CacheEntry::Load {
+                shape: shape.clone(),
+                prototype: Some(prototype_shape.clone()),
+                slot,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The shapes of the prototype chain of `object`, when none of them has `key`.
+    pub fn prototypes(object: &Object, key: &PropertyKey) -> Option<Vec<Rc<Shape>>> {
+        let mut shapes = Vec::new();
+        let mut current = object.prototype;
+        while let Some(ptr) = current {
+            let prototype = unsafe { ptr.as_ref() };
+            let shape = prototype.properties.shape()?;
+            if shape.slot(key).is_some() {
+                return None;
+            }
+            shapes.push(shape.clone());
+            current = prototype.prototype;
+        }
+
+        Some(shapes)
+    }
+
+    /// The entry for storing `key` to `object`, `before` is the shape of `object` before the store,
+    /// `prototypes` is its prototype chain without `key` ( see `CacheEntry::prototypes` ).
+    pub fn store(object: &Object, key: &PropertyKey, before: Option<Rc<Shape>>, prototypes: Option<Vec<Rc<Shape>>>) -> Option<CacheEntry> {
+        if object.is_array() {
+            return None;
+        }
+
+        let before = before?;
+        let after = object.properties.shape()?;
+        let slot = after.slot(key)?;
+        if Rc::ptr_eq(&before, after) {
+            match object.properties.slot(slot) {
+                Some(property) if property.writable && !property.is_accessor() => Some(CacheEntry::Store { shape: before, slot }),
+                _ => None,
+            }
+        } else if slot as usize == before.len() && after.parent().map(|parent| Rc::ptr_eq(parent, &before)).unwrap_or(false) {
+            Some(CacheEntry::Add { shape: before, target: after.clone(), prototypes: prototypes? })
+        } else {
+            None
+        }
+    }
+}
+
+impl InlineCache {
+    #[inline]
+    pub fn entries(&self) -> &[CacheEntry] {
+        match *self {
+            InlineCache::Monomorphic(ref entry) => std::slice::from_ref(entry),
+            InlineCache::Polymorphic(ref entries) => entries,
+            InlineCache::Uninitialized | InlineCache::Megamorphic => &[],
+        }
+    }
+
+    #[inline]
+    pub fn is_megamorphic(&self) -> bool {
+        matches!(*self, InlineCache::Megamorphic)
+    }
+
+    /// Adds the entry, the stale entry of the same shape is replaced.
+    pub fn add(&mut self, entry: CacheEntry) {
+        let mut entries = match std::mem::replace(self, InlineCache::Megamorphic) {
+            InlineCache::Uninitialized => Vec::new(),
+            InlineCache::Monomorphic(entry) => vec![ entry ],
+            InlineCache::Polymorphic(entries) => entries,
+            InlineCache::Megamorphic => return,
+        };
+        entries.retain(|item| !Rc::ptr_eq(item.shape(), entry.shape()));
+        if entries.len() >= MAX_POLYMORPHIC_ENTRIES {
+            return;
+        }
+
+        entries.push(entry);
+        *self = if entries.len() == 1 {
+            InlineCache::Monomorphic(entries.pop().unwrap())
+        } else {
+            InlineCache::Polymorphic(entries)
+        };
+    }
+
+    /// The value of the cached property, `None` when missed.
+    pub fn load(&self, object: &Object) -> Option<Value> {
+        let shape = object.properties.shape()?;
+        let entry = self.entries().iter().find(|entry| Rc::ptr_eq(entry.shape(), shape))?;
+        let (holder, slot) = match *entry {
+            CacheEntry::Load { prototype: None, slot, .. } => (object, slot),
+            CacheEntry::Load { prototype: Some(ref expected), slot, .. } => {
+                let prototype = unsafe { object.prototype?.as_ref() };
+                match prototype.properties.shape() {
+                    Some(shape) if Rc::ptr_eq(shape, expected) => (prototype, slot),
+                    _ => return None,
+                }
+            },
+            _ => return None,
+        };
+
+        match holder.properties.slot(slot) {
+            Some(property) if !property.is_accessor() => Some(property.value()),
+            _ => None,
+        }
+    }
+
+    /// Stores the value to the cached property, returns `false` when missed.
+    pub fn store(&self, object: &mut Object, value: &Value) -> bool {
+        if object.is_array() {
+            return false;
+        }
+        let shape = match object.properties.shape() {
+            Some(shape) => Rc::as_ptr(shape),
+            None => return false,
+        };
+
+        match self.entries().iter().find(|entry| Rc::as_ptr(entry.shape()) == shape) {
+            Some(&CacheEntry::Store { slot, .. }) => match object.properties.slot_mut(slot) {
+                Some(property) if property.writable && !property.is_accessor() => {
+                    property.value = PackedValue::from(value.clone());
+                    true
+                },
+                _ => false,
+            },
+            Some(CacheEntry::Add { target, prototypes, .. }) => {
+                if !object.is_extensible || !matches_prototypes(object, prototypes) {
+                    return false;
+                }
+                object.properties.push(target.clone(), Property::data(value.clone()));
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+fn matches_prototypes(object: &Object, prototypes: &[Rc<Shape>]) -> bool {
+    let mut current = object.prototype;
+    for expected in prototypes {
+        let prototype = match current {
+            Some(ptr) => unsafe { ptr.as_ref() },
+            None => return false,
+        };
+        match prototype.properties.shape() {
+            Some(shape) if Rc::ptr_eq(shape, expected) => { },
+            _ => return false,
+        }
+        current = prototype.prototype;
+    }
+
+    current.is_none()
+}
+
+
+impl Vm {
+    /// `GetNamed`, uses the inline cache of the instruction.
+    pub(crate) fn get_named(&mut self, name: u32, cache: u32, object: Value) -> Result<Value, NativeError> {
+        let target = object.as_object();
+
+        let megamorphic = match (target, unsafe { self.frame().code.cache(cache) }) {
+            (Some(ptr), Some(cache)) => match cache.load(unsafe { ptr.as_ref() }) {
+                Some(value) => return Ok(value),
+                None => cache.is_megamorphic(),
+            },
+            _ => false,
+        };
+
+        let key = PropertyKey::String(self.name(name)?);
+        let value = self.get_value(&object, &key)?;
+
+        if let (Some(ptr), false) = (target, megamorphic) {
+            if let Some(entry) = CacheEntry::load(unsafe { ptr.as_ref() }, &key) {
+                unsafe { self.frame().code.update_cache(cache, entry) };
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// `SetNamed`, uses the inline cache of the instruction.
+    pub(crate) fn set_named(&mut self, name: u32, cache: u32, object: Value, value: Value) -> Result<(), NativeError> {
+        let target = object.as_object();
+
+        let (hit, megamorphic) = match (target, unsafe { self.frame().code.cache(cache) }) {
+            (Some(ptr), Some(cache)) => (cache.store(unsafe { &mut *ptr.as_ptr() }, &value), cache.is_megamorphic()),
+            _ => (false, false),
+        };
+        if let (Some(ptr), true) = (target, hit) {
+            self.write_barrier(ptr);
+            return Ok(());
+        }
+
+        let key = PropertyKey::String(self.name(name)?);
+        let (before, prototypes) = match (target, megamorphic) {
+            (Some(ptr), false) => {
+                let receiver = unsafe { ptr.as_ref() };
+                let before = receiver.properties.shape().cloned();
+                let prototypes = match before {
+                    Some(ref shape) if shape.slot(&key).is_none() => CacheEntry::prototypes(receiver, &key),
+                    _ => None,
+                };
+                (before, prototypes)
+            },
+            _ => (None, None),
+        };
+
+        let strict = self.is_strict();
+        self.set_value(&object, key.clone(), value, strict)?;
+
+        if let Some(ptr) = target {
+            if let Some(entry) = CacheEntry::store(unsafe { ptr.as_ref() }, &key, before, prototypes) {
+                unsafe { self.frame().code.update_cache(cache, entry) };
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+fn counter(count: i64) -> ecmascript::compiler::bytecode::CodeObject {
+    use ecmascript::compiler::bytecode::{ CodeObject, Instruction, };
+    use ecmascript::lexer::span::Loc;
+
+    // NOTE: while (n) { o = {}; o.a = n; o.a = o.a + 1; sum = sum + o.a; n--; } return sum;
+    let loc = Loc::default();
+    let mut code = CodeObject::new("<script>");
+    code.local_count = 3;
+    let a = code.add_string("a");
+
+    code.emit(Instruction::Int(count), loc);
+    code.emit(Instruction::SetLocal(0), loc);
+    code.emit(Instruction::Pop, loc);
+    code.emit(Instruction::Int(0), loc);
+    code.emit(Instruction::SetLocal(2), loc);
+    code.emit(Instruction::Pop, loc);
+    let start = code.emit(Instruction::GetLocal(0), loc);
+    let exit = code.emit(Instruction::JumpIfFalse(0), loc);
+    code.emit(Instruction::Object, loc);
+    code.emit(Instruction::SetLocal(1), loc);
+    code.emit(Instruction::GetLocal(0), loc);
+    let cache = code.cache();
+    code.emit(Instruction::SetNamed(a, cache), loc);
+    code.emit(Instruction::Pop, loc);
+    code.emit(Instruction::GetLocal(1), loc);
+    code.emit(Instruction::GetLocal(1), loc);
+    let cache = code.cache();
+    code.emit(Instruction::GetNamed(a, cache), loc);
+    code.emit(Instruction::Int(1), loc);
+    code.emit(Instruction::Add, loc);
+    let cache = code.cache();
+    code.emit(Instruction::SetNamed(a, cache), loc);
+    code.emit(Instruction::Pop, loc);
+    code.emit(Instruction::GetLocal(2), loc);
+    code.emit(Instruction::GetLocal(1), loc);
+    let cache = code.cache();
+    code.emit(Instruction::GetNamed(a, cache), loc);
+    code.emit(Instruction::Add, loc);
+    code.emit(Instruction::SetLocal(2), loc);
+    code.emit(Instruction::Pop, loc);
+    code.emit(Instruction::GetLocal(0), loc);
+    code.emit(Instruction::Dec, loc);
+    code.emit(Instruction::SetLocal(0), loc);
+    code.emit(Instruction::Pop, loc);
+    let jump = code.emit(Instruction::Jump(0), loc);
+    let end = code.emit(Instruction::GetLocal(2), loc);
+    code.emit(Instruction::Return, loc);
+    code.patch_jump(exit, end);
+    code.patch_jump(jump, start);
+
+    code
+}
+
+#[test]
+fn test_inline_caches() {
+    let key = |name: &str| PropertyKey::from(name);
+
+    // NOTE: 字节码中的三条 SetNamed / GetNamed 指令都是单态的
+    let mut vm = Vm::new();
+    let sum = vm.run(counter(100)).unwrap();
+    assert_eq!(sum.as_f64(), Some((2 ..= 101).sum::<i64>() as f64));
+    let code = vm.scripts.last().unwrap().clone();
+    assert_eq!(code.object.cache_count, 4);
+    assert_eq!(unsafe { code.cache(4) }, None);
+    let kinds = (0..4)
+        .map(|index| match unsafe { code.cache(index) } {
+            Some(&InlineCache::Monomorphic(CacheEntry::Load { .. })) => "load",
+            Some(&InlineCache::Monomorphic(CacheEntry::Store { .. })) => "store",
+            Some(&InlineCache::Monomorphic(CacheEntry::Add { .. })) => "add",
+            _ => "other",
+        })
+        .collect::<Vec<&str>>();
+    assert_eq!(kinds, vec![ "add", "load", "store", "load" ]);
+
+    // 单态、多态与超态
+    let objects = (0..MAX_POLYMORPHIC_ENTRIES + 1)
+        .map(|index| {
+            let mut object = vm.new_object();
+            for padding in 0..index {
+                unsafe { object.as_mut() }.insert(format!("p{}", padding), Property::data(0));
+            }
+            unsafe { object.as_mut() }.insert("x", Property::data(index as i64));
+            object
+        })
+        .collect::<Vec<NonNull<Object>>>();
+    let mut cache = InlineCache::Monomorphic(CacheEntry::load(unsafe { objects[0].as_ref() }, &key("x")).unwrap());
+    assert_eq!(cache.load(unsafe { objects[0].as_ref() }), Some(Value::I64(0)));
+    assert_eq!(cache.load(unsafe { objects[1].as_ref() }), None);
+    for object in objects[1..MAX_POLYMORPHIC_ENTRIES].iter() {
+        cache.add(CacheEntry::load(unsafe { object.as_ref() }, &key("x")).unwrap());
+    }
+    assert_eq!(cache.entries().len(), MAX_POLYMORPHIC_ENTRIES);
+    assert_eq!(cache.load(unsafe { objects[3].as_ref() }), Some(Value::I64(3)));
+    cache.add(CacheEntry::load(unsafe { objects[MAX_POLYMORPHIC_ENTRIES].as_ref() }, &key("x")).unwrap());
+    assert!(cache.is_megamorphic());
+    assert_eq!(cache.load(unsafe { objects[0].as_ref() }), None);
+
+    // 原型上的属性：原型的值改变后读取新值，原型的 Shape 改变或者接收者遮蔽它时不再命中
+    let mut prototype = vm.new_object();
+    unsafe { prototype.as_mut() }.insert("m", Property::data(1));
+    let mut object = vm.new_object();
+    unsafe { object.as_mut() }.prototype = Some(prototype);
+    let cache = InlineCache::Monomorphic(CacheEntry::load(unsafe { object.as_ref() }, &key("m")).unwrap());
+    unsafe { prototype.as_mut() }.insert("m", Property::data(2));
+    assert_eq!(cache.load(unsafe { object.as_ref() }), Some(Value::I64(2)));
+    unsafe { prototype.as_mut() }.insert("n", Property::data(3));
+    assert_eq!(cache.load(unsafe { object.as_ref() }), None);
+    let cache = InlineCache::Monomorphic(CacheEntry::load(unsafe { object.as_ref() }, &key("m")).unwrap());
+    unsafe { object.as_mut() }.insert("m", Property::data(4));
+    assert_eq!(cache.load(unsafe { object.as_ref() }), None);
+
+    // 写入：只读属性与 getter 不会命中，原型链上出现同名属性后添加属性的缓存失效
+    let shape = unsafe { object.as_ref() }.properties.shape().cloned();
+    let cache = InlineCache::Monomorphic(CacheEntry::store(unsafe { object.as_ref() }, &key("m"), shape, None).unwrap());
+    assert!(cache.store(unsafe { object.as_mut() }, &Value::I64(5)));
+    assert_eq!(unsafe { object.as_ref() }.get("m").map(Property::value), Some(Value::I64(5)));
+    unsafe { object.as_mut() }.insert("m", Property::readonly(6));
+    assert!(!cache.store(unsafe { object.as_mut() }, &Value::I64(7)));
+    unsafe { object.as_mut() }.insert("m", Property::accessor(Value::Object(prototype), Value::Undefined, true));
+    assert_eq!(CacheEntry::load(unsafe { object.as_ref() }, &key("m")), None);
+
+    let mut other = vm.new_object();
+    unsafe { other.as_mut() }.prototype = Some(prototype);
+    let before = unsafe { other.as_ref() }.properties.shape().cloned();
+    let prototypes = CacheEntry::prototypes(unsafe { other.as_ref() }, &key("z"));
+    assert!(vm.set(other, key("z"), Value::I64(8), Value::Object(other)).unwrap());
+    let cache = InlineCache::Monomorphic(CacheEntry::store(unsafe { other.as_ref() }, &key("z"), before, prototypes).unwrap());
+    let mut another = vm.new_object();
+    unsafe { another.as_mut() }.prototype = Some(prototype);
+    assert!(cache.store(unsafe { another.as_mut() }, &Value::I64(9)));
+    assert!(Rc::ptr_eq(unsafe { another.as_ref() }.properties.shape().unwrap(), unsafe { other.as_ref() }.properties.shape().unwrap()));
+    let mut third = vm.new_object();
+    unsafe { third.as_mut() }.prototype = Some(prototype);
+    unsafe { prototype.as_mut() }.insert("z", Property::readonly(10));
+    assert!(!cache.store(unsafe { third.as_mut() }, &Value::I64(11)));
+    assert!(unsafe { third.as_ref() }.get("z").is_none());
+}
@@ -0,0 +1,1436 @@
+// Interpreter
+//
+//      loop {
+//          decode the instruction at `pc` of the running frame
+//          execute it, errors unwind to the innermost exception handler
+//      }
+//
+// NOTE: 异常处理：在抛出异常的 Frame 的异常处理表中查找覆盖当前指令（`ip`）的处理器，
+//       找不到时弹出这个 Frame，在调用者的 call 指令处继续查找。
+//       Native 函数（getter、`Function.prototype.call` ...）以及生成器的恢复会嵌套调用 `dispatch`，
+//       嵌套的 `dispatch` 只运行它自己压入的 Frame（`base` 之上的部分）。
+
+use crate::value::{ Value, PackedValue, };
+use crate::error::NativeError;
+use crate::env::{ Environment, EnvironmentKind, RecordKind, };
+use crate::object::{ Object, ObjectKind, Property, PropertyKey, };
+use crate::function::{ Function, FunctionCode, NativeFunction, };
+use crate::frame::{ Code, Frame, Generator, GeneratorState, };
+use crate::operations::Hint;
+use crate::vm::{ Vm, MAX_FRAMES, MAX_NATIVE_DEPTH, };
+
+use ecmascript::error::ErrorKind;
+use ecmascript::compiler::bytecode::{ Instruction, CodeFlags, Constant, Capture, error_kind_from_u8, };
+
+use std::rc::Rc;
+use std::ptr::NonNull;
+
+
+/// How the frame at the base of a `dispatch` finished.
+#[derive(Debug)]
+pub(crate) enum Completion {
+    Return(Value),
+    Yield(Value),
+}
+
+enum Flow {
+    Next,
+    Return(Value),
+    Yield(Value),
+}
+
+/// How a suspended generator is resumed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Resume {
+    Next,
+    Return,
+    Throw,
+}
+
+/// Where a variable looked up by name lives.
+enum Reference {
+    Record(NonNull<Environment>),
+    Object(NonNull<Object>),
+    Unresolvable,
+}
+
+#[inline]
+fn stack_underflow() -> NativeError {
+    NativeError::internal_error("operand stack underflow")
+}
+
+fn stack_overflow() -> NativeError {
+    NativeError::range_error("Maximum call stack size exceeded")
+}
+
+fn unsupported_async() -> NativeError {
+    NativeError::internal_error("async functions are not supported yet")
+}
+
+
+impl Vm {
+    pub(crate) fn push_frame(&mut self, frame: Frame) -> Result<(), NativeError> {
+        if self.frames.len() >= MAX_FRAMES {
+            return Err(stack_overflow());
+        }
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    /// Run the frames above `base` until the frame at `base` returns or yields,
+    /// an exception that is not caught by them is returned as `Err`.
+    pub(crate) fn dispatch(&mut self, base: usize, exception: Option<Value>) -> Result<Completion, Value> {
+        let mut exception = exception;
+        loop {
+            if let Some(value) = exception.take() {
+                self.unwind(base, value)?;
+            }
+
+            if let Err(error) = self.safe_point() {
+                exception = Some(self.new_error(&error));
+                continue;
+            }
+
+            match self.step(base) {
+                Ok(Flow::Next) => { },
+                Ok(Flow::Return(value)) => return Ok(Completion::Return(value)),
+                Ok(Flow::Yield(value)) => return Ok(Completion::Yield(value)),
+                Err(error) => exception = Some(self.new_error(&error)),
+            }
+        }
+    }
+
+    fn unwind(&mut self, base: usize, value: Value) -> Result<(), Value> {
+        // NOTE: 嵌套的 dispatch 返回后，外层的 Frame 继续展开同一个异常，只记录最内层的抛出位置
+        if self.throw_site.is_none() && self.frames.len() > base {
+            let frame = self.frames.last().unwrap();
+            self.throw_site = Some((frame.code.clone(), frame.ip));
+        }
+
+        while self.frames.len() > base {
+            let frame = self.frames.last_mut().unwrap();
+            let ip = frame.ip as u32;
+            let handler = frame.code.object.handlers.iter()
+                .find(|handler| handler.start <= ip && ip < handler.end)
+                .cloned();
+            if let Some(handler) = handler {
+                frame.stack.truncate(handler.stack_depth as usize);
+                frame.stack.push(PackedValue::from(value));
+                frame.pc = handler.target as usize;
+                self.throw_site = None;
+                return Ok(());
+            }
+
+            self.frames.pop();
+        }
+
+        Err(value)
+    }
+
+    /// Run a frame on a nested `dispatch`, for calls from native code.
+    fn run_frame(&mut self, frame: Frame) -> Result<Value, NativeError> {
+        if self.native_depth >= MAX_NATIVE_DEPTH {
+            return Err(stack_overflow());
+        }
+
+        let base = self.frames.len();
+        self.push_frame(frame)?;
+        self.native_depth += 1;
+        let result = self.dispatch(base, None);
+        self.native_depth -= 1;
+
+        match result {
+            Ok(Completion::Return(value)) => Ok(value),
+            Ok(Completion::Yield(_)) => Err(NativeError::internal_error("yield outside of a generator")),
+            Err(value) => Err(NativeError::Exception(value)),
+        }
+    }
+
+    fn call_native(&mut self, native: NativeFunction, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+        if self.native_depth >= MAX_NATIVE_DEPTH {
+            return Err(stack_overflow());
+        }
+
+        self.native_depth += 1;
+        let result = native(self, this, args);
+        self.native_depth -= 1;
+
+        result
+    }
+
+    /// Call a function from native code.
+    pub fn call(&mut self, callee: Value, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+        let function = self.callable(&callee)?;
+        match unsafe { function.as_ref() }.code().clone() {
+            FunctionCode::NativeCode(native) => self.call_native(native, this, args),
+            FunctionCode::ByteCode(code) => {
+                let frame = self.prepare_call(function, code, this, args.to_vec())?;
+                self.run_frame(frame)
+            },
+        }
+    }
+
+    /// `new callee(...args)` from native code.
+    pub fn construct(&mut self, callee: Value, args: &[Value], new_target: Value) -> Result<Value, NativeError> {
+        let function = self.constructor(&callee)?;
+        match unsafe { function.as_ref() }.code().clone() {
+            FunctionCode::NativeCode(native) => {
+                let value = self.call_native(native, Value::Hole, args)?;
+                self.bind_new_target(value, &callee, &new_target)
+            },
+            FunctionCode::ByteCode(code) => {
+                let frame = self.prepare_construct(function, code, args.to_vec(), new_target)?;
+                self.run_frame(frame)
+            },
+        }
+    }
+
+    fn callable(&self, callee: &Value) -> Result<NonNull<Function>, NativeError> {
+        match *callee {
+            Value::Function(function) => Ok(function),
+            _ => Err(NativeError::type_error(format!("{} is not a function", self.display(callee)))),
+        }
+    }
+
+    fn constructor(&self, callee: &Value) -> Result<NonNull<Function>, NativeError> {
+        match *callee {
+            Value::Function(function) if unsafe { function.as_ref() }.is_constructor => Ok(function),
+            _ => Err(NativeError::type_error(format!("{} is not a constructor", self.display(callee)))),
+        }
+    }
+
+    fn prepare_call(&mut self, function: NonNull<Function>, code: Rc<Code>, this: Value, args: Vec<Value>) -> Result<Frame, NativeError> {
+        let flags = code.object.flags;
+        if flags.contains(CodeFlags::CLASS_CONSTRUCTOR) {
+            let message = format!("Class constructor {} cannot be invoked without 'new'", code.object.name);
+            return Err(NativeError::type_error(message));
+        }
+        if flags.contains(CodeFlags::ASYNC) {
+            return Err(unsupported_async());
+        }
+
+        let f = unsafe { function.as_ref() };
+        let this = if flags.contains(CodeFlags::ARROW) {
+            f.this_value.clone()
+        } else if flags.contains(CodeFlags::STRICT) {
+            this
+        } else {
+            match this {
+                Value::Undefined | Value::Null => Value::Object(self.global),
+                this => this,
+            }
+        };
+
+        let env = f.env.unwrap_or(self.global_env);
+        let mut frame = Frame::new(code, Some(function), this, args, env);
+        if flags.contains(CodeFlags::ARROW) {
+            frame.new_target = f.new_target.clone();
+            if !f.arguments.is_undefined() {
+                frame.arguments_object = Some(f.arguments.clone());
+            }
+        }
+
+        Ok(frame)
+    }
+
+    fn prepare_construct(&mut self, function: NonNull<Function>, code: Rc<Code>, args: Vec<Value>, new_target: Value) -> Result<Frame, NativeError> {
+        let this = if code.object.flags.contains(CodeFlags::DERIVED) {
+            Value::Hole
+        } else {
+            let default = self.intrinsics.object_prototype;
+            let prototype = self.prototype_from_constructor(&new_target, default)?;
+            Value::Object(self.alloc(Object::with_prototype(prototype)))
+        };
+
+        let env = unsafe { function.as_ref() }.env.unwrap_or(self.global_env);
+        let mut frame = Frame::new(code, Some(function), this, args, env);
+        frame.is_construct = true;
+        frame.new_target = new_target;
+
+        Ok(frame)
+    }
+
+    fn prototype_from_constructor(&mut self, constructor: &Value, default: NonNull<Object>) -> Result<NonNull<Object>, NativeError> {
+        let prototype = self.get_value(constructor, &"prototype".into())?;
+        Ok(prototype.as_object().unwrap_or(default))
+    }
+
+    /// Objects created by native constructors inherit from the `prototype` of `new.target` ( subclasses ).
+    fn bind_new_target(&mut self, value: Value, callee: &Value, new_target: &Value) -> Result<Value, NativeError> {
+        if new_target == callee {
+            return Ok(value);
+        }
+        if let Some(object) = value.as_object() {
+            let default = unsafe { object.as_ref() }.prototype.unwrap_or(self.intrinsics.object_prototype);
+            let prototype = self.prototype_from_constructor(new_target, default)?;
+            unsafe { &mut *object.as_ptr() }.prototype = Some(prototype);
+            self.write_barrier(object);
+        }
+
+        Ok(value)
+    }
+
+    /// Call from the running frame, the result is pushed when the callee returns.
+    fn call_value(&mut self, callee: Value, this: Value, args: Vec<Value>) -> Result<(), NativeError> {
+        let function = self.callable(&callee)?;
+        match unsafe { function.as_ref() }.code().clone() {
+            FunctionCode::NativeCode(native) => {
+                let value = self.call_native(native, this, &args)?;
+                self.push(value);
+                Ok(())
+            },
+            FunctionCode::ByteCode(code) => {
+                let frame = self.prepare_call(function, code, this, args)?;
+                self.push_frame(frame)
+            },
+        }
+    }
+
+    fn construct_value(&mut self, callee: Value, args: Vec<Value>) -> Result<(), NativeError> {
+        let function = self.constructor(&callee)?;
+        match unsafe { function.as_ref() }.code().clone() {
+            FunctionCode::NativeCode(native) => {
+                let value = self.call_native(native, Value::Hole, &args)?;
+                self.push(value);
+                Ok(())
+            },
+            FunctionCode::ByteCode(code) => {
+                let frame = self.prepare_construct(function, code, args, callee)?;
+                self.push_frame(frame)
+            },
+        }
+    }
+
+    fn super_call(&mut self, args: Vec<Value>) -> Result<(), NativeError> {
+        let frame = self.frame();
+        let function = match frame.function {
+            Some(function) if frame.flags().contains(CodeFlags::DERIVED) => function,
+            // NOTE: 箭头函数中的 `super()` 还不支持
+            _ => return Err(NativeError::syntax_error("'super' keyword unexpected here")),
+        };
+        if !frame.this.is_hole() {
+            return Err(NativeError::reference_error("Super constructor may only be called once"));
+        }
+        let new_target = frame.new_target.clone();
+
+        let parent = match unsafe { function.as_ref() }.object().prototype {
+            Some(parent) => self.object_value(parent),
+            None => Value::Null,
+        };
+        let constructor = match parent {
+            Value::Function(constructor) if unsafe { constructor.as_ref() }.is_constructor => constructor,
+            _ => {
+                let message = format!("Super constructor {} of anonymous class is not a constructor", self.display(&parent));
+                return Err(NativeError::type_error(message));
+            },
+        };
+
+        match unsafe { constructor.as_ref() }.code().clone() {
+            FunctionCode::NativeCode(native) => {
+                let value = self.call_native(native, Value::Hole, &args)?;
+                let value = self.bind_new_target(value, &parent, &new_target)?;
+                self.frame_mut().this = value.clone();
+                self.push(value);
+                Ok(())
+            },
+            FunctionCode::ByteCode(code) => {
+                let mut frame = self.prepare_construct(constructor, code, args, new_target)?;
+                frame.is_super_call = true;
+                self.push_frame(frame)
+            },
+        }
+    }
+
+    fn do_return(&mut self, base: usize) -> Result<Flow, NativeError> {
+        let value = self.pop()?;
+        let frame = self.frames.pop().unwrap();
+
+        let value = if frame.is_construct && !value.is_object() {
+            if frame.flags().contains(CodeFlags::DERIVED) && !value.is_undefined() {
+                return Err(NativeError::type_error("Derived constructors may only return object or undefined"));
+            }
+            if frame.this.is_hole() {
+                return Err(NativeError::reference_error(
+                    "Must call super constructor in derived class before accessing 'this' or returning from derived constructor"
+                ));
+            }
+            frame.this
+        } else {
+            value
+        };
+
+        if self.frames.len() <= base {
+            return Ok(Flow::Return(value));
+        }
+        if frame.is_super_call {
+            if !self.frame().this.is_hole() {
+                return Err(NativeError::reference_error("Super constructor may only be called once"));
+            }
+            self.frame_mut().this = value.clone();
+        }
+        self.push(value);
+
+        Ok(Flow::Next)
+    }
+
+    // Generators
+
+    fn initial_yield(&mut self, base: usize) -> Result<Flow, NativeError> {
+        let mut frame = self.frames.pop().unwrap();
+        let function = frame.function.ok_or_else(|| NativeError::internal_error("InitialYield outside of a generator"))?;
+
+        let default = self.intrinsics.generator_prototype;
+        let prototype = self.prototype_from_constructor(&Value::Function(function), default)?;
+        let generator = Generator { state: GeneratorState::SuspendedStart, frame: None };
+        let object = self.alloc(Object::with_kind(prototype, ObjectKind::Generator(Box::new(generator))));
+        frame.generator = Some(object);
+        if let ObjectKind::Generator(ref mut generator) = unsafe { &mut *object.as_ptr() }.kind {
+            generator.frame = Some(frame);
+        }
+
+        let value = Value::Object(object);
+        if self.frames.len() <= base {
+            return Ok(Flow::Return(value));
+        }
+        self.push(value);
+
+        Ok(Flow::Next)
+    }
+
+    fn yield_value(&mut self) -> Result<Flow, NativeError> {
+        let value = self.pop()?;
+        let frame = self.frames.pop().unwrap();
+        let object = frame.generator.ok_or_else(|| NativeError::internal_error("yield outside of a generator"))?;
+        if let ObjectKind::Generator(ref mut generator) = unsafe { &mut *object.as_ptr() }.kind {
+            generator.frame = Some(frame);
+        }
+        self.write_barrier(object);
+
+        Ok(Flow::Yield(value))
+    }
+
+    /// `generator.next()`, `generator.return()` and `generator.throw()`.
+    pub(crate) fn resume_generator(&mut self, this: Value, mode: Resume, value: Value) -> Result<Value, NativeError> {
+        let object = match this {
+            Value::Object(object) => object,
+            _ => return Err(NativeError::type_error("next method called on incompatible receiver")),
+        };
+        let generator = match unsafe { &mut *object.as_ptr() }.kind {
+            ObjectKind::Generator(ref mut generator) => generator,
+            _ => return Err(NativeError::type_error("next method called on incompatible receiver")),
+        };
+
+        match (generator.state, mode) {
+            (GeneratorState::Executing, _) => return Err(NativeError::type_error("Generator is already running")),
+            (GeneratorState::Completed, Resume::Next) => return Ok(self.iterator_result(Value::Undefined, true)),
+            // NOTE: `return()` 不会执行 `finally` 块
+            (_, Resume::Return) | (GeneratorState::SuspendedStart, Resume::Throw) | (GeneratorState::Completed, Resume::Throw) => {
+                generator.state = GeneratorState::Completed;
+                generator.frame = None;
+                return match mode {
+                    Resume::Return => Ok(self.iterator_result(value, true)),
+                    _ => Err(NativeError::Exception(value)),
+                };
+            },
+            _ => { },
+        }
+
+        let mut frame = generator.frame.take().ok_or_else(|| NativeError::internal_error("generator without frame"))?;
+        let exception = match mode {
+            Resume::Throw => Some(value),
+            _ => {
+                if generator.state == GeneratorState::SuspendedYield {
+                    frame.stack.push(PackedValue::from(value));
+                }
+                None
+            },
+        };
+        if self.native_depth >= MAX_NATIVE_DEPTH || self.frames.len() >= MAX_FRAMES {
+            generator.frame = Some(frame);
+            self.write_barrier(object);
+            return Err(stack_overflow());
+        }
+        generator.state = GeneratorState::Executing;
+
+        let base = self.frames.len();
+        self.frames.push(frame);
+        self.native_depth += 1;
+        let result = self.dispatch(base, exception);
+        self.native_depth -= 1;
+
+        let generator = match unsafe { &mut *object.as_ptr() }.kind {
+            ObjectKind::Generator(ref mut generator) => generator,
+            _ => unreachable!(),
+        };
+        match result {
+            Ok(Completion::Yield(value)) => {
+                generator.state = GeneratorState::SuspendedYield;
+                Ok(self.iterator_result(value, false))
+            },
+            Ok(Completion::Return(value)) => {
+                generator.state = GeneratorState::Completed;
+                Ok(self.iterator_result(value, true))
+            },
+            Err(value) => {
+                generator.state = GeneratorState::Completed;
+                Err(NativeError::Exception(value))
+            },
+        }
+    }
+
+    // Frames and operands
+
+    #[inline]
+    pub(crate) fn frame(&self) -> &Frame {
+        self.frames.last().unwrap()
+    }
+
+    #[inline]
+    fn frame_mut(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    #[inline]
+    fn push(&mut self, value: Value) {
+        self.frame_mut().stack.push(PackedValue::from(value));
+    }
+
+    /// Push a value loaded from a local variable or a Cell, it is not unpacked.
+    #[inline]
+    fn push_packed(&mut self, value: PackedValue) {
+        self.frame_mut().stack.push(value);
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Result<Value, NativeError> {
+        self.frame_mut().stack.pop().map(Value::from).ok_or_else(stack_underflow)
+    }
+
+    fn pop_n(&mut self, n: u32) -> Result<Vec<Value>, NativeError> {
+        let stack = &mut self.frame_mut().stack;
+        let n = n as usize;
+        if stack.len() < n {
+            return Err(stack_underflow());
+        }
+        let at = stack.len() - n;
+        Ok(stack.drain(at..).map(Value::from).collect())
+    }
+
+    #[inline]
+    fn peek(&self) -> Result<Value, NativeError> {
+        self.peek_packed().map(|value| Value::from(value.clone()))
+    }
+
+    #[inline]
+    fn peek_packed(&self) -> Result<&PackedValue, NativeError> {
+        self.frame().stack.last().ok_or_else(stack_underflow)
+    }
+
+    fn peek_object(&self) -> Result<NonNull<Object>, NativeError> {
+        self.peek()?.as_object().ok_or_else(|| NativeError::internal_error("expected an object on the operand stack"))
+    }
+
+    fn local(&mut self, slot: u32) -> Result<&mut PackedValue, NativeError> {
+        self.frame_mut().locals.get_mut(slot as usize)
+            .ok_or_else(|| NativeError::internal_error(format!("local slot {} out of range", slot)))
+    }
+
+    fn cell(&self, slot: u32) -> Result<NonNull<PackedValue>, NativeError> {
+        self.frame().cells.get(slot as usize).cloned().and_then(|cell| cell)
+            .ok_or_else(|| NativeError::internal_error(format!("local slot {} is not a cell", slot)))
+    }
+
+    fn upvalue(&self, index: u32) -> Result<NonNull<PackedValue>, NativeError> {
+        self.frame().function
+            .and_then(|function| unsafe { function.as_ref() }.captures.get(index as usize).cloned())
+            .ok_or_else(|| NativeError::internal_error(format!("upvalue {} out of range", index)))
+    }
+
+    fn constant(&self, index: u32) -> Result<&Constant, NativeError> {
+        self.frame().code.object.constants.get(index as usize)
+            .ok_or_else(|| NativeError::internal_error(format!("constant {} out of range", index)))
+    }
+
+    pub(crate) fn name(&self, index: u32) -> Result<String, NativeError> {
+        match *self.constant(index)? {
+            Constant::String(ref name) => Ok(name.clone()),
+            _ => Err(NativeError::internal_error(format!("constant {} is not a string", index))),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_strict(&self) -> bool {
+        self.frame().flags().contains(CodeFlags::STRICT)
+    }
+
+    fn this_value(&self) -> Result<Value, NativeError> {
+        match self.frame().this {
+            Value::Hole => Err(NativeError::reference_error(
+                "Must call super constructor in derived class before accessing 'this' or returning from derived constructor"
+            )),
+            ref this => Ok(this.clone()),
+        }
+    }
+
+    fn jump(&mut self, offset: i32) {
+        let frame = self.frame_mut();
+        frame.pc = (frame.pc as i64 + i64::from(offset)) as usize;
+    }
+
+    /// String, BigInt and template object constants are created once and shared.
+    fn cached_constant(&mut self, index: u32) -> Result<Value, NativeError> {
+        let key = (Rc::as_ptr(&self.frame().code), index);
+        if let Some(value) = self.constants.get(&key) {
+            return Ok(value.clone());
+        }
+
+        let value = match self.constant(index)?.clone() {
+            Constant::String(s) => self.new_string(s),
+            Constant::BigInt(digits) => {
+                let n = num::BigInt::parse_bytes(digits.as_bytes(), 10)
+                    .ok_or_else(|| NativeError::internal_error(format!("invalid BigInt constant {}", digits)))?;
+                self.new_bigint(n)
+            },
+            Constant::Template { cooked, raw } => {
+                let raw = raw.into_iter().map(|s| self.new_string(s)).collect::<Vec<Value>>();
+                let raw = self.new_array(raw);
+                let cooked = cooked.into_iter()
+                    .map(|s| s.map(|s| self.new_string(s)).unwrap_or(Value::Undefined))
+                    .collect::<Vec<Value>>();
+                let strings = self.new_array(cooked);
+                let (raw, strings) = (raw.as_object().unwrap(), strings.as_object().unwrap());
+                unsafe { &mut *strings.as_ptr() }.insert("raw", Property::readonly(Value::Object(raw)));
+                self.freeze(raw);
+                self.freeze(strings);
+                Value::Object(strings)
+            },
+            _ => return Err(NativeError::internal_error(format!("constant {} can not be loaded", index))),
+        };
+        self.constants.insert(key, value.clone());
+
+        Ok(value)
+    }
+
+    fn spread_arguments(&mut self, array: Value) -> Result<Vec<Value>, NativeError> {
+        let length = self.length_of_array_like(&array)?;
+        let mut args = Vec::with_capacity(length as usize);
+        for index in 0..length {
+            args.push(self.get_value(&array, &index.into())?);
+        }
+
+        Ok(args)
+    }
+
+    fn arguments_object(&mut self) -> Value {
+        if let Some(ref arguments) = self.frame().arguments_object {
+            return arguments.clone();
+        }
+
+        let args = self.frame().arguments.clone();
+        let mut object = Object::with_kind(self.intrinsics.object_prototype, ObjectKind::Arguments);
+        let length = args.len();
+        for (index, value) in args.into_iter().enumerate() {
+            object.insert(index as u32, Property::data(value));
+        }
+        object.insert("length", Property::hidden(length as i64));
+        object.insert(self.intrinsics.symbol_iterator, Property::hidden(self.intrinsics.array_values.clone()));
+
+        let value = Value::Object(self.alloc(object));
+        self.frame_mut().arguments_object = Some(value.clone());
+        value
+    }
+
+    // Functions
+
+    pub(crate) fn new_function(&mut self, code: Rc<Code>) -> NonNull<Function> {
+        let flags = code.object.flags;
+        let name = code.object.name.clone();
+        let length = code.object.length;
+
+        let mut object = self.alloc(Object::with_prototype(self.intrinsics.function_prototype));
+        let mut function = Function::new(name.clone(), FunctionCode::ByteCode(code), unsafe { object.as_mut() });
+        function.is_constructor = flags.contains(CodeFlags::CLASS_CONSTRUCTOR)
+            || !(flags.contains(CodeFlags::ARROW) || flags.contains(CodeFlags::METHOD)
+                 || flags.contains(CodeFlags::GENERATOR) || flags.contains(CodeFlags::ASYNC));
+        let is_constructor = function.is_constructor;
+        let function = self.alloc(function);
+
+        let name = self.new_string(name);
+        let prototype = if flags.contains(CodeFlags::GENERATOR) {
+            Some(self.alloc(Object::with_prototype(self.intrinsics.generator_prototype)))
+        } else if is_constructor && !flags.contains(CodeFlags::CLASS_CONSTRUCTOR) {
+            let mut prototype = self.new_object();
+            unsafe { prototype.as_mut() }.insert("constructor", Property::hidden(Value::Function(function)));
+            Some(prototype)
+        } else {
+            None
+        };
+
+        let object = unsafe { object.as_mut() };
+        object.kind = ObjectKind::Function(function);
+        object.insert("length", Property { configurable: true, ..Property::readonly(length as i64) });
+        object.insert("name", Property { configurable: true, ..Property::readonly(name) });
+        if let Some(prototype) = prototype {
+            object.insert("prototype", Property { writable: true, ..Property::readonly(Value::Object(prototype)) });
+        }
+
+        function
+    }
+
+    fn new_closure(&mut self, code: Rc<Code>) -> Result<NonNull<Function>, NativeError> {
+        let flags = code.object.flags;
+        let frame = self.frame();
+
+        let mut captures = Vec::with_capacity(code.object.captures.len());
+        for capture in code.object.captures.iter() {
+            captures.push(match *capture {
+                Capture::Local(slot) => self.cell(slot)?,
+                Capture::Upvalue(index) => self.upvalue(index)?,
+            });
+        }
+
+        let env = frame.env;
+        let home_object = frame.function.and_then(|function| unsafe { function.as_ref() }.home_object);
+        let this_value = frame.this.clone();
+        let new_target = frame.new_target.clone();
+        let is_arrow = flags.contains(CodeFlags::ARROW);
+        let arguments = if is_arrow && code.uses_arguments && frame.function.is_some() {
+            self.arguments_object()
+        } else {
+            Value::Undefined
+        };
+
+        let mut function = self.new_function(code);
+        let f = unsafe { function.as_mut() };
+        f.captures = captures;
+        f.env = Some(env);
+        if is_arrow {
+            f.this_value = this_value;
+            f.new_target = new_target;
+            f.arguments = arguments;
+            f.home_object = home_object;
+        }
+
+        Ok(function)
+    }
+
+    fn define_class(&mut self) -> Result<(), NativeError> {
+        let constructor = self.pop()?;
+        let heritage = self.pop()?;
+        let mut function = match constructor {
+            Value::Function(function) => function,
+            _ => return Err(NativeError::internal_error("class constructor is not a function")),
+        };
+
+        let (prototype_parent, constructor_parent) = match heritage {
+            Value::Hole => (Some(self.intrinsics.object_prototype), self.intrinsics.function_prototype),
+            Value::Null => (None, self.intrinsics.function_prototype),
+            Value::Function(parent) if unsafe { parent.as_ref() }.is_constructor => {
+                let prototype = self.get_value(&heritage, &"prototype".into())?;
+                let prototype = match prototype {
+                    Value::Null => None,
+                    Value::Object(_) | Value::Function(_) => prototype.as_object(),
+                    _ => {
+                        let message = format!("Class extends value does not have valid prototype property {}", self.display(&prototype));
+                        return Err(NativeError::type_error(message));
+                    },
+                };
+                (prototype, unsafe { parent.as_ref() }.object_ptr())
+            },
+            _ => {
+                let message = format!("Class extends value {} is not a constructor or null", self.display(&heritage));
+                return Err(NativeError::type_error(message));
+            },
+        };
+
+        let mut prototype = self.alloc(Object::with_prototype(prototype_parent));
+        unsafe { prototype.as_mut() }.insert("constructor", Property::hidden(constructor.clone()));
+        let f = unsafe { function.as_mut() };
+        f.home_object = Some(prototype);
+        f.object_mut().prototype = Some(constructor_parent);
+        f.object_mut().insert("prototype", Property::readonly(Value::Object(prototype)));
+        let object = f.object_ptr();
+        self.write_barrier(function);
+        self.write_barrier(object);
+
+        self.push(constructor);
+        self.push(Value::Object(prototype));
+        Ok(())
+    }
+
+    fn define_method(&mut self, instruction: Instruction) -> Result<(), NativeError> {
+        let function = self.pop()?;
+        let key = self.pop()?;
+        let key = self.to_property_key(key)?;
+        let object = self.peek_object()?;
+        if let Value::Function(mut function) = function {
+            unsafe { function.as_mut() }.home_object = Some(object);
+            self.write_barrier(function);
+        }
+
+        let existing = unsafe { object.as_ref() }.get(key.clone()).cloned().filter(|property| property.is_accessor());
+        let property = match instruction {
+            Instruction::DefineMethod(enumerable) => Property { enumerable: enumerable != 0, ..Property::data(function) },
+            Instruction::DefineGetter(enumerable) => {
+                let setter = existing.map(|property| property.setter()).unwrap_or(Value::Undefined);
+                Property::accessor(function, setter, enumerable != 0)
+            },
+            Instruction::DefineSetter(enumerable) => {
+                let getter = existing.map(|property| property.getter()).unwrap_or(Value::Undefined);
+                Property::accessor(getter, function, enumerable != 0)
+            },
+            _ => unreachable!(),
+        };
+        self.define_own(object, key, property)?;
+
+        Ok(())
+    }
+
+    /// The object `super` refers to, the prototype of the home object.
+    fn super_base(&self) -> Result<Option<NonNull<Object>>, NativeError> {
+        let home_object = self.frame().function.and_then(|function| unsafe { function.as_ref() }.home_object);
+        match home_object {
+            Some(home_object) => Ok(unsafe { home_object.as_ref() }.prototype),
+            None => Err(NativeError::syntax_error("'super' keyword unexpected here")),
+        }
+    }
+
+    // Variables looked up by name
+
+    fn resolve(&self, name: &str) -> Reference {
+        let key = PropertyKey::from(name);
+        let mut current = Some(self.frame().env);
+        while let Some(ptr) = current {
+            let env = unsafe { ptr.as_ref() };
+            match env.object() {
+                Some(object) => if self.has_property(object, &key) {
+                    return Reference::Object(object);
+                },
+                None => if env.contains_key(name) {
+                    return Reference::Record(ptr);
+                },
+            }
+
+            current = env.parent_ptr();
+            if current.is_none() && self.has_property(self.global, &key) {
+                return Reference::Object(self.global);
+            }
+        }
+
+        Reference::Unresolvable
+    }
+
+    fn get_name(&mut self, name: &str) -> Result<Value, NativeError> {
+        match self.resolve(name) {
+            Reference::Record(env) => Ok(unsafe { env.as_ref() }.get(name)?.clone()),
+            Reference::Object(object) => self.get(object, &name.into(), Value::Object(object)),
+            Reference::Unresolvable => Err(NativeError::reference_error(format!("{} is not defined", name))),
+        }
+    }
+
+    fn set_name(&mut self, name: &str, value: Value) -> Result<(), NativeError> {
+        match self.resolve(name) {
+            Reference::Record(mut env) => {
+                self.write_barrier(env);
+                unsafe { env.as_mut() }.update(name, value)
+            },
+            Reference::Object(object) => {
+                let ok = self.set(object, name.into(), value, Value::Object(object))?;
+                if !ok && self.is_strict() {
+                    return Err(NativeError::type_error(format!("Cannot assign to read only property '{}' of object", name)));
+                }
+                Ok(())
+            },
+            Reference::Unresolvable => {
+                if self.is_strict() {
+                    return Err(NativeError::reference_error(format!("{} is not defined", name)));
+                }
+                let global = self.global;
+                self.define_own(global, name.into(), Property::data(value))?;
+                Ok(())
+            },
+        }
+    }
+
+    fn declare_name(&mut self, name: &str) -> Result<(), NativeError> {
+        if self.frame().function.is_none() {
+            // NOTE: Script 顶层的 `var` 是全局对象的属性
+            let global = self.global;
+            let exists = unsafe { self.global_env.as_ref() }.contains_key(name)
+                || unsafe { global.as_ref() }.contains_key(name);
+            if !exists {
+                let property = Property { configurable: false, ..Property::data(Value::Undefined) };
+                self.define_own(global, name.into(), property)?;
+            }
+            return Ok(());
+        }
+
+        if !self.frame().has_own_env {
+            let parent = self.frame().env;
+            let env = self.alloc(Environment::with_parent(EnvironmentKind::Function, parent));
+            let frame = self.frame_mut();
+            frame.env = env;
+            frame.has_own_env = true;
+        }
+        let mut env = self.frame().env;
+        let env = unsafe { env.as_mut() };
+        if !env.contains_key(name) {
+            env.insert(name, RecordKind::Var, Value::Undefined)?;
+        }
+
+        Ok(())
+    }
+
+    fn delete_value(&mut self, object: Value, key: PropertyKey) -> Result<bool, NativeError> {
+        let target = self.to_object(object)?;
+        let ok = self.delete_property(target, &key);
+        if !ok && self.is_strict() {
+            return Err(NativeError::type_error(format!("Cannot delete property '{}' of object", key)));
+        }
+
+        Ok(ok)
+    }
+
+    fn require_object_coercible(&self, value: &Value, key: &Value) -> Result<(), NativeError> {
+        if value.is_nullish() {
+            let message = format!("Cannot read properties of {} (reading '{}')", self.display(value), self.display(key));
+            return Err(NativeError::type_error(message));
+        }
+
+        Ok(())
+    }
+
+    fn step(&mut self, base: usize) -> Result<Flow, NativeError> {
+        let frame = self.frames.last_mut().unwrap();
+        frame.ip = frame.pc;
+        let (instruction, next) = Instruction::decode(&frame.code.object.code, frame.pc)
+            .map_err(|error| NativeError::internal_error(error.message()))?;
+        frame.pc = next;
+
+        match instruction {
+            Instruction::Nop | Instruction::Debugger => { },
+
+            // Literals
+            Instruction::Undefined => self.push(Value::Undefined),
+            Instruction::Null => self.push(Value::Null),
+            Instruction::True => self.push(Value::Boolean(true)),
+            Instruction::False => self.push(Value::Boolean(false)),
+            Instruction::Hole => self.push(Value::Hole),
+            Instruction::Int(n) => self.push(if n.abs() <= 9007199254740992 { Value::I64(n) } else { Value::F64(n as f64) }),
+            Instruction::Const(index) => {
+                let value = match *self.constant(index)? {
+                    Constant::Number(n) => Value::number(n),
+                    _ => self.cached_constant(index)?,
+                };
+                self.push(value);
+            },
+            Instruction::This => {
+                let this = self.this_value()?;
+                self.push(this);
+            },
+            Instruction::NewTarget => {
+                let new_target = self.frame().new_target.clone();
+                self.push(new_target);
+            },
+            Instruction::Closure(index) => {
+                let code = self.frame().code.function(index).cloned()
+                    .ok_or_else(|| NativeError::internal_error(format!("constant {} is not a function", index)))?;
+                let function = self.new_closure(code)?;
+                self.push(Value::Function(function));
+            },
+            Instruction::RegExp(index) => {
+                let (pattern, flags) = match *self.constant(index)? {
+                    Constant::RegExp { ref pattern, ref flags } => (pattern.clone(), flags.clone()),
+                    _ => return Err(NativeError::internal_error(format!("constant {} is not a regular expression", index))),
+                };
+                let source = self.new_string(pattern.clone());
+                let flags_value = self.new_string(flags.clone());
+                let mut object = Object::with_kind(self.intrinsics.regexp_prototype, ObjectKind::RegExp { pattern, flags });
+                object.insert("lastIndex", Property { configurable: false, ..Property::hidden(0) });
+                object.insert("source", Property::readonly(source));
+                object.insert("flags", Property::readonly(flags_value));
+                let object = self.alloc(object);
+                self.push(Value::Object(object));
+            },
+            Instruction::TemplateObject(index) => {
+                let value = self.cached_constant(index)?;
+                self.push(value);
+            },
+            Instruction::Object => {
+                let object = self.new_object();
+                self.push(Value::Object(object));
+            },
+            Instruction::Array(count) => {
+                let values = self.pop_n(count)?;
+                let array = self.new_array(values);
+                self.push(array);
+            },
+
+            // Stack
+            Instruction::Pop => { self.pop()?; },
+            Instruction::Dup => {
+                let value = self.peek_packed()?.clone();
+                self.push_packed(value);
+            },
+            Instruction::Dup2 => {
+                let stack = &mut self.frame_mut().stack;
+                let len = stack.len();
+                if len < 2 {
+                    return Err(stack_underflow());
+                }
+                stack.extend_from_within(len - 2..);
+            },
+            Instruction::Swap => {
+                let stack = &mut self.frame_mut().stack;
+                let len = stack.len();
+                if len < 2 {
+                    return Err(stack_underflow());
+                }
+                stack.swap(len - 1, len - 2);
+            },
+            Instruction::Rot3 => {
+                let stack = &mut self.frame_mut().stack;
+                let len = stack.len();
+                if len < 3 {
+                    return Err(stack_underflow());
+                }
+                let c = stack.pop().unwrap();
+                stack.insert(len - 3, c);
+            },
+
+            // Variables
+            Instruction::GetLocal(slot) => {
+                let value = self.local(slot)?.clone();
+                self.push_packed(value);
+            },
+            Instruction::SetLocal(slot) => {
+                let value = self.peek_packed()?.clone();
+                *self.local(slot)? = value;
+            },
+            Instruction::NewCell(slot) => {
+                let cell = self.alloc(PackedValue::from(Value::Hole));
+                let cells = &mut self.frame_mut().cells;
+                match cells.get_mut(slot as usize) {
+                    Some(item) => *item = Some(cell),
+                    None => return Err(NativeError::internal_error(format!("local slot {} out of range", slot))),
+                }
+            },
+            Instruction::GetCell(slot) => {
+                let value = unsafe { self.cell(slot)?.as_ref() }.clone();
+                self.push_packed(value);
+            },
+            Instruction::SetCell(slot) => {
+                let value = self.peek_packed()?.clone();
+                let mut cell = self.cell(slot)?;
+                *unsafe { cell.as_mut() } = value;
+                self.write_barrier(cell);
+            },
+            Instruction::GetUpvalue(index) => {
+                let value = unsafe { self.upvalue(index)?.as_ref() }.clone();
+                self.push_packed(value);
+            },
+            Instruction::SetUpvalue(index) => {
+                let value = self.peek_packed()?.clone();
+                let mut cell = self.upvalue(index)?;
+                *unsafe { cell.as_mut() } = value;
+                self.write_barrier(cell);
+            },
+            Instruction::CheckHole(name) => {
+                if self.peek()?.is_hole() {
+                    let name = self.name(name)?;
+                    return Err(NativeError::reference_error(format!("Cannot access '{}' before initialization", name)));
+                }
+            },
+            Instruction::GetName(name) => {
+                let name = self.name(name)?;
+                let value = self.get_name(&name)?;
+                self.push(value);
+            },
+            Instruction::SetName(name) => {
+                let name = self.name(name)?;
+                let value = self.peek()?;
+                self.set_name(&name, value)?;
+            },
+            Instruction::TypeofName(name) => {
+                let name = self.name(name)?;
+                let type_of = match self.resolve(&name) {
+                    Reference::Unresolvable => "undefined",
+                    _ => self.get_name(&name)?.type_of(),
+                };
+                let value = self.new_string(type_of);
+                self.push(value);
+            },
+            Instruction::DeleteName(name) => {
+                let name = self.name(name)?;
+                let ok = match self.resolve(&name) {
+                    Reference::Record(_) => false,
+                    Reference::Object(object) => self.delete_property(object, &name.into()),
+                    Reference::Unresolvable => true,
+                };
+                self.push(Value::Boolean(ok));
+            },
+            Instruction::DeclareName(name) => {
+                let name = self.name(name)?;
+                self.declare_name(&name)?;
+            },
+            Instruction::Arguments => {
+                let arguments = self.arguments_object();
+                self.push(arguments);
+            },
+            Instruction::Rest(start) => {
+                let rest = self.frame().arguments.get(start as usize..).map(|args| args.to_vec()).unwrap_or_default();
+                let array = self.new_array(rest);
+                self.push(array);
+            },
+
+            // Properties
+            Instruction::GetNamed(name, cache) => {
+                let object = self.pop()?;
+                let value = self.get_named(name, cache, object)?;
+                self.push(value);
+            },
+            Instruction::SetNamed(name, cache) => {
+                let value = self.pop()?;
+                let object = self.pop()?;
+                self.set_named(name, cache, object, value.clone())?;
+                self.push(value);
+            },
+            Instruction::GetKeyed => {
+                let key = self.pop()?;
+                let object = self.pop()?;
+                self.require_object_coercible(&object, &key)?;
+                let key = self.to_property_key(key)?;
+                let value = self.get_value(&object, &key)?;
+                self.push(value);
+            },
+            Instruction::SetKeyed => {
+                let value = self.pop()?;
+                let key = self.pop()?;
+                let object = self.pop()?;
+                let key = match object {
+                    Value::Undefined | Value::Null => PropertyKey::String(self.display(&key)),
+                    _ => self.to_property_key(key)?,
+                };
+                let strict = self.is_strict();
+                self.set_value(&object, key, value.clone(), strict)?;
+                self.push(value);
+            },
+            Instruction::DeleteNamed(name) => {
+                let key = PropertyKey::String(self.name(name)?);
+                let object = self.pop()?;
+                let ok = self.delete_value(object, key)?;
+                self.push(Value::Boolean(ok));
+            },
+            Instruction::DeleteKeyed => {
+                let key = self.pop()?;
+                let object = self.pop()?;
+                self.require_object_coercible(&object, &key)?;
+                let key = self.to_property_key(key)?;
+                let ok = self.delete_value(object, key)?;
+                self.push(Value::Boolean(ok));
+            },
+            Instruction::GetSuper => {
+                let key = self.pop()?;
+                let key = self.to_property_key(key)?;
+                let this = self.this_value()?;
+                let value = match self.super_base()? {
+                    Some(base) => self.get(base, &key, this)?,
+                    None => return Err(NativeError::type_error(format!("Cannot read properties of null (reading '{}')", key))),
+                };
+                self.push(value);
+            },
+            Instruction::SetSuper => {
+                let value = self.pop()?;
+                let key = self.pop()?;
+                let key = self.to_property_key(key)?;
+                let this = self.this_value()?;
+                let ok = match self.super_base()? {
+                    Some(base) => self.set(base, key.clone(), value.clone(), this)?,
+                    None => false,
+                };
+                if !ok && self.is_strict() {
+                    return Err(NativeError::type_error(format!("Cannot assign to read only property '{}' of object", key)));
+                }
+                self.push(value);
+            },
+            Instruction::DefineField => {
+                let value = self.pop()?;
+                let key = self.pop()?;
+                let key = self.to_property_key(key)?;
+                let object = self.peek_object()?;
+                self.define_own(object, key, Property::data(value))?;
+            },
+            Instruction::DefineNamed(name) => {
+                let key = PropertyKey::String(self.name(name)?);
+                let value = self.pop()?;
+                let object = self.peek_object()?;
+                self.define_own(object, key, Property::data(value))?;
+            },
+            Instruction::DefineMethod(_) | Instruction::DefineGetter(_) | Instruction::DefineSetter(_) => {
+                self.define_method(instruction)?;
+            },
+            Instruction::CopyDataProperties => {
+                let source = self.pop()?;
+                let target = self.peek_object()?;
+                if !source.is_nullish() {
+                    let source = self.to_object(source)?;
+                    let keys = unsafe { source.as_ref() }.keys().into_iter()
+                        .filter(|key| unsafe { source.as_ref() }.properties[*key].enumerable)
+                        .cloned()
+                        .collect::<Vec<PropertyKey>>();
+                    let receiver = self.object_value(source);
+                    for key in keys {
+                        let value = self.get(source, &key, receiver.clone())?;
+                        self.define_own(target, key, Property::data(value))?;
+                    }
+                }
+            },
+            Instruction::SetPrototype => {
+                let prototype = self.pop()?;
+                let object = self.peek_object()?;
+                match prototype {
+                    Value::Null => unsafe { &mut *object.as_ptr() }.prototype = None,
+                    Value::Object(_) | Value::Function(_) => unsafe { &mut *object.as_ptr() }.prototype = prototype.as_object(),
+                    _ => { },
+                }
+                self.write_barrier(object);
+            },
+            Instruction::ArrayPush => {
+                let value = self.pop()?;
+                let array = self.peek_object()?;
+                self.array_push(array, value)?;
+            },
+            Instruction::ArrayHole => {
+                let array = self.peek_object()?;
+                let length = match unsafe { array.as_ref() }.get("length").map(Property::value) {
+                    Some(Value::I64(n)) => n,
+                    _ => 0,
+                };
+                self.define_own(array, "length".into(), Property::data(length + 1))?;
+            },
+            Instruction::ArraySpread => {
+                let iterable = self.pop()?;
+                let array = self.peek_object()?;
+                let iterator = self.get_iterator(iterable)?;
+                loop {
+                    let (value, done) = self.iterator_step(&iterator)?;
+                    if done {
+                        break;
+                    }
+                    self.array_push(array, value)?;
+                }
+            },
+            Instruction::Class(_) => self.define_class()?,
+
+            // Operators
+            Instruction::Add | Instruction::Sub | Instruction::Mul | Instruction::Div
+            | Instruction::Mod | Instruction::Exp | Instruction::Shl | Instruction::Shr
+            | Instruction::UShr | Instruction::BitAnd | Instruction::BitOr | Instruction::BitXor => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                let value = self.binary(instruction, a, b)?;
+                self.push(value);
+            },
+            Instruction::Eq | Instruction::Ne => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                let equals = self.loose_equals(a, b)?;
+                self.push(Value::Boolean(equals == (instruction == Instruction::Eq)));
+            },
+            Instruction::StrictEq | Instruction::StrictNe => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                let equals = self.strict_equals(&a, &b);
+                self.push(Value::Boolean(equals == (instruction == Instruction::StrictEq)));
+            },
+            Instruction::Lt | Instruction::Le | Instruction::Gt | Instruction::Ge => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                let result = match instruction {
+                    Instruction::Lt => self.less_than(a, b, true)? == Some(true),
+                    Instruction::Gt => self.less_than(b, a, false)? == Some(true),
+                    Instruction::Le => self.less_than(b, a, false)? == Some(false),
+                    _ => self.less_than(a, b, true)? == Some(false),
+                };
+                self.push(Value::Boolean(result));
+            },
+            Instruction::In => {
+                let object = self.pop()?;
+                let key = self.pop()?;
+                let object = match object.as_object() {
+                    Some(object) => object,
+                    None => {
+                        let message = format!("Cannot use 'in' operator to search for '{}' in {}", self.display(&key), self.display(&object));
+                        return Err(NativeError::type_error(message));
+                    },
+                };
+                let key = self.to_property_key(key)?;
+                let result = self.has_property(object, &key);
+                self.push(Value::Boolean(result));
+            },
+            Instruction::InstanceOf => {
+                let target = self.pop()?;
+                let value = self.pop()?;
+                let result = self.instance_of(&value, &target)?;
+                self.push(Value::Boolean(result));
+            },
+            Instruction::Neg | Instruction::BitNot | Instruction::Inc | Instruction::Dec => {
+                let value = self.pop()?;
+                let value = self.unary(instruction, value)?;
+                self.push(value);
+            },
+            Instruction::Plus => {
+                let value = self.pop()?;
+                let n = self.to_number(value)?;
+                self.push(Value::number(n));
+            },
+            Instruction::Not => {
+                let value = self.pop()?;
+                self.push(Value::Boolean(!value.to_boolean()));
+            },
+            Instruction::TypeOf => {
+                let value = self.pop()?;
+                let type_of = self.new_string(value.type_of());
+                self.push(type_of);
+            },
+            Instruction::ToNumeric => {
+                let value = self.pop()?;
+                let value = self.to_numeric(value)?;
+                self.push(value);
+            },
+            Instruction::ToPropertyKey => {
+                let value = self.pop()?;
+                let value = match value {
+                    Value::String(_) | Value::Symbol(_) => value,
+                    value => {
+                        let key = self.to_property_key(value)?;
+                        self.key_value(key)
+                    },
+                };
+                self.push(value);
+            },
+            Instruction::ToString => {
+                let value = self.pop()?;
+                let value = self.to_string_value(value)?;
+                self.push(value);
+            },
+
+            // Control flow
+            Instruction::Jump(offset) => self.jump(offset),
+            Instruction::JumpIfTrue(offset) | Instruction::JumpIfFalse(offset) => {
+                let condition = self.pop()?.to_boolean();
+                if condition == (instruction == Instruction::JumpIfTrue(offset)) {
+                    self.jump(offset);
+                }
+            },
+            Instruction::JumpIfNullish(offset) | Instruction::JumpIfNotNullish(offset) => {
+                let nullish = self.pop()?.is_nullish();
+                if nullish == (instruction == Instruction::JumpIfNullish(offset)) {
+                    self.jump(offset);
+                }
+            },
+            Instruction::Return => return self.do_return(base),
+            Instruction::Throw => {
+                let value = self.pop()?;
+                return Err(NativeError::Exception(value));
+            },
+            Instruction::ThrowError(kind, message) => {
+                let message = self.name(message)?;
+                return Err(match error_kind_from_u8(kind) {
+                    Some(ErrorKind::SyntaxError) => NativeError::syntax_error(message),
+                    Some(ErrorKind::EvalError) => NativeError::eval_error(0, 0, NativeError::error(message)),
+                    Some(ErrorKind::RangeError) => NativeError::range_error(message),
+                    Some(ErrorKind::ReferenceError) => NativeError::reference_error(message),
+                    Some(ErrorKind::TypeError) => NativeError::type_error(message),
+                    Some(ErrorKind::URIError) => NativeError::uri_error(message),
+                    Some(ErrorKind::InternalError) | None => NativeError::internal_error(message),
+                });
+            },
+
+            // Calls
+            Instruction::Call(argc) => {
+                let args = self.pop_n(argc)?;
+                let this = self.pop()?;
+                let callee = self.pop()?;
+                self.call_value(callee, this, args)?;
+            },
+            Instruction::CallSpread => {
+                let array = self.pop()?;
+                let args = self.spread_arguments(array)?;
+                let this = self.pop()?;
+                let callee = self.pop()?;
+                self.call_value(callee, this, args)?;
+            },
+            Instruction::New(argc) => {
+                let args = self.pop_n(argc)?;
+                let callee = self.pop()?;
+                self.construct_value(callee, args)?;
+            },
+            Instruction::NewSpread => {
+                let array = self.pop()?;
+                let args = self.spread_arguments(array)?;
+                let callee = self.pop()?;
+                self.construct_value(callee, args)?;
+            },
+            Instruction::SuperCall(argc) => {
+                let args = self.pop_n(argc)?;
+                self.super_call(args)?;
+            },
+            Instruction::SuperCallSpread => {
+                let array = self.pop()?;
+                let args = self.spread_arguments(array)?;
+                self.super_call(args)?;
+            },
+
+            // Environments
+            Instruction::EnterWith => {
+                let object = self.pop()?;
+                let object = self.to_object(object)?;
+                let parent = self.frame().env;
+                let env = self.alloc(Environment::with_object(object, parent));
+                self.frame_mut().env = env;
+            },
+            Instruction::LeaveWith => {
+                let env = unsafe { self.frame().env.as_ref() };
+                match (env.kind(), env.parent_ptr()) {
+                    (EnvironmentKind::Object, Some(parent)) => self.frame_mut().env = parent,
+                    _ => return Err(NativeError::internal_error("LeaveWith outside of a with statement")),
+                }
+            },
+
+            // Iterators
+            Instruction::GetIterator => {
+                let iterable = self.pop()?;
+                let iterator = self.get_iterator(iterable)?;
+                self.push(iterator);
+            },
+            Instruction::IteratorNext => {
+                let iterator = self.peek()?;
+                let (value, done) = self.iterator_step(&iterator)?;
+                self.push(value);
+                self.push(Value::Boolean(done));
+            },
+            Instruction::IteratorClose => {
+                let iterator = self.pop()?;
+                self.iterator_close(&iterator)?;
+            },
+            Instruction::ForInEnumerate => {
+                let object = self.pop()?;
+                let iterator = self.for_in_iterator(object)?;
+                self.push(iterator);
+            },
+
+            // Generators and async functions
+            Instruction::InitialYield => return self.initial_yield(base),
+            Instruction::Yield => return self.yield_value(),
+            Instruction::GetAsyncIterator | Instruction::Await => return Err(unsupported_async()),
+
+            Instruction::Callee => {
+                let callee = self.frame().function.map(Value::Function).unwrap_or(Value::Undefined);
+                self.push(callee);
+            },
+        }
+
+        Ok(Flow::Next)
+    }
+}
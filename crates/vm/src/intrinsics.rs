@@ -0,0 +1,639 @@
+// Intrinsics
+//
+//      Object.prototype <- Function.prototype
+//                       <- Array.prototype
+//                       <- Error.prototype <- TypeError.prototype ...
+//                       <- %IteratorPrototype% <- %ArrayIteratorPrototype%
+//                                              <- %GeneratorPrototype%
+//
+// NOTE: 只实现了运行脚本所需要的最小的一组内置对象。
+
+use crate::value::Value;
+use crate::error::NativeError;
+use crate::object::{ Object, ObjectKind, Property, PropertyKey, };
+use crate::function::{ self, Function, FunctionCode, NativeFunction, };
+use crate::frame::GeneratorState;
+use crate::interpreter::Resume;
+use crate::symbol::Symbol;
+use crate::vm::{ Vm, Allocator, };
+
+use std::ptr::NonNull;
+
+
+#[derive(Debug)]
+pub struct Intrinsics {
+    pub object_prototype: NonNull<Object>,
+    pub function_prototype: NonNull<Object>,
+    pub array_prototype: NonNull<Object>,
+    pub string_prototype: NonNull<Object>,
+    pub number_prototype: NonNull<Object>,
+    pub boolean_prototype: NonNull<Object>,
+    pub symbol_prototype: NonNull<Object>,
+    pub bigint_prototype: NonNull<Object>,
+    pub regexp_prototype: NonNull<Object>,
+    pub error_prototype: NonNull<Object>,
+    pub syntax_error_prototype: NonNull<Object>,
+    pub eval_error_prototype: NonNull<Object>,
+    pub range_error_prototype: NonNull<Object>,
+    pub reference_error_prototype: NonNull<Object>,
+    pub type_error_prototype: NonNull<Object>,
+    pub uri_error_prototype: NonNull<Object>,
+    pub internal_error_prototype: NonNull<Object>,
+    pub iterator_prototype: NonNull<Object>,
+    pub array_iterator_prototype: NonNull<Object>,
+    pub generator_prototype: NonNull<Object>,
+    /// `Array.prototype.values`, also the `@@iterator` of arguments objects.
+    pub array_values: Value,
+    /// `Symbol.iterator`
+    pub symbol_iterator: Symbol,
+}
+
+impl Intrinsics {
+    pub fn new(allocator: &mut Allocator, symbols: &mut Vec<Option<String>>) -> Self {
+        let object_prototype = allocator.alloc(Object::empty());
+        let mut object = |prototype: NonNull<Object>| allocator.alloc(Object::with_prototype(prototype));
+
+        let function_prototype = object(object_prototype);
+        let error_prototype = object(object_prototype);
+        let iterator_prototype = object(object_prototype);
+
+        let symbol_iterator = Symbol::new(false, symbols.len());
+        symbols.push(Some("Symbol.iterator".to_string()));
+
+        Intrinsics {
+            object_prototype,
+            function_prototype,
+            array_prototype: object(object_prototype),
+            string_prototype: object(object_prototype),
+            number_prototype: object(object_prototype),
+            boolean_prototype: object(object_prototype),
+            symbol_prototype: object(object_prototype),
+            bigint_prototype: object(object_prototype),
+            regexp_prototype: object(object_prototype),
+            error_prototype,
+            syntax_error_prototype: object(error_prototype),
+            eval_error_prototype: object(error_prototype),
+            range_error_prototype: object(error_prototype),
+            reference_error_prototype: object(error_prototype),
+            type_error_prototype: object(error_prototype),
+            uri_error_prototype: object(error_prototype),
+            internal_error_prototype: object(error_prototype),
+            iterator_prototype,
+            array_iterator_prototype: object(iterator_prototype),
+            generator_prototype: object(iterator_prototype),
+            array_values: Value::Undefined,
+            symbol_iterator,
+        }
+    }
+}
+
+
+#[inline]
+fn arg(args: &[Value], index: usize) -> Value {
+    args.get(index).cloned().unwrap_or(Value::Undefined)
+}
+
+impl Vm {
+    fn define_methods(&mut self, object: NonNull<Object>, methods: &[(&str, u32, NativeFunction)]) {
+        for &(name, length, native) in methods {
+            let function = self.new_native_function(name, length, native);
+            unsafe { &mut *object.as_ptr() }.insert(name, Property::hidden(function));
+        }
+    }
+
+    fn define_value<K: Into<PropertyKey>>(&mut self, object: NonNull<Object>, key: K, value: Value) {
+        unsafe { &mut *object.as_ptr() }.insert(key, Property::hidden(value));
+    }
+
+    /// Create a constructor, links it with its prototype and defines it on the global object.
+    fn define_constructor(&mut self, name: &str, length: u32, native: NativeFunction, prototype: NonNull<Object>) -> Value {
+        let constructor = self.new_native_function(name, length, native);
+        if let Value::Function(mut function) = constructor {
+            let function = unsafe { function.as_mut() };
+            function.is_constructor = true;
+            function.object_mut().insert("prototype", Property::readonly(Value::Object(prototype)));
+        }
+        self.define_value(prototype, "constructor", constructor.clone());
+        let global = self.global;
+        self.define_value(global, name, constructor.clone());
+
+        constructor
+    }
+
+    pub(crate) fn install_globals(&mut self) {
+        let intrinsics = &self.intrinsics;
+        let global = self.global;
+        let object_prototype = intrinsics.object_prototype;
+        let function_prototype = intrinsics.function_prototype;
+        let array_prototype = intrinsics.array_prototype;
+        let iterator_prototype = intrinsics.iterator_prototype;
+        let symbol_iterator = intrinsics.symbol_iterator;
+
+        self.define_value(global, "global", Value::Object(global));
+        self.define_value(global, "globalThis", Value::Object(global));
+        unsafe { &mut *global.as_ptr() }.insert("undefined", Property::readonly(Value::Undefined));
+        unsafe { &mut *global.as_ptr() }.insert("NaN", Property::readonly(Value::F64(f64::NAN)));
+        unsafe { &mut *global.as_ptr() }.insert("Infinity", Property::readonly(Value::F64(f64::INFINITY)));
+        self.define_methods(global, &[ ("isNaN", 1, function::isNaN) ]);
+
+        // Object
+        let object = self.define_constructor("Object", 1, object_constructor, object_prototype);
+        let object = object.as_object().unwrap();
+        self.define_methods(object, &[
+            ("keys", 1, object_keys),
+            ("getPrototypeOf", 1, object_get_prototype_of),
+            ("create", 2, object_create),
+        ]);
+        self.define_methods(object_prototype, &[
+            ("toString", 0, object_prototype_to_string),
+            ("valueOf", 0, object_prototype_value_of),
+            ("hasOwnProperty", 1, object_prototype_has_own_property),
+        ]);
+
+        // Function
+        self.define_constructor("Function", 1, function_constructor, function_prototype);
+        self.define_methods(function_prototype, &[
+            ("toString", 0, function_prototype_to_string),
+            ("call", 1, function_prototype_call),
+        ]);
+
+        // Array
+        let array = self.define_constructor("Array", 1, array_constructor, array_prototype);
+        let array = array.as_object().unwrap();
+        self.define_methods(array, &[ ("isArray", 1, array_is_array) ]);
+        unsafe { &mut *array_prototype.as_ptr() }.kind = ObjectKind::Array;
+        unsafe { &mut *array_prototype.as_ptr() }.insert("length", Property { enumerable: false, configurable: false, ..Property::data(0) });
+        self.define_methods(array_prototype, &[
+            ("push", 1, array_prototype_push),
+            ("pop", 0, array_prototype_pop),
+            ("join", 1, array_prototype_join),
+            ("toString", 0, array_prototype_to_string),
+            ("values", 0, array_prototype_values),
+        ]);
+        let values = self.get_data(array_prototype, &"values".into()).unwrap_or(Value::Undefined);
+        self.define_value(array_prototype, symbol_iterator, values.clone());
+        self.intrinsics.array_values = values;
+
+        // String, Number, Boolean, Symbol
+        let string_prototype = self.intrinsics.string_prototype;
+        unsafe { &mut *string_prototype.as_ptr() }.kind = ObjectKind::String(self.alloc(String::new()));
+        self.define_constructor("String", 1, string_constructor, string_prototype);
+        self.define_methods(string_prototype, &[
+            ("toString", 0, string_prototype_value_of),
+            ("valueOf", 0, string_prototype_value_of),
+        ]);
+        let string_iterator = self.new_native_function("[Symbol.iterator]", 0, string_prototype_iterator);
+        self.define_value(string_prototype, symbol_iterator, string_iterator);
+
+        let number_prototype = self.intrinsics.number_prototype;
+        unsafe { &mut *number_prototype.as_ptr() }.kind = ObjectKind::Number(0.0);
+        self.define_constructor("Number", 1, number_constructor, number_prototype);
+        self.define_methods(number_prototype, &[
+            ("toString", 0, number_prototype_to_string),
+            ("valueOf", 0, number_prototype_value_of),
+        ]);
+
+        let boolean_prototype = self.intrinsics.boolean_prototype;
+        unsafe { &mut *boolean_prototype.as_ptr() }.kind = ObjectKind::Boolean(false);
+        self.define_constructor("Boolean", 1, boolean_constructor, boolean_prototype);
+        self.define_methods(boolean_prototype, &[
+            ("toString", 0, boolean_prototype_to_string),
+            ("valueOf", 0, boolean_prototype_value_of),
+        ]);
+
+        let symbol_prototype = self.intrinsics.symbol_prototype;
+        let symbol = self.define_constructor("Symbol", 0, symbol_constructor, symbol_prototype);
+        let symbol = symbol.as_object().unwrap();
+        unsafe { &mut *symbol.as_ptr() }.insert("iterator", Property::readonly(Value::Symbol(symbol_iterator)));
+        self.define_methods(symbol_prototype, &[ ("toString", 0, symbol_prototype_to_string) ]);
+
+        // Errors
+        let errors: [(&str, NativeFunction, NonNull<Object>); 8] = [
+            ("Error", error_constructor, self.intrinsics.error_prototype),
+            ("SyntaxError", syntax_error_constructor, self.intrinsics.syntax_error_prototype),
+            ("EvalError", eval_error_constructor, self.intrinsics.eval_error_prototype),
+            ("RangeError", range_error_constructor, self.intrinsics.range_error_prototype),
+            ("ReferenceError", reference_error_constructor, self.intrinsics.reference_error_prototype),
+            ("TypeError", type_error_constructor, self.intrinsics.type_error_prototype),
+            ("URIError", uri_error_constructor, self.intrinsics.uri_error_prototype),
+            ("InternalError", internal_error_constructor, self.intrinsics.internal_error_prototype),
+        ];
+        let mut error = None;
+        for &(name, native, prototype) in errors.iter() {
+            let constructor = self.define_constructor(name, 1, native, prototype);
+            // NOTE: NativeError 构造函数的原型是 Error
+            match error {
+                None => error = constructor.as_object(),
+                Some(error) => unsafe { &mut *constructor.as_object().unwrap().as_ptr() }.prototype = Some(error),
+            }
+            let name = self.new_string(name);
+            self.define_value(prototype, "name", name);
+            let message = self.new_string("");
+            self.define_value(prototype, "message", message);
+        }
+        let error_prototype = self.intrinsics.error_prototype;
+        self.define_methods(error_prototype, &[ ("toString", 0, error_prototype_to_string) ]);
+
+        // Iterators and generators
+        let iterator = self.new_native_function("[Symbol.iterator]", 0, iterator_prototype_iterator);
+        self.define_value(iterator_prototype, symbol_iterator, iterator);
+        let array_iterator_prototype = self.intrinsics.array_iterator_prototype;
+        self.define_methods(array_iterator_prototype, &[ ("next", 0, array_iterator_prototype_next) ]);
+        let generator_prototype = self.intrinsics.generator_prototype;
+        self.define_methods(generator_prototype, &[
+            ("next", 1, generator_prototype_next),
+            ("return", 1, generator_prototype_return),
+            ("throw", 1, generator_prototype_throw),
+        ]);
+    }
+}
+
+
+// Object
+fn object_constructor(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    match arg(args, 0) {
+        Value::Undefined | Value::Null => Ok(Value::Object(vm.new_object())),
+        value => vm.to_object(value.clone()).map(|object| vm.object_value(object)),
+    }
+}
+
+fn object_keys(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let object = vm.to_object(arg(args, 0))?;
+    let keys = vm.own_enumerable_keys(object);
+    let keys = keys.into_iter()
+        .map(|key| vm.new_string(key))
+        .collect::<Vec<Value>>();
+
+    Ok(vm.new_array(keys))
+}
+
+fn object_get_prototype_of(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let object = vm.to_object(arg(args, 0))?;
+    Ok(match unsafe { object.as_ref() }.prototype {
+        Some(prototype) => vm.object_value(prototype),
+        None => Value::Null,
+    })
+}
+
+fn object_create(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let prototype = match arg(args, 0) {
+        Value::Null => None,
+        value => match value.as_object() {
+            Some(prototype) => Some(prototype),
+            None => return Err(NativeError::type_error(format!("Object prototype may only be an Object or null: {}", vm.display(&value)))),
+        },
+    };
+
+    Ok(Value::Object(vm.alloc(Object::with_prototype(prototype))))
+}
+
+fn object_prototype_to_string(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let tag = match this {
+        Value::Undefined => "Undefined",
+        Value::Null => "Null",
+        Value::Function(_) => "Function",
+        _ => match unsafe { vm.to_object(this.clone())?.as_ref() }.kind {
+            ObjectKind::Array => "Array",
+            ObjectKind::Arguments => "Arguments",
+            ObjectKind::Error => "Error",
+            ObjectKind::Boolean(_) => "Boolean",
+            ObjectKind::Number(_) => "Number",
+            ObjectKind::String(_) => "String",
+            ObjectKind::RegExp { .. } => "RegExp",
+            _ => "Object",
+        },
+    };
+
+    Ok(vm.new_string(format!("[object {}]", tag)))
+}
+
+fn object_prototype_value_of(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let object = vm.to_object(this)?;
+    Ok(vm.object_value(object))
+}
+
+fn object_prototype_has_own_property(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let key = vm.to_property_key(arg(args, 0))?;
+    let object = vm.to_object(this)?;
+    Ok(Value::Boolean(unsafe { object.as_ref() }.contains_key(key)))
+}
+
+
+// Function
+fn function_constructor(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    Err(NativeError::internal_error("Function constructor is not supported yet"))
+}
+
+fn function_prototype_to_string(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    match this {
+        Value::Function(function) => {
+            let name = match unsafe { function.as_ref() }.object().get("name") {
+                Some(property) => vm.display(&property.value()),
+                None => String::new(),
+            };
+            Ok(vm.new_string(format!("function {}() {{ [native code] }}", name)))
+        },
+        _ => Err(NativeError::type_error("Function.prototype.toString requires that 'this' be a Function")),
+    }
+}
+
+fn function_prototype_call(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let (receiver, args) = match args.split_first() {
+        Some((receiver, args)) => (receiver.clone(), args),
+        None => (Value::Undefined, args),
+    };
+    vm.call(this, receiver, args)
+}
+
+
+// Array
+fn array_constructor(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    match args {
+        [ length ] if length.is_number() => {
+            let n = vm.to_number(length.clone())?;
+            if n < 0.0 || n.fract() != 0.0 || n > 4294967295.0 {
+                return Err(NativeError::range_error("Invalid array length"));
+            }
+            Ok(vm.new_array(vec![ Value::Hole; n as usize ]))
+        },
+        _ => Ok(vm.new_array(args.to_vec())),
+    }
+}
+
+fn array_is_array(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    Ok(Value::Boolean(match arg(args, 0) {
+        Value::Object(object) => unsafe { object.as_ref() }.is_array(),
+        _ => false,
+    }))
+}
+
+fn array_prototype_push(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let object = vm.to_object(this.clone())?;
+    let mut length = vm.length_of_array_like(&this)?;
+    for value in args {
+        vm.set(object, length.into(), value.clone(), this.clone())?;
+        length += 1;
+    }
+    vm.set(object, "length".into(), Value::I64(length as i64), this)?;
+
+    Ok(Value::I64(length as i64))
+}
+
+fn array_prototype_pop(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let object = vm.to_object(this.clone())?;
+    let length = vm.length_of_array_like(&this)?;
+    if length == 0 {
+        vm.set(object, "length".into(), Value::I64(0), this)?;
+        return Ok(Value::Undefined);
+    }
+
+    let key = PropertyKey::from(length - 1);
+    let value = vm.get(object, &key, this.clone())?;
+    vm.delete_property(object, &key);
+    vm.set(object, "length".into(), Value::I64(length as i64 - 1), this)?;
+
+    Ok(value)
+}
+
+fn array_prototype_join(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let object = vm.to_object(this.clone())?;
+    let separator = match arg(args, 0) {
+        Value::Undefined => ",".to_string(),
+        separator => vm.to_string(separator)?,
+    };
+    let length = vm.length_of_array_like(&this)?;
+
+    let mut items = Vec::with_capacity(length as usize);
+    for index in 0..length {
+        let item = vm.get(object, &index.into(), this.clone())?;
+        items.push(match item {
+            Value::Undefined | Value::Null => String::new(),
+            item => vm.to_string(item)?,
+        });
+    }
+
+    Ok(vm.new_string(items.join(&separator)))
+}
+
+fn array_prototype_to_string(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    array_prototype_join(vm, this, &[])
+}
+
+fn array_prototype_values(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let object = vm.to_object(this)?;
+    let target = vm.object_value(object);
+    let prototype = vm.intrinsics.array_iterator_prototype;
+
+    Ok(Value::Object(vm.alloc(Object::with_kind(prototype, ObjectKind::ArrayIterator { target, index: 0 }))))
+}
+
+
+// String, Number, Boolean, Symbol
+fn string_constructor(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let value = match args.first() {
+        Some(&Value::Symbol(symbol)) if !this.is_hole() => {
+            let description = vm.symbol_description(symbol).unwrap_or("").to_string();
+            vm.new_string(format!("Symbol({})", description))
+        },
+        Some(value) => vm.to_string_value(value.clone())?,
+        None => vm.new_string(""),
+    };
+
+    match this {
+        // NOTE: `new String(value)`
+        Value::Hole => vm.to_object(value).map(Value::Object),
+        _ => Ok(value),
+    }
+}
+
+fn this_string(vm: &mut Vm, this: &Value) -> Result<Value, NativeError> {
+    match *this {
+        Value::String(_) => Ok(this.clone()),
+        Value::Object(object) => match unsafe { object.as_ref() }.kind {
+            ObjectKind::String(s) => Ok(Value::String(s)),
+            _ => Err(NativeError::type_error("String.prototype.valueOf requires that 'this' be a String")),
+        },
+        _ => Err(NativeError::type_error("String.prototype.valueOf requires that 'this' be a String")),
+    }
+}
+
+fn string_prototype_value_of(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    this_string(vm, &this)
+}
+
+fn string_prototype_iterator(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let target = match this {
+        Value::Undefined | Value::Null => {
+            return Err(NativeError::type_error("String.prototype[Symbol.iterator] called on null or undefined"));
+        },
+        this => match vm.to_string_value(this)? {
+            Value::String(s) => s,
+            _ => unreachable!(),
+        },
+    };
+    let prototype = vm.intrinsics.array_iterator_prototype;
+    let kind = ObjectKind::StringIterator { target: Some(target), index: 0 };
+
+    Ok(Value::Object(vm.alloc(Object::with_kind(prototype, kind))))
+}
+
+fn number_constructor(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let n = match args.first() {
+        Some(value) => match vm.to_numeric(value.clone())? {
+            Value::BigInt(n) => num::ToPrimitive::to_f64(unsafe { n.as_ref() }).unwrap_or(f64::NAN),
+            value => value.as_f64().unwrap_or(f64::NAN),
+        },
+        None => 0.0,
+    };
+
+    match this {
+        Value::Hole => vm.to_object(Value::F64(n)).map(Value::Object),
+        _ => Ok(Value::number(n)),
+    }
+}
+
+fn this_number(this: &Value) -> Result<f64, NativeError> {
+    match *this {
+        Value::I64(n) => Ok(n as f64),
+        Value::F64(n) => Ok(n),
+        Value::Object(object) => match unsafe { object.as_ref() }.kind {
+            ObjectKind::Number(n) => Ok(n),
+            _ => Err(NativeError::type_error("Number.prototype.valueOf requires that 'this' be a Number")),
+        },
+        _ => Err(NativeError::type_error("Number.prototype.valueOf requires that 'this' be a Number")),
+    }
+}
+
+fn number_prototype_to_string(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let n = this_number(&this)?;
+    match arg(args, 0) {
+        Value::Undefined | Value::I64(10) => vm.to_string_value(Value::number(n)),
+        _ => Err(NativeError::internal_error("Number.prototype.toString only supports radix 10 yet")),
+    }
+}
+
+fn number_prototype_value_of(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    this_number(&this).map(Value::number)
+}
+
+fn boolean_constructor(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let b = arg(args, 0).to_boolean();
+    match this {
+        Value::Hole => vm.to_object(Value::Boolean(b)).map(Value::Object),
+        _ => Ok(Value::Boolean(b)),
+    }
+}
+
+fn this_boolean(this: &Value) -> Result<bool, NativeError> {
+    match *this {
+        Value::Boolean(b) => Ok(b),
+        Value::Object(object) => match unsafe { object.as_ref() }.kind {
+            ObjectKind::Boolean(b) => Ok(b),
+            _ => Err(NativeError::type_error("Boolean.prototype.valueOf requires that 'this' be a Boolean")),
+        },
+        _ => Err(NativeError::type_error("Boolean.prototype.valueOf requires that 'this' be a Boolean")),
+    }
+}
+
+fn boolean_prototype_to_string(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let b = this_boolean(&this)?;
+    Ok(vm.new_string(if b { "true" } else { "false" }))
+}
+
+fn boolean_prototype_value_of(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    this_boolean(&this).map(Value::Boolean)
+}
+
+fn symbol_constructor(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    if this.is_hole() {
+        return Err(NativeError::type_error("Symbol is not a constructor"));
+    }
+    let description = match arg(args, 0) {
+        Value::Undefined => None,
+        description => Some(vm.to_string(description)?),
+    };
+
+    Ok(Value::Symbol(vm.new_symbol(description)))
+}
+
+fn symbol_prototype_to_string(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let symbol = match this {
+        Value::Symbol(symbol) => symbol,
+        Value::Object(object) => match unsafe { object.as_ref() }.kind {
+            ObjectKind::Symbol(symbol) => symbol,
+            _ => return Err(NativeError::type_error("Symbol.prototype.toString requires that 'this' be a Symbol")),
+        },
+        _ => return Err(NativeError::type_error("Symbol.prototype.toString requires that 'this' be a Symbol")),
+    };
+    let description = vm.symbol_description(symbol).unwrap_or("").to_string();
+
+    Ok(vm.new_string(format!("Symbol({})", description)))
+}
+
+
+// Errors
+macro_rules! error_constructors {
+    ( $( $name:ident => $prototype:ident, )* ) => {
+        $(
+            fn $name(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+                let message = match arg(args, 0) {
+                    Value::Undefined => String::new(),
+                    message => vm.to_string(message)?,
+                };
+                let prototype = vm.intrinsics.$prototype;
+                Ok(vm.new_error_object(prototype, message))
+            }
+        )*
+    }
+}
+
+error_constructors! {
+    error_constructor => error_prototype,
+    syntax_error_constructor => syntax_error_prototype,
+    eval_error_constructor => eval_error_prototype,
+    range_error_constructor => range_error_prototype,
+    reference_error_constructor => reference_error_prototype,
+    type_error_constructor => type_error_prototype,
+    uri_error_constructor => uri_error_prototype,
+    internal_error_constructor => internal_error_prototype,
+}
+
+fn error_prototype_to_string(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let name = match vm.get_value(&this, &"name".into())? {
+        Value::Undefined => "Error".to_string(),
+        name => vm.to_string(name)?,
+    };
+    let message = match vm.get_value(&this, &"message".into())? {
+        Value::Undefined => String::new(),
+        message => vm.to_string(message)?,
+    };
+
+    Ok(vm.new_string(match (name.is_empty(), message.is_empty()) {
+        (_, true) => name,
+        (true, false) => message,
+        (false, false) => format!("{}: {}", name, message),
+    }))
+}
+
+
+// Iterators and generators
+fn iterator_prototype_iterator(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    Ok(this)
+}
+
+fn array_iterator_prototype_next(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    let (value, done) = vm.iterator_step(&this)?;
+    Ok(vm.iterator_result(value, done))
+}
+
+fn generator_prototype_next(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    vm.resume_generator(this, Resume::Next, arg(args, 0))
+}
+
+fn generator_prototype_return(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    vm.resume_generator(this, Resume::Return, arg(args, 0))
+}
+
+fn generator_prototype_throw(vm: &mut Vm, this: Value, args: &[Value]) -> Result<Value, NativeError> {
+    vm.resume_generator(this, Resume::Throw, arg(args, 0))
+}
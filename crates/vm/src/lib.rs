@@ -4,6 +4,10 @@
     non_snake_case, unreachable_code, dead_code, unused_mut,
     unused_macros,
 )]
+// NOTE: `PackedValue` 只有在启用 nan-boxing 时才是 Copy 的，其它情况下需要 clone ；
+//       未启用时 `PackedValue` 就是 `Value` ，打包和解包都是相同类型之间的转换。
+#![cfg_attr(feature = "nan-boxing", allow(clippy::clone_on_copy))]
+#![cfg_attr(not(feature = "nan-boxing"), allow(clippy::useless_conversion))]
 
 extern crate num;
 // TODO: 每日构建版标准库里面的 HashMap 已经是 FxHashMap 了，所以这个只在 std 版本启用
@@ -14,6 +18,15 @@ pub mod error;
 pub mod env;
 pub mod symbol;
 pub mod function;
+pub mod shape;
 pub mod object;
 pub mod value;
+#[cfg(feature = "nan-boxing")]
+pub mod nanbox;
+pub mod frame;
+pub mod operations;
+pub mod intrinsics;
+pub mod interpreter;
+pub mod inline_cache;
+pub mod gc;
 pub mod vm;
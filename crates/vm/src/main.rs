@@ -8,7 +8,7 @@
 
 use std::ptr::{ NonNull, };
 use std::io::{ self, Write, Read, };
-use std::alloc::{ alloc, dealloc, Global, System, GlobalAlloc, Layout, Alloc, };
+use std::alloc::{ alloc, dealloc, Global, System, GlobalAlloc, Layout, };
 use std::rc::{ Rc, };
 use std::cell::{ Cell, Ref, RefMut, RefCell, };
 
@@ -0,0 +1,481 @@
+// NaN-boxed values
+//
+//      bits 63..48     payload
+//      < 0xFFF9        a double, every NaN is stored as 0x7FF8_0000_0000_0000
+//      0xFFF9          Undefined = 0, Null = 1, false = 2, true = 3, Hole = 4
+//      0xFFFA          I64, 48 bit two's complement
+//      0xFFFB          Symbol, bit 47 is the public flag
+//      0xFFFC          String pointer
+//      0xFFFD          BigInt pointer
+//      0xFFFE          Function pointer
+//      0xFFFF          Object pointer
+//
+// NOTE: 所有的值都是 8 个字节（`value::Value` 是 16 个字节），代价是读取时需要解码。
+//       NaN 在装箱时规范化，所以高 16 位大于等于 0xFFF9 的位模式不会是一个 double。
+//       指针只使用低 48 位（x86_64 与 aarch64 的用户空间地址），
+//       超出 48 位范围的整数保存为 F64，它们的 `kind()` 是 `ValueKind::F64`，数值不变。
+
+use crate::value::{ self, ValueKind, Cast, BigInt, };
+use crate::error::NativeError;
+use crate::symbol::Symbol;
+use crate::function::Function;
+use crate::object::Object;
+
+use std::fmt;
+use std::ptr::NonNull;
+
+
+#[cfg(not(target_pointer_width = "64"))]
+compile_error!("the `nan-boxing` feature requires 64 bit pointers");
+
+const TAG_SHIFT: u32 = 48;
+const PAYLOAD_MASK: u64 = (1 << TAG_SHIFT) - 1;
+const CANONICAL_NAN: u64 = 0x7FF8_0000_0000_0000;
+
+const TAG_SPECIAL: u64 = 0xFFF9;
+const TAG_INT: u64 = 0xFFFA;
+const TAG_SYMBOL: u64 = 0xFFFB;
+const TAG_STRING: u64 = 0xFFFC;
+const TAG_BIGINT: u64 = 0xFFFD;
+const TAG_FUNCTION: u64 = 0xFFFE;
+const TAG_OBJECT: u64 = 0xFFFF;
+
+const SYMBOL_PUBLIC: u64 = 1 << 47;
+
+/// The integers in this range are boxed as `I64`.
+pub const MIN_INT: i64 = -(1 << 47);
+pub const MAX_INT: i64 = (1 << 47) - 1;
+
+
+/// An 8 byte `value::Value`.
+#[derive(Clone, Copy)]
+pub struct Value(u64);
+
+impl Value {
+    pub const UNDEFINED: Value = Value(TAG_SPECIAL << TAG_SHIFT);
+    pub const NULL: Value = Value(TAG_SPECIAL << TAG_SHIFT | 1);
+    pub const FALSE: Value = Value(TAG_SPECIAL << TAG_SHIFT | 2);
+    pub const TRUE: Value = Value(TAG_SPECIAL << TAG_SHIFT | 3);
+    pub const HOLE: Value = Value(TAG_SPECIAL << TAG_SHIFT | 4);
+
+    #[inline]
+    const fn boxed(tag: u64, payload: u64) -> Self {
+        Value(tag << TAG_SHIFT | payload)
+    }
+
+    #[inline]
+    fn tag(self) -> u64 {
+        self.0 >> TAG_SHIFT
+    }
+
+    #[inline]
+    fn payload(self) -> u64 {
+        self.0 & PAYLOAD_MASK
+    }
+
+    #[inline]
+    fn pointer<T>(tag: u64, ptr: NonNull<T>) -> Self {
+        let address = ptr.as_ptr() as usize as u64;
+        assert_eq!(address & !PAYLOAD_MASK, 0, "pointer {:p} does not fit in 48 bits", ptr);
+        Value::boxed(tag, address)
+    }
+
+    #[inline]
+    fn as_pointer<T>(self, tag: u64) -> Option<NonNull<T>> {
+        if self.tag() == tag {
+            Some(unsafe { NonNull::new_unchecked(self.payload() as usize as *mut T) })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn boolean(b: bool) -> Self {
+        if b { Value::TRUE } else { Value::FALSE }
+    }
+
+    /// An `I64` value, integers out of the 48 bit range are boxed as `F64`.
+    #[inline]
+    pub fn i64(n: i64) -> Self {
+        if (MIN_INT..=MAX_INT).contains(&n) {
+            Value::boxed(TAG_INT, n as u64 & PAYLOAD_MASK)
+        } else {
+            Value::f64(n as f64)
+        }
+    }
+
+    /// An `F64` value.
+    #[inline]
+    pub fn f64(n: f64) -> Self {
+        if n.is_nan() {
+            Value(CANONICAL_NAN)
+        } else {
+            Value(n.to_bits())
+        }
+    }
+
+    /// A Number value, integral values are kept as `I64`, see `value::Value::number`.
+    #[inline]
+    pub fn number(n: f64) -> Self {
+        if n.fract() == 0.0 && MIN_INT as f64 <= n && n <= MAX_INT as f64 && !(n == 0.0 && n.is_sign_negative()) {
+            Value::i64(n as i64)
+        } else {
+            Value::f64(n)
+        }
+    }
+
+    #[inline]
+    pub fn symbol(symbol: Symbol) -> Self {
+        let id = symbol.id() as u64;
+        assert!(id < SYMBOL_PUBLIC, "Symbol id too large to be boxed (actual: {})", id);
+        Value::boxed(TAG_SYMBOL, if symbol.is_public() { SYMBOL_PUBLIC | id } else { id })
+    }
+
+    #[inline]
+    pub fn string(ptr: NonNull<String>) -> Self {
+        Value::pointer(TAG_STRING, ptr)
+    }
+
+    #[inline]
+    pub fn bigint(ptr: NonNull<BigInt>) -> Self {
+        Value::pointer(TAG_BIGINT, ptr)
+    }
+
+    #[inline]
+    pub fn function(ptr: NonNull<Function>) -> Self {
+        Value::pointer(TAG_FUNCTION, ptr)
+    }
+
+    #[inline]
+    pub fn object(ptr: NonNull<Object>) -> Self {
+        Value::pointer(TAG_OBJECT, ptr)
+    }
+
+    /// The encoded bits.
+    #[inline]
+    pub fn to_bits(self) -> u64 {
+        self.0
+    }
+
+    #[inline]
+    pub fn kind(&self) -> ValueKind {
+        match self.tag() {
+            TAG_SPECIAL => match self.payload() {
+                0 => ValueKind::Undefined,
+                1 => ValueKind::Null,
+                2 | 3 => ValueKind::Boolean,
+                _ => ValueKind::Hole,
+            },
+            TAG_INT => ValueKind::I64,
+            TAG_SYMBOL => ValueKind::Symbol,
+            TAG_STRING => ValueKind::String,
+            TAG_BIGINT => ValueKind::BigInt,
+            TAG_FUNCTION => ValueKind::Function,
+            TAG_OBJECT => ValueKind::Object,
+            _ => ValueKind::F64,
+        }
+    }
+
+    #[inline]
+    pub fn is_undefined(&self) -> bool {
+        self.0 == Value::UNDEFINED.0
+    }
+
+    #[inline]
+    pub fn is_nullish(&self) -> bool {
+        self.0 == Value::UNDEFINED.0 || self.0 == Value::NULL.0
+    }
+
+    #[inline]
+    pub fn is_hole(&self) -> bool {
+        self.0 == Value::HOLE.0
+    }
+
+    #[inline]
+    fn is_f64(&self) -> bool {
+        self.tag() < TAG_SPECIAL
+    }
+
+    #[inline]
+    pub fn is_number(&self) -> bool {
+        self.is_f64() || self.tag() == TAG_INT
+    }
+
+    #[inline]
+    pub fn is_object(&self) -> bool {
+        self.tag() >= TAG_FUNCTION
+    }
+
+    #[inline]
+    pub fn as_i64(&self) -> Option<i64> {
+        if self.tag() == TAG_INT {
+            // NOTE: 符号扩展
+            Some(((self.payload() << 16) as i64) >> 16)
+        } else {
+            None
+        }
+    }
+
+    /// The value of Number values, `None` for the others.
+    #[inline]
+    pub fn as_f64(&self) -> Option<f64> {
+        if self.is_f64() {
+            Some(f64::from_bits(self.0))
+        } else {
+            self.as_i64().map(|n| n as f64)
+        }
+    }
+
+    #[inline]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.0 {
+            bits if bits == Value::TRUE.0 => Some(true),
+            bits if bits == Value::FALSE.0 => Some(false),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_symbol(&self) -> Option<Symbol> {
+        if self.tag() == TAG_SYMBOL {
+            let payload = self.payload();
+            Some(Symbol::new(payload & SYMBOL_PUBLIC != 0, (payload & !SYMBOL_PUBLIC) as usize))
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn as_string(&self) -> Option<NonNull<String>> {
+        self.as_pointer(TAG_STRING)
+    }
+
+    #[inline]
+    pub fn as_bigint(&self) -> Option<NonNull<BigInt>> {
+        self.as_pointer(TAG_BIGINT)
+    }
+
+    #[inline]
+    pub fn as_function(&self) -> Option<NonNull<Function>> {
+        self.as_pointer(TAG_FUNCTION)
+    }
+
+    /// The object of Object values, a function is an object too.
+    #[inline]
+    pub fn as_object(&self) -> Option<NonNull<Object>> {
+        match self.as_function() {
+            Some(ptr) => Some(unsafe { ptr.as_ref() }.object_ptr()),
+            None => self.as_pointer(TAG_OBJECT),
+        }
+    }
+
+    #[inline]
+    pub fn type_of(&self) -> &'static str {
+        match self.kind() {
+            ValueKind::Undefined | ValueKind::Hole => "undefined",
+            ValueKind::Null | ValueKind::Object => "object",
+            ValueKind::I64 | ValueKind::F64 => "number",
+            ValueKind::Symbol => "symbol",
+            ValueKind::Boolean => "boolean",
+            ValueKind::String => "string",
+            ValueKind::BigInt | ValueKind::Complex => "bigint",
+            ValueKind::Function => "function",
+        }
+    }
+
+    #[inline]
+    pub fn to_boolean(&self) -> bool {
+        match self.kind() {
+            ValueKind::Undefined | ValueKind::Null | ValueKind::Hole => false,
+            ValueKind::Boolean => self.0 == Value::TRUE.0,
+            ValueKind::I64 => self.payload() != 0,
+            ValueKind::F64 => {
+                let n = f64::from_bits(self.0);
+                !(n == 0.0 || n.is_nan())
+            },
+            _ => self.unpack().to_boolean(),
+        }
+    }
+
+    /// The `value::Value` of the same value.
+    pub fn unpack(self) -> value::Value {
+        match self.kind() {
+            ValueKind::Undefined => value::Value::Undefined,
+            ValueKind::Null => value::Value::Null,
+            ValueKind::Hole => value::Value::Hole,
+            ValueKind::Boolean => value::Value::Boolean(self.0 == Value::TRUE.0),
+            ValueKind::I64 => value::Value::I64(self.as_i64().unwrap()),
+            ValueKind::F64 => value::Value::F64(f64::from_bits(self.0)),
+            ValueKind::Symbol => value::Value::Symbol(self.as_symbol().unwrap()),
+            ValueKind::String => value::Value::String(self.as_pointer(TAG_STRING).unwrap()),
+            ValueKind::BigInt | ValueKind::Complex => value::Value::BigInt(self.as_pointer(TAG_BIGINT).unwrap()),
+            ValueKind::Function => value::Value::Function(self.as_pointer(TAG_FUNCTION).unwrap()),
+            ValueKind::Object => value::Value::Object(self.as_pointer(TAG_OBJECT).unwrap()),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    // NOTE: 与 `value::Value` 相同，NaN 不等于它自己，0 等于 -0
+    fn eq(&self, other: &Self) -> bool {
+        if self.is_f64() && other.is_f64() {
+            f64::from_bits(self.0) == f64::from_bits(other.0)
+        } else {
+            self.0 == other.0
+        }
+    }
+}
+
+impl From<value::Value> for Value {
+    fn from(value: value::Value) -> Self {
+        match value {
+            value::Value::Undefined => Value::UNDEFINED,
+            value::Value::Null => Value::NULL,
+            value::Value::Hole => Value::HOLE,
+            value::Value::Boolean(b) => Value::boolean(b),
+            value::Value::I64(n) => Value::i64(n),
+            value::Value::F64(n) => Value::f64(n),
+            value::Value::Symbol(symbol) => Value::symbol(symbol),
+            value::Value::String(ptr) => Value::string(ptr),
+            value::Value::BigInt(ptr) => Value::bigint(ptr),
+            value::Value::Function(ptr) => Value::function(ptr),
+            value::Value::Object(ptr) => Value::object(ptr),
+        }
+    }
+}
+
+impl From<Value> for value::Value {
+    fn from(value: Value) -> Self {
+        value.unpack()
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::boolean(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::i64(n)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::number(n)
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.unpack(), f)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.unpack(), f)
+    }
+}
+
+
+impl Cast<i64> for Value {
+    fn cast(self) -> Result<i64, NativeError> {
+        match (self.as_i64(), self.as_f64()) {
+            (Some(n), _) => Ok(n),
+            (None, Some(n)) if n.fract() == 0.0 && n.abs() <= 9007199254740992.0 => Ok(n as i64),
+            _ => Err(NativeError::type_error(format!("{} is not an integer", self))),
+        }
+    }
+
+    fn bitcast(self) -> i64 {
+        match (self.as_i64(), self.as_f64()) {
+            (Some(n), _) => n,
+            (None, Some(n)) => n as i64,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Cast<f64> for Value {
+    fn cast(self) -> Result<f64, NativeError> {
+        self.as_f64().ok_or_else(|| NativeError::type_error(format!("{} is not a number", self)))
+    }
+
+    fn bitcast(self) -> f64 {
+        self.as_f64().unwrap()
+    }
+}
+
+impl Cast<bool> for Value {
+    fn cast(self) -> Result<bool, NativeError> {
+        self.as_bool().ok_or_else(|| NativeError::type_error(format!("{} is not a boolean", self)))
+    }
+
+    fn bitcast(self) -> bool {
+        self.as_bool().unwrap()
+    }
+}
+
+
+#[test]
+fn test_nan_boxing() {
+    use crate::vm::Vm;
+
+    assert_eq!(std::mem::size_of::<Value>(), 8);
+    assert!(std::mem::size_of::<value::Value>() > 8);
+
+    let mut vm = Vm::new();
+    let string = vm.new_string("s");
+    let bigint = vm.new_bigint(BigInt::from(1));
+    let object = Value::from(value::Value::Object(vm.new_object()));
+    let function = vm.new_native_function("f", 0, |_, _, _| Ok(value::Value::Undefined));
+    let symbol = vm.new_symbol(None);
+    let values = vec![
+        value::Value::Undefined, value::Value::Null, value::Value::Hole,
+        value::Value::Boolean(true), value::Value::Boolean(false),
+        value::Value::I64(0), value::Value::I64(-1), value::Value::I64(MIN_INT), value::Value::I64(MAX_INT),
+        value::Value::F64(0.5), value::Value::F64(-0.0), value::Value::F64(f64::INFINITY),
+        value::Value::F64(f64::NEG_INFINITY), value::Value::F64(f64::MIN_POSITIVE),
+        value::Value::Symbol(symbol), value::Value::Symbol(Symbol::new(true, 7)),
+        string, bigint, object.unpack(), function.clone(),
+    ];
+    for value in values {
+        let boxed = Value::from(value.clone());
+        assert_eq!(boxed.unpack(), value);
+        assert_eq!(boxed.kind(), value.kind());
+        assert_eq!(boxed.type_of(), value.type_of());
+        assert_eq!(boxed.to_boolean(), value.to_boolean());
+        assert_eq!(boxed.as_f64(), value.as_f64());
+        assert_eq!(boxed.as_object(), value.as_object());
+        assert_eq!(boxed.is_object(), value.is_object());
+        assert_eq!(boxed.is_nullish(), value.is_nullish());
+        assert_eq!(format!("{}", boxed), format!("{}", value));
+    }
+
+    // NaN 被规范化，不会与其它的标签冲突
+    let nan = Value::f64(f64::from_bits(0xFFFF_0000_0000_0001));
+    assert_eq!(nan.kind(), ValueKind::F64);
+    assert_eq!(nan.to_bits(), CANONICAL_NAN);
+    assert!(nan != nan && !nan.to_boolean());
+    assert!(Value::f64(-0.0) == Value::f64(0.0));
+    assert!(Value::f64(-0.0).as_f64().unwrap().is_sign_negative());
+    assert!(Value::i64(1) != Value::f64(1.0));
+
+    // 超出 48 位的整数保存为 F64
+    let n = 1i64 << 50;
+    assert_eq!(Value::i64(n).kind(), ValueKind::F64);
+    assert_eq!(Value::number(n as f64).as_f64(), Some(n as f64));
+    assert_eq!(Value::number(-3.0).kind(), ValueKind::I64);
+    assert_eq!(Value::number(-0.0).kind(), ValueKind::F64);
+
+    assert_eq!(Cast::<i64>::cast(Value::i64(-5)).ok(), Some(-5));
+    assert_eq!(Cast::<i64>::cast(Value::i64(n)).ok(), Some(n));
+    assert!(Cast::<i64>::cast(Value::f64(0.5)).is_err());
+    assert_eq!(Cast::<f64>::cast(Value::i64(2)).ok(), Some(2.0));
+    assert_eq!(Cast::<bool>::cast(Value::TRUE).ok(), Some(true));
+    assert!(Cast::<bool>::cast(Value::NULL).is_err());
+    assert_eq!(Cast::<f64>::bitcast(Value::f64(1.5)), 1.5);
+}
@@ -1,13 +1,14 @@
 use std::fmt;
 use std::ptr::NonNull;
-use rustc_hash::FxHashMap;
 
 
 use crate::error::NativeError;
-use crate::value::{ Value, ValueKind, };
+use crate::value::{ Value, ValueKind, PackedValue, };
 use crate::symbol::Symbol;
 use crate::function::{ Function, FunctionCode, NativeFunction, };
 use crate::vm::{ Vm, };
+use crate::frame::Generator;
+use crate::shape::Properties;
 
 
 
@@ -27,27 +28,103 @@ pub struct Property {
     pub configurable: bool,
     
     // DataPropertyDescriptor
-    pub value: Value, // NOTE: Any ECMAScript Value
+    pub value: PackedValue, // NOTE: Any ECMAScript Value
     pub writable: bool,
 
     // AccessorPropertyDescriptor
-    pub getter: Value,  // WARN: Value::Undefined || Value::Object<+ Callable>
-    pub setter: Value,  // WARN: Value::Undefined || Value::Object<+ Callable>
+    pub getter: PackedValue,  // WARN: Value::Undefined || Value::Object<+ Callable>
+    pub setter: PackedValue,  // WARN: Value::Undefined || Value::Object<+ Callable>
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+impl Property {
+    /// A writable, enumerable and configurable data property.
+    #[inline]
+    pub fn data<V: Into<Value>>(value: V) -> Self {
+        Self {
+            enumerable: true,
+            configurable: true,
+            value: PackedValue::from(value.into()),
+            writable: true,
+            getter: PackedValue::from(Value::Undefined),
+            setter: PackedValue::from(Value::Undefined),
+        }
+    }
+
+    /// A writable and configurable data property that is not enumerable, as the properties of builtin objects.
+    #[inline]
+    pub fn hidden<V: Into<Value>>(value: V) -> Self {
+        Self { enumerable: false, ..Self::data(value) }
+    }
+
+    /// A data property that can not be changed.
+    #[inline]
+    pub fn readonly<V: Into<Value>>(value: V) -> Self {
+        Self { enumerable: false, configurable: false, writable: false, ..Self::data(value) }
+    }
+
+    #[inline]
+    pub fn accessor(getter: Value, setter: Value, enumerable: bool) -> Self {
+        Self {
+            enumerable,
+            configurable: true,
+            value: PackedValue::from(Value::Undefined),
+            writable: false,
+            getter: PackedValue::from(getter),
+            setter: PackedValue::from(setter),
+        }
+    }
+
+    #[inline]
+    pub fn is_accessor(&self) -> bool {
+        !self.getter.is_undefined() || !self.setter.is_undefined()
+    }
+
+    /// The value of a data property, unpacked.
+    #[inline]
+    pub fn value(&self) -> Value {
+        Value::from(self.value.clone())
+    }
+
+    #[inline]
+    pub fn getter(&self) -> Value {
+        Value::from(self.getter.clone())
+    }
+
+    #[inline]
+    pub fn setter(&self) -> Value {
+        Value::from(self.setter.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum ObjectKind {
-    Function,
-    ArrowFunction,
-    RegExp,
-    Map,
-    Set,
+    Ordinary,
+    /// The object of a function, see `Function::object`.
+    Function(NonNull<Function>),
+    /// The `length` property is kept in sync with the indices.
+    Array,
+    Arguments,
+    Error,
+    // Wrappers of primitive values
+    Boolean(bool),
+    Number(f64),
+    String(NonNull<String>),
+    Symbol(Symbol),
+    RegExp { pattern: String, flags: String },
+    /// The iterator of arrays ( and array-likes ), `target` is `undefined` when it is done.
+    ArrayIterator { target: Value, index: usize },
+    StringIterator { target: Option<NonNull<String>>, index: usize },
+    /// The enumerable keys of `for-in`.
+    ForInIterator { object: Option<NonNull<Object>>, keys: Vec<PropertyKey>, index: usize },
+    Generator(Box<Generator>),
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 pub struct Object {
-    pub properties: FxHashMap<PropertyKey, Property>,
-    // pub prototype: NonNull<Object>,
+    // NOTE: 保留属性的插入顺序，枚举时整数索引按大小排在前面，Symbol 排在最后
+    pub properties: Properties,
+    pub prototype: Option<NonNull<Object>>,
+    pub kind: ObjectKind,
     pub is_frozen: bool,
     pub is_sealed: bool,
     pub is_extensible: bool,
@@ -57,10 +134,12 @@ impl Object {
     #[inline]
     pub fn empty() -> Self {
         Self {
-            properties: FxHashMap::default(),
+            properties: Properties::new(),
+            prototype: None,
+            kind: ObjectKind::Ordinary,
             is_frozen: false,
             is_sealed: false,
-            is_extensible: false,
+            is_extensible: true,
         }
     }
 
@@ -70,16 +149,24 @@ impl Object {
     }
 
     #[inline]
-    pub fn with_prototype(prototype: &Object) -> Self {
+    pub fn with_prototype<P: Into<Option<NonNull<Object>>>>(prototype: P) -> Self {
         let mut object = Self::empty();
-        
-        for (key, value) in &prototype.properties {
-            object.insert(key, value.clone());
-        }
+        object.prototype = prototype.into();
+        object
+    }
 
+    #[inline]
+    pub fn with_kind<P: Into<Option<NonNull<Object>>>>(prototype: P, kind: ObjectKind) -> Self {
+        let mut object = Self::with_prototype(prototype);
+        object.kind = kind;
         object
     }
 
+    #[inline]
+    pub fn is_array(&self) -> bool {
+        matches!(self.kind, ObjectKind::Array)
+    }
+
     #[inline]
     pub fn as_ptr(&self) -> *const Object {
         self as *const Object
@@ -112,26 +199,26 @@ impl Object {
         let constructor = Property {
             enumerable: false,
             configurable: true,
-            value: Value::Null,
+            value: PackedValue::from(Value::Null),
             writable: true,
-            getter: Value::Undefined,
-            setter: Value::Undefined,
+            getter: PackedValue::from(Value::Undefined),
+            setter: PackedValue::from(Value::Undefined),
         };
         let toString = Property {
             enumerable: false,
             configurable: true,
-            value: Value::Null,
+            value: PackedValue::from(Value::Null),
             writable: true,
-            getter: Value::Undefined,
-            setter: Value::Undefined,
+            getter: PackedValue::from(Value::Undefined),
+            setter: PackedValue::from(Value::Undefined),
         };
         let toSource = Property {
             enumerable: false,
             configurable: true,
-            value: Value::Null,
+            value: PackedValue::from(Value::Null),
             writable: true,
-            getter: Value::Undefined,
-            setter: Value::Undefined,
+            getter: PackedValue::from(Value::Undefined),
+            setter: PackedValue::from(Value::Undefined),
         };
 
         obj.insert("constructor", constructor);
@@ -166,14 +253,29 @@ impl Object {
         self.properties.is_empty()
     }
     
+    /// Own property keys, integer indices ascending, then strings and symbols in insertion order.
     #[inline]
     pub fn keys(&self) -> Vec<&PropertyKey> {
-        self.properties.keys().collect::<Vec<&PropertyKey>>()
+        let mut indices = self.properties.keys()
+            .filter_map(|key| key.as_index().map(|index| (index, key)))
+            .collect::<Vec<(u32, &PropertyKey)>>();
+        indices.sort_by_key(|&(index, _)| index);
+
+        let strings = self.properties.keys().filter(|key| match key {
+            PropertyKey::String(_) => key.as_index().is_none(),
+            PropertyKey::Symbol(_) => false,
+        });
+        let symbols = self.properties.keys().filter(|key| match key {
+            PropertyKey::String(_) => false,
+            PropertyKey::Symbol(_) => true,
+        });
+
+        indices.into_iter().map(|(_, key)| key).chain(strings).chain(symbols).collect()
     }
 
     #[inline]
     pub fn values(&self) -> Vec<&Property> {
-        self.properties.values().collect::<Vec<&Property>>()
+        self.keys().into_iter().map(|key| &self.properties[key]).collect::<Vec<&Property>>()
     }
 
     #[inline]
@@ -182,129 +284,55 @@ impl Object {
     }
 
     #[inline]
-    pub fn insert<K: Into<PropertyKey>, >(&mut self, k: K, v: Property) {
-        self.properties.insert(k.into(), v);
+    pub fn get_mut<K: Into<PropertyKey>>(&mut self, k: K) -> Option<&mut Property> {
+        self.properties.get_mut(&k.into())
     }
 
     #[inline]
-    pub fn remove<K: Into<PropertyKey>>(&mut self, k: K) {
-        unimplemented!()
+    pub fn contains_key<K: Into<PropertyKey>>(&self, k: K) -> bool {
+        self.properties.contains_key(&k.into())
     }
 
-
     #[inline]
-    pub fn global<T: Into<String>>(name: T) -> Self {
-        let mut object_prototype = Object::empty();
-        let mut function_prototype = Object::empty();
-
-        let mut global = Object::empty();
-
-        // function Object() { }
-        let mut object_function = Function::new(
-            "Object",
-            FunctionCode::NativeCode(|vm: NonNull<Vm>| -> Result<Value, NativeError> {
-                unimplemented!()
-            }),
-            &mut function_prototype,
-        );
-        let mut object_function_value = Value::Function(NonNull::new(&mut object_function).unwrap());
-        
-        // function Function() { }
-        let mut function_function = Function::new(
-            "Function",
-            FunctionCode::NativeCode(|vm: NonNull<Vm>| -> Result<Value, NativeError> {
-                unimplemented!()
-            }),
-            &mut function_prototype,
-        );
-        let mut function_function_value = Value::Function(NonNull::new(&mut function_function).unwrap());
-
-        global.insert("Object", Property {
-            enumerable: false,
-            configurable: true,
-            value: object_function_value.clone(),
-            writable: true,
-            getter: Value::Undefined,
-            setter: Value::Undefined,
-        });
-        global.insert("Function", Property {
-            enumerable: false,
-            configurable: true,
-            value: function_function_value.clone(),
-            writable: true,
-            getter: Value::Undefined,
-            setter: Value::Undefined,
-        });
-        
-        // Update Object Prototype and Function Prototype
-        function_prototype.insert("constructor", Property {
-            enumerable: false,
-            configurable: true,
-            value: function_function_value.clone(),
-            writable: true,
-            getter: Value::Undefined,
-            setter: Value::Undefined,
-        });
-
-        object_prototype.insert("constructor", Property {
-            enumerable: false,
-            configurable: true,
-            value: object_function_value.clone(),
-            writable: true,
-            getter: Value::Undefined,
-            setter: Value::Undefined,
-        });
-        
-        let mut object_prototype_toString = Function::new(
-            "toString",
-            FunctionCode::NativeCode(|vm: NonNull<Vm>| -> Result<Value, NativeError> {
-                unimplemented!()
-            }),
-            &mut function_prototype,
-        );
-        let mut object_prototype_toString_value = Value::Function(NonNull::new(&mut object_prototype_toString).unwrap());
-        object_prototype.insert("toString", Property {
-            enumerable: false,
-            configurable: true,
-            value: object_prototype_toString_value,
-            writable: true,
-            getter: Value::Undefined,
-            setter: Value::Undefined,
-        });
+    pub fn insert<K: Into<PropertyKey>, >(&mut self, k: K, v: Property) {
+        self.properties.insert(k.into(), v);
+    }
 
-        let mut object_prototype_toSource = Function::new(
-            "toSource",
-            FunctionCode::NativeCode(|vm: NonNull<Vm>| -> Result<Value, NativeError> {
-                unimplemented!()
-            }),
-            &mut function_prototype,
-        );
-        let mut object_prototype_toSource_value = Value::Function(NonNull::new(&mut object_prototype_toSource).unwrap());
-        object_prototype.insert("toSource", Property {
-            enumerable: false,
-            configurable: true,
-            value: object_prototype_toSource_value,
-            writable: true,
-            getter: Value::Undefined,
-            setter: Value::Undefined,
-        });
+    #[inline]
+    pub fn remove<K: Into<PropertyKey>>(&mut self, k: K) -> Option<Property> {
+        self.properties.remove(&k.into())
+    }
+}
 
-        // Update Global Object
-        let global_value = Value::Object(global.as_raw_non_null());
-        global.insert(name.into(), Property {
-            enumerable: true,
-            configurable: false,
-            value: global_value,
-            writable: false,
-            getter: Value::Undefined,
-            setter: Value::Undefined,
-        });
 
-        global
+impl PropertyKey {
+    /// The array index of canonical numeric strings ( `"0"`, `"1"` ... ).
+    #[inline]
+    pub fn as_index(&self) -> Option<u32> {
+        match *self {
+            PropertyKey::String(ref s) => {
+                if s.is_empty() || s.len() > 10 || (s.len() > 1 && s.starts_with('0')) {
+                    return None;
+                }
+                match s.parse::<u64>() {
+                    // NOTE: 2 ** 32 - 1 不是数组的索引
+                    Ok(n) if n < 4294967295 && s.bytes().all(|b| b.is_ascii_digit()) => Some(n as u32),
+                    _ => None,
+                }
+            },
+            PropertyKey::Symbol(_) => None,
+        }
     }
 }
 
-
+impl fmt::Display for PropertyKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PropertyKey::String(ref s) => write!(f, "{}", s),
+            PropertyKey::Symbol(_) => write!(f, "Symbol()"),
+        }
+    }
+}
 
 impl From<&PropertyKey> for PropertyKey {
     fn from(s: &PropertyKey) -> Self {
@@ -323,6 +351,18 @@ impl From<String> for PropertyKey {
     }
 }
 
+impl From<u32> for PropertyKey {
+    fn from(index: u32) -> Self {
+        PropertyKey::String(index.to_string())
+    }
+}
+
+impl From<Symbol> for PropertyKey {
+    fn from(symbol: Symbol) -> Self {
+        PropertyKey::Symbol(symbol)
+    }
+}
+
 // impl PartialEq for Object {
 //     fn eq(&self, other: &Object) -> bool {
 //         false
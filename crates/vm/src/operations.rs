@@ -0,0 +1,901 @@
+// Abstract operations
+//
+//      ToPrimitive, ToNumber, ToString, ToPropertyKey, ToObject
+//      Get, Set, DeleteProperty, HasProperty
+//      Abstract Equality Comparison, Abstract Relational Comparison
+//
+// NOTE: https://www.ecma-international.org/ecma-262/9.0/index.html#sec-abstract-operations
+//       字符串在堆上保存为 Rust 的 `String`，长度与下标按照 UTF-16 计算，单独的代理项读取为 U+FFFD。
+
+use num::{ BigInt, Zero, One, Signed, ToPrimitive, FromPrimitive, };
+
+use crate::value::{ Value, PackedValue, };
+use crate::error::NativeError;
+use crate::object::{ Object, ObjectKind, Property, PropertyKey, };
+use crate::function::Function;
+use crate::vm::Vm;
+
+use ecmascript::compiler::bytecode::Instruction;
+
+use std::cmp::Ordering;
+use std::ptr::NonNull;
+
+
+/// The preferred type of `ToPrimitive`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Hint {
+    Default,
+    Number,
+    String,
+}
+
+// https://www.ecma-international.org/ecma-262/9.0/index.html#sec-touint32
+pub fn to_uint32(n: f64) -> u32 {
+    if !n.is_finite() {
+        return 0;
+    }
+    let n = n.trunc() % 4294967296.0;
+    let n = if n < 0.0 { n + 4294967296.0 } else { n };
+    n as u32
+}
+
+#[inline]
+pub fn to_int32(n: f64) -> i32 {
+    to_uint32(n) as i32
+}
+
+pub fn number_to_string(n: f64) -> String {
+    format!("{}", ecmascript::vm::value::Number(n))
+}
+
+/// StringToBigInt, only decimal digits are supported.
+fn string_to_bigint(s: &str) -> Option<BigInt> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Some(BigInt::zero());
+    }
+    BigInt::parse_bytes(s.as_bytes(), 10)
+}
+
+fn compare_bigint_number(x: &BigInt, y: f64) -> Option<Ordering> {
+    if y.is_nan() {
+        return None;
+    }
+    if y.is_infinite() {
+        return Some(if y > 0.0 { Ordering::Less } else { Ordering::Greater });
+    }
+    let floor = BigInt::from_f64(y.floor())?;
+    match x.cmp(&floor) {
+        Ordering::Equal if y.fract() != 0.0 => Some(Ordering::Less),
+        ordering => Some(ordering),
+    }
+}
+
+fn exponentiate(x: f64, y: f64) -> f64 {
+    if y.is_nan() || (x.abs() == 1.0 && y.is_infinite()) {
+        return f64::NAN;
+    }
+    x.powf(y)
+}
+
+fn number_operation(op: Instruction, x: f64, y: f64) -> Option<Value> {
+    let shift = to_uint32(y) & 31;
+    let n = match op {
+        Instruction::Add => x + y,
+        Instruction::Sub => x - y,
+        Instruction::Mul => x * y,
+        Instruction::Div => x / y,
+        Instruction::Mod => x % y,
+        Instruction::Exp => exponentiate(x, y),
+        Instruction::Shl => to_int32(x).wrapping_shl(shift) as f64,
+        Instruction::Shr => (to_int32(x) >> shift) as f64,
+        Instruction::UShr => (to_uint32(x) >> shift) as f64,
+        Instruction::BitAnd => (to_int32(x) & to_int32(y)) as f64,
+        Instruction::BitOr => (to_int32(x) | to_int32(y)) as f64,
+        Instruction::BitXor => (to_int32(x) ^ to_int32(y)) as f64,
+        _ => return None,
+    };
+
+    Some(Value::number(n))
+}
+
+fn bigint_operation(op: Instruction, x: &BigInt, y: &BigInt) -> Result<BigInt, NativeError> {
+    let shift = |x: &BigInt, y: &BigInt| -> Result<BigInt, NativeError> {
+        let n = y.abs().to_usize().ok_or_else(|| NativeError::range_error("Maximum BigInt size exceeded"))?;
+        Ok(if y.is_negative() { x >> n } else { x << n })
+    };
+
+    Ok(match op {
+        Instruction::Add => x + y,
+        Instruction::Sub => x - y,
+        Instruction::Mul => x * y,
+        Instruction::Div | Instruction::Mod if y.is_zero() => return Err(NativeError::range_error("Division by zero")),
+        Instruction::Div => x / y,
+        Instruction::Mod => x % y,
+        Instruction::Exp => {
+            if y.is_negative() {
+                return Err(NativeError::range_error("Exponent must be non-negative"));
+            }
+            let e = y.to_usize().ok_or_else(|| NativeError::range_error("Maximum BigInt size exceeded"))?;
+            num::pow(x.clone(), e)
+        },
+        Instruction::Shl => shift(x, y)?,
+        Instruction::Shr => shift(x, &-y)?,
+        Instruction::UShr => return Err(NativeError::type_error("BigInts have no unsigned right shift, use >> instead")),
+        Instruction::BitAnd => x & y,
+        Instruction::BitOr => x | y,
+        Instruction::BitXor => x ^ y,
+        _ => return Err(NativeError::internal_error(format!("{:?} is not a binary operator", op))),
+    })
+}
+
+// NOTE: 相等比较时按照类型分组，函数也是对象
+fn category(value: &Value) -> u8 {
+    match *value {
+        Value::Undefined | Value::Hole => 0,
+        Value::Null => 1,
+        Value::I64(_) | Value::F64(_) => 2,
+        Value::String(_) => 3,
+        Value::Boolean(_) => 4,
+        Value::Symbol(_) => 5,
+        Value::BigInt(_) => 6,
+        Value::Object(_) | Value::Function(_) => 7,
+    }
+}
+
+
+impl Vm {
+    /// The value of an object, the object of a function is the function itself.
+    pub fn object_value(&self, object: NonNull<Object>) -> Value {
+        match unsafe { object.as_ref() }.kind {
+            ObjectKind::Function(function) => Value::Function(function),
+            _ => Value::Object(object),
+        }
+    }
+
+    /// A short description of the value for error messages, without running any script.
+    pub fn display(&self, value: &Value) -> String {
+        match *value {
+            Value::String(ptr) => unsafe { ptr.as_ref() }.clone(),
+            Value::F64(n) => number_to_string(n),
+            Value::Symbol(symbol) => format!("Symbol({})", self.symbol_description(symbol).unwrap_or("")),
+            Value::Function(ptr) => match unsafe { ptr.as_ref() }.object().get("name").map(Property::value) {
+                Some(Value::String(name)) => format!("function {}", unsafe { name.as_ref() }),
+                _ => "function".to_string(),
+            },
+            Value::Object(ptr) => match unsafe { ptr.as_ref() }.kind {
+                ObjectKind::Array => "[object Array]".to_string(),
+                _ => "#<Object>".to_string(),
+            },
+            _ => format!("{}", value),
+        }
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-toprimitive
+    pub fn to_primitive(&mut self, value: Value, hint: Hint) -> Result<Value, NativeError> {
+        if !value.is_object() {
+            return Ok(value);
+        }
+
+        let names = match hint {
+            Hint::String => [ "toString", "valueOf" ],
+            Hint::Default | Hint::Number => [ "valueOf", "toString" ],
+        };
+        for name in names.iter() {
+            let method = self.get_value(&value, &(*name).into())?;
+            if let Value::Function(_) = method {
+                let result = self.call(method, value.clone(), &[])?;
+                if !result.is_object() {
+                    return Ok(result);
+                }
+            }
+        }
+
+        Err(NativeError::type_error("Cannot convert object to primitive value"))
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-tonumber
+    pub fn to_number(&mut self, value: Value) -> Result<f64, NativeError> {
+        match value {
+            Value::Undefined | Value::Hole => Ok(f64::NAN),
+            Value::Null => Ok(0.0),
+            Value::Boolean(b) => Ok(if b { 1.0 } else { 0.0 }),
+            Value::I64(n) => Ok(n as f64),
+            Value::F64(n) => Ok(n),
+            Value::String(ptr) => Ok(ecmascript::vm::value::Number::from(unsafe { ptr.as_ref() }.as_str()).0),
+            Value::Symbol(_) => Err(NativeError::type_error("Cannot convert a Symbol value to a number")),
+            Value::BigInt(_) => Err(NativeError::type_error("Cannot convert a BigInt value to a number")),
+            Value::Object(_) | Value::Function(_) => {
+                let value = self.to_primitive(value, Hint::Number)?;
+                self.to_number(value)
+            },
+        }
+    }
+
+    /// ToNumeric, returns a Number or a BigInt.
+    pub fn to_numeric(&mut self, value: Value) -> Result<Value, NativeError> {
+        let value = self.to_primitive(value, Hint::Number)?;
+        match value {
+            Value::I64(_) | Value::F64(_) | Value::BigInt(_) => Ok(value),
+            value => Ok(Value::number(self.to_number(value)?)),
+        }
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-tostring
+    pub fn to_string(&mut self, value: Value) -> Result<String, NativeError> {
+        match value {
+            Value::Undefined | Value::Hole => Ok("undefined".to_string()),
+            Value::Null => Ok("null".to_string()),
+            Value::Boolean(b) => Ok(b.to_string()),
+            Value::I64(n) => Ok(n.to_string()),
+            Value::F64(n) => Ok(number_to_string(n)),
+            Value::String(ptr) => Ok(unsafe { ptr.as_ref() }.clone()),
+            Value::Symbol(_) => Err(NativeError::type_error("Cannot convert a Symbol value to a string")),
+            Value::BigInt(ptr) => Ok(unsafe { ptr.as_ref() }.to_string()),
+            Value::Object(_) | Value::Function(_) => {
+                let value = self.to_primitive(value, Hint::String)?;
+                self.to_string(value)
+            },
+        }
+    }
+
+    /// ToString, strings are returned as they are.
+    pub fn to_string_value(&mut self, value: Value) -> Result<Value, NativeError> {
+        match value {
+            Value::String(_) => Ok(value),
+            value => {
+                let s = self.to_string(value)?;
+                Ok(self.new_string(s))
+            },
+        }
+    }
+
+    pub fn to_property_key(&mut self, value: Value) -> Result<PropertyKey, NativeError> {
+        match value {
+            Value::Symbol(symbol) => Ok(PropertyKey::Symbol(symbol)),
+            Value::String(ptr) => Ok(PropertyKey::String(unsafe { ptr.as_ref() }.clone())),
+            Value::Object(_) | Value::Function(_) => {
+                let value = self.to_primitive(value, Hint::String)?;
+                self.to_property_key(value)
+            },
+            value => Ok(PropertyKey::String(self.to_string(value)?)),
+        }
+    }
+
+    pub(crate) fn key_value(&mut self, key: PropertyKey) -> Value {
+        match key {
+            PropertyKey::String(s) => self.new_string(s),
+            PropertyKey::Symbol(symbol) => Value::Symbol(symbol),
+        }
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-toobject
+    pub fn to_object(&mut self, value: Value) -> Result<NonNull<Object>, NativeError> {
+        let intrinsics = &self.intrinsics;
+        let object = match value {
+            Value::Undefined | Value::Null | Value::Hole => {
+                return Err(NativeError::type_error("Cannot convert undefined or null to object"));
+            },
+            Value::Object(ptr) => return Ok(ptr),
+            Value::Function(ptr) => return Ok(unsafe { ptr.as_ref() }.object_ptr()),
+            Value::Boolean(b) => Object::with_kind(intrinsics.boolean_prototype, ObjectKind::Boolean(b)),
+            Value::I64(n) => Object::with_kind(intrinsics.number_prototype, ObjectKind::Number(n as f64)),
+            Value::F64(n) => Object::with_kind(intrinsics.number_prototype, ObjectKind::Number(n)),
+            Value::Symbol(symbol) => Object::with_kind(intrinsics.symbol_prototype, ObjectKind::Symbol(symbol)),
+            Value::BigInt(_) => Object::with_prototype(intrinsics.bigint_prototype),
+            Value::String(ptr) => {
+                let mut object = Object::with_kind(intrinsics.string_prototype, ObjectKind::String(ptr));
+                let length = unsafe { ptr.as_ref() }.encode_utf16().count();
+                object.insert("length", Property::readonly(length as i64));
+                object
+            },
+        };
+
+        Ok(self.alloc(object))
+    }
+
+    /// LengthOfArrayLike, clamped to the length of arrays.
+    pub fn length_of_array_like(&mut self, value: &Value) -> Result<u32, NativeError> {
+        let length = self.get_value(value, &"length".into())?;
+        let n = self.to_number(length)?;
+        Ok(if n.is_nan() || n <= 0.0 {
+            0
+        } else if n >= 4294967295.0 {
+            4294967295
+        } else {
+            n as u32
+        })
+    }
+
+    // Properties
+
+    /// Look up a property on the prototype chain, returns the object that has it.
+    pub fn find_property(&self, object: NonNull<Object>, key: &PropertyKey) -> Option<(NonNull<Object>, Property)> {
+        let mut current = Some(object);
+        while let Some(ptr) = current {
+            let object = unsafe { ptr.as_ref() };
+            if let Some(property) = object.properties.get(key) {
+                return Some((ptr, property.clone()));
+            }
+            current = object.prototype;
+        }
+
+        None
+    }
+
+    #[inline]
+    pub fn has_property(&self, object: NonNull<Object>, key: &PropertyKey) -> bool {
+        self.find_property(object, key).is_some()
+    }
+
+    /// The value of a data property on the prototype chain, accessors are ignored.
+    pub fn get_data(&self, object: NonNull<Object>, key: &PropertyKey) -> Option<Value> {
+        match self.find_property(object, key) {
+            Some((_, ref property)) if property.is_accessor() => None,
+            Some((_, property)) => Some(property.value()),
+            None => None,
+        }
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-ordinary-object-internal-methods-and-internal-slots-get-p-receiver
+    pub fn get(&mut self, object: NonNull<Object>, key: &PropertyKey, receiver: Value) -> Result<Value, NativeError> {
+        match self.find_property(object, key) {
+            Some((_, property)) => {
+                if !property.is_accessor() {
+                    return Ok(property.value());
+                }
+                match property.getter() {
+                    getter @ Value::Function(_) => self.call(getter, receiver, &[]),
+                    _ => Ok(Value::Undefined),
+                }
+            },
+            None => Ok(Value::Undefined),
+        }
+    }
+
+    /// GetV, properties of primitive values are looked up on their prototypes.
+    pub fn get_value(&mut self, value: &Value, key: &PropertyKey) -> Result<Value, NativeError> {
+        let intrinsics = &self.intrinsics;
+        let prototype = match *value {
+            Value::Object(ptr) => return self.get(ptr, key, value.clone()),
+            Value::Function(ptr) => return self.get(unsafe { ptr.as_ref() }.object_ptr(), key, value.clone()),
+            Value::String(ptr) => {
+                let s = unsafe { ptr.as_ref() };
+                if let PropertyKey::String(ref name) = *key {
+                    if name == "length" {
+                        return Ok(Value::I64(s.encode_utf16().count() as i64));
+                    }
+                    if let Some(index) = key.as_index() {
+                        return Ok(match s.encode_utf16().nth(index as usize) {
+                            Some(unit) => self.new_string(String::from_utf16_lossy(&[ unit ])),
+                            None => Value::Undefined,
+                        });
+                    }
+                }
+                intrinsics.string_prototype
+            },
+            Value::I64(_) | Value::F64(_) => intrinsics.number_prototype,
+            Value::Boolean(_) => intrinsics.boolean_prototype,
+            Value::Symbol(_) => intrinsics.symbol_prototype,
+            Value::BigInt(_) => intrinsics.bigint_prototype,
+            Value::Undefined | Value::Null | Value::Hole => {
+                let message = format!("Cannot read properties of {} (reading '{}')", self.display(value), key);
+                return Err(NativeError::type_error(message));
+            },
+        };
+
+        self.get(prototype, key, value.clone())
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-ordinaryset
+    /// Returns `false` when the property can not be written.
+    pub fn set(&mut self, object: NonNull<Object>, key: PropertyKey, value: Value, receiver: Value) -> Result<bool, NativeError> {
+        match self.find_property(object, &key) {
+            Some((_, ref property)) if property.is_accessor() => {
+                return match property.setter() {
+                    setter @ Value::Function(_) => {
+                        self.call(setter, receiver, &[ value ])?;
+                        Ok(true)
+                    },
+                    _ => Ok(false),
+                };
+            },
+            Some((_, ref property)) if !property.writable => return Ok(false),
+            _ => { },
+        }
+
+        let target = match receiver.as_object() {
+            Some(target) => target,
+            None => return Ok(false),
+        };
+        match unsafe { target.as_ref() }.properties.get(&key) {
+            Some(property) if property.is_accessor() || !property.writable => Ok(false),
+            Some(_) => self.write_own(target, key, value),
+            None => {
+                if !unsafe { target.as_ref() }.is_extensible {
+                    return Ok(false);
+                }
+                self.define_own(target, key, Property::data(value))
+            },
+        }
+    }
+
+    /// PutValue, a failed assignment throws a TypeError in strict mode code.
+    pub fn set_value(&mut self, target: &Value, key: PropertyKey, value: Value, strict: bool) -> Result<(), NativeError> {
+        let ok = match *target {
+            Value::Undefined | Value::Null | Value::Hole => {
+                let message = format!("Cannot set properties of {} (setting '{}')", self.display(target), key);
+                return Err(NativeError::type_error(message));
+            },
+            Value::Object(_) | Value::Function(_) => {
+                let object = target.as_object().unwrap();
+                self.set(object, key.clone(), value, target.clone())?
+            },
+            _ => false,
+        };
+
+        if !ok && strict {
+            let message = format!("Cannot assign to read only property '{}' of {}", key, self.display(target));
+            return Err(NativeError::type_error(message));
+        }
+
+        Ok(())
+    }
+
+    /// Replace the value of an own data property.
+    fn write_own(&mut self, object: NonNull<Object>, key: PropertyKey, value: Value) -> Result<bool, NativeError> {
+        let target = unsafe { &mut *object.as_ptr() };
+        if target.is_array() && key == PropertyKey::from("length") {
+            return self.set_array_length(object, value);
+        }
+        if let Some(property) = target.properties.get_mut(&key) {
+            property.value = PackedValue::from(value);
+        }
+        self.write_barrier(object);
+
+        Ok(true)
+    }
+
+    /// Define ( or replace ) an own property, the `length` of arrays is kept in sync.
+    pub fn define_own(&mut self, object: NonNull<Object>, key: PropertyKey, property: Property) -> Result<bool, NativeError> {
+        let target = unsafe { &mut *object.as_ptr() };
+        if let Some(existing) = target.properties.get(&key) {
+            if !existing.configurable {
+                if existing.is_accessor() || !existing.writable || property.is_accessor() {
+                    return Ok(false);
+                }
+                return self.write_own(object, key, property.value());
+            }
+        } else if !target.is_extensible {
+            return Ok(false);
+        }
+
+        if target.is_array() {
+            if key == PropertyKey::from("length") {
+                return self.set_array_length(object, property.value());
+            }
+            if let Some(index) = key.as_index() {
+                let length = array_length(target);
+                if index >= length {
+                    match target.properties.get_mut(&PropertyKey::from("length")) {
+                        Some(ref property) if !property.writable => return Ok(false),
+                        Some(property) => property.value = PackedValue::from(Value::I64(index as i64 + 1)),
+                        None => { },
+                    }
+                }
+            }
+        }
+        target.insert(key, property);
+        self.write_barrier(object);
+
+        Ok(true)
+    }
+
+    fn set_array_length(&mut self, object: NonNull<Object>, value: Value) -> Result<bool, NativeError> {
+        let n = self.to_number(value)?;
+        let length = to_uint32(n);
+        if length as f64 != n {
+            return Err(NativeError::range_error("Invalid array length"));
+        }
+
+        let target = unsafe { &mut *object.as_ptr() };
+        match target.properties.get(&PropertyKey::from("length")) {
+            Some(property) if !property.writable => return Ok(array_length(target) == length),
+            _ => { },
+        }
+        if length < array_length(target) {
+            let removed = target.properties.keys()
+                .filter(|key| key.as_index().map(|index| index >= length).unwrap_or(false))
+                .cloned()
+                .collect::<Vec<PropertyKey>>();
+            for key in removed {
+                target.remove(key);
+            }
+        }
+        target.insert("length", Property { enumerable: false, configurable: false, ..Property::data(length as i64) });
+
+        Ok(true)
+    }
+
+    pub(crate) fn array_push(&mut self, array: NonNull<Object>, value: Value) -> Result<(), NativeError> {
+        let length = array_length(unsafe { array.as_ref() });
+        self.define_own(array, length.into(), Property::data(value))?;
+        Ok(())
+    }
+
+    pub fn delete_property(&mut self, object: NonNull<Object>, key: &PropertyKey) -> bool {
+        let target = unsafe { &mut *object.as_ptr() };
+        match target.properties.get(key) {
+            None => true,
+            Some(property) if !property.configurable => false,
+            Some(_) => {
+                target.remove(key);
+                true
+            },
+        }
+    }
+
+    /// Own enumerable string keys, in the order of `Object.keys`.
+    pub fn own_enumerable_keys(&self, object: NonNull<Object>) -> Vec<String> {
+        let object = unsafe { object.as_ref() };
+        object.keys().into_iter()
+            .filter(|key| object.properties[*key].enumerable)
+            .filter_map(|key| match *key {
+                PropertyKey::String(ref s) => Some(s.clone()),
+                PropertyKey::Symbol(_) => None,
+            })
+            .collect()
+    }
+
+    pub(crate) fn freeze(&mut self, object: NonNull<Object>) {
+        let target = unsafe { &mut *object.as_ptr() };
+        for property in target.properties.values_mut() {
+            property.configurable = false;
+            if !property.is_accessor() {
+                property.writable = false;
+            }
+        }
+        target.is_extensible = false;
+        target.is_sealed = true;
+        target.is_frozen = true;
+    }
+
+    // Comparison
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-strict-equality-comparison
+    pub fn strict_equals(&self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (&Value::String(x), &Value::String(y)) => x == y || unsafe { x.as_ref() == y.as_ref() },
+            (&Value::BigInt(x), &Value::BigInt(y)) => x == y || unsafe { x.as_ref() == y.as_ref() },
+            _ => match (a.as_f64(), b.as_f64()) {
+                (Some(x), Some(y)) => x == y,
+                (None, None) => a == b,
+                _ => false,
+            },
+        }
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-abstract-equality-comparison
+    pub fn loose_equals(&mut self, a: Value, b: Value) -> Result<bool, NativeError> {
+        let (x, y) = (category(&a), category(&b));
+        if x == y {
+            return Ok(self.strict_equals(&a, &b));
+        }
+
+        match (x, y) {
+            (0, 1) | (1, 0) => Ok(true),
+            (2, 3) => Ok(Some(self.to_number(b)?) == a.as_f64()),
+            (3, 2) => self.loose_equals(b, a),
+            (6, 3) => Ok(match (&a, &b) {
+                (&Value::BigInt(n), &Value::String(s)) => string_to_bigint(unsafe { s.as_ref() }) == Some(unsafe { n.as_ref() }.clone()),
+                _ => unreachable!(),
+            }),
+            (3, 6) => self.loose_equals(b, a),
+            (4, _) => {
+                let a = Value::number(self.to_number(a)?);
+                self.loose_equals(a, b)
+            },
+            (_, 4) => self.loose_equals(b, a),
+            (2, 7) | (3, 7) | (5, 7) | (6, 7) => {
+                let b = self.to_primitive(b, Hint::Default)?;
+                self.loose_equals(a, b)
+            },
+            (7, 2) | (7, 3) | (7, 5) | (7, 6) => self.loose_equals(b, a),
+            (2, 6) => self.loose_equals(b, a),
+            (6, 2) => Ok(match a {
+                Value::BigInt(n) => compare_bigint_number(unsafe { n.as_ref() }, b.as_f64().unwrap()) == Some(Ordering::Equal),
+                _ => unreachable!(),
+            }),
+            _ => Ok(false),
+        }
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-abstract-relational-comparison
+    /// `a < b`, `None` when the values can not be compared ( `NaN` ).
+    pub fn less_than(&mut self, a: Value, b: Value, left_first: bool) -> Result<Option<bool>, NativeError> {
+        let (a, b) = if left_first {
+            let a = self.to_primitive(a, Hint::Number)?;
+            (a, self.to_primitive(b, Hint::Number)?)
+        } else {
+            let b = self.to_primitive(b, Hint::Number)?;
+            (self.to_primitive(a, Hint::Number)?, b)
+        };
+
+        match (&a, &b) {
+            (&Value::String(x), &Value::String(y)) => {
+                let (x, y) = unsafe { (x.as_ref(), y.as_ref()) };
+                return Ok(Some(x.encode_utf16().lt(y.encode_utf16())));
+            },
+            (&Value::BigInt(x), &Value::String(y)) => {
+                return Ok(string_to_bigint(unsafe { y.as_ref() }).map(|y| unsafe { x.as_ref() } < &y));
+            },
+            (&Value::String(x), &Value::BigInt(y)) => {
+                return Ok(string_to_bigint(unsafe { x.as_ref() }).map(|x| &x < unsafe { y.as_ref() }));
+            },
+            _ => { },
+        }
+
+        let a = self.to_numeric(a)?;
+        let b = self.to_numeric(b)?;
+        Ok(match (&a, &b) {
+            (&Value::BigInt(x), &Value::BigInt(y)) => Some(unsafe { x.as_ref() < y.as_ref() }),
+            (&Value::BigInt(x), _) => compare_bigint_number(unsafe { x.as_ref() }, b.as_f64().unwrap()).map(|o| o == Ordering::Less),
+            (_, &Value::BigInt(y)) => compare_bigint_number(unsafe { y.as_ref() }, a.as_f64().unwrap()).map(|o| o == Ordering::Greater),
+            _ => {
+                let (x, y) = (a.as_f64().unwrap(), b.as_f64().unwrap());
+                if x.is_nan() || y.is_nan() { None } else { Some(x < y) }
+            },
+        })
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-instanceofoperator
+    pub fn instance_of(&mut self, value: &Value, target: &Value) -> Result<bool, NativeError> {
+        match *target {
+            Value::Function(_) => { },
+            _ => return Err(NativeError::type_error("Right-hand side of 'instanceof' is not callable")),
+        }
+        let mut current = match value.as_object() {
+            Some(object) => unsafe { object.as_ref() }.prototype,
+            None => return Ok(false),
+        };
+
+        let prototype = self.get_value(target, &"prototype".into())?;
+        let prototype = match prototype.as_object() {
+            Some(prototype) => prototype,
+            None => {
+                let message = format!("Function has non-object prototype '{}' in instanceof check", self.display(&prototype));
+                return Err(NativeError::type_error(message));
+            },
+        };
+        while let Some(object) = current {
+            if object == prototype {
+                return Ok(true);
+            }
+            current = unsafe { object.as_ref() }.prototype;
+        }
+
+        Ok(false)
+    }
+
+    // Operators
+
+    /// The binary arithmetic and bitwise operators.
+    pub(crate) fn binary(&mut self, op: Instruction, a: Value, b: Value) -> Result<Value, NativeError> {
+        if let (Some(x), Some(y)) = (a.as_f64(), b.as_f64()) {
+            if let Some(value) = number_operation(op, x, y) {
+                return Ok(value);
+            }
+        }
+
+        let (a, b) = match op {
+            Instruction::Add => {
+                let a = self.to_primitive(a, Hint::Default)?;
+                let b = self.to_primitive(b, Hint::Default)?;
+                match (&a, &b) {
+                    (&Value::String(_), _) | (_, &Value::String(_)) => {
+                        let mut s = self.to_string(a)?;
+                        s.push_str(&self.to_string(b)?);
+                        return Ok(self.new_string(s));
+                    },
+                    _ => (a, b),
+                }
+            },
+            _ => (a, b),
+        };
+
+        let a = self.to_numeric(a)?;
+        let b = self.to_numeric(b)?;
+        match (&a, &b) {
+            (&Value::BigInt(x), &Value::BigInt(y)) => {
+                let n = bigint_operation(op, unsafe { x.as_ref() }, unsafe { y.as_ref() })?;
+                Ok(self.new_bigint(n))
+            },
+            (&Value::BigInt(_), _) | (_, &Value::BigInt(_)) => {
+                Err(NativeError::type_error("Cannot mix BigInt and other types, use explicit conversions"))
+            },
+            _ => number_operation(op, a.as_f64().unwrap(), b.as_f64().unwrap())
+                .ok_or_else(|| NativeError::internal_error(format!("{:?} is not a binary operator", op))),
+        }
+    }
+
+    /// `-`, `~`, `++` and `--` on a numeric value.
+    pub(crate) fn unary(&mut self, op: Instruction, value: Value) -> Result<Value, NativeError> {
+        let value = self.to_numeric(value)?;
+        if let Value::BigInt(n) = value {
+            let n = unsafe { n.as_ref() };
+            let n = match op {
+                Instruction::Neg => -n,
+                Instruction::BitNot => -n - BigInt::one(),
+                Instruction::Inc => n + BigInt::one(),
+                Instruction::Dec => n - BigInt::one(),
+                _ => return Err(NativeError::internal_error(format!("{:?} is not an unary operator", op))),
+            };
+            return Ok(self.new_bigint(n));
+        }
+
+        let n = value.as_f64().unwrap();
+        Ok(match op {
+            Instruction::Neg => Value::number(-n),
+            Instruction::BitNot => Value::I64(i64::from(!to_int32(n))),
+            Instruction::Inc => Value::number(n + 1.0),
+            Instruction::Dec => Value::number(n - 1.0),
+            _ => return Err(NativeError::internal_error(format!("{:?} is not an unary operator", op))),
+        })
+    }
+
+    // Iterators
+
+    pub fn iterator_result(&mut self, value: Value, done: bool) -> Value {
+        let mut object = Object::with_prototype(self.intrinsics.object_prototype);
+        object.insert("value", Property::data(value));
+        object.insert("done", Property::data(done));
+        Value::Object(self.alloc(object))
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-getiterator
+    pub fn get_iterator(&mut self, value: Value) -> Result<Value, NativeError> {
+        let key = PropertyKey::Symbol(self.intrinsics.symbol_iterator);
+        let method = self.get_value(&value, &key)?;
+        match method {
+            Value::Function(_) => { },
+            _ => return Err(NativeError::type_error(format!("{} is not iterable", self.display(&value)))),
+        }
+
+        let iterator = self.call(method, value, &[])?;
+        if !iterator.is_object() {
+            return Err(NativeError::type_error("Result of the Symbol.iterator method is not an object"));
+        }
+
+        Ok(iterator)
+    }
+
+    /// Step the iterator, returns the value and whether it is done.
+    ///
+    /// The builtin iterators ( arrays, strings and `for-in` ) are stepped without calling `next`.
+    pub fn iterator_step(&mut self, iterator: &Value) -> Result<(Value, bool), NativeError> {
+        if let Value::Object(ptr) = *iterator {
+            let kind = unsafe { &mut (*ptr.as_ptr()).kind };
+            match *kind {
+                ObjectKind::ArrayIterator { ref target, index } => {
+                    let target = target.clone();
+                    if target.is_undefined() {
+                        return Ok((Value::Undefined, true));
+                    }
+                    // NOTE: 读取 length 与元素时可能会调用 getter
+                    let length = self.length_of_array_like(&target)?;
+                    let kind = unsafe { &mut (*ptr.as_ptr()).kind };
+                    if index >= length as usize {
+                        *kind = ObjectKind::ArrayIterator { target: Value::Undefined, index };
+                        return Ok((Value::Undefined, true));
+                    }
+                    *kind = ObjectKind::ArrayIterator { target: target.clone(), index: index + 1 };
+                    let value = self.get_value(&target, &(index as u32).into())?;
+                    return Ok((value, false));
+                },
+                ObjectKind::StringIterator { ref mut target, ref mut index } => {
+                    let ch = target.and_then(|s| unsafe { &*s.as_ptr() }[*index..].chars().next());
+                    return Ok(match ch {
+                        Some(ch) => {
+                            *index += ch.len_utf8();
+                            (self.new_string(ch.to_string()), false)
+                        },
+                        None => {
+                            *target = None;
+                            (Value::Undefined, true)
+                        },
+                    });
+                },
+                ObjectKind::ForInIterator { object, ref keys, ref mut index } => {
+                    while *index < keys.len() {
+                        let key = keys[*index].clone();
+                        *index += 1;
+                        // NOTE: 枚举过程中被删除的属性不会再被访问
+                        if object.map(|object| self.has_property(object, &key)).unwrap_or(false) {
+                            return Ok((self.key_value(key), false));
+                        }
+                    }
+                    return Ok((Value::Undefined, true));
+                },
+                _ => { },
+            }
+        }
+
+        let next = self.get_value(iterator, &"next".into())?;
+        let result = self.call(next, iterator.clone(), &[])?;
+        let object = match result.as_object() {
+            Some(object) => object,
+            None => return Err(NativeError::type_error(format!("Iterator result {} is not an object", self.display(&result)))),
+        };
+        let done = self.get(object, &"done".into(), result.clone())?.to_boolean();
+        let value = self.get(object, &"value".into(), result)?;
+
+        Ok((value, done))
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-iteratorclose
+    pub fn iterator_close(&mut self, iterator: &Value) -> Result<(), NativeError> {
+        if let Value::Object(ptr) = *iterator {
+            let kind = unsafe { &mut (*ptr.as_ptr()).kind };
+            match *kind {
+                ObjectKind::ArrayIterator { ref mut target, .. } => {
+                    *target = Value::Undefined;
+                    return Ok(());
+                },
+                ObjectKind::StringIterator { ref mut target, .. } => {
+                    *target = None;
+                    return Ok(());
+                },
+                ObjectKind::ForInIterator { .. } => return Ok(()),
+                _ => { },
+            }
+        }
+
+        let method = self.get_value(iterator, &"return".into())?;
+        if method.is_nullish() {
+            return Ok(());
+        }
+        let result = self.call(method, iterator.clone(), &[])?;
+        if !result.is_object() {
+            return Err(NativeError::type_error(format!("Iterator result {} is not an object", self.display(&result))));
+        }
+
+        Ok(())
+    }
+
+    /// The iterator of `for-in`, the enumerable string keys of the object and its prototypes.
+    pub(crate) fn for_in_iterator(&mut self, value: Value) -> Result<Value, NativeError> {
+        let object = match value {
+            Value::Undefined | Value::Null => None,
+            value => Some(self.to_object(value)?),
+        };
+
+        let mut keys = Vec::new();
+        let mut visited = std::collections::HashSet::new();
+        let mut current = object;
+        while let Some(ptr) = current {
+            let target = unsafe { ptr.as_ref() };
+            for key in target.keys() {
+                if let PropertyKey::Symbol(_) = *key {
+                    continue;
+                }
+                // NOTE: 被遮蔽的属性即使不可枚举也不会再被访问
+                if visited.insert(key.clone()) && target.properties[key].enumerable {
+                    keys.push(key.clone());
+                }
+            }
+            current = target.prototype;
+        }
+
+        let prototype = self.intrinsics.iterator_prototype;
+        let kind = ObjectKind::ForInIterator { object, keys, index: 0 };
+        Ok(Value::Object(self.alloc(Object::with_kind(prototype, kind))))
+    }
+}
+
+fn array_length(object: &Object) -> u32 {
+    match object.get("length").map(Property::value) {
+        Some(Value::I64(n)) => n as u32,
+        Some(Value::F64(n)) => to_uint32(n),
+        _ => 0,
+    }
+}
@@ -0,0 +1,390 @@
+// Shapes ( hidden classes )
+//
+//      let a = { x: 1, y: 2 };      a: <root> -x-> { x: 0 } -y-> { x: 0, y: 1 }    slots: [ 1, 2 ]
+//      let b = { x: 3, y: 4 };      b: <root> -x-> { x: 0 } -y-> { x: 0, y: 1 }    slots: [ 3, 4 ]
+//      let c = { y: 5 };            c: <root> -y-> { y: 0 }                        slots: [ 5 ]
+//      delete a.x;                  a: <dictionary> { y: 2 }
+//
+// NOTE: 以相同的顺序添加相同属性的对象共享同一个 Shape，Shape 只记录属性名到槽位的映射，
+//       属性的值与特性（writable、enumerable ...）保存在对象自己的槽位中。
+//       Shape 的比较使用指针（`Rc::ptr_eq`），内联缓存只需要比较对象的 Shape 就知道属性在哪个槽位。
+//
+//       删除属性或者属性过多（`MAX_SHAPED_PROPERTIES`）时对象切换到字典模式，不再拥有 Shape，
+//       字典模式的对象不会被内联缓存。
+//       转换表使用弱引用（子 Shape 持有父 Shape），不再被对象（以及内联缓存）使用的 Shape 会被释放。
+
+use rustc_hash::FxHashMap;
+
+use crate::object::{ Property, PropertyKey, };
+
+use std::rc::{ Rc, Weak, };
+use std::cell::RefCell;
+use std::ops::Index;
+use std::collections::hash_map;
+use std::slice;
+
+
+/// Objects with more properties switch to the dictionary mode.
+pub const MAX_SHAPED_PROPERTIES: usize = 64;
+
+
+thread_local! {
+    static ROOT: Rc<Shape> = Rc::new(Shape {
+        parent: None,
+        table: FxHashMap::default(),
+        keys: Vec::new(),
+        transitions: RefCell::new(FxHashMap::default()),
+    });
+}
+
+/// The layout of the properties, shared by the objects that have added the same keys in the same order.
+pub struct Shape {
+    /// Keeps the transition chain alive, so the objects that add the same keys later find the same shapes.
+    parent: Option<Rc<Shape>>,
+    /// The slots of the keys.
+    table: FxHashMap<PropertyKey, u32>,
+    /// The keys in slot ( insertion ) order.
+    keys: Vec<PropertyKey>,
+    transitions: RefCell<FxHashMap<PropertyKey, Weak<Shape>>>,
+}
+
+impl Shape {
+    /// The shape of objects without properties.
+    #[inline]
+    pub fn root() -> Rc<Shape> {
+        ROOT.with(|root| root.clone())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The shape before adding the last key, `None` for the root shape.
+    #[inline]
+    pub fn parent(&self) -> Option<&Rc<Shape>> {
+        self.parent.as_ref()
+    }
+
+    #[inline]
+    pub fn keys(&self) -> &[PropertyKey] {
+        &self.keys
+    }
+
+    #[inline]
+    pub fn slot(&self, key: &PropertyKey) -> Option<u32> {
+        self.table.get(key).cloned()
+    }
+
+    /// The shape after adding `key`, the same shape is returned for the same key as long as it is in use.
+    pub fn transition(self: &Rc<Self>, key: &PropertyKey) -> Rc<Shape> {
+        debug_assert!(!self.table.contains_key(key));
+
+        if let Some(shape) = self.transitions.borrow().get(key).and_then(Weak::upgrade) {
+            return shape;
+        }
+
+        let mut table = self.table.clone();
+        table.insert(key.clone(), self.keys.len() as u32);
+        let mut keys = self.keys.clone();
+        keys.push(key.clone());
+        let shape = Rc::new(Shape { parent: Some(self.clone()), table, keys, transitions: RefCell::new(FxHashMap::default()) });
+
+        let mut transitions = self.transitions.borrow_mut();
+        transitions.retain(|_, shape| shape.strong_count() > 0);
+        transitions.insert(key.clone(), Rc::downgrade(&shape));
+
+        shape
+    }
+}
+
+impl PartialEq for Shape {
+    fn eq(&self, other: &Shape) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl std::fmt::Debug for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Shape {:?}", self.keys)
+    }
+}
+
+
+/// Own properties of an object.
+#[derive(Debug, Clone)]
+pub enum Properties {
+    /// The slots are indexed by the shape.
+    Shaped { shape: Rc<Shape>, slots: Vec<Property> },
+    /// Objects that have deleted properties or have too many properties.
+    Dictionary { map: FxHashMap<PropertyKey, Property>, order: Vec<PropertyKey> },
+}
+
+impl Properties {
+    #[inline]
+    pub fn new() -> Self {
+        Properties::Shaped { shape: Shape::root(), slots: Vec::new() }
+    }
+
+    /// `None` in the dictionary mode.
+    #[inline]
+    pub fn shape(&self) -> Option<&Rc<Shape>> {
+        match *self {
+            Properties::Shaped { ref shape, .. } => Some(shape),
+            Properties::Dictionary { .. } => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_dictionary(&self) -> bool {
+        self.shape().is_none()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        match *self {
+            Properties::Shaped { ref slots, .. } => slots.len(),
+            Properties::Dictionary { ref map, .. } => map.len(),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The keys in insertion order.
+    #[inline]
+    pub fn keys(&self) -> slice::Iter<'_, PropertyKey> {
+        match *self {
+            Properties::Shaped { ref shape, .. } => shape.keys().iter(),
+            Properties::Dictionary { ref order, .. } => order.iter(),
+        }
+    }
+
+    #[inline]
+    pub fn values(&self) -> Values<'_> {
+        match *self {
+            Properties::Shaped { ref slots, .. } => Values::Slots(slots.iter()),
+            Properties::Dictionary { ref map, .. } => Values::Map(map.values()),
+        }
+    }
+
+    #[inline]
+    pub fn values_mut(&mut self) -> ValuesMut<'_> {
+        match *self {
+            Properties::Shaped { ref mut slots, .. } => ValuesMut::Slots(slots.iter_mut()),
+            Properties::Dictionary { ref mut map, .. } => ValuesMut::Map(map.values_mut()),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, key: &PropertyKey) -> Option<&Property> {
+        match *self {
+            Properties::Shaped { ref shape, ref slots } => shape.slot(key).map(|slot| &slots[slot as usize]),
+            Properties::Dictionary { ref map, .. } => map.get(key),
+        }
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, key: &PropertyKey) -> Option<&mut Property> {
+        match *self {
+            Properties::Shaped { ref shape, ref mut slots } => shape.slot(key).map(move |slot| &mut slots[slot as usize]),
+            Properties::Dictionary { ref mut map, .. } => map.get_mut(key),
+        }
+    }
+
+    #[inline]
+    pub fn contains_key(&self, key: &PropertyKey) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The property in `slot`, only for shaped properties.
+    #[inline]
+    pub fn slot(&self, slot: u32) -> Option<&Property> {
+        match *self {
+            Properties::Shaped { ref slots, .. } => slots.get(slot as usize),
+            Properties::Dictionary { .. } => None,
+        }
+    }
+
+    #[inline]
+    pub fn slot_mut(&mut self, slot: u32) -> Option<&mut Property> {
+        match *self {
+            Properties::Shaped { ref mut slots, .. } => slots.get_mut(slot as usize),
+            Properties::Dictionary { .. } => None,
+        }
+    }
+
+    /// Replaces the existing property in place, or adds the property to the end.
+    pub fn insert(&mut self, key: PropertyKey, property: Property) -> Option<Property> {
+        if let Some(existing) = self.get_mut(&key) {
+            return Some(std::mem::replace(existing, property));
+        }
+
+        if self.len() >= MAX_SHAPED_PROPERTIES {
+            self.make_dictionary();
+        }
+
+        match *self {
+            Properties::Shaped { ref mut shape, ref mut slots } => {
+                *shape = shape.transition(&key);
+                slots.push(property);
+            },
+            Properties::Dictionary { ref mut map, ref mut order } => {
+                map.insert(key.clone(), property);
+                order.push(key);
+            },
+        }
+
+        None
+    }
+
+    /// Adds a property with a known transition, `target` must be the transition of the current shape.
+    pub(crate) fn push(&mut self, target: Rc<Shape>, property: Property) {
+        match *self {
+            Properties::Shaped { ref mut shape, ref mut slots } => {
+                debug_assert_eq!(target.len(), slots.len() + 1);
+                *shape = target;
+                slots.push(property);
+            },
+            Properties::Dictionary { .. } => unreachable!(),
+        }
+    }
+
+    /// Removes the property, the properties switch to the dictionary mode.
+    pub fn remove(&mut self, key: &PropertyKey) -> Option<Property> {
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        self.make_dictionary();
+        match *self {
+            Properties::Dictionary { ref mut map, ref mut order } => {
+                let property = map.remove(key)?;
+                if let Some(pos) = order.iter().position(|item| item == key) {
+                    order.remove(pos);
+                }
+                Some(property)
+            },
+            Properties::Shaped { .. } => unreachable!(),
+        }
+    }
+
+    fn make_dictionary(&mut self) {
+        let (keys, slots) = match *self {
+            Properties::Shaped { ref shape, ref mut slots } => (shape.keys().to_vec(), std::mem::take(slots)),
+            Properties::Dictionary { .. } => return,
+        };
+
+        let map = keys.iter().cloned().zip(slots).collect::<FxHashMap<PropertyKey, Property>>();
+        *self = Properties::Dictionary { map, order: keys };
+    }
+}
+
+impl Default for Properties {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Index<&'a PropertyKey> for Properties {
+    type Output = Property;
+
+    fn index(&self, key: &'a PropertyKey) -> &Property {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+
+pub enum Values<'a> {
+    Slots(slice::Iter<'a, Property>),
+    Map(hash_map::Values<'a, PropertyKey, Property>),
+}
+
+impl<'a> Iterator for Values<'a> {
+    type Item = &'a Property;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a Property> {
+        match *self {
+            Values::Slots(ref mut iter) => iter.next(),
+            Values::Map(ref mut iter) => iter.next(),
+        }
+    }
+}
+
+pub enum ValuesMut<'a> {
+    Slots(slice::IterMut<'a, Property>),
+    Map(hash_map::ValuesMut<'a, PropertyKey, Property>),
+}
+
+impl<'a> Iterator for ValuesMut<'a> {
+    type Item = &'a mut Property;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a mut Property> {
+        match *self {
+            ValuesMut::Slots(ref mut iter) => iter.next(),
+            ValuesMut::Map(ref mut iter) => iter.next(),
+        }
+    }
+}
+
+
+#[test]
+fn test_shapes() {
+    let key = |name: &str| PropertyKey::from(name);
+
+    let mut a = Properties::new();
+    let mut b = Properties::new();
+    let mut c = Properties::new();
+    a.insert(key("x"), Property::data(1));
+    a.insert(key("y"), Property::data(2));
+    b.insert(key("x"), Property::data(3));
+    b.insert(key("y"), Property::data(4));
+    c.insert(key("y"), Property::data(5));
+    c.insert(key("x"), Property::data(6));
+
+    // NOTE: 相同的添加顺序共享 Shape
+    assert!(Rc::ptr_eq(a.shape().unwrap(), b.shape().unwrap()));
+    assert!(!Rc::ptr_eq(a.shape().unwrap(), c.shape().unwrap()));
+    assert_eq!(a.shape().unwrap().slot(&key("y")), Some(1));
+    assert_eq!(c.shape().unwrap().slot(&key("y")), Some(0));
+    assert_eq!(b[&key("y")].value.as_f64(), Some(4.0));
+
+    // NOTE: 修改已有的属性不改变 Shape
+    let shape = a.shape().unwrap().clone();
+    a.insert(key("x"), Property::readonly(7));
+    assert!(Rc::ptr_eq(a.shape().unwrap(), &shape));
+    assert_eq!(a.slot(0).map(|property| property.writable), Some(false));
+
+    // NOTE: 删除属性切换到字典模式，保留插入顺序
+    assert!(a.remove(&key("x")).is_some());
+    assert!(a.is_dictionary());
+    assert!(a.get(&key("x")).is_none());
+    a.insert(key("z"), Property::data(8));
+    assert_eq!(a.keys().collect::<Vec<&PropertyKey>>(), vec![ &key("y"), &key("z") ]);
+    assert!(!b.is_dictionary());
+
+    // NOTE: 属性过多切换到字典模式
+    let mut d = Properties::new();
+    for index in 0..MAX_SHAPED_PROPERTIES as u32 + 1 {
+        d.insert(PropertyKey::from(index), Property::data(index as i64));
+    }
+    assert!(d.is_dictionary());
+    assert_eq!(d.len(), MAX_SHAPED_PROPERTIES + 1);
+    assert_eq!(d.keys().next(), Some(&PropertyKey::from(0)));
+    assert_eq!(d.values().count(), d.len());
+
+    // NOTE: 不再使用的 Shape 被释放
+    let weak = Rc::downgrade(b.shape().unwrap());
+    drop(b);
+    drop(shape);
+    assert!(weak.upgrade().is_none());
+}
@@ -22,8 +22,8 @@ impl Symbol {
         }
         
         match is_public {
-            true => Self(std::usize::MAX | id),
-            false => Self(Self::MAX | id),
+            true => Self((1 << (Self::WIDTH - 1)) | id),
+            false => Self(id),
         }
     }
 
@@ -22,6 +22,7 @@ pub enum ValueKind {
     F64,
     Boolean,
     Symbol,
+    Hole,
     // Copy Ref
     // Heap RcRef Value
     String,
@@ -35,7 +36,7 @@ pub enum ValueKind {
     Object,
 }
 
-// TODO: 考虑 Tagged Pointer
+// NOTE: 8 个字节的 NaN-boxing 表示见 `nanbox::Value`（`nan-boxing` feature）
 // https://blog.devtang.com/2014/05/30/understand-tagged-pointer/
 #[derive(Debug, PartialEq, Clone)]
 pub enum Value {
@@ -45,6 +46,8 @@ pub enum Value {
     F64(f64),
     Symbol(Symbol),
     Boolean(bool),
+    /// Internal, the value of `let`/`const`/`class` bindings in the TDZ, never visible to scripts.
+    Hole,
 
     String(NonNull<String>),
     BigInt(NonNull<BigInt>),
@@ -53,6 +56,14 @@ pub enum Value {
     Object(NonNull<Object>),
 }
 
+/// The values stored by the VM: the operand stacks, local variables, Cells ( captured variables ) and property slots.
+///
+/// With the `nan-boxing` feature they are 8 byte `nanbox::Value`s and are unpacked into `Value` when they are used.
+#[cfg(feature = "nan-boxing")]
+pub type PackedValue = crate::nanbox::Value;
+#[cfg(not(feature = "nan-boxing"))]
+pub type PackedValue = Value;
+
 
 pub trait Cast<T> {
     // Safe
@@ -68,14 +79,194 @@ pub trait Copy: Clone {
 }
 
 
+impl Value {
+    /// A Number value, integral values are kept as `I64`.
+    #[inline]
+    pub fn number(n: f64) -> Self {
+        // NOTE: -0 必须保持为 F64
+        if n.fract() == 0.0 && n.abs() <= 9007199254740992.0 && !(n == 0.0 && n.is_sign_negative()) {
+            Value::I64(n as i64)
+        } else {
+            Value::F64(n)
+        }
+    }
+
+    #[inline]
+    pub fn kind(&self) -> ValueKind {
+        match *self {
+            Value::Undefined => ValueKind::Undefined,
+            Value::Null => ValueKind::Null,
+            Value::I64(_) => ValueKind::I64,
+            Value::F64(_) => ValueKind::F64,
+            Value::Symbol(_) => ValueKind::Symbol,
+            Value::Boolean(_) => ValueKind::Boolean,
+            Value::Hole => ValueKind::Hole,
+            Value::String(_) => ValueKind::String,
+            Value::BigInt(_) => ValueKind::BigInt,
+            Value::Function(_) => ValueKind::Function,
+            Value::Object(_) => ValueKind::Object,
+        }
+    }
+
+    #[inline]
+    pub fn is_undefined(&self) -> bool {
+        *self == Value::Undefined
+    }
+
+    #[inline]
+    pub fn is_nullish(&self) -> bool {
+        matches!(*self, Value::Undefined | Value::Null)
+    }
+
+    #[inline]
+    pub fn is_hole(&self) -> bool {
+        *self == Value::Hole
+    }
+
+    #[inline]
+    pub fn is_number(&self) -> bool {
+        matches!(*self, Value::I64(_) | Value::F64(_))
+    }
+
+    #[inline]
+    pub fn is_object(&self) -> bool {
+        matches!(*self, Value::Object(_) | Value::Function(_))
+    }
+
+    /// The value of Number values, `None` for the others.
+    #[inline]
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::I64(n) => Some(n as f64),
+            Value::F64(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// The object of Object values, a function is an object too.
+    #[inline]
+    pub fn as_object(&self) -> Option<NonNull<Object>> {
+        match *self {
+            Value::Object(ptr) => Some(ptr),
+            Value::Function(ptr) => Some(unsafe { ptr.as_ref() }.object_ptr()),
+            _ => None,
+        }
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-typeof-operator
+    #[inline]
+    pub fn type_of(&self) -> &'static str {
+        match *self {
+            Value::Undefined | Value::Hole => "undefined",
+            Value::Null => "object",
+            Value::I64(_) | Value::F64(_) => "number",
+            Value::Symbol(_) => "symbol",
+            Value::Boolean(_) => "boolean",
+            Value::String(_) => "string",
+            Value::BigInt(_) => "bigint",
+            Value::Function(_) => "function",
+            Value::Object(_) => "object",
+        }
+    }
+
+    // https://www.ecma-international.org/ecma-262/9.0/index.html#sec-toboolean
+    #[inline]
+    pub fn to_boolean(&self) -> bool {
+        match *self {
+            Value::Undefined | Value::Null | Value::Hole => false,
+            Value::Boolean(b) => b,
+            Value::I64(n) => n != 0,
+            Value::F64(n) => !(n == 0.0 || n.is_nan()),
+            Value::String(ptr) => !unsafe { ptr.as_ref() }.is_empty(),
+            Value::BigInt(ptr) => !unsafe { ptr.as_ref() }.is_zero(),
+            Value::Symbol(_) | Value::Function(_) | Value::Object(_) => true,
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Boolean(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::I64(n)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::number(n)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Undefined => write!(f, "undefined"),
+            Value::Null => write!(f, "null"),
+            Value::I64(n) => write!(f, "{}", n),
+            Value::F64(n) => write!(f, "{}", ecmascript::vm::value::Number(n)),
+            Value::Symbol(_) => write!(f, "Symbol()"),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Hole => write!(f, "<hole>"),
+            Value::String(ptr) => write!(f, "{:?}", unsafe { ptr.as_ref() }),
+            Value::BigInt(ptr) => write!(f, "{}n", unsafe { ptr.as_ref() }),
+            Value::Function(_) => write!(f, "[Function]"),
+            Value::Object(_) => write!(f, "[object Object]"),
+        }
+    }
+}
+
+
 impl Cast<i64> for Value {
     // Safe
     fn cast(self) -> Result<i64, NativeError> {
-        unimplemented!()
+        match self {
+            Value::I64(n) => Ok(n),
+            Value::F64(n) if n.fract() == 0.0 && n.abs() <= 9007199254740992.0 => Ok(n as i64),
+            _ => Err(NativeError::type_error(format!("{} is not an integer", self))),
+        }
     }
 
     fn bitcast(self) -> i64 {
-        unimplemented!()
+        match self {
+            Value::I64(n) => n,
+            Value::F64(n) => n as i64,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Cast<f64> for Value {
+    fn cast(self) -> Result<f64, NativeError> {
+        self.as_f64().ok_or_else(|| NativeError::type_error(format!("{} is not a number", self)))
+    }
+
+    fn bitcast(self) -> f64 {
+        match self {
+            Value::I64(n) => n as f64,
+            Value::F64(n) => n,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Cast<bool> for Value {
+    fn cast(self) -> Result<bool, NativeError> {
+        match self {
+            Value::Boolean(b) => Ok(b),
+            _ => Err(NativeError::type_error(format!("{} is not a boolean", self))),
+        }
+    }
+
+    fn bitcast(self) -> bool {
+        match self {
+            Value::Boolean(b) => b,
+            _ => unreachable!(),
+        }
     }
 }
 
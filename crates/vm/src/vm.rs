@@ -1,17 +1,35 @@
-use rustc_hash::FxHashMap;
+use rustc_hash::{ FxHashMap, FxHashSet, };
+use num::BigInt;
 
-use crate::value::{ Value, ValueKind, };
+use crate::value::{ Value, ValueKind, PackedValue, };
 use crate::error::NativeError;
 use crate::env::{ Environment, EnvironmentKind, RecordKind, };
-use crate::object::{ Object, Property, PropertyKey, };
-use crate::function::{ Function, FunctionCode, };
+use crate::object::{ Object, ObjectKind, Property, PropertyKey, };
+use crate::function::{ Function, FunctionCode, NativeFunction, };
+use crate::frame::{ Code, Frame, };
+use crate::intrinsics::Intrinsics;
+use crate::interpreter::Completion;
+use crate::symbol::Symbol;
+use crate::gc::{ Trace, Tracer, };
 
+use ecmascript::compiler::bytecode::CodeObject;
+use ecmascript::compiler::verifier;
+use ecmascript::lexer::span::Loc;
+use ecmascript::vm::isolate::HeapStats;
 
+use std::rc::Rc;
 use std::ptr::NonNull;
+use std::time::Duration;
 
 
 pub type ByteCode = Vec<u8>;
 
+/// Calls nested deeper than this throw a RangeError.
+pub const MAX_FRAMES: usize = 10000;
+/// Native functions ( getters, `Function.prototype.call` ... ) calling back into the interpreter
+/// nest on the Rust stack, so they have a much smaller limit.
+pub const MAX_NATIVE_DEPTH: usize = 64;
+
 
 #[derive(Debug)]
 pub struct Module {
@@ -25,62 +43,436 @@ pub struct Task {
 }
 
 
+/// A value owned by the `Allocator`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum HeapRef {
+    String(NonNull<String>),
+    BigInt(NonNull<BigInt>),
+    Object(NonNull<Object>),
+    Function(NonNull<Function>),
+    Environment(NonNull<Environment>),
+    /// A variable captured by closures.
+    Cell(NonNull<PackedValue>),
+}
+
+impl HeapRef {
+    /// Drop the value, its destructor finalizes what it owns ( property tables, suspended generator frames ... ).
+    unsafe fn free(self) {
+        match self {
+            HeapRef::String(ptr) => drop(Box::from_raw(ptr.as_ptr())),
+            HeapRef::BigInt(ptr) => drop(Box::from_raw(ptr.as_ptr())),
+            HeapRef::Object(ptr) => drop(Box::from_raw(ptr.as_ptr())),
+            HeapRef::Function(ptr) => drop(Box::from_raw(ptr.as_ptr())),
+            HeapRef::Environment(ptr) => drop(Box::from_raw(ptr.as_ptr())),
+            HeapRef::Cell(ptr) => drop(Box::from_raw(ptr.as_ptr())),
+        }
+    }
+
+    unsafe fn size(self) -> usize {
+        match self {
+            HeapRef::String(ptr) => ptr.as_ref().size(),
+            HeapRef::BigInt(ptr) => ptr.as_ref().size(),
+            HeapRef::Object(ptr) => ptr.as_ref().size(),
+            HeapRef::Function(ptr) => ptr.as_ref().size(),
+            HeapRef::Environment(ptr) => ptr.as_ref().size(),
+            HeapRef::Cell(ptr) => ptr.as_ref().size(),
+        }
+    }
+}
+
+pub trait Allocation: Sized {
+    fn heap_ref(ptr: NonNull<Self>) -> HeapRef;
+
+    /// Bytes accounted to the value, it must not change while the value is alive.
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+macro_rules! allocation {
+    ( $( $ty:ty => $kind:ident, )* ) => {
+        $(
+            impl Allocation for $ty {
+                #[inline]
+                fn heap_ref(ptr: NonNull<Self>) -> HeapRef {
+                    HeapRef::$kind(ptr)
+                }
+            }
+        )*
+    }
+}
+
+allocation! {
+    BigInt => BigInt,
+    Object => Object,
+    Function => Function,
+    Environment => Environment,
+    PackedValue => Cell,
+}
+
+impl Allocation for String {
+    #[inline]
+    fn heap_ref(ptr: NonNull<Self>) -> HeapRef {
+        HeapRef::String(ptr)
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.capacity()
+    }
+}
+
+
+/// Collect when this many bytes have been allocated since the last collection at least.
+pub const MIN_GC_THRESHOLD: usize = 1024 * 1024;
+/// Bytes allocated in the young generation between minor collections.
+pub const DEFAULT_NURSERY_SIZE: usize = 256 * 1024;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GcMode {
+    /// Every collection marks the whole heap.
+    Full,
+    /// New values are allocated in a nursery that is collected on its own,
+    /// the values that survive a collection are promoted to the old generation.
+    Generational,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Collection {
+    /// Collect the young generation.
+    Minor,
+    /// Collect the whole heap.
+    Major,
+}
+
 #[derive(Debug)]
 pub struct Allocator {
-    store: Vec<*mut u8>,
+    mode: GcMode,
+    /// The old generation, all the values in `Full` mode.
+    store: Vec<HeapRef>,
+    young: Vec<HeapRef>,
+    /// Old values written since the last collection, see `Vm::write_barrier`.
+    remembered: FxHashSet<HeapRef>,
+    /// Bytes of the values in `store`.
+    bytes: usize,
+    young_bytes: usize,
+    allocated: u64,
+    /// The next major collection happens when `bytes` exceeds it.
+    threshold: usize,
+    nursery_size: usize,
+    limit: Option<usize>,
+    minor_collections: usize,
+    major_collections: usize,
+    last_pause: Duration,
+    max_pause: Duration,
+    total_pause: Duration,
 }
 
 impl Allocator {
     pub fn new() -> Self {
-        let store = Vec::new();
-        Self { store }
+        Self {
+            mode: GcMode::Full,
+            store: Vec::new(),
+            young: Vec::new(),
+            remembered: FxHashSet::default(),
+            bytes: 0,
+            young_bytes: 0,
+            allocated: 0,
+            threshold: MIN_GC_THRESHOLD,
+            nursery_size: DEFAULT_NURSERY_SIZE,
+            limit: None,
+            minor_collections: 0,
+            major_collections: 0,
+            last_pause: Duration::default(),
+            max_pause: Duration::default(),
+            total_pause: Duration::default(),
+        }
+    }
+
+    /// Move the value to the heap, it lives until it is swept or the `Allocator` is dropped.
+    pub fn alloc<T: Allocation>(&mut self, value: T) -> NonNull<T> {
+        let size = value.size();
+        self.allocated += size as u64;
+        let ptr = NonNull::from(Box::leak(Box::new(value)));
+        match self.mode {
+            GcMode::Full => {
+                self.bytes += size;
+                self.store.push(T::heap_ref(ptr));
+            },
+            GcMode::Generational => {
+                self.young_bytes += size;
+                self.young.push(T::heap_ref(ptr));
+            },
+        }
+
+        ptr
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.store.len() + self.young.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty() && self.young.is_empty()
     }
 
-    pub fn alloc() {
-        unimplemented!()
+    #[inline]
+    pub fn bytes(&self) -> usize {
+        self.bytes + self.young_bytes
     }
 
-    pub fn gc(&mut self) {
+    #[inline]
+    pub fn young_bytes(&self) -> usize {
+        self.young_bytes
+    }
 
+    #[inline]
+    pub fn collections(&self) -> usize {
+        self.minor_collections + self.major_collections
     }
 
-    pub fn mark(&mut self) {
+    pub fn mode(&self) -> GcMode {
+        self.mode
+    }
 
+    /// The values allocated so far stay in their generation, a major collection promotes the young ones.
+    pub fn set_mode(&mut self, mode: GcMode) {
+        self.mode = mode;
+        self.remembered.clear();
     }
 
-    pub fn sweep(&mut self) {
+    pub fn set_nursery_size(&mut self, size: usize) {
+        self.nursery_size = size;
+    }
 
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// The memory cap, collections that can not bring the heap below it throw a RangeError.
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+        self.update_threshold();
+    }
+
+    #[inline]
+    pub fn should_collect(&self) -> bool {
+        self.pending().is_some()
+    }
+
+    /// The collection to run at the next safe point.
+    pub fn pending(&self) -> Option<Collection> {
+        if self.bytes > self.threshold || self.is_over_limit() {
+            Some(Collection::Major)
+        } else if self.mode == GcMode::Generational && self.young_bytes > self.nursery_size {
+            Some(Collection::Minor)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn is_over_limit(&self) -> bool {
+        self.limit.map(|limit| self.bytes() > limit).unwrap_or(false)
+    }
+
+    fn update_threshold(&mut self) {
+        let threshold = std::cmp::max(self.bytes.saturating_mul(2), MIN_GC_THRESHOLD);
+        self.threshold = match self.limit {
+            Some(limit) => std::cmp::min(threshold, limit),
+            None => threshold,
+        };
+    }
+
+    /// Record a write to the value, the young values it refers to are kept by minor collections.
+    #[inline]
+    pub fn remember(&mut self, item: HeapRef) {
+        if self.mode == GcMode::Generational {
+            self.remembered.insert(item);
+        }
+    }
+
+    /// Collect the values that are not reachable from `roots`.
+    pub fn gc<R: Trace + ?Sized>(&mut self, roots: &R) {
+        let marked = self.mark(roots);
+        self.sweep(&marked, Collection::Major);
+    }
+
+    /// The values reachable from `roots`.
+    pub fn mark<R: Trace + ?Sized>(&self, roots: &R) -> FxHashSet<HeapRef> {
+        let mut tracer = Tracer::new();
+        roots.trace(&mut tracer);
+        tracer.finish()
+    }
+
+    /// The young values reachable from `roots` and from the remembered old values.
+    pub fn mark_young<R: Trace + ?Sized>(&self, roots: &R) -> FxHashSet<HeapRef> {
+        let mut tracer = Tracer::young(self.young.iter().cloned().collect());
+        roots.trace(&mut tracer);
+        for item in self.remembered.iter() {
+            if !tracer.is_young(item) {
+                tracer.scan(*item);
+            }
+        }
+        let marked = tracer.finish();
+
+        // NOTE: 遗漏的写屏障会让可达的新生代对象被释放，测试中与完整的标记结果比较
+        #[cfg(test)]
+        {
+            let reachable = self.mark(roots);
+            for item in self.young.iter().filter(|item| reachable.contains(item)) {
+                assert!(marked.contains(item), "missing write barrier, {:?} is reachable but not marked", item);
+            }
+        }
+
+        marked
+    }
+
+    /// Free the values that are not marked, returns the number of freed values.
+    ///
+    /// The young values that are marked are promoted, a minor collection keeps all the old values.
+    pub fn sweep(&mut self, marked: &FxHashSet<HeapRef>, collection: Collection) -> usize {
+        let before = self.len();
+
+        let mut freed = 0;
+        if collection == Collection::Major {
+            self.store.retain(|item| {
+                if marked.contains(item) {
+                    return true;
+                }
+                unsafe {
+                    freed += item.size();
+                    item.free();
+                }
+                false
+            });
+            self.bytes -= freed;
+        }
+
+        let mut promoted = 0;
+        for item in self.young.drain(..) {
+            unsafe {
+                if marked.contains(&item) {
+                    promoted += item.size();
+                    self.store.push(item);
+                } else {
+                    item.free();
+                }
+            }
+        }
+        self.bytes += promoted;
+        self.young_bytes = 0;
+        self.remembered.clear();
+
+        match collection {
+            Collection::Minor => self.minor_collections += 1,
+            Collection::Major => {
+                self.major_collections += 1;
+                self.update_threshold();
+            },
+        }
+
+        before - self.len()
+    }
+
+    pub fn record_pause(&mut self, pause: Duration) {
+        self.last_pause = pause;
+        self.max_pause = std::cmp::max(self.max_pause, pause);
+        self.total_pause += pause;
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        let mut stats = HeapStats {
+            heap_bytes: self.bytes(),
+            young_bytes: self.young_bytes,
+            allocated_bytes: self.allocated,
+            minor_collections: self.minor_collections,
+            major_collections: self.major_collections,
+            last_pause: self.last_pause,
+            max_pause: self.max_pause,
+            total_pause: self.total_pause,
+            ..HeapStats::default()
+        };
+        for item in self.store.iter().chain(self.young.iter()) {
+            match *item {
+                HeapRef::String(_) => stats.strings += 1,
+                HeapRef::BigInt(_) => stats.bigints += 1,
+                HeapRef::Object(_) => stats.objects += 1,
+                HeapRef::Function(_) => stats.functions += 1,
+                HeapRef::Environment(_) => stats.environments += 1,
+                HeapRef::Cell(_) => stats.cells += 1,
+            }
+        }
+
+        stats
+    }
+}
+
+impl Drop for Allocator {
+    fn drop(&mut self) {
+        for item in self.store.drain(..).chain(self.young.drain(..)) {
+            unsafe { item.free() };
+        }
     }
 }
 
+
 #[derive(Debug)]
 pub struct Vm {
-    allocator: Allocator,
+    pub(crate) allocator: Allocator,
     modules: FxHashMap<String, Module>,
     // scripts: ByteCode,
     // tasks: Vec<Task>,
-    envs: Vec<Environment>,
-
-    // object_prototype: NonNull<Object>,
-    // function_prototype: NonNull<Object>,
+    pub(crate) global_env: NonNull<Environment>,
+    pub(crate) global: NonNull<Object>,
+    pub(crate) intrinsics: Intrinsics,
+    pub(crate) frames: Vec<Frame>,
+    /// The code of the Scripts that have been run, the constants below are keyed by their addresses.
+    pub(crate) scripts: Vec<Rc<Code>>,
+    /// String, BigInt and template object constants, created once for each code object.
+    pub(crate) constants: FxHashMap<(*const Code, u32), Value>,
+    /// The dispatch loops nested on the Rust stack.
+    pub(crate) native_depth: usize,
+    /// Descriptions of the symbols, indexed by their ids.
+    pub(crate) symbols: Vec<Option<String>>,
+    /// Values rooted by `Handle`s.
+    pub(crate) handles: Vec<Option<Value>>,
+    /// The code and the instruction offset where the exception being unwound was thrown.
+    pub(crate) throw_site: Option<(Rc<Code>, usize)>,
 }
 
 impl Vm {
     pub fn new() -> Self {
-        let allocator = Allocator::new();
+        let mut allocator = Allocator::new();
         let modules = FxHashMap::default();
-        let mut global_object = Object::global("global");
-        let global_value = Value::Object(global_object.as_raw_non_null());
+        let mut symbols = Vec::new();
 
-        let mut global_env = Environment::new(EnvironmentKind::Global);
-        global_env.insert("global", RecordKind::Const, global_value);
-        let envs = vec![ global_env ];
+        let intrinsics = Intrinsics::new(&mut allocator, &mut symbols);
+        let global = allocator.alloc(Object::with_prototype(intrinsics.object_prototype));
+        let global_env = allocator.alloc(Environment::new(EnvironmentKind::Global));
 
-        Vm {
+        let mut vm = Vm {
             allocator,
             modules,
-            envs,
-        }
+            global_env,
+            global,
+            intrinsics,
+            frames: Vec::new(),
+            scripts: Vec::new(),
+            constants: FxHashMap::default(),
+            native_depth: 0,
+            symbols,
+            handles: Vec::new(),
+            throw_site: None,
+        };
+        vm.install_globals();
+
+        let global_value = Value::Object(global);
+        unsafe { vm.global_env.as_mut() }.insert("global", RecordKind::Const, global_value)
+            .expect("global environment is empty");
+
+        vm
     }
 
     #[inline]
@@ -98,12 +490,393 @@ impl Vm {
         NonNull::new(self.as_mut_ptr()).unwrap()
     }
 
+    /// The environment of the running code.
     pub fn environment(&self) -> &Environment {
-        debug_assert!(self.envs.len() > 0);
-        &self.envs.last().unwrap()
+        let env = self.frames.last().map(|frame| frame.env).unwrap_or(self.global_env);
+        unsafe { env.as_ref() }
+    }
+
+    /// The global object.
+    #[inline]
+    pub fn global(&self) -> Value {
+        Value::Object(self.global)
+    }
+
+    #[inline]
+    pub fn allocator(&self) -> &Allocator {
+        &self.allocator
+    }
+
+    /// Limit the bytes allocated on the heap, see `Allocator::set_limit`.
+    pub fn set_heap_limit(&mut self, limit: Option<usize>) {
+        self.allocator.set_limit(limit);
     }
 
+    /// Decode a code object and run it as a Script, the code is verified before it runs.
     pub fn evaluate(&mut self, bytecode: &[u8]) -> Result<Value, NativeError> {
-        unimplemented!()
+        let mut pos = 0;
+        let code = CodeObject::decode(bytecode, &mut pos)
+            .map_err(|error| NativeError::internal_error(error.message()))?;
+        if pos != bytecode.len() {
+            return Err(NativeError::internal_error(format!("unexpected data at {} after the code object", pos)));
+        }
+        verifier::verify(&code).map_err(|error| NativeError::internal_error(error.message()))?;
+
+        self.run(code)
+    }
+
+    /// Run the code object of a Script, returns its completion value.
+    pub fn run(&mut self, code: CodeObject) -> Result<Value, NativeError> {
+        let code = Code::new(code);
+        self.scripts.push(code.clone());
+
+        let frame = Frame::new(code, None, Value::Object(self.global), Vec::new(), self.global_env);
+        let base = self.frames.len();
+        self.throw_site = None;
+        self.push_frame(frame)?;
+
+        match self.dispatch(base, None) {
+            Ok(Completion::Return(value)) => Ok(value),
+            Ok(Completion::Yield(_)) => Err(NativeError::internal_error("Scripts can not yield")),
+            Err(value) => Err(self.uncaught(value)),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn alloc<T: Allocation>(&mut self, value: T) -> NonNull<T> {
+        self.allocator.alloc(value)
+    }
+
+    pub fn new_string<S: Into<String>>(&mut self, s: S) -> Value {
+        Value::String(self.alloc(s.into()))
+    }
+
+    pub fn new_bigint(&mut self, n: BigInt) -> Value {
+        Value::BigInt(self.alloc(n))
+    }
+
+    /// A new ordinary object inheriting from `Object.prototype`.
+    pub fn new_object(&mut self) -> NonNull<Object> {
+        let prototype = self.intrinsics.object_prototype;
+        self.alloc(Object::with_prototype(prototype))
+    }
+
+    pub fn new_array(&mut self, values: Vec<Value>) -> Value {
+        let prototype = self.intrinsics.array_prototype;
+        let mut array = Object::with_kind(prototype, ObjectKind::Array);
+        let length = values.len();
+        for (index, value) in values.into_iter().enumerate() {
+            if !value.is_hole() {
+                array.insert(index as u32, Property::data(value));
+            }
+        }
+        array.insert("length", Property { enumerable: false, configurable: false, ..Property::data(length as i64) });
+
+        Value::Object(self.alloc(array))
+    }
+
+    /// The error object of a native error.
+    pub fn new_error(&mut self, error: &NativeError) -> Value {
+        let intrinsics = &self.intrinsics;
+        let (prototype, message) = match *error {
+            NativeError::Error(ref message) => (intrinsics.error_prototype, message.clone()),
+            NativeError::EarlySyntaxError { ref message, .. } => (intrinsics.syntax_error_prototype, message.clone()),
+            NativeError::SyntaxError(ref message) => (intrinsics.syntax_error_prototype, message.clone()),
+            NativeError::EvalError { ref native_error, .. } => (intrinsics.eval_error_prototype, format!("{}", native_error)),
+            NativeError::RangeError(ref message) => (intrinsics.range_error_prototype, message.clone()),
+            NativeError::ReferenceError(ref message) => (intrinsics.reference_error_prototype, message.clone()),
+            NativeError::TypeError(ref message) => (intrinsics.type_error_prototype, message.clone()),
+            NativeError::URIError(ref message) => (intrinsics.uri_error_prototype, message.clone()),
+            NativeError::InternalError(ref message) => (intrinsics.internal_error_prototype, message.clone()),
+            NativeError::Exception(ref value) => return value.clone(),
+        };
+
+        self.new_error_object(prototype, message)
+    }
+
+    pub(crate) fn new_error_object(&mut self, prototype: NonNull<Object>, message: String) -> Value {
+        let mut object = Object::with_kind(prototype, ObjectKind::Error);
+        if !message.is_empty() {
+            let message = self.new_string(message);
+            object.insert("message", Property::hidden(message));
+        }
+
+        Value::Object(self.alloc(object))
+    }
+
+    pub fn new_native_function(&mut self, name: &str, length: u32, native: NativeFunction) -> Value {
+        let prototype = self.intrinsics.function_prototype;
+        let mut object = self.alloc(Object::with_prototype(prototype));
+        let function = Function::new(name, FunctionCode::NativeCode(native), unsafe { object.as_mut() });
+        let function = self.alloc(function);
+
+        let name = self.new_string(name);
+        let object = unsafe { object.as_mut() };
+        object.kind = ObjectKind::Function(function);
+        object.insert("length", Property { configurable: true, ..Property::readonly(length as i64) });
+        object.insert("name", Property { configurable: true, ..Property::readonly(name) });
+
+        Value::Function(function)
+    }
+
+    pub fn new_symbol(&mut self, description: Option<String>) -> Symbol {
+        let symbol = Symbol::new(false, self.symbols.len());
+        self.symbols.push(description);
+        symbol
+    }
+
+    pub fn symbol_description(&self, symbol: Symbol) -> Option<&str> {
+        self.symbols.get(symbol.id()).and_then(|description| description.as_ref().map(|s| s.as_str()))
+    }
+
+    fn to_isolate_error(&self, error: NativeError) -> ecmascript::error::Error {
+        use ecmascript::error::{ Error, ErrorKind, };
+
+        let kind = match error {
+            NativeError::EarlySyntaxError { .. } | NativeError::SyntaxError(_) => ErrorKind::SyntaxError,
+            NativeError::EvalError { .. } => ErrorKind::EvalError,
+            NativeError::RangeError(_) => ErrorKind::RangeError,
+            NativeError::ReferenceError(_) => ErrorKind::ReferenceError,
+            NativeError::TypeError(_) => ErrorKind::TypeError,
+            NativeError::URIError(_) => ErrorKind::URIError,
+            // NOTE: `Error` 以及抛出的其它值没有对应的 ErrorKind
+            NativeError::Error(_) | NativeError::Exception(_) | NativeError::InternalError(_) => ErrorKind::InternalError,
+        };
+        let message = match error {
+            NativeError::EarlySyntaxError { message, .. } => message,
+            NativeError::SyntaxError(message) | NativeError::RangeError(message)
+            | NativeError::ReferenceError(message) | NativeError::TypeError(message)
+            | NativeError::URIError(message) | NativeError::InternalError(message) => message,
+            error => format!("{}", error),
+        };
+        Error::new(kind, message)
+    }
+
+    fn to_isolate_value(&self, value: Value) -> Result<ecmascript::vm::value::Value, ecmascript::error::Error> {
+        use ecmascript::error::{ Error, ErrorKind, };
+        use ecmascript::vm::value as js;
+
+        let value = match value {
+            Value::Undefined | Value::Hole => js::Value::Undefined(js::Undefined),
+            Value::Null => js::Value::Null(js::Null),
+            Value::Boolean(b) => js::Value::Boolean(js::Boolean(b)),
+            Value::I64(n) => js::Value::Number(js::Number(n as f64)),
+            Value::F64(n) => js::Value::Number(js::Number(n)),
+            Value::String(ptr) => js::Value::String(js::String::from(unsafe { ptr.as_ref() }.as_str())),
+            Value::Symbol(_) | Value::BigInt(_) => {
+                let message = format!("Cannot convert a {} value to the Isolate", value.type_of());
+                return Err(Error::new(ErrorKind::TypeError, message));
+            },
+            Value::Object(_) | Value::Function(_) => js::Value::Object(js::Object {
+                properties: Default::default(),
+                is_frozen: false,
+                is_sealed: false,
+                is_extensible: true,
+                kind: match value {
+                    Value::Function(_) => js::object::ObjectKind::Function,
+                    _ => js::object::ObjectKind::Normal,
+                },
+            }),
+        };
+
+        Ok(value)
+    }
+
+    /// The source location of the instruction which threw the last uncaught exception.
+    pub fn throw_location(&self) -> Option<Loc> {
+        self.throw_site.as_ref().and_then(|&(ref code, ip)| code.object.lines.find(ip as u32))
+    }
+
+    /// The error of an exception that was not caught by the script.
+    pub(crate) fn uncaught(&mut self, value: Value) -> NativeError {
+        let object = match value {
+            Value::Object(ptr) => ptr,
+            _ => return NativeError::Exception(value),
+        };
+        match unsafe { object.as_ref() }.kind {
+            ObjectKind::Error => { },
+            _ => return NativeError::Exception(value),
+        }
+
+        let name = self.get_data(object, &"name".into()).map(|name| self.display(&name)).unwrap_or_default();
+        let message = self.get_data(object, &"message".into()).map(|message| self.display(&message)).unwrap_or_default();
+        match name.as_str() {
+            "Error" => NativeError::Error(message),
+            "SyntaxError" => NativeError::SyntaxError(message),
+            "RangeError" => NativeError::RangeError(message),
+            "ReferenceError" => NativeError::ReferenceError(message),
+            "TypeError" => NativeError::TypeError(message),
+            "URIError" => NativeError::URIError(message),
+            "InternalError" => NativeError::InternalError(message),
+            _ => NativeError::Exception(value),
+        }
+    }
+}
+
+
+// NOTE: 返回值转换为 `ecmascript::vm::value::Value`：
+//       对象（包括函数）只保留种类，不复制属性；Symbol 与 BigInt 没有对应的值，转换时返回 TypeError。
+impl ecmascript::vm::isolate::Executor for Vm {
+    fn execute(&mut self, code: CodeObject) -> Result<ecmascript::vm::value::Value, ecmascript::error::Error> {
+        let value = self.run(code).map_err(|error| self.to_isolate_error(error))?;
+        self.to_isolate_value(value)
+    }
+
+    fn execute_source(&mut self, code: CodeObject, filename: &str, source: &str) -> Result<ecmascript::vm::value::Value, ecmascript::error::Error> {
+        let value = self.run(code).map_err(|error| {
+            let mut error = self.to_isolate_error(error);
+            if let Some(loc) = self.throw_location() {
+                error.set_location(filename, source, loc.start);
+            }
+            error
+        })?;
+        self.to_isolate_value(value)
+    }
+
+    fn heap_stats(&self) -> HeapStats {
+        Vm::heap_stats(self)
+    }
+}
+
+
+#[cfg(test)]
+fn run_script(source: &str) -> Result<ecmascript::vm::value::Value, ecmascript::error::Error> {
+    use ecmascript::vm::isolate::Isolate;
+
+    Isolate::new(Vm::new()).run_script(source)
+}
+
+/// Runs the prelude as is, then lowers the script to ES5 and runs it in the same isolate.
+#[cfg(test)]
+fn run_lowered_script(prelude: &str, source: &str) -> Result<ecmascript::vm::value::Value, ecmascript::error::Error> {
+    use toolshed::Arena;
+    use ecmascript::parser::Parser;
+    use ecmascript::ast::owned::{ ToOwnedAst, ToArenaAst, };
+    use ecmascript::compiler::bytecodegen;
+    use ecmascript::compiler::scope::Goal;
+    use ecmascript::compiler::transform::Transformer;
+    use ecmascript::version::ECMAScriptVersion;
+    use ecmascript::vm::isolate::Isolate;
+
+    let arena = Arena::new();
+    let code = arena.alloc_vec(source.chars().collect::<Vec<char>>());
+    let mut parser = Parser::new(&arena, code, "<script>");
+    parser.parse()?;
+
+    let mut body = parser.body.as_slice().to_owned_ast();
+    Transformer::new(ECMAScriptVersion::ES5).transform_program(&mut body)?;
+    let body = body.iter().map(|stmt| stmt.to_arena_ast(&arena)).collect::<Vec<_>>();
+    let code = bytecodegen::compile(&body, Goal::Script)?;
+
+    let isolate = Isolate::new(Vm::new());
+    isolate.run_script(prelude)?;
+    isolate.execute(code)
+}
+
+#[test]
+fn test_run_script() {
+    use ecmascript::error::ErrorKind;
+    use ecmascript::compiler::bytecode::{ Constant, Instruction, };
+    use ecmascript::vm::isolate::Executor;
+    use ecmascript::vm::value as js;
+
+    assert_eq!(run_script("1 + 1"), Ok(js::Value::Number(js::Number(2.0))));
+    assert_eq!(run_script("1 +").unwrap_err().kind(), ErrorKind::SyntaxError);
+    assert_eq!(run_script("\"a\" + 1"), Ok(js::Value::String(js::String::from("a1"))));
+    assert_eq!(run_script("typeof (x => x)"), Ok(js::Value::String(js::String::from("function"))));
+
+    // NOTE: 调用字节码函数不会递归调用解释器，栈溢出时抛出 RangeError
+    let error = run_script("(f => f(f))(f => f(f))").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::RangeError);
+    let error = run_script("null.x").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::TypeError);
+    assert!(format!("{:?}", error).contains(" --> <script>:1:0"));
+
+    // NOTE: 位置是最内层抛出异常的指令，被捕获的异常不影响之后的错误
+    let error = run_script("try { null.x } catch (e) { }\nvar f = () =>\n  undefined.y;\nf.call()").unwrap_err();
+    assert_eq!((error.filename(), error.line_number(), error.column_number()), ("<script>", 2, 2));
+
+    // NOTE: 对象不复制属性，Symbol 与 BigInt 不能转换
+    let object_kind = |source| match run_script(source) {
+        Ok(js::Value::Object(object)) if object.properties.is_empty() => Some(object.kind),
+        _ => None,
+    };
+    assert_eq!(object_kind("var o = Object(); o.a = 1; o"), Some(js::object::ObjectKind::Normal));
+    assert_eq!(object_kind("(x => x)"), Some(js::object::ObjectKind::Function));
+    assert_eq!(run_script("Symbol()").unwrap_err().kind(), ErrorKind::TypeError);
+
+    // NOTE: 解析器还不支持 BigInt 字面量，直接构造代码对象
+    let mut script = CodeObject::new("<script>");
+    let n = script.add_constant(Constant::BigInt("1".to_string()));
+    script.emit(Instruction::Const(n), Loc::default());
+    script.emit(Instruction::Return, Loc::default());
+    assert_eq!(Vm::new().execute(script).unwrap_err().kind(), ErrorKind::TypeError);
+}
+
+#[test]
+fn test_exponent_lowering() {
+    // NOTE: Vm 中没有 `Math` ，序言（不降级）用 `**` 实现 `Math.pow` ，对比的是降级前后的求值顺序与分组
+    let prelude = "Math = Object(); Math.pow = (a, b) => a ** b; o = Object(); o.a = Object(); o.a.v = 5";
+    for script in [
+        "(o.a.v **= 2, o.a.v)",
+        "(o.a.v **= 2, o.a.v **= 2, o.a.v)",
+        "(o.a.v **= 2) + o.a.v",
+        "x = 2 ** 3, x + 1",
+        "[2 ** 10, 2 ** -1][1]",
+        "2 ** 3 ** 2",
+    ].iter() {
+        let expected = run_script(&format!("{}; {}", prelude, script));
+        assert_eq!(run_lowered_script(prelude, script), expected, "{}", script);
+    }
+    assert_eq!(run_lowered_script(prelude, "(o.a.v **= 2, o.a.v)"), run_script("25"));
+}
+
+#[test]
+fn test_exception_unwinding() {
+    use ecmascript::compiler::bytecode::{ Constant, Handler, Instruction, };
+    use ecmascript::lexer::span::Loc;
+
+    let loc = Loc::default();
+
+    // function f() { return null.x; }
+    let mut f = CodeObject::new("f");
+    let x = f.add_string("x");
+    f.emit(Instruction::Null, loc);
+    let cache = f.cache();
+    f.emit(Instruction::GetNamed(x, cache), loc);
+    f.emit(Instruction::Return, loc);
+
+    // try { f(); } catch (e) { e.name }
+    let mut script = CodeObject::new("<script>");
+    let f = script.add_constant(Constant::Function(Box::new(f)));
+    let name = script.add_string("name");
+    let start = script.emit(Instruction::Closure(f), loc);
+    script.emit(Instruction::Undefined, loc);
+    script.emit(Instruction::Call(0), loc);
+    script.emit(Instruction::Return, loc);
+    let cache = script.cache();
+    let target = script.emit(Instruction::GetNamed(name, cache), loc);
+    script.emit(Instruction::Return, loc);
+    script.handlers.push(Handler { start, end: target, target, stack_depth: 0 });
+
+    let mut bytecode = Vec::new();
+    script.encode(&mut bytecode);
+
+    let mut vm = Vm::new();
+    let value = vm.evaluate(&bytecode).unwrap();
+    assert_eq!(format!("{}", value), "\"TypeError\"");
+    assert!(vm.frames.is_empty());
+
+    assert!(vm.evaluate(&bytecode[..bytecode.len() - 1]).is_err());
+
+    // NOTE: 未经检查的字节码会导致操作数栈下溢
+    let mut script = CodeObject::new("<script>");
+    script.emit(Instruction::Add, loc);
+    script.emit(Instruction::Return, loc);
+    let mut bytecode = Vec::new();
+    script.encode(&mut bytecode);
+    match vm.evaluate(&bytecode) {
+        Err(NativeError::InternalError(message)) => assert!(message.contains("stack underflow at 0")),
+        result => panic!("unexpected result {:?}", result),
     }
 }
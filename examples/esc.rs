@@ -6,19 +6,65 @@ extern crate log;
 extern crate env_logger;
 extern crate ecmascript;
 
+use ecmascript::compiler::scope::Goal;
+use ecmascript::compiler::disassembler::Disassembler;
+use ecmascript::vm::isolate::Isolate;
+
 use std::mem;
 use std::env;
+use std::fs;
+use std::process;
 use std::io::{ self, Read, };
 
 
+const USAGE: &str = "Usage: esc [--dump-bytecode] [--module] [FILE]";
+
 fn main() {
     env::set_var("RUST_LOG", "ecmascript=trace,esc=trace");
     env_logger::init();
 
-    let filename = "src/main.js";
-    let mut source = String::new();
-    io::stdin().read_to_string(&mut source).unwrap();
-    
+    let mut dump_bytecode = false;
+    let mut goal = Goal::Script;
+    let mut filename = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--dump-bytecode" => dump_bytecode = true,
+            "--module" => goal = Goal::Module,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            },
+        }
+    }
+
+    let (filename, source) = match filename {
+        Some(filename) => {
+            let source = fs::read_to_string(&filename).unwrap();
+            (filename, source)
+        },
+        None => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source).unwrap();
+            ("src/main.js".to_string(), source)
+        },
+    };
+
+    if dump_bytecode {
+        match Isolate::compile(&source, &filename, goal) {
+            Ok(code) => print!("{}", Disassembler::with_source(&source).disassemble(&code)),
+            Err(error) => {
+                eprintln!("{}: {}", filename, error.message());
+                process::exit(1);
+            },
+        }
+        return;
+    }
+
     // ecmascript::lexer::tokenize(&source, &filename);
     ecmascript::parser::parse(&source, &filename);
-}
\ No newline at end of file
+}
@@ -0,0 +1,289 @@
+// Owned AST builder
+//
+// NOTE: 变换过程中合成的节点没有对应的源代码位置，`Loc` 和 `Span` 均为默认值（`Loc::is_dummy`），
+//       代码生成时不会为它们产生 Source Map 映射。
+
+use crate::lexer::span::{ Loc, Span, };
+use crate::lexer::token::{ Keyword, LiteralNull, LiteralBoolean, };
+use crate::lexer::keyword::KeywordKind;
+use crate::lexer::operator::{ PrefixOperator, InfixOperator, PostfixOperator, AssignmentOperator, };
+use crate::ast::numberic::{ Numberic, Float, };
+use crate::ast::statement::{ LexicalDeclarationKind, EmptyStatement, };
+use crate::ast::owned::*;
+
+
+pub fn ident(name: &str) -> Identifier {
+    Identifier { loc: Loc::default(), span: Span::default(), raw: name.to_string(), cooked: None }
+}
+
+pub fn ident_expr(name: &str) -> Expression {
+    Expression::Identifier(Box::new(ident(name)))
+}
+
+pub fn this() -> Expression {
+    Expression::This(Box::new(Keyword { loc: Loc::default(), span: Span::default(), kind: KeywordKind::This }))
+}
+
+pub fn null() -> Expression {
+    Expression::Null(Box::new(LiteralNull { loc: Loc::default(), span: Span::default() }))
+}
+
+pub fn boolean(value: bool) -> Expression {
+    Expression::Boolean(Box::new(LiteralBoolean { loc: Loc::default(), span: Span::default(), value }))
+}
+
+/// `raw` is the content between the quotes, already escaped.
+pub fn string(raw: &str) -> Expression {
+    Expression::String(Box::new(LiteralString { loc: Loc::default(), span: Span::default(), raw: raw.to_string(), cooked: None }))
+}
+
+/// A string literal whose value is `value`, escaped as needed.
+pub fn string_value(value: &str) -> Expression {
+    let mut raw = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => raw.push_str("\\\\"),
+            '"' => raw.push_str("\\\""),
+            '\n' => raw.push_str("\\n"),
+            '\r' => raw.push_str("\\r"),
+            '\u{2028}' => raw.push_str("\\u2028"),
+            '\u{2029}' => raw.push_str("\\u2029"),
+            _ => raw.push(c),
+        }
+    }
+
+    string(&raw)
+}
+
+pub fn number(value: i64) -> Expression {
+    let raw = value.to_string();
+    Expression::Numeric(Box::new(LiteralNumeric { loc: Loc::default(), span: Span::default(), raw, value: Numberic::I64(value) }))
+}
+
+pub fn float(value: f64) -> Expression {
+    let raw = format!("{}", value);
+    Expression::Numeric(Box::new(LiteralNumeric { loc: Loc::default(), span: Span::default(), raw, value: Numberic::F64(Float(value)) }))
+}
+
+/// `void 0`
+pub fn undefined() -> Expression {
+    prefix(PrefixOperator::Void, number(0))
+}
+
+pub fn paren(items: Vec<Expression>) -> ParenthesizedExpression {
+    ParenthesizedExpression { loc: Loc::default(), span: Span::default(), items }
+}
+
+pub fn parenthesized(expr: Expression) -> Expression {
+    Expression::Parenthesized(Box::new(paren(vec![ expr ])))
+}
+
+pub fn member(object: Expression, name: &str) -> Expression {
+    Expression::Member(Box::new(MemberExpression {
+        loc: Loc::default(),
+        span: Span::default(),
+        left: object,
+        right: ident_expr(name),
+        computed: false,
+        optional: false,
+    }))
+}
+
+pub fn computed_member(object: Expression, property: Expression) -> Expression {
+    Expression::Member(Box::new(MemberExpression {
+        loc: Loc::default(),
+        span: Span::default(),
+        left: object,
+        right: property,
+        computed: true,
+        optional: false,
+    }))
+}
+
+pub fn call(callee: Expression, arguments: Vec<Expression>) -> Expression {
+    Expression::Call(Box::new(CallExpression { loc: Loc::default(), span: Span::default(), callee, arguments: paren(arguments), optional: false }))
+}
+
+pub fn new(callee: Expression, arguments: Vec<Expression>) -> Expression {
+    Expression::New(Box::new(NewExpression { loc: Loc::default(), span: Span::default(), callee, arguments: Some(paren(arguments)) }))
+}
+
+pub fn prefix(operator: PrefixOperator, operand: Expression) -> Expression {
+    Expression::Prefix(Box::new(PrefixExpression { loc: Loc::default(), span: Span::default(), operator, operand }))
+}
+
+pub fn postfix(operator: PostfixOperator, operand: Expression) -> Expression {
+    Expression::Postfix(Box::new(PostfixExpression { loc: Loc::default(), span: Span::default(), operator, operand }))
+}
+
+pub fn infix(left: Expression, operator: InfixOperator, right: Expression) -> Expression {
+    Expression::Infix(Box::new(InfixExpression { loc: Loc::default(), span: Span::default(), left, operator, right }))
+}
+
+pub fn assign(left: Expression, right: Expression) -> Expression {
+    assign_op(left, AssignmentOperator::Assign, right)
+}
+
+pub fn assign_op(left: Expression, operator: AssignmentOperator, right: Expression) -> Expression {
+    Expression::Assignment(Box::new(AssignmentExpression { loc: Loc::default(), span: Span::default(), left, operator, right }))
+}
+
+pub fn conditional(condition: Expression, and_then: Expression, or_else: Expression) -> Expression {
+    Expression::Conditional(Box::new(ConditionalExpression { loc: Loc::default(), span: Span::default(), condition, and_then, or_else }))
+}
+
+/// 只有一个元素时直接返回该元素。
+pub fn comma(mut items: Vec<Expression>) -> Expression {
+    if items.len() == 1 {
+        return items.remove(0);
+    }
+
+    Expression::Comma(Box::new(CommaExpression { loc: Loc::default(), span: Span::default(), items }))
+}
+
+pub fn array(elems: Vec<Expression>) -> Expression {
+    Expression::ArrayLiteral(Box::new(ArrayLiteral { loc: Loc::default(), span: Span::default(), elems: elems.into_iter().map(Some).collect() }))
+}
+
+pub fn object(properties: Vec<ObjectProperty>) -> Expression {
+    Expression::ObjectLiteral(Box::new(ObjectLiteral { loc: Loc::default(), span: Span::default(), properties }))
+}
+
+pub fn property(name: PropertyName, value: Expression) -> ObjectProperty {
+    ObjectProperty::Property { loc: Loc::default(), span: Span::default(), name, puct: colon(), value }
+}
+
+/// `key: value`，`key` 为合法的 IdentifierName。
+pub fn named_property(key: &str, value: Expression) -> ObjectProperty {
+    property(PropertyName::Identifier(ident(key)), value)
+}
+
+pub fn colon() -> crate::lexer::token::Punctuator {
+    crate::lexer::token::Punctuator {
+        loc: Loc::default(),
+        span: Span::default(),
+        kind: crate::lexer::punctuator::PunctuatorKind::Colon,
+    }
+}
+
+pub fn function(params: Vec<Expression>, body: Vec<Statement>) -> Function {
+    Function { loc: Loc::default(), span: Span::default(), params: paren(params), body }
+}
+
+pub fn function_expr(name: Option<&str>, params: Vec<Expression>, body: Vec<Statement>) -> Expression {
+    Expression::Function(Box::new(FunctionExpression {
+        loc: Loc::default(),
+        span: Span::default(),
+        is_async: false,
+        is_generator: false,
+        name: name.map(ident),
+        func: function(params, body),
+    }))
+}
+
+pub fn function_decl(name: &str, params: Vec<Expression>, body: Vec<Statement>) -> Statement {
+    Statement::Function(Box::new(FunctionDeclaration {
+        loc: Loc::default(),
+        span: Span::default(),
+        is_async: false,
+        is_generator: false,
+        name: ident(name),
+        func: function(params, body),
+    }))
+}
+
+/// `(function () { ... })( ...arguments )`
+pub fn iife(params: Vec<Expression>, body: Vec<Statement>, arguments: Vec<Expression>) -> Expression {
+    call(parenthesized(function_expr(None, params, body)), arguments)
+}
+
+
+pub fn expr_stmt(expr: Expression) -> Statement {
+    Statement::Expression(Box::new(expr))
+}
+
+pub fn empty() -> Statement {
+    Statement::Empty(Box::new(EmptyStatement { loc: Loc::default(), span: Span::default() }))
+}
+
+pub fn declarator(name: Expression, initializer: Option<Expression>) -> LexicalBinding {
+    LexicalBinding { loc: Loc::default(), span: Span::default(), name, initializer }
+}
+
+pub fn variable(kind: LexicalDeclarationKind, declarators: Vec<LexicalBinding>) -> Statement {
+    Statement::Variable(Box::new(VariableStatement { loc: Loc::default(), span: Span::default(), kind, declarators }))
+}
+
+/// `var name = init;`
+pub fn var(name: &str, initializer: Option<Expression>) -> Statement {
+    variable(LexicalDeclarationKind::Var, vec![ declarator(ident_expr(name), initializer) ])
+}
+
+pub fn block(body: Vec<Statement>) -> BlockStatement {
+    BlockStatement { loc: Loc::default(), span: Span::default(), body }
+}
+
+pub fn block_stmt(body: Vec<Statement>) -> Statement {
+    Statement::Block(Box::new(block(body)))
+}
+
+pub fn if_stmt(condition: Expression, and_then: Statement, or_else: Option<Statement>) -> Statement {
+    Statement::If(Box::new(IfStatement {
+        loc: Loc::default(),
+        span: Span::default(),
+        condition,
+        and_then,
+        or_else: or_else.unwrap_or_else(empty),
+    }))
+}
+
+pub fn return_stmt(value: Option<Expression>) -> Statement {
+    Statement::Return(Box::new(ReturnStatement { loc: Loc::default(), span: Span::default(), value }))
+}
+
+pub fn throw_stmt(value: Expression) -> Statement {
+    Statement::Throw(Box::new(ThrowStatement { loc: Loc::default(), span: Span::default(), value }))
+}
+
+pub fn break_stmt(label: Option<&str>) -> Statement {
+    Statement::Break(Box::new(BreakStatement { loc: Loc::default(), span: Span::default(), label: label.map(ident) }))
+}
+
+pub fn continue_stmt(label: Option<&str>) -> Statement {
+    Statement::Continue(Box::new(ContinueStatement { loc: Loc::default(), span: Span::default(), label: label.map(ident) }))
+}
+
+pub fn for_stmt(init: Option<Statement>, condition: Option<Expression>, finally: Option<Expression>, body: Statement) -> Statement {
+    Statement::For(Box::new(ForStatement { loc: Loc::default(), span: Span::default(), init, condition, finally, body }))
+}
+
+pub fn for_in_stmt(left: Expression, right: Expression, body: Statement) -> Statement {
+    Statement::ForIn(Box::new(ForInStatement { loc: Loc::default(), span: Span::default(), left, right, body }))
+}
+
+pub fn while_stmt(condition: Expression, body: Statement) -> Statement {
+    Statement::While(Box::new(WhileStatement { loc: Loc::default(), span: Span::default(), condition, body }))
+}
+
+/// `clauses` are `( test, body )`, `None` being the `default` clause.
+pub fn switch_stmt(value: Expression, clauses: Vec<(Option<Expression>, Vec<Statement>)>) -> Statement {
+    let clauses = clauses.into_iter()
+        .map(|(value, body)| SwitchStatementCaseClause { loc: Loc::default(), span: Span::default(), value, body: block_stmt(body) })
+        .collect();
+    Statement::Switch(Box::new(SwitchStatement { loc: Loc::default(), span: Span::default(), value, clauses }))
+}
+
+pub fn try_stmt(body: Vec<Statement>, catch: Option<(Expression, Vec<Statement>)>, finally: Option<Vec<Statement>>) -> Statement {
+    let (catch_parameter, catch_body) = match catch {
+        Some((param, body)) => (Some(param), Some(block(body))),
+        None => (None, None),
+    };
+    Statement::Try(Box::new(TryStatement {
+        loc: Loc::default(),
+        span: Span::default(),
+        body: block(body),
+        catch_parameter,
+        catch_body,
+        finally: finally.map(block),
+    }))
+}
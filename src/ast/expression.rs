@@ -427,6 +427,8 @@ pub struct MemberExpression<'ast> {
     pub left: Expression<'ast>,
     pub right: Expression<'ast>,
     pub computed: bool,
+    // a?.b
+    pub optional: bool,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -451,6 +453,8 @@ pub struct CallExpression<'ast> {
     pub callee: Expression<'ast>,
     // TODO:
     pub arguments: ParenthesizedExpression<'ast>,
+    // a?.()
+    pub optional: bool,
 }
 
 
@@ -8,6 +8,10 @@ pub mod expression;
 pub mod function;
 pub mod class;
 pub mod pattern;
+pub mod owned;
+pub mod node;
+pub mod visit;
+pub mod builder;
 
 // pub mod declaration;
 // pub mod module;
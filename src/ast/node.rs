@@ -0,0 +1,725 @@
+use crate::lexer::span::{ Loc, Span, LineColumn, };
+use crate::lexer::token::Identifier;
+use crate::ast::statement::{ Statement, BlockStatement, LexicalBinding, SwitchStatementCaseClause, };
+use crate::ast::expression::Expression;
+use crate::ast::function::{ Function, ConciseBody, };
+use crate::ast::class::{ Class, MethodDefinition, };
+use crate::ast::pattern::{
+    PropertyName, ObjectProperty,
+    BindingPattern, BindingElement, BindingProperty, BindingRestElement,
+    AssignmentElement, AssignmentProperty, AssignmentPattern,
+};
+use crate::ast::jsx::{
+    JSXElement, JSXFragment, JSXElementName, JSXAttribute, JSXNormalAttribute, JSXNormalAttributeName,
+    JSXNormalAttributeInitializer, JSXChild, JSXChildren, JSXAttributes,
+};
+
+use std::collections::HashMap;
+
+
+// NOTE: NodeId 按照前序遍历的顺序分配，父节点的 NodeId 总是小于子节点。
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct NodeId(pub u32);
+
+impl NodeId {
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+
+/// A reference to any node of the AST.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Node<'ast> {
+    Statement(Statement<'ast>),
+    Expression(Expression<'ast>),
+    // NOTE: 不作为表达式出现的标识符，例如函数名、标签、属性名
+    Identifier(&'ast Identifier<'ast>),
+    Block(&'ast BlockStatement<'ast>),
+    LexicalBinding(&'ast LexicalBinding<'ast>),
+    SwitchCase(&'ast SwitchStatementCaseClause<'ast>),
+    Function(&'ast Function<'ast>),
+    Class(&'ast Class<'ast>),
+    MethodDefinition(&'ast MethodDefinition<'ast>),
+    PropertyName(&'ast PropertyName<'ast>),
+    ObjectProperty(&'ast ObjectProperty<'ast>),
+    BindingPattern(&'ast BindingPattern<'ast>),
+    BindingElement(&'ast BindingElement<'ast>),
+    BindingProperty(&'ast BindingProperty<'ast>),
+    AssignmentElement(&'ast AssignmentElement<'ast>),
+    AssignmentProperty(&'ast AssignmentProperty<'ast>),
+    JSXElement(&'ast JSXElement<'ast>),
+    JSXFragment(&'ast JSXFragment<'ast>),
+    JSXAttribute(&'ast JSXNormalAttribute<'ast>),
+}
+
+#[inline]
+fn addr<T>(item: &T) -> usize {
+    item as *const T as usize
+}
+
+#[inline]
+fn join(start: Loc, end: Loc) -> Loc {
+    Loc { start: start.start, end: end.end }
+}
+
+impl<'ast> Node<'ast> {
+    pub fn loc(&self) -> Loc {
+        match *self {
+            Node::Statement(inner) => inner.loc(),
+            Node::Expression(inner) => inner.loc(),
+            Node::Identifier(inner) => inner.loc,
+            Node::Block(inner) => inner.loc,
+            Node::LexicalBinding(inner) => inner.loc,
+            Node::SwitchCase(inner) => inner.loc,
+            Node::Function(inner) => inner.loc,
+            Node::Class(inner) => inner.loc,
+            Node::MethodDefinition(inner) => inner.loc(),
+            Node::PropertyName(inner) => inner.loc(),
+            Node::ObjectProperty(inner) => match *inner {
+                ObjectProperty::Identifier(ref ident) => ident.loc,
+                ObjectProperty::Property { loc, .. } => loc,
+                ObjectProperty::MethodDefinition(ref method) => method.loc(),
+                ObjectProperty::Spread { ref puct, ref target } => join(puct.loc, target.loc()),
+            },
+            Node::BindingPattern(inner) => inner.loc(),
+            Node::BindingElement(inner) => match *inner {
+                BindingElement::SingleNameBinding { loc, .. } => loc,
+                BindingElement::BindingPattern { loc, .. } => loc,
+            },
+            Node::BindingProperty(inner) => match *inner {
+                BindingProperty::SingleNameBinding { loc, .. } => loc,
+                BindingProperty::Property { loc, .. } => loc,
+                BindingProperty::Spread { loc, .. } => loc,
+            },
+            Node::AssignmentElement(inner) => inner.loc,
+            Node::AssignmentProperty(inner) => match *inner {
+                AssignmentProperty::Identifier { loc, .. } => loc,
+                AssignmentProperty::Property { loc, .. } => loc,
+                AssignmentProperty::Spread { loc, .. } => loc,
+            },
+            Node::JSXElement(inner) => inner.loc(),
+            Node::JSXFragment(inner) => inner.loc(),
+            Node::JSXAttribute(inner) => inner.loc,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match *self {
+            Node::Statement(inner) => inner.span(),
+            Node::Expression(inner) => inner.span(),
+            Node::Identifier(inner) => inner.span,
+            Node::Block(inner) => inner.span,
+            Node::LexicalBinding(inner) => inner.span,
+            Node::SwitchCase(inner) => inner.span,
+            Node::Function(inner) => inner.span,
+            Node::Class(inner) => inner.span,
+            Node::MethodDefinition(inner) => inner.span(),
+            Node::PropertyName(inner) => inner.span(),
+            Node::ObjectProperty(inner) => match *inner {
+                ObjectProperty::Identifier(ref ident) => ident.span,
+                ObjectProperty::Property { span, .. } => span,
+                ObjectProperty::MethodDefinition(ref method) => method.span(),
+                ObjectProperty::Spread { ref puct, ref target } => Span { start: puct.span.start, end: target.span().end },
+            },
+            Node::BindingPattern(inner) => inner.span(),
+            Node::BindingElement(inner) => match *inner {
+                BindingElement::SingleNameBinding { span, .. } => span,
+                BindingElement::BindingPattern { span, .. } => span,
+            },
+            Node::BindingProperty(inner) => match *inner {
+                BindingProperty::SingleNameBinding { span, .. } => span,
+                BindingProperty::Property { span, .. } => span,
+                BindingProperty::Spread { span, .. } => span,
+            },
+            Node::AssignmentElement(inner) => inner.span,
+            Node::AssignmentProperty(inner) => match *inner {
+                AssignmentProperty::Identifier { span, .. } => span,
+                AssignmentProperty::Property { span, .. } => span,
+                AssignmentProperty::Spread { span, .. } => span,
+            },
+            Node::JSXElement(inner) => inner.span(),
+            Node::JSXFragment(inner) => inner.span(),
+            Node::JSXAttribute(inner) => inner.span,
+        }
+    }
+
+    /// Identity of the node: its kind plus the address of the arena allocation it refers to.
+    fn key(&self) -> (u8, usize) {
+        macro_rules! inner_addr {
+            ($value:expr, $kind:ident, [ $($variant:ident),* ]) => {
+                match $value {
+                    $( $kind::$variant(inner) => addr(inner), )*
+                }
+            };
+        }
+
+        match *self {
+            Node::Statement(inner) => (0, inner_addr!(inner, Statement, [
+                Empty, Debugger, Expression, Variable, Function, Class, Block, If,
+                DoWhile, While, For, ForIn, ForOf, ForAwaitOf,
+                Continue, Break, Return, With, Switch, Labelled, Throw, Try
+            ])),
+            Node::Expression(inner) => (1, inner_addr!(inner, Expression, [
+                This, Spread, Super, Identifier, Null, Boolean, String, Numeric, RegularExpression, Template,
+                ArrayLiteral, ObjectLiteral, Function, ArrowFunction, Class, Parenthesized, Member,
+                TaggedTemplate, NewTarget, Call, New, Prefix, Infix, Postfix, Assignment, Conditional,
+                Yield, Comma, AssignmentPattern, BindingPattern, JSXFragment, JSXElement
+            ])),
+            Node::Identifier(inner) => (2, addr(inner)),
+            Node::Block(inner) => (3, addr(inner)),
+            Node::LexicalBinding(inner) => (4, addr(inner)),
+            Node::SwitchCase(inner) => (5, addr(inner)),
+            Node::Function(inner) => (6, addr(inner)),
+            Node::Class(inner) => (7, addr(inner)),
+            Node::MethodDefinition(inner) => (8, addr(inner)),
+            Node::PropertyName(inner) => (9, addr(inner)),
+            Node::ObjectProperty(inner) => (10, addr(inner)),
+            Node::BindingPattern(inner) => (11, addr(inner)),
+            Node::BindingElement(inner) => (12, addr(inner)),
+            Node::BindingProperty(inner) => (13, addr(inner)),
+            Node::AssignmentElement(inner) => (14, addr(inner)),
+            Node::AssignmentProperty(inner) => (15, addr(inner)),
+            Node::JSXElement(inner) => (16, addr(inner)),
+            Node::JSXFragment(inner) => (17, addr(inner)),
+            Node::JSXAttribute(inner) => (18, addr(inner)),
+        }
+    }
+
+    /// Direct children, in source order.
+    pub fn children(&self) -> Vec<Node<'ast>> {
+        let mut children = Vec::new();
+
+        match *self {
+            Node::Statement(stmt) => statement_children(stmt, &mut children),
+            Node::Expression(expr) => expression_children(expr, &mut children),
+            Node::Identifier(_) => { },
+            Node::Block(inner) => {
+                children.extend(inner.body.iter().map(|stmt| Node::Statement(*stmt)));
+            },
+            Node::LexicalBinding(inner) => {
+                children.push(Node::Expression(inner.name));
+                if let Some(init) = inner.initializer {
+                    children.push(Node::Expression(init));
+                }
+            },
+            Node::SwitchCase(inner) => {
+                if let Some(value) = inner.value {
+                    children.push(Node::Expression(value));
+                }
+                match inner.body {
+                    Statement::Block(block) => children.extend(block.body.iter().map(|stmt| Node::Statement(*stmt))),
+                    stmt => children.push(Node::Statement(stmt)),
+                }
+            },
+            Node::Function(inner) => {
+                children.extend(inner.params.items.iter().map(|item| Node::Expression(*item)));
+                children.extend(inner.body.iter().map(|stmt| Node::Statement(*stmt)));
+            },
+            Node::Class(inner) => {
+                if let Some(heritage) = inner.heritage {
+                    children.push(Node::Expression(heritage));
+                }
+                children.extend(inner.body.iter().map(|method| Node::MethodDefinition(&method.method)));
+            },
+            Node::MethodDefinition(inner) => {
+                let (name, params, body) = match *inner {
+                    MethodDefinition::Method(ref method) => (&method.name, Some(&method.params), method.body),
+                    MethodDefinition::Getter(ref getter) => (&getter.name, None, getter.body),
+                    MethodDefinition::Setter(ref setter) => (&setter.name, Some(&setter.params), setter.body),
+                };
+                children.push(Node::Expression(*name));
+                if let Some(params) = params {
+                    children.extend(params.items.iter().map(|item| Node::Expression(*item)));
+                }
+                children.extend(body.iter().map(|stmt| Node::Statement(*stmt)));
+            },
+            Node::PropertyName(inner) => match *inner {
+                PropertyName::Identifier(ref ident) => children.push(Node::Identifier(ident)),
+                PropertyName::Computed(expr) => children.push(Node::Expression(expr)),
+                _ => { },
+            },
+            Node::ObjectProperty(inner) => match *inner {
+                ObjectProperty::Identifier(ref ident) => children.push(Node::Identifier(ident)),
+                ObjectProperty::Property { ref name, value, .. } => {
+                    children.push(Node::PropertyName(name));
+                    children.push(Node::Expression(value));
+                },
+                ObjectProperty::MethodDefinition(ref method) => children.push(Node::MethodDefinition(method)),
+                ObjectProperty::Spread { target, .. } => children.push(Node::Expression(target)),
+            },
+            Node::BindingPattern(inner) => binding_pattern_children(inner, &mut children),
+            Node::BindingElement(inner) => match *inner {
+                BindingElement::SingleNameBinding { ref name, init, .. } => {
+                    children.push(Node::Identifier(name));
+                    if let Some(init) = init {
+                        children.push(Node::Expression(init));
+                    }
+                },
+                BindingElement::BindingPattern { ref pattern, init, .. } => {
+                    children.push(Node::BindingPattern(pattern));
+                    if let Some(init) = init {
+                        children.push(Node::Expression(init));
+                    }
+                },
+            },
+            Node::BindingProperty(inner) => match *inner {
+                BindingProperty::SingleNameBinding { ref name, init, .. } => {
+                    children.push(Node::Identifier(name));
+                    if let Some(init) = init {
+                        children.push(Node::Expression(init));
+                    }
+                },
+                BindingProperty::Property { ref name, ref value, .. } => {
+                    children.push(Node::PropertyName(name));
+                    children.push(Node::BindingElement(value));
+                },
+                BindingProperty::Spread { ref name, .. } => children.push(Node::Identifier(name)),
+            },
+            Node::AssignmentElement(inner) => {
+                children.push(Node::Expression(inner.elem));
+                if let Some(init) = inner.init {
+                    children.push(Node::Expression(init));
+                }
+            },
+            Node::AssignmentProperty(inner) => match *inner {
+                AssignmentProperty::Identifier { ref name, init, .. } => {
+                    children.push(Node::Identifier(name));
+                    if let Some(init) = init {
+                        children.push(Node::Expression(init));
+                    }
+                },
+                AssignmentProperty::Property { ref name, ref value, .. } => {
+                    children.push(Node::PropertyName(name));
+                    children.push(Node::AssignmentElement(value));
+                },
+                AssignmentProperty::Spread { target, .. } => children.push(Node::Expression(target)),
+            },
+            Node::JSXElement(inner) => match *inner {
+                JSXElement::SelfClosing(ref elem) => {
+                    jsx_name_children(&elem.name, &mut children);
+                    jsx_attributes_children(elem.attrs, &mut children);
+                },
+                JSXElement::Normal(ref elem) => {
+                    jsx_name_children(&elem.opening.name, &mut children);
+                    jsx_attributes_children(elem.opening.attrs, &mut children);
+                    jsx_children_children(elem.children, &mut children);
+                    jsx_name_children(&elem.closing.name, &mut children);
+                },
+            },
+            Node::JSXFragment(inner) => jsx_children_children(inner.children, &mut children),
+            Node::JSXAttribute(inner) => {
+                match inner.name {
+                    JSXNormalAttributeName::Identifier(ref ident) => children.push(Node::Identifier(ident)),
+                    JSXNormalAttributeName::NamespacedName(ref name) => {
+                        children.push(Node::Identifier(&name.namespace));
+                        children.push(Node::Identifier(&name.name));
+                    },
+                }
+                match inner.init {
+                    Some(JSXNormalAttributeInitializer::Assignment(expr)) => children.push(Node::Expression(expr)),
+                    Some(JSXNormalAttributeInitializer::Element(ref elem)) => children.push(Node::JSXElement(elem)),
+                    Some(JSXNormalAttributeInitializer::Fragment(ref frag)) => children.push(Node::JSXFragment(frag)),
+                    _ => { },
+                }
+            },
+        }
+
+        children
+    }
+}
+
+fn statement_children<'ast>(stmt: Statement<'ast>, children: &mut Vec<Node<'ast>>) {
+    match stmt {
+        Statement::Empty(_) | Statement::Debugger(_) => { },
+        Statement::Expression(inner) => children.push(Node::Expression(*inner)),
+        Statement::Variable(inner) => {
+            children.extend(inner.declarators.iter().map(|declarator| Node::LexicalBinding(declarator)));
+        },
+        Statement::Function(inner) => {
+            children.push(Node::Identifier(&inner.name));
+            children.push(Node::Function(&inner.func));
+        },
+        Statement::Class(inner) => {
+            children.push(Node::Identifier(&inner.name));
+            children.push(Node::Class(&inner.class));
+        },
+        Statement::Block(inner) => children.extend(inner.body.iter().map(|stmt| Node::Statement(*stmt))),
+        Statement::If(inner) => {
+            children.push(Node::Expression(inner.condition));
+            children.push(Node::Statement(inner.and_then));
+            if let Statement::Empty(_) = inner.or_else {
+                // NOTE: 没有 else 分支
+            } else {
+                children.push(Node::Statement(inner.or_else));
+            }
+        },
+        Statement::DoWhile(inner) => {
+            children.push(Node::Statement(inner.body));
+            children.push(Node::Expression(inner.condition));
+        },
+        Statement::While(inner) => {
+            children.push(Node::Expression(inner.condition));
+            children.push(Node::Statement(inner.body));
+        },
+        Statement::For(inner) => {
+            if let Some(init) = inner.init {
+                children.push(Node::Statement(init));
+            }
+            if let Some(condition) = inner.condition {
+                children.push(Node::Expression(condition));
+            }
+            if let Some(finally) = inner.finally {
+                children.push(Node::Expression(finally));
+            }
+            children.push(Node::Statement(inner.body));
+        },
+        Statement::ForIn(inner) => {
+            children.push(Node::Expression(inner.left));
+            children.push(Node::Expression(inner.right));
+            children.push(Node::Statement(inner.body));
+        },
+        Statement::ForOf(inner) => {
+            children.push(Node::Expression(inner.left));
+            children.push(Node::Expression(inner.right));
+            children.push(Node::Statement(inner.body));
+        },
+        Statement::ForAwaitOf(inner) => {
+            children.push(Node::Expression(inner.left));
+            children.push(Node::Expression(inner.right));
+            children.push(Node::Statement(inner.body));
+        },
+        Statement::Continue(inner) => {
+            if let Some(ref label) = inner.label {
+                children.push(Node::Identifier(label));
+            }
+        },
+        Statement::Break(inner) => {
+            if let Some(ref label) = inner.label {
+                children.push(Node::Identifier(label));
+            }
+        },
+        Statement::Return(inner) => {
+            if let Some(value) = inner.value {
+                children.push(Node::Expression(value));
+            }
+        },
+        Statement::With(inner) => {
+            children.push(Node::Expression(inner.condition));
+            children.push(Node::Statement(inner.then));
+        },
+        Statement::Switch(inner) => {
+            children.push(Node::Expression(inner.value));
+            children.extend(inner.clauses.iter().map(|clause| Node::SwitchCase(clause)));
+        },
+        Statement::Labelled(inner) => {
+            children.push(Node::Identifier(&inner.label));
+            children.push(Node::Statement(inner.item));
+        },
+        Statement::Throw(inner) => children.push(Node::Expression(inner.value)),
+        Statement::Try(inner) => {
+            children.push(Node::Block(&inner.body));
+            if let Some(param) = inner.catch_parameter {
+                children.push(Node::Expression(param));
+            }
+            if let Some(ref catch_body) = inner.catch_body {
+                children.push(Node::Block(catch_body));
+            }
+            if let Some(ref finally) = inner.finally {
+                children.push(Node::Block(finally));
+            }
+        },
+    }
+}
+
+fn expression_children<'ast>(expr: Expression<'ast>, children: &mut Vec<Node<'ast>>) {
+    match expr {
+        Expression::This(_) | Expression::Super(_) | Expression::Identifier(_)
+        | Expression::Null(_) | Expression::Boolean(_) | Expression::String(_)
+        | Expression::Numeric(_) | Expression::RegularExpression(_) | Expression::NewTarget(_) => { },
+        Expression::Template(inner) => children.extend(inner.bounds.iter().map(|bound| Node::Expression(*bound))),
+        Expression::TaggedTemplate(inner) => {
+            children.push(Node::Expression(inner.tag));
+            children.extend(inner.template.bounds.iter().map(|bound| Node::Expression(*bound)));
+        },
+        Expression::Spread(inner) => children.push(Node::Expression(inner.item)),
+        Expression::ArrayLiteral(inner) => {
+            children.extend(inner.elems.iter().filter_map(|elem| elem.map(Node::Expression)));
+        },
+        Expression::ObjectLiteral(inner) => {
+            children.extend(inner.properties.iter().map(|prop| Node::ObjectProperty(prop)));
+        },
+        Expression::Function(inner) => {
+            if let Some(ref name) = inner.name {
+                children.push(Node::Identifier(name));
+            }
+            children.push(Node::Function(&inner.func));
+        },
+        Expression::ArrowFunction(inner) => {
+            children.push(Node::Expression(inner.params));
+            match inner.body {
+                ConciseBody::Expr(body) => children.push(Node::Expression(body)),
+                ConciseBody::Stmt(body) => children.extend(body.iter().map(|stmt| Node::Statement(*stmt))),
+            }
+        },
+        Expression::Class(inner) => {
+            if let Some(ref name) = inner.name {
+                children.push(Node::Identifier(name));
+            }
+            children.push(Node::Class(&inner.class));
+        },
+        Expression::Parenthesized(inner) => children.extend(inner.items.iter().map(|item| Node::Expression(*item))),
+        Expression::Comma(inner) => children.extend(inner.items.iter().map(|item| Node::Expression(*item))),
+        Expression::Member(inner) => {
+            children.push(Node::Expression(inner.left));
+            children.push(Node::Expression(inner.right));
+        },
+        Expression::Call(inner) => {
+            children.push(Node::Expression(inner.callee));
+            children.extend(inner.arguments.items.iter().map(|item| Node::Expression(*item)));
+        },
+        Expression::New(inner) => {
+            children.push(Node::Expression(inner.callee));
+            if let Some(ref arguments) = inner.arguments {
+                children.extend(arguments.items.iter().map(|item| Node::Expression(*item)));
+            }
+        },
+        Expression::Prefix(inner) => children.push(Node::Expression(inner.operand)),
+        Expression::Postfix(inner) => children.push(Node::Expression(inner.operand)),
+        Expression::Infix(inner) => {
+            children.push(Node::Expression(inner.left));
+            children.push(Node::Expression(inner.right));
+        },
+        Expression::Assignment(inner) => {
+            children.push(Node::Expression(inner.left));
+            children.push(Node::Expression(inner.right));
+        },
+        Expression::Conditional(inner) => {
+            children.push(Node::Expression(inner.condition));
+            children.push(Node::Expression(inner.and_then));
+            children.push(Node::Expression(inner.or_else));
+        },
+        Expression::Yield(inner) => children.push(Node::Expression(inner.item)),
+        Expression::AssignmentPattern(inner) => match *inner {
+            AssignmentPattern::Object(ref pattern) => {
+                children.extend(pattern.properties.iter().map(|prop| Node::AssignmentProperty(prop)));
+            },
+            AssignmentPattern::Array(ref pattern) => {
+                children.extend(pattern.elems.iter().filter_map(|elem| elem.as_ref().map(Node::AssignmentElement)));
+                if let Some(rest) = pattern.rest_elem {
+                    children.push(Node::Expression(rest));
+                }
+            },
+        },
+        Expression::BindingPattern(inner) => binding_pattern_children(inner, children),
+        Expression::JSXFragment(inner) => jsx_children_children(inner.children, children),
+        Expression::JSXElement(inner) => children.push(Node::JSXElement(inner)),
+    }
+}
+
+fn binding_pattern_children<'ast>(pattern: &'ast BindingPattern<'ast>, children: &mut Vec<Node<'ast>>) {
+    match *pattern {
+        BindingPattern::Object(ref inner) => {
+            children.extend(inner.properties.iter().map(|prop| Node::BindingProperty(prop)));
+        },
+        BindingPattern::Array(ref inner) => {
+            children.extend(inner.elems.iter().filter_map(|elem| elem.as_ref().map(Node::BindingElement)));
+            match inner.rest_elem {
+                Some(BindingRestElement::Identifier(ref ident)) => children.push(Node::Identifier(ident)),
+                Some(BindingRestElement::BindingPattern(ref pattern)) => children.push(Node::BindingPattern(pattern)),
+                None => { },
+            }
+        },
+    }
+}
+
+fn jsx_name_children<'ast>(name: &'ast JSXElementName<'ast>, children: &mut Vec<Node<'ast>>) {
+    match *name {
+        JSXElementName::Identifier(ref ident) => children.push(Node::Identifier(ident)),
+        JSXElementName::NamespacedName(ref name) => {
+            children.push(Node::Identifier(&name.namespace));
+            children.push(Node::Identifier(&name.name));
+        },
+        JSXElementName::MemberExpression(idents) => children.extend(idents.iter().map(Node::Identifier)),
+    }
+}
+
+fn jsx_attributes_children<'ast>(attrs: Option<JSXAttributes<'ast>>, children: &mut Vec<Node<'ast>>) {
+    for attr in attrs.unwrap_or(&[]).iter() {
+        match *attr {
+            JSXAttribute::Normal(ref attr) => children.push(Node::JSXAttribute(attr)),
+            JSXAttribute::Spread(expr) => children.push(Node::Expression(expr)),
+        }
+    }
+}
+
+fn jsx_children_children<'ast>(items: Option<JSXChildren<'ast>>, children: &mut Vec<Node<'ast>>) {
+    for child in items.unwrap_or(&[]).iter() {
+        match *child {
+            JSXChild::Text(_) => { },
+            JSXChild::Element(ref elem) => children.push(Node::JSXElement(elem)),
+            JSXChild::ChildExpression(exprs) => {
+                children.extend(exprs.unwrap_or(&[]).iter().map(|expr| Node::Expression(*expr)));
+            },
+        }
+    }
+}
+
+
+/// `NodeId` side table: every node of a parsed program, its parent and its children.
+#[derive(Debug, Default)]
+pub struct NodeTable<'ast> {
+    nodes: Vec<Node<'ast>>,
+    parents: Vec<Option<NodeId>>,
+    children: Vec<Vec<NodeId>>,
+    roots: Vec<NodeId>,
+    ids: HashMap<(u8, usize), NodeId>,
+}
+
+impl<'ast> NodeTable<'ast> {
+    pub fn new(body: &[Statement<'ast>]) -> Self {
+        let mut table = NodeTable::default();
+        for stmt in body.iter() {
+            let id = table.insert(Node::Statement(*stmt), None);
+            table.roots.push(id);
+        }
+
+        table
+    }
+
+    fn insert(&mut self, node: Node<'ast>, parent: Option<NodeId>) -> NodeId {
+        let id = NodeId(self.nodes.len() as u32);
+        self.nodes.push(node);
+        self.parents.push(parent);
+        self.children.push(Vec::new());
+        self.ids.insert(node.key(), id);
+
+        for child in node.children() {
+            let child_id = self.insert(child, Some(id));
+            self.children[id.index()].push(child_id);
+        }
+
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn node(&self, id: NodeId) -> Node<'ast> {
+        self.nodes[id.index()]
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.parents[id.index()]
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.children[id.index()]
+    }
+
+    /// The `NodeId` of a node, if it belongs to this table.
+    pub fn id(&self, node: Node<'ast>) -> Option<NodeId> {
+        self.ids.get(&node.key()).cloned()
+    }
+
+    /// Parent, grand parent, ... up to the root statement.
+    pub fn ancestors(&self, id: NodeId) -> Vec<NodeId> {
+        let mut ancestors = Vec::new();
+        let mut current = self.parent(id);
+        while let Some(parent) = current {
+            ancestors.push(parent);
+            current = self.parent(parent);
+        }
+
+        ancestors
+    }
+
+    fn innermost<F: Fn(&Node<'ast>) -> bool>(&self, covers: F) -> Option<NodeId> {
+        let mut found = None;
+        let mut candidates = &self.roots[..];
+
+        'descend: loop {
+            for id in candidates.iter() {
+                let node = self.nodes[id.index()];
+                if !node.loc().is_dummy() && covers(&node) {
+                    found = Some(*id);
+                    candidates = &self.children[id.index()];
+                    continue 'descend;
+                }
+            }
+
+            return found;
+        }
+    }
+
+    /// The innermost node whose range contains `offset` ( both ends inclusive, so a cursor right after a token still hits it ).
+    pub fn node_at(&self, offset: usize) -> Option<NodeId> {
+        self.innermost(|node| {
+            let loc = node.loc();
+            loc.start <= offset && offset <= loc.end
+        })
+    }
+
+    /// Same as `node_at`, but using the line and column of `pos`.
+    pub fn node_at_line_column(&self, pos: LineColumn) -> Option<NodeId> {
+        let target = (pos.line, pos.column);
+        self.innermost(|node| {
+            let span = node.span();
+            (span.start.line, span.start.column) <= target && target <= (span.end.line, span.end.column)
+        })
+    }
+}
+
+
+#[test]
+fn test_node_table() {
+    use crate::toolshed::Arena;
+    use crate::parser::Parser;
+
+    let source = "function foo(a) { a.b(c); }";
+    let arena = Arena::new();
+    let code = arena.alloc_vec(source.chars().collect::<Vec<char>>());
+    let mut parser = Parser::new(&arena, &code, "main.js");
+    parser.parse().unwrap();
+
+    let nodes = &parser.nodes;
+    assert_eq!(nodes.roots().len(), 1);
+    assert_eq!(nodes.parent(nodes.roots()[0]), None);
+
+    // `c`
+    let id = nodes.node_at(23).unwrap();
+    match nodes.node(id) {
+        Node::Expression(Expression::Identifier(ident)) => assert_eq!(ident.raw, &['c']),
+        node => panic!("unexpected node {:?}", node),
+    }
+    assert_eq!(nodes.id(nodes.node(id)), Some(id));
+
+    // c -> a.b(c) -> `a.b(c);` -> Function -> FunctionDeclaration
+    let ancestors = nodes.ancestors(id);
+    match nodes.node(ancestors[0]) {
+        Node::Expression(Expression::Call(_)) => { },
+        node => panic!("unexpected node {:?}", node),
+    }
+    assert_eq!(*ancestors.last().unwrap(), nodes.roots()[0]);
+    assert!(ancestors.windows(2).all(|pair| pair[0] > pair[1]));
+
+    // `foo`
+    let id = nodes.node_at(10).unwrap();
+    match nodes.node(id) {
+        Node::Identifier(ident) => assert_eq!(ident.raw, &['f', 'o', 'o']),
+        node => panic!("unexpected node {:?}", node),
+    }
+    let pos = nodes.node(id).span().start;
+    assert_eq!(nodes.node_at_line_column(pos), Some(id));
+
+    assert_eq!(nodes.node_at(100), None);
+}
@@ -0,0 +1,1049 @@
+// Owned AST
+//
+// NOTE: 解析器产出的 AST 借用自 `Arena` 以及源代码 `&[char]`，生命周期受限，
+//       不方便缓存、跨线程传递或者长期保存（例如 Language Server）。
+//       这里提供一份结构完全相同、不带生命周期的 AST，以及两者之间的无损转换：
+//
+//          Arena AST  --- ToOwnedAst::to_owned_ast --->  Owned AST
+//          Owned AST  --- ToArenaAst::to_arena_ast --->  Arena AST
+//
+//       类型名称与 Arena AST 保持一致，字符切片使用 `String` 保存，引用使用 `Box`，切片使用 `Vec`。
+
+use crate::toolshed::Arena;
+
+use crate::lexer::span::{ Loc, Span, };
+use crate::lexer::token::{ self, Keyword, Punctuator, LiteralNull, LiteralBoolean, };
+use crate::lexer::operator::{ PrefixOperator, InfixOperator, PostfixOperator, AssignmentOperator, };
+use crate::ast::numberic::Numberic;
+use crate::ast::statement::{ self, LexicalDeclarationKind, EmptyStatement, DebuggerStatement, };
+use crate::ast::expression::{ self, NewTargetExpression, };
+use crate::ast::function;
+use crate::ast::class;
+use crate::ast::pattern;
+use crate::ast::jsx;
+
+
+/// Arena AST → Owned AST
+pub trait ToOwnedAst {
+    type Owned;
+
+    fn to_owned_ast(&self) -> Self::Owned;
+}
+
+/// Owned AST → Arena AST
+pub trait ToArenaAst<'ast> {
+    type Node;
+
+    fn to_arena_ast(&self, arena: &'ast Arena) -> Self::Node;
+}
+
+
+// NOTE: 不含生命周期的节点两边共用
+macro_rules! shared_node {
+    ($($name:ty),* $(,)*) => {
+        $(
+            impl ToOwnedAst for $name {
+                type Owned = $name;
+
+                #[inline]
+                fn to_owned_ast(&self) -> $name {
+                    *self
+                }
+            }
+
+            impl<'ast> ToArenaAst<'ast> for $name {
+                type Node = $name;
+
+                #[inline]
+                fn to_arena_ast(&self, _arena: &'ast Arena) -> $name {
+                    *self
+                }
+            }
+        )*
+    };
+}
+
+shared_node!(
+    bool, Loc, Span, Numberic,
+    Keyword, Punctuator, LiteralNull, LiteralBoolean,
+    PrefixOperator, InfixOperator, PostfixOperator, AssignmentOperator,
+    LexicalDeclarationKind, EmptyStatement, DebuggerStatement, NewTargetExpression,
+);
+
+
+impl<'a> ToOwnedAst for &'a [char] {
+    type Owned = String;
+
+    fn to_owned_ast(&self) -> String {
+        self.iter().collect::<String>()
+    }
+}
+
+impl<'ast> ToArenaAst<'ast> for String {
+    type Node = &'ast [char];
+
+    fn to_arena_ast(&self, arena: &'ast Arena) -> &'ast [char] {
+        arena.alloc_vec(self.chars().collect::<Vec<char>>())
+    }
+}
+
+impl<'a, T: ToOwnedAst> ToOwnedAst for &'a [T] {
+    type Owned = Vec<T::Owned>;
+
+    fn to_owned_ast(&self) -> Self::Owned {
+        self.iter().map(|item| item.to_owned_ast()).collect()
+    }
+}
+
+impl<'ast, T: ToArenaAst<'ast>> ToArenaAst<'ast> for Vec<T> where T::Node: Copy + 'ast {
+    type Node = &'ast [T::Node];
+
+    fn to_arena_ast(&self, arena: &'ast Arena) -> Self::Node {
+        arena.alloc_vec(self.iter().map(|item| item.to_arena_ast(arena)).collect())
+    }
+}
+
+impl<'a, T: ToOwnedAst> ToOwnedAst for &'a T {
+    type Owned = Box<T::Owned>;
+
+    fn to_owned_ast(&self) -> Self::Owned {
+        Box::new((**self).to_owned_ast())
+    }
+}
+
+impl<'ast, T: ToArenaAst<'ast>> ToArenaAst<'ast> for Box<T> where T::Node: Copy + 'ast {
+    type Node = &'ast T::Node;
+
+    fn to_arena_ast(&self, arena: &'ast Arena) -> Self::Node {
+        arena.alloc((**self).to_arena_ast(arena))
+    }
+}
+
+impl<T: ToOwnedAst> ToOwnedAst for Option<T> {
+    type Owned = Option<T::Owned>;
+
+    fn to_owned_ast(&self) -> Self::Owned {
+        self.as_ref().map(|item| item.to_owned_ast())
+    }
+}
+
+impl<'ast, T: ToArenaAst<'ast>> ToArenaAst<'ast> for Option<T> {
+    type Node = Option<T::Node>;
+
+    fn to_arena_ast(&self, arena: &'ast Arena) -> Self::Node {
+        self.as_ref().map(|item| item.to_arena_ast(arena))
+    }
+}
+
+
+macro_rules! owned_struct {
+    ($module:ident::$arena:ident => pub struct $name:ident { $(pub $field:ident: $ty:ty),* $(,)* }) => {
+        #[derive(Debug, PartialEq, Clone)]
+        pub struct $name {
+            $(pub $field: $ty),*
+        }
+
+        impl<'ast> ToOwnedAst for $module::$arena<'ast> {
+            type Owned = $name;
+
+            fn to_owned_ast(&self) -> $name {
+                $name { $($field: self.$field.to_owned_ast()),* }
+            }
+        }
+
+        impl<'ast> ToArenaAst<'ast> for $name {
+            type Node = $module::$arena<'ast>;
+
+            fn to_arena_ast(&self, arena: &'ast Arena) -> Self::Node {
+                $module::$arena { $($field: self.$field.to_arena_ast(arena)),* }
+            }
+        }
+    };
+}
+
+macro_rules! owned_enum {
+    ($module:ident::$arena:ident => pub enum $name:ident { $($variant:ident($ty:ty)),* $(,)* }) => {
+        #[derive(Debug, PartialEq, Clone)]
+        pub enum $name {
+            $($variant($ty)),*
+        }
+
+        impl<'ast> ToOwnedAst for $module::$arena<'ast> {
+            type Owned = $name;
+
+            fn to_owned_ast(&self) -> $name {
+                match *self {
+                    $($module::$arena::$variant(ref inner) => $name::$variant(inner.to_owned_ast())),*
+                }
+            }
+        }
+
+        impl<'ast> ToArenaAst<'ast> for $name {
+            type Node = $module::$arena<'ast>;
+
+            fn to_arena_ast(&self, arena: &'ast Arena) -> Self::Node {
+                match *self {
+                    $($name::$variant(ref inner) => $module::$arena::$variant(inner.to_arena_ast(arena))),*
+                }
+            }
+        }
+    };
+}
+
+
+// ---------- token ----------
+
+owned_struct!(token::Identifier => pub struct Identifier {
+    pub loc: Loc,
+    pub span: Span,
+    pub raw: String,
+    pub cooked: Option<String>,
+});
+
+impl Identifier {
+    /// The identifier name with escape sequences resolved.
+    pub fn name(&self) -> &str {
+        self.cooked.as_ref().unwrap_or(&self.raw)
+    }
+}
+
+owned_struct!(token::LiteralString => pub struct LiteralString {
+    pub loc: Loc,
+    pub span: Span,
+    pub raw: String,
+    pub cooked: Option<String>,
+});
+
+owned_struct!(token::LiteralNumeric => pub struct LiteralNumeric {
+    pub loc: Loc,
+    pub span: Span,
+    pub raw: String,
+    pub value: Numberic,
+});
+
+owned_struct!(token::LiteralRegularExpression => pub struct LiteralRegularExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub body: String,
+    pub flags: Option<String>,
+});
+
+
+// ---------- statement ----------
+
+owned_enum!(statement::Statement => pub enum Statement {
+    Empty(Box<EmptyStatement>),
+    Debugger(Box<DebuggerStatement>),
+    Expression(Box<Expression>),
+    Variable(Box<VariableStatement>),
+    Function(Box<FunctionDeclaration>),
+    Class(Box<ClassDeclaration>),
+    Block(Box<BlockStatement>),
+    If(Box<IfStatement>),
+    DoWhile(Box<DoWhileStatement>),
+    While(Box<WhileStatement>),
+    For(Box<ForStatement>),
+    ForIn(Box<ForInStatement>),
+    ForOf(Box<ForOfStatement>),
+    ForAwaitOf(Box<ForAwaitOfStatement>),
+    Continue(Box<ContinueStatement>),
+    Break(Box<BreakStatement>),
+    Return(Box<ReturnStatement>),
+    With(Box<WithStatement>),
+    Switch(Box<SwitchStatement>),
+    Labelled(Box<LabelledStatement>),
+    Throw(Box<ThrowStatement>),
+    Try(Box<TryStatement>),
+});
+
+owned_struct!(statement::LexicalBinding => pub struct LexicalBinding {
+    pub loc: Loc,
+    pub span: Span,
+    pub name: Expression,
+    pub initializer: Option<Expression>,
+});
+
+owned_struct!(statement::VariableStatement => pub struct VariableStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub kind: LexicalDeclarationKind,
+    pub declarators: Vec<LexicalBinding>,
+});
+
+impl VariableStatement {
+    pub fn is_var(&self) -> bool {
+        self.kind == LexicalDeclarationKind::Var
+    }
+}
+
+owned_struct!(statement::BlockStatement => pub struct BlockStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub body: Vec<Statement>,
+});
+
+owned_struct!(statement::IfStatement => pub struct IfStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub condition: Expression,
+    pub and_then: Statement,
+    pub or_else: Statement,
+});
+
+owned_struct!(statement::DoWhileStatement => pub struct DoWhileStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub condition: Expression,
+    pub body: Statement,
+});
+
+owned_struct!(statement::WhileStatement => pub struct WhileStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub condition: Expression,
+    pub body: Statement,
+});
+
+owned_struct!(statement::ForStatement => pub struct ForStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub init: Option<Statement>,
+    pub condition: Option<Expression>,
+    pub finally: Option<Expression>,
+    pub body: Statement,
+});
+
+owned_struct!(statement::ForInStatement => pub struct ForInStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub left: Expression,
+    pub right: Expression,
+    pub body: Statement,
+});
+
+owned_struct!(statement::ForOfStatement => pub struct ForOfStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub left: Expression,
+    pub right: Expression,
+    pub body: Statement,
+});
+
+owned_struct!(statement::ForAwaitOfStatement => pub struct ForAwaitOfStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub left: Expression,
+    pub right: Expression,
+    pub body: Statement,
+});
+
+owned_struct!(statement::ContinueStatement => pub struct ContinueStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub label: Option<Identifier>,
+});
+
+owned_struct!(statement::BreakStatement => pub struct BreakStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub label: Option<Identifier>,
+});
+
+owned_struct!(statement::ReturnStatement => pub struct ReturnStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub value: Option<Expression>,
+});
+
+owned_struct!(statement::WithStatement => pub struct WithStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub condition: Expression,
+    pub then: Statement,
+});
+
+owned_struct!(statement::SwitchStatementCaseClause => pub struct SwitchStatementCaseClause {
+    pub loc: Loc,
+    pub span: Span,
+    pub value: Option<Expression>,
+    pub body: Statement,
+});
+
+owned_struct!(statement::SwitchStatement => pub struct SwitchStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub value: Expression,
+    pub clauses: Vec<SwitchStatementCaseClause>,
+});
+
+owned_struct!(statement::LabelledStatement => pub struct LabelledStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub label: Identifier,
+    pub item: Statement,
+});
+
+owned_struct!(statement::ThrowStatement => pub struct ThrowStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub value: Expression,
+});
+
+owned_struct!(statement::TryStatement => pub struct TryStatement {
+    pub loc: Loc,
+    pub span: Span,
+    pub body: BlockStatement,
+    pub catch_parameter: Option<Expression>,
+    pub catch_body: Option<BlockStatement>,
+    pub finally: Option<BlockStatement>,
+});
+
+
+// ---------- expression ----------
+
+owned_enum!(expression::Expression => pub enum Expression {
+    This(Box<Keyword>),
+    Spread(Box<SpreadExpression>),
+    Super(Box<Keyword>),
+    Identifier(Box<Identifier>),
+    Null(Box<LiteralNull>),
+    Boolean(Box<LiteralBoolean>),
+    String(Box<LiteralString>),
+    Numeric(Box<LiteralNumeric>),
+    RegularExpression(Box<LiteralRegularExpression>),
+    Template(Box<LiteralTemplateExpression>),
+    ArrayLiteral(Box<ArrayLiteral>),
+    ObjectLiteral(Box<ObjectLiteral>),
+    Function(Box<FunctionExpression>),
+    ArrowFunction(Box<ArrowFunctionExpression>),
+    Class(Box<ClassExpression>),
+    Parenthesized(Box<ParenthesizedExpression>),
+    Member(Box<MemberExpression>),
+    TaggedTemplate(Box<TaggedTemplateExpression>),
+    NewTarget(Box<NewTargetExpression>),
+    Call(Box<CallExpression>),
+    New(Box<NewExpression>),
+    Prefix(Box<PrefixExpression>),
+    Infix(Box<InfixExpression>),
+    Postfix(Box<PostfixExpression>),
+    Assignment(Box<AssignmentExpression>),
+    Conditional(Box<ConditionalExpression>),
+    Yield(Box<YieldExpression>),
+    Comma(Box<CommaExpression>),
+    AssignmentPattern(Box<AssignmentPattern>),
+    BindingPattern(Box<BindingPattern>),
+    JSXFragment(Box<JSXFragment>),
+    JSXElement(Box<JSXElement>),
+});
+
+owned_struct!(expression::SpreadExpression => pub struct SpreadExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub item: Expression,
+});
+
+owned_struct!(expression::LiteralTemplateExpression => pub struct LiteralTemplateExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub strings: Vec<LiteralString>,
+    pub bounds: Vec<Expression>,
+});
+
+owned_struct!(expression::ParenthesizedExpression => pub struct ParenthesizedExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub items: Vec<Expression>,
+});
+
+owned_struct!(expression::MemberExpression => pub struct MemberExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub left: Expression,
+    pub right: Expression,
+    pub computed: bool,
+    pub optional: bool,
+});
+
+owned_struct!(expression::TaggedTemplateExpression => pub struct TaggedTemplateExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub tag: Expression,
+    pub template: LiteralTemplateExpression,
+});
+
+owned_struct!(expression::CallExpression => pub struct CallExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub callee: Expression,
+    pub arguments: ParenthesizedExpression,
+    pub optional: bool,
+});
+
+owned_struct!(expression::NewExpression => pub struct NewExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub callee: Expression,
+    pub arguments: Option<ParenthesizedExpression>,
+});
+
+owned_struct!(expression::PrefixExpression => pub struct PrefixExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub operator: PrefixOperator,
+    pub operand: Expression,
+});
+
+owned_struct!(expression::InfixExpression => pub struct InfixExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub left: Expression,
+    pub operator: InfixOperator,
+    pub right: Expression,
+});
+
+owned_struct!(expression::PostfixExpression => pub struct PostfixExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub operator: PostfixOperator,
+    pub operand: Expression,
+});
+
+owned_struct!(expression::ConditionalExpression => pub struct ConditionalExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub condition: Expression,
+    pub and_then: Expression,
+    pub or_else: Expression,
+});
+
+owned_struct!(expression::YieldExpression => pub struct YieldExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub star: bool,
+    pub item: Expression,
+});
+
+owned_struct!(expression::AssignmentExpression => pub struct AssignmentExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub left: Expression,
+    pub operator: AssignmentOperator,
+    pub right: Expression,
+});
+
+owned_struct!(expression::CommaExpression => pub struct CommaExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub items: Vec<Expression>,
+});
+
+
+// ---------- function ----------
+
+owned_struct!(function::FunctionDeclaration => pub struct FunctionDeclaration {
+    pub loc: Loc,
+    pub span: Span,
+    pub is_async: bool,
+    pub is_generator: bool,
+    pub name: Identifier,
+    pub func: Function,
+});
+
+owned_struct!(function::FunctionExpression => pub struct FunctionExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub is_async: bool,
+    pub is_generator: bool,
+    pub name: Option<Identifier>,
+    pub func: Function,
+});
+
+owned_struct!(function::Function => pub struct Function {
+    pub loc: Loc,
+    pub span: Span,
+    pub params: ParenthesizedExpression,
+    pub body: Vec<Statement>,
+});
+
+owned_struct!(function::ArrowFunctionExpression => pub struct ArrowFunctionExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub is_async: bool,
+    pub params: Expression,
+    pub body: ConciseBody,
+});
+
+owned_enum!(function::ConciseBody => pub enum ConciseBody {
+    Expr(Expression),
+    Stmt(Vec<Statement>),
+});
+
+
+// ---------- class ----------
+
+owned_struct!(class::ClassDeclaration => pub struct ClassDeclaration {
+    pub loc: Loc,
+    pub span: Span,
+    pub name: Identifier,
+    pub class: Class,
+});
+
+owned_struct!(class::ClassExpression => pub struct ClassExpression {
+    pub loc: Loc,
+    pub span: Span,
+    pub name: Option<Identifier>,
+    pub class: Class,
+});
+
+owned_struct!(class::Method => pub struct Method {
+    pub loc: Loc,
+    pub span: Span,
+    pub is_async: bool,
+    pub is_generator: bool,
+    pub name: Expression,
+    pub params: ParenthesizedExpression,
+    pub body: Vec<Statement>,
+});
+
+owned_struct!(class::Getter => pub struct Getter {
+    pub loc: Loc,
+    pub span: Span,
+    pub name: Expression,
+    pub body: Vec<Statement>,
+});
+
+owned_struct!(class::Setter => pub struct Setter {
+    pub loc: Loc,
+    pub span: Span,
+    pub name: Expression,
+    pub params: ParenthesizedExpression,
+    pub body: Vec<Statement>,
+});
+
+owned_enum!(class::MethodDefinition => pub enum MethodDefinition {
+    Method(Method),
+    Getter(Getter),
+    Setter(Setter),
+});
+
+owned_struct!(class::ClassMethodDefinition => pub struct ClassMethodDefinition {
+    pub is_static: bool,
+    pub method: MethodDefinition,
+});
+
+owned_struct!(class::Class => pub struct Class {
+    pub loc: Loc,
+    pub span: Span,
+    pub heritage: Option<Expression>,
+    pub body: Vec<ClassMethodDefinition>,
+});
+
+
+// ---------- pattern ----------
+
+owned_enum!(pattern::PropertyName => pub enum PropertyName {
+    Identifier(Identifier),
+    Numberic(LiteralNumeric),
+    String(LiteralString),
+    Computed(Expression),
+});
+
+owned_enum!(pattern::BindingPattern => pub enum BindingPattern {
+    Object(ObjectBindingPattern),
+    Array(ArrayBindingPattern),
+});
+
+owned_struct!(pattern::ObjectBindingPattern => pub struct ObjectBindingPattern {
+    pub loc: Loc,
+    pub span: Span,
+    pub properties: Vec<BindingProperty>,
+});
+
+owned_struct!(pattern::ArrayBindingPattern => pub struct ArrayBindingPattern {
+    pub loc: Loc,
+    pub span: Span,
+    pub elems: Vec<Option<BindingElement>>,
+    pub rest_elem: Option<Box<BindingRestElement>>,
+});
+
+owned_enum!(pattern::BindingRestElement => pub enum BindingRestElement {
+    Identifier(Identifier),
+    BindingPattern(BindingPattern),
+});
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum BindingElement {
+    SingleNameBinding {
+        loc: Loc,
+        span: Span,
+        name: Identifier,
+        init: Option<Expression>,
+    },
+    BindingPattern {
+        loc: Loc,
+        span: Span,
+        pattern: BindingPattern,
+        init: Option<Expression>,
+    },
+}
+
+impl<'ast> ToOwnedAst for pattern::BindingElement<'ast> {
+    type Owned = BindingElement;
+
+    fn to_owned_ast(&self) -> BindingElement {
+        match *self {
+            pattern::BindingElement::SingleNameBinding { loc, span, ref name, ref init } => {
+                BindingElement::SingleNameBinding { loc, span, name: name.to_owned_ast(), init: init.to_owned_ast() }
+            },
+            pattern::BindingElement::BindingPattern { loc, span, ref pattern, ref init } => {
+                BindingElement::BindingPattern { loc, span, pattern: pattern.to_owned_ast(), init: init.to_owned_ast() }
+            },
+        }
+    }
+}
+
+impl<'ast> ToArenaAst<'ast> for BindingElement {
+    type Node = pattern::BindingElement<'ast>;
+
+    fn to_arena_ast(&self, arena: &'ast Arena) -> Self::Node {
+        match *self {
+            BindingElement::SingleNameBinding { loc, span, ref name, ref init } => {
+                pattern::BindingElement::SingleNameBinding { loc, span, name: name.to_arena_ast(arena), init: init.to_arena_ast(arena) }
+            },
+            BindingElement::BindingPattern { loc, span, ref pattern, ref init } => {
+                pattern::BindingElement::BindingPattern { loc, span, pattern: pattern.to_arena_ast(arena), init: init.to_arena_ast(arena) }
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum BindingProperty {
+    SingleNameBinding {
+        loc: Loc,
+        span: Span,
+        name: Identifier,
+        init: Option<Expression>,
+    },
+    Property {
+        loc: Loc,
+        span: Span,
+        name: PropertyName,
+        puct: Punctuator,
+        value: BindingElement,
+    },
+    Spread {
+        loc: Loc,
+        span: Span,
+        puct: Punctuator,
+        name: Identifier,
+    },
+}
+
+impl<'ast> ToOwnedAst for pattern::BindingProperty<'ast> {
+    type Owned = BindingProperty;
+
+    fn to_owned_ast(&self) -> BindingProperty {
+        match *self {
+            pattern::BindingProperty::SingleNameBinding { loc, span, ref name, ref init } => {
+                BindingProperty::SingleNameBinding { loc, span, name: name.to_owned_ast(), init: init.to_owned_ast() }
+            },
+            pattern::BindingProperty::Property { loc, span, ref name, puct, ref value } => {
+                BindingProperty::Property { loc, span, name: name.to_owned_ast(), puct, value: value.to_owned_ast() }
+            },
+            pattern::BindingProperty::Spread { loc, span, puct, ref name } => {
+                BindingProperty::Spread { loc, span, puct, name: name.to_owned_ast() }
+            },
+        }
+    }
+}
+
+impl<'ast> ToArenaAst<'ast> for BindingProperty {
+    type Node = pattern::BindingProperty<'ast>;
+
+    fn to_arena_ast(&self, arena: &'ast Arena) -> Self::Node {
+        match *self {
+            BindingProperty::SingleNameBinding { loc, span, ref name, ref init } => {
+                pattern::BindingProperty::SingleNameBinding { loc, span, name: name.to_arena_ast(arena), init: init.to_arena_ast(arena) }
+            },
+            BindingProperty::Property { loc, span, ref name, puct, ref value } => {
+                pattern::BindingProperty::Property { loc, span, name: name.to_arena_ast(arena), puct, value: value.to_arena_ast(arena) }
+            },
+            BindingProperty::Spread { loc, span, puct, ref name } => {
+                pattern::BindingProperty::Spread { loc, span, puct, name: name.to_arena_ast(arena) }
+            },
+        }
+    }
+}
+
+owned_enum!(pattern::AssignmentPattern => pub enum AssignmentPattern {
+    Object(ObjectAssignmentPattern),
+    Array(ArrayAssignmentPattern),
+});
+
+owned_struct!(pattern::ObjectAssignmentPattern => pub struct ObjectAssignmentPattern {
+    pub loc: Loc,
+    pub span: Span,
+    pub properties: Vec<AssignmentProperty>,
+});
+
+owned_struct!(pattern::ArrayAssignmentPattern => pub struct ArrayAssignmentPattern {
+    pub loc: Loc,
+    pub span: Span,
+    pub elems: Vec<Option<AssignmentElement>>,
+    pub rest_elem: Option<Expression>,
+});
+
+owned_struct!(pattern::AssignmentElement => pub struct AssignmentElement {
+    pub loc: Loc,
+    pub span: Span,
+    pub elem: Expression,
+    pub init: Option<Expression>,
+});
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum AssignmentProperty {
+    Identifier {
+        loc: Loc,
+        span: Span,
+        name: Identifier,
+        init: Option<Expression>,
+    },
+    Property {
+        loc: Loc,
+        span: Span,
+        name: PropertyName,
+        puct: Punctuator,
+        value: AssignmentElement,
+    },
+    Spread {
+        loc: Loc,
+        span: Span,
+        puct: Punctuator,
+        target: Expression,
+    },
+}
+
+impl<'ast> ToOwnedAst for pattern::AssignmentProperty<'ast> {
+    type Owned = AssignmentProperty;
+
+    fn to_owned_ast(&self) -> AssignmentProperty {
+        match *self {
+            pattern::AssignmentProperty::Identifier { loc, span, ref name, ref init } => {
+                AssignmentProperty::Identifier { loc, span, name: name.to_owned_ast(), init: init.to_owned_ast() }
+            },
+            pattern::AssignmentProperty::Property { loc, span, ref name, puct, ref value } => {
+                AssignmentProperty::Property { loc, span, name: name.to_owned_ast(), puct, value: value.to_owned_ast() }
+            },
+            pattern::AssignmentProperty::Spread { loc, span, puct, ref target } => {
+                AssignmentProperty::Spread { loc, span, puct, target: target.to_owned_ast() }
+            },
+        }
+    }
+}
+
+impl<'ast> ToArenaAst<'ast> for AssignmentProperty {
+    type Node = pattern::AssignmentProperty<'ast>;
+
+    fn to_arena_ast(&self, arena: &'ast Arena) -> Self::Node {
+        match *self {
+            AssignmentProperty::Identifier { loc, span, ref name, ref init } => {
+                pattern::AssignmentProperty::Identifier { loc, span, name: name.to_arena_ast(arena), init: init.to_arena_ast(arena) }
+            },
+            AssignmentProperty::Property { loc, span, ref name, puct, ref value } => {
+                pattern::AssignmentProperty::Property { loc, span, name: name.to_arena_ast(arena), puct, value: value.to_arena_ast(arena) }
+            },
+            AssignmentProperty::Spread { loc, span, puct, ref target } => {
+                pattern::AssignmentProperty::Spread { loc, span, puct, target: target.to_arena_ast(arena) }
+            },
+        }
+    }
+}
+
+owned_struct!(pattern::ObjectLiteral => pub struct ObjectLiteral {
+    pub loc: Loc,
+    pub span: Span,
+    pub properties: Vec<ObjectProperty>,
+});
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ObjectProperty {
+    Identifier(Identifier),
+    Property {
+        loc: Loc,
+        span: Span,
+        name: PropertyName,
+        puct: Punctuator,
+        value: Expression,
+    },
+    MethodDefinition(MethodDefinition),
+    Spread {
+        puct: Punctuator,
+        target: Expression,
+    },
+}
+
+impl<'ast> ToOwnedAst for pattern::ObjectProperty<'ast> {
+    type Owned = ObjectProperty;
+
+    fn to_owned_ast(&self) -> ObjectProperty {
+        match *self {
+            pattern::ObjectProperty::Identifier(ref ident) => ObjectProperty::Identifier(ident.to_owned_ast()),
+            pattern::ObjectProperty::Property { loc, span, ref name, puct, ref value } => {
+                ObjectProperty::Property { loc, span, name: name.to_owned_ast(), puct, value: value.to_owned_ast() }
+            },
+            pattern::ObjectProperty::MethodDefinition(ref method) => ObjectProperty::MethodDefinition(method.to_owned_ast()),
+            pattern::ObjectProperty::Spread { puct, ref target } => {
+                ObjectProperty::Spread { puct, target: target.to_owned_ast() }
+            },
+        }
+    }
+}
+
+impl<'ast> ToArenaAst<'ast> for ObjectProperty {
+    type Node = pattern::ObjectProperty<'ast>;
+
+    fn to_arena_ast(&self, arena: &'ast Arena) -> Self::Node {
+        match *self {
+            ObjectProperty::Identifier(ref ident) => pattern::ObjectProperty::Identifier(ident.to_arena_ast(arena)),
+            ObjectProperty::Property { loc, span, ref name, puct, ref value } => {
+                pattern::ObjectProperty::Property { loc, span, name: name.to_arena_ast(arena), puct, value: value.to_arena_ast(arena) }
+            },
+            ObjectProperty::MethodDefinition(ref method) => pattern::ObjectProperty::MethodDefinition(method.to_arena_ast(arena)),
+            ObjectProperty::Spread { puct, ref target } => {
+                pattern::ObjectProperty::Spread { puct, target: target.to_arena_ast(arena) }
+            },
+        }
+    }
+}
+
+owned_struct!(pattern::ArrayLiteral => pub struct ArrayLiteral {
+    pub loc: Loc,
+    pub span: Span,
+    pub elems: Vec<Option<Expression>>,
+});
+
+
+// ---------- JSX ----------
+
+owned_struct!(jsx::JSXFragment => pub struct JSXFragment {
+    pub loc: Loc,
+    pub span: Span,
+    pub children: Option<Vec<JSXChild>>,
+});
+
+owned_enum!(jsx::JSXElement => pub enum JSXElement {
+    SelfClosing(JSXSelfClosingElement),
+    Normal(JSXNormalElement),
+});
+
+owned_struct!(jsx::JSXOpeningElement => pub struct JSXOpeningElement {
+    pub loc: Loc,
+    pub span: Span,
+    pub name: JSXElementName,
+    pub attrs: Option<Vec<JSXAttribute>>,
+});
+
+owned_struct!(jsx::JSXClosingElement => pub struct JSXClosingElement {
+    pub loc: Loc,
+    pub span: Span,
+    pub name: JSXElementName,
+});
+
+owned_struct!(jsx::JSXSelfClosingElement => pub struct JSXSelfClosingElement {
+    pub loc: Loc,
+    pub span: Span,
+    pub name: JSXElementName,
+    pub attrs: Option<Vec<JSXAttribute>>,
+});
+
+owned_struct!(jsx::JSXNormalElement => pub struct JSXNormalElement {
+    pub loc: Loc,
+    pub span: Span,
+    pub opening: JSXOpeningElement,
+    pub children: Option<Vec<JSXChild>>,
+    pub closing: JSXClosingElement,
+});
+
+owned_enum!(jsx::JSXElementName => pub enum JSXElementName {
+    Identifier(Identifier),
+    NamespacedName(JSXNamespacedName),
+    MemberExpression(Vec<Identifier>),
+});
+
+owned_struct!(jsx::JSXNamespacedName => pub struct JSXNamespacedName {
+    pub loc: Loc,
+    pub span: Span,
+    pub namespace: Identifier,
+    pub name: Identifier,
+});
+
+owned_enum!(jsx::JSXAttribute => pub enum JSXAttribute {
+    Normal(JSXNormalAttribute),
+    Spread(Expression),
+});
+
+owned_struct!(jsx::JSXNormalAttribute => pub struct JSXNormalAttribute {
+    pub loc: Loc,
+    pub span: Span,
+    pub name: JSXNormalAttributeName,
+    pub init: Option<JSXNormalAttributeInitializer>,
+});
+
+owned_enum!(jsx::JSXNormalAttributeName => pub enum JSXNormalAttributeName {
+    Identifier(Identifier),
+    NamespacedName(JSXNamespacedName),
+});
+
+owned_enum!(jsx::JSXNormalAttributeInitializer => pub enum JSXNormalAttributeInitializer {
+    Identifier(Identifier),
+    Assignment(Expression),
+    Element(JSXElement),
+    Fragment(JSXFragment),
+});
+
+owned_enum!(jsx::JSXChild => pub enum JSXChild {
+    Text(LiteralString),
+    Element(JSXElement),
+    ChildExpression(Option<Vec<Expression>>),
+});
+
+
+#[test]
+fn test_owned_ast_roundtrip() {
+    use crate::parser::Parser;
+
+    fn assert_send_sync_clone<T: Send + Sync + Clone>() { }
+    assert_send_sync_clone::<Statement>();
+    assert_send_sync_clone::<Expression>();
+
+    let source = "function f(a, b) { a.b[c](1, 'x', b => b * 2); }\nclass A { get x() { } static y() { } }";
+    let owned = {
+        let arena = Arena::new();
+        let code = arena.alloc_vec(source.chars().collect::<Vec<char>>());
+        let mut parser = Parser::new(&arena, &code, "main.js");
+        parser.parse().unwrap();
+
+        let owned = parser.body.as_slice().to_owned_ast();
+        let back = owned.to_arena_ast(&arena);
+        assert_eq!(back, parser.body.as_slice());
+
+        owned
+    };
+
+    // NOTE: Arena 释放之后仍然可用，并且可以跨线程传递。
+    let handle = ::std::thread::spawn(move || owned.clone());
+    let owned = handle.join().unwrap();
+    assert_eq!(owned.len(), 2);
+
+    let arena = Arena::new();
+    let body = owned.to_arena_ast(&arena);
+    assert_eq!(body.to_owned_ast(), owned);
+    match body[0] {
+        statement::Statement::Function(func) => {
+            assert_eq!(func.name.raw, &['f']);
+            assert_eq!(func.func.body.len(), 2);
+        },
+        _ => panic!("expected a function declaration"),
+    }
+}
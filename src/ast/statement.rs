@@ -185,20 +185,19 @@ impl<'ast> Statement<'ast> {
     }
 
     pub fn is_hoistable(&self) -> bool {
-        unimplemented!()
-        // match *self {
-        //     // Statement::Variable(_) ?
-        //     Statement::Function(_) => true,
-        //     _ => false,
-        // }
+        // NOTE: `var` 声明和函数声明会被提升到函数作用域的顶部
+        match *self {
+            Statement::Variable(inner) => inner.is_var(),
+            _ => self.is_hoistable_declaration(),
+        }
     }
 
     pub fn is_hoistable_declaration(&self) -> bool {
-        unimplemented!()
-        // match *self {
-        //     Statement::Function(_) => true,
-        //     _ => false,
-        // }
+        // https://www.ecma-international.org/ecma-262/9.0/index.html#prod-HoistableDeclaration
+        match *self {
+            Statement::Function(_) => true,
+            _ => false,
+        }
     }
 }
 
@@ -370,7 +369,8 @@ pub struct WithStatement<'ast> {
 pub struct SwitchStatementCaseClause<'ast> {
     pub loc: Loc,
     pub span: Span,
-    pub value: Expression<'ast>,
+    // NOTE: `None` 表示 `default` 分支
+    pub value: Option<Expression<'ast>>,
     pub body: Statement<'ast>,
 }
 
@@ -399,12 +399,13 @@ pub struct ThrowStatement<'ast> {
     pub value: Expression<'ast>,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub struct TryStatement<'ast> {
     pub loc: Loc,
     pub span: Span,
     pub body: BlockStatement<'ast>,
     pub catch_parameter: Option<Expression<'ast>>,
-    pub catch_body: BlockStatement<'ast>,
+    // NOTE: `try { } finally { }` 没有 catch 子句
+    pub catch_body: Option<BlockStatement<'ast>>,
     pub finally: Option<BlockStatement<'ast>>,
 }
@@ -0,0 +1,495 @@
+// Owned AST visitor
+//
+// NOTE: 所有的 AST 变换（降级、常量折叠、死代码消除、变量名混淆 ...）都作用在 Owned AST 上，
+//       默认的 `walk_*` 函数负责遍历子节点，只需要重写感兴趣的 `visit_*` 方法即可。
+//
+//       以下位置的标识符 *不是* 变量引用，默认不会被访问：
+//          * 非计算属性的成员访问 `a.b` 中的 `b`
+//          * 对象字面量/方法定义中的属性名 `{ b: 1 }`、`class A { b() {} }`
+//          * 标签 `label: ...`、`break label`
+//       简写属性 `{ x }`、`{ x } = o`、`let { x } = o` 既是属性名也是变量，
+//       默认只通过 `visit_object_property`、`visit_assignment_property`、`visit_binding_property` 访问。
+
+use crate::ast::owned::*;
+
+
+pub trait VisitMut {
+    fn visit_statements(&mut self, body: &mut Vec<Statement>) {
+        walk_statements(self, body)
+    }
+
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        walk_statement(self, stmt)
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        walk_expression(self, expr)
+    }
+
+    /// Names introduced by declarations: function/class names, pattern bindings.
+    fn visit_binding_identifier(&mut self, _ident: &mut Identifier) {
+
+    }
+
+    fn visit_function(&mut self, func: &mut Function) {
+        walk_function(self, func)
+    }
+
+    fn visit_arrow_function(&mut self, arrow: &mut ArrowFunctionExpression) {
+        walk_arrow_function(self, arrow)
+    }
+
+    fn visit_class(&mut self, class: &mut Class) {
+        walk_class(self, class)
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        walk_method_definition(self, method)
+    }
+
+    fn visit_property_name(&mut self, name: &mut PropertyName) {
+        walk_property_name(self, name)
+    }
+
+    fn visit_object_property(&mut self, prop: &mut ObjectProperty) {
+        walk_object_property(self, prop)
+    }
+
+    fn visit_binding_pattern(&mut self, pattern: &mut BindingPattern) {
+        walk_binding_pattern(self, pattern)
+    }
+
+    fn visit_binding_element(&mut self, elem: &mut BindingElement) {
+        walk_binding_element(self, elem)
+    }
+
+    fn visit_binding_property(&mut self, prop: &mut BindingProperty) {
+        walk_binding_property(self, prop)
+    }
+
+    fn visit_assignment_pattern(&mut self, pattern: &mut AssignmentPattern) {
+        walk_assignment_pattern(self, pattern)
+    }
+
+    fn visit_assignment_property(&mut self, prop: &mut AssignmentProperty) {
+        walk_assignment_property(self, prop)
+    }
+
+    fn visit_jsx_element(&mut self, elem: &mut JSXElement) {
+        walk_jsx_element(self, elem)
+    }
+
+    fn visit_jsx_fragment(&mut self, frag: &mut JSXFragment) {
+        walk_jsx_children(self, &mut frag.children)
+    }
+}
+
+
+pub fn walk_statements<V: VisitMut + ?Sized>(visitor: &mut V, body: &mut Vec<Statement>) {
+    for stmt in body.iter_mut() {
+        visitor.visit_statement(stmt);
+    }
+}
+
+fn walk_block<V: VisitMut + ?Sized>(visitor: &mut V, block: &mut BlockStatement) {
+    visitor.visit_statements(&mut block.body);
+}
+
+pub fn walk_statement<V: VisitMut + ?Sized>(visitor: &mut V, stmt: &mut Statement) {
+    match *stmt {
+        Statement::Empty(_) | Statement::Debugger(_) => { },
+        Statement::Expression(ref mut inner) => visitor.visit_expression(inner),
+        Statement::Variable(ref mut inner) => {
+            for declarator in inner.declarators.iter_mut() {
+                visitor.visit_expression(&mut declarator.name);
+                if let Some(ref mut init) = declarator.initializer {
+                    visitor.visit_expression(init);
+                }
+            }
+        },
+        Statement::Function(ref mut inner) => {
+            visitor.visit_binding_identifier(&mut inner.name);
+            visitor.visit_function(&mut inner.func);
+        },
+        Statement::Class(ref mut inner) => {
+            visitor.visit_binding_identifier(&mut inner.name);
+            visitor.visit_class(&mut inner.class);
+        },
+        Statement::Block(ref mut inner) => walk_block(visitor, inner),
+        Statement::If(ref mut inner) => {
+            visitor.visit_expression(&mut inner.condition);
+            visitor.visit_statement(&mut inner.and_then);
+            visitor.visit_statement(&mut inner.or_else);
+        },
+        Statement::DoWhile(ref mut inner) => {
+            visitor.visit_statement(&mut inner.body);
+            visitor.visit_expression(&mut inner.condition);
+        },
+        Statement::While(ref mut inner) => {
+            visitor.visit_expression(&mut inner.condition);
+            visitor.visit_statement(&mut inner.body);
+        },
+        Statement::For(ref mut inner) => {
+            if let Some(ref mut init) = inner.init {
+                visitor.visit_statement(init);
+            }
+            if let Some(ref mut condition) = inner.condition {
+                visitor.visit_expression(condition);
+            }
+            if let Some(ref mut finally) = inner.finally {
+                visitor.visit_expression(finally);
+            }
+            visitor.visit_statement(&mut inner.body);
+        },
+        Statement::ForIn(ref mut inner) => {
+            visitor.visit_expression(&mut inner.left);
+            visitor.visit_expression(&mut inner.right);
+            visitor.visit_statement(&mut inner.body);
+        },
+        Statement::ForOf(ref mut inner) => {
+            visitor.visit_expression(&mut inner.left);
+            visitor.visit_expression(&mut inner.right);
+            visitor.visit_statement(&mut inner.body);
+        },
+        Statement::ForAwaitOf(ref mut inner) => {
+            visitor.visit_expression(&mut inner.left);
+            visitor.visit_expression(&mut inner.right);
+            visitor.visit_statement(&mut inner.body);
+        },
+        Statement::Continue(_) | Statement::Break(_) => { },
+        Statement::Return(ref mut inner) => {
+            if let Some(ref mut value) = inner.value {
+                visitor.visit_expression(value);
+            }
+        },
+        Statement::With(ref mut inner) => {
+            visitor.visit_expression(&mut inner.condition);
+            visitor.visit_statement(&mut inner.then);
+        },
+        Statement::Switch(ref mut inner) => {
+            visitor.visit_expression(&mut inner.value);
+            for clause in inner.clauses.iter_mut() {
+                if let Some(ref mut value) = clause.value {
+                    visitor.visit_expression(value);
+                }
+                visitor.visit_statement(&mut clause.body);
+            }
+        },
+        Statement::Labelled(ref mut inner) => visitor.visit_statement(&mut inner.item),
+        Statement::Throw(ref mut inner) => visitor.visit_expression(&mut inner.value),
+        Statement::Try(ref mut inner) => {
+            walk_block(visitor, &mut inner.body);
+            if let Some(ref mut param) = inner.catch_parameter {
+                visitor.visit_expression(param);
+            }
+            if let Some(ref mut catch_body) = inner.catch_body {
+                walk_block(visitor, catch_body);
+            }
+            if let Some(ref mut finally) = inner.finally {
+                walk_block(visitor, finally);
+            }
+        },
+    }
+}
+
+pub fn walk_expression<V: VisitMut + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match *expr {
+        Expression::This(_) | Expression::Super(_) | Expression::Identifier(_)
+        | Expression::Null(_) | Expression::Boolean(_) | Expression::String(_)
+        | Expression::Numeric(_) | Expression::RegularExpression(_) | Expression::NewTarget(_) => { },
+        Expression::Spread(ref mut inner) => visitor.visit_expression(&mut inner.item),
+        Expression::Template(ref mut inner) => {
+            for bound in inner.bounds.iter_mut() {
+                visitor.visit_expression(bound);
+            }
+        },
+        Expression::TaggedTemplate(ref mut inner) => {
+            visitor.visit_expression(&mut inner.tag);
+            for bound in inner.template.bounds.iter_mut() {
+                visitor.visit_expression(bound);
+            }
+        },
+        Expression::ArrayLiteral(ref mut inner) => {
+            for elem in inner.elems.iter_mut() {
+                if let Some(ref mut elem) = *elem {
+                    visitor.visit_expression(elem);
+                }
+            }
+        },
+        Expression::ObjectLiteral(ref mut inner) => {
+            for prop in inner.properties.iter_mut() {
+                visitor.visit_object_property(prop);
+            }
+        },
+        Expression::Function(ref mut inner) => {
+            if let Some(ref mut name) = inner.name {
+                visitor.visit_binding_identifier(name);
+            }
+            visitor.visit_function(&mut inner.func);
+        },
+        Expression::ArrowFunction(ref mut inner) => visitor.visit_arrow_function(inner),
+        Expression::Class(ref mut inner) => {
+            if let Some(ref mut name) = inner.name {
+                visitor.visit_binding_identifier(name);
+            }
+            visitor.visit_class(&mut inner.class);
+        },
+        Expression::Parenthesized(ref mut inner) => {
+            for item in inner.items.iter_mut() {
+                visitor.visit_expression(item);
+            }
+        },
+        Expression::Comma(ref mut inner) => {
+            for item in inner.items.iter_mut() {
+                visitor.visit_expression(item);
+            }
+        },
+        Expression::Member(ref mut inner) => {
+            visitor.visit_expression(&mut inner.left);
+            if inner.computed {
+                visitor.visit_expression(&mut inner.right);
+            }
+        },
+        Expression::Call(ref mut inner) => {
+            visitor.visit_expression(&mut inner.callee);
+            for item in inner.arguments.items.iter_mut() {
+                visitor.visit_expression(item);
+            }
+        },
+        Expression::New(ref mut inner) => {
+            visitor.visit_expression(&mut inner.callee);
+            if let Some(ref mut arguments) = inner.arguments {
+                for item in arguments.items.iter_mut() {
+                    visitor.visit_expression(item);
+                }
+            }
+        },
+        Expression::Prefix(ref mut inner) => visitor.visit_expression(&mut inner.operand),
+        Expression::Postfix(ref mut inner) => visitor.visit_expression(&mut inner.operand),
+        Expression::Infix(ref mut inner) => {
+            visitor.visit_expression(&mut inner.left);
+            visitor.visit_expression(&mut inner.right);
+        },
+        Expression::Assignment(ref mut inner) => {
+            visitor.visit_expression(&mut inner.left);
+            visitor.visit_expression(&mut inner.right);
+        },
+        Expression::Conditional(ref mut inner) => {
+            visitor.visit_expression(&mut inner.condition);
+            visitor.visit_expression(&mut inner.and_then);
+            visitor.visit_expression(&mut inner.or_else);
+        },
+        Expression::Yield(ref mut inner) => visitor.visit_expression(&mut inner.item),
+        Expression::AssignmentPattern(ref mut inner) => visitor.visit_assignment_pattern(inner),
+        Expression::BindingPattern(ref mut inner) => visitor.visit_binding_pattern(inner),
+        Expression::JSXFragment(ref mut inner) => visitor.visit_jsx_fragment(inner),
+        Expression::JSXElement(ref mut inner) => visitor.visit_jsx_element(inner),
+    }
+}
+
+pub fn walk_function<V: VisitMut + ?Sized>(visitor: &mut V, func: &mut Function) {
+    for item in func.params.items.iter_mut() {
+        visitor.visit_expression(item);
+    }
+    visitor.visit_statements(&mut func.body);
+}
+
+pub fn walk_arrow_function<V: VisitMut + ?Sized>(visitor: &mut V, arrow: &mut ArrowFunctionExpression) {
+    visitor.visit_expression(&mut arrow.params);
+    match arrow.body {
+        ConciseBody::Expr(ref mut body) => visitor.visit_expression(body),
+        ConciseBody::Stmt(ref mut body) => visitor.visit_statements(body),
+    }
+}
+
+pub fn walk_class<V: VisitMut + ?Sized>(visitor: &mut V, class: &mut Class) {
+    if let Some(ref mut heritage) = class.heritage {
+        visitor.visit_expression(heritage);
+    }
+    for method in class.body.iter_mut() {
+        visitor.visit_method_definition(&mut method.method);
+    }
+}
+
+/// `Identifier`、`String`、`Numeric` 形式的方法名只是属性名，其余的都是计算属性名。
+pub fn is_computed_method_name(name: &Expression) -> bool {
+    match *name {
+        Expression::Identifier(_) | Expression::String(_) | Expression::Numeric(_) => false,
+        _ => true,
+    }
+}
+
+pub fn walk_method_definition<V: VisitMut + ?Sized>(visitor: &mut V, method: &mut MethodDefinition) {
+    let (name, params, body) = match *method {
+        MethodDefinition::Method(ref mut inner) => (&mut inner.name, Some(&mut inner.params), &mut inner.body),
+        MethodDefinition::Getter(ref mut inner) => (&mut inner.name, None, &mut inner.body),
+        MethodDefinition::Setter(ref mut inner) => (&mut inner.name, Some(&mut inner.params), &mut inner.body),
+    };
+
+    if is_computed_method_name(name) {
+        visitor.visit_expression(name);
+    }
+    if let Some(params) = params {
+        for item in params.items.iter_mut() {
+            visitor.visit_expression(item);
+        }
+    }
+    visitor.visit_statements(body);
+}
+
+pub fn walk_property_name<V: VisitMut + ?Sized>(visitor: &mut V, name: &mut PropertyName) {
+    if let PropertyName::Computed(ref mut expr) = *name {
+        visitor.visit_expression(expr);
+    }
+}
+
+pub fn walk_object_property<V: VisitMut + ?Sized>(visitor: &mut V, prop: &mut ObjectProperty) {
+    match *prop {
+        ObjectProperty::Identifier(_) => { },
+        ObjectProperty::Property { ref mut name, ref mut value, .. } => {
+            visitor.visit_property_name(name);
+            visitor.visit_expression(value);
+        },
+        ObjectProperty::MethodDefinition(ref mut method) => visitor.visit_method_definition(method),
+        ObjectProperty::Spread { ref mut target, .. } => visitor.visit_expression(target),
+    }
+}
+
+pub fn walk_binding_pattern<V: VisitMut + ?Sized>(visitor: &mut V, pattern: &mut BindingPattern) {
+    match *pattern {
+        BindingPattern::Object(ref mut inner) => {
+            for prop in inner.properties.iter_mut() {
+                visitor.visit_binding_property(prop);
+            }
+        },
+        BindingPattern::Array(ref mut inner) => {
+            for elem in inner.elems.iter_mut() {
+                if let Some(ref mut elem) = *elem {
+                    visitor.visit_binding_element(elem);
+                }
+            }
+            if let Some(ref mut rest) = inner.rest_elem {
+                match **rest {
+                    BindingRestElement::Identifier(ref mut ident) => visitor.visit_binding_identifier(ident),
+                    BindingRestElement::BindingPattern(ref mut pattern) => visitor.visit_binding_pattern(pattern),
+                }
+            }
+        },
+    }
+}
+
+pub fn walk_binding_element<V: VisitMut + ?Sized>(visitor: &mut V, elem: &mut BindingElement) {
+    match *elem {
+        BindingElement::SingleNameBinding { ref mut name, ref mut init, .. } => {
+            visitor.visit_binding_identifier(name);
+            if let Some(ref mut init) = *init {
+                visitor.visit_expression(init);
+            }
+        },
+        BindingElement::BindingPattern { ref mut pattern, ref mut init, .. } => {
+            visitor.visit_binding_pattern(pattern);
+            if let Some(ref mut init) = *init {
+                visitor.visit_expression(init);
+            }
+        },
+    }
+}
+
+pub fn walk_binding_property<V: VisitMut + ?Sized>(visitor: &mut V, prop: &mut BindingProperty) {
+    match *prop {
+        BindingProperty::SingleNameBinding { ref mut init, .. } => {
+            if let Some(ref mut init) = *init {
+                visitor.visit_expression(init);
+            }
+        },
+        BindingProperty::Property { ref mut name, ref mut value, .. } => {
+            visitor.visit_property_name(name);
+            visitor.visit_binding_element(value);
+        },
+        BindingProperty::Spread { ref mut name, .. } => visitor.visit_binding_identifier(name),
+    }
+}
+
+pub fn walk_assignment_pattern<V: VisitMut + ?Sized>(visitor: &mut V, pattern: &mut AssignmentPattern) {
+    match *pattern {
+        AssignmentPattern::Object(ref mut inner) => {
+            for prop in inner.properties.iter_mut() {
+                visitor.visit_assignment_property(prop);
+            }
+        },
+        AssignmentPattern::Array(ref mut inner) => {
+            for elem in inner.elems.iter_mut() {
+                if let Some(ref mut elem) = *elem {
+                    visitor.visit_expression(&mut elem.elem);
+                    if let Some(ref mut init) = elem.init {
+                        visitor.visit_expression(init);
+                    }
+                }
+            }
+            if let Some(ref mut rest) = inner.rest_elem {
+                visitor.visit_expression(rest);
+            }
+        },
+    }
+}
+
+pub fn walk_assignment_property<V: VisitMut + ?Sized>(visitor: &mut V, prop: &mut AssignmentProperty) {
+    match *prop {
+        AssignmentProperty::Identifier { ref mut init, .. } => {
+            if let Some(ref mut init) = *init {
+                visitor.visit_expression(init);
+            }
+        },
+        AssignmentProperty::Property { ref mut name, ref mut value, .. } => {
+            visitor.visit_property_name(name);
+            visitor.visit_expression(&mut value.elem);
+            if let Some(ref mut init) = value.init {
+                visitor.visit_expression(init);
+            }
+        },
+        AssignmentProperty::Spread { ref mut target, .. } => visitor.visit_expression(target),
+    }
+}
+
+fn walk_jsx_attributes<V: VisitMut + ?Sized>(visitor: &mut V, attrs: &mut Option<Vec<JSXAttribute>>) {
+    if let Some(ref mut attrs) = *attrs {
+        for attr in attrs.iter_mut() {
+            match *attr {
+                JSXAttribute::Normal(ref mut attr) => match attr.init {
+                    Some(JSXNormalAttributeInitializer::Assignment(ref mut expr)) => visitor.visit_expression(expr),
+                    Some(JSXNormalAttributeInitializer::Element(ref mut elem)) => visitor.visit_jsx_element(elem),
+                    Some(JSXNormalAttributeInitializer::Fragment(ref mut frag)) => visitor.visit_jsx_fragment(frag),
+                    _ => { },
+                },
+                JSXAttribute::Spread(ref mut expr) => visitor.visit_expression(expr),
+            }
+        }
+    }
+}
+
+pub fn walk_jsx_children<V: VisitMut + ?Sized>(visitor: &mut V, children: &mut Option<Vec<JSXChild>>) {
+    if let Some(ref mut children) = *children {
+        for child in children.iter_mut() {
+            match *child {
+                JSXChild::Text(_) => { },
+                JSXChild::Element(ref mut elem) => visitor.visit_jsx_element(elem),
+                JSXChild::ChildExpression(Some(ref mut exprs)) => {
+                    for expr in exprs.iter_mut() {
+                        visitor.visit_expression(expr);
+                    }
+                },
+                JSXChild::ChildExpression(None) => { },
+            }
+        }
+    }
+}
+
+pub fn walk_jsx_element<V: VisitMut + ?Sized>(visitor: &mut V, elem: &mut JSXElement) {
+    match *elem {
+        JSXElement::SelfClosing(ref mut inner) => walk_jsx_attributes(visitor, &mut inner.attrs),
+        JSXElement::Normal(ref mut inner) => {
+            walk_jsx_attributes(visitor, &mut inner.opening.attrs);
+            walk_jsx_children(visitor, &mut inner.children);
+        },
+    }
+}
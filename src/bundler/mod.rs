@@ -0,0 +1,493 @@
+// Bundler
+//
+//      entry.js ──┬── ./math.js
+//                 └── lib ( node_modules/lib/package.json )
+//
+// NOTE: 从入口文件开始解析所有依赖的模块，构建依赖图，然后输出为一个 Script：
+//
+//          (function () {
+//          var _modules = [], _cache = [];
+//          function _require(id) { ... }
+//          _modules[0] = function (_exports, _require) { ... };
+//          _modules[1] = function (_exports, _require) { ... };
+//          _require(0);
+//          })();
+//
+//       每个模块是一个函数，导出的绑定以 getter 的形式挂在 `_exports` 上（live binding），
+//       导入的绑定改写为 `_dep.name`。模块按照依赖的顺序执行，循环依赖时，
+//       先被执行的模块只能看到另一个模块中已经初始化的绑定（与 ES 模块的语义一致，函数声明会被提升）。
+//
+//       开启 tree shaking 时，只打包被使用的模块和导出，见 `tree_shake.rs`。
+
+mod module;
+mod resolve;
+mod tree_shake;
+
+pub use self::module::{ ImportName, Import, Export, ModuleSyntax, ParsedModule, };
+pub use self::resolve::{ Resolver, normalize, };
+pub use self::tree_shake::{ Usage, TreeShaking, };
+
+use crate::toolshed::Arena;
+use crate::error::{ ErrorKind, Error, };
+use crate::ast::owned::{ Expression, Statement, PropertyName, ToArenaAst, };
+use crate::ast::statement::LexicalDeclarationKind;
+use crate::ast::builder;
+use crate::compiler::codegen::CodeGen;
+use crate::compiler::sourcemap::SourceMap;
+use crate::compiler::transform::NameGenerator;
+use crate::compiler::optimize::Optimizer;
+
+use self::module::ImportRewriter;
+
+use std::fs;
+use std::collections::{ HashMap, HashSet, };
+use std::path::{ Path, PathBuf, };
+
+
+/// A module in the dependency graph.
+#[derive(Debug)]
+pub struct Module {
+    pub path: PathBuf,
+    pub source: String,
+    pub parsed: ParsedModule,
+    /// Module ids of `parsed.syntax.requests`.
+    pub dependencies: Vec<usize>,
+}
+
+/// Modules reachable from the entry, the entry is module `0`.
+#[derive(Debug)]
+pub struct ModuleGraph {
+    pub modules: Vec<Module>,
+}
+
+impl ModuleGraph {
+    /// Load the entry and everything it imports.
+    pub fn build(entry: &Path, resolver: &Resolver) -> Result<Self, Error> {
+        let mut graph = ModuleGraph { modules: Vec::new() };
+        let mut ids = HashMap::new();
+        graph.load(&normalize(entry), resolver, &mut ids)?;
+
+        Ok(graph)
+    }
+
+    fn load(&mut self, path: &Path, resolver: &Resolver, ids: &mut HashMap<PathBuf, usize>) -> Result<usize, Error> {
+        if let Some(&id) = ids.get(path) {
+            return Ok(id);
+        }
+
+        let source = fs::read_to_string(path)
+            .map_err(|e| Error::new(ErrorKind::InternalError, format!("Cannot read module '{}': {}", path.display(), e)))?;
+        let parsed = module::parse(&source, &path.to_string_lossy())?;
+
+        let id = self.modules.len();
+        ids.insert(path.to_path_buf(), id);
+        let requests = parsed.syntax.requests.clone();
+        self.modules.push(Module { path: path.to_path_buf(), source, parsed, dependencies: Vec::new() });
+
+        let mut dependencies = Vec::new();
+        for request in requests.iter() {
+            let resolved = resolver.resolve(request, path)?;
+            dependencies.push(self.load(&resolved, resolver, ids)?);
+        }
+        self.modules[id].dependencies = dependencies;
+
+        Ok(id)
+    }
+
+    /// Import cycles, each one listed from the module that closes it.
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Color { White, Gray, Black }
+
+        fn visit(graph: &ModuleGraph, id: usize, colors: &mut Vec<Color>, stack: &mut Vec<usize>, cycles: &mut Vec<Vec<usize>>) {
+            colors[id] = Color::Gray;
+            stack.push(id);
+
+            for &dep in graph.modules[id].dependencies.iter() {
+                match colors[dep] {
+                    Color::White => visit(graph, dep, colors, stack, cycles),
+                    Color::Gray => {
+                        let start = stack.iter().position(|&m| m == dep).unwrap_or(0);
+                        cycles.push(stack[start..].to_vec());
+                    },
+                    Color::Black => { },
+                }
+            }
+
+            stack.pop();
+            colors[id] = Color::Black;
+        }
+
+        let mut colors = vec![ Color::White; self.modules.len() ];
+        let mut cycles = Vec::new();
+        for id in 0..self.modules.len() {
+            if colors[id] == Color::White {
+                visit(self, id, &mut colors, &mut Vec::new(), &mut cycles);
+            }
+        }
+
+        cycles
+    }
+}
+
+
+/// The bundled script.
+#[derive(Debug)]
+pub struct Bundle {
+    pub code: String,
+    pub source_map: SourceMap,
+    /// Paths of the modules in the dependency graph, indexed by module id.
+    pub modules: Vec<PathBuf>,
+    /// Module ids of the modules that were bundled, the others were removed by tree shaking.
+    pub included: Vec<usize>,
+    /// Import cycles, they are allowed but usually worth a warning.
+    pub cycles: Vec<Vec<PathBuf>>,
+}
+
+#[derive(Debug)]
+pub struct Bundler {
+    resolver: Resolver,
+    minify: bool,
+    tree_shaking: bool,
+    output: PathBuf,
+}
+
+impl Bundler {
+    pub fn new() -> Self {
+        Self { resolver: Resolver::new(), minify: false, tree_shaking: true, output: PathBuf::from("bundle.js") }
+    }
+
+    pub fn set_resolver(&mut self, resolver: Resolver) {
+        self.resolver = resolver;
+    }
+
+    pub fn set_minify(&mut self, minify: bool) {
+        self.minify = minify;
+    }
+
+    /// Remove unused exports and side effect free modules, enabled by default.
+    pub fn set_tree_shaking(&mut self, tree_shaking: bool) {
+        self.tree_shaking = tree_shaking;
+    }
+
+    /// The `file` of the source map, defaults to `bundle.js`.
+    pub fn set_output_file<P: Into<PathBuf>>(&mut self, output: P) {
+        self.output = output.into();
+    }
+
+    pub fn bundle<P: AsRef<Path>>(&self, entry: P) -> Result<Bundle, Error> {
+        let graph = ModuleGraph::build(entry.as_ref(), &self.resolver)?;
+        let shaking = if self.tree_shaking { TreeShaking::new(&graph, &self.resolver) } else { TreeShaking::none(&graph) };
+        let (code, source_map) = self.emit(&graph, &shaking)?;
+
+        let modules = graph.modules.iter().map(|module| module.path.clone()).collect::<Vec<PathBuf>>();
+        let included = (0..modules.len()).filter(|&id| shaking.included[id]).collect();
+        let cycles = graph.cycles().into_iter()
+            .map(|cycle| cycle.into_iter().map(|id| modules[id].clone()).collect())
+            .collect();
+
+        Ok(Bundle { code, source_map, modules, included, cycles })
+    }
+
+    fn emit(&self, graph: &ModuleGraph, shaking: &TreeShaking) -> Result<(String, SourceMap), Error> {
+        let mut names = NameGenerator::new();
+        for module in graph.modules.iter() {
+            names.reserve_all(module.parsed.syntax.names.iter().cloned());
+        }
+        let runtime = Runtime {
+            modules: names.fresh("modules"),
+            cache: names.fresh("cache"),
+            require: names.fresh("require"),
+            export: names.fresh("export"),
+            export_star: names.fresh("exportStar"),
+            exports: names.fresh("exports"),
+            // NOTE: 依赖的变量名取自模块的文件名，`./utils/math.js` → `_math`，每个模块只取一次
+            dependencies: graph.modules.iter().map(|module| names.fresh(&dependency_name(&module.path))).collect(),
+        };
+
+        let arena = Arena::new();
+        let newline = if self.minify { "" } else { "\n" };
+        let mut code = format!("(function () {{{}", newline);
+        let mut source_map = SourceMap::new(&self.output);
+
+        let prelude = runtime.prelude().iter().map(|stmt| stmt.to_arena_ast(&arena)).collect::<Vec<_>>();
+        code.push_str(&self.generate(&prelude, None)?);
+
+        for (id, module) in graph.modules.iter().enumerate() {
+            if !shaking.included[id] {
+                continue;
+            }
+
+            let mut body = vec![ runtime.module(id, module, shaking) ];
+            if self.tree_shaking {
+                let mut optimizer = Optimizer::new();
+                optimizer.set_pure_calls(module.parsed.pure_calls.iter().cloned());
+                optimizer.optimize(&mut body);
+            }
+            let body = body.iter().map(|stmt| stmt.to_arena_ast(&arena)).collect::<Vec<_>>();
+
+            let mut map = SourceMap::new(&self.output);
+            map.add_source(&module.path, Some(module.source.clone()));
+            let chunk = self.generate(&body, Some(&mut map))?;
+
+            let line = code.matches('\n').count();
+            let column = code.len() - code.rfind('\n').map(|pos| pos + 1).unwrap_or(0);
+            source_map.add_section(&map, line, column)?;
+            code.push_str(&chunk);
+        }
+
+        let start = builder::expr_stmt(builder::call(builder::ident_expr(&runtime.require), vec![ builder::number(0) ]));
+        code.push_str(&self.generate(&[ start.to_arena_ast(&arena) ], None)?);
+        code.push_str("})();");
+        code.push_str(newline);
+
+        Ok((code, source_map))
+    }
+
+    fn generate(&self, body: &[crate::ast::statement::Statement], source_map: Option<&mut SourceMap>) -> Result<String, Error> {
+        let output = match source_map {
+            Some(source_map) => {
+                let mut codegen = CodeGen::with_source_map(Vec::new(), source_map, 0);
+                codegen.set_minify(self.minify);
+                codegen.gen_program(body).map_err(io_error)?;
+                codegen.into_inner()
+            },
+            None => {
+                let mut codegen = CodeGen::new(Vec::new());
+                codegen.set_minify(self.minify);
+                codegen.gen_program(body).map_err(io_error)?;
+                codegen.into_inner()
+            },
+        };
+
+        let mut output = String::from_utf8(output).map_err(|e| Error::new(ErrorKind::InternalError, format!("{}", e)))?;
+        if !self.minify && !output.ends_with('\n') {
+            output.push('\n');
+        }
+
+        Ok(output)
+    }
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::new(ErrorKind::InternalError, format!("{}", e))
+}
+
+
+/// Names of the module registry, none of them appears in the bundled modules.
+struct Runtime {
+    modules: String,
+    cache: String,
+    require: String,
+    export: String,
+    export_star: String,
+    exports: String,
+    dependencies: Vec<String>,
+}
+
+impl Runtime {
+    fn prelude(&self) -> Vec<Statement> {
+        use crate::lexer::operator::{ PrefixOperator, InfixOperator, };
+
+        let id = builder::ident_expr;
+        let define_property = |target: Expression, name: Expression, getter: Expression| {
+            builder::expr_stmt(builder::call(builder::member(id("Object"), "defineProperty"), vec![
+                target,
+                name,
+                builder::object(vec![
+                    builder::named_property("enumerable", builder::boolean(true)),
+                    builder::named_property("get", getter),
+                ]),
+            ]))
+        };
+
+        vec![
+            // var _modules = [], _cache = [];
+            builder::variable(LexicalDeclarationKind::Var, vec![
+                builder::declarator(id(&self.modules), Some(builder::array(vec![]))),
+                builder::declarator(id(&self.cache), Some(builder::array(vec![]))),
+            ]),
+            // function _require(id) {
+            //     if (id in _cache) return _cache[id].exports;
+            //     var module = _cache[id] = { exports: {} };
+            //     _modules[id].call(undefined, module.exports, _require);
+            //     return module.exports;
+            // }
+            builder::function_decl(&self.require, vec![ id("id") ], vec![
+                builder::if_stmt(
+                    builder::infix(id("id"), InfixOperator::In, id(&self.cache)),
+                    builder::return_stmt(Some(builder::member(builder::computed_member(id(&self.cache), id("id")), "exports"))),
+                    None,
+                ),
+                builder::var("module", Some(builder::assign(
+                    builder::computed_member(id(&self.cache), id("id")),
+                    builder::object(vec![ builder::named_property("exports", builder::object(vec![])) ]),
+                ))),
+                builder::expr_stmt(builder::call(builder::member(builder::computed_member(id(&self.modules), id("id")), "call"), vec![
+                    builder::undefined(),
+                    builder::member(id("module"), "exports"),
+                    id(&self.require),
+                ])),
+                builder::return_stmt(Some(builder::member(id("module"), "exports"))),
+            ]),
+            // function _export(target, getters) {
+            //     var name;
+            //     for (name in getters) Object.defineProperty(target, name, { enumerable: true, get: getters[name] });
+            // }
+            builder::function_decl(&self.export, vec![ id("target"), id("getters") ], vec![
+                builder::var("name", None),
+                builder::for_in_stmt(id("name"), id("getters"),
+                    define_property(id("target"), id("name"), builder::computed_member(id("getters"), id("name")))),
+            ]),
+            // function _exportStar(target, source) {
+            //     Object.keys(source).forEach(function (name) {
+            //         if (name !== "default" && !(name in target)) Object.defineProperty(target, name, { ... });
+            //     });
+            // }
+            builder::function_decl(&self.export_star, vec![ id("target"), id("source") ], vec![
+                builder::expr_stmt(builder::call(
+                    builder::member(builder::call(builder::member(id("Object"), "keys"), vec![ id("source") ]), "forEach"),
+                    vec![ builder::function_expr(None, vec![ id("name") ], vec![
+                        builder::if_stmt(
+                            builder::infix(
+                                builder::infix(id("name"), InfixOperator::StrictNeq, builder::string_value("default")),
+                                InfixOperator::And,
+                                builder::prefix(PrefixOperator::Not, builder::parenthesized(builder::infix(id("name"), InfixOperator::In, id("target")))),
+                            ),
+                            define_property(id("target"), id("name"), builder::function_expr(None, vec![], vec![
+                                builder::return_stmt(Some(builder::computed_member(id("source"), id("name")))),
+                            ])),
+                            None,
+                        ),
+                    ]) ],
+                )),
+            ]),
+        ]
+    }
+
+    /// `_modules[id] = function (_exports, _require) { ... };`
+    fn module(&self, id: usize, module: &Module, shaking: &TreeShaking) -> Statement {
+        let syntax = &module.parsed.syntax;
+        let mut body = vec![ builder::expr_stmt(builder::string("use strict")) ];
+
+        if let Some(ref local) = syntax.default_local {
+            body.push(builder::var(local, None));
+        }
+
+        let deps = module.dependencies.iter().map(|&dep| self.dependencies[dep].clone()).collect::<Vec<String>>();
+
+        let getters = syntax.exports.iter()
+            .filter_map(|export| {
+                let (exported, value) = match *export {
+                    // NOTE: `import { a } from "m"; export { a };`
+                    Export::Local { ref exported, ref local } => match syntax.imports.iter().find(|import| &import.local == local) {
+                        Some(import) => (exported, module::import_member(&deps[import.request], &import.name)),
+                        None => (exported, builder::ident_expr(local)),
+                    },
+                    Export::Indirect { ref exported, request, ref name } => (exported, module::import_member(&deps[request], name)),
+                    Export::Star { .. } => return None,
+                };
+                if !shaking.used[id].contains(exported) {
+                    return None;
+                }
+                let getter = builder::function_expr(None, vec![], vec![ builder::return_stmt(Some(value)) ]);
+                Some(match is_identifier_name(exported) {
+                    true => builder::named_property(exported, getter),
+                    false => builder::property(PropertyName::Computed(builder::string_value(exported)), getter),
+                })
+            })
+            .collect::<Vec<_>>();
+        if !getters.is_empty() {
+            body.push(builder::expr_stmt(builder::call(builder::ident_expr(&self.export), vec![
+                builder::ident_expr(&self.exports),
+                builder::object(getters),
+            ])));
+        }
+
+        for (request, &dep) in module.dependencies.iter().enumerate() {
+            if !shaking.included[dep] {
+                continue;
+            }
+            body.push(builder::var(&deps[request], Some(builder::call(builder::ident_expr(&self.require), vec![ builder::number(dep as i64) ]))));
+        }
+
+        let mut stars = HashSet::new();
+        for export in syntax.exports.iter() {
+            if let Export::Star { request } = *export {
+                if shaking.included[module.dependencies[request]] && stars.insert(request) {
+                    body.push(builder::expr_stmt(builder::call(builder::ident_expr(&self.export_star), vec![
+                        builder::ident_expr(&self.exports),
+                        builder::ident_expr(&deps[request]),
+                    ])));
+                }
+            }
+        }
+
+        let imports = syntax.imports.iter()
+            .map(|import| (import.local.clone(), (deps[import.request].clone(), import.name.clone())))
+            .collect::<HashMap<_, _>>();
+        let mut code = module.parsed.body.clone();
+        ImportRewriter::new(&module.parsed.scopes, imports).rewrite(&mut code);
+        body.extend(code);
+
+        builder::expr_stmt(builder::assign(
+            builder::computed_member(builder::ident_expr(&self.modules), builder::number(id as i64)),
+            builder::function_expr(None, vec![ builder::ident_expr(&self.exports), builder::ident_expr(&self.require) ], body),
+        ))
+    }
+}
+
+fn is_identifier_name(name: &str) -> bool {
+    name.chars().next().map(|c| !c.is_ascii_digit()).unwrap_or(false)
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+fn dependency_name(path: &Path) -> String {
+    let mut stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    // NOTE: `lib/index.js` → `_lib`
+    if stem == "index" {
+        if let Some(dir) = path.parent().and_then(|dir| dir.file_name()) {
+            stem = dir.to_string_lossy().to_string();
+        }
+    }
+
+    let name = stem.chars().map(|c| if c.is_alphanumeric() || c == '_' || c == '$' { c } else { '_' }).collect::<String>();
+    if is_identifier_name(&name) { name } else { "dep".to_string() }
+}
+
+
+#[test]
+fn test_bundle() {
+    let root = std::env::temp_dir().join(format!("ecmascript-bundle-{}", std::process::id()));
+    let write = |path: &str, content: &str| {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+
+    write("src/main.js", "import { add } from \"./math\";\nimport * as util from './util';\nimport lib from \"lib\";\nadd(lib, util.one);\n");
+    write("src/math.js", "import { twice } from './util/index.js';\nexport function add(a, b) { twice(a) + b; }\n");
+    write("src/util/index.js", "import { add } from '../math';\nexport function one() {}\nexport function twice(x) { add(x, x); }\n");
+    write("node_modules/lib/package.json", r#"{ "name": "lib", "exports": { ".": { "require": "./lib.cjs", "import": "./lib.mjs" } } }"#);
+    write("node_modules/lib/lib.mjs", "export default 40 + 2;\n");
+
+    let mut bundler = Bundler::new();
+    bundler.set_output_file("dist/bundle.js");
+    let bundle = bundler.bundle(root.join("src/main.js")).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(bundle.modules, vec![
+        root.join("src/main.js"),
+        root.join("src/math.js"),
+        root.join("src/util/index.js"),
+        root.join("node_modules/lib/lib.mjs"),
+    ]);
+    assert_eq!(bundle.cycles, vec![ vec![ root.join("src/math.js"), root.join("src/util/index.js") ] ]);
+    assert_eq!(bundle.source_map.sources(), bundle.modules.as_slice());
+
+    // NOTE: 导入的绑定改写为对依赖的 exports 对象的访问，调用时不绑定 `this`
+    assert!(bundle.code.contains("(0, _math.add)(_lib.default, _util.one)"), "{}", bundle.code);
+    assert!(bundle.code.contains("(0, _util.twice)(a) + b"), "{}", bundle.code);
+    assert!(bundle.code.contains("_default = 42"), "{}", bundle.code);
+    assert!(bundle.code.ends_with("_require(0);\n})();\n"), "{}", bundle.code);
+}
@@ -0,0 +1,639 @@
+// ES module syntax
+// https://www.ecma-international.org/ecma-262/9.0/index.html#sec-modules
+//
+// NOTE: Parser 还不支持 `import`/`export` 声明，这里先用 Lexer 扫描出顶层的模块声明，
+//       记录下导入导出的信息，然后在源代码中把它们替换成等长的空白（保留换行），
+//       剩下的代码作为普通的 Script 交给 Parser，行列位置与原始的源代码完全一致。
+//
+//          import a, { b as c } from "./m";     →   （空白）
+//          export { a, c as d };                →   （空白）
+//          export function f() {}               →          function f() {}
+//          export default a + 1;                →   _default = a + 1;
+
+use crate::toolshed::Arena;
+use crate::error::{ ErrorKind, Error, };
+use crate::lexer::Lexer;
+use crate::lexer::eschar::ESChar;
+use crate::lexer::token::Token;
+use crate::lexer::keyword::KeywordKind;
+use crate::lexer::punctuator::PunctuatorKind;
+use crate::ast::statement::Statement;
+use crate::ast::owned::{ self, ToOwnedAst, };
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::parser::Parser;
+use crate::compiler::scope::{ self, Goal, ScopeTree, };
+use crate::compiler::transform::NameGenerator;
+
+use std::collections::{ HashMap, HashSet, };
+
+
+/// What an import binding refers to in the imported module.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ImportName {
+    /// `import { name } from "m"`, `import name from "m"` ( `default` )
+    Named(String),
+    /// `import * as ns from "m"`
+    Namespace,
+}
+
+/// `import ... from "m"`, `request` indexes `ModuleSyntax::requests`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Import {
+    pub local: String,
+    pub request: usize,
+    pub name: ImportName,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Export {
+    /// `export { local as exported }`, `export function local() {}`, `export default ...`
+    Local { exported: String, local: String },
+    /// `export { name as exported } from "m"`, `export * as exported from "m"`
+    Indirect { exported: String, request: usize, name: ImportName },
+    /// `export * from "m"`
+    Star { request: usize },
+}
+
+/// The module declarations of a source file, and the remaining code.
+#[derive(Debug, Clone)]
+pub struct ModuleSyntax {
+    /// The source code with the module declarations blanked out.
+    pub code: Vec<char>,
+    /// Module specifiers in the order they first appear.
+    pub requests: Vec<String>,
+    pub imports: Vec<Import>,
+    pub exports: Vec<Export>,
+    /// Local name of `export default <expression>`, declared by the bundler.
+    pub default_local: Option<String>,
+    /// Every identifier appearing in the module.
+    pub names: HashSet<String>,
+    // NOTE: `export <declaration>` 中声明的起始位置，解析之后才能得到声明的名字
+    declarations: Vec<usize>,
+}
+
+impl ModuleSyntax {
+    fn request(&mut self, specifier: String) -> usize {
+        match self.requests.iter().position(|request| request == &specifier) {
+            Some(index) => index,
+            None => {
+                self.requests.push(specifier);
+                self.requests.len() - 1
+            }
+        }
+    }
+
+    /// Replace `start..end` with `replacement` followed by spaces, line terminators are kept.
+    fn blank(&mut self, start: usize, end: usize, replacement: &str) {
+        let mut replacement = replacement.chars();
+        for c in self.code[start..end].iter_mut() {
+            if !c.is_es_line_terminator() {
+                *c = replacement.next().unwrap_or(' ');
+            }
+        }
+    }
+}
+
+
+#[derive(Debug, PartialEq, Clone)]
+enum Tok {
+    Name(String),
+    Str(String),
+    Punct(PunctuatorKind),
+    Other,
+}
+
+#[derive(Debug, Clone)]
+struct Item {
+    tok: Tok,
+    start: usize,
+    end: usize,
+}
+
+struct Scanner<'ast> {
+    lexer: Lexer<'ast>,
+    peeked: Option<Item>,
+    regex_allowed: bool,
+    names: HashSet<String>,
+}
+
+impl<'ast> Scanner<'ast> {
+    fn error(&self, message: &str) -> Error {
+        Error::new(ErrorKind::SyntaxError, format!("{}: {}", self.lexer.filename(), message))
+    }
+
+    fn peek(&mut self) -> Result<Option<&Item>, Error> {
+        if self.peeked.is_none() {
+            self.peeked = self.read()?;
+        }
+
+        Ok(self.peeked.as_ref())
+    }
+
+    fn next(&mut self) -> Result<Option<Item>, Error> {
+        match self.peeked.take() {
+            Some(item) => Ok(Some(item)),
+            None => self.read(),
+        }
+    }
+
+    fn expect_next(&mut self) -> Result<Item, Error> {
+        self.next()?.ok_or_else(|| self.error("unexpected end of input in module declaration"))
+    }
+
+    fn expect_name(&mut self, name: &str) -> Result<Item, Error> {
+        let item = self.expect_next()?;
+        match item.tok {
+            Tok::Name(ref found) if found == name => Ok(item.clone()),
+            _ => Err(self.error(&format!("expected `{}` in module declaration", name))),
+        }
+    }
+
+    fn expect_string(&mut self) -> Result<(String, usize), Error> {
+        let item = self.expect_next()?;
+        match item.tok {
+            Tok::Str(value) => Ok((value, item.end)),
+            _ => Err(self.error("expected a module specifier")),
+        }
+    }
+
+    fn is_next(&mut self, tok: &Tok) -> Result<bool, Error> {
+        Ok(self.peek()?.map(|item| &item.tok == tok).unwrap_or(false))
+    }
+
+    fn is_next_name(&mut self, name: &str) -> Result<bool, Error> {
+        Ok(match self.peek()? {
+            Some(Item { tok: Tok::Name(ref found), .. }) => found == name,
+            _ => false,
+        })
+    }
+
+    /// The next significant token, regular expressions and templates are skipped as a whole.
+    fn read(&mut self) -> Result<Option<Item>, Error> {
+        loop {
+            let token = match self.lexer.consume()? {
+                Some(Token::LineTerminator) => continue,
+                Some(token) => token,
+                None => return Ok(None),
+            };
+            let start = self.lexer.loc_start();
+
+            let (tok, regex_allowed) = match token {
+                Token::Identifier(ident) => {
+                    let name = ident.cooked.unwrap_or(ident.raw).iter().collect::<String>();
+                    // NOTE: `return /a/`、`typeof /a/` 之后是正则表达式，`a / b`、`this / b` 之后是除号
+                    let regex_allowed = match ident.to_keyword_or_literal() {
+                        Some(Token::Keyword(kw)) => kw.kind != KeywordKind::This && kw.kind != KeywordKind::Super,
+                        _ => false,
+                    };
+                    self.names.insert(name.clone());
+                    (Tok::Name(name), regex_allowed)
+                },
+                Token::LiteralString(lit) => (Tok::Str(lit.cooked.unwrap_or(lit.raw).iter().collect()), false),
+                Token::Punctuator(punct) => match punct.kind {
+                    PunctuatorKind::Div if self.regex_allowed => {
+                        self.lexer.read_literal_regular_expression()?;
+                        (Tok::Other, false)
+                    },
+                    PunctuatorKind::RParen | PunctuatorKind::RBracket
+                    | PunctuatorKind::Increment | PunctuatorKind::Decrement => (Tok::Punct(punct.kind), false),
+                    kind => (Tok::Punct(kind), true),
+                },
+                Token::TemplateOpenning => {
+                    self.template()?;
+                    (Tok::Other, false)
+                },
+                _ => (Tok::Other, false),
+            };
+
+            self.regex_allowed = regex_allowed;
+            return Ok(Some(Item { tok, start, end: self.lexer.offset() }));
+        }
+    }
+
+    fn template(&mut self) -> Result<(), Error> {
+        loop {
+            let (_, is_end) = self.lexer.read_literal_template_string()?;
+            if is_end {
+                return Ok(());
+            }
+
+            // NOTE: `${ ... }` 中的代码，直到与之匹配的 `}`
+            self.regex_allowed = true;
+            let mut depth = 0usize;
+            loop {
+                let item = self.read()?.ok_or_else(|| self.error("unterminated template literal"))?;
+                match item.tok {
+                    Tok::Punct(PunctuatorKind::LBrace) => depth += 1,
+                    Tok::Punct(PunctuatorKind::RBrace) if depth == 0 => break,
+                    Tok::Punct(PunctuatorKind::RBrace) => depth -= 1,
+                    _ => { },
+                }
+            }
+        }
+    }
+
+    /// `{ a, b as c, "d" as e }`, returns `(name, alias)` pairs.
+    fn specifiers(&mut self) -> Result<Vec<(String, String)>, Error> {
+        let mut specifiers = Vec::new();
+        loop {
+            let item = self.expect_next()?;
+            let name = match item.tok {
+                Tok::Punct(PunctuatorKind::RBrace) => break,
+                Tok::Name(name) | Tok::Str(name) => name,
+                _ => return Err(self.error("unexpected token in module specifiers")),
+            };
+
+            let alias = if self.is_next_name("as")? {
+                self.next()?;
+                match self.expect_next()?.tok {
+                    Tok::Name(alias) | Tok::Str(alias) => alias,
+                    _ => return Err(self.error("unexpected token in module specifiers")),
+                }
+            } else {
+                name.clone()
+            };
+            specifiers.push((name, alias));
+
+            match self.expect_next()?.tok {
+                Tok::Punct(PunctuatorKind::Comma) => continue,
+                Tok::Punct(PunctuatorKind::RBrace) => break,
+                _ => return Err(self.error("unexpected token in module specifiers")),
+            }
+        }
+
+        Ok(specifiers)
+    }
+
+    /// `from "m"` plus import attributes and the optional `;`, returns the specifier and the end offset.
+    fn from_clause(&mut self) -> Result<(String, usize), Error> {
+        self.expect_name("from")?;
+        let (specifier, end) = self.expect_string()?;
+        Ok((specifier, self.clause_end(end)?))
+    }
+
+    fn clause_end(&mut self, mut end: usize) -> Result<usize, Error> {
+        // NOTE: `with { type: "json" }`
+        if self.is_next_name("with")? || self.is_next_name("assert")? {
+            self.next()?;
+            loop {
+                let item = self.expect_next()?;
+                end = item.end;
+                if item.tok == Tok::Punct(PunctuatorKind::RBrace) {
+                    break;
+                }
+            }
+        }
+
+        if self.is_next(&Tok::Punct(PunctuatorKind::Semicolon))? {
+            end = self.expect_next()?.end;
+        }
+
+        Ok(end)
+    }
+}
+
+
+fn import_declaration(scanner: &mut Scanner, syntax: &mut ModuleSyntax, start: usize) -> Result<(), Error> {
+    let mut bindings = Vec::new();
+
+    let mut item = scanner.expect_next()?;
+    if let Tok::Str(specifier) = item.tok {
+        // import "m";
+        let end = scanner.clause_end(item.end)?;
+        syntax.request(specifier);
+        syntax.blank(start, end, "");
+        return Ok(());
+    }
+
+    if let Tok::Name(local) = item.tok.clone() {
+        bindings.push((local, ImportName::Named("default".to_string())));
+        if !scanner.is_next(&Tok::Punct(PunctuatorKind::Comma))? {
+            let (specifier, end) = scanner.from_clause()?;
+            return Ok(finish_import(syntax, specifier, bindings, start, end));
+        }
+        scanner.next()?;
+        item = scanner.expect_next()?;
+    }
+
+    match item.tok {
+        Tok::Punct(PunctuatorKind::LBrace) => {
+            for (name, local) in scanner.specifiers()? {
+                bindings.push((local, ImportName::Named(name)));
+            }
+        },
+        Tok::Punct(PunctuatorKind::Mul) => {
+            scanner.expect_name("as")?;
+            match scanner.expect_next()?.tok {
+                Tok::Name(local) => bindings.push((local, ImportName::Namespace)),
+                _ => return Err(scanner.error("expected a namespace import name")),
+            }
+        },
+        _ => return Err(scanner.error("unexpected token in import declaration")),
+    }
+
+    let (specifier, end) = scanner.from_clause()?;
+    Ok(finish_import(syntax, specifier, bindings, start, end))
+}
+
+fn finish_import(syntax: &mut ModuleSyntax, specifier: String, bindings: Vec<(String, ImportName)>, start: usize, end: usize) {
+    let request = syntax.request(specifier);
+    for (local, name) in bindings {
+        syntax.imports.push(Import { local, request, name });
+    }
+    syntax.blank(start, end, "");
+}
+
+fn export_declaration(scanner: &mut Scanner, syntax: &mut ModuleSyntax, start: usize) -> Result<(), Error> {
+    let item = scanner.expect_next()?;
+    match item.tok {
+        Tok::Punct(PunctuatorKind::Mul) => {
+            let exported = if scanner.is_next_name("as")? {
+                scanner.next()?;
+                match scanner.expect_next()?.tok {
+                    Tok::Name(name) | Tok::Str(name) => Some(name),
+                    _ => return Err(scanner.error("unexpected token in export declaration")),
+                }
+            } else {
+                None
+            };
+
+            let (specifier, end) = scanner.from_clause()?;
+            let request = syntax.request(specifier);
+            syntax.exports.push(match exported {
+                Some(exported) => Export::Indirect { exported, request, name: ImportName::Namespace },
+                None => Export::Star { request },
+            });
+            syntax.blank(start, end, "");
+        },
+        Tok::Punct(PunctuatorKind::LBrace) => {
+            let specifiers = scanner.specifiers()?;
+            let end = if scanner.is_next_name("from")? {
+                let (specifier, end) = scanner.from_clause()?;
+                let request = syntax.request(specifier);
+                for (name, exported) in specifiers {
+                    syntax.exports.push(Export::Indirect { exported, request, name: ImportName::Named(name) });
+                }
+                end
+            } else {
+                for (local, exported) in specifiers {
+                    syntax.exports.push(Export::Local { exported, local });
+                }
+                scanner.clause_end(item.end)?
+            };
+            syntax.blank(start, end, "");
+        },
+        Tok::Name(ref name) if name == "default" => {
+            // NOTE: 具名的函数、类声明保持为声明，其余的（包括匿名的函数、类）都视为表达式
+            if scanner.is_next_name("async")? {
+                scanner.next()?;
+            }
+            if scanner.is_next_name("function")? || scanner.is_next_name("class")? {
+                scanner.next()?;
+                if scanner.is_next(&Tok::Punct(PunctuatorKind::Mul))? {
+                    scanner.next()?;
+                }
+                let local = match scanner.peek()? {
+                    Some(Item { tok: Tok::Name(ref local), .. }) if local != "extends" => Some(local.clone()),
+                    _ => None,
+                };
+                if let Some(local) = local {
+                    syntax.exports.push(Export::Local { exported: "default".to_string(), local });
+                    syntax.blank(start, item.end, "");
+                    return Ok(());
+                }
+            }
+
+            syntax.declarations.push(start);
+            syntax.default_local = Some(String::new());
+        },
+        Tok::Name(ref name) => match name.as_str() {
+            "var" | "let" | "const" | "function" | "class" | "async" => {
+                syntax.declarations.push(item.start);
+                syntax.blank(start, item.start, "");
+            },
+            _ => return Err(scanner.error("unexpected token in export declaration")),
+        },
+        _ => return Err(scanner.error("unexpected token in export declaration")),
+    }
+
+    Ok(())
+}
+
+/// Extract the module declarations of `source`.
+pub fn scan(source: &[char], filename: &str) -> Result<ModuleSyntax, Error> {
+    let mut syntax = ModuleSyntax {
+        code: source.to_vec(),
+        requests: Vec::new(),
+        imports: Vec::new(),
+        exports: Vec::new(),
+        default_local: None,
+        names: HashSet::new(),
+        declarations: Vec::new(),
+    };
+
+    if source.is_empty() {
+        return Ok(syntax);
+    }
+
+    let arena = Arena::new();
+    let source = arena.alloc_vec(source.to_vec());
+    let filename = arena.alloc_str(filename);
+    let mut scanner = Scanner { lexer: Lexer::new(&arena, source, filename), peeked: None, regex_allowed: true, names: HashSet::new() };
+
+    // NOTE: 模块声明只能出现在顶层，`import(...)`、`import.meta` 和 `a.import` 不是声明
+    let mut depth = 0usize;
+    let mut after_dot = false;
+    let mut default_start = None;
+    while let Some(item) = scanner.next()? {
+        match item.tok {
+            Tok::Punct(PunctuatorKind::LBrace) | Tok::Punct(PunctuatorKind::LParen) | Tok::Punct(PunctuatorKind::LBracket) => depth += 1,
+            Tok::Punct(PunctuatorKind::RBrace) | Tok::Punct(PunctuatorKind::RParen) | Tok::Punct(PunctuatorKind::RBracket) => {
+                depth = depth.saturating_sub(1);
+            },
+            Tok::Name(ref name) if depth == 0 && !after_dot && name == "import" => {
+                if !scanner.is_next(&Tok::Punct(PunctuatorKind::LParen))? && !scanner.is_next(&Tok::Punct(PunctuatorKind::Dot))? {
+                    import_declaration(&mut scanner, &mut syntax, item.start)?;
+                }
+            },
+            Tok::Name(ref name) if depth == 0 && !after_dot && name == "export" => {
+                let is_default = syntax.default_local.is_some();
+                export_declaration(&mut scanner, &mut syntax, item.start)?;
+                if !is_default && syntax.default_local.is_some() {
+                    default_start = syntax.declarations.pop();
+                }
+            },
+            _ => { },
+        }
+
+        after_dot = match item.tok {
+            Tok::Punct(PunctuatorKind::Dot) | Tok::Punct(PunctuatorKind::QuestionDot) => true,
+            _ => false,
+        };
+    }
+
+    syntax.names = scanner.names;
+
+    // export default <expression>;   →   _default = <expression>;
+    if let Some(start) = default_start {
+        let mut names = NameGenerator::new();
+        names.reserve_all(syntax.names.iter().cloned());
+        let local = names.fresh("default");
+
+        // NOTE: `export default` 至少 14 个字符，足够容纳 `_default =`
+        let end = start + syntax.code[start..].iter().collect::<String>().find("default").map(|pos| pos + 7).unwrap_or(0);
+        syntax.blank(start, end, &format!("{} =", local));
+        syntax.exports.push(Export::Local { exported: "default".to_string(), local: local.clone() });
+        syntax.names.insert(local.clone());
+        syntax.default_local = Some(local);
+    }
+
+    Ok(syntax)
+}
+
+
+/// A parsed module: its declarations, its code as a Script and the scope tree of that code.
+#[derive(Debug)]
+pub struct ParsedModule {
+    pub syntax: ModuleSyntax,
+    pub body: Vec<owned::Statement>,
+    pub scopes: ScopeTree,
+    /// Source offsets of the calls annotated with `/* @__PURE__ */`.
+    pub pure_calls: HashSet<usize>,
+}
+
+/// Scan and parse the module `source`.
+pub fn parse(source: &str, filename: &str) -> Result<ParsedModule, Error> {
+    let chars = source.chars().collect::<Vec<char>>();
+    let mut syntax = scan(&chars, filename)?;
+
+    if syntax.code.iter().all(|c| c.is_es_whitespace() || c.is_es_line_terminator()) {
+        let scopes = scope::analyze(&[], Goal::Module)?;
+        return Ok(ParsedModule { syntax, body: Vec::new(), scopes, pure_calls: HashSet::new() });
+    }
+
+    let arena = Arena::new();
+    let code = arena.alloc_vec(syntax.code.clone());
+    let filename = arena.alloc_str(filename);
+    let mut parser = Parser::new(&arena, code, filename);
+    parser.parse()?;
+
+    // export function f() {}   export var a, { b } = c;
+    for offset in syntax.declarations.drain(..).collect::<Vec<usize>>() {
+        let stmt = parser.body.iter().find(|stmt| stmt.loc().start >= offset);
+        let mut names = Vec::new();
+        match stmt {
+            Some(Statement::Function(inner)) => names.push(inner.name),
+            Some(Statement::Class(inner)) => names.push(inner.name),
+            Some(Statement::Variable(inner)) => {
+                for declarator in inner.declarators.iter() {
+                    scope::collect_bound_names(&declarator.name, &mut names);
+                }
+            },
+            _ => return Err(Error::new(ErrorKind::SyntaxError, format!("{}: expected a declaration after `export`", filename))),
+        }
+
+        for ident in names {
+            let local = ident.cooked.unwrap_or(ident.raw).iter().collect::<String>();
+            syntax.exports.push(Export::Local { exported: local.clone(), local });
+        }
+    }
+
+    let scopes = scope::analyze(&parser.body, Goal::Module)?;
+    let body = parser.body.as_slice().to_owned_ast();
+    let pure_calls = parser.pure_annotations().iter().cloned().collect();
+
+    Ok(ParsedModule { syntax, body, scopes, pure_calls })
+}
+
+
+/// `_dep.name`, or `_dep["name"]` when `name` is not an identifier.
+pub fn import_member(dep: &str, name: &ImportName) -> owned::Expression {
+    match *name {
+        ImportName::Namespace => builder::ident_expr(dep),
+        ImportName::Named(ref name) => {
+            let is_identifier = name.chars().next().map(|c| !c.is_ascii_digit()).unwrap_or(false)
+                && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+            if is_identifier {
+                builder::member(builder::ident_expr(dep), name)
+            } else {
+                builder::computed_member(builder::ident_expr(dep), builder::string_value(name))
+            }
+        },
+    }
+}
+
+/// Replace free references to imported bindings with member accesses on the dependency.
+///
+/// NOTE: 导入的绑定是 live binding，所以每一次引用都要重新读取 `_dep.name`；
+///       被调用时改写为 `(0, _dep.name)()`，避免 `this` 变成依赖的 exports 对象。
+pub struct ImportRewriter<'a> {
+    scopes: &'a ScopeTree,
+    imports: HashMap<String, (String, ImportName)>,
+}
+
+impl<'a> ImportRewriter<'a> {
+    /// `imports` maps local names to the dependency variable and the imported name.
+    pub fn new(scopes: &'a ScopeTree, imports: HashMap<String, (String, ImportName)>) -> Self {
+        Self { scopes, imports }
+    }
+
+    pub fn rewrite(&mut self, body: &mut Vec<owned::Statement>) {
+        self.visit_statements(body);
+    }
+
+    fn replacement(&self, ident: &owned::Identifier) -> Option<owned::Expression> {
+        if ident.loc.is_dummy() || self.scopes.binding_at(ident.loc.start).is_some() {
+            return None;
+        }
+
+        self.imports.get(ident.name()).map(|&(ref dep, ref name)| import_member(dep, name))
+    }
+
+    fn callee(&self, callee: &owned::Expression) -> Option<owned::Expression> {
+        match *callee {
+            owned::Expression::Identifier(ref ident) => {
+                self.replacement(ident).map(|member| builder::parenthesized(builder::comma(vec![ builder::number(0), member ])))
+            },
+            _ => None,
+        }
+    }
+}
+
+impl<'a> VisitMut for ImportRewriter<'a> {
+    fn visit_expression(&mut self, expr: &mut owned::Expression) {
+        match *expr {
+            owned::Expression::Identifier(ref ident) => {
+                if let Some(replacement) = self.replacement(ident) {
+                    *expr = replacement;
+                }
+                return;
+            },
+            owned::Expression::Call(ref mut call) => {
+                if let Some(callee) = self.callee(&call.callee) {
+                    call.callee = callee;
+                }
+            },
+            owned::Expression::TaggedTemplate(ref mut tagged) => {
+                if let Some(tag) = self.callee(&tagged.tag) {
+                    tagged.tag = tag;
+                }
+            },
+            _ => { },
+        }
+
+        visit::walk_expression(self, expr)
+    }
+
+    fn visit_object_property(&mut self, prop: &mut owned::ObjectProperty) {
+        // { a }   →   { a: _dep.a }
+        let replacement = match *prop {
+            owned::ObjectProperty::Identifier(ref ident) => self.replacement(ident).map(|value| (ident.clone(), value)),
+            _ => return visit::walk_object_property(self, prop),
+        };
+
+        if let Some((key, value)) = replacement {
+            *prop = builder::property(owned::PropertyName::Identifier(key), value);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+// Module resolution
+// https://nodejs.org/api/modules.html#modules_all_together
+// https://nodejs.org/api/packages.html#packages_package_entry_points
+//
+// NOTE: 相对路径（`./a`、`../a`、`/a`）相对于导入者所在的目录解析，依次尝试文件本身、
+//       加上扩展名、以及作为目录（`package.json` 的 `module`/`main`，然后是 `index.js`）。
+//       其它的视为包名，从导入者所在的目录开始逐级向上查找 `node_modules/<name>`，
+//       包的 `package.json` 中有 `exports` 时只使用 `exports`。
+//
+//       `package.json` 的 `sideEffects` 为 `false` 时，包中的模块都没有副作用；
+//       为数组时，只有匹配其中的 glob（相对于包的目录，不含 `/` 的匹配文件名）的模块有副作用。
+//
+//       `serde_json` 的对象不保留键的顺序，所以 `exports` 的条件按 `conditions` 的优先级匹配，
+//       而不是按对象中出现的顺序。
+
+use crate::error::{ ErrorKind, Error, };
+
+use serde_json::Value;
+use regex::Regex;
+
+use std::fs;
+use std::path::{ Path, PathBuf, Component, };
+
+
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    extensions: Vec<String>,
+    main_fields: Vec<String>,
+    conditions: Vec<String>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self {
+            extensions: vec![ ".js".to_string(), ".mjs".to_string() ],
+            main_fields: vec![ "module".to_string(), "main".to_string() ],
+            conditions: vec![ "import".to_string(), "module".to_string(), "default".to_string() ],
+        }
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Extensions tried on paths without one, defaults to `.js`, `.mjs`.
+    pub fn set_extensions(&mut self, extensions: Vec<String>) {
+        self.extensions = extensions;
+    }
+
+    /// `exports` conditions by priority, defaults to `import`, `module`, `default`.
+    pub fn set_conditions(&mut self, conditions: Vec<String>) {
+        self.conditions = conditions;
+    }
+
+    /// Resolve `specifier` imported by the module at `importer`.
+    pub fn resolve(&self, specifier: &str, importer: &Path) -> Result<PathBuf, Error> {
+        let dir = importer.parent().unwrap_or_else(|| Path::new(""));
+
+        let resolved = if specifier.starts_with("./") || specifier.starts_with("../") || specifier.starts_with('/')
+            || specifier == "." || specifier == ".." {
+            self.file_or_directory(&dir.join(specifier))
+        } else {
+            self.package(specifier, dir)?
+        };
+
+        match resolved {
+            Some(path) => Ok(normalize(&path)),
+            None => Err(Error::new(ErrorKind::InternalError,
+                                   format!("Cannot find module '{}' from '{}'", specifier, importer.display()))),
+        }
+    }
+
+    /// Whether evaluating the module at `path` may have side effects, see `sideEffects` in `package.json`.
+    pub fn has_side_effects(&self, path: &Path) -> bool {
+        for dir in path.ancestors().skip(1) {
+            let package = match read_package(dir) {
+                Some(package) => package,
+                None => continue,
+            };
+
+            return match package.get("sideEffects") {
+                Some(&Value::Bool(side_effects)) => side_effects,
+                Some(&Value::Array(ref patterns)) => {
+                    let relative = path.strip_prefix(dir).unwrap_or(path).to_string_lossy().replace('\\', "/");
+                    patterns.iter()
+                        .filter_map(|pattern| pattern.as_str())
+                        .any(|pattern| glob(pattern).map(|re| re.is_match(&relative)).unwrap_or(true))
+                },
+                _ => true,
+            };
+        }
+
+        true
+    }
+
+    fn file(&self, path: &Path) -> Option<PathBuf> {
+        if path.is_file() {
+            return Some(path.to_path_buf());
+        }
+
+        self.extensions.iter()
+            .map(|ext| PathBuf::from(format!("{}{}", path.display(), ext)))
+            .find(|path| path.is_file())
+    }
+
+    fn directory(&self, path: &Path) -> Option<PathBuf> {
+        if !path.is_dir() {
+            return None;
+        }
+
+        if let Some(package) = read_package(path) {
+            for field in self.main_fields.iter() {
+                if let Some(main) = package.get(field).and_then(|v| v.as_str()) {
+                    let main = path.join(main);
+                    if let Some(resolved) = self.file(&main).or_else(|| self.index(&main)) {
+                        return Some(resolved);
+                    }
+                }
+            }
+        }
+
+        self.index(path)
+    }
+
+    fn index(&self, path: &Path) -> Option<PathBuf> {
+        self.extensions.iter()
+            .map(|ext| path.join(format!("index{}", ext)))
+            .find(|path| path.is_file())
+    }
+
+    fn file_or_directory(&self, path: &Path) -> Option<PathBuf> {
+        self.file(path).or_else(|| self.directory(path))
+    }
+
+    fn package(&self, specifier: &str, dir: &Path) -> Result<Option<PathBuf>, Error> {
+        // NOTE: `@scope/name/sub/path` 的包名包含两段
+        let name_len = if specifier.starts_with('@') { 2 } else { 1 };
+        let parts = specifier.split('/').collect::<Vec<&str>>();
+        if parts.len() < name_len {
+            return Ok(None);
+        }
+
+        let name = parts[..name_len].join("/");
+        let subpath = if parts.len() == name_len { ".".to_string() } else { format!("./{}", parts[name_len..].join("/")) };
+
+        self.node_modules(&name, &subpath, dir)
+    }
+
+    fn node_modules(&self, name: &str, subpath: &str, dir: &Path) -> Result<Option<PathBuf>, Error> {
+        for ancestor in dir.ancestors() {
+            let root = ancestor.join("node_modules").join(name);
+            if !root.is_dir() {
+                continue;
+            }
+
+            if let Some(exports) = read_package(&root).and_then(|package| package.get("exports").cloned()) {
+                return match self.exports(&exports, subpath) {
+                    Some(target) => Ok(Some(root.join(target)).filter(|path| path.is_file())),
+                    None => Err(Error::new(ErrorKind::InternalError,
+                                           format!("Package subpath '{}' is not defined by \"exports\" in {}", subpath, root.display()))),
+                };
+            }
+
+            return Ok(if subpath == "." { self.directory(&root) } else { self.file_or_directory(&root.join(subpath)) });
+        }
+
+        Ok(None)
+    }
+
+    /// The target of `subpath` in the `exports` field.
+    fn exports(&self, exports: &Value, subpath: &str) -> Option<String> {
+        let is_subpath_map = match *exports {
+            Value::Object(ref map) => map.keys().all(|key| key.starts_with('.')),
+            _ => false,
+        };
+
+        if !is_subpath_map {
+            // NOTE: `"exports": "./index.js"` 和 `"exports": { "import": ... }` 都是 `"."` 的简写
+            return if subpath == "." { self.target(exports, None) } else { None };
+        }
+
+        let map = exports.as_object()?;
+        if let Some(target) = map.get(subpath) {
+            return self.target(target, None);
+        }
+
+        // NOTE: `"./features/*": "./src/features/*.js"`，最长的前缀优先
+        let mut patterns = map.keys()
+            .filter(|key| key.matches('*').count() == 1)
+            .filter_map(|key| {
+                let star = key.find('*')?;
+                let (prefix, suffix) = (&key[..star], &key[star + 1..]);
+                if subpath.len() >= prefix.len() + suffix.len() && subpath.starts_with(prefix) && subpath.ends_with(suffix) {
+                    Some((prefix.len(), key, &subpath[prefix.len()..subpath.len() - suffix.len()]))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        patterns.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        let (_, key, matched) = patterns.into_iter().next()?;
+        self.target(&map[key], Some(matched))
+    }
+
+    fn target(&self, target: &Value, matched: Option<&str>) -> Option<String> {
+        match *target {
+            Value::String(ref target) => {
+                if !target.starts_with("./") {
+                    return None;
+                }
+                Some(match matched {
+                    Some(matched) => target.replace('*', matched),
+                    None => target.clone(),
+                })
+            },
+            Value::Array(ref targets) => targets.iter().filter_map(|target| self.target(target, matched)).next(),
+            Value::Object(ref conditions) => {
+                self.conditions.iter()
+                    .filter_map(|condition| conditions.get(condition))
+                    .filter_map(|target| self.target(target, matched))
+                    .next()
+            },
+            _ => None,
+        }
+    }
+}
+
+fn read_package(dir: &Path) -> Option<Value> {
+    let content = fs::read_to_string(dir.join("package.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// `*.css`, `./src/polyfill.js`, `src/**/*.js`
+fn glob(pattern: &str) -> Option<Regex> {
+    let pattern = pattern.trim_start_matches("./");
+    let mut re = String::from(if pattern.contains('/') { "^" } else { "(?:^|/)" });
+
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    re.push_str("(?:.*/)?");
+                } else {
+                    re.push_str(".*");
+                }
+            },
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+
+    Regex::new(&re).ok()
+}
+
+/// Remove `.` and `..` components without touching the file system.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => { },
+            Component::ParentDir => {
+                if !result.pop() {
+                    result.push("..");
+                }
+            },
+            component => result.push(component.as_os_str()),
+        }
+    }
+
+    result
+}
@@ -0,0 +1,281 @@
+// Tree shaking
+//
+// NOTE: 从入口开始沿着依赖图传播每个模块被使用的导出：
+//          import { a } from "m"            引用了 `a` 才算使用 `m` 的 `a`
+//          import * as ns from "m"          `ns.a`、`ns["a"]` 使用 `a`，其它任何形式的引用都视为使用全部导出
+//          export { a as b } from "m"       `b` 被使用时才使用 `m` 的 `a`
+//          export * from "m"                本模块没有定义的名字都可能来自 `m`
+//
+//       没有副作用（`package.json` 的 `sideEffects`）并且没有导出被使用的模块不会被打包，
+//       未被使用的导出不再挂到 `_exports` 上，之后由死代码消除删除对应的声明。
+
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::compiler::scope::ScopeTree;
+use crate::compiler::optimize::Constant;
+
+use super::{ ModuleGraph, Resolver, };
+use super::module::{ ImportName, Export, };
+
+use std::collections::{ HashMap, HashSet, };
+
+
+/// The exports of a module that are used.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Usage {
+    all: bool,
+    names: HashSet<String>,
+}
+
+impl Usage {
+    pub fn all() -> Self {
+        Self { all: true, names: HashSet::new() }
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.all
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.all && self.names.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.all || self.names.contains(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item=&String> {
+        self.names.iter()
+    }
+
+    /// Returns whether `self` changed.
+    pub fn insert(&mut self, name: &str) -> bool {
+        !self.all && self.names.insert(name.to_string())
+    }
+
+    /// Returns whether `self` changed.
+    pub fn extend(&mut self, other: &Usage) -> bool {
+        if self.all {
+            return false;
+        }
+        if other.all {
+            self.all = true;
+            self.names.clear();
+            return true;
+        }
+
+        let before = self.names.len();
+        self.names.extend(other.names.iter().cloned());
+        self.names.len() != before
+    }
+}
+
+
+/// Exports used through the references to the imported bindings of a module, by local name.
+struct ImportReferences<'a> {
+    scopes: &'a ScopeTree,
+    imports: &'a HashMap<String, ImportName>,
+    used: HashMap<String, Usage>,
+}
+
+impl<'a> ImportReferences<'a> {
+    fn import(&self, ident: &Identifier) -> Option<&'a ImportName> {
+        if ident.loc.is_dummy() || self.scopes.binding_at(ident.loc.start).is_some() {
+            return None;
+        }
+
+        self.imports.get(ident.name())
+    }
+
+    fn reference(&mut self, ident: &Identifier) {
+        let usage = match self.import(ident) {
+            Some(&ImportName::Named(ref name)) => {
+                let mut usage = Usage::default();
+                usage.insert(name);
+                usage
+            },
+            Some(&ImportName::Namespace) => Usage::all(),
+            None => return,
+        };
+
+        self.used.entry(ident.name().to_string()).or_insert_with(Usage::default).extend(&usage);
+    }
+}
+
+impl<'a> VisitMut for ImportReferences<'a> {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        match *expr {
+            Expression::Identifier(ref ident) => return self.reference(ident),
+            Expression::Member(ref mut member) => {
+                // ns.a   ns["a"]
+                let namespace = match member.left {
+                    Expression::Identifier(ref ident) if self.import(ident) == Some(&ImportName::Namespace) => Some(ident.name().to_string()),
+                    _ => None,
+                };
+                let property = match (member.computed, &member.right) {
+                    (false, &Expression::Identifier(ref ident)) => Some(ident.name().to_string()),
+                    (true, right) => match Constant::from_expression(right) {
+                        Some(Constant::String(value)) => Some(value),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+
+                if let (Some(namespace), Some(property)) = (namespace, property) {
+                    self.used.entry(namespace).or_insert_with(Usage::default).insert(&property);
+                    return;
+                }
+            },
+            _ => { },
+        }
+
+        visit::walk_expression(self, expr)
+    }
+
+    fn visit_object_property(&mut self, prop: &mut ObjectProperty) {
+        match *prop {
+            ObjectProperty::Identifier(ref ident) => self.reference(ident),
+            _ => visit::walk_object_property(self, prop),
+        }
+    }
+}
+
+
+/// The result of tree shaking a module graph, indexed by module id.
+#[derive(Debug)]
+pub struct TreeShaking {
+    /// Modules that must be bundled.
+    pub included: Vec<bool>,
+    /// Exports of each module that are used by the bundled modules.
+    pub used: Vec<Usage>,
+}
+
+impl TreeShaking {
+    /// Nothing is removed, every module and every export is kept.
+    pub fn none(graph: &ModuleGraph) -> Self {
+        Self { included: vec![ true; graph.modules.len() ], used: vec![ Usage::all(); graph.modules.len() ] }
+    }
+
+    pub fn new(graph: &ModuleGraph, resolver: &Resolver) -> Self {
+        let modules = &graph.modules;
+        let side_effects = modules.iter().map(|module| resolver.has_side_effects(&module.path)).collect::<Vec<bool>>();
+
+        // NOTE: 直接导入的使用情况只取决于模块本身，只需要计算一次
+        let direct = modules.iter().map(|module| {
+            let syntax = &module.parsed.syntax;
+            let imports = syntax.imports.iter().map(|import| (import.local.clone(), import.name.clone())).collect::<HashMap<_, _>>();
+            let mut references = ImportReferences { scopes: &module.parsed.scopes, imports: &imports, used: HashMap::new() };
+            references.visit_statements(&mut module.parsed.body.clone());
+
+            let mut used = vec![ Usage::default(); syntax.requests.len() ];
+            for import in syntax.imports.iter() {
+                if let Some(usage) = references.used.get(&import.local) {
+                    used[import.request].extend(usage);
+                }
+            }
+            used
+        }).collect::<Vec<Vec<Usage>>>();
+
+        let mut included = vec![ false; modules.len() ];
+        let mut used = vec![ Usage::default(); modules.len() ];
+        if modules.is_empty() {
+            return Self { included, used };
+        }
+
+        included[0] = true;
+        used[0] = Usage::all();
+
+        let mut queue = vec![ 0 ];
+        while let Some(id) = queue.pop() {
+            let syntax = &modules[id].parsed.syntax;
+            let mut requests = direct[id].clone();
+
+            let locals = syntax.exports.iter()
+                .filter_map(|export| match *export {
+                    Export::Local { ref exported, .. } | Export::Indirect { ref exported, .. } => Some(exported.as_str()),
+                    Export::Star { .. } => None,
+                })
+                .collect::<HashSet<&str>>();
+            for export in syntax.exports.iter() {
+                match *export {
+                    // NOTE: `export { a }` 导出导入的绑定 `a`
+                    Export::Local { ref exported, ref local } if used[id].contains(exported) => {
+                        for import in syntax.imports.iter().filter(|import| &import.local == local) {
+                            match import.name {
+                                ImportName::Named(ref name) => { requests[import.request].insert(name); },
+                                ImportName::Namespace => { requests[import.request].extend(&Usage::all()); },
+                            }
+                        }
+                    },
+                    Export::Indirect { ref exported, request, ref name } if used[id].contains(exported) => {
+                        match *name {
+                            ImportName::Named(ref name) => { requests[request].insert(name); },
+                            ImportName::Namespace => { requests[request].extend(&Usage::all()); },
+                        }
+                    },
+                    Export::Star { request } => {
+                        if used[id].is_all() {
+                            requests[request].extend(&Usage::all());
+                        }
+                        for name in used[id].names().filter(|name| name.as_str() != "default" && !locals.contains(name.as_str())) {
+                            requests[request].insert(name);
+                        }
+                    },
+                    _ => { },
+                }
+            }
+
+            for (request, usage) in requests.iter().enumerate() {
+                let dep = modules[id].dependencies[request];
+                let mut changed = used[dep].extend(usage);
+                if !included[dep] && (side_effects[dep] || !used[dep].is_empty()) {
+                    included[dep] = true;
+                    changed = true;
+                }
+                if changed && included[dep] {
+                    queue.push(dep);
+                }
+            }
+        }
+
+        Self { included, used }
+    }
+}
+
+
+#[test]
+fn test_tree_shaking() {
+    use super::Bundler;
+    use std::fs;
+
+    let root = std::env::temp_dir().join(format!("ecmascript-tree-shaking-{}", std::process::id()));
+    let write = |path: &str, content: &str| {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+
+    write("src/main.js", "import * as utils from \"utils\";\nimport { used } from './local';\nutils.add(used);\n");
+    write("src/local.js", "export function used() {}\nexport function unused() {}\n");
+    write("node_modules/utils/package.json", r#"{ "main": "index.js", "sideEffects": false }"#);
+    write("node_modules/utils/index.js", "export * from './math';\nexport { format } from './format';\n");
+    write("node_modules/utils/math.js", "export function add(a) { a; }\nexport function sub(a) { a; }\n");
+    write("node_modules/utils/format.js", "export function format(a) { a; }\n");
+
+    let bundle = Bundler::new().bundle(root.join("src/main.js")).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+
+    // NOTE: `format.js` 没有副作用，也没有导出被使用
+    let included = bundle.included.iter().map(|&id| bundle.modules[id].strip_prefix(&root).unwrap().to_path_buf()).collect::<Vec<_>>();
+    assert_eq!(included, vec![
+        std::path::PathBuf::from("src/main.js"),
+        std::path::PathBuf::from("node_modules/utils/index.js"),
+        std::path::PathBuf::from("node_modules/utils/math.js"),
+        std::path::PathBuf::from("src/local.js"),
+    ]);
+    assert!(bundle.code.contains("function add(a)"), "{}", bundle.code);
+    assert!(bundle.code.contains("function used()"), "{}", bundle.code);
+    assert!(!bundle.code.contains("sub"), "{}", bundle.code);
+    assert!(!bundle.code.contains("unused"), "{}", bundle.code);
+    assert!(!bundle.code.contains("format"), "{}", bundle.code);
+}
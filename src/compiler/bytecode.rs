@@ -1,14 +1,963 @@
+// Bytecode
+//
+//      function add(a, b) { return a + b; }
+//
+//      0000  GetLocal slot=0
+//      0002  GetLocal slot=1
+//      0004  Add
+//      0005  Return
+//
+// NOTE: 基于栈的指令集，每个函数编译为一个 `CodeObject`（指令、常量表、异常处理表、行号表）。
+//       指令编码为一个字节的操作码加上操作数：
+//          u8     单个字节
+//          u32    常量、变量槽等索引，无符号 LEB128
+//          i64    整数字面量，zigzag + LEB128
+//          i32    跳转偏移，固定 4 个字节（小端），相对于 *下一条* 指令的起始位置，方便回填
+//
+//       操作码按照在 `instructions!` 中出现的顺序编号，新的指令只能添加在末尾。
+//
+//       栈的约定（注释中 `a b -> c` 表示弹出 `a`、`b`，压入 `c`）：
+//          * 所有的写入指令（`SetLocal`、`SetNamed` ...）都保留写入的值，语句需要再 `Pop`
+//          * 调用的栈布局为 `callee this arg0 .. argN`
+//          * 被闭包捕获的变量保存在 Cell 中（`NewCell`/`GetCell`/`SetCell`），
+//            闭包创建时按照 `CodeObject::captures` 捕获 Cell，函数内通过 `GetUpvalue`/`SetUpvalue` 访问
+//          * `Hole` 是 TDZ 中的 `let`/`const`/`class` 绑定的值，也用于表示数组的空位、没有父类的 class
 
-#[repr(u8)]
-#[derive(Debug)]
-pub enum Instruction {
-    SourceMap,
-    Push,
+use crate::error::{ ErrorKind, Error, };
+use crate::lexer::span::Loc;
+
+use std::fmt;
+
+
+/// Functions nested deeper than this are rejected by `CodeObject::decode` and the verifier.
+// NOTE: 解码、检查、编码与释放都会递归处理内层函数，调试版本每一层大约使用 16KB 的栈，
+//       64 层在 2MB 的线程栈（测试线程的默认大小）上仍有余量。
+pub const MAX_FUNCTION_NESTING: usize = 64;
+
+pub trait Operand: Sized + Copy {
+    fn encode(self, output: &mut Vec<u8>);
+    fn decode(code: &[u8], pos: &mut usize) -> Result<Self, Error>;
+}
+
+fn truncated() -> Error {
+    Error::new(ErrorKind::InternalError, "truncated bytecode")
+}
+
+impl Operand for u8 {
+    fn encode(self, output: &mut Vec<u8>) {
+        output.push(self);
+    }
+
+    fn decode(code: &[u8], pos: &mut usize) -> Result<Self, Error> {
+        let byte = *code.get(*pos).ok_or_else(truncated)?;
+        *pos += 1;
+        Ok(byte)
+    }
+}
+
+impl Operand for u32 {
+    fn encode(self, output: &mut Vec<u8>) {
+        write_uleb128(output, u64::from(self));
+    }
+
+    fn decode(code: &[u8], pos: &mut usize) -> Result<Self, Error> {
+        let value = read_uleb128(code, pos)?;
+        if value > u64::from(u32::max_value()) {
+            return Err(Error::new(ErrorKind::InternalError, "bytecode operand out of range"));
+        }
+        Ok(value as u32)
+    }
+}
+
+impl Operand for i64 {
+    fn encode(self, output: &mut Vec<u8>) {
+        write_uleb128(output, ((self << 1) ^ (self >> 63)) as u64);
+    }
+
+    fn decode(code: &[u8], pos: &mut usize) -> Result<Self, Error> {
+        let value = read_uleb128(code, pos)?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+}
+
+// NOTE: 跳转偏移使用固定宽度，生成代码时先写入 0，确定目标之后再回填
+impl Operand for i32 {
+    fn encode(self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(code: &[u8], pos: &mut usize) -> Result<Self, Error> {
+        let bytes = code.get(*pos..*pos + 4).ok_or_else(truncated)?;
+        *pos += 4;
+        Ok(i32::from_le_bytes([ bytes[0], bytes[1], bytes[2], bytes[3] ]))
+    }
+}
+
+pub fn write_uleb128(output: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            output.push(byte);
+            return;
+        }
+        output.push(byte | 0x80);
+    }
+}
+
+pub fn read_uleb128(code: &[u8], pos: &mut usize) -> Result<u64, Error> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *code.get(*pos).ok_or_else(truncated)?;
+        *pos += 1;
+        if shift >= 64 || (shift == 63 && byte > 1) {
+            return Err(Error::new(ErrorKind::InternalError, "LEB128 value overflows"));
+        }
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+/// A length prefix, checked against the remaining input.
+fn read_len(input: &[u8], pos: &mut usize) -> Result<usize, Error> {
+    let len = read_uleb128(input, pos)?;
+    if len > (input.len() - *pos) as u64 {
+        return Err(truncated());
+    }
+    Ok(len as usize)
+}
+
+fn write_str(output: &mut Vec<u8>, value: &str) {
+    write_uleb128(output, value.len() as u64);
+    output.extend_from_slice(value.as_bytes());
+}
+
+fn read_str(input: &[u8], pos: &mut usize) -> Result<String, Error> {
+    let len = read_len(input, pos)?;
+    let value = ::std::str::from_utf8(&input[*pos..*pos + len])
+        .map_err(|_| Error::new(ErrorKind::InternalError, "invalid UTF-8 in bytecode"))?
+        .to_string();
+    *pos += len;
+    Ok(value)
+}
+
+
+macro_rules! instructions {
+    ( $( $(#[$attr:meta])* $name:ident $( { $( $field:ident : $ty:ty ),* } )* , )* ) => {
+        #[repr(u8)]
+        #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+        pub enum Opcode {
+            $( $name, )*
+        }
+
+        const OPCODES: &[Opcode] = &[ $( Opcode::$name, )* ];
+
+        impl Opcode {
+            pub fn from_u8(byte: u8) -> Option<Opcode> {
+                OPCODES.get(byte as usize).cloned()
+            }
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $( Opcode::$name => stringify!($name), )*
+                }
+            }
+
+            /// Names of the operands, in encoding order.
+            pub fn operands(self) -> &'static [&'static str] {
+                match self {
+                    $( Opcode::$name => &[ $( $( stringify!($field), )* )* ], )*
+                }
+            }
+        }
+
+        #[derive(Debug, PartialEq, Clone, Copy)]
+        pub enum Instruction {
+            $( $(#[$attr])* $name $( ( $( $ty ),* ) )*, )*
+        }
+
+        impl Instruction {
+            pub fn opcode(&self) -> Opcode {
+                match *self {
+                    $( Instruction::$name { .. } => Opcode::$name, )*
+                }
+            }
+
+            pub fn encode(&self, output: &mut Vec<u8>) {
+                output.push(self.opcode() as u8);
+                match *self {
+                    $( Instruction::$name $( ( $( $field ),* ) )* => { $( $( Operand::encode($field, output); )* )* }, )*
+                }
+            }
+
+            /// Decode the instruction at `pos`, returns it with the offset of the next instruction.
+            pub fn decode(code: &[u8], pos: usize) -> Result<(Instruction, usize), Error> {
+                let mut next = pos;
+                let byte = <u8 as Operand>::decode(code, &mut next)?;
+                let opcode = Opcode::from_u8(byte)
+                    .ok_or_else(|| Error::new(ErrorKind::InternalError, format!("unknown opcode 0x{:02x} at {}", byte, pos)))?;
+
+                let instruction = match opcode {
+                    $( Opcode::$name => Instruction::$name $( ( $( <$ty as Operand>::decode(code, &mut next)? ),* ) )*, )*
+                };
+
+                Ok((instruction, next))
+            }
+
+            /// Operand values, in encoding order.
+            pub fn operands(&self) -> Vec<i64> {
+                match *self {
+                    $( Instruction::$name $( ( $( $field ),* ) )* => vec![ $( $( i64::from($field), )* )* ], )*
+                }
+            }
+        }
+    }
+}
+
+instructions! {
+    Nop,
+
+    // Literals
+    /// `-> undefined`
+    Undefined,
+    Null,
+    True,
+    False,
+    /// `-> <hole>`
+    Hole,
+    Int { value: i64 },
+    /// Number, String or BigInt constant.
+    Const { index: u32 },
+    This,
+    NewTarget,
+    /// `-> function`, the constant is a `CodeObject`.
+    Closure { index: u32 },
+    /// `-> regexp`, a new object every time.
+    RegExp { index: u32 },
+    /// `-> strings`, the same frozen array for a template literal every time.
+    TemplateObject { index: u32 },
+    /// `-> {}`
+    Object,
+    /// `v0 .. vN -> [v0 .. vN]`
+    Array { count: u32 },
+
+    // Stack
+    Pop,
+    /// `a -> a a`
+    Dup,
+    /// `a b -> a b a b`
+    Dup2,
+    /// `a b -> b a`
+    Swap,
+    /// `a b c -> c a b`
+    Rot3,
+
+    // Variables
+    GetLocal { slot: u32 },
+    SetLocal { slot: u32 },
+    /// Store a new Cell holding `<hole>` in the local slot.
+    NewCell { slot: u32 },
+    GetCell { slot: u32 },
+    SetCell { slot: u32 },
+    GetUpvalue { index: u32 },
+    SetUpvalue { index: u32 },
+    /// `v -> v`, throws a ReferenceError when `v` is `<hole>`.
+    CheckHole { name: u32 },
+    /// Global ( or `with` ) variables, looked up by name.
+    GetName { name: u32 },
+    SetName { name: u32 },
+    /// `-> type`, unresolvable references are `"undefined"`.
+    TypeofName { name: u32 },
+    DeleteName { name: u32 },
+    /// Create the `var` binding if it does not exist, in the global object for Scripts
+    /// and in the environment of the running function otherwise.
+    DeclareName { name: u32 },
+    /// `-> arguments`
+    Arguments,
+    /// `-> [arguments[start] ..]`
+    Rest { start: u32 },
+
+    // Properties
+    /// `object -> value`, `cache` is the index of the inline cache of the instruction.
+    GetNamed { name: u32, cache: u32 },
+    /// `object value -> value`
+    SetNamed { name: u32, cache: u32 },
+    /// `object key -> value`
+    GetKeyed,
+    /// `object key value -> value`
+    SetKeyed,
+    /// `object -> boolean`
+    DeleteNamed { name: u32 },
+    /// `object key -> boolean`
+    DeleteKeyed,
+    /// `key -> value`, `super[key]`
+    GetSuper,
+    /// `key value -> value`
+    SetSuper,
+    /// `object key value -> object`
+    DefineField,
+    /// `object value -> object`
+    DefineNamed { name: u32 },
+    /// `object key function -> object`, also sets the home object of the function.
+    DefineMethod { enumerable: u8 },
+    DefineGetter { enumerable: u8 },
+    DefineSetter { enumerable: u8 },
+    /// `object source -> object`, `{ ...source }`
+    CopyDataProperties,
+    /// `object prototype -> object`, `{ __proto__: prototype }`
+    SetPrototype,
+    /// `array value -> array`
+    ArrayPush,
+    /// `array -> array`
+    ArrayHole,
+    /// `array iterable -> array`
+    ArraySpread,
+    /// `heritage constructor -> constructor prototype`, `heritage` is `<hole>` without `extends`.
+    Class { name: u32 },
+
+    // Operators
+    /// `a b -> a + b`
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Exp,
+    Shl,
+    Shr,
+    UShr,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Eq,
+    Ne,
+    StrictEq,
+    StrictNe,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    InstanceOf,
+    /// `a -> -a`
+    Neg,
+    /// `a -> +a`
+    Plus,
+    Not,
+    BitNot,
+    TypeOf,
+    Inc,
+    Dec,
+    ToNumeric,
+    ToPropertyKey,
+    ToString,
+
+    // Control flow
+    Jump { offset: i32 },
+    /// `condition ->`
+    JumpIfTrue { offset: i32 },
+    JumpIfFalse { offset: i32 },
+    /// `value ->`
+    JumpIfNullish { offset: i32 },
+    JumpIfNotNullish { offset: i32 },
+    /// `value ->`
+    Return,
+    /// `value ->`, unwinds to the innermost handler covering the instruction.
+    Throw,
+    /// Throws a new `ErrorKind` error with a String constant as message.
+    ThrowError { kind: u8, message: u32 },
+    Debugger,
+
+    // Calls
+    /// `callee this arg0 .. argN -> result`
+    Call { argc: u32 },
+    /// `callee this arguments -> result`
+    CallSpread,
+    /// `callee arg0 .. argN -> object`
+    New { argc: u32 },
+    /// `callee arguments -> object`
+    NewSpread,
+    /// `arg0 .. argN -> this`
+    SuperCall { argc: u32 },
+    SuperCallSpread,
+
+    // Environments
+    /// `object ->`
+    EnterWith,
+    LeaveWith,
+
+    // Iterators
+    /// `iterable -> iterator`
+    GetIterator,
+    /// `iterable -> iterator`, `IteratorNext` awaits the results of the iterator.
+    GetAsyncIterator,
+    /// `iterator -> iterator value done`
+    IteratorNext,
+    /// `iterator ->`
+    IteratorClose,
+    /// `object -> iterator`, the iterator yields the enumerable keys for `for-in`.
+    ForInEnumerate,
+
+    // Generators and async functions
+    /// Suspend right after the arguments are bound, `generator()` returns at this point.
+    InitialYield,
+    /// `value -> received`
+    Yield,
+    /// `value -> resolved`
+    Await,
+
+    /// `-> function`, the running function.
+    Callee,
+}
+
+
+pub fn error_kind_to_u8(kind: ErrorKind) -> u8 {
+    match kind {
+        ErrorKind::SyntaxError => 0,
+        ErrorKind::EvalError => 1,
+        ErrorKind::RangeError => 2,
+        ErrorKind::ReferenceError => 3,
+        ErrorKind::TypeError => 4,
+        ErrorKind::URIError => 5,
+        ErrorKind::InternalError => 6,
+    }
+}
+
+pub fn error_kind_from_u8(kind: u8) -> Option<ErrorKind> {
+    match kind {
+        0 => Some(ErrorKind::SyntaxError),
+        1 => Some(ErrorKind::EvalError),
+        2 => Some(ErrorKind::RangeError),
+        3 => Some(ErrorKind::ReferenceError),
+        4 => Some(ErrorKind::TypeError),
+        5 => Some(ErrorKind::URIError),
+        6 => Some(ErrorKind::InternalError),
+        _ => None,
+    }
+}
+
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Constant {
+    Number(f64),
+    String(String),
+    /// Decimal digits.
+    BigInt(String),
+    RegExp { pattern: String, flags: String },
+    /// `cooked` is `None` for invalid escapes in tagged templates.
+    Template { cooked: Vec<Option<String>>, raw: Vec<String> },
+    Function(Box<CodeObject>),
+}
+
+impl Constant {
+    pub fn encode(&self, output: &mut Vec<u8>) {
+        match *self {
+            Constant::Number(n) => {
+                output.push(0);
+                output.extend_from_slice(&n.to_bits().to_le_bytes());
+            },
+            Constant::String(ref s) => { output.push(1); write_str(output, s); },
+            Constant::BigInt(ref s) => { output.push(2); write_str(output, s); },
+            Constant::RegExp { ref pattern, ref flags } => {
+                output.push(3);
+                write_str(output, pattern);
+                write_str(output, flags);
+            },
+            Constant::Template { ref cooked, ref raw } => {
+                output.push(4);
+                write_uleb128(output, raw.len() as u64);
+                for (cooked, raw) in cooked.iter().zip(raw.iter()) {
+                    match *cooked {
+                        Some(ref cooked) => { output.push(1); write_str(output, cooked); },
+                        None => output.push(0),
+                    }
+                    write_str(output, raw);
+                }
+            },
+            Constant::Function(ref code) => { output.push(5); code.encode(output); },
+        }
+    }
+
+    pub fn decode(input: &[u8], pos: &mut usize) -> Result<Self, Error> {
+        Constant::decode_nested(input, pos, 0)
+    }
+
+    /// `depth` is the nesting depth of the function which the constant belongs to.
+    fn decode_nested(input: &[u8], pos: &mut usize, depth: usize) -> Result<Self, Error> {
+        let constant = match <u8 as Operand>::decode(input, pos)? {
+            0 => {
+                let bytes = input.get(*pos..*pos + 8).ok_or_else(truncated)?;
+                *pos += 8;
+                let mut bits = [0u8; 8];
+                bits.copy_from_slice(bytes);
+                Constant::Number(f64::from_bits(u64::from_le_bytes(bits)))
+            },
+            1 => Constant::String(read_str(input, pos)?),
+            2 => Constant::BigInt(read_str(input, pos)?),
+            3 => Constant::RegExp { pattern: read_str(input, pos)?, flags: read_str(input, pos)? },
+            4 => {
+                let len = read_len(input, pos)?;
+                let (mut cooked, mut raw) = (Vec::with_capacity(len), Vec::with_capacity(len));
+                for _ in 0..len {
+                    cooked.push(match <u8 as Operand>::decode(input, pos)? {
+                        0 => None,
+                        _ => Some(read_str(input, pos)?),
+                    });
+                    raw.push(read_str(input, pos)?);
+                }
+                Constant::Template { cooked, raw }
+            },
+            5 => Constant::Function(Box::new(CodeObject::decode_nested(input, pos, depth + 1)?)),
+            tag => return Err(Error::new(ErrorKind::InternalError, format!("invalid constant tag {}", tag))),
+        };
+
+        Ok(constant)
+    }
+
+    // NOTE: 只有原始值会被合并，`0` 与 `-0`、不同的 NaN 按位比较
+    fn is_same(&self, other: &Constant) -> bool {
+        match (self, other) {
+            (&Constant::Number(a), &Constant::Number(b)) => a.to_bits() == b.to_bits(),
+            (&Constant::String(ref a), &Constant::String(ref b)) => a == b,
+            (&Constant::BigInt(ref a), &Constant::BigInt(ref b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+
+/// How a closure captures a variable of the enclosing function.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Capture {
+    /// The Cell in a local slot of the enclosing function.
+    Local(u32),
+    /// An upvalue of the enclosing function.
+    Upvalue(u32),
+}
+
+/// Exceptions thrown by the instructions in `start..end` jump to `target`,
+/// with the operand stack truncated to `stack_depth` and the exception pushed.
+///
+/// Handlers are searched in order, inner handlers come first.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Handler {
+    pub start: u32,
+    pub end: u32,
+    pub target: u32,
+    pub stack_depth: u32,
+}
+
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct CodeFlags(u8);
+
+impl CodeFlags {
+    pub const STRICT: CodeFlags = CodeFlags(1);
+    pub const ARROW: CodeFlags = CodeFlags(1 << 1);
+    pub const GENERATOR: CodeFlags = CodeFlags(1 << 2);
+    pub const ASYNC: CodeFlags = CodeFlags(1 << 3);
+    pub const CLASS_CONSTRUCTOR: CodeFlags = CodeFlags(1 << 4);
+    pub const DERIVED: CodeFlags = CodeFlags(1 << 5);
+    pub const METHOD: CodeFlags = CodeFlags(1 << 6);
+
+    const NAMES: &'static [(CodeFlags, &'static str)] = &[
+        (CodeFlags::STRICT, "strict"),
+        (CodeFlags::ARROW, "arrow"),
+        (CodeFlags::GENERATOR, "generator"),
+        (CodeFlags::ASYNC, "async"),
+        (CodeFlags::CLASS_CONSTRUCTOR, "class_constructor"),
+        (CodeFlags::DERIVED, "derived"),
+        (CodeFlags::METHOD, "method"),
+    ];
+
+    pub fn empty() -> Self {
+        CodeFlags(0)
+    }
+
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        if bits >> CodeFlags::NAMES.len() == 0 { Some(CodeFlags(bits)) } else { None }
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(self, other: CodeFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: CodeFlags) {
+        self.0 |= other.0;
+    }
+}
+
+impl fmt::Debug for CodeFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = CodeFlags::NAMES.iter()
+            .filter(|&&(flag, _)| self.contains(flag))
+            .map(|&(_, name)| name)
+            .collect::<Vec<&str>>();
+        write!(f, "{}", names.join(" | "))
+    }
+}
+
+
+/// Instruction offsets to source locations, an entry covers the instructions up to the next entry.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct LineTable {
+    entries: Vec<(u32, Loc)>,
+}
+
+impl LineTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entries(&self) -> &[(u32, Loc)] {
+        &self.entries
+    }
+
+    /// Instructions from `offset` on come from `loc`, offsets must not decrease.
+    pub fn add(&mut self, offset: u32, loc: Loc) {
+        debug_assert!(self.entries.last().map(|&(last, _)| last <= offset).unwrap_or(true));
+
+        match self.entries.last_mut() {
+            Some(&mut (_, last)) if last == loc => { },
+            Some(&mut (last_offset, ref mut last)) if last_offset == offset => *last = loc,
+            _ => self.entries.push((offset, loc)),
+        }
+    }
+
+    pub fn find(&self, offset: u32) -> Option<Loc> {
+        match self.entries.binary_search_by_key(&offset, |&(start, _)| start) {
+            Ok(index) => Some(self.entries[index].1),
+            Err(0) => None,
+            Err(index) => Some(self.entries[index - 1].1),
+        }
+    }
+
+    // NOTE: 偏移量与位置都按差值编码：offset 差值（无符号）、start 差值（zigzag）、长度（无符号）
+    pub fn encode(&self, output: &mut Vec<u8>) {
+        write_uleb128(output, self.entries.len() as u64);
+
+        let (mut offset, mut start) = (0u32, 0usize);
+        for &(entry_offset, loc) in self.entries.iter() {
+            write_uleb128(output, u64::from(entry_offset - offset));
+            (loc.start as i64 - start as i64).encode(output);
+            write_uleb128(output, (loc.end - loc.start) as u64);
+            offset = entry_offset;
+            start = loc.start;
+        }
+    }
+
+    pub fn decode(input: &[u8], pos: &mut usize) -> Result<Self, Error> {
+        let invalid = || Error::new(ErrorKind::InternalError, "invalid line table");
+
+        let len = read_uleb128(input, pos)? as usize;
+        let mut entries = Vec::with_capacity(len.min(input.len()));
+        let (mut offset, mut start) = (0u64, 0i64);
+        for _ in 0..len {
+            offset += read_uleb128(input, pos)?;
+            start += <i64 as Operand>::decode(input, pos)?;
+            let length = read_uleb128(input, pos)?;
+            if offset > u64::from(u32::max_value()) || start < 0 {
+                return Err(invalid());
+            }
+            let end = (start as u64).checked_add(length).ok_or_else(invalid)?;
+            entries.push((offset as u32, Loc { start: start as usize, end: end as usize }));
+        }
+
+        Ok(LineTable { entries })
+    }
+}
+
+
+/// The compiled code of a Script, a Module or a function.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CodeObject {
+    pub name: String,
+    pub flags: CodeFlags,
+    /// Number of formal parameters without the rest parameter,
+    /// the arguments are copied to the first `param_count` local slots.
+    pub param_count: u32,
+    /// The `length` of the function, formal parameters before the first one with a default value.
+    pub length: u32,
+    /// Parameters and other variables that live in stack slots.
+    pub local_count: u32,
+    /// Number of inline caches, one for each `GetNamed` and `SetNamed` instruction.
+    pub cache_count: u32,
+    pub captures: Vec<Capture>,
+    pub code: Vec<u8>,
+    pub constants: Vec<Constant>,
+    pub handlers: Vec<Handler>,
+    pub lines: LineTable,
+}
+
+impl CodeObject {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            flags: CodeFlags::empty(),
+            param_count: 0,
+            length: 0,
+            local_count: 0,
+            cache_count: 0,
+            captures: Vec::new(),
+            code: Vec::new(),
+            constants: Vec::new(),
+            handlers: Vec::new(),
+            lines: LineTable::new(),
+        }
+    }
+
+    /// Offset of the next instruction.
+    pub fn offset(&self) -> u32 {
+        self.code.len() as u32
+    }
+
+    /// Allocate an inline cache for a `GetNamed` or `SetNamed` instruction.
+    pub fn cache(&mut self) -> u32 {
+        self.cache_count += 1;
+        self.cache_count - 1
+    }
+
+    /// Append an instruction, returns its offset.
+    pub fn emit(&mut self, instruction: Instruction, loc: Loc) -> u32 {
+        let offset = self.offset();
+        if !loc.is_dummy() {
+            self.lines.add(offset, loc);
+        }
+        instruction.encode(&mut self.code);
+        offset
+    }
+
+    /// Point the jump instruction at `at` to `target`.
+    pub fn patch_jump(&mut self, at: u32, target: u32) {
+        let (instruction, next) = Instruction::decode(&self.code, at as usize).expect("Ooops ...");
+        let offset = target as i32 - next as i32;
+        let patched = match instruction {
+            Instruction::Jump(_) => Instruction::Jump(offset),
+            Instruction::JumpIfTrue(_) => Instruction::JumpIfTrue(offset),
+            Instruction::JumpIfFalse(_) => Instruction::JumpIfFalse(offset),
+            Instruction::JumpIfNullish(_) => Instruction::JumpIfNullish(offset),
+            Instruction::JumpIfNotNullish(_) => Instruction::JumpIfNotNullish(offset),
+            _ => panic!("Ooops ... {:?} at {} is not a jump", instruction, at),
+        };
+
+        let mut encoded = Vec::with_capacity(next - at as usize);
+        patched.encode(&mut encoded);
+        self.code[at as usize..next].copy_from_slice(&encoded);
+    }
+
+    /// Add a constant to the pool, Numbers, Strings and BigInts are shared.
+    pub fn add_constant(&mut self, constant: Constant) -> u32 {
+        if let Some(index) = self.constants.iter().position(|c| c.is_same(&constant)) {
+            return index as u32;
+        }
+
+        self.constants.push(constant);
+        (self.constants.len() - 1) as u32
+    }
+
+    pub fn add_string<S: Into<String>>(&mut self, value: S) -> u32 {
+        self.add_constant(Constant::String(value.into()))
+    }
+
+    /// Decoded instructions with their offsets.
+    pub fn instructions(&self) -> Result<Vec<(u32, Instruction)>, Error> {
+        let mut instructions = Vec::new();
+        let mut pos = 0;
+        while pos < self.code.len() {
+            let (instruction, next) = Instruction::decode(&self.code, pos)?;
+            instructions.push((pos as u32, instruction));
+            pos = next;
+        }
+
+        Ok(instructions)
+    }
+
+    /// The absolute target of the jump instruction at `offset`.
+    pub fn jump_target(&self, offset: u32) -> Result<Option<u32>, Error> {
+        let (instruction, next) = Instruction::decode(&self.code, offset as usize)?;
+        let relative = match instruction {
+            Instruction::Jump(relative) | Instruction::JumpIfTrue(relative) | Instruction::JumpIfFalse(relative)
+            | Instruction::JumpIfNullish(relative) | Instruction::JumpIfNotNullish(relative) => relative,
+            _ => return Ok(None),
+        };
+
+        Ok(Some((next as i64 + i64::from(relative)) as u32))
+    }
+
+    // NOTE: 内层函数的 `CodeObject` 嵌套在常量表中一起编码
+    pub fn encode(&self, output: &mut Vec<u8>) {
+        write_str(output, &self.name);
+        output.push(self.flags.bits());
+        self.param_count.encode(output);
+        self.length.encode(output);
+        self.local_count.encode(output);
+        self.cache_count.encode(output);
+
+        write_uleb128(output, self.captures.len() as u64);
+        for capture in self.captures.iter() {
+            match *capture {
+                Capture::Local(slot) => { output.push(0); slot.encode(output); },
+                Capture::Upvalue(index) => { output.push(1); index.encode(output); },
+            }
+        }
+
+        write_uleb128(output, self.code.len() as u64);
+        output.extend_from_slice(&self.code);
+
+        write_uleb128(output, self.constants.len() as u64);
+        for constant in self.constants.iter() {
+            constant.encode(output);
+        }
+
+        write_uleb128(output, self.handlers.len() as u64);
+        for handler in self.handlers.iter() {
+            handler.start.encode(output);
+            handler.end.encode(output);
+            handler.target.encode(output);
+            handler.stack_depth.encode(output);
+        }
+
+        self.lines.encode(output);
+    }
+
+    pub fn decode(input: &[u8], pos: &mut usize) -> Result<Self, Error> {
+        CodeObject::decode_nested(input, pos, 0)
+    }
+
+    // NOTE: 内层函数递归解码，限制嵌套的深度，避免不可信的输入耗尽调用栈
+    fn decode_nested(input: &[u8], pos: &mut usize, depth: usize) -> Result<Self, Error> {
+        if depth > MAX_FUNCTION_NESTING {
+            return Err(Error::new(ErrorKind::InternalError, "functions are nested too deeply"));
+        }
+
+        let name = read_str(input, pos)?;
+        let flags = <u8 as Operand>::decode(input, pos)?;
+        let flags = CodeFlags::from_bits(flags)
+            .ok_or_else(|| Error::new(ErrorKind::InternalError, format!("invalid code flags 0x{:02x}", flags)))?;
+        let param_count = <u32 as Operand>::decode(input, pos)?;
+        let length = <u32 as Operand>::decode(input, pos)?;
+        let local_count = <u32 as Operand>::decode(input, pos)?;
+        let cache_count = <u32 as Operand>::decode(input, pos)?;
+
+        let len = read_len(input, pos)?;
+        let mut captures = Vec::with_capacity(len);
+        for _ in 0..len {
+            let capture = match <u8 as Operand>::decode(input, pos)? {
+                0 => Capture::Local(<u32 as Operand>::decode(input, pos)?),
+                1 => Capture::Upvalue(<u32 as Operand>::decode(input, pos)?),
+                tag => return Err(Error::new(ErrorKind::InternalError, format!("invalid capture tag {}", tag))),
+            };
+            captures.push(capture);
+        }
+
+        let len = read_len(input, pos)?;
+        let code = input[*pos..*pos + len].to_vec();
+        *pos += len;
+
+        let len = read_len(input, pos)?;
+        let mut constants = Vec::with_capacity(len);
+        for _ in 0..len {
+            constants.push(Constant::decode_nested(input, pos, depth)?);
+        }
+
+        let len = read_len(input, pos)?;
+        let mut handlers = Vec::with_capacity(len);
+        for _ in 0..len {
+            handlers.push(Handler {
+                start: <u32 as Operand>::decode(input, pos)?,
+                end: <u32 as Operand>::decode(input, pos)?,
+                target: <u32 as Operand>::decode(input, pos)?,
+                stack_depth: <u32 as Operand>::decode(input, pos)?,
+            });
+        }
+
+        let lines = LineTable::decode(input, pos)?;
+
+        Ok(CodeObject { name, flags, param_count, length, local_count, cache_count, captures, code, constants, handlers, lines })
+    }
 }
 
-#[derive(Debug)]
-pub struct ByteCode {
-    pub kind: Instruction,
-    pub offset: usize,
-    pub size: usize,
+
+#[test]
+fn test_bytecode_encoding() {
+    let loc = |start, end| Loc { start, end };
+
+    // function f(a) { if (a) return 300; return -1; }
+    let mut code = CodeObject::new("f");
+    code.param_count = 1;
+    code.local_count = 1;
+    code.emit(Instruction::GetLocal(0), loc(20, 21));
+    let jump = code.emit(Instruction::JumpIfFalse(0), loc(16, 35));
+    code.emit(Instruction::Int(300), loc(30, 33));
+    code.emit(Instruction::Return, loc(23, 34));
+    let target = code.emit(Instruction::Int(-1), loc(42, 44));
+    code.emit(Instruction::Return, loc(35, 45));
+    code.patch_jump(jump, target);
+
+    assert_eq!(code.code, vec![
+        Opcode::GetLocal as u8, 0,
+        Opcode::JumpIfFalse as u8, 4, 0, 0, 0,
+        Opcode::Int as u8, 0xd8, 0x04,
+        Opcode::Return as u8,
+        Opcode::Int as u8, 1,
+        Opcode::Return as u8,
+    ]);
+    assert_eq!(code.instructions().unwrap(), vec![
+        (0, Instruction::GetLocal(0)),
+        (2, Instruction::JumpIfFalse(4)),
+        (7, Instruction::Int(300)),
+        (10, Instruction::Return),
+        (11, Instruction::Int(-1)),
+        (13, Instruction::Return),
+    ]);
+    assert_eq!(code.jump_target(jump).unwrap(), Some(target));
+    assert_eq!(Opcode::JumpIfFalse.operands(), &[ "offset" ]);
+
+    assert_eq!(code.lines.find(0), Some(loc(20, 21)));
+    assert_eq!(code.lines.find(8), Some(loc(30, 33)));
+    let mut encoded = Vec::new();
+    code.lines.encode(&mut encoded);
+    assert_eq!(LineTable::decode(&encoded, &mut 0).unwrap(), code.lines);
+
+    assert_eq!(code.add_string("x"), 0);
+    assert_eq!(code.add_constant(Constant::Number(1.0)), 1);
+    assert_eq!(code.add_string("x"), 0);
+    assert_eq!(code.add_constant(Constant::Number(-0.0)), 2);
+
+    code.add_constant(Constant::Template { cooked: vec![ None ], raw: vec![ "\\u".to_string() ] });
+    let mut outer = CodeObject::new("<script>");
+    outer.captures.push(Capture::Upvalue(3));
+    outer.add_constant(Constant::Function(Box::new(code.clone())));
+    let mut encoded = Vec::new();
+    outer.encode(&mut encoded);
+    assert_eq!(CodeObject::decode(&encoded, &mut 0).unwrap(), outer);
+    assert!(CodeObject::decode(&encoded[..encoded.len() - 1], &mut 0).is_err());
+
+    assert!(Instruction::decode(&[ 0xff ], 0).is_err());
+    assert!(Instruction::decode(&[ Opcode::Jump as u8, 0 ], 0).is_err());
+
+    // 嵌套过深的函数
+    let nested = |depth: usize| {
+        let mut code = CodeObject::new("f");
+        for _ in 0..depth {
+            let mut outer = CodeObject::new("f");
+            outer.add_constant(Constant::Function(Box::new(code)));
+            code = outer;
+        }
+        let mut encoded = Vec::new();
+        code.encode(&mut encoded);
+        encoded
+    };
+    assert!(CodeObject::decode(&nested(MAX_FUNCTION_NESTING), &mut 0).is_ok());
+    let error = CodeObject::decode(&nested(MAX_FUNCTION_NESTING + 1), &mut 0).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InternalError);
+    assert_eq!(error.message(), "functions are nested too deeply");
 }
@@ -0,0 +1,2621 @@
+// Bytecode generation
+//
+//      function add(a, b) { return a + b; }
+//
+//      add:    GetLocal slot=0
+//              GetLocal slot=1
+//              Add
+//              Return
+//
+// NOTE: 先做作用域分析，再把每个函数（以及 Script、Module 本身）编译为一个 `CodeObject`，
+//       内层函数作为常量（`Constant::Function`）保存在外层函数的常量表中。
+//
+//       绑定的存放位置：
+//          * Script 顶层的绑定、对直接 `eval` 可见的绑定、在 `with` 语句中被引用的绑定按名字存取
+//            （`DeclareName`/`GetName`/`SetName`），块级的按名字绑定会被提升到函数的环境中
+//          * 其它绑定保存在局部变量槽中，前 `param_count` 个槽是实参，
+//            被内层函数引用的绑定保存在 Cell 中，内层函数通过 upvalue 访问
+//          * 没有绑定的 `arguments` 编译为 `Arguments`，箭头函数中由 VM 取外层函数的值（`this`、`new.target` 同理）
+//
+//       语句之间操作数栈总是空的，循环、switch 需要的状态保存在临时变量槽中，
+//       所以异常处理表的 `stack_depth` 总是 0，`break`/`continue` 也不需要清理操作数栈。
+//
+//       `finally` 块在正常结束、`break`/`continue`/`return` 跳出时被内联，
+//       异常时由异常处理表跳到另一份副本，执行完后重新抛出。
+//       `with`（`LeaveWith`）、`for-of`（`IteratorClose`）使用同样的方式清理。
+//
+//       已知的限制：生成器的 `return()` 不会执行 `finally`；`yield*` 不转发 `next()` 的参数；
+//       Script 的结果是最后执行的表达式语句的值。
+
+use crate::error::{ ErrorKind, Error, };
+use crate::lexer::span::Loc;
+use crate::lexer::token::{ Identifier, LiteralString, };
+use crate::lexer::escape::{ unescape_string, unescape_template, };
+use crate::lexer::operator::{ PrefixOperator, InfixOperator, PostfixOperator, AssignmentOperator, };
+use crate::ast::numberic::{ Float, Numberic, };
+use crate::ast::statement::{
+    Statement, BlockStatement, VariableStatement, ForStatement, SwitchStatement, TryStatement,
+};
+use crate::ast::expression::{
+    Expression, LiteralTemplateExpression, ParenthesizedExpression,
+    MemberExpression, CallExpression,
+};
+use crate::ast::function::{ FunctionBody, ConciseBody, };
+use crate::ast::class::{ Class, MethodDefinition, };
+use crate::ast::pattern::{
+    PropertyName, ObjectProperty,
+    BindingPattern, BindingElement, BindingProperty, BindingRestElement,
+    AssignmentPattern, AssignmentProperty,
+};
+use crate::compiler::scope::{ self, Goal, ScopeKind, ScopeId, BindingId, DeclarationKind, ScopeTree, };
+use crate::compiler::bytecode::{
+    Instruction, CodeObject, CodeFlags, Constant, Capture, Handler, error_kind_to_u8,
+};
+use crate::compiler::transform::ByteCodeGen;
+use crate::vm::value::Number;
+
+use std::collections::{ HashMap, HashSet, };
+
+
+#[inline]
+fn ident_name(ident: &Identifier) -> String {
+    ident.cooked.unwrap_or(ident.raw).iter().collect::<String>()
+}
+
+fn string_value(lit: &LiteralString) -> String {
+    match lit.cooked {
+        Some(cooked) => cooked.iter().collect(),
+        None if lit.raw.contains(&'\\') => match unescape_string(lit.raw) {
+            Ok(value) => value.into_iter().collect(),
+            Err(_) => lit.raw.iter().collect(),
+        },
+        None => lit.raw.iter().collect(),
+    }
+}
+
+// NOTE: 带标签的模板中允许非法的转义，它的 cooked 值是 `undefined`
+fn template_cooked(lit: &LiteralString) -> Option<String> {
+    match lit.cooked {
+        Some(cooked) => Some(cooked.iter().collect()),
+        None if lit.raw.contains(&'\\') => unescape_template(lit.raw).ok().map(|value| value.into_iter().collect()),
+        None => Some(lit.raw.iter().collect()),
+    }
+}
+
+fn number_value(value: Numberic) -> f64 {
+    match value {
+        Numberic::I64(n) => n as f64,
+        Numberic::F64(Float(n)) => n,
+    }
+}
+
+#[inline]
+fn parenthesized_items<'a, 'ast>(expr: &'a ParenthesizedExpression<'ast>) -> &'a [Expression<'ast>] {
+    match expr.items {
+        [Expression::Comma(inner)] => inner.items,
+        items => items,
+    }
+}
+
+fn unparenthesized<'ast>(expr: Expression<'ast>) -> Expression<'ast> {
+    match expr {
+        Expression::Parenthesized(inner) if inner.items.len() == 1 => unparenthesized(inner.items[0]),
+        expr => expr,
+    }
+}
+
+fn is_use_strict(body: &[Statement]) -> bool {
+    for stmt in body.iter() {
+        match *stmt {
+            Statement::Empty(_) => continue,
+            Statement::Expression(Expression::String(lit)) => {
+                if lit.raw.iter().collect::<String>() == "use strict" {
+                    return true;
+                }
+            },
+            _ => return false,
+        }
+    }
+
+    false
+}
+
+fn syntax_error<M: Into<String>>(message: M) -> Error {
+    Error::new(ErrorKind::SyntaxError, message)
+}
+
+fn infix_instruction(operator: InfixOperator) -> Option<Instruction> {
+    let instruction = match operator {
+        InfixOperator::Add => Instruction::Add,
+        InfixOperator::Sub => Instruction::Sub,
+        InfixOperator::Mul => Instruction::Mul,
+        InfixOperator::Div => Instruction::Div,
+        InfixOperator::Rem => Instruction::Mod,
+        InfixOperator::Pow => Instruction::Exp,
+        InfixOperator::BitShl => Instruction::Shl,
+        InfixOperator::BitShr => Instruction::Shr,
+        InfixOperator::BitUShr => Instruction::UShr,
+        InfixOperator::BitAnd => Instruction::BitAnd,
+        InfixOperator::BitXor => Instruction::BitXor,
+        InfixOperator::BitOr => Instruction::BitOr,
+        InfixOperator::Gt => Instruction::Gt,
+        InfixOperator::Lt => Instruction::Lt,
+        InfixOperator::GtEq => Instruction::Ge,
+        InfixOperator::LtEq => Instruction::Le,
+        InfixOperator::Eq => Instruction::Eq,
+        InfixOperator::Neq => Instruction::Ne,
+        InfixOperator::StrictEq => Instruction::StrictEq,
+        InfixOperator::StrictNeq => Instruction::StrictNe,
+        InfixOperator::InstanceOf => Instruction::InstanceOf,
+        InfixOperator::In => Instruction::In,
+        InfixOperator::And | InfixOperator::Or | InfixOperator::NullishCoalescing => return None,
+    };
+
+    Some(instruction)
+}
+
+fn assignment_instruction(operator: AssignmentOperator) -> Option<Instruction> {
+    let instruction = match operator {
+        AssignmentOperator::AddAssign => Instruction::Add,
+        AssignmentOperator::SubAssign => Instruction::Sub,
+        AssignmentOperator::MulAssign => Instruction::Mul,
+        AssignmentOperator::DivAssign => Instruction::Div,
+        AssignmentOperator::RemAssign => Instruction::Mod,
+        AssignmentOperator::PowAssign => Instruction::Exp,
+        AssignmentOperator::BitAndAssign => Instruction::BitAnd,
+        AssignmentOperator::BitOrAssign => Instruction::BitOr,
+        AssignmentOperator::BitXorAssign => Instruction::BitXor,
+        AssignmentOperator::BitShlAssign => Instruction::Shl,
+        AssignmentOperator::BitShrAssign => Instruction::Shr,
+        AssignmentOperator::BitUShrAssign => Instruction::UShr,
+        AssignmentOperator::Assign
+        | AssignmentOperator::AndAssign
+        | AssignmentOperator::OrAssign
+        | AssignmentOperator::NullishAssign => return None,
+    };
+
+    Some(instruction)
+}
+
+
+/// Compile a Script or a Module.
+pub fn compile<'ast>(body: &[Statement<'ast>], goal: Goal) -> Result<CodeObject, Error> {
+    let tree = scope::analyze(body, goal)?;
+    ByteCodeCompiler::new(&tree).program(body, goal)
+}
+
+impl<'ast> ByteCodeGen for [Statement<'ast>] {
+    fn byte_code_gen(&self, goal: Goal) -> Result<CodeObject, Error> {
+        compile(self, goal)
+    }
+}
+
+
+/// Where a binding lives at runtime.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Place {
+    Local(u32),
+    Cell(u32),
+    Upvalue(u32),
+    /// The String constant of the name.
+    Name(u32),
+    Arguments,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Mode {
+    /// Initialize a declared binding.
+    Init,
+    Assign,
+}
+
+/// An assignment target, with the values it needs ( object, key ) pushed on the stack.
+#[derive(Clone, Copy)]
+enum Target<'ast> {
+    Binding(&'ast Identifier<'ast>),
+    /// `object`
+    Named(u32),
+    /// `object key`
+    Keyed,
+    /// `key`
+    Super,
+}
+
+#[derive(Clone, Copy)]
+enum FunctionBodyKind<'ast> {
+    Body(FunctionBody<'ast>),
+    Expr(Expression<'ast>),
+}
+
+#[derive(Clone, Copy)]
+enum ControlKind<'ast> {
+    Loop,
+    Switch,
+    Label,
+    Catch,
+    /// The finally block and the scope enclosing the try statement.
+    Finally(BlockStatement<'ast>, ScopeId),
+    With,
+    /// The slot of a `for-of` iterator, closed when the loop is exited.
+    Iterator(u32),
+}
+
+impl<'ast> ControlKind<'ast> {
+    fn has_handler(&self) -> bool {
+        match *self {
+            ControlKind::Loop | ControlKind::Switch | ControlKind::Label => false,
+            _ => true,
+        }
+    }
+
+    fn has_cleanup(&self) -> bool {
+        match *self {
+            ControlKind::Finally(..) | ControlKind::With | ControlKind::Iterator(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A statement that `break`, `continue`, `return` or an exception may leave.
+struct Control<'ast> {
+    kind: ControlKind<'ast>,
+    labels: Vec<String>,
+    breaks: Vec<u32>,
+    continues: Vec<u32>,
+    // NOTE: 内联的清理代码不能被自己的异常处理覆盖，所以处理范围可能被分成多段
+    region: Option<u32>,
+    segments: Vec<(u32, u32)>,
+}
+
+struct FunctionState<'ast> {
+    code: CodeObject,
+    scope: ScopeId,
+    current: ScopeId,
+    loc: Loc,
+    slots: HashMap<BindingId, u32>,
+    next_slot: u32,
+    upvalues: Vec<BindingId>,
+    controls: Vec<Control<'ast>>,
+    // NOTE: (嵌套深度, Handler)，结束时按深度排序，内层的在前
+    handlers: Vec<(usize, Handler)>,
+    completion: Option<u32>,
+    is_strict: bool,
+    is_async: bool,
+}
+
+
+pub struct ByteCodeCompiler<'a, 'ast> {
+    tree: &'a ScopeTree,
+    // NOTE: 每个绑定所属的函数作用域
+    owners: Vec<ScopeId>,
+    named: HashSet<BindingId>,
+    captured: HashSet<BindingId>,
+    entered: HashSet<ScopeId>,
+    functions: Vec<FunctionState<'ast>>,
+}
+
+impl<'a, 'ast> ByteCodeCompiler<'a, 'ast> {
+    pub fn new(tree: &'a ScopeTree) -> Self {
+        let function_scope = |mut id: ScopeId| {
+            loop {
+                match tree.scope(id).kind {
+                    ScopeKind::Function | ScopeKind::Global | ScopeKind::Module => return id,
+                    _ => id = tree.scope(id).parent.expect("Ooops ..."),
+                }
+            }
+        };
+
+        let mut owners = Vec::with_capacity(tree.bindings().len());
+        let mut named = HashSet::new();
+        let mut captured = HashSet::new();
+        for (id, binding) in tree.bindings().iter().enumerate() {
+            let owner = function_scope(binding.scope);
+            owners.push(owner);
+
+            let scope = tree.scope(binding.scope);
+            let references = binding.reads.iter().chain(binding.writes.iter()).map(|id| tree.reference(*id));
+            if scope.kind == ScopeKind::Global
+                || scope.has_direct_eval
+                || binding.kind == DeclarationKind::Import
+                || references.clone().any(|reference| tree.is_in_with(reference.scope)) {
+                named.insert(id);
+            } else if references.clone().any(|reference| function_scope(reference.scope) != owner) {
+                captured.insert(id);
+            }
+        }
+
+        Self { tree, owners, named, captured, entered: HashSet::new(), functions: Vec::new() }
+    }
+
+    pub fn program(mut self, body: &[Statement<'ast>], goal: Goal) -> Result<CodeObject, Error> {
+        let tree = self.tree;
+        let (name, scope) = match goal {
+            Goal::Script => ("<script>", tree.root()),
+            Goal::Module => ("<module>", self.find_scope(ScopeKind::Module, Loc::default())?),
+        };
+
+        let is_strict = goal == Goal::Module || is_use_strict(body);
+        self.begin_function(CodeObject::new(name), scope, is_strict, false);
+        if goal == Goal::Script {
+            let slot = self.temp();
+            self.state_mut().completion = Some(slot);
+        }
+
+        self.declare_bindings(scope)?;
+        self.hoist_functions(body)?;
+        self.statements(body)?;
+
+        match self.state().completion {
+            Some(slot) => self.emit(Instruction::GetLocal(slot)),
+            None => self.emit(Instruction::Undefined),
+        };
+        self.emit(Instruction::Return);
+
+        let mut code = self.end_function();
+        if is_strict {
+            code.flags.insert(CodeFlags::STRICT);
+        }
+
+        Ok(code)
+    }
+
+    // ---------- functions ----------
+
+    fn state(&self) -> &FunctionState<'ast> {
+        self.functions.last().expect("Ooops ...")
+    }
+
+    fn state_mut(&mut self) -> &mut FunctionState<'ast> {
+        self.functions.last_mut().expect("Ooops ...")
+    }
+
+    fn begin_function(&mut self, code: CodeObject, scope: ScopeId, is_strict: bool, is_async: bool) {
+        let mut state = FunctionState {
+            code,
+            scope,
+            current: scope,
+            loc: Loc::default(),
+            slots: HashMap::new(),
+            next_slot: 0,
+            upvalues: Vec::new(),
+            controls: Vec::new(),
+            handlers: Vec::new(),
+            completion: None,
+            is_strict,
+            is_async,
+        };
+
+        // NOTE: 参数占用前 `param_count` 个槽，这里只给其它的绑定分配
+        for (id, &owner) in self.owners.iter().enumerate() {
+            if owner == scope && !self.named.contains(&id) {
+                state.slots.insert(id, 0);
+            }
+        }
+        self.functions.push(state);
+    }
+
+    /// Assign the local slots, simple parameters use the slots of their arguments.
+    fn allocate_slots(&mut self, params: &[Expression<'ast>], param_count: u32) {
+        let tree = self.tree;
+        let scope = self.state().scope;
+
+        let mut simple = HashMap::new();
+        for (index, param) in params.iter().enumerate() {
+            if let Expression::Identifier(ident) = *param {
+                if let Some(id) = tree.resolve(scope, &ident_name(ident)) {
+                    // NOTE: `function f(a, a) {}` 中重复的参数需要单独处理
+                    simple.entry(id).and_modify(|slot: &mut Option<u32>| *slot = None).or_insert(Some(index as u32));
+                }
+            }
+        }
+
+        let state = self.functions.last_mut().expect("Ooops ...");
+        let mut ids = state.slots.keys().cloned().collect::<Vec<BindingId>>();
+        ids.sort();
+        state.next_slot = param_count;
+        for id in ids {
+            let slot = match simple.get(&id) {
+                Some(&Some(slot)) if !self.captured.contains(&id) => slot,
+                _ => {
+                    state.next_slot += 1;
+                    state.next_slot - 1
+                },
+            };
+            state.slots.insert(id, slot);
+        }
+    }
+
+    fn end_function(&mut self) -> CodeObject {
+        let mut state = self.functions.pop().expect("Ooops ...");
+        state.handlers.sort_by(|a, b| b.0.cmp(&a.0));
+        state.code.handlers = state.handlers.into_iter().map(|(_, handler)| handler).collect();
+        state.code.local_count = state.next_slot;
+        state.code
+    }
+
+    fn function(&mut self,
+                name: String,
+                own_name: Option<&'ast Identifier<'ast>>,
+                params: &[Expression<'ast>],
+                body: FunctionBodyKind<'ast>,
+                mut flags: CodeFlags,
+                loc: Loc) -> Result<u32, Error> {
+        let scope = self.find_scope(ScopeKind::Function, loc)?;
+
+        let is_strict = self.state().is_strict || flags.contains(CodeFlags::STRICT) || match body {
+            FunctionBodyKind::Body(body) => is_use_strict(body),
+            FunctionBodyKind::Expr(_) => false,
+        };
+        if is_strict {
+            flags.insert(CodeFlags::STRICT);
+        }
+
+        let param_count = params.iter().filter(|param| match **param {
+            Expression::Spread(_) => false,
+            _ => true,
+        }).count() as u32;
+        let length = params.iter().position(|param| match *param {
+            Expression::Spread(_) | Expression::Assignment(_) => true,
+            _ => false,
+        }).unwrap_or(params.len()) as u32;
+
+        let mut code = CodeObject::new(name);
+        code.flags = flags;
+        code.param_count = param_count;
+        code.length = length;
+
+        self.begin_function(code, scope, is_strict, flags.contains(CodeFlags::ASYNC));
+        self.allocate_slots(params, param_count);
+        self.state_mut().loc = loc;
+
+        self.declare_bindings(scope)?;
+        if let Some(ident) = own_name {
+            self.emit(Instruction::Callee);
+            self.store(ident, Mode::Init)?;
+            self.emit(Instruction::Pop);
+        }
+        self.parameters(params)?;
+
+        match body {
+            FunctionBodyKind::Body(body) => {
+                self.hoist_functions(body)?;
+                if flags.contains(CodeFlags::GENERATOR) {
+                    self.emit(Instruction::InitialYield);
+                }
+                self.statements(body)?;
+                self.state_mut().loc = loc;
+                self.emit(Instruction::Undefined);
+                self.emit(Instruction::Return);
+            },
+            FunctionBodyKind::Expr(expr) => {
+                self.expression(&expr)?;
+                self.emit(Instruction::Return);
+            },
+        }
+
+        let code = self.end_function();
+        Ok(self.state_mut().code.add_constant(Constant::Function(Box::new(code))))
+    }
+
+    fn parameters(&mut self, params: &[Expression<'ast>]) -> Result<(), Error> {
+        let tree = self.tree;
+        let scope = self.state().scope;
+
+        for (index, param) in params.iter().enumerate() {
+            let index = index as u32;
+            match *param {
+                Expression::Spread(inner) => {
+                    self.emit(Instruction::Rest(index));
+                    self.assign_to(&inner.item, Mode::Init)?;
+                },
+                Expression::Identifier(ident) => {
+                    let binding = tree.resolve(scope, &ident_name(ident));
+                    let is_simple = binding.map(|id| self.place_of(id) == Place::Local(index)).unwrap_or(false);
+                    if !is_simple {
+                        self.emit(Instruction::GetLocal(index));
+                        self.store(ident, Mode::Init)?;
+                        self.emit(Instruction::Pop);
+                    }
+                },
+                _ => {
+                    self.emit(Instruction::GetLocal(index));
+                    self.assign_to(param, Mode::Init)?;
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn function_flags(is_async: bool, is_generator: bool) -> CodeFlags {
+        let mut flags = CodeFlags::empty();
+        if is_async {
+            flags.insert(CodeFlags::ASYNC);
+        }
+        if is_generator {
+            flags.insert(CodeFlags::GENERATOR);
+        }
+        flags
+    }
+
+    fn method(&mut self, method: &MethodDefinition<'ast>, name: String, mut flags: CodeFlags) -> Result<u32, Error> {
+        flags.insert(CodeFlags::METHOD);
+        match *method {
+            MethodDefinition::Method(ref inner) => {
+                flags.insert(Self::function_flags(inner.is_async, inner.is_generator));
+                let params = parenthesized_items(&inner.params);
+                self.function(name, None, params, FunctionBodyKind::Body(inner.body), flags, inner.loc)
+            },
+            MethodDefinition::Getter(ref inner) => {
+                self.function(name, None, &[], FunctionBodyKind::Body(inner.body), flags, inner.loc)
+            },
+            MethodDefinition::Setter(ref inner) => {
+                let params = parenthesized_items(&inner.params);
+                self.function(name, None, params, FunctionBodyKind::Body(inner.body), flags, inner.loc)
+            },
+        }
+    }
+
+    // ---------- emitting ----------
+
+    fn emit(&mut self, instruction: Instruction) -> u32 {
+        let state = self.state_mut();
+        let loc = state.loc;
+        state.code.emit(instruction, loc)
+    }
+
+    fn offset(&self) -> u32 {
+        self.state().code.offset()
+    }
+
+    /// `GetNamed` with a new inline cache.
+    fn emit_get_named(&mut self, name: u32) -> u32 {
+        let cache = self.state_mut().code.cache();
+        self.emit(Instruction::GetNamed(name, cache))
+    }
+
+    /// `SetNamed` with a new inline cache.
+    fn emit_set_named(&mut self, name: u32) -> u32 {
+        let cache = self.state_mut().code.cache();
+        self.emit(Instruction::SetNamed(name, cache))
+    }
+
+    /// Emit a jump to be patched later.
+    fn jump(&mut self, instruction: Instruction) -> u32 {
+        self.emit(instruction)
+    }
+
+    /// Point the jump at `at` to the next instruction.
+    fn patch(&mut self, at: u32) {
+        let target = self.offset();
+        self.state_mut().code.patch_jump(at, target);
+    }
+
+    fn jump_to(&mut self, instruction: Instruction, target: u32) {
+        let at = self.emit(instruction);
+        self.state_mut().code.patch_jump(at, target);
+    }
+
+    fn string<S: Into<String>>(&mut self, value: S) -> u32 {
+        self.state_mut().code.add_string(value)
+    }
+
+    fn temp(&mut self) -> u32 {
+        let state = self.state_mut();
+        state.next_slot += 1;
+        state.next_slot - 1
+    }
+
+    fn throw_error<M: Into<String>>(&mut self, kind: ErrorKind, message: M) {
+        let message = self.string(message);
+        self.emit(Instruction::ThrowError(error_kind_to_u8(kind), message));
+    }
+
+    fn number(&mut self, value: Numberic) {
+        match value {
+            Numberic::I64(n) => self.emit(Instruction::Int(n)),
+            Numberic::F64(Float(n)) => {
+                let index = self.state_mut().code.add_constant(Constant::Number(n));
+                self.emit(Instruction::Const(index))
+            },
+        };
+    }
+
+    // ---------- scopes and bindings ----------
+
+    /// The scope the analyzer created for the node at `loc`, a child of the current scope.
+    fn find_scope(&mut self, kind: ScopeKind, loc: Loc) -> Result<ScopeId, Error> {
+        let tree = self.tree;
+        let current = match self.functions.last() {
+            Some(state) => state.current,
+            None => tree.root(),
+        };
+
+        // NOTE: 编译器合成的节点的 Loc 都相同，按照分析时的顺序匹配第一个未使用的作用域
+        let id = tree.scope(current).children.iter().cloned()
+            .find(|id| {
+                let scope = tree.scope(*id);
+                scope.kind == kind && scope.loc == loc && !self.entered.contains(id)
+            })
+            .ok_or_else(|| Error::new(ErrorKind::InternalError, format!("no {:?} scope at {:?}", kind, loc)))?;
+        self.entered.insert(id);
+
+        Ok(id)
+    }
+
+    fn enter_scope(&mut self, kind: ScopeKind, loc: Loc) -> Result<ScopeId, Error> {
+        let id = self.find_scope(kind, loc)?;
+        self.state_mut().current = id;
+        Ok(id)
+    }
+
+    fn exit_scope(&mut self) {
+        let parent = self.tree.scope(self.state().current).parent.expect("Ooops ...");
+        self.state_mut().current = parent;
+    }
+
+    fn place_of(&mut self, id: BindingId) -> Place {
+        if self.named.contains(&id) {
+            let name = self.tree.binding(id).name.clone();
+            return Place::Name(self.string(name));
+        }
+
+        let level = self.functions.len() - 1;
+        if self.owners[id] == self.functions[level].scope {
+            let slot = self.functions[level].slots[&id];
+            if self.captured.contains(&id) { Place::Cell(slot) } else { Place::Local(slot) }
+        } else {
+            Place::Upvalue(self.upvalue(level, id))
+        }
+    }
+
+    fn upvalue(&mut self, level: usize, id: BindingId) -> u32 {
+        if let Some(index) = self.functions[level].upvalues.iter().position(|binding| *binding == id) {
+            return index as u32;
+        }
+
+        let parent = level - 1;
+        let capture = if self.functions[parent].scope == self.owners[id] {
+            Capture::Local(self.functions[parent].slots[&id])
+        } else {
+            Capture::Upvalue(self.upvalue(parent, id))
+        };
+
+        let state = &mut self.functions[level];
+        state.upvalues.push(id);
+        state.code.captures.push(capture);
+        (state.upvalues.len() - 1) as u32
+    }
+
+    fn resolve(&mut self, name: &str) -> (Option<BindingId>, Place) {
+        let tree = self.tree;
+        let current = self.state().current;
+        match tree.resolve(current, name) {
+            Some(id) => (Some(id), self.place_of(id)),
+            None => {
+                let this_scope = tree.this_scope(current);
+                if name == "arguments" && tree.scope(this_scope).kind == ScopeKind::Function {
+                    (None, Place::Arguments)
+                } else {
+                    (None, Place::Name(self.string(name)))
+                }
+            },
+        }
+    }
+
+    /// Lexical bindings read before their declaration ( or from another function ) may be in the TDZ.
+    fn needs_check(&self, id: BindingId, ident: &Identifier) -> bool {
+        let binding = self.tree.binding(id);
+        match binding.kind {
+            DeclarationKind::Let | DeclarationKind::Const | DeclarationKind::Class => { },
+            _ => return false,
+        }
+        if self.named.contains(&id) {
+            return false;
+        }
+        if self.owners[id] != self.state().scope {
+            return true;
+        }
+
+        let declaration = binding.declarations[0];
+        ident.loc.is_dummy() || declaration.is_dummy() || ident.loc.start <= declaration.start
+    }
+
+    fn get_place(&mut self, place: Place) {
+        match place {
+            Place::Local(slot) => self.emit(Instruction::GetLocal(slot)),
+            Place::Cell(slot) => self.emit(Instruction::GetCell(slot)),
+            Place::Upvalue(index) => self.emit(Instruction::GetUpvalue(index)),
+            Place::Name(name) => self.emit(Instruction::GetName(name)),
+            Place::Arguments => self.emit(Instruction::Arguments),
+        };
+    }
+
+    fn set_place(&mut self, place: Place) {
+        match place {
+            Place::Local(slot) => self.emit(Instruction::SetLocal(slot)),
+            Place::Cell(slot) => self.emit(Instruction::SetCell(slot)),
+            Place::Upvalue(index) => self.emit(Instruction::SetUpvalue(index)),
+            Place::Name(name) => self.emit(Instruction::SetName(name)),
+            Place::Arguments => {
+                let name = self.string("arguments");
+                self.emit(Instruction::SetName(name))
+            },
+        };
+    }
+
+    /// `-> value`
+    fn load(&mut self, ident: &Identifier<'ast>) {
+        let name = ident_name(ident);
+        let (binding, place) = self.resolve(&name);
+        self.get_place(place);
+        if let Some(id) = binding {
+            if self.needs_check(id, ident) {
+                let name = self.string(name);
+                self.emit(Instruction::CheckHole(name));
+            }
+        }
+    }
+
+    /// `value -> value`
+    fn store(&mut self, ident: &Identifier<'ast>, mode: Mode) -> Result<(), Error> {
+        let name = ident_name(ident);
+        let (binding, place) = self.resolve(&name);
+
+        if let (Mode::Assign, Some(id)) = (mode, binding) {
+            if self.tree.binding(id).kind.is_const() {
+                self.throw_error(ErrorKind::TypeError, "Assignment to constant variable.");
+                return Ok(());
+            }
+            if self.needs_check(id, ident) {
+                let name = self.string(name);
+                self.get_place(place);
+                self.emit(Instruction::CheckHole(name));
+                self.emit(Instruction::Pop);
+            }
+        }
+
+        self.set_place(place);
+        Ok(())
+    }
+
+    /// Create the bindings of a scope when entering it: names, Cells and TDZ.
+    fn declare_bindings(&mut self, scope: ScopeId) -> Result<(), Error> {
+        let tree = self.tree;
+        let mut ids = tree.scope(scope).bindings.values().cloned().collect::<Vec<BindingId>>();
+        ids.sort();
+
+        for id in ids {
+            let binding = tree.binding(id);
+            if self.named.contains(&id) {
+                let name = self.string(binding.name.clone());
+                self.emit(Instruction::DeclareName(name));
+                continue;
+            }
+
+            let slot = self.state().slots[&id];
+            let is_captured = self.captured.contains(&id);
+            match binding.kind {
+                DeclarationKind::Let | DeclarationKind::Const | DeclarationKind::Class => {
+                    if is_captured {
+                        self.emit(Instruction::NewCell(slot));
+                    } else {
+                        self.emit(Instruction::Hole);
+                        self.emit(Instruction::SetLocal(slot));
+                        self.emit(Instruction::Pop);
+                    }
+                },
+                DeclarationKind::Var | DeclarationKind::Function if is_captured => {
+                    self.emit(Instruction::NewCell(slot));
+                    self.emit(Instruction::Undefined);
+                    self.emit(Instruction::SetCell(slot));
+                    self.emit(Instruction::Pop);
+                },
+                DeclarationKind::Param if is_captured => {
+                    self.emit(Instruction::NewCell(slot));
+                },
+                _ => { },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Function declarations are initialized when entering their scope.
+    fn hoist_functions(&mut self, body: &[Statement<'ast>]) -> Result<(), Error> {
+        for stmt in body.iter() {
+            if let Statement::Function(inner) = *stmt {
+                let saved = self.state().loc;
+                self.state_mut().loc = inner.loc;
+
+                let flags = Self::function_flags(inner.is_async, inner.is_generator);
+                let params = parenthesized_items(&inner.func.params);
+                let index = self.function(ident_name(&inner.name), None, params,
+                                          FunctionBodyKind::Body(inner.func.body), flags, inner.func.loc)?;
+                self.emit(Instruction::Closure(index));
+                self.store(&inner.name, Mode::Init)?;
+                self.emit(Instruction::Pop);
+
+                self.state_mut().loc = saved;
+            }
+        }
+
+        Ok(())
+    }
+
+    // ---------- control flow ----------
+
+    fn push_control(&mut self, kind: ControlKind<'ast>, labels: Vec<String>) {
+        let region = if kind.has_handler() { Some(self.offset()) } else { None };
+        self.state_mut().controls.push(Control {
+            kind,
+            labels,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            region,
+            segments: Vec::new(),
+        });
+    }
+
+    fn pop_control(&mut self) -> Control<'ast> {
+        let offset = self.offset();
+        let mut control = self.state_mut().controls.pop().expect("Ooops ...");
+        if let Some(start) = control.region.take() {
+            if start < offset {
+                control.segments.push((start, offset));
+            }
+        }
+        control
+    }
+
+    /// The exceptions thrown in the regions of a popped control jump to the next instruction.
+    fn add_handlers(&mut self, control: &Control<'ast>) {
+        let target = self.offset();
+        let state = self.state_mut();
+        let depth = state.controls.len();
+        for &(start, end) in control.segments.iter() {
+            state.handlers.push((depth, Handler { start, end, target, stack_depth: 0 }));
+        }
+    }
+
+    fn close_regions(&mut self, from: usize) {
+        let offset = self.offset();
+        for control in self.state_mut().controls[from..].iter_mut() {
+            if let Some(start) = control.region.take() {
+                if start < offset {
+                    control.segments.push((start, offset));
+                }
+            }
+        }
+    }
+
+    fn reopen_regions(&mut self, from: usize) {
+        let offset = self.offset();
+        for control in self.state_mut().controls[from..].iter_mut() {
+            if control.kind.has_handler() {
+                control.region = Some(offset);
+            }
+        }
+    }
+
+    /// Compile a copy of the finally block of `controls[index]`, as if the try statement was exited.
+    fn finally_copy(&mut self, index: usize) -> Result<(), Error> {
+        let (block, scope) = match self.state().controls[index].kind {
+            ControlKind::Finally(block, scope) => (block, scope),
+            _ => unreachable!(),
+        };
+
+        let controls = self.state_mut().controls.split_off(index);
+        let current = self.state().current;
+        let entered = self.entered.clone();
+        self.state_mut().current = scope;
+
+        let result = self.block(&block);
+
+        self.entered = entered;
+        let state = self.state_mut();
+        state.current = current;
+        state.controls.extend(controls);
+        result
+    }
+
+    /// Run the cleanups of `controls[from..]`, innermost first.
+    fn unwind(&mut self, from: usize) -> Result<(), Error> {
+        self.close_regions(from);
+        let len = self.state().controls.len();
+        for index in (from..len).rev() {
+            match self.state().controls[index].kind {
+                ControlKind::Finally(..) => self.finally_copy(index)?,
+                ControlKind::With => { self.emit(Instruction::LeaveWith); },
+                ControlKind::Iterator(slot) => {
+                    self.emit(Instruction::GetLocal(slot));
+                    self.emit(Instruction::IteratorClose);
+                },
+                _ => { },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn break_statement(&mut self, label: Option<&Identifier<'ast>>) -> Result<(), Error> {
+        let controls = &self.state().controls;
+        let index = match label {
+            Some(label) => {
+                let label = ident_name(label);
+                controls.iter().rposition(|control| control.labels.contains(&label))
+                    .ok_or_else(|| syntax_error(format!("Undefined label '{}'", label)))?
+            },
+            None => controls.iter().rposition(|control| match control.kind {
+                ControlKind::Loop | ControlKind::Switch => true,
+                _ => false,
+            }).ok_or_else(|| syntax_error("Illegal break statement"))?,
+        };
+
+        self.unwind(index + 1)?;
+        let jump = self.jump(Instruction::Jump(0));
+        self.state_mut().controls[index].breaks.push(jump);
+        self.reopen_regions(index + 1);
+        Ok(())
+    }
+
+    fn continue_statement(&mut self, label: Option<&Identifier<'ast>>) -> Result<(), Error> {
+        let controls = &self.state().controls;
+        let index = match label {
+            Some(label) => {
+                let label = ident_name(label);
+                let index = controls.iter().rposition(|control| control.labels.contains(&label))
+                    .ok_or_else(|| syntax_error(format!("Undefined label '{}'", label)))?;
+                match controls[index].kind {
+                    ControlKind::Loop => index,
+                    _ => return Err(syntax_error(format!("Illegal continue statement: '{}' does not denote an iteration statement", label))),
+                }
+            },
+            None => controls.iter().rposition(|control| match control.kind {
+                ControlKind::Loop => true,
+                _ => false,
+            }).ok_or_else(|| syntax_error("Illegal continue statement: no surrounding iteration statement"))?,
+        };
+
+        // NOTE: `continue` 不会离开 for-of 循环，不需要关闭迭代器
+        let from = match controls.get(index + 1).map(|control| control.kind) {
+            Some(ControlKind::Iterator(_)) => index + 2,
+            _ => index + 1,
+        };
+
+        self.unwind(from)?;
+        let jump = self.jump(Instruction::Jump(0));
+        self.state_mut().controls[index].continues.push(jump);
+        self.reopen_regions(from);
+        Ok(())
+    }
+
+    /// `value ->`
+    fn return_value(&mut self) -> Result<(), Error> {
+        if !self.state().controls.iter().any(|control| control.kind.has_cleanup()) {
+            self.emit(Instruction::Return);
+            return Ok(());
+        }
+
+        let slot = self.temp();
+        self.emit(Instruction::SetLocal(slot));
+        self.emit(Instruction::Pop);
+        self.unwind(0)?;
+        self.emit(Instruction::GetLocal(slot));
+        self.emit(Instruction::Return);
+        self.reopen_regions(0);
+        Ok(())
+    }
+
+    fn patch_loop(&mut self, control: Control<'ast>, head: u32) {
+        for jump in control.continues.iter() {
+            self.state_mut().code.patch_jump(*jump, head);
+        }
+        for jump in control.breaks.iter() {
+            self.patch(*jump);
+        }
+    }
+
+    // ---------- statements ----------
+
+    fn statements(&mut self, body: &[Statement<'ast>]) -> Result<(), Error> {
+        for stmt in body.iter() {
+            self.statement(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &Statement<'ast>) -> Result<(), Error> {
+        let saved = self.state().loc;
+        self.state_mut().loc = stmt.loc();
+        let result = self.statement_inner(stmt, Vec::new());
+        self.state_mut().loc = saved;
+        result
+    }
+
+    fn statement_inner(&mut self, stmt: &Statement<'ast>, labels: Vec<String>) -> Result<(), Error> {
+        match *stmt {
+            Statement::Empty(_) => { },
+            Statement::Debugger(_) => { self.emit(Instruction::Debugger); },
+            Statement::Expression(expr) => {
+                self.expression(expr)?;
+                if let Some(slot) = self.state().completion {
+                    self.emit(Instruction::SetLocal(slot));
+                }
+                self.emit(Instruction::Pop);
+            },
+            Statement::Variable(inner) => self.variable(inner)?,
+            // NOTE: 函数声明在进入作用域时已经初始化
+            Statement::Function(_) => { },
+            Statement::Class(inner) => {
+                self.class(Some(&inner.name), &inner.class, ident_name(&inner.name))?;
+                self.store(&inner.name, Mode::Init)?;
+                self.emit(Instruction::Pop);
+            },
+            Statement::Block(inner) => self.block(inner)?,
+            Statement::If(inner) => {
+                self.expression(&inner.condition)?;
+                let else_jump = self.jump(Instruction::JumpIfFalse(0));
+                self.statement(&inner.and_then)?;
+                if let Statement::Empty(_) = inner.or_else {
+                    self.patch(else_jump);
+                } else {
+                    let end_jump = self.jump(Instruction::Jump(0));
+                    self.patch(else_jump);
+                    self.statement(&inner.or_else)?;
+                    self.patch(end_jump);
+                }
+            },
+            Statement::DoWhile(inner) => {
+                self.push_control(ControlKind::Loop, labels);
+                let head = self.offset();
+                self.statement(&inner.body)?;
+                let condition = self.offset();
+                self.expression(&inner.condition)?;
+                self.jump_to(Instruction::JumpIfTrue(0), head);
+                let control = self.pop_control();
+                self.patch_loop(control, condition);
+            },
+            Statement::While(inner) => {
+                let head = self.offset();
+                self.expression(&inner.condition)?;
+                let exit = self.jump(Instruction::JumpIfFalse(0));
+                self.push_control(ControlKind::Loop, labels);
+                self.statement(&inner.body)?;
+                self.jump_to(Instruction::Jump(0), head);
+                let control = self.pop_control();
+                self.patch(exit);
+                self.patch_loop(control, head);
+            },
+            Statement::For(inner) => self.for_statement(inner, labels)?,
+            Statement::ForIn(inner) => self.for_each(&inner.left, &inner.right, &inner.body, Instruction::ForInEnumerate, labels)?,
+            Statement::ForOf(inner) => self.for_each(&inner.left, &inner.right, &inner.body, Instruction::GetIterator, labels)?,
+            Statement::ForAwaitOf(inner) => self.for_each(&inner.left, &inner.right, &inner.body, Instruction::GetAsyncIterator, labels)?,
+            Statement::Continue(inner) => self.continue_statement(inner.label.as_ref())?,
+            Statement::Break(inner) => self.break_statement(inner.label.as_ref())?,
+            Statement::Return(inner) => {
+                if self.functions.len() == 1 {
+                    return Err(syntax_error("Illegal return statement"));
+                }
+                match inner.value {
+                    Some(ref value) => self.expression(value)?,
+                    None => { self.emit(Instruction::Undefined); },
+                }
+                self.return_value()?;
+            },
+            Statement::With(inner) => {
+                if self.state().is_strict {
+                    return Err(syntax_error("Strict mode code may not include a with statement"));
+                }
+                self.expression(&inner.condition)?;
+                self.emit(Instruction::EnterWith);
+                self.push_control(ControlKind::With, Vec::new());
+                self.enter_scope(ScopeKind::With, inner.loc)?;
+                self.statement(&inner.then)?;
+                self.exit_scope();
+                let control = self.pop_control();
+                self.emit(Instruction::LeaveWith);
+
+                if !control.segments.is_empty() {
+                    let end = self.jump(Instruction::Jump(0));
+                    self.add_handlers(&control);
+                    self.emit(Instruction::LeaveWith);
+                    self.emit(Instruction::Throw);
+                    self.patch(end);
+                }
+            },
+            Statement::Switch(inner) => self.switch(inner, labels)?,
+            Statement::Labelled(inner) => {
+                let mut labels = labels;
+                labels.push(ident_name(&inner.label));
+                if inner.item.is_iteration_statement() || matches_labelled(&inner.item) {
+                    self.statement_inner(&inner.item, labels)?;
+                } else {
+                    self.push_control(ControlKind::Label, labels);
+                    self.statement(&inner.item)?;
+                    let control = self.pop_control();
+                    for jump in control.breaks.iter() {
+                        self.patch(*jump);
+                    }
+                }
+            },
+            Statement::Throw(inner) => {
+                self.expression(&inner.value)?;
+                self.emit(Instruction::Throw);
+            },
+            Statement::Try(inner) => self.try_statement(inner)?,
+        }
+
+        Ok(())
+    }
+
+    fn block(&mut self, block: &BlockStatement<'ast>) -> Result<(), Error> {
+        let scope = self.enter_scope(ScopeKind::Block, block.loc)?;
+        self.declare_bindings(scope)?;
+        self.hoist_functions(block.body)?;
+        self.statements(block.body)?;
+        self.exit_scope();
+        Ok(())
+    }
+
+    fn variable(&mut self, inner: &VariableStatement<'ast>) -> Result<(), Error> {
+        for declarator in inner.declarators.iter() {
+            match declarator.initializer {
+                Some(ref init) => {
+                    let name = match declarator.name {
+                        Expression::Identifier(ident) => Some(ident_name(ident)),
+                        _ => None,
+                    };
+                    self.named_expression(init, name)?;
+                },
+                None if inner.is_var() => continue,
+                None => { self.emit(Instruction::Undefined); },
+            }
+            self.assign_to(&declarator.name, Mode::Init)?;
+        }
+
+        Ok(())
+    }
+
+    fn for_statement(&mut self, inner: &ForStatement<'ast>, labels: Vec<String>) -> Result<(), Error> {
+        let scope = self.enter_scope(ScopeKind::Block, inner.loc)?;
+        self.declare_bindings(scope)?;
+
+        if let Some(ref init) = inner.init {
+            self.statement(init)?;
+        }
+
+        let head = self.offset();
+        let exit = match inner.condition {
+            Some(ref condition) => {
+                self.expression(condition)?;
+                Some(self.jump(Instruction::JumpIfFalse(0)))
+            },
+            None => None,
+        };
+
+        self.push_control(ControlKind::Loop, labels);
+        self.statement(&inner.body)?;
+        let control = self.pop_control();
+
+        // NOTE: 每次迭代都有新的 `let` 绑定，被闭包捕获的需要复制到新的 Cell 中
+        let next = self.offset();
+        let mut ids = self.tree.scope(scope).bindings.values().cloned().collect::<Vec<BindingId>>();
+        ids.sort();
+        for id in ids {
+            if self.captured.contains(&id) && self.tree.binding(id).kind == DeclarationKind::Let {
+                let slot = self.state().slots[&id];
+                self.emit(Instruction::GetCell(slot));
+                self.emit(Instruction::NewCell(slot));
+                self.emit(Instruction::SetCell(slot));
+                self.emit(Instruction::Pop);
+            }
+        }
+        if let Some(ref finally) = inner.finally {
+            self.expression(finally)?;
+            self.emit(Instruction::Pop);
+        }
+        self.jump_to(Instruction::Jump(0), head);
+
+        if let Some(exit) = exit {
+            self.patch(exit);
+        }
+        self.patch_loop(control, next);
+        self.exit_scope();
+        Ok(())
+    }
+
+    /// for-in, for-of and for-await-of, `start` turns the object into an iterator.
+    fn for_each(&mut self,
+                left: &Expression<'ast>,
+                right: &Expression<'ast>,
+                body: &Statement<'ast>,
+                start: Instruction,
+                labels: Vec<String>) -> Result<(), Error> {
+        let is_for_in = start == Instruction::ForInEnumerate;
+
+        self.expression(right)?;
+        self.emit(start);
+        let iterator = self.temp();
+        self.emit(Instruction::SetLocal(iterator));
+        self.emit(Instruction::Pop);
+
+        self.push_control(ControlKind::Loop, labels);
+        if !is_for_in {
+            self.push_control(ControlKind::Iterator(iterator), Vec::new());
+        }
+
+        let head = self.offset();
+        self.emit(Instruction::GetLocal(iterator));
+        self.emit(Instruction::IteratorNext);
+        let done = self.jump(Instruction::JumpIfTrue(0));
+        self.emit(Instruction::Swap);
+        self.emit(Instruction::Pop);
+        self.assign_to(left, Mode::Assign)?;
+        self.statement(body)?;
+        self.jump_to(Instruction::Jump(0), head);
+
+        let iterator_control = if is_for_in { None } else { Some(self.pop_control()) };
+
+        // iterator value
+        self.patch(done);
+        self.emit(Instruction::Pop);
+        self.emit(Instruction::Pop);
+
+        if let Some(control) = iterator_control {
+            if !control.segments.is_empty() {
+                let end = self.jump(Instruction::Jump(0));
+                self.add_handlers(&control);
+                let exception = self.temp();
+                self.emit(Instruction::SetLocal(exception));
+                self.emit(Instruction::Pop);
+                self.emit(Instruction::GetLocal(iterator));
+                self.emit(Instruction::IteratorClose);
+                self.emit(Instruction::GetLocal(exception));
+                self.emit(Instruction::Throw);
+                self.patch(end);
+            }
+        }
+
+        let control = self.pop_control();
+        self.patch_loop(control, head);
+        Ok(())
+    }
+
+    fn switch(&mut self, inner: &SwitchStatement<'ast>, labels: Vec<String>) -> Result<(), Error> {
+        self.expression(&inner.value)?;
+        let value = self.temp();
+        self.emit(Instruction::SetLocal(value));
+        self.emit(Instruction::Pop);
+
+        // NOTE: 所有 case 子句共享同一个词法作用域
+        let scope = self.enter_scope(ScopeKind::Block, inner.loc)?;
+        let bodies = inner.clauses.iter().map(|clause| match clause.body {
+            Statement::Block(block) => block.body.to_vec(),
+            stmt => vec![ stmt ],
+        }).collect::<Vec<Vec<Statement<'ast>>>>();
+        self.declare_bindings(scope)?;
+        for body in bodies.iter() {
+            self.hoist_functions(body)?;
+        }
+
+        let mut cases = Vec::with_capacity(inner.clauses.len());
+        for clause in inner.clauses.iter() {
+            match clause.value {
+                Some(ref test) => {
+                    self.emit(Instruction::GetLocal(value));
+                    self.expression(test)?;
+                    self.emit(Instruction::StrictEq);
+                    cases.push(Some(self.jump(Instruction::JumpIfTrue(0))));
+                },
+                None => cases.push(None),
+            }
+        }
+        let default_jump = self.jump(Instruction::Jump(0));
+
+        self.push_control(ControlKind::Switch, labels);
+        let mut default_target = None;
+        for (case, body) in cases.iter().zip(bodies.iter()) {
+            match *case {
+                Some(jump) => self.patch(jump),
+                None => default_target = Some(self.offset()),
+            }
+            self.statements(body)?;
+        }
+        let control = self.pop_control();
+
+        let end = self.offset();
+        self.state_mut().code.patch_jump(default_jump, default_target.unwrap_or(end));
+        for jump in control.breaks.iter() {
+            self.patch(*jump);
+        }
+        self.exit_scope();
+        Ok(())
+    }
+
+    fn try_statement(&mut self, inner: &TryStatement<'ast>) -> Result<(), Error> {
+        if let Some(finally) = inner.finally {
+            let scope = self.state().current;
+            self.push_control(ControlKind::Finally(finally, scope), Vec::new());
+        }
+
+        match inner.catch_body {
+            Some(ref catch_body) => {
+                self.push_control(ControlKind::Catch, Vec::new());
+                self.block(&inner.body)?;
+                let control = self.pop_control();
+                let end = self.jump(Instruction::Jump(0));
+
+                // exception
+                self.add_handlers(&control);
+                let scope = self.enter_scope(ScopeKind::Catch, catch_body.loc)?;
+                self.declare_bindings(scope)?;
+                match inner.catch_parameter {
+                    Some(ref param) => self.assign_to(param, Mode::Init)?,
+                    None => { self.emit(Instruction::Pop); },
+                }
+                self.hoist_functions(catch_body.body)?;
+                self.statements(catch_body.body)?;
+                self.exit_scope();
+                self.patch(end);
+            },
+            None => self.block(&inner.body)?,
+        }
+
+        if let Some(finally) = inner.finally {
+            let control = self.pop_control();
+            let entered = self.entered.clone();
+            self.block(&finally)?;
+
+            if !control.segments.is_empty() {
+                let end = self.jump(Instruction::Jump(0));
+                self.add_handlers(&control);
+                let exception = self.temp();
+                self.emit(Instruction::SetLocal(exception));
+                self.emit(Instruction::Pop);
+                self.entered = entered;
+                self.block(&finally)?;
+                self.emit(Instruction::GetLocal(exception));
+                self.emit(Instruction::Throw);
+                self.patch(end);
+            }
+        }
+
+        Ok(())
+    }
+
+    // ---------- assignment targets ----------
+
+    /// `value ->`, store the value into a binding, a property or a pattern.
+    fn assign_to(&mut self, target: &Expression<'ast>, mode: Mode) -> Result<(), Error> {
+        match *target {
+            Expression::Identifier(ident) => {
+                self.store(ident, mode)?;
+                self.emit(Instruction::Pop);
+            },
+            Expression::Member(_) if mode == Mode::Assign => {
+                match self.target(target)? {
+                    Target::Named(name) => {
+                        // value object
+                        self.emit(Instruction::Swap);
+                        self.emit_set_named(name);
+                    },
+                    Target::Keyed => {
+                        // value object key
+                        self.emit(Instruction::Rot3);
+                        self.emit(Instruction::Rot3);
+                        self.emit(Instruction::SetKeyed);
+                    },
+                    Target::Super => {
+                        self.emit(Instruction::Swap);
+                        self.emit(Instruction::SetSuper);
+                    },
+                    Target::Binding(_) => unreachable!(),
+                }
+                self.emit(Instruction::Pop);
+            },
+            Expression::Parenthesized(inner) if inner.items.len() == 1 => self.assign_to(&inner.items[0], mode)?,
+            Expression::Assignment(inner) if inner.operator == AssignmentOperator::Assign => {
+                self.default_value(&inner.right, &inner.left)?;
+                self.assign_to(&inner.left, mode)?;
+            },
+            Expression::ArrayLiteral(inner) => {
+                let iterator = self.iterator_begin();
+                let len = inner.elems.len();
+                for (index, elem) in inner.elems.iter().enumerate() {
+                    match *elem {
+                        Some(Expression::Spread(spread)) if index + 1 == len => {
+                            self.iterator_rest(iterator);
+                            self.assign_to(&spread.item, mode)?;
+                        },
+                        Some(ref elem) => {
+                            self.iterator_value(iterator);
+                            self.assign_to(elem, mode)?;
+                        },
+                        None => {
+                            self.iterator_value(iterator);
+                            self.emit(Instruction::Pop);
+                        },
+                    }
+                }
+            },
+            Expression::ObjectLiteral(inner) => {
+                let mut excluded = Vec::new();
+                for prop in inner.properties.iter() {
+                    match *prop {
+                        ObjectProperty::Identifier(ref ident) => {
+                            let name = self.string(ident_name(ident));
+                            self.emit(Instruction::Dup);
+                            self.emit_get_named(name);
+                            excluded.push(Err(name));
+                            self.store(ident, mode)?;
+                            self.emit(Instruction::Pop);
+                        },
+                        ObjectProperty::Property { ref name, ref value, .. } => {
+                            excluded.push(self.pattern_property(name)?);
+                            self.assign_to(value, mode)?;
+                        },
+                        ObjectProperty::Spread { ref target, .. } => {
+                            self.object_rest(&excluded);
+                            return self.assign_to(target, mode);
+                        },
+                        ObjectProperty::MethodDefinition(_) => return Err(syntax_error("Invalid destructuring assignment target")),
+                    }
+                }
+                self.emit(Instruction::Pop);
+            },
+            Expression::AssignmentPattern(AssignmentPattern::Array(ref inner)) => {
+                let iterator = self.iterator_begin();
+                for elem in inner.elems.iter() {
+                    self.iterator_value(iterator);
+                    match *elem {
+                        Some(ref elem) => {
+                            if let Some(ref init) = elem.init {
+                                self.default_value(init, &elem.elem)?;
+                            }
+                            self.assign_to(&elem.elem, mode)?;
+                        },
+                        None => { self.emit(Instruction::Pop); },
+                    }
+                }
+                if let Some(ref rest) = inner.rest_elem {
+                    self.iterator_rest(iterator);
+                    self.assign_to(rest, mode)?;
+                }
+            },
+            Expression::AssignmentPattern(AssignmentPattern::Object(ref inner)) => {
+                let mut excluded = Vec::new();
+                for prop in inner.properties.iter() {
+                    match *prop {
+                        AssignmentProperty::Identifier { ref name, ref init, .. } => {
+                            let key = self.string(ident_name(name));
+                            self.emit(Instruction::Dup);
+                            self.emit_get_named(key);
+                            excluded.push(Err(key));
+                            if let Some(ref init) = *init {
+                                self.default_value_named(init, Some(ident_name(name)))?;
+                            }
+                            self.store(name, mode)?;
+                            self.emit(Instruction::Pop);
+                        },
+                        AssignmentProperty::Property { ref name, ref value, .. } => {
+                            excluded.push(self.pattern_property(name)?);
+                            if let Some(ref init) = value.init {
+                                self.default_value(init, &value.elem)?;
+                            }
+                            self.assign_to(&value.elem, mode)?;
+                        },
+                        AssignmentProperty::Spread { ref target, .. } => {
+                            self.object_rest(&excluded);
+                            return self.assign_to(target, mode);
+                        },
+                    }
+                }
+                self.emit(Instruction::Pop);
+            },
+            Expression::BindingPattern(pattern) => self.binding_pattern(pattern, mode)?,
+            _ => return Err(syntax_error("Invalid left-hand side in assignment")),
+        }
+
+        Ok(())
+    }
+
+    fn binding_pattern(&mut self, pattern: &BindingPattern<'ast>, mode: Mode) -> Result<(), Error> {
+        match *pattern {
+            BindingPattern::Array(ref inner) => {
+                let iterator = self.iterator_begin();
+                for elem in inner.elems.iter() {
+                    self.iterator_value(iterator);
+                    match *elem {
+                        Some(ref elem) => self.binding_element(elem, mode)?,
+                        None => { self.emit(Instruction::Pop); },
+                    }
+                }
+                match inner.rest_elem {
+                    Some(&BindingRestElement::Identifier(ref ident)) => {
+                        self.iterator_rest(iterator);
+                        self.store(ident, mode)?;
+                        self.emit(Instruction::Pop);
+                    },
+                    Some(&BindingRestElement::BindingPattern(ref pattern)) => {
+                        self.iterator_rest(iterator);
+                        self.binding_pattern(pattern, mode)?;
+                    },
+                    None => { },
+                }
+            },
+            BindingPattern::Object(ref inner) => {
+                let mut excluded = Vec::new();
+                for prop in inner.properties.iter() {
+                    match *prop {
+                        BindingProperty::SingleNameBinding { ref name, ref init, .. } => {
+                            let key = self.string(ident_name(name));
+                            self.emit(Instruction::Dup);
+                            self.emit_get_named(key);
+                            excluded.push(Err(key));
+                            if let Some(ref init) = *init {
+                                self.default_value_named(init, Some(ident_name(name)))?;
+                            }
+                            self.store(name, mode)?;
+                            self.emit(Instruction::Pop);
+                        },
+                        BindingProperty::Property { ref name, ref value, .. } => {
+                            excluded.push(self.pattern_property(name)?);
+                            self.binding_element(value, mode)?;
+                        },
+                        BindingProperty::Spread { ref name, .. } => {
+                            self.object_rest(&excluded);
+                            self.store(name, mode)?;
+                            self.emit(Instruction::Pop);
+                            return Ok(());
+                        },
+                    }
+                }
+                self.emit(Instruction::Pop);
+            },
+        }
+
+        Ok(())
+    }
+
+    fn binding_element(&mut self, elem: &BindingElement<'ast>, mode: Mode) -> Result<(), Error> {
+        match *elem {
+            BindingElement::SingleNameBinding { ref name, ref init, .. } => {
+                if let Some(ref init) = *init {
+                    self.default_value_named(init, Some(ident_name(name)))?;
+                }
+                self.store(name, mode)?;
+                self.emit(Instruction::Pop);
+            },
+            BindingElement::BindingPattern { ref pattern, ref init, .. } => {
+                if let Some(ref init) = *init {
+                    self.default_value_named(init, None)?;
+                }
+                self.binding_pattern(pattern, mode)?;
+            },
+        }
+
+        Ok(())
+    }
+
+    /// `value -> value`, replace `undefined` with the default value.
+    fn default_value(&mut self, init: &Expression<'ast>, target: &Expression<'ast>) -> Result<(), Error> {
+        let name = match *target {
+            Expression::Identifier(ident) => Some(ident_name(ident)),
+            _ => None,
+        };
+        self.default_value_named(init, name)
+    }
+
+    fn default_value_named(&mut self, init: &Expression<'ast>, name: Option<String>) -> Result<(), Error> {
+        self.emit(Instruction::Dup);
+        self.emit(Instruction::Undefined);
+        self.emit(Instruction::StrictEq);
+        let skip = self.jump(Instruction::JumpIfFalse(0));
+        self.emit(Instruction::Pop);
+        self.named_expression(init, name)?;
+        self.patch(skip);
+        Ok(())
+    }
+
+    /// `iterable ->`, returns the slot of the iterator.
+    fn iterator_begin(&mut self) -> u32 {
+        let iterator = self.temp();
+        self.emit(Instruction::GetIterator);
+        self.emit(Instruction::SetLocal(iterator));
+        self.emit(Instruction::Pop);
+        iterator
+    }
+
+    /// `-> value`, `undefined` when the iterator is done.
+    fn iterator_value(&mut self, iterator: u32) {
+        self.emit(Instruction::GetLocal(iterator));
+        self.emit(Instruction::IteratorNext);
+        self.emit(Instruction::Pop);
+        self.emit(Instruction::Swap);
+        self.emit(Instruction::Pop);
+    }
+
+    /// `-> [rest ..]`
+    fn iterator_rest(&mut self, iterator: u32) {
+        self.emit(Instruction::Array(0));
+        self.emit(Instruction::GetLocal(iterator));
+        self.emit(Instruction::ArraySpread);
+    }
+
+    /// `object -> object value`, returns the name constant or the slot of a computed key.
+    fn pattern_property(&mut self, name: &PropertyName<'ast>) -> Result<Result<u32, u32>, Error> {
+        self.emit(Instruction::Dup);
+        match self.static_key(name) {
+            Some(key) => {
+                let key = self.string(key);
+                self.emit_get_named(key);
+                Ok(Err(key))
+            },
+            None => {
+                let slot = self.temp();
+                self.property_key(name)?;
+                self.emit(Instruction::SetLocal(slot));
+                self.emit(Instruction::GetKeyed);
+                Ok(Ok(slot))
+            },
+        }
+    }
+
+    /// `object -> rest`, copy the properties that are not destructured.
+    fn object_rest(&mut self, excluded: &[Result<u32, u32>]) {
+        self.emit(Instruction::Object);
+        self.emit(Instruction::Swap);
+        self.emit(Instruction::CopyDataProperties);
+        for key in excluded.iter() {
+            self.emit(Instruction::Dup);
+            match *key {
+                Err(name) => self.emit(Instruction::DeleteNamed(name)),
+                Ok(slot) => {
+                    self.emit(Instruction::GetLocal(slot));
+                    self.emit(Instruction::DeleteKeyed)
+                },
+            };
+            self.emit(Instruction::Pop);
+        }
+    }
+
+    /// Push the object and the key of a reference.
+    fn target(&mut self, expr: &Expression<'ast>) -> Result<Target<'ast>, Error> {
+        match unparenthesized(*expr) {
+            Expression::Identifier(ident) => Ok(Target::Binding(ident)),
+            Expression::Member(inner) if inner.optional => Err(syntax_error("Invalid left-hand side in assignment")),
+            Expression::Member(inner) => {
+                if let Expression::Super(_) = inner.left {
+                    self.member_key(inner)?;
+                    return Ok(Target::Super);
+                }
+
+                self.expression(&inner.left)?;
+                if inner.computed {
+                    self.expression(&inner.right)?;
+                    self.emit(Instruction::ToPropertyKey);
+                    Ok(Target::Keyed)
+                } else {
+                    let name = self.member_name(inner)?;
+                    Ok(Target::Named(name))
+                }
+            },
+            _ => Err(syntax_error("Invalid left-hand side in assignment")),
+        }
+    }
+
+    /// `.. -> .. value`, read a reference keeping its object and key.
+    fn get_target(&mut self, target: Target<'ast>) {
+        match target {
+            Target::Binding(ident) => self.load(ident),
+            Target::Named(name) => {
+                self.emit(Instruction::Dup);
+                self.emit_get_named(name);
+            },
+            Target::Keyed => {
+                self.emit(Instruction::Dup2);
+                self.emit(Instruction::GetKeyed);
+            },
+            Target::Super => {
+                self.emit(Instruction::Dup);
+                self.emit(Instruction::GetSuper);
+            },
+        }
+    }
+
+    /// `.. value -> value`
+    fn set_target(&mut self, target: Target<'ast>) -> Result<(), Error> {
+        match target {
+            Target::Binding(ident) => return self.store(ident, Mode::Assign),
+            Target::Named(name) => self.emit_set_named(name),
+            Target::Keyed => self.emit(Instruction::SetKeyed),
+            Target::Super => self.emit(Instruction::SetSuper),
+        };
+        Ok(())
+    }
+
+    /// `.. value -> value`, drop the object and the key.
+    fn drop_target(&mut self, target: Target<'ast>) {
+        match target {
+            Target::Binding(_) => { },
+            Target::Named(_) | Target::Super => {
+                self.emit(Instruction::Swap);
+                self.emit(Instruction::Pop);
+            },
+            Target::Keyed => {
+                self.emit(Instruction::Rot3);
+                self.emit(Instruction::Pop);
+                self.emit(Instruction::Pop);
+            },
+        }
+    }
+
+    // ---------- expressions ----------
+
+    fn expression(&mut self, expr: &Expression<'ast>) -> Result<(), Error> {
+        let saved = self.state().loc;
+        let loc = expr.loc();
+        if !loc.is_dummy() {
+            self.state_mut().loc = loc;
+        }
+        let result = self.expression_inner(expr, None);
+        self.state_mut().loc = saved;
+        result
+    }
+
+    /// Anonymous functions and classes take the name of the binding or the property they are assigned to.
+    fn named_expression(&mut self, expr: &Expression<'ast>, name: Option<String>) -> Result<(), Error> {
+        match (*expr, name.is_some()) {
+            (Expression::Function(_), true)
+            | (Expression::ArrowFunction(_), true)
+            | (Expression::Class(_), true) => {
+                let saved = self.state().loc;
+                let loc = expr.loc();
+                if !loc.is_dummy() {
+                    self.state_mut().loc = loc;
+                }
+                let result = self.expression_inner(expr, name);
+                self.state_mut().loc = saved;
+                result
+            },
+            _ => self.expression(expr),
+        }
+    }
+
+    fn expression_inner(&mut self, expr: &Expression<'ast>, name: Option<String>) -> Result<(), Error> {
+        match *expr {
+            Expression::This(_) => { self.emit(Instruction::This); },
+            Expression::Spread(_) => return Err(syntax_error("Unexpected token '...'")),
+            Expression::Super(_) => return Err(syntax_error("'super' keyword unexpected here")),
+            Expression::Identifier(ident) => self.load(ident),
+            Expression::Null(_) => { self.emit(Instruction::Null); },
+            Expression::Boolean(inner) => { self.emit(if inner.value { Instruction::True } else { Instruction::False }); },
+            Expression::String(inner) => {
+                let index = self.string(string_value(inner));
+                self.emit(Instruction::Const(index));
+            },
+            Expression::Numeric(inner) => self.number(inner.value),
+            Expression::RegularExpression(inner) => {
+                let constant = Constant::RegExp {
+                    pattern: inner.body.iter().collect(),
+                    flags: inner.flags.map(|flags| flags.iter().collect()).unwrap_or_default(),
+                };
+                let index = self.state_mut().code.add_constant(constant);
+                self.emit(Instruction::RegExp(index));
+            },
+            Expression::Template(inner) => self.template(inner)?,
+            Expression::ArrayLiteral(inner) => self.array(inner.elems)?,
+            Expression::ObjectLiteral(inner) => self.object(inner.properties)?,
+            Expression::Function(inner) => {
+                let name = inner.name.as_ref().map(|ident| ident_name(ident)).or(name).unwrap_or_default();
+                let flags = Self::function_flags(inner.is_async, inner.is_generator);
+                let params = parenthesized_items(&inner.func.params);
+                let index = self.function(name, inner.name.as_ref(), params, FunctionBodyKind::Body(inner.func.body), flags, inner.func.loc)?;
+                self.emit(Instruction::Closure(index));
+            },
+            Expression::ArrowFunction(inner) => {
+                let params = match inner.params {
+                    Expression::Parenthesized(ref params) => parenthesized_items(params),
+                    ref params => ::std::slice::from_ref(params),
+                };
+                let body = match inner.body {
+                    ConciseBody::Expr(expr) => FunctionBodyKind::Expr(expr),
+                    ConciseBody::Stmt(body) => FunctionBodyKind::Body(body),
+                };
+                let mut flags = Self::function_flags(inner.is_async, false);
+                flags.insert(CodeFlags::ARROW);
+                let index = self.function(name.unwrap_or_default(), None, params, body, flags, inner.loc)?;
+                self.emit(Instruction::Closure(index));
+            },
+            Expression::Class(inner) => {
+                let name = inner.name.as_ref().map(|ident| ident_name(ident)).or(name).unwrap_or_default();
+                self.class(inner.name.as_ref(), &inner.class, name)?;
+            },
+            Expression::Parenthesized(inner) => self.sequence(inner.items)?,
+            Expression::Comma(inner) => self.sequence(inner.items)?,
+            Expression::Member(_) | Expression::Call(_) => {
+                let mut jumps = Vec::new();
+                self.chain_element(expr, &mut jumps)?;
+                self.chain_end(jumps);
+            },
+            Expression::TaggedTemplate(inner) => {
+                self.callee(&inner.tag, &mut Vec::new())?;
+                let constant = Constant::Template {
+                    cooked: inner.template.strings.iter().map(template_cooked).collect(),
+                    raw: inner.template.strings.iter().map(|lit| lit.raw.iter().collect()).collect(),
+                };
+                let index = self.state_mut().code.add_constant(constant);
+                self.emit(Instruction::TemplateObject(index));
+                for bound in inner.template.bounds.iter() {
+                    self.expression(bound)?;
+                }
+                self.emit(Instruction::Call(inner.template.bounds.len() as u32 + 1));
+            },
+            Expression::NewTarget(_) => { self.emit(Instruction::NewTarget); },
+            Expression::New(inner) => {
+                self.expression(&inner.callee)?;
+                let arguments = inner.arguments.as_ref().map(|arguments| parenthesized_items(arguments)).unwrap_or(&[]);
+                match self.arguments(arguments)? {
+                    Some(argc) => self.emit(Instruction::New(argc)),
+                    None => self.emit(Instruction::NewSpread),
+                };
+            },
+            Expression::Prefix(inner) => self.prefix(inner.operator, &inner.operand)?,
+            Expression::Infix(inner) => {
+                self.expression(&inner.left)?;
+                let condition = match inner.operator {
+                    InfixOperator::And => Instruction::JumpIfFalse(0),
+                    InfixOperator::Or => Instruction::JumpIfTrue(0),
+                    InfixOperator::NullishCoalescing => Instruction::JumpIfNotNullish(0),
+                    operator => {
+                        self.expression(&inner.right)?;
+                        self.emit(infix_instruction(operator).expect("Ooops ..."));
+                        return Ok(());
+                    },
+                };
+                self.emit(Instruction::Dup);
+                let end = self.jump(condition);
+                self.emit(Instruction::Pop);
+                self.expression(&inner.right)?;
+                self.patch(end);
+            },
+            Expression::Postfix(inner) => {
+                let increment = inner.operator == PostfixOperator::Increment;
+                self.update(&inner.operand, increment, false)?;
+            },
+            Expression::Assignment(inner) => self.assignment(&inner.left, inner.operator, &inner.right)?,
+            Expression::Conditional(inner) => {
+                self.expression(&inner.condition)?;
+                let else_jump = self.jump(Instruction::JumpIfFalse(0));
+                self.expression(&inner.and_then)?;
+                let end = self.jump(Instruction::Jump(0));
+                self.patch(else_jump);
+                self.expression(&inner.or_else)?;
+                self.patch(end);
+            },
+            Expression::Yield(inner) => {
+                self.expression(&inner.item)?;
+                if inner.star {
+                    self.yield_star()?;
+                } else {
+                    if self.state().is_async {
+                        self.emit(Instruction::Await);
+                    }
+                    self.emit(Instruction::Yield);
+                }
+            },
+            Expression::AssignmentPattern(_)
+            | Expression::BindingPattern(_) => return Err(syntax_error("Invalid destructuring assignment target")),
+            Expression::JSXFragment(_)
+            | Expression::JSXElement(_) => return Err(syntax_error("JSX must be transformed before compiling to bytecode")),
+        }
+
+        Ok(())
+    }
+
+    /// `a, b, c`, the value of the last item.
+    fn sequence(&mut self, items: &[Expression<'ast>]) -> Result<(), Error> {
+        if items.is_empty() {
+            self.emit(Instruction::Undefined);
+            return Ok(());
+        }
+
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                self.emit(Instruction::Pop);
+            }
+            self.expression(item)?;
+        }
+
+        Ok(())
+    }
+
+    fn template(&mut self, template: &LiteralTemplateExpression<'ast>) -> Result<(), Error> {
+        let first = template.strings.first().map(string_value).unwrap_or_default();
+        let index = self.string(first);
+        self.emit(Instruction::Const(index));
+
+        for (index, bound) in template.bounds.iter().enumerate() {
+            self.expression(bound)?;
+            self.emit(Instruction::ToString);
+            self.emit(Instruction::Add);
+
+            let string = template.strings.get(index + 1).map(string_value).unwrap_or_default();
+            if !string.is_empty() {
+                let string = self.string(string);
+                self.emit(Instruction::Const(string));
+                self.emit(Instruction::Add);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn array(&mut self, elems: &[Option<Expression<'ast>>]) -> Result<(), Error> {
+        let is_simple = elems.iter().all(|elem| match *elem {
+            Some(Expression::Spread(_)) | None => false,
+            Some(_) => true,
+        });
+
+        if is_simple {
+            for elem in elems.iter().flatten() {
+                self.expression(elem)?;
+            }
+            self.emit(Instruction::Array(elems.len() as u32));
+            return Ok(());
+        }
+
+        self.emit(Instruction::Array(0));
+        for elem in elems.iter() {
+            match *elem {
+                Some(Expression::Spread(inner)) => {
+                    self.expression(&inner.item)?;
+                    self.emit(Instruction::ArraySpread);
+                },
+                Some(ref elem) => {
+                    self.expression(elem)?;
+                    self.emit(Instruction::ArrayPush);
+                },
+                None => { self.emit(Instruction::ArrayHole); },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Push the arguments of a call, returns `None` when they are collected in an array for spreading.
+    fn arguments(&mut self, items: &[Expression<'ast>]) -> Result<Option<u32>, Error> {
+        let has_spread = items.iter().any(|item| match *item {
+            Expression::Spread(_) => true,
+            _ => false,
+        });
+
+        if !has_spread {
+            for item in items.iter() {
+                self.expression(item)?;
+            }
+            return Ok(Some(items.len() as u32));
+        }
+
+        self.emit(Instruction::Array(0));
+        for item in items.iter() {
+            match *item {
+                Expression::Spread(inner) => {
+                    self.expression(&inner.item)?;
+                    self.emit(Instruction::ArraySpread);
+                },
+                _ => {
+                    self.expression(item)?;
+                    self.emit(Instruction::ArrayPush);
+                },
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The name of a non computed property key.
+    fn static_key(&self, name: &PropertyName<'ast>) -> Option<String> {
+        match *name {
+            PropertyName::Identifier(ref ident) => Some(ident_name(ident)),
+            PropertyName::String(ref lit) => Some(string_value(lit)),
+            PropertyName::Numberic(ref lit) => Some(Number(number_value(lit.value)).to_string()),
+            PropertyName::Computed(_) => None,
+        }
+    }
+
+    /// `-> key`
+    fn property_key(&mut self, name: &PropertyName<'ast>) -> Result<(), Error> {
+        match *name {
+            PropertyName::Computed(ref expr) => {
+                self.expression(expr)?;
+                self.emit(Instruction::ToPropertyKey);
+            },
+            _ => {
+                let key = self.static_key(name).expect("Ooops ...");
+                let key = self.string(key);
+                self.emit(Instruction::Const(key));
+            },
+        }
+
+        Ok(())
+    }
+
+    /// The key of a method, `None` when it is computed.
+    fn method_key(&mut self, name: &Expression<'ast>) -> Result<Option<String>, Error> {
+        let key = match *name {
+            Expression::Identifier(ident) => ident_name(ident),
+            Expression::String(lit) => string_value(lit),
+            Expression::Numeric(lit) => Number(number_value(lit.value)).to_string(),
+            _ => {
+                self.expression(name)?;
+                self.emit(Instruction::ToPropertyKey);
+                return Ok(None);
+            },
+        };
+
+        let index = self.string(key.clone());
+        self.emit(Instruction::Const(index));
+        Ok(Some(key))
+    }
+
+    /// `object -> object`
+    fn define_method(&mut self, method: &MethodDefinition<'ast>, enumerable: u8, flags: CodeFlags) -> Result<(), Error> {
+        let name = self.method_key(method.name())?.unwrap_or_default();
+        let index = self.method(method, name, flags)?;
+        self.emit(Instruction::Closure(index));
+        match *method {
+            MethodDefinition::Method(_) => self.emit(Instruction::DefineMethod(enumerable)),
+            MethodDefinition::Getter(_) => self.emit(Instruction::DefineGetter(enumerable)),
+            MethodDefinition::Setter(_) => self.emit(Instruction::DefineSetter(enumerable)),
+        };
+        Ok(())
+    }
+
+    fn object(&mut self, properties: &[ObjectProperty<'ast>]) -> Result<(), Error> {
+        self.emit(Instruction::Object);
+
+        for prop in properties.iter() {
+            match *prop {
+                ObjectProperty::Identifier(ref ident) => {
+                    let name = self.string(ident_name(ident));
+                    self.load(ident);
+                    self.emit(Instruction::DefineNamed(name));
+                },
+                ObjectProperty::Property { ref name, ref value, .. } => {
+                    match self.static_key(name) {
+                        // NOTE: 只有非计算的 `__proto__: value` 设置原型
+                        Some(ref key) if key == "__proto__" => {
+                            self.expression(value)?;
+                            self.emit(Instruction::SetPrototype);
+                        },
+                        Some(key) => {
+                            let index = self.string(key.clone());
+                            self.named_expression(value, Some(key))?;
+                            self.emit(Instruction::DefineNamed(index));
+                        },
+                        None => {
+                            self.property_key(name)?;
+                            self.expression(value)?;
+                            self.emit(Instruction::DefineField);
+                        },
+                    }
+                },
+                ObjectProperty::MethodDefinition(ref method) => self.define_method(method, 1, CodeFlags::empty())?,
+                ObjectProperty::Spread { ref target, .. } => {
+                    self.expression(target)?;
+                    self.emit(Instruction::CopyDataProperties);
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `-> constructor`
+    fn class(&mut self, name: Option<&'ast Identifier<'ast>>, class: &Class<'ast>, class_name: String) -> Result<(), Error> {
+        let scope = self.enter_scope(ScopeKind::Class, class.loc)?;
+        self.declare_bindings(scope)?;
+
+        match class.heritage {
+            Some(ref heritage) => self.expression(heritage)?,
+            None => { self.emit(Instruction::Hole); },
+        }
+
+        let is_constructor = |method: &MethodDefinition| match *method {
+            MethodDefinition::Method(ref inner) => match inner.name {
+                Expression::Identifier(ident) => ident_name(ident) == "constructor",
+                Expression::String(lit) => string_value(lit) == "constructor",
+                _ => false,
+            },
+            _ => false,
+        };
+
+        let mut flags = CodeFlags::CLASS_CONSTRUCTOR;
+        flags.insert(CodeFlags::STRICT);
+        if class.heritage.is_some() {
+            flags.insert(CodeFlags::DERIVED);
+        }
+
+        let constructor = class.body.iter().find(|method| !method.is_static && is_constructor(&method.method));
+        let index = match constructor {
+            Some(method) => self.method(&method.method, class_name.clone(), flags)?,
+            None => {
+                // NOTE: 默认的构造函数 `constructor(...args) { super(...args); }`
+                let mut code = CodeObject::new(class_name.clone());
+                code.flags = flags;
+                let loc = self.state().loc;
+                if class.heritage.is_some() {
+                    code.emit(Instruction::Rest(0), loc);
+                    code.emit(Instruction::SuperCallSpread, loc);
+                    code.emit(Instruction::Pop, loc);
+                }
+                code.emit(Instruction::Undefined, loc);
+                code.emit(Instruction::Return, loc);
+                self.state_mut().code.add_constant(Constant::Function(Box::new(code)))
+            },
+        };
+        self.emit(Instruction::Closure(index));
+        let name_index = self.string(class_name);
+        self.emit(Instruction::Class(name_index));
+
+        // constructor prototype
+        let mut method_flags = CodeFlags::empty();
+        method_flags.insert(CodeFlags::STRICT);
+        for method in class.body.iter() {
+            if constructor.map(|constructor| ::std::ptr::eq(constructor, method)).unwrap_or(false) {
+                continue;
+            }
+            if method.is_static {
+                self.emit(Instruction::Swap);
+            }
+            self.define_method(&method.method, 0, method_flags)?;
+            if method.is_static {
+                self.emit(Instruction::Swap);
+            }
+        }
+        self.emit(Instruction::Pop);
+
+        if let Some(ident) = name {
+            self.store(ident, Mode::Init)?;
+        }
+        self.exit_scope();
+        Ok(())
+    }
+
+    fn member_name(&mut self, member: &MemberExpression<'ast>) -> Result<u32, Error> {
+        match member.right {
+            Expression::Identifier(ident) => Ok(self.string(ident_name(ident))),
+            _ => Err(syntax_error("Unexpected token")),
+        }
+    }
+
+    /// `-> key`
+    fn member_key(&mut self, member: &MemberExpression<'ast>) -> Result<(), Error> {
+        if member.computed {
+            self.expression(&member.right)?;
+            self.emit(Instruction::ToPropertyKey);
+        } else {
+            let name = self.member_name(member)?;
+            self.emit(Instruction::Const(name));
+        }
+
+        Ok(())
+    }
+
+    /// Compile a link of an optional chain, `jumps` are the jumps to the end of the chain
+    /// with the number of values to pop there.
+    fn chain_element(&mut self, expr: &Expression<'ast>, jumps: &mut Vec<(u32, u32)>) -> Result<(), Error> {
+        let saved = self.state().loc;
+        let loc = expr.loc();
+        if !loc.is_dummy() {
+            self.state_mut().loc = loc;
+        }
+
+        let result = match *expr {
+            Expression::Member(inner) => self.member(inner, jumps),
+            Expression::Call(inner) => self.call(inner, jumps),
+            _ => self.expression(expr),
+        };
+
+        self.state_mut().loc = saved;
+        result
+    }
+
+    fn chain_end(&mut self, jumps: Vec<(u32, u32)>) {
+        if jumps.is_empty() {
+            return;
+        }
+
+        // NOTE: 不同深度的跳转依次落到 Pop 上：`L2: Pop  L1: Pop  Undefined`
+        let end = self.jump(Instruction::Jump(0));
+        let max = jumps.iter().map(|&(_, pops)| pops).max().unwrap_or(0);
+        for pops in (1..max + 1).rev() {
+            for &(jump, _) in jumps.iter().filter(|&&(_, n)| n == pops) {
+                self.patch(jump);
+            }
+            self.emit(Instruction::Pop);
+        }
+        self.emit(Instruction::Undefined);
+        self.patch(end);
+    }
+
+    /// `value -> value`, or jump to the end of the chain when the value is nullish.
+    fn optional(&mut self, pops: u32, jumps: &mut Vec<(u32, u32)>) {
+        self.emit(Instruction::Dup);
+        let jump = self.jump(Instruction::JumpIfNullish(0));
+        jumps.push((jump, pops));
+    }
+
+    fn member(&mut self, inner: &MemberExpression<'ast>, jumps: &mut Vec<(u32, u32)>) -> Result<(), Error> {
+        if let Expression::Super(_) = inner.left {
+            self.member_key(inner)?;
+            self.emit(Instruction::GetSuper);
+            return Ok(());
+        }
+
+        self.chain_element(&inner.left, jumps)?;
+        if inner.optional {
+            self.optional(1, jumps);
+        }
+        if inner.computed {
+            self.expression(&inner.right)?;
+            self.emit(Instruction::GetKeyed);
+        } else {
+            let name = self.member_name(inner)?;
+            self.emit_get_named(name);
+        }
+
+        Ok(())
+    }
+
+    /// `-> function this`
+    fn callee(&mut self, callee: &Expression<'ast>, jumps: &mut Vec<(u32, u32)>) -> Result<(), Error> {
+        match *callee {
+            Expression::Member(inner) => {
+                if let Expression::Super(_) = inner.left {
+                    self.member_key(inner)?;
+                    self.emit(Instruction::GetSuper);
+                    self.emit(Instruction::This);
+                    return Ok(());
+                }
+
+                self.chain_element(&inner.left, jumps)?;
+                if inner.optional {
+                    self.optional(1, jumps);
+                }
+                self.emit(Instruction::Dup);
+                if inner.computed {
+                    self.expression(&inner.right)?;
+                    self.emit(Instruction::GetKeyed);
+                } else {
+                    let name = self.member_name(inner)?;
+                    self.emit_get_named(name);
+                }
+                self.emit(Instruction::Swap);
+            },
+            _ => {
+                self.chain_element(callee, jumps)?;
+                self.emit(Instruction::Undefined);
+            },
+        }
+
+        Ok(())
+    }
+
+    fn call(&mut self, inner: &CallExpression<'ast>, jumps: &mut Vec<(u32, u32)>) -> Result<(), Error> {
+        let arguments = parenthesized_items(&inner.arguments);
+
+        if let Expression::Super(_) = inner.callee {
+            match self.arguments(arguments)? {
+                Some(argc) => self.emit(Instruction::SuperCall(argc)),
+                None => self.emit(Instruction::SuperCallSpread),
+            };
+            return Ok(());
+        }
+
+        self.callee(&inner.callee, jumps)?;
+        if inner.optional {
+            // function this -> this function
+            self.emit(Instruction::Swap);
+            self.optional(2, jumps);
+            self.emit(Instruction::Swap);
+        }
+
+        match self.arguments(arguments)? {
+            Some(argc) => self.emit(Instruction::Call(argc)),
+            None => self.emit(Instruction::CallSpread),
+        };
+
+        Ok(())
+    }
+
+    fn prefix(&mut self, operator: PrefixOperator, operand: &Expression<'ast>) -> Result<(), Error> {
+        let instruction = match operator {
+            PrefixOperator::Await => Instruction::Await,
+            PrefixOperator::Positive => Instruction::Plus,
+            PrefixOperator::Negative => Instruction::Neg,
+            PrefixOperator::BitNot => Instruction::BitNot,
+            PrefixOperator::Not => Instruction::Not,
+            PrefixOperator::Increment => return self.update(operand, true, true),
+            PrefixOperator::Decrement => return self.update(operand, false, true),
+            PrefixOperator::Void => {
+                self.expression(operand)?;
+                self.emit(Instruction::Pop);
+                self.emit(Instruction::Undefined);
+                return Ok(());
+            },
+            PrefixOperator::TypeOf => {
+                if let Expression::Identifier(ident) = unparenthesized(*operand) {
+                    let (_, place) = self.resolve(&ident_name(ident));
+                    if let Place::Name(name) = place {
+                        self.emit(Instruction::TypeofName(name));
+                        return Ok(());
+                    }
+                }
+                Instruction::TypeOf
+            },
+            PrefixOperator::Delete => return self.delete(operand),
+        };
+
+        self.expression(operand)?;
+        self.emit(instruction);
+        Ok(())
+    }
+
+    fn delete(&mut self, operand: &Expression<'ast>) -> Result<(), Error> {
+        match unparenthesized(*operand) {
+            Expression::Member(inner) => {
+                if let Expression::Super(_) = inner.left {
+                    self.throw_error(ErrorKind::ReferenceError, "Unsupported reference to 'super'");
+                    self.emit(Instruction::True);
+                    return Ok(());
+                }
+
+                self.expression(&inner.left)?;
+                if inner.computed {
+                    self.expression(&inner.right)?;
+                    self.emit(Instruction::DeleteKeyed);
+                } else {
+                    let name = self.member_name(inner)?;
+                    self.emit(Instruction::DeleteNamed(name));
+                }
+            },
+            Expression::Identifier(ident) => {
+                if self.state().is_strict {
+                    return Err(syntax_error("Delete of an unqualified identifier in strict mode."));
+                }
+                match self.resolve(&ident_name(ident)).1 {
+                    Place::Name(name) => self.emit(Instruction::DeleteName(name)),
+                    _ => self.emit(Instruction::False),
+                };
+            },
+            _ => {
+                self.expression(operand)?;
+                self.emit(Instruction::Pop);
+                self.emit(Instruction::True);
+            },
+        }
+
+        Ok(())
+    }
+
+    /// `++a`, `a--` ..
+    fn update(&mut self, operand: &Expression<'ast>, increment: bool, is_prefix: bool) -> Result<(), Error> {
+        let instruction = if increment { Instruction::Inc } else { Instruction::Dec };
+        let target = self.target(operand)
+            .map_err(|_| syntax_error("Invalid left-hand side expression in postfix operation"))?;
+
+        self.get_target(target);
+        if is_prefix {
+            self.emit(instruction);
+            return self.set_target(target);
+        }
+
+        let old = self.temp();
+        self.emit(Instruction::ToNumeric);
+        self.emit(Instruction::SetLocal(old));
+        self.emit(instruction);
+        self.set_target(target)?;
+        self.emit(Instruction::Pop);
+        self.emit(Instruction::GetLocal(old));
+        Ok(())
+    }
+
+    fn assignment(&mut self, left: &Expression<'ast>, operator: AssignmentOperator, right: &Expression<'ast>) -> Result<(), Error> {
+        let left = unparenthesized(*left);
+        let name = match left {
+            Expression::Identifier(ident) => Some(ident_name(ident)),
+            _ => None,
+        };
+
+        if operator == AssignmentOperator::Assign {
+            return match left {
+                Expression::Identifier(ident) => {
+                    self.named_expression(right, name)?;
+                    self.store(ident, Mode::Assign)
+                },
+                Expression::Member(_) => {
+                    let target = self.target(&left)?;
+                    self.expression(right)?;
+                    self.set_target(target)
+                },
+                _ => {
+                    self.expression(right)?;
+                    self.emit(Instruction::Dup);
+                    self.assign_to(&left, Mode::Assign)
+                },
+            };
+        }
+
+        let target = self.target(&left)?;
+        self.get_target(target);
+
+        if let Some(instruction) = assignment_instruction(operator) {
+            self.expression(right)?;
+            self.emit(instruction);
+            return self.set_target(target);
+        }
+
+        // a &&= b    a ||= b    a ??= b
+        let condition = match operator {
+            AssignmentOperator::AndAssign => Instruction::JumpIfFalse(0),
+            AssignmentOperator::OrAssign => Instruction::JumpIfTrue(0),
+            _ => Instruction::JumpIfNotNullish(0),
+        };
+        self.emit(Instruction::Dup);
+        let short = self.jump(condition);
+        self.emit(Instruction::Pop);
+        self.named_expression(right, name)?;
+        self.set_target(target)?;
+        let end = self.jump(Instruction::Jump(0));
+        self.patch(short);
+        self.drop_target(target);
+        self.patch(end);
+        Ok(())
+    }
+
+    /// `iterable -> result`
+    fn yield_star(&mut self) -> Result<(), Error> {
+        let iterator = self.temp();
+        if self.state().is_async {
+            self.emit(Instruction::GetAsyncIterator);
+        } else {
+            self.emit(Instruction::GetIterator);
+        }
+        self.emit(Instruction::SetLocal(iterator));
+        self.emit(Instruction::Pop);
+
+        let head = self.offset();
+        self.emit(Instruction::GetLocal(iterator));
+        self.emit(Instruction::IteratorNext);
+        let done = self.jump(Instruction::JumpIfTrue(0));
+        self.emit(Instruction::Swap);
+        self.emit(Instruction::Pop);
+        self.emit(Instruction::Yield);
+        self.emit(Instruction::Pop);
+        self.jump_to(Instruction::Jump(0), head);
+
+        // iterator value
+        self.patch(done);
+        self.emit(Instruction::Swap);
+        self.emit(Instruction::Pop);
+        Ok(())
+    }
+}
+
+fn matches_labelled(stmt: &Statement) -> bool {
+    match *stmt {
+        Statement::Labelled(_) => true,
+        _ => false,
+    }
+}
+
+
+#[cfg(test)]
+fn compile_owned(body: Vec<crate::ast::owned::Statement>) -> Result<CodeObject, Error> {
+    use crate::toolshed::Arena;
+    use crate::ast::owned::ToArenaAst;
+
+    let arena = Arena::new();
+    let body = body.iter().map(|stmt| stmt.to_arena_ast(&arena)).collect::<Vec<_>>();
+    compile(&body, Goal::Script)
+}
+
+#[cfg(test)]
+fn function_constant(code: &CodeObject, index: usize) -> &CodeObject {
+    match code.constants.iter().filter_map(|constant| match *constant {
+        Constant::Function(ref code) => Some(code),
+        _ => None,
+    }).nth(index) {
+        Some(code) => code,
+        None => panic!("no function constant {} in {:?}", index, code),
+    }
+}
+
+#[test]
+fn test_bytecode_generation() {
+    use crate::toolshed::Arena;
+    use crate::parser::Parser;
+    use crate::ast::builder::*;
+    use crate::ast::owned::{ Statement as OwnedStatement, LabelledStatement, };
+
+    let instructions = |code: &CodeObject| code.instructions().unwrap().into_iter().map(|(_, instruction)| instruction).collect::<Vec<_>>();
+
+    // NOTE: Script 的结果是最后一个表达式语句的值
+    let arena = Arena::new();
+    let source = arena.alloc_vec("1 + 1; function* g(a, b = 1) { yield a; }; x => x;".chars().collect::<Vec<char>>());
+    let mut parser = Parser::new(&arena, &source, "main.js");
+    parser.parse().unwrap();
+    let script = parser.body.byte_code_gen(Goal::Script).unwrap();
+    assert_eq!(&instructions(&script)[..8], &[
+        Instruction::DeclareName(0),
+        Instruction::Closure(1),
+        Instruction::SetName(0),
+        Instruction::Pop,
+        Instruction::Int(1),
+        Instruction::Int(1),
+        Instruction::Add,
+        Instruction::SetLocal(0),
+    ]);
+    let g = function_constant(&script, 0);
+    assert_eq!((g.name.as_str(), g.param_count, g.length), ("g", 2, 1));
+    assert!(g.flags.contains(CodeFlags::GENERATOR) && !g.flags.contains(CodeFlags::ARROW));
+    assert_eq!(instructions(g), vec![
+        Instruction::GetLocal(1),
+        Instruction::Dup,
+        Instruction::Undefined,
+        Instruction::StrictEq,
+        Instruction::JumpIfFalse(3),
+        Instruction::Pop,
+        Instruction::Int(1),
+        Instruction::SetLocal(2),
+        Instruction::Pop,
+        Instruction::InitialYield,
+        Instruction::GetLocal(0),
+        Instruction::Yield,
+        Instruction::Pop,
+        Instruction::Undefined,
+        Instruction::Return,
+    ]);
+    assert!(function_constant(&script, 1).flags.contains(CodeFlags::ARROW));
+
+    // function outer() { var x = 1; return function () { return x; }; }
+    let inner = function_expr(None, vec![], vec![ return_stmt(Some(ident_expr("x"))) ]);
+    let script = compile_owned(vec![
+        function_decl("outer", vec![], vec![ var("x", Some(number(1))), return_stmt(Some(inner)) ]),
+    ]).unwrap();
+    let outer = function_constant(&script, 0);
+    assert_eq!(&instructions(outer)[..6], &[
+        Instruction::NewCell(0),
+        Instruction::Undefined,
+        Instruction::SetCell(0),
+        Instruction::Pop,
+        Instruction::Int(1),
+        Instruction::SetCell(0),
+    ]);
+    let inner = function_constant(outer, 0);
+    assert_eq!(inner.captures, vec![ Capture::Local(0) ]);
+    assert_eq!(instructions(inner)[0], Instruction::GetUpvalue(0));
+
+    // outer: while (a) { try { if (b) continue outer; break; } finally { f(); } }
+    let finally = vec![ expr_stmt(call(ident_expr("f"), vec![])) ];
+    let body = vec![ if_stmt(ident_expr("b"), continue_stmt(Some("outer")), None), break_stmt(None) ];
+    let labelled = OwnedStatement::Labelled(Box::new(LabelledStatement {
+        loc: Default::default(),
+        span: Default::default(),
+        label: ident("outer"),
+        item: while_stmt(ident_expr("a"), block_stmt(vec![ try_stmt(body, None, Some(finally)) ])),
+    }));
+    let script = compile_owned(vec![ labelled ]).unwrap();
+    let calls = instructions(&script).into_iter().filter(|instruction| *instruction == Instruction::Call(0)).count();
+    // NOTE: continue、break、正常结束、异常各一份 finally
+    assert_eq!(calls, 4);
+    assert_eq!(script.handlers.len(), 1);
+    assert!(script.handlers.iter().all(|handler| handler.stack_depth == 0 && handler.target > handler.end));
+
+    let error = compile_owned(vec![ break_stmt(None) ]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::SyntaxError);
+}
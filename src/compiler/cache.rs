@@ -0,0 +1,176 @@
+// Bytecode cache files
+//
+//      magic       "ESBC"
+//      version     u32  FORMAT_VERSION
+//      goal        u8   0: Script, 1: Module
+//      source      u64  hash of the source text
+//      length      u64  length of the payload
+//      checksum    u64  hash of the payload
+//      payload     the top level `CodeObject`, nested functions are in its constant table
+//
+// NOTE: 定长字段都是小端序，哈希使用 FNV-1a（64 位），不依赖于平台和编译器版本。
+//       指令集、常量或者 `CodeObject` 的编码有任何变化都需要增加 `FORMAT_VERSION`，
+//       旧的缓存文件会被拒绝，然后重新编译。
+//       加载时先检查所有的字段与校验和，再解码并用 `verifier` 检查每一个函数，检查通过之后才会交给 VM 执行。
+
+use crate::error::{ ErrorKind, Error, };
+use crate::compiler::scope::Goal;
+use crate::compiler::bytecode::CodeObject;
+use crate::compiler::verifier;
+
+use fnv::FnvHasher;
+
+use std::fs;
+use std::path::Path;
+use std::hash::Hasher;
+
+
+pub const MAGIC: &[u8; 4] = b"ESBC";
+pub const FORMAT_VERSION: u32 = 2;
+
+const HEADER_SIZE: usize = 4 + 4 + 1 + 8 + 8 + 8;
+
+
+pub fn hash(bytes: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[inline]
+pub fn source_hash(source: &str) -> u64 {
+    hash(source.as_bytes())
+}
+
+fn invalid<S: Into<String>>(message: S) -> Error {
+    Error::new(ErrorKind::InternalError, format!("invalid bytecode cache: {}", message.into()))
+}
+
+fn read_u64(input: &[u8], pos: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&input[pos..pos + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+
+pub fn encode(code: &CodeObject, source: &str, goal: Goal) -> Vec<u8> {
+    let mut payload = Vec::new();
+    code.encode(&mut payload);
+
+    let mut output = Vec::with_capacity(HEADER_SIZE + payload.len());
+    output.extend_from_slice(MAGIC);
+    output.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    output.push(match goal { Goal::Script => 0, Goal::Module => 1, });
+    output.extend_from_slice(&source_hash(source).to_le_bytes());
+    output.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    output.extend_from_slice(&hash(&payload).to_le_bytes());
+    output.extend_from_slice(&payload);
+
+    output
+}
+
+/// Decode a cache file, it must be written by this format version for the same source and goal.
+pub fn decode(input: &[u8], source: &str, goal: Goal) -> Result<CodeObject, Error> {
+    if input.len() < HEADER_SIZE || &input[..4] != MAGIC {
+        return Err(invalid("not a bytecode cache file"));
+    }
+
+    let mut version = [0u8; 4];
+    version.copy_from_slice(&input[4..8]);
+    let version = u32::from_le_bytes(version);
+    if version != FORMAT_VERSION {
+        return Err(invalid(format!("format version {} is not supported (expected {})", version, FORMAT_VERSION)));
+    }
+
+    let expected = match goal { Goal::Script => 0, Goal::Module => 1, };
+    if input[8] != expected {
+        return Err(invalid("compiled for another goal"));
+    }
+    if read_u64(input, 9) != source_hash(source) {
+        return Err(invalid("the source has changed"));
+    }
+
+    let payload = &input[HEADER_SIZE..];
+    if read_u64(input, 17) != payload.len() as u64 {
+        return Err(invalid("payload length mismatch"));
+    }
+    if read_u64(input, 25) != hash(payload) {
+        return Err(invalid("checksum mismatch"));
+    }
+
+    let mut pos = 0;
+    let code = CodeObject::decode(payload, &mut pos).map_err(|error| invalid(error.message()))?;
+    if pos != payload.len() {
+        return Err(invalid(format!("unexpected data at {} after the code object", pos)));
+    }
+
+    verifier::verify(&code)?;
+
+    Ok(code)
+}
+
+pub fn write<P: AsRef<Path>>(path: P, code: &CodeObject, source: &str, goal: Goal) -> Result<(), Error> {
+    fs::write(path.as_ref(), encode(code, source, goal))
+        .map_err(|error| Error::new(ErrorKind::InternalError, format!("{}: {}", path.as_ref().display(), error)))
+}
+
+pub fn read<P: AsRef<Path>>(path: P, source: &str, goal: Goal) -> Result<CodeObject, Error> {
+    let input = fs::read(path.as_ref())
+        .map_err(|error| Error::new(ErrorKind::InternalError, format!("{}: {}", path.as_ref().display(), error)))?;
+    decode(&input, source, goal)
+}
+
+
+#[test]
+fn test_bytecode_cache() {
+    use crate::compiler::bytecode::{ Constant, Instruction, Handler, };
+    use crate::lexer::span::Loc;
+
+    let loc = |start, end| Loc { start, end };
+    let source = "f(1.5)";
+
+    let mut f = CodeObject::new("f");
+    f.param_count = 1;
+    f.local_count = 1;
+    f.emit(Instruction::GetLocal(0), loc(0, 1));
+    f.emit(Instruction::Return, loc(0, 1));
+
+    let mut code = CodeObject::new("<script>");
+    let index = code.add_constant(Constant::Function(Box::new(f)));
+    let number = code.add_constant(Constant::Number(1.5));
+    code.emit(Instruction::Closure(index), loc(0, 1));
+    code.emit(Instruction::Undefined, loc(0, 1));
+    code.emit(Instruction::Const(number), loc(2, 5));
+    code.emit(Instruction::Call(1), loc(0, 6));
+    code.emit(Instruction::Return, loc(0, 6));
+
+    let encoded = encode(&code, source, Goal::Script);
+    assert_eq!(&encoded[..4], MAGIC);
+    assert_eq!(decode(&encoded, source, Goal::Script), Ok(code.clone()));
+
+    assert!(decode(&encoded, "f(2.5)", Goal::Script).is_err());
+    assert!(decode(&encoded, source, Goal::Module).is_err());
+    assert!(decode(&encoded[..encoded.len() - 1], source, Goal::Script).is_err());
+
+    let mut corrupted = encoded.clone();
+    *corrupted.last_mut().unwrap() ^= 1;
+    assert_eq!(decode(&corrupted, source, Goal::Script).unwrap_err().kind(), ErrorKind::InternalError);
+
+    let mut version = encoded.clone();
+    version[4] = FORMAT_VERSION as u8 + 1;
+    assert!(decode(&version, source, Goal::Script).is_err());
+
+    // NOTE: 校验和正确，但是引用了不存在的常量
+    let mut invalid_code = code.clone();
+    invalid_code.emit(Instruction::Const(9), loc(0, 6));
+    assert!(decode(&encode(&invalid_code, source, Goal::Script), source, Goal::Script).is_err());
+
+    let mut invalid_code = code.clone();
+    invalid_code.handlers.push(Handler { start: 0, end: 2, target: 100, stack_depth: 0 });
+    assert!(decode(&encode(&invalid_code, source, Goal::Script), source, Goal::Script).is_err());
+
+    let path = std::env::temp_dir().join(format!("ecmascript-test-{}.esbc", std::process::id()));
+    write(&path, &code, source, Goal::Script).unwrap();
+    assert_eq!(read(&path, source, Goal::Script), Ok(code));
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,1549 @@
+use crate::lexer::span::{ Loc, Span, LineColumn, };
+use crate::lexer::token::{ Identifier, LiteralString, };
+use crate::lexer::operator::{ PrefixOperator, InfixOperator, PostfixOperator, AssignmentOperator, };
+use crate::ast::statement::{
+    Statement, VariableStatement, LexicalDeclarationKind, LexicalBinding,
+    BlockStatement, IfStatement, ForStatement, SwitchStatement, TryStatement,
+};
+use crate::ast::expression::{
+    Expression, LiteralTemplateExpression, ParenthesizedExpression,
+};
+use crate::ast::function::{ Function, ConciseBody, FunctionBody, };
+use crate::ast::class::{ Class, ClassMethodDefinition, MethodDefinition, };
+use crate::ast::pattern::{
+    PropertyName, ObjectProperty,
+    BindingPattern, BindingElement, BindingProperty, BindingRestElement,
+    AssignmentPattern, AssignmentElement, AssignmentProperty,
+};
+use crate::ast::jsx::{
+    JSXElement, JSXFragment, JSXElementName, JSXAttribute, JSXNormalAttributeName,
+    JSXNormalAttributeInitializer, JSXChild, JSXChildren, JSXAttributes,
+};
+use crate::compiler::sourcemap::{ SourceMap, Position, };
+
+use std::io::{ self, Write, };
+use std::collections::HashMap;
+
+
+const INDENT: &str = "    ";
+
+// NOTE: `Expression::precedence` 用 -1 表示 PrimaryExpression，这里将其视为最高优先级。
+const PRIMARY_PRECEDENCE: i8     = 21;
+const ASSIGNMENT_PRECEDENCE: i8  = 3;
+const CONDITIONAL_PRECEDENCE: i8 = 4;
+const UNARY_PRECEDENCE: i8       = 16;
+const POSTFIX_PRECEDENCE: i8     = 17;
+const NEW_PRECEDENCE: i8         = 18;
+const MEMBER_PRECEDENCE: i8      = 19;
+
+
+pub fn prefix_operator_str(op: PrefixOperator) -> &'static str {
+    match op {
+        PrefixOperator::Await => "await",
+        PrefixOperator::Delete => "delete",
+        PrefixOperator::Void => "void",
+        PrefixOperator::TypeOf => "typeof",
+        PrefixOperator::Positive => "+",
+        PrefixOperator::Negative => "-",
+        PrefixOperator::BitNot => "~",
+        PrefixOperator::Not => "!",
+        PrefixOperator::Increment => "++",
+        PrefixOperator::Decrement => "--",
+    }
+}
+
+pub fn infix_operator_str(op: InfixOperator) -> &'static str {
+    match op {
+        InfixOperator::Add => "+",
+        InfixOperator::Sub => "-",
+        InfixOperator::Mul => "*",
+        InfixOperator::Div => "/",
+        InfixOperator::Rem => "%",
+        InfixOperator::Pow => "**",
+        InfixOperator::BitShl => "<<",
+        InfixOperator::BitShr => ">>",
+        InfixOperator::BitUShr => ">>>",
+        InfixOperator::And => "&&",
+        InfixOperator::Or => "||",
+        InfixOperator::NullishCoalescing => "??",
+        InfixOperator::BitAnd => "&",
+        InfixOperator::BitXor => "^",
+        InfixOperator::BitOr => "|",
+        InfixOperator::Gt => ">",
+        InfixOperator::Lt => "<",
+        InfixOperator::GtEq => ">=",
+        InfixOperator::LtEq => "<=",
+        InfixOperator::Eq => "==",
+        InfixOperator::Neq => "!=",
+        InfixOperator::StrictEq => "===",
+        InfixOperator::StrictNeq => "!==",
+        InfixOperator::InstanceOf => "instanceof",
+        InfixOperator::In => "in",
+    }
+}
+
+pub fn postfix_operator_str(op: PostfixOperator) -> &'static str {
+    match op {
+        PostfixOperator::Increment => "++",
+        PostfixOperator::Decrement => "--",
+    }
+}
+
+pub fn assignment_operator_str(op: AssignmentOperator) -> &'static str {
+    match op {
+        AssignmentOperator::Assign => "=",
+        AssignmentOperator::AddAssign => "+=",
+        AssignmentOperator::SubAssign => "-=",
+        AssignmentOperator::MulAssign => "*=",
+        AssignmentOperator::DivAssign => "/=",
+        AssignmentOperator::RemAssign => "%=",
+        AssignmentOperator::PowAssign => "**=",
+        AssignmentOperator::BitAndAssign => "&=",
+        AssignmentOperator::BitOrAssign => "|=",
+        AssignmentOperator::BitXorAssign => "^=",
+        AssignmentOperator::BitShlAssign => "<<=",
+        AssignmentOperator::BitShrAssign => ">>=",
+        AssignmentOperator::BitUShrAssign => ">>>=",
+        AssignmentOperator::AndAssign => "&&=",
+        AssignmentOperator::OrAssign => "||=",
+        AssignmentOperator::NullishAssign => "??=",
+    }
+}
+
+#[inline]
+fn mixes_nullish(operator: InfixOperator, operand: &Expression) -> bool {
+    let is_logical = |op: InfixOperator| op == InfixOperator::And || op == InfixOperator::Or;
+
+    match *operand {
+        Expression::Infix(inner) => {
+            (operator == InfixOperator::NullishCoalescing && is_logical(inner.operator))
+            || (is_logical(operator) && inner.operator == InfixOperator::NullishCoalescing)
+        },
+        _ => false,
+    }
+}
+
+#[inline]
+fn precedence(expr: &Expression) -> i8 {
+    match expr.precedence() {
+        -1 => PRIMARY_PRECEDENCE,
+        n => n,
+    }
+}
+
+/// `;` in statement lists are not printed, the parser keeps the semicolon after statements as `EmptyStatement`.
+#[inline]
+fn is_empty_statement(stmt: &Statement) -> bool {
+    match *stmt {
+        Statement::Empty(_) => true,
+        _ => false,
+    }
+}
+
+#[inline]
+fn is_identifier_part(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$' || c == '\\'
+}
+
+/// Does the expression begin with a token that would make an `ExpressionStatement` ambiguous ?
+/// ( `{`, `function`, `class` )
+fn starts_with_ambiguous_token(expr: &Expression) -> bool {
+    match *expr {
+        Expression::ObjectLiteral(_)
+        | Expression::Function(_)
+        | Expression::Class(_) => true,
+        Expression::AssignmentPattern(AssignmentPattern::Object(_))
+        | Expression::BindingPattern(BindingPattern::Object(_)) => true,
+        Expression::Member(inner) => starts_with_ambiguous_token(&inner.left),
+        Expression::Call(inner) => starts_with_ambiguous_token(&inner.callee),
+        Expression::TaggedTemplate(inner) => starts_with_ambiguous_token(&inner.tag),
+        Expression::Infix(inner) => starts_with_ambiguous_token(&inner.left),
+        Expression::Postfix(inner) => starts_with_ambiguous_token(&inner.operand),
+        Expression::Assignment(inner) => starts_with_ambiguous_token(&inner.left),
+        Expression::Conditional(inner) => starts_with_ambiguous_token(&inner.condition),
+        Expression::Comma(inner) => match inner.items.first() {
+            Some(item) => starts_with_ambiguous_token(item),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// NOTE: 参数列表被解析为 `ParenthesizedExpression`，其唯一的元素可能是 `CommaExpression`。
+fn parenthesized_items<'a, 'ast>(expr: &'a ParenthesizedExpression<'ast>) -> &'a [Expression<'ast>] {
+    match expr.items {
+        [Expression::Comma(inner)] => inner.items,
+        items => items,
+    }
+}
+
+#[inline]
+fn ident_name(ident: &Identifier) -> String {
+    ident.cooked.unwrap_or(ident.raw).iter().collect::<String>()
+}
+
+
+/// Print the AST back to ECMAScript source code.
+///
+/// When a `SourceMap` is attached, every emitted token whose node carries
+/// an original `Loc` records a mapping (identifiers also record their name).
+pub struct CodeGen<'sm, W: Write> {
+    output: W,
+    line: usize,
+    column: usize,
+    indent: usize,
+    minify: bool,
+    last_char: Option<char>,
+
+    source_map: Option<&'sm mut SourceMap>,
+    source_index: usize,
+    original_names: Option<&'sm HashMap<usize, String>>,
+    mapped_line: usize,
+    pending: Option<(LineColumn, Option<String>)>,
+}
+
+impl<'sm, W: Write> CodeGen<'sm, W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            output,
+            line: 0,
+            column: 0,
+            indent: 0,
+            minify: false,
+            last_char: None,
+            source_map: None,
+            source_index: 0,
+            original_names: None,
+            mapped_line: 0,
+            pending: None,
+        }
+    }
+
+    pub fn with_source_map(output: W, source_map: &'sm mut SourceMap, source_index: usize) -> Self {
+        let mut codegen = Self::new(output);
+        codegen.source_map = Some(source_map);
+        codegen.source_index = source_index;
+        codegen
+    }
+
+    pub fn set_minify(&mut self, minify: bool) {
+        self.minify = minify;
+    }
+
+    /// Which entry of the source map `sources` the nodes being printed come from.
+    pub fn set_source_index(&mut self, source_index: usize) {
+        self.source_index = source_index;
+    }
+
+    /// Names of the renamed identifiers by source offset ( `Mangler::original_names` ),
+    /// the source map records these instead of the printed names.
+    pub fn set_original_names(&mut self, original_names: &'sm HashMap<usize, String>) {
+        self.original_names = Some(original_names);
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    // ---------- low level output ----------
+
+    fn raw(&mut self, s: &str) -> io::Result<()> {
+        for c in s.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 0;
+            } else {
+                self.column += 1;
+            }
+        }
+
+        if let Some(c) = s.chars().last() {
+            self.last_char = Some(c);
+        }
+
+        self.output.write_all(s.as_bytes())
+    }
+
+    fn flush_mapping(&mut self) {
+        let (start, name) = match self.pending.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+
+        let source_map = match self.source_map {
+            Some(ref mut source_map) => source_map,
+            None => return,
+        };
+
+        while self.mapped_line < self.line {
+            source_map.add_line();
+            self.mapped_line += 1;
+        }
+
+        let ident_index = name.map(|name| source_map.add_name(&name));
+
+        source_map.add_pos(Position {
+            dst_column: self.column,
+            src_file_index: self.source_index,
+            src_line: start.line,
+            src_column: start.column,
+            ident_index: ident_index,
+        });
+    }
+
+    /// Write a token, inserting a space when it would otherwise merge with the previous one.
+    fn token(&mut self, s: &str) -> io::Result<()> {
+        if let (Some(last), Some(next)) = (self.last_char, s.chars().next()) {
+            let need_space = (is_identifier_part(last) && is_identifier_part(next))
+                || (last == '+' && next == '+')
+                || (last == '-' && next == '-')
+                || (last == '/' && next == '/');
+
+            if need_space {
+                self.raw(" ")?;
+            }
+        }
+
+        self.flush_mapping();
+        self.raw(s)
+    }
+
+    fn space(&mut self) -> io::Result<()> {
+        if self.minify {
+            Ok(())
+        } else {
+            self.raw(" ")
+        }
+    }
+
+    fn newline(&mut self) -> io::Result<()> {
+        if self.minify {
+            Ok(())
+        } else {
+            self.raw("\n")
+        }
+    }
+
+    fn write_indent(&mut self) -> io::Result<()> {
+        if self.minify {
+            return Ok(());
+        }
+
+        for _ in 0..self.indent {
+            self.raw(INDENT)?;
+        }
+
+        Ok(())
+    }
+
+    fn mark(&mut self, loc: Loc, span: Span, name: Option<String>) {
+        if self.source_map.is_none() || loc.is_dummy() {
+            return;
+        }
+
+        self.pending = Some((span.start, name));
+    }
+
+    fn identifier(&mut self, ident: &Identifier) -> io::Result<()> {
+        let original_name = self.original_names.and_then(|names| names.get(&ident.loc.start)).cloned();
+        self.mark(ident.loc, ident.span, Some(original_name.unwrap_or_else(|| ident_name(ident))));
+        let name = ident.raw.iter().collect::<String>();
+        self.token(&name)
+    }
+
+    fn string_literal(&mut self, lit: &LiteralString) -> io::Result<()> {
+        self.mark(lit.loc, lit.span, None);
+
+        let mut s = String::with_capacity(lit.raw.len() + 2);
+        s.push('"');
+
+        let mut escaped = false;
+        for c in lit.raw.iter() {
+            match *c {
+                '"' if !escaped => s.push_str("\\\""),
+                '\n' if !escaped => s.push_str("\\n"),
+                '\r' if !escaped => s.push_str("\\r"),
+                '\u{2028}' if !escaped => s.push_str("\\u2028"),
+                '\u{2029}' if !escaped => s.push_str("\\u2029"),
+                c => s.push(c),
+            }
+
+            escaped = !escaped && *c == '\\';
+        }
+
+        s.push('"');
+        self.token(&s)
+    }
+
+    fn comma_list<T, F>(&mut self, items: &[T], mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&mut Self, &T) -> io::Result<()>,
+    {
+        for (idx, item) in items.iter().enumerate() {
+            if idx > 0 {
+                self.token(",")?;
+                self.space()?;
+            }
+            f(self, item)?;
+        }
+
+        Ok(())
+    }
+
+    // ---------- program / statements ----------
+
+    pub fn gen_program(&mut self, body: &[Statement]) -> io::Result<()> {
+        for stmt in body.iter().filter(|stmt| !is_empty_statement(stmt)) {
+            self.gen_statement(stmt)?;
+        }
+
+        self.output.flush()
+    }
+
+    pub fn gen_statement(&mut self, stmt: &Statement) -> io::Result<()> {
+        self.write_indent()?;
+        self.statement(stmt)?;
+        self.newline()
+    }
+
+    fn statement_list(&mut self, body: &[Statement]) -> io::Result<()> {
+        self.token("{")?;
+        self.newline()?;
+        self.indent += 1;
+        for stmt in body.iter().filter(|stmt| !is_empty_statement(stmt)) {
+            self.gen_statement(stmt)?;
+        }
+        self.indent -= 1;
+        self.write_indent()?;
+        self.token("}")
+    }
+
+    fn block(&mut self, block: &BlockStatement) -> io::Result<()> {
+        self.mark(block.loc, block.span, None);
+        self.statement_list(block.body)
+    }
+
+    /// The body of `if`/`for`/`while`/`with`/labelled statements.
+    fn sub_statement(&mut self, stmt: &Statement) -> io::Result<()> {
+        match *stmt {
+            Statement::Block(block) => {
+                self.space()?;
+                self.block(block)
+            },
+            _ => {
+                self.newline()?;
+                self.indent += 1;
+                self.write_indent()?;
+                self.statement(stmt)?;
+                self.indent -= 1;
+                Ok(())
+            }
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> io::Result<()> {
+        self.mark(stmt.loc(), stmt.span(), None);
+
+        match *stmt {
+            Statement::Empty(_) => self.token(";"),
+            Statement::Debugger(_) => {
+                self.token("debugger")?;
+                self.token(";")
+            },
+            Statement::Expression(expr) => {
+                if starts_with_ambiguous_token(expr) {
+                    self.token("(")?;
+                    self.expression(expr, 0)?;
+                    self.token(")")?;
+                } else {
+                    self.expression(expr, 0)?;
+                }
+                self.token(";")
+            },
+            Statement::Variable(inner) => {
+                self.variable(inner)?;
+                self.token(";")
+            },
+            Statement::Function(inner) => {
+                self.function_head(inner.is_async, inner.is_generator)?;
+                self.space()?;
+                self.identifier(&inner.name)?;
+                self.function(&inner.func)
+            },
+            Statement::Class(inner) => {
+                self.token("class")?;
+                self.identifier(&inner.name)?;
+                self.class(&inner.class)
+            },
+            Statement::Block(inner) => self.block(inner),
+            Statement::If(inner) => self.if_statement(inner),
+            Statement::DoWhile(inner) => {
+                self.token("do")?;
+                match inner.body {
+                    Statement::Block(block) => {
+                        self.space()?;
+                        self.block(block)?;
+                        self.space()?;
+                    },
+                    _ => {
+                        self.sub_statement(&inner.body)?;
+                        self.newline()?;
+                        self.write_indent()?;
+                    }
+                }
+                self.token("while")?;
+                self.space()?;
+                self.token("(")?;
+                self.expression(&inner.condition, 0)?;
+                self.token(")")?;
+                self.token(";")
+            },
+            Statement::While(inner) => {
+                self.token("while")?;
+                self.space()?;
+                self.token("(")?;
+                self.expression(&inner.condition, 0)?;
+                self.token(")")?;
+                self.sub_statement(&inner.body)
+            },
+            Statement::For(inner) => self.for_statement(inner),
+            Statement::ForIn(inner) => {
+                self.token("for")?;
+                self.space()?;
+                self.token("(")?;
+                self.expression(&inner.left, MEMBER_PRECEDENCE)?;
+                self.token("in")?;
+                self.space()?;
+                self.expression(&inner.right, 0)?;
+                self.token(")")?;
+                self.sub_statement(&inner.body)
+            },
+            Statement::ForOf(inner) => {
+                self.token("for")?;
+                self.space()?;
+                self.token("(")?;
+                self.expression(&inner.left, MEMBER_PRECEDENCE)?;
+                self.token("of")?;
+                self.space()?;
+                self.expression(&inner.right, ASSIGNMENT_PRECEDENCE)?;
+                self.token(")")?;
+                self.sub_statement(&inner.body)
+            },
+            Statement::ForAwaitOf(inner) => {
+                self.token("for")?;
+                self.token("await")?;
+                self.space()?;
+                self.token("(")?;
+                self.expression(&inner.left, MEMBER_PRECEDENCE)?;
+                self.token("of")?;
+                self.space()?;
+                self.expression(&inner.right, ASSIGNMENT_PRECEDENCE)?;
+                self.token(")")?;
+                self.sub_statement(&inner.body)
+            },
+            Statement::Continue(inner) => {
+                self.token("continue")?;
+                if let Some(ref label) = inner.label {
+                    self.raw(" ")?;
+                    self.identifier(label)?;
+                }
+                self.token(";")
+            },
+            Statement::Break(inner) => {
+                self.token("break")?;
+                if let Some(ref label) = inner.label {
+                    self.raw(" ")?;
+                    self.identifier(label)?;
+                }
+                self.token(";")
+            },
+            Statement::Return(inner) => {
+                self.token("return")?;
+                if let Some(ref value) = inner.value {
+                    self.space()?;
+                    self.expression(value, 0)?;
+                }
+                self.token(";")
+            },
+            Statement::With(inner) => {
+                self.token("with")?;
+                self.space()?;
+                self.token("(")?;
+                self.expression(&inner.condition, 0)?;
+                self.token(")")?;
+                self.sub_statement(&inner.then)
+            },
+            Statement::Switch(inner) => self.switch_statement(inner),
+            Statement::Labelled(inner) => {
+                self.identifier(&inner.label)?;
+                self.token(":")?;
+                self.space()?;
+                self.statement(&inner.item)
+            },
+            Statement::Throw(inner) => {
+                self.token("throw")?;
+                self.space()?;
+                self.expression(&inner.value, 0)?;
+                self.token(";")
+            },
+            Statement::Try(inner) => self.try_statement(inner),
+        }
+    }
+
+    fn variable(&mut self, stmt: &VariableStatement) -> io::Result<()> {
+        match stmt.kind {
+            LexicalDeclarationKind::Var => self.token("var")?,
+            LexicalDeclarationKind::Let => self.token("let")?,
+            LexicalDeclarationKind::Const => self.token("const")?,
+        }
+        self.space()?;
+
+        self.comma_list(stmt.declarators, |this, binding: &LexicalBinding| {
+            this.expression(&binding.name, ASSIGNMENT_PRECEDENCE)?;
+            if let Some(ref init) = binding.initializer {
+                this.space()?;
+                this.token("=")?;
+                this.space()?;
+                this.expression(init, ASSIGNMENT_PRECEDENCE)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn if_statement(&mut self, stmt: &IfStatement) -> io::Result<()> {
+        self.token("if")?;
+        self.space()?;
+        self.token("(")?;
+        self.expression(&stmt.condition, 0)?;
+        self.token(")")?;
+
+        let has_else = match stmt.or_else {
+            Statement::Empty(_) => false,
+            _ => true,
+        };
+
+        // NOTE: dangling else, `if (a) if (b) c; else d;`
+        match stmt.and_then {
+            Statement::If(inner) if has_else => {
+                self.space()?;
+                self.token("{")?;
+                self.newline()?;
+                self.indent += 1;
+                self.gen_statement(&Statement::If(inner))?;
+                self.indent -= 1;
+                self.write_indent()?;
+                self.token("}")?;
+            },
+            _ => self.sub_statement(&stmt.and_then)?,
+        }
+
+        if !has_else {
+            return Ok(());
+        }
+
+        match stmt.and_then {
+            Statement::Block(_) | Statement::If(_) => self.space()?,
+            _ => {
+                self.newline()?;
+                self.write_indent()?;
+            }
+        }
+
+        self.token("else")?;
+        match stmt.or_else {
+            Statement::If(_) => {
+                self.raw(" ")?;
+                self.statement(&stmt.or_else)
+            },
+            _ => self.sub_statement(&stmt.or_else),
+        }
+    }
+
+    fn for_statement(&mut self, stmt: &ForStatement) -> io::Result<()> {
+        self.token("for")?;
+        self.space()?;
+        self.token("(")?;
+        match stmt.init {
+            Some(Statement::Variable(inner)) => self.variable(inner)?,
+            Some(Statement::Expression(inner)) => self.expression(inner, 0)?,
+            Some(ref other) => self.statement(other)?,
+            None => { },
+        }
+        self.token(";")?;
+        if let Some(ref condition) = stmt.condition {
+            self.space()?;
+            self.expression(condition, 0)?;
+        }
+        self.token(";")?;
+        if let Some(ref finally) = stmt.finally {
+            self.space()?;
+            self.expression(finally, 0)?;
+        }
+        self.token(")")?;
+        self.sub_statement(&stmt.body)
+    }
+
+    fn switch_statement(&mut self, stmt: &SwitchStatement) -> io::Result<()> {
+        self.token("switch")?;
+        self.space()?;
+        self.token("(")?;
+        self.expression(&stmt.value, 0)?;
+        self.token(")")?;
+        self.space()?;
+        self.token("{")?;
+        self.newline()?;
+        self.indent += 1;
+        for clause in stmt.clauses.iter() {
+            self.write_indent()?;
+            self.mark(clause.loc, clause.span, None);
+            match clause.value {
+                Some(ref value) => {
+                    self.token("case")?;
+                    self.raw(" ")?;
+                    self.expression(value, 0)?;
+                },
+                None => self.token("default")?,
+            }
+            self.token(":")?;
+
+            let body = match clause.body {
+                Statement::Block(block) => block.body,
+                ref other => ::std::slice::from_ref(other),
+            };
+            self.newline()?;
+            self.indent += 1;
+            for stmt in body.iter() {
+                self.gen_statement(stmt)?;
+            }
+            self.indent -= 1;
+        }
+        self.indent -= 1;
+        self.write_indent()?;
+        self.token("}")
+    }
+
+    fn try_statement(&mut self, stmt: &TryStatement) -> io::Result<()> {
+        self.token("try")?;
+        self.space()?;
+        self.block(&stmt.body)?;
+
+        if let Some(ref catch_body) = stmt.catch_body {
+            self.space()?;
+            self.token("catch")?;
+            if let Some(ref param) = stmt.catch_parameter {
+                self.space()?;
+                self.token("(")?;
+                self.expression(param, 0)?;
+                self.token(")")?;
+            }
+            self.space()?;
+            self.block(catch_body)?;
+        }
+
+        if let Some(ref finally) = stmt.finally {
+            self.space()?;
+            self.token("finally")?;
+            self.space()?;
+            self.block(finally)?;
+        }
+
+        Ok(())
+    }
+
+    // ---------- functions / classes ----------
+
+    fn function_head(&mut self, is_async: bool, is_generator: bool) -> io::Result<()> {
+        if is_async {
+            self.token("async")?;
+            self.raw(" ")?;
+        }
+        self.token("function")?;
+        if is_generator {
+            self.token("*")?;
+        }
+
+        Ok(())
+    }
+
+    fn params(&mut self, params: &ParenthesizedExpression) -> io::Result<()> {
+        self.mark(params.loc, params.span, None);
+        self.token("(")?;
+        self.comma_list(parenthesized_items(params), |this, item| this.list_item(item))?;
+        self.token(")")
+    }
+
+    fn function_body(&mut self, body: FunctionBody) -> io::Result<()> {
+        self.space()?;
+        self.statement_list(body)
+    }
+
+    fn function(&mut self, func: &Function) -> io::Result<()> {
+        self.params(&func.params)?;
+        self.function_body(func.body)
+    }
+
+    fn class(&mut self, class: &Class) -> io::Result<()> {
+        if let Some(ref heritage) = class.heritage {
+            self.raw(" ")?;
+            self.token("extends")?;
+            self.raw(" ")?;
+            self.expression(heritage, MEMBER_PRECEDENCE)?;
+        }
+        self.space()?;
+        self.token("{")?;
+        self.newline()?;
+        self.indent += 1;
+        for method in class.body.iter() {
+            self.write_indent()?;
+            self.class_method(method)?;
+            self.newline()?;
+        }
+        self.indent -= 1;
+        self.write_indent()?;
+        self.token("}")
+    }
+
+    fn class_method(&mut self, method: &ClassMethodDefinition) -> io::Result<()> {
+        if method.is_static {
+            self.token("static")?;
+            self.raw(" ")?;
+        }
+        self.method_definition(&method.method)
+    }
+
+    fn method_name(&mut self, name: &Expression) -> io::Result<()> {
+        match *name {
+            Expression::Identifier(_)
+            | Expression::String(_)
+            | Expression::Numeric(_) => self.expression(name, PRIMARY_PRECEDENCE),
+            _ => {
+                // ComputedPropertyName
+                self.token("[")?;
+                self.expression(name, ASSIGNMENT_PRECEDENCE)?;
+                self.token("]")
+            }
+        }
+    }
+
+    fn method_definition(&mut self, method: &MethodDefinition) -> io::Result<()> {
+        self.mark(method.loc(), method.span(), None);
+
+        match *method {
+            MethodDefinition::Method(ref inner) => {
+                if inner.is_async {
+                    self.token("async")?;
+                    self.raw(" ")?;
+                }
+                if inner.is_generator {
+                    self.token("*")?;
+                }
+                self.method_name(&inner.name)?;
+                self.params(&inner.params)?;
+                self.function_body(inner.body)
+            },
+            MethodDefinition::Getter(ref inner) => {
+                self.token("get")?;
+                self.raw(" ")?;
+                self.method_name(&inner.name)?;
+                self.token("(")?;
+                self.token(")")?;
+                self.function_body(inner.body)
+            },
+            MethodDefinition::Setter(ref inner) => {
+                self.token("set")?;
+                self.raw(" ")?;
+                self.method_name(&inner.name)?;
+                self.params(&inner.params)?;
+                self.function_body(inner.body)
+            },
+        }
+    }
+
+    // ---------- expressions ----------
+
+    pub fn gen_expression(&mut self, expr: &Expression) -> io::Result<()> {
+        self.expression(expr, 0)
+    }
+
+    /// Print `expr`, wrap it with parentheses when its precedence is lower than `min_precedence`.
+    fn expression(&mut self, expr: &Expression, min_precedence: i8) -> io::Result<()> {
+        if precedence(expr) < min_precedence {
+            self.token("(")?;
+            self.expression(expr, 0)?;
+            return self.token(")");
+        }
+
+        match *expr {
+            Expression::Identifier(inner) => return self.identifier(inner),
+            _ => self.mark(expr.loc(), expr.span(), None),
+        }
+
+        match *expr {
+            Expression::This(_) => self.token("this"),
+            Expression::Super(_) => self.token("super"),
+            Expression::Identifier(_) => unreachable!(),
+            Expression::Null(_) => self.token("null"),
+            Expression::Boolean(inner) => self.token(if inner.value { "true" } else { "false" }),
+            Expression::String(inner) => self.string_literal(inner),
+            Expression::Numeric(inner) => {
+                let raw = inner.raw.iter().collect::<String>();
+                self.token(&raw)
+            },
+            Expression::RegularExpression(inner) => {
+                let mut s = String::new();
+                s.push('/');
+                s.extend(inner.body.iter());
+                s.push('/');
+                if let Some(flags) = inner.flags {
+                    s.extend(flags.iter());
+                }
+                self.token(&s)
+            },
+            Expression::Template(inner) => self.template(inner),
+            Expression::Spread(inner) => {
+                self.token("...")?;
+                self.expression(&inner.item, ASSIGNMENT_PRECEDENCE)
+            },
+            Expression::ArrayLiteral(inner) => {
+                self.token("[")?;
+                self.array_elems(inner.elems, |this, elem| this.list_item(elem))?;
+                self.token("]")
+            },
+            Expression::ObjectLiteral(inner) => {
+                if inner.properties.is_empty() {
+                    self.token("{")?;
+                    return self.token("}");
+                }
+
+                self.token("{")?;
+                self.space()?;
+                self.comma_list(inner.properties, |this, prop| this.object_property(prop))?;
+                self.space()?;
+                self.token("}")
+            },
+            Expression::Function(inner) => {
+                self.function_head(inner.is_async, inner.is_generator)?;
+                if let Some(ref name) = inner.name {
+                    self.raw(" ")?;
+                    self.identifier(name)?;
+                }
+                self.function(&inner.func)
+            },
+            Expression::ArrowFunction(inner) => {
+                if inner.is_async {
+                    self.token("async")?;
+                    self.space()?;
+                }
+                match inner.params {
+                    Expression::Parenthesized(params) => self.params(params)?,
+                    Expression::Identifier(ident) => self.identifier(ident)?,
+                    ref params => {
+                        self.token("(")?;
+                        self.expression(params, ASSIGNMENT_PRECEDENCE)?;
+                        self.token(")")?;
+                    }
+                }
+                self.space()?;
+                self.token("=>")?;
+                match inner.body {
+                    ConciseBody::Expr(ref body) => {
+                        self.space()?;
+                        if starts_with_ambiguous_token(body) {
+                            self.token("(")?;
+                            self.expression(body, 0)?;
+                            self.token(")")
+                        } else {
+                            self.expression(body, ASSIGNMENT_PRECEDENCE)
+                        }
+                    },
+                    ConciseBody::Stmt(body) => self.function_body(body),
+                }
+            },
+            Expression::Class(inner) => {
+                self.token("class")?;
+                if let Some(ref name) = inner.name {
+                    self.raw(" ")?;
+                    self.identifier(name)?;
+                }
+                self.class(&inner.class)
+            },
+            Expression::Parenthesized(inner) => {
+                self.token("(")?;
+                self.comma_list(parenthesized_items(inner), |this, item| this.list_item(item))?;
+                self.token(")")
+            },
+            Expression::Member(inner) => {
+                let needs_parens = match inner.left {
+                    // NOTE: `1.toString()` is a SyntaxError.
+                    Expression::Numeric(num) if !inner.computed => {
+                        !num.raw.iter().any(|c| *c == '.' || *c == 'e' || *c == 'E' || *c == 'x' || *c == 'X')
+                    },
+                    _ => false,
+                };
+                if needs_parens {
+                    self.token("(")?;
+                    self.expression(&inner.left, 0)?;
+                    self.token(")")?;
+                } else {
+                    self.expression(&inner.left, MEMBER_PRECEDENCE)?;
+                }
+
+                if inner.optional {
+                    self.token("?.")?;
+                }
+
+                if inner.computed {
+                    self.token("[")?;
+                    self.expression(&inner.right, 0)?;
+                    self.token("]")
+                } else {
+                    if !inner.optional {
+                        self.token(".")?;
+                    }
+                    self.expression(&inner.right, PRIMARY_PRECEDENCE)
+                }
+            },
+            Expression::TaggedTemplate(inner) => {
+                self.expression(&inner.tag, MEMBER_PRECEDENCE)?;
+                self.template(&inner.template)
+            },
+            Expression::NewTarget(_) => {
+                self.token("new")?;
+                self.token(".")?;
+                self.token("target")
+            },
+            Expression::Call(inner) => {
+                self.expression(&inner.callee, MEMBER_PRECEDENCE)?;
+                if inner.optional {
+                    self.token("?.")?;
+                }
+                self.arguments(&inner.arguments)
+            },
+            Expression::New(inner) => {
+                self.token("new")?;
+                self.raw(" ")?;
+                self.expression(&inner.callee, NEW_PRECEDENCE)?;
+                match inner.arguments {
+                    Some(ref arguments) => self.arguments(arguments),
+                    None => Ok(()),
+                }
+            },
+            Expression::Prefix(inner) => {
+                self.token(prefix_operator_str(inner.operator))?;
+                match inner.operator {
+                    PrefixOperator::Await
+                    | PrefixOperator::Delete
+                    | PrefixOperator::Void
+                    | PrefixOperator::TypeOf => self.space()?,
+                    _ => { },
+                }
+                self.expression(&inner.operand, UNARY_PRECEDENCE)
+            },
+            Expression::Infix(inner) => {
+                let op_precedence = inner.operator.precedence();
+                let (left_precedence, right_precedence) = match inner.operator {
+                    // NOTE: `**` is right-associative, and `-a ** b` is a SyntaxError.
+                    InfixOperator::Pow => (UNARY_PRECEDENCE + 1, op_precedence),
+                    _ => (op_precedence, op_precedence + 1),
+                };
+                // NOTE: `??` 不能与 `&&`/`||` 直接混用，必须加括号。
+                let left_precedence = if mixes_nullish(inner.operator, &inner.left) { PRIMARY_PRECEDENCE } else { left_precedence };
+                let right_precedence = if mixes_nullish(inner.operator, &inner.right) { PRIMARY_PRECEDENCE } else { right_precedence };
+
+                self.expression(&inner.left, left_precedence)?;
+                self.space()?;
+                self.token(infix_operator_str(inner.operator))?;
+                self.space()?;
+                self.expression(&inner.right, right_precedence)
+            },
+            Expression::Postfix(inner) => {
+                self.expression(&inner.operand, POSTFIX_PRECEDENCE)?;
+                self.token(postfix_operator_str(inner.operator))
+            },
+            Expression::Assignment(inner) => {
+                self.expression(&inner.left, MEMBER_PRECEDENCE)?;
+                self.space()?;
+                self.token(assignment_operator_str(inner.operator))?;
+                self.space()?;
+                self.expression(&inner.right, ASSIGNMENT_PRECEDENCE)
+            },
+            Expression::Conditional(inner) => {
+                self.expression(&inner.condition, CONDITIONAL_PRECEDENCE + 1)?;
+                self.space()?;
+                self.token("?")?;
+                self.space()?;
+                self.expression(&inner.and_then, ASSIGNMENT_PRECEDENCE)?;
+                self.space()?;
+                self.token(":")?;
+                self.space()?;
+                self.expression(&inner.or_else, ASSIGNMENT_PRECEDENCE)
+            },
+            Expression::Yield(inner) => {
+                self.token("yield")?;
+                if inner.star {
+                    self.token("*")?;
+                }
+                self.space()?;
+                self.expression(&inner.item, ASSIGNMENT_PRECEDENCE)
+            },
+            Expression::Comma(inner) => {
+                self.comma_list(inner.items, |this, item| this.expression(item, ASSIGNMENT_PRECEDENCE))
+            },
+            Expression::AssignmentPattern(inner) => self.assignment_pattern(inner),
+            Expression::BindingPattern(inner) => self.binding_pattern(inner),
+            Expression::JSXFragment(inner) => self.jsx_fragment(inner),
+            Expression::JSXElement(inner) => self.jsx_element(inner),
+        }
+    }
+
+    /// Items of arguments, parameters and array literals, `...xs` is printed without parentheses.
+    fn list_item(&mut self, item: &Expression) -> io::Result<()> {
+        match *item {
+            Expression::Spread(inner) => {
+                self.mark(inner.loc, inner.span, None);
+                self.token("...")?;
+                self.expression(&inner.item, ASSIGNMENT_PRECEDENCE)
+            },
+            _ => self.expression(item, ASSIGNMENT_PRECEDENCE),
+        }
+    }
+
+    fn arguments(&mut self, arguments: &ParenthesizedExpression) -> io::Result<()> {
+        self.token("(")?;
+        self.comma_list(parenthesized_items(arguments), |this, item| this.list_item(item))?;
+        self.token(")")
+    }
+
+    fn template(&mut self, template: &LiteralTemplateExpression) -> io::Result<()> {
+        self.token("`")?;
+        for (idx, string) in template.strings.iter().enumerate() {
+            let raw = string.raw.iter().collect::<String>();
+            self.raw(&raw)?;
+
+            if let Some(bound) = template.bounds.get(idx) {
+                self.raw("${")?;
+                self.expression(bound, 0)?;
+                self.raw("}")?;
+            }
+        }
+        self.raw("`")
+    }
+
+    fn array_elems<T, F>(&mut self, elems: &[Option<T>], mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&mut Self, &T) -> io::Result<()>,
+    {
+        for (idx, elem) in elems.iter().enumerate() {
+            if idx > 0 {
+                self.token(",")?;
+                if elem.is_some() {
+                    self.space()?;
+                }
+            }
+            if let Some(ref elem) = *elem {
+                f(self, elem)?;
+            }
+        }
+
+        // NOTE: `[a, ]` 与 `[a, , ]` 的区别
+        if let Some(None) = elems.last() {
+            self.token(",")?;
+        }
+
+        Ok(())
+    }
+
+    fn property_name(&mut self, name: &PropertyName) -> io::Result<()> {
+        match *name {
+            PropertyName::Identifier(ref ident) => self.identifier(ident),
+            PropertyName::Numberic(ref num) => {
+                self.mark(num.loc, num.span, None);
+                let raw = num.raw.iter().collect::<String>();
+                self.token(&raw)
+            },
+            PropertyName::String(ref lit) => self.string_literal(lit),
+            PropertyName::Computed(ref expr) => {
+                self.token("[")?;
+                self.expression(expr, ASSIGNMENT_PRECEDENCE)?;
+                self.token("]")
+            },
+        }
+    }
+
+    fn object_property(&mut self, prop: &ObjectProperty) -> io::Result<()> {
+        match *prop {
+            ObjectProperty::Identifier(ref ident) => self.identifier(ident),
+            ObjectProperty::Property { ref name, ref value, .. } => {
+                self.property_name(name)?;
+                self.token(":")?;
+                self.space()?;
+                self.expression(value, ASSIGNMENT_PRECEDENCE)
+            },
+            ObjectProperty::MethodDefinition(ref method) => self.method_definition(method),
+            ObjectProperty::Spread { ref target, .. } => {
+                self.token("...")?;
+                self.expression(target, ASSIGNMENT_PRECEDENCE)
+            },
+        }
+    }
+
+    fn initializer(&mut self, init: &Option<Expression>) -> io::Result<()> {
+        if let Some(ref init) = *init {
+            self.space()?;
+            self.token("=")?;
+            self.space()?;
+            self.expression(init, ASSIGNMENT_PRECEDENCE)?;
+        }
+
+        Ok(())
+    }
+
+    fn binding_pattern(&mut self, pattern: &BindingPattern) -> io::Result<()> {
+        match *pattern {
+            BindingPattern::Object(ref inner) => {
+                self.token("{")?;
+                self.space()?;
+                self.comma_list(inner.properties, |this, prop| this.binding_property(prop))?;
+                self.space()?;
+                self.token("}")
+            },
+            BindingPattern::Array(ref inner) => {
+                self.token("[")?;
+                self.array_elems(inner.elems, |this, elem| this.binding_element(elem))?;
+                if let Some(rest) = inner.rest_elem {
+                    if !inner.elems.is_empty() {
+                        self.token(",")?;
+                        self.space()?;
+                    }
+                    self.token("...")?;
+                    match *rest {
+                        BindingRestElement::Identifier(ref ident) => self.identifier(ident)?,
+                        BindingRestElement::BindingPattern(ref pattern) => self.binding_pattern(pattern)?,
+                    }
+                }
+                self.token("]")
+            },
+        }
+    }
+
+    fn binding_element(&mut self, elem: &BindingElement) -> io::Result<()> {
+        match *elem {
+            BindingElement::SingleNameBinding { ref name, ref init, .. } => {
+                self.identifier(name)?;
+                self.initializer(init)
+            },
+            BindingElement::BindingPattern { ref pattern, ref init, .. } => {
+                self.binding_pattern(pattern)?;
+                self.initializer(init)
+            },
+        }
+    }
+
+    fn binding_property(&mut self, prop: &BindingProperty) -> io::Result<()> {
+        match *prop {
+            BindingProperty::SingleNameBinding { ref name, ref init, .. } => {
+                self.identifier(name)?;
+                self.initializer(init)
+            },
+            BindingProperty::Property { ref name, ref value, .. } => {
+                self.property_name(name)?;
+                self.token(":")?;
+                self.space()?;
+                self.binding_element(value)
+            },
+            BindingProperty::Spread { ref name, .. } => {
+                self.token("...")?;
+                self.identifier(name)
+            },
+        }
+    }
+
+    fn assignment_pattern(&mut self, pattern: &AssignmentPattern) -> io::Result<()> {
+        match *pattern {
+            AssignmentPattern::Object(ref inner) => {
+                self.token("{")?;
+                self.space()?;
+                self.comma_list(inner.properties, |this, prop| this.assignment_property(prop))?;
+                self.space()?;
+                self.token("}")
+            },
+            AssignmentPattern::Array(ref inner) => {
+                self.token("[")?;
+                self.array_elems(inner.elems, |this, elem| this.assignment_element(elem))?;
+                if let Some(ref rest) = inner.rest_elem {
+                    if !inner.elems.is_empty() {
+                        self.token(",")?;
+                        self.space()?;
+                    }
+                    self.token("...")?;
+                    self.expression(rest, MEMBER_PRECEDENCE)?;
+                }
+                self.token("]")
+            },
+        }
+    }
+
+    fn assignment_element(&mut self, elem: &AssignmentElement) -> io::Result<()> {
+        self.expression(&elem.elem, MEMBER_PRECEDENCE)?;
+        self.initializer(&elem.init)
+    }
+
+    fn assignment_property(&mut self, prop: &AssignmentProperty) -> io::Result<()> {
+        match *prop {
+            AssignmentProperty::Identifier { ref name, ref init, .. } => {
+                self.identifier(name)?;
+                self.initializer(init)
+            },
+            AssignmentProperty::Property { ref name, ref value, .. } => {
+                self.property_name(name)?;
+                self.token(":")?;
+                self.space()?;
+                self.assignment_element(value)
+            },
+            AssignmentProperty::Spread { ref target, .. } => {
+                self.token("...")?;
+                self.expression(target, MEMBER_PRECEDENCE)
+            },
+        }
+    }
+
+    // ---------- JSX ----------
+
+    fn jsx_element_name(&mut self, name: &JSXElementName) -> io::Result<()> {
+        match *name {
+            JSXElementName::Identifier(ref ident) => self.identifier(ident),
+            JSXElementName::NamespacedName(ref inner) => {
+                self.identifier(&inner.namespace)?;
+                self.raw(":")?;
+                self.identifier(&inner.name)
+            },
+            JSXElementName::MemberExpression(idents) => {
+                for (idx, ident) in idents.iter().enumerate() {
+                    if idx > 0 {
+                        self.raw(".")?;
+                    }
+                    self.identifier(ident)?;
+                }
+                Ok(())
+            },
+        }
+    }
+
+    fn jsx_attributes(&mut self, attrs: &Option<JSXAttributes>) -> io::Result<()> {
+        let attrs = match *attrs {
+            Some(attrs) => attrs,
+            None => return Ok(()),
+        };
+
+        for attr in attrs.iter() {
+            self.raw(" ")?;
+            match *attr {
+                JSXAttribute::Spread(ref expr) => {
+                    self.raw("{...")?;
+                    self.expression(expr, ASSIGNMENT_PRECEDENCE)?;
+                    self.raw("}")?;
+                },
+                JSXAttribute::Normal(ref attr) => {
+                    match attr.name {
+                        JSXNormalAttributeName::Identifier(ref ident) => self.identifier(ident)?,
+                        JSXNormalAttributeName::NamespacedName(ref inner) => {
+                            self.identifier(&inner.namespace)?;
+                            self.raw(":")?;
+                            self.identifier(&inner.name)?;
+                        },
+                    }
+                    match attr.init {
+                        Some(JSXNormalAttributeInitializer::Identifier(ref ident)) => {
+                            self.raw("=")?;
+                            let raw = ident.raw.iter().collect::<String>();
+                            self.raw(&format!("\"{}\"", raw))?;
+                        },
+                        Some(JSXNormalAttributeInitializer::Assignment(ref expr)) => {
+                            self.raw("={")?;
+                            self.expression(expr, ASSIGNMENT_PRECEDENCE)?;
+                            self.raw("}")?;
+                        },
+                        Some(JSXNormalAttributeInitializer::Element(ref elem)) => {
+                            self.raw("=")?;
+                            self.jsx_element(elem)?;
+                        },
+                        Some(JSXNormalAttributeInitializer::Fragment(ref frag)) => {
+                            self.raw("=")?;
+                            self.jsx_fragment(frag)?;
+                        },
+                        None => { },
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn jsx_children(&mut self, children: &Option<JSXChildren>) -> io::Result<()> {
+        let children = match *children {
+            Some(children) => children,
+            None => return Ok(()),
+        };
+
+        for child in children.iter() {
+            match *child {
+                JSXChild::Text(ref text) => {
+                    let raw = text.raw.iter().collect::<String>();
+                    self.raw(&raw)?;
+                },
+                JSXChild::Element(ref elem) => self.jsx_element(elem)?,
+                JSXChild::ChildExpression(ref exprs) => {
+                    self.raw("{")?;
+                    if let Some(exprs) = *exprs {
+                        self.comma_list(exprs, |this, expr| this.expression(expr, ASSIGNMENT_PRECEDENCE))?;
+                    }
+                    self.raw("}")?;
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn jsx_fragment(&mut self, frag: &JSXFragment) -> io::Result<()> {
+        self.token("<>")?;
+        self.jsx_children(&frag.children)?;
+        self.raw("</>")
+    }
+
+    fn jsx_element(&mut self, elem: &JSXElement) -> io::Result<()> {
+        match *elem {
+            JSXElement::SelfClosing(ref inner) => {
+                self.token("<")?;
+                self.jsx_element_name(&inner.name)?;
+                self.jsx_attributes(&inner.attrs)?;
+                self.raw(" />")
+            },
+            JSXElement::Normal(ref inner) => {
+                self.token("<")?;
+                self.jsx_element_name(&inner.opening.name)?;
+                self.jsx_attributes(&inner.opening.attrs)?;
+                self.raw(">")?;
+                self.jsx_children(&inner.children)?;
+                self.raw("</")?;
+                self.jsx_element_name(&inner.closing.name)?;
+                self.raw(">")
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+fn parse_and_print(source: &str, minify: bool) -> (String, SourceMap) {
+    use crate::toolshed::Arena;
+    use crate::parser::Parser;
+
+    let arena = Arena::new();
+    let code = arena.alloc_vec(source.chars().collect::<Vec<char>>());
+    let mut parser = Parser::new(&arena, &code, "src/main.js");
+    parser.parse().unwrap();
+
+    let mut source_map = SourceMap::new("dist/main.js");
+    let source_index = source_map.add_source("src/main.js", Some(source.to_string()));
+
+    let output = {
+        let mut codegen = CodeGen::with_source_map(Vec::new(), &mut source_map, source_index);
+        codegen.set_minify(minify);
+        codegen.gen_program(&parser.body).unwrap();
+        String::from_utf8(codegen.into_inner()).unwrap()
+    };
+
+    (output, source_map)
+}
+
+#[test]
+fn test_codegen() {
+    assert_eq!(parse_and_print("a + b * c", false).0, "a + b * c;\n");
+    assert_eq!(parse_and_print("typeof a === 'x'", false).0, "typeof a === \"x\";\n");
+    assert_eq!(parse_and_print("a.b[c](1, 2)", true).0, "a.b[c](1,2);");
+    assert_eq!(parse_and_print("x => x + 1", true).0, "x=>x+1;");
+    assert_eq!(parse_and_print("a = - -b", true).0, "a=- -b;");
+    assert_eq!(parse_and_print("function* g(a) { yield a }", false).0, "function* g(a) {\n    yield a;\n}\n");
+    assert_eq!(parse_and_print("a?.b.c?.[0]?.(x) ?? d", true).0, "a?.b.c?.[0]?.(x)??d;");
+    assert_eq!(parse_and_print("a ||= b &&= c ??= 1_000", true).0, "a||=b&&=c??=1_000;");
+}
+
+#[test]
+fn test_codegen_reparse() {
+    // NOTE: 打印的结果可以再次解析，并且打印出相同的代码
+    for &(source, expected) in [
+        ("f(...xs)", "f(...xs);"),
+        ("f(a, ...b.c)", "f(a,...b.c);"),
+        ("new F(...xs)", "new F(...xs);"),
+        ("(...args) => args", "(...args)=>args;"),
+        ("a;;b;", "a;b;"),
+        ("function f() { a; b; }", "function f(){a;b;}"),
+        ("f(a, ...b.c, ...d)", "f(a,...b.c,...d);"),
+        ("f(...a, b)", "f(...a,b);"),
+        ("f(...a + b, c)", "f(...a+b,c);"),
+        ("f(a + b, c)", "f(a+b,c);"),
+        ("new F(...a, b)", "new F(...a,b);"),
+        ("x = [...a, b, ...c]", "x=[...a,b,...c];"),
+        ("x = [...(a, b)]", "x=[...(a,b)];"),
+        ("x = [, ...a]", "x=[,...a];"),
+        ("x = [a, , b, ]", "x=[a,,b];"),
+        ("f([...a], ...[b])", "f([...a],...[b]);"),
+        ("[1 + 2, 3]", "[1+2,3];"),
+        ("[2 ** 10, 2 ** -1]", "[2**10,2**-1];"),
+        ("[o.n ?? 1, o.z ?? 1]", "[o.n??1,o.z??1];"),
+        ("[a = 1, b]", "[a=1,b];"),
+        ("x = a + b, c", "x=a+b,c;"),
+        ("x = (a + b, c)", "x=(a+b,c);"),
+        ("n < 2 ? n : f(n)", "n<2?n:f(n);"),
+        ("a || b ? c : d, e", "a||b?c:d,e;"),
+        ("a === null || a === void 0 ? void 0 : a.b", "a===null||a===void 0?void 0:a.b;"),
+        ("a ? b ? c : d : e ? f : g", "a?b?c:d:e?f:g;"),
+        ("(a + b) * c, d", "(a+b)*c,d;"),
+        ("`${a}${b}c${d}`", "`${a}${b}c${d}`;"),
+        ("var a", "var a;"),
+        ("var _ref, a = 1, b = (c, d); _ref = a", "var _ref,a=1,b=(c,d);_ref=a;"),
+        ("let a = [1, 2], b = a ? c : d", "let a=[1,2],b=a?c:d;"),
+        ("function f(x) { const y = x + 1; }", "function f(x){const y=x+1;}"),
+        ("try { f() } catch (e) { g(e) }", "try{f();}catch(e){g(e);}"),
+        ("try { f() } catch { g() } finally { h() }", "try{f();}catch{g();}finally{h();}"),
+        ("try {\n} finally {\n}\nx", "try{}finally{}x;"),
+    ].iter() {
+        let output = parse_and_print(source, true).0;
+        assert_eq!(output, expected, "{}", source);
+        assert_eq!(parse_and_print(&output, true).0, expected);
+    }
+}
+
+#[test]
+fn test_codegen_source_map() {
+    let (output, source_map) = parse_and_print("foo(bar);\nbaz;", false);
+    assert_eq!(output, "foo(bar);\nbaz;\n");
+    assert_eq!(source_map.names(), &["foo".to_string(), "bar".to_string(), "baz".to_string()]);
+    // foo -> 0:0, bar -> 0:4, baz -> 1:0
+    assert_eq!(source_map.mappings(), &b"AAAAA,IAAIC;AACJC"[..]);
+}
+
+
+impl<'ast> crate::compiler::transform::ToSourceCode for Statement<'ast> {
+    fn source_code_gen<W: Write>(&self, output: &mut W) {
+        let mut codegen = CodeGen::new(output);
+        let _ = codegen.gen_statement(self);
+    }
+}
+
+impl<'ast> crate::compiler::transform::ToSourceCode for Expression<'ast> {
+    fn source_code_gen<W: Write>(&self, output: &mut W) {
+        let mut codegen = CodeGen::new(output);
+        let _ = codegen.gen_expression(self);
+    }
+}
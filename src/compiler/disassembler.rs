@@ -0,0 +1,281 @@
+// Bytecode disassembler
+//
+//      function <script> (params: 0, length: 0, locals: 0)
+//        constants:
+//          0: Function add
+//        code:
+//                ; 1:1  (function add(a, b) { return a + b; })
+//          0000  Closure index=0                ; add
+//          0002  Return
+//
+//      function <script>/add (params: 2, length: 2, locals: 2)
+//        code:
+//                ; 1:22  (function add(a, b) { return a + b; })
+//          0000  GetLocal slot=0
+//          ...
+//
+// NOTE: 操作数按照 `Opcode::operands` 中的名字解释：
+//          index、name、message    常量表中的索引，在注释中打印常量
+//          offset                  跳转偏移，在注释中打印跳转的目标
+//          kind                    `ThrowError` 的错误类型
+//       解码失败时打印错误并停止当前函数，方便调试生成错误字节码的情况。
+
+use crate::compiler::bytecode::{ CodeObject, Constant, Capture, Instruction, error_kind_from_u8, };
+use crate::lexer::span::Loc;
+
+use std::fmt::Write;
+
+
+/// Prints code objects and their nested functions.
+#[derive(Debug, Default)]
+pub struct Disassembler {
+    source: Option<Vec<char>>,
+    /// Offsets of the first characters of the lines.
+    line_starts: Vec<usize>,
+}
+
+impl Disassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Annotate the instructions with the source lines they come from.
+    pub fn with_source(source: &str) -> Self {
+        let source = source.chars().collect::<Vec<char>>();
+        let mut line_starts = vec![ 0 ];
+        for (index, c) in source.iter().enumerate() {
+            match *c {
+                '\r' if source.get(index + 1) == Some(&'\n') => { },
+                '\n' | '\r' | '\u{2028}' | '\u{2029}' => line_starts.push(index + 1),
+                _ => { },
+            }
+        }
+
+        Disassembler { source: Some(source), line_starts }
+    }
+
+    pub fn disassemble(&self, code: &CodeObject) -> String {
+        let mut output = String::new();
+        self.function(code, function_name(code), &mut output);
+        output
+    }
+
+    fn function(&self, code: &CodeObject, path: &str, output: &mut String) {
+        let _ = write!(output, "function {} (params: {}, length: {}, locals: {}",
+            path, code.param_count, code.length, code.local_count);
+        if code.cache_count != 0 {
+            let _ = write!(output, ", caches: {}", code.cache_count);
+        }
+        if code.flags.bits() != 0 {
+            let _ = write!(output, ", flags: {:?}", code.flags);
+        }
+        output.push_str(")\n");
+
+        if !code.captures.is_empty() {
+            output.push_str("  captures:\n");
+            for (index, capture) in code.captures.iter().enumerate() {
+                let _ = match *capture {
+                    Capture::Local(slot) => writeln!(output, "    {}: local {}", index, slot),
+                    Capture::Upvalue(upvalue) => writeln!(output, "    {}: upvalue {}", index, upvalue),
+                };
+            }
+        }
+
+        if !code.constants.is_empty() {
+            output.push_str("  constants:\n");
+            for (index, constant) in code.constants.iter().enumerate() {
+                let _ = writeln!(output, "    {}: {}", index, constant_kind(constant));
+            }
+        }
+
+        if !code.handlers.is_empty() {
+            output.push_str("  handlers:\n");
+            for (index, handler) in code.handlers.iter().enumerate() {
+                let _ = writeln!(output, "    {}: {:04}..{:04} -> {:04} (stack depth {})",
+                    index, handler.start, handler.end, handler.target, handler.stack_depth);
+            }
+        }
+
+        output.push_str("  code:\n");
+        let mut last_line = None;
+        let mut pos = 0;
+        while pos < code.code.len() {
+            if let Some(loc) = code.lines.find(pos as u32) {
+                let (line, annotation) = self.annotation(loc);
+                if last_line != Some(line) {
+                    let _ = writeln!(output, "          ; {}", annotation);
+                    last_line = Some(line);
+                }
+            }
+
+            match Instruction::decode(&code.code, pos) {
+                Ok((instruction, next)) => {
+                    let _ = writeln!(output, "    {}", instruction_line(code, pos, next, instruction));
+                    pos = next;
+                },
+                Err(error) => {
+                    let _ = writeln!(output, "    {:04}  <{}>", pos, error.message());
+                    break;
+                },
+            }
+        }
+
+        for constant in code.constants.iter() {
+            if let Constant::Function(ref function) = *constant {
+                output.push('\n');
+                self.function(function, &format!("{}/{}", path, function_name(function)), output);
+            }
+        }
+    }
+
+    /// The line of the location ( the location itself without source ) and its annotation.
+    fn annotation(&self, loc: Loc) -> (usize, String) {
+        let source = match self.source {
+            Some(ref source) => source,
+            None => return (loc.start, format!("{}..{}", loc.start, loc.end)),
+        };
+
+        let line = match self.line_starts.binary_search(&loc.start) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        let start = self.line_starts[line];
+        let end = self.line_starts.get(line + 1).cloned().unwrap_or_else(|| source.len());
+        let text = source[start..end].iter().collect::<String>();
+
+        (line, format!("{}:{}  {}", line + 1, loc.start - start + 1, text.trim()))
+    }
+}
+
+/// Disassemble a code object without source line annotations.
+pub fn disassemble(code: &CodeObject) -> String {
+    Disassembler::new().disassemble(code)
+}
+
+
+fn instruction_line(code: &CodeObject, pos: usize, next: usize, instruction: Instruction) -> String {
+    let opcode = instruction.opcode();
+    let mut line = format!("{:04}  {}", pos, opcode.name());
+    let mut comments = Vec::new();
+
+    for (name, value) in opcode.operands().iter().zip(instruction.operands()) {
+        let _ = write!(line, " {}={}", name, value);
+        match *name {
+            "index" | "name" | "message" => {
+                let comment = code.constants.get(value as usize)
+                    .map(constant_value)
+                    .unwrap_or_else(|| "<invalid constant>".to_string());
+                comments.push(comment);
+            },
+            "offset" => comments.push(format!("-> {:04}", next as i64 + value)),
+            "kind" => {
+                let comment = error_kind_from_u8(value as u8)
+                    .map(|kind| format!("{:?}", kind))
+                    .unwrap_or_else(|| "<invalid error kind>".to_string());
+                comments.push(comment);
+            },
+            _ => { },
+        }
+    }
+
+    if !comments.is_empty() {
+        line = format!("{:<36} ; {}", line, comments.join(", "));
+    }
+
+    line
+}
+
+fn constant_kind(constant: &Constant) -> String {
+    let kind = match *constant {
+        Constant::Number(_) => "Number",
+        Constant::String(_) => "String",
+        Constant::BigInt(_) => "BigInt",
+        Constant::RegExp { .. } => "RegExp",
+        Constant::Template { .. } => "Template",
+        Constant::Function(_) => "Function",
+    };
+
+    format!("{} {}", kind, constant_value(constant))
+}
+
+fn constant_value(constant: &Constant) -> String {
+    match *constant {
+        Constant::Number(n) if n == 0.0 && n.is_sign_negative() => "-0".to_string(),
+        Constant::Number(n) => format!("{}", n),
+        Constant::String(ref s) => format!("{:?}", s),
+        Constant::BigInt(ref digits) => format!("{}n", digits),
+        Constant::RegExp { ref pattern, ref flags } => format!("/{}/{}", pattern, flags),
+        Constant::Template { ref cooked, ref raw } => format!("cooked: {:?}, raw: {:?}", cooked, raw),
+        Constant::Function(ref code) => function_name(code).to_string(),
+    }
+}
+
+fn function_name(code: &CodeObject) -> &str {
+    if code.name.is_empty() { "<anonymous>" } else { &code.name }
+}
+
+
+#[test]
+fn test_disassemble() {
+    use crate::compiler::bytecode::{ Handler, CodeFlags, };
+
+    let source = "try {\n  f(1);\n} catch (e) { throw e; }";
+    let loc = |start, end| Loc { start, end };
+
+    let mut f = CodeObject::new("f");
+    f.param_count = 1;
+    f.length = 1;
+    f.local_count = 1;
+    f.flags.insert(CodeFlags::STRICT);
+    f.emit(Instruction::GetLocal(0), loc(0, 0));
+    f.emit(Instruction::Return, loc(0, 0));
+
+    let mut code = CodeObject::new("<script>");
+    let index = code.add_constant(Constant::Function(Box::new(f)));
+    let message = code.add_string("oops");
+    let start = code.emit(Instruction::Closure(index), loc(8, 9));
+    code.emit(Instruction::Undefined, loc(8, 9));
+    code.emit(Instruction::Int(1), loc(10, 11));
+    code.emit(Instruction::Call(1), loc(8, 12));
+    code.emit(Instruction::Pop, loc(8, 13));
+    let jump = code.emit(Instruction::Jump(0), loc(8, 13));
+    let target = code.emit(Instruction::Throw, loc(28, 36));
+    let end = code.emit(Instruction::ThrowError(4, message), loc(28, 36));
+    code.emit(Instruction::Undefined, loc(0, 0));
+    code.emit(Instruction::Return, loc(0, 0));
+    code.patch_jump(jump, end);
+    code.handlers.push(Handler { start, end: target, target, stack_depth: 0 });
+
+    let output = Disassembler::with_source(source).disassemble(&code);
+    assert_eq!(output, r#"function <script> (params: 0, length: 0, locals: 0)
+  constants:
+    0: Function f
+    1: String "oops"
+  handlers:
+    0: 0000..0013 -> 0013 (stack depth 0)
+  code:
+          ; 2:3  f(1);
+    0000  Closure index=0                ; f
+    0002  Undefined
+    0003  Int value=1
+    0005  Call argc=1
+    0007  Pop
+    0008  Jump offset=1                  ; -> 0014
+          ; 3:15  } catch (e) { throw e; }
+    0013  Throw
+    0014  ThrowError kind=4 message=1    ; TypeError, "oops"
+    0017  Undefined
+    0018  Return
+
+function <script>/f (params: 1, length: 1, locals: 1, flags: strict)
+  code:
+    0000  GetLocal slot=0
+    0002  Return
+"#);
+
+    // NOTE: 没有源代码时打印位置，截断的指令打印错误
+    code.code.truncate(10);
+    let output = disassemble(&code);
+    assert!(output.contains("          ; 8..9\n    0000  Closure index=0"));
+    assert!(output.ends_with("    0008  <truncated bytecode>\n\nfunction <script>/f (params: 1, length: 1, locals: 1, flags: strict)\n  code:\n    0000  GetLocal slot=0\n    0002  Return\n"));
+}
@@ -1,5 +1,12 @@
 pub mod sourcemap;
+pub mod codegen;
+pub mod scope;
 pub mod bytecode;
+pub mod bytecodegen;
+pub mod disassembler;
+pub mod cache;
+pub mod verifier;
 pub mod transform;
+pub mod optimize;
 
 pub mod value;
\ No newline at end of file
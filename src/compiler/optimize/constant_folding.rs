@@ -0,0 +1,486 @@
+// Constant folding
+//
+//      1 + 2 * 3           →   7
+//      "a" + 1 + "b"       →   "a1b"
+//      x + "a" + "b"       →   x + "ab"
+//      typeof null         →   "object"
+//      !0, !"", void 1     →   true, true, void 0
+//      1 / 0, 0 / 0        →   1 / 0, 0 / 0
+//      0.1 < 0.2 === !0    →   true
+//      true ? a : b        →   a
+//      if (false) { var x = f(); } else { g(); }   →   var x; g();
+//
+// NOTE: 运算遵循 JavaScript 的语义（`vm::value::Number`），数字的结果比原来的表达式更长时不折叠（`1 / 3`）。
+//       `NaN`、`Infinity` 和 `undefined` 可以被重新绑定，因此分别使用 `0/0`、`1/0` 和 `void 0`。
+
+use crate::lexer::operator::{ PrefixOperator, InfixOperator, };
+use crate::lexer::escape::unescape_string;
+use crate::ast::numberic::{ Float, Numberic, };
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::vm::value::Number;
+use crate::compiler::optimize::var_declared_names;
+
+use std::mem;
+use std::cmp;
+
+
+/// A primitive value known at compile time.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Constant {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Number(Number),
+    String(String),
+}
+
+impl Constant {
+    /// The value of `expr` when it is a literal ( or `void` of a literal, `-1`, `0/0`, ... ).
+    pub fn from_expression(expr: &Expression) -> Option<Constant> {
+        match *expr {
+            Expression::Null(_) => Some(Constant::Null),
+            Expression::Boolean(ref inner) => Some(Constant::Boolean(inner.value)),
+            Expression::Numeric(ref inner) => Some(Constant::Number(Number(match inner.value {
+                Numberic::I64(n) => n as f64,
+                Numberic::F64(Float(n)) => n,
+            }))),
+            Expression::String(ref inner) => {
+                let value = match inner.cooked {
+                    Some(ref cooked) => cooked.clone(),
+                    None if inner.raw.contains('\\') => {
+                        let raw = inner.raw.chars().collect::<Vec<char>>();
+                        unescape_string(&raw).ok()?.into_iter().collect()
+                    },
+                    None => inner.raw.clone(),
+                };
+                Some(Constant::String(value))
+            },
+            Expression::Parenthesized(ref inner) if inner.items.len() == 1 => Constant::from_expression(&inner.items[0]),
+            Expression::Prefix(ref inner) => {
+                let operand = Constant::from_expression(&inner.operand)?;
+                match inner.operator {
+                    PrefixOperator::Void => Some(Constant::Undefined),
+                    PrefixOperator::Negative => Some(Constant::Number(-operand.to_number())),
+                    _ => None,
+                }
+            },
+            Expression::Infix(ref inner) if inner.operator == InfixOperator::Div => {
+                let left = Constant::from_expression(&inner.left)?.to_number();
+                let right = Constant::from_expression(&inner.right)?.to_number();
+                Some(Constant::Number(left / right))
+            },
+            _ => None,
+        }
+    }
+
+    pub fn to_boolean(&self) -> bool {
+        match *self {
+            Constant::Undefined | Constant::Null => false,
+            Constant::Boolean(value) => value,
+            Constant::Number(n) => n.into(),
+            Constant::String(ref s) => !s.is_empty(),
+        }
+    }
+
+    pub fn to_number(&self) -> Number {
+        match *self {
+            Constant::Undefined => Number::NAN,
+            Constant::Null => Number(0.0f64),
+            Constant::Boolean(value) => value.into(),
+            Constant::Number(n) => n,
+            Constant::String(ref s) => Number::from(s.as_str()),
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        match *self {
+            Constant::Undefined => "undefined".to_string(),
+            Constant::Null => "null".to_string(),
+            Constant::Boolean(value) => value.to_string(),
+            Constant::Number(n) => n.to_string(),
+            Constant::String(ref s) => s.clone(),
+        }
+    }
+
+    pub fn type_of(&self) -> &'static str {
+        match *self {
+            Constant::Undefined => "undefined",
+            Constant::Null => "object",
+            Constant::Boolean(_) => "boolean",
+            Constant::Number(_) => "number",
+            Constant::String(_) => "string",
+        }
+    }
+
+    pub fn is_nullish(&self) -> bool {
+        match *self {
+            Constant::Undefined | Constant::Null => true,
+            _ => false,
+        }
+    }
+
+    /// `===`
+    pub fn strict_equals(&self, other: &Constant) -> bool {
+        match (self, other) {
+            // NOTE: `Number` 的 `PartialEq` 认为 NaN 等于 NaN
+            (&Constant::Number(a), &Constant::Number(b)) => a.0 == b.0,
+            (a, b) => a == b,
+        }
+    }
+
+    /// `==`
+    pub fn loose_equals(&self, other: &Constant) -> bool {
+        match (self, other) {
+            (a, b) if a.is_nullish() || b.is_nullish() => a.is_nullish() && b.is_nullish(),
+            (&Constant::String(_), &Constant::String(_)) => self.strict_equals(other),
+            _ => self.to_number().0 == other.to_number().0,
+        }
+    }
+
+    /// `<`, `None` when either side is NaN ( every comparison is `false` ).
+    pub fn compare(&self, other: &Constant) -> Option<cmp::Ordering> {
+        match (self, other) {
+            // NOTE: 字符串按 UTF-16 码元比较
+            (&Constant::String(ref a), &Constant::String(ref b)) => Some(a.encode_utf16().cmp(b.encode_utf16())),
+            _ => self.to_number().0.partial_cmp(&other.to_number().0),
+        }
+    }
+}
+
+
+fn is_string(expr: &Expression) -> bool {
+    match *expr {
+        Expression::String(_) => true,
+        _ => false,
+    }
+}
+
+/// The shortest expression evaluating to `n`.
+pub fn number(n: Number) -> Expression {
+    let value = n.0;
+    if value.is_nan() {
+        return builder::infix(builder::number(0), InfixOperator::Div, builder::number(0));
+    }
+    if value < 0.0f64 || (value == 0.0f64 && value.is_sign_negative()) {
+        return builder::prefix(PrefixOperator::Negative, number(Number(-value)));
+    }
+    if value.is_infinite() {
+        return builder::infix(builder::number(1), InfixOperator::Div, builder::number(0));
+    }
+
+    if value.fract() == 0.0f64 && value <= Number::MAX_SAFE_INTEGER.0 {
+        builder::number(value as i64)
+    } else {
+        let raw = n.to_string();
+        Expression::Numeric(Box::new(LiteralNumeric { loc: Default::default(), span: Default::default(), raw, value: Numberic::F64(Float(value)) }))
+    }
+}
+
+fn number_len(n: Number) -> usize {
+    if n.0.is_nan() || n.0.is_infinite() {
+        return 3 + n.0.is_sign_negative() as usize;
+    }
+
+    n.to_string().len()
+}
+
+pub fn constant(value: Constant) -> Expression {
+    match value {
+        Constant::Undefined => builder::undefined(),
+        Constant::Null => builder::null(),
+        Constant::Boolean(value) => builder::boolean(value),
+        Constant::Number(n) => number(n),
+        Constant::String(s) => builder::string_value(&s),
+    }
+}
+
+fn fold_prefix(operator: PrefixOperator, operand: &Expression) -> Option<Expression> {
+    let value = Constant::from_expression(operand)?;
+    match operator {
+        PrefixOperator::Not => Some(builder::boolean(!value.to_boolean())),
+        PrefixOperator::TypeOf => Some(builder::string(value.type_of())),
+        PrefixOperator::Void => match *operand {
+            Expression::Numeric(ref inner) if inner.raw == "0" => None,
+            _ => Some(builder::undefined()),
+        },
+        PrefixOperator::Positive => Some(number(value.to_number())),
+        PrefixOperator::BitNot => Some(number(Number::from(!value.to_number().toInt32()))),
+        // NOTE: `-1` 本身已经是最短的形式
+        PrefixOperator::Negative => match value {
+            Constant::Number(_) => None,
+            value => Some(number(-value.to_number())),
+        },
+        _ => None,
+    }
+}
+
+fn fold_infix(left: &Expression, operator: InfixOperator, right: &Expression) -> Option<Expression> {
+    let a = Constant::from_expression(left)?;
+    let b = Constant::from_expression(right)?;
+
+    // NOTE: `0/0` 和 `1/0` 已经是 NaN 和 Infinity 最短的形式
+    if operator == InfixOperator::Div && !(a.to_number() / b.to_number()).0.is_finite() {
+        match (left, right) {
+            (&Expression::Numeric(_), &Expression::Numeric(_)) => return None,
+            _ => { },
+        }
+    }
+
+    let n = match operator {
+        InfixOperator::Add => match (&a, &b) {
+            (&Constant::String(_), _) | (_, &Constant::String(_)) => {
+                return Some(builder::string_value(&format!("{}{}", a.to_string(), b.to_string())));
+            },
+            _ => a.to_number() + b.to_number(),
+        },
+        InfixOperator::Sub => a.to_number() - b.to_number(),
+        InfixOperator::Mul => a.to_number() * b.to_number(),
+        InfixOperator::Div => a.to_number() / b.to_number(),
+        InfixOperator::Rem => a.to_number() % b.to_number(),
+        InfixOperator::Pow => a.to_number().pow(&b.to_number()),
+        InfixOperator::BitShl => a.to_number() << b.to_number(),
+        InfixOperator::BitShr => a.to_number() >> b.to_number(),
+        InfixOperator::BitUShr => a.to_number().unsigned_shr(&b.to_number()),
+        InfixOperator::BitAnd => Number::from(a.to_number().toInt32() & b.to_number().toInt32()),
+        InfixOperator::BitOr => Number::from(a.to_number().toInt32() | b.to_number().toInt32()),
+        InfixOperator::BitXor => Number::from(a.to_number().toInt32() ^ b.to_number().toInt32()),
+
+        InfixOperator::StrictEq => return Some(builder::boolean(a.strict_equals(&b))),
+        InfixOperator::StrictNeq => return Some(builder::boolean(!a.strict_equals(&b))),
+        InfixOperator::Eq => return Some(builder::boolean(a.loose_equals(&b))),
+        InfixOperator::Neq => return Some(builder::boolean(!a.loose_equals(&b))),
+        InfixOperator::Lt => return Some(builder::boolean(a.compare(&b) == Some(cmp::Ordering::Less))),
+        InfixOperator::Gt => return Some(builder::boolean(a.compare(&b) == Some(cmp::Ordering::Greater))),
+        InfixOperator::LtEq => return Some(builder::boolean(a.compare(&b).map(|o| o != cmp::Ordering::Greater).unwrap_or(false))),
+        InfixOperator::GtEq => return Some(builder::boolean(a.compare(&b).map(|o| o != cmp::Ordering::Less).unwrap_or(false))),
+
+        _ => return None,
+    };
+
+    // NOTE: `1 / 3` 折叠之后反而更长
+    let len = |value: &Constant| match *value {
+        Constant::Number(n) => number_len(n),
+        _ => value.to_string().len() + 2,
+    };
+    if number_len(n) > len(&a) + len(&b) + 1 {
+        return None;
+    }
+
+    Some(number(n))
+}
+
+/// `x + "a" + "b"` → `x + "ab"`: the left side is already a string.
+fn fold_concat(left: &mut Expression, right: &Expression) -> bool {
+    let value = match Constant::from_expression(right) {
+        Some(value) => value,
+        None => return false,
+    };
+
+    if let Expression::Infix(ref mut inner) = *left {
+        if inner.operator == InfixOperator::Add && is_string(&inner.right) {
+            if let Some(Constant::String(s)) = Constant::from_expression(&inner.right) {
+                inner.right = builder::string_value(&format!("{}{}", s, value.to_string()));
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+fn unparenthesized(expr: Expression) -> Expression {
+    match expr {
+        Expression::Parenthesized(mut inner) if inner.items.len() == 1 => unparenthesized(inner.items.remove(0)),
+        expr => expr,
+    }
+}
+
+// NOTE: `(o.m)()` 和 `o.m()` 一样带有 `this`
+fn is_reference(expr: &Expression) -> bool {
+    match *expr {
+        Expression::Member(_) | Expression::Identifier(_) => true,
+        Expression::Parenthesized(ref inner) if inner.items.len() == 1 => is_reference(&inner.items[0]),
+        _ => false,
+    }
+}
+
+/// `(0, o.m)`: a folded callee must not become a method call ( or a direct `eval` ),
+/// the folded operand of `delete`/`typeof` must not become a reference.
+fn detach(expr: Expression, is_callee: bool) -> Expression {
+    if !is_reference(&expr) {
+        return expr;
+    }
+
+    let expr = unparenthesized(expr);
+    let is_plain_call = match expr {
+        Expression::Identifier(ref ident) => is_callee && ident.name() != "eval",
+        _ => false,
+    };
+
+    if is_plain_call {
+        expr
+    } else {
+        builder::comma(vec![ builder::number(0), expr ])
+    }
+}
+
+
+struct ConstantFolding {
+    changed: bool,
+}
+
+impl ConstantFolding {
+    fn fold(&mut self, expr: &mut Expression) {
+        let folded = match *expr {
+            Expression::Prefix(ref inner) => fold_prefix(inner.operator, &inner.operand),
+            Expression::Infix(ref mut inner) => match inner.operator {
+                InfixOperator::And | InfixOperator::Or | InfixOperator::NullishCoalescing => {
+                    let left = match Constant::from_expression(&inner.left) {
+                        Some(left) => left,
+                        None => return,
+                    };
+                    let short_circuit = match inner.operator {
+                        InfixOperator::And => !left.to_boolean(),
+                        InfixOperator::Or => left.to_boolean(),
+                        _ => !left.is_nullish(),
+                    };
+                    let side = if short_circuit { &mut inner.left } else { &mut inner.right };
+                    Some(mem::replace(side, builder::null()))
+                },
+                InfixOperator::Add if fold_concat(&mut inner.left, &inner.right) => {
+                    Some(mem::replace(&mut inner.left, builder::null()))
+                },
+                operator => fold_infix(&inner.left, operator, &inner.right),
+            },
+            Expression::Conditional(ref mut inner) => match Constant::from_expression(&inner.condition) {
+                Some(test) => {
+                    let side = if test.to_boolean() { &mut inner.and_then } else { &mut inner.or_else };
+                    Some(mem::replace(side, builder::null()))
+                },
+                None => None,
+            },
+            Expression::Parenthesized(ref mut inner) if inner.items.len() == 1 => match inner.items[0] {
+                Expression::Null(_) | Expression::Boolean(_) | Expression::String(_) | Expression::Numeric(_) => inner.items.pop(),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if let Some(folded) = folded {
+            *expr = folded;
+            self.changed = true;
+        }
+    }
+}
+
+impl VisitMut for ConstantFolding {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        visit::walk_statement(self, stmt);
+
+        let test = match *stmt {
+            Statement::If(ref inner) => match Constant::from_expression(&inner.condition) {
+                Some(test) => test.to_boolean(),
+                None => return,
+            },
+            _ => return,
+        };
+
+        if let Statement::If(inner) = mem::replace(stmt, builder::empty()) {
+            let IfStatement { and_then, or_else, .. } = *inner;
+            let (mut taken, mut dropped) = if test { (and_then, or_else) } else { (or_else, and_then) };
+
+            // NOTE: 被删除的分支中的 `var` 声明仍然有效
+            let names = var_declared_names(&mut dropped);
+            *stmt = if names.is_empty() {
+                taken
+            } else {
+                let declarators = names.iter().map(|name| builder::declarator(builder::ident_expr(name), None)).collect();
+                let declaration = builder::variable(crate::ast::statement::LexicalDeclarationKind::Var, declarators);
+                match taken {
+                    Statement::Empty(_) => declaration,
+                    taken => builder::block_stmt(vec![ declaration, taken ]),
+                }
+            };
+            self.changed = true;
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        // NOTE: `(true ? o.m : f)()` 折叠后的被调用者不能带上 `this`，`delete`/`typeof` 的操作数也不能变成引用。
+        let keep_detached = match *expr {
+            Expression::Call(ref inner) => !is_reference(&inner.callee),
+            Expression::Prefix(ref inner) if inner.operator == PrefixOperator::Delete || inner.operator == PrefixOperator::TypeOf => {
+                !is_reference(&inner.operand)
+            },
+            _ => false,
+        };
+
+        visit::walk_expression(self, expr);
+
+        if keep_detached {
+            match *expr {
+                Expression::Call(ref mut inner) => {
+                    let callee = mem::replace(&mut inner.callee, builder::null());
+                    inner.callee = detach(callee, true);
+                },
+                Expression::Prefix(ref mut inner) => {
+                    let operand = mem::replace(&mut inner.operand, builder::null());
+                    inner.operand = detach(operand, false);
+                },
+                _ => { },
+            }
+        }
+
+        self.fold(expr);
+    }
+}
+
+
+/// Folds constant expressions of `body`, returns whether anything changed.
+pub fn fold_constants(body: &mut Vec<Statement>) -> bool {
+    let mut pass = ConstantFolding { changed: false };
+    pass.visit_statements(body);
+
+    pass.changed
+}
+
+
+#[test]
+fn test_constant_folding() {
+    use crate::compiler::transform::{ parse_owned, print_owned, };
+
+    fn fold(source: &str) -> String {
+        let mut body = parse_owned(source);
+        fold_constants(&mut body);
+        print_owned(&body)
+    }
+
+    assert_eq!(fold("1 + 2 * 3 - 4 / 2"), "5;");
+    assert_eq!(fold("\"a\" + 1 + true + null"), "\"a1truenull\";");
+    assert_eq!(fold("x + \"a\" + 1"), "x+\"a1\";");
+    assert_eq!(fold("0.5 + 0.25"), "0.75;");
+    assert_eq!(fold("0.1 + 0.2"), "0.1+0.2;");
+    assert_eq!(fold("0 / 0 === 0 / 0"), "false;");
+    assert_eq!(fold("1e21 + \"\""), "\"1e+21\";");
+    assert_eq!(fold("-1 >>> 28"), "15;");
+    assert_eq!(fold("typeof null + typeof void 1"), "\"objectundefined\";");
+    assert_eq!(fold("!\"\" && \"1\" == 1"), "true;");
+    assert_eq!(fold("null ?? x"), "x;");
+    assert_eq!(fold("1 ? x : y"), "x;");
+    assert_eq!(fold("(0 ? y : o.m)()"), "(0,o.m)();");
+    assert_eq!(fold("typeof (1 ? x : y)"), "typeof(0,x);");
+    assert_eq!(fold("\"b\" < \"a\" || 2 ** 10"), "1024;");
+
+    // if (0) { var a = f(); } else b();
+    let mut body = vec![
+        builder::if_stmt(
+            builder::number(0),
+            builder::block_stmt(vec![ builder::var("a", Some(builder::call(builder::ident_expr("f"), vec![]))) ]),
+            Some(builder::expr_stmt(builder::call(builder::ident_expr("b"), vec![]))),
+        ),
+    ];
+    assert_eq!(fold_constants(&mut body), true);
+    assert_eq!(print_owned(&body), "{var a;b();}");
+}
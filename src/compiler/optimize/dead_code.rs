@@ -0,0 +1,472 @@
+// Dead code elimination
+//
+//      return a; b(); var c = 1; function d() {}   →   return a; var c; function d() {}
+//      while (false) { e(); }                      →
+//      function f() { var g = 1, h = i(); function j() {} }   →   function f() { var h = i(); }
+//      1, /* @__PURE__ */ k(l()), m();             →   l(), m();
+//
+// NOTE: 函数内的声明只有在整个函数（包括嵌套的函数）中没有出现同名的引用时才会被删除，
+//       遮蔽只会让判断更保守。使用 `eval` 或 `with` 的函数以及顶层的声明（全局变量）不做处理。
+
+use crate::lexer::span::Loc;
+use crate::lexer::operator::{ PrefixOperator, InfixOperator, };
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::ast::statement::LexicalDeclarationKind;
+use crate::compiler::transform::{ bound_names, param_names, count_names, count_names_in_expression, };
+use crate::compiler::optimize::{ Constant, var_declared_names, };
+
+use std::mem;
+use std::collections::{ HashMap, HashSet, };
+
+
+// NOTE: 程序中声明过的名字，读取它们不会抛出 ReferenceError
+#[derive(Default)]
+struct DeclaredNames {
+    names: HashSet<String>,
+}
+
+impl VisitMut for DeclaredNames {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        let mut names = Vec::new();
+        match *stmt {
+            Statement::Variable(ref inner) => {
+                for declarator in inner.declarators.iter() {
+                    bound_names(&declarator.name, &mut names);
+                }
+            },
+            Statement::Try(ref inner) => {
+                if let Some(ref param) = inner.catch_parameter {
+                    bound_names(param, &mut names);
+                }
+            },
+            _ => { },
+        }
+        self.names.extend(names);
+        visit::walk_statement(self, stmt)
+    }
+
+    fn visit_binding_identifier(&mut self, ident: &mut Identifier) {
+        self.names.insert(ident.name().to_string());
+    }
+
+    fn visit_function(&mut self, func: &mut Function) {
+        self.names.extend(param_names(&func.params.items));
+        visit::walk_function(self, func)
+    }
+
+    fn visit_arrow_function(&mut self, arrow: &mut ArrowFunctionExpression) {
+        let mut names = Vec::new();
+        bound_names(&arrow.params, &mut names);
+        self.names.extend(names);
+        visit::walk_arrow_function(self, arrow)
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        match *method {
+            MethodDefinition::Method(ref inner) => self.names.extend(param_names(&inner.params.items)),
+            MethodDefinition::Setter(ref inner) => self.names.extend(param_names(&inner.params.items)),
+            MethodDefinition::Getter(_) => { },
+        }
+        visit::walk_method_definition(self, method)
+    }
+}
+
+
+// NOTE: 查找 `with` 语句和直接调用的 `eval`（包括嵌套的函数）
+#[derive(Default)]
+struct DynamicScopeFinder {
+    found: bool,
+}
+
+impl VisitMut for DynamicScopeFinder {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        if let Statement::With(_) = *stmt {
+            self.found = true;
+        }
+        if !self.found {
+            visit::walk_statement(self, stmt)
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        if let Expression::Call(ref inner) = *expr {
+            if let Expression::Identifier(ref ident) = inner.callee {
+                self.found = self.found || ident.name() == "eval";
+            }
+        }
+        if !self.found {
+            visit::walk_expression(self, expr)
+        }
+    }
+}
+
+
+fn is_terminator(stmt: &Statement) -> bool {
+    match *stmt {
+        Statement::Return(_) | Statement::Throw(_) | Statement::Break(_) | Statement::Continue(_) => true,
+        _ => false,
+    }
+}
+
+fn is_directive(stmt: &Statement) -> bool {
+    match *stmt {
+        Statement::Expression(ref expr) => match **expr {
+            Expression::String(_) => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+
+struct DeadCodeElimination<'a> {
+    pure_calls: &'a HashSet<usize>,
+    declared: HashSet<String>,
+    changed: bool,
+}
+
+impl<'a> DeadCodeElimination<'a> {
+    fn is_pure_call(&self, loc: Loc) -> bool {
+        !loc.is_dummy() && self.pure_calls.contains(&loc.start)
+    }
+
+    /// Evaluating `expr` has no observable effect ( besides its value ).
+    fn is_pure(&self, expr: &Expression) -> bool {
+        match *expr {
+            Expression::This(_) | Expression::Null(_) | Expression::Boolean(_)
+            | Expression::String(_) | Expression::Numeric(_) | Expression::RegularExpression(_)
+            | Expression::Function(_) | Expression::ArrowFunction(_) => true,
+            Expression::Identifier(ref ident) => self.declared.contains(ident.name()),
+            Expression::Parenthesized(ref inner) => inner.items.iter().all(|item| self.is_pure(item)),
+            Expression::Comma(ref inner) => inner.items.iter().all(|item| self.is_pure(item)),
+            Expression::ArrayLiteral(ref inner) => inner.elems.iter().all(|elem| match *elem {
+                Some(Expression::Spread(_)) => false,
+                Some(ref elem) => self.is_pure(elem),
+                None => true,
+            }),
+            Expression::ObjectLiteral(ref inner) => inner.properties.iter().all(|prop| match *prop {
+                ObjectProperty::Identifier(ref ident) => self.declared.contains(ident.name()),
+                ObjectProperty::Property { name: PropertyName::Computed(ref key), ref value, .. } => {
+                    Constant::from_expression(key).is_some() && self.is_pure(value)
+                },
+                ObjectProperty::Property { ref value, .. } => self.is_pure(value),
+                ObjectProperty::MethodDefinition(_) => !is_computed(prop),
+                ObjectProperty::Spread { .. } => false,
+            }),
+            Expression::Prefix(ref inner) => match inner.operator {
+                PrefixOperator::Not | PrefixOperator::Void => self.is_pure(&inner.operand),
+                PrefixOperator::TypeOf => match inner.operand {
+                    Expression::Identifier(_) => true,
+                    ref operand => self.is_pure(operand),
+                },
+                // NOTE: 其它运算符可能调用 `valueOf`
+                _ => Constant::from_expression(&inner.operand).is_some(),
+            },
+            Expression::Infix(ref inner) => match inner.operator {
+                InfixOperator::And | InfixOperator::Or | InfixOperator::NullishCoalescing
+                | InfixOperator::StrictEq | InfixOperator::StrictNeq => self.is_pure(&inner.left) && self.is_pure(&inner.right),
+                InfixOperator::InstanceOf | InfixOperator::In => false,
+                _ => Constant::from_expression(&inner.left).is_some() && Constant::from_expression(&inner.right).is_some(),
+            },
+            Expression::Conditional(ref inner) => {
+                self.is_pure(&inner.condition) && self.is_pure(&inner.and_then) && self.is_pure(&inner.or_else)
+            },
+            Expression::Call(ref inner) => {
+                self.is_pure_call(inner.loc) && inner.arguments.items.iter().all(|item| self.is_pure(item))
+            },
+            Expression::New(ref inner) => {
+                self.is_pure_call(inner.loc) && inner.arguments.iter().flat_map(|args| args.items.iter()).all(|item| self.is_pure(item))
+            },
+            _ => false,
+        }
+    }
+
+    /// The parts of an unused `expr` that must still be evaluated.
+    fn side_effects(&self, expr: Expression) -> Option<Expression> {
+        if self.is_pure(&expr) {
+            return None;
+        }
+
+        match expr {
+            Expression::Comma(inner) => {
+                let items = inner.items.into_iter().filter_map(|item| self.side_effects(item)).collect::<Vec<_>>();
+                if items.is_empty() { None } else { Some(builder::comma(items)) }
+            },
+            Expression::Parenthesized(mut inner) if inner.items.len() == 1 => self.side_effects(inner.items.remove(0)),
+            // NOTE: 标注了 `@__PURE__` 的调用只需要计算参数
+            Expression::Call(inner) if self.is_pure_call(inner.loc) => {
+                let items = inner.arguments.items.into_iter().filter_map(|item| self.side_effects(item)).collect::<Vec<_>>();
+                if items.is_empty() { None } else { Some(builder::comma(items)) }
+            },
+            expr => Some(expr),
+        }
+    }
+
+    /// Drops unreachable and useless statements of a statement list.
+    fn statements(&mut self, body: &mut Vec<Statement>) {
+        let mut result = Vec::with_capacity(body.len());
+        let mut reachable = true;
+
+        for stmt in body.drain(..) {
+            if !reachable {
+                match stmt {
+                    // NOTE: 函数声明会被提升，`var` 声明的变量仍然存在
+                    Statement::Function(_) => result.push(stmt),
+                    Statement::Variable(ref inner) if inner.is_var() && inner.declarators.iter().all(|declarator| {
+                        declarator.initializer.is_none() && match declarator.name { Expression::Identifier(_) => true, _ => false }
+                    }) => result.push(stmt.clone()),
+                    mut stmt => {
+                        let names = var_declared_names(&mut stmt);
+                        if !names.is_empty() {
+                            let declarators = names.iter().map(|name| builder::declarator(builder::ident_expr(name), None)).collect();
+                            result.push(builder::variable(LexicalDeclarationKind::Var, declarators));
+                        }
+                        self.changed = true;
+                    },
+                }
+                continue;
+            }
+
+            match stmt {
+                Statement::Empty(_) => self.changed = true,
+                Statement::Block(ref inner) if inner.body.is_empty() => self.changed = true,
+                Statement::Expression(ref expr) if is_directive(&stmt) => result.push(stmt.clone()),
+                Statement::Expression(expr) => {
+                    let is_pure_call = match *expr {
+                        Expression::Call(ref inner) => self.is_pure_call(inner.loc),
+                        Expression::Comma(_) | Expression::Parenthesized(_) => true,
+                        _ => false,
+                    };
+                    if !is_pure_call && !self.is_pure(&expr) {
+                        result.push(Statement::Expression(expr));
+                        continue;
+                    }
+
+                    self.changed = true;
+                    if let Some(expr) = self.side_effects(*expr) {
+                        result.push(builder::expr_stmt(expr));
+                    }
+                },
+                stmt => {
+                    reachable = !is_terminator(&stmt);
+                    result.push(stmt);
+                },
+            }
+        }
+
+        *body = result;
+    }
+
+    /// Removes the unused declarations of a function body.
+    fn function_body(&mut self, params: &mut [Expression], body: &mut Vec<Statement>) {
+        let mut finder = DynamicScopeFinder::default();
+        finder.visit_statements(body);
+        if finder.found {
+            return;
+        }
+
+        let mut counts = count_names(body);
+        for param in params.iter_mut() {
+            for (name, count) in count_names_in_expression(param) {
+                *counts.entry(name).or_insert(0) += count;
+            }
+        }
+
+        let mut remover = UnusedRemover { parent: self, counts: &counts };
+        remover.visit_statements(body);
+    }
+}
+
+fn is_computed(prop: &ObjectProperty) -> bool {
+    match *prop {
+        ObjectProperty::MethodDefinition(MethodDefinition::Method(ref inner)) => visit::is_computed_method_name(&inner.name),
+        ObjectProperty::MethodDefinition(MethodDefinition::Getter(ref inner)) => visit::is_computed_method_name(&inner.name),
+        ObjectProperty::MethodDefinition(MethodDefinition::Setter(ref inner)) => visit::is_computed_method_name(&inner.name),
+        _ => false,
+    }
+}
+
+impl<'a> VisitMut for DeadCodeElimination<'a> {
+    fn visit_statements(&mut self, body: &mut Vec<Statement>) {
+        visit::walk_statements(self, body);
+        self.statements(body);
+    }
+
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        visit::walk_statement(self, stmt);
+
+        // while (false) { ... }
+        let is_dead_loop = match *stmt {
+            Statement::While(ref inner) => Constant::from_expression(&inner.condition).map(|test| !test.to_boolean()).unwrap_or(false),
+            _ => false,
+        };
+        if is_dead_loop {
+            let names = var_declared_names(stmt);
+            *stmt = if names.is_empty() {
+                builder::empty()
+            } else {
+                let declarators = names.iter().map(|name| builder::declarator(builder::ident_expr(name), None)).collect();
+                builder::variable(LexicalDeclarationKind::Var, declarators)
+            };
+            self.changed = true;
+        }
+    }
+
+    fn visit_function(&mut self, func: &mut Function) {
+        visit::walk_function(self, func);
+        self.function_body(&mut func.params.items, &mut func.body);
+    }
+
+    fn visit_arrow_function(&mut self, arrow: &mut ArrowFunctionExpression) {
+        visit::walk_arrow_function(self, arrow);
+        if let ConciseBody::Stmt(ref mut body) = arrow.body {
+            self.function_body(::std::slice::from_mut(&mut arrow.params), body);
+        }
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        visit::walk_method_definition(self, method);
+        match *method {
+            MethodDefinition::Method(ref mut inner) => self.function_body(&mut inner.params.items, &mut inner.body),
+            MethodDefinition::Getter(ref mut inner) => self.function_body(&mut [], &mut inner.body),
+            MethodDefinition::Setter(ref mut inner) => self.function_body(&mut inner.params.items, &mut inner.body),
+        }
+    }
+}
+
+
+// NOTE: 删除函数体内（不进入嵌套函数）只出现过一次（即声明本身）的名字的声明
+struct UnusedRemover<'a, 'b: 'a> {
+    parent: &'a mut DeadCodeElimination<'b>,
+    counts: &'a HashMap<String, usize>,
+}
+
+impl<'a, 'b> UnusedRemover<'a, 'b> {
+    fn is_unused(&self, name: &str) -> bool {
+        self.counts.get(name).cloned().unwrap_or(0) <= 1
+    }
+}
+
+impl<'a, 'b> VisitMut for UnusedRemover<'a, 'b> {
+    fn visit_statements(&mut self, body: &mut Vec<Statement>) {
+        visit::walk_statements(self, body);
+
+        let before = body.len();
+        body.retain(|stmt| match *stmt {
+            Statement::Empty(_) => false,
+            _ => true,
+        });
+        self.parent.changed = self.parent.changed || body.len() != before;
+    }
+
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        match *stmt {
+            Statement::Function(ref inner) if self.is_unused(inner.name.name()) => {
+                *stmt = builder::empty();
+            },
+            Statement::Variable(ref mut inner) => {
+                let before = inner.declarators.len();
+                let declarators = mem::replace(&mut inner.declarators, Vec::new());
+                for declarator in declarators {
+                    let is_unused = match declarator.name {
+                        Expression::Identifier(ref ident) => self.is_unused(ident.name()),
+                        _ => false,
+                    };
+                    let is_pure = declarator.initializer.as_ref().map(|init| self.parent.is_pure(init)).unwrap_or(true);
+                    if !(is_unused && is_pure) {
+                        inner.declarators.push(declarator);
+                    }
+                }
+
+                if inner.declarators.len() == before {
+                    return;
+                }
+                if inner.declarators.is_empty() {
+                    *stmt = builder::empty();
+                }
+            },
+            // NOTE: 不进入嵌套的函数，只处理块级语句
+            _ => return visit::walk_statement(self, stmt),
+        }
+
+        self.parent.changed = true;
+    }
+
+    fn visit_expression(&mut self, _expr: &mut Expression) { }
+}
+
+
+/// Removes unreachable code and unused declarations of `body`, returns whether anything changed.
+///
+/// `pure_calls` are the source offsets of the calls annotated with `/* @__PURE__ */` ( `Parser::pure_annotations` ).
+pub fn eliminate_dead_code(body: &mut Vec<Statement>, pure_calls: &HashSet<usize>) -> bool {
+    let mut declared = DeclaredNames::default();
+    declared.visit_statements(body);
+
+    let mut pass = DeadCodeElimination { pure_calls, declared: declared.names, changed: false };
+    pass.visit_statements(body);
+
+    pass.changed
+}
+
+
+#[test]
+fn test_dead_code_elimination() {
+    use crate::ast::owned::ToOwnedAst;
+    use crate::parser::Parser;
+    use crate::compiler::transform::print_owned;
+    use crate::compiler::optimize::Optimizer;
+
+    let arena = crate::toolshed::Arena::new();
+    let code = arena.alloc_vec("1, /* @__PURE__ */ f(a()); /* @__PURE__ */ new G(); b();".chars().collect::<Vec<char>>());
+    let mut parser = Parser::new(&arena, &code, "main.js");
+    parser.parse().ok().unwrap();
+    let mut body = parser.body.as_slice().to_owned_ast();
+    let pure_calls = parser.pure_annotations().iter().cloned().collect::<HashSet<usize>>();
+    assert_eq!(eliminate_dead_code(&mut body, &pure_calls), true);
+    assert_eq!(print_owned(&body), "a();b();");
+
+    // function f(x) {
+    //     "use strict";
+    //     var a = 1, b = g(), c = [ x ];
+    //     function d() { }
+    //     while (false) { var e = h(); }
+    //     if (!1) { i(); }
+    //     return b;
+    //     j();
+    //     var k = 2;
+    //     function l() { return c; }
+    // }
+    let call = |name: &str| builder::call(builder::ident_expr(name), vec![]);
+    let mut body = vec![
+        builder::function_decl("f", vec![ builder::ident_expr("x") ], vec![
+            builder::expr_stmt(builder::string("use strict")),
+            builder::variable(LexicalDeclarationKind::Var, vec![
+                builder::declarator(builder::ident_expr("a"), Some(builder::number(1))),
+                builder::declarator(builder::ident_expr("b"), Some(call("g"))),
+                builder::declarator(builder::ident_expr("c"), Some(builder::array(vec![ builder::ident_expr("x") ]))),
+            ]),
+            builder::function_decl("d", vec![], vec![]),
+            builder::while_stmt(builder::boolean(false), builder::block_stmt(vec![ builder::var("e", Some(call("h"))) ])),
+            builder::if_stmt(builder::prefix(PrefixOperator::Not, builder::number(1)), builder::block_stmt(vec![ builder::expr_stmt(call("i")) ]), None),
+            builder::return_stmt(Some(builder::ident_expr("b"))),
+            builder::expr_stmt(call("j")),
+            builder::var("k", Some(builder::number(2))),
+            builder::function_decl("l", vec![], vec![ builder::return_stmt(Some(builder::ident_expr("c"))) ]),
+        ]),
+    ];
+    Optimizer::new().optimize(&mut body);
+    assert_eq!(print_owned(&body), "function f(x){\"use strict\";var b=g();return b;}");
+
+    // NOTE: 顶层的声明是全局变量，使用 `eval` 的函数不做处理
+    let mut body = vec![
+        builder::var("a", Some(builder::number(1))),
+        builder::function_decl("f", vec![], vec![
+            builder::var("b", Some(builder::number(1))),
+            builder::expr_stmt(builder::call(builder::ident_expr("eval"), vec![ builder::ident_expr("s") ])),
+        ]),
+    ];
+    assert_eq!(eliminate_dead_code(&mut body, &HashSet::new()), false);
+}
@@ -0,0 +1,604 @@
+// Identifier mangling
+//
+//      function f(value, index) { var result = value * index; return result + index; }
+//          →   function f(c, a) { var b = c * a; return b + a; }
+//
+// NOTE: 名字按作用域从外到内分配，同一作用域内出现次数越多的绑定分配越短的名字。
+//       一个绑定只需要避开在它的作用域内被引用到的外层绑定（以及全局变量）的名字，
+//       所以兄弟作用域会复用同样的短名字。
+//
+//       顶层（全局）的绑定保持原名；含有直接调用的 `eval`、`with` 语句或者 JSX 的作用域，
+//       以及它们的外层作用域，都保持原名。
+//
+//       属性名只处理 IdentifierName 形式的（`a.b`、`{ b: 1 }`、`{ b() {} }`），
+//       带引号的属性名 `{ "b": 1 }`、`a["b"]` 保持原样。
+
+use crate::lexer::keyword::KeywordKind;
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{ bound_names, param_names, };
+
+use regex::Regex;
+
+use std::mem;
+use std::collections::{ HashMap, HashSet, };
+
+
+const FIRST_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ$_";
+const REST_CHARS: &[u8]  = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ$_0123456789";
+
+/// The `index`-th short name: `a`, `b`, ... `_`, `aa`, `ba`, ...
+fn short_name(mut index: usize) -> String {
+    let mut name = String::new();
+    name.push(FIRST_CHARS[index % FIRST_CHARS.len()] as char);
+    index /= FIRST_CHARS.len();
+
+    while index > 0 {
+        index -= 1;
+        name.push(REST_CHARS[index % REST_CHARS.len()] as char);
+        index /= REST_CHARS.len();
+    }
+
+    name
+}
+
+fn is_reserved_word(name: &str) -> bool {
+    match name {
+        "null" | "true" | "false" | "arguments" | "eval" => true,
+        _ => name.parse::<KeywordKind>().is_ok(),
+    }
+}
+
+/// The first short name starting from `*next` that is neither reserved nor in `avoid`.
+fn next_name(next: &mut usize, avoid: &HashSet<String>) -> String {
+    loop {
+        let name = short_name(*next);
+        *next += 1;
+        if !is_reserved_word(&name) && !avoid.contains(&name) {
+            return name;
+        }
+    }
+}
+
+
+struct Binding {
+    name: String,
+    count: usize,
+    mangled: Option<String>,
+}
+
+struct Scope {
+    parent: Option<usize>,
+    is_function: bool,
+    dynamic: bool,
+    bindings: Vec<Binding>,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone)]
+enum Reference {
+    Binding(usize, usize),
+    Free(String),
+}
+
+struct Property {
+    count: usize,
+    order: usize,
+    mangled: Option<String>,
+}
+
+
+// NOTE: 同一个 Visitor 遍历两次：第一次收集作用域、绑定和引用，第二次重命名。
+//       两次遍历创建作用域的顺序相同，作用域的编号即为创建的顺序（外层作用域的编号更小）。
+struct ScopeWalker<'a> {
+    collecting: bool,
+    scopes: Vec<Scope>,
+    references: Vec<(usize, String)>,
+    current: usize,
+    next: usize,
+
+    properties: Option<&'a Regex>,
+    property_names: HashMap<String, Property>,
+    original_names: &'a mut HashMap<usize, String>,
+}
+
+impl<'a> ScopeWalker<'a> {
+    fn enter(&mut self, is_function: bool) -> usize {
+        if self.collecting {
+            let parent = Some(self.current);
+            self.scopes.push(Scope { parent, is_function, dynamic: false, bindings: Vec::new() });
+        }
+
+        let saved = self.current;
+        self.current = self.next;
+        self.next += 1;
+        saved
+    }
+
+    fn exit(&mut self, saved: usize) {
+        self.current = saved;
+    }
+
+    fn function_scope(&self) -> usize {
+        let mut scope = self.current;
+        while !self.scopes[scope].is_function {
+            scope = self.scopes[scope].parent.unwrap_or(0);
+        }
+        scope
+    }
+
+    fn declare<I: IntoIterator<Item=String>>(&mut self, scope: usize, names: I) {
+        if !self.collecting {
+            return;
+        }
+
+        let bindings = &mut self.scopes[scope].bindings;
+        for name in names {
+            if bindings.iter().all(|binding| binding.name != name) {
+                bindings.push(Binding { name, count: 0, mangled: None });
+            }
+        }
+    }
+
+    fn declare_pattern(&mut self, scope: usize, target: &Expression) {
+        let mut names = Vec::new();
+        bound_names(target, &mut names);
+        self.declare(scope, names);
+    }
+
+    fn set_dynamic(&mut self) {
+        if self.collecting {
+            self.scopes[self.current].dynamic = true;
+        }
+    }
+
+    fn resolve(&self, mut scope: usize, name: &str) -> Option<(usize, usize)> {
+        loop {
+            if let Some(index) = self.scopes[scope].bindings.iter().position(|binding| binding.name == name) {
+                return Some((scope, index));
+            }
+            scope = self.scopes[scope].parent?;
+        }
+    }
+
+    fn rename(&mut self, ident: &mut Identifier, name: String) {
+        if !ident.loc.is_dummy() {
+            self.original_names.insert(ident.loc.start, ident.name().to_string());
+        }
+        ident.raw = name;
+        ident.cooked = None;
+    }
+
+    /// A variable name occurrence, either a reference or a binding.
+    fn variable(&mut self, ident: &mut Identifier) {
+        if self.collecting {
+            self.references.push((self.current, ident.name().to_string()));
+            return;
+        }
+
+        let mangled = self.resolve(self.current, ident.name())
+            .and_then(|(scope, index)| self.scopes[scope].bindings[index].mangled.clone());
+        if let Some(name) = mangled {
+            self.rename(ident, name);
+        }
+    }
+
+    /// A property name occurrence.
+    fn property(&mut self, ident: &mut Identifier) {
+        if self.properties.is_none() {
+            return;
+        }
+
+        if self.collecting {
+            let order = self.property_names.len();
+            let property = self.property_names.entry(ident.name().to_string())
+                .or_insert(Property { count: 0, order, mangled: None });
+            property.count += 1;
+            return;
+        }
+
+        let mangled = self.property_names.get(ident.name()).and_then(|property| property.mangled.clone());
+        if let Some(name) = mangled {
+            self.rename(ident, name);
+        }
+    }
+
+    /// `{ x }` → `{ p: a }` when either name changes, returns the renamed property and variable names.
+    fn shorthand(&mut self, ident: &Identifier) -> Option<(Identifier, Identifier)> {
+        let mut key = ident.clone();
+        let mut value = ident.clone();
+        self.property(&mut key);
+        self.variable(&mut value);
+
+        if self.collecting || (key.raw == ident.raw && value.raw == ident.raw) {
+            None
+        } else {
+            Some((key, value))
+        }
+    }
+
+    fn method_name(&mut self, name: &mut Expression) {
+        match *name {
+            Expression::Identifier(ref mut ident) => self.property(ident),
+            Expression::String(_) | Expression::Numeric(_) => { },
+            ref mut name => self.visit_expression(name),
+        }
+    }
+
+    /// Assigns the new names once all scopes and references are known.
+    fn assign(&mut self) {
+        // NOTE: `eval` 和 `with` 可以访问外层作用域的所有绑定
+        for scope in (0..self.scopes.len()).rev() {
+            if self.scopes[scope].dynamic {
+                if let Some(parent) = self.scopes[scope].parent {
+                    self.scopes[parent].dynamic = true;
+                }
+            }
+        }
+
+        // NOTE: 引用穿过的作用域（不包括绑定所在的作用域）不能使用与该绑定相同的名字
+        let mut through = vec![HashSet::new(); self.scopes.len()];
+        for (scope, name) in mem::replace(&mut self.references, Vec::new()) {
+            let (reference, target) = match self.resolve(scope, &name) {
+                Some((target, index)) => {
+                    self.scopes[target].bindings[index].count += 1;
+                    (Reference::Binding(target, index), Some(target))
+                },
+                None => (Reference::Free(name), None),
+            };
+
+            let mut scope = Some(scope);
+            while let Some(current) = scope {
+                if Some(current) == target {
+                    break;
+                }
+                through[current].insert(reference.clone());
+                scope = self.scopes[current].parent;
+            }
+        }
+
+        for scope in 1..self.scopes.len() {
+            if self.scopes[scope].dynamic {
+                continue;
+            }
+
+            let avoid = through[scope].iter().map(|reference| match *reference {
+                Reference::Binding(target, index) => {
+                    let binding = &self.scopes[target].bindings[index];
+                    binding.mangled.clone().unwrap_or_else(|| binding.name.clone())
+                },
+                Reference::Free(ref name) => name.clone(),
+            }).collect::<HashSet<String>>();
+
+            let bindings = &mut self.scopes[scope].bindings;
+            let mut order = (0..bindings.len()).collect::<Vec<usize>>();
+            order.sort_by_key(|&index| (usize::max_value() - bindings[index].count, index));
+
+            let mut next = 0;
+            for index in order {
+                bindings[index].mangled = Some(next_name(&mut next, &avoid));
+            }
+        }
+
+        if let Some(pattern) = self.properties {
+            let avoid = self.property_names.keys()
+                .filter(|name| !pattern.is_match(name))
+                .cloned()
+                .collect::<HashSet<String>>();
+
+            let mut order = self.property_names.iter_mut()
+                .filter(|&(name, _)| pattern.is_match(name))
+                .map(|(_, property)| property)
+                .collect::<Vec<&mut Property>>();
+            order.sort_by_key(|property| (usize::max_value() - property.count, property.order));
+
+            let mut next = 0;
+            for property in order {
+                property.mangled = Some(next_name(&mut next, &avoid));
+            }
+        }
+    }
+}
+
+impl<'a> VisitMut for ScopeWalker<'a> {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        match *stmt {
+            Statement::Variable(ref inner) => {
+                let scope = if inner.is_var() { self.function_scope() } else { self.current };
+                for declarator in inner.declarators.iter() {
+                    self.declare_pattern(scope, &declarator.name);
+                }
+            },
+            // NOTE: 块中的函数声明（Annex B）也会在函数作用域中创建同名的变量，这里统一视为函数作用域的绑定
+            Statement::Function(ref inner) => {
+                let scope = self.function_scope();
+                self.declare(scope, vec![ inner.name.name().to_string() ]);
+            },
+            Statement::Class(ref inner) => {
+                let scope = self.current;
+                self.declare(scope, vec![ inner.name.name().to_string() ]);
+            },
+            Statement::With(_) => self.set_dynamic(),
+            Statement::Block(_) | Statement::For(_) | Statement::ForIn(_) | Statement::ForOf(_)
+            | Statement::ForAwaitOf(_) | Statement::Switch(_) => {
+                let saved = self.enter(false);
+                visit::walk_statement(self, stmt);
+                return self.exit(saved);
+            },
+            Statement::Try(ref mut inner) => {
+                let saved = self.enter(false);
+                self.visit_statements(&mut inner.body.body);
+                self.exit(saved);
+
+                if let Some(ref mut catch_body) = inner.catch_body {
+                    let saved = self.enter(false);
+                    if let Some(ref mut param) = inner.catch_parameter {
+                        let scope = self.current;
+                        self.declare_pattern(scope, param);
+                        self.visit_expression(param);
+                    }
+                    self.visit_statements(&mut catch_body.body);
+                    self.exit(saved);
+                }
+
+                if let Some(ref mut finally) = inner.finally {
+                    let saved = self.enter(false);
+                    self.visit_statements(&mut finally.body);
+                    self.exit(saved);
+                }
+                return;
+            },
+            _ => { },
+        }
+
+        visit::walk_statement(self, stmt)
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        match *expr {
+            Expression::Identifier(ref mut ident) => return self.variable(ident),
+            Expression::Call(ref inner) => match inner.callee {
+                Expression::Identifier(ref ident) if ident.name() == "eval" => self.set_dynamic(),
+                _ => { },
+            },
+            Expression::Member(ref mut inner) if !inner.computed => {
+                self.visit_expression(&mut inner.left);
+                if let Expression::Identifier(ref mut ident) = inner.right {
+                    self.property(ident);
+                }
+                return;
+            },
+            // NOTE: 函数表达式和类表达式的名字只在其内部可见
+            Expression::Function(ref mut inner) if inner.name.is_some() => {
+                let saved = self.enter(false);
+                if let Some(ref mut name) = inner.name {
+                    let scope = self.current;
+                    self.declare(scope, vec![ name.name().to_string() ]);
+                    self.variable(name);
+                }
+                self.visit_function(&mut inner.func);
+                return self.exit(saved);
+            },
+            Expression::Class(ref mut inner) if inner.name.is_some() => {
+                let saved = self.enter(false);
+                if let Some(ref mut name) = inner.name {
+                    let scope = self.current;
+                    self.declare(scope, vec![ name.name().to_string() ]);
+                    self.variable(name);
+                }
+                self.visit_class(&mut inner.class);
+                return self.exit(saved);
+            },
+            _ => { },
+        }
+
+        visit::walk_expression(self, expr)
+    }
+
+    fn visit_binding_identifier(&mut self, ident: &mut Identifier) {
+        self.variable(ident)
+    }
+
+    fn visit_function(&mut self, func: &mut Function) {
+        let saved = self.enter(true);
+        let scope = self.current;
+        self.declare(scope, param_names(&func.params.items));
+        visit::walk_function(self, func);
+        self.exit(saved);
+    }
+
+    fn visit_arrow_function(&mut self, arrow: &mut ArrowFunctionExpression) {
+        let saved = self.enter(true);
+        let scope = self.current;
+        self.declare_pattern(scope, &arrow.params);
+        visit::walk_arrow_function(self, arrow);
+        self.exit(saved);
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        let (name, params, body) = match *method {
+            MethodDefinition::Method(ref mut inner) => (&mut inner.name, Some(&mut inner.params.items), &mut inner.body),
+            MethodDefinition::Getter(ref mut inner) => (&mut inner.name, None, &mut inner.body),
+            MethodDefinition::Setter(ref mut inner) => (&mut inner.name, Some(&mut inner.params.items), &mut inner.body),
+        };
+
+        // NOTE: 计算属性名在外层作用域中求值
+        self.method_name(name);
+
+        let saved = self.enter(true);
+        if let Some(params) = params {
+            let scope = self.current;
+            self.declare(scope, param_names(params));
+            for item in params.iter_mut() {
+                self.visit_expression(item);
+            }
+        }
+        self.visit_statements(body);
+        self.exit(saved);
+    }
+
+    fn visit_property_name(&mut self, name: &mut PropertyName) {
+        match *name {
+            PropertyName::Identifier(ref mut ident) => self.property(ident),
+            _ => visit::walk_property_name(self, name),
+        }
+    }
+
+    fn visit_object_property(&mut self, prop: &mut ObjectProperty) {
+        let renamed = match *prop {
+            ObjectProperty::Identifier(ref ident) => self.shorthand(ident),
+            _ => return visit::walk_object_property(self, prop),
+        };
+
+        if let Some((key, value)) = renamed {
+            *prop = builder::property(PropertyName::Identifier(key), Expression::Identifier(Box::new(value)));
+        }
+    }
+
+    fn visit_binding_property(&mut self, prop: &mut BindingProperty) {
+        let renamed = match *prop {
+            BindingProperty::SingleNameBinding { ref name, .. } => self.shorthand(name),
+            _ => None,
+        };
+        visit::walk_binding_property(self, prop);
+
+        if let Some((key, value)) = renamed {
+            if let BindingProperty::SingleNameBinding { loc, span, ref mut init, .. } = *prop {
+                let init = init.take();
+                let value = BindingElement::SingleNameBinding { loc, span, name: value, init };
+                *prop = BindingProperty::Property { loc, span, name: PropertyName::Identifier(key), puct: builder::colon(), value };
+            }
+        }
+    }
+
+    fn visit_assignment_property(&mut self, prop: &mut AssignmentProperty) {
+        let renamed = match *prop {
+            AssignmentProperty::Identifier { ref name, .. } => self.shorthand(name),
+            _ => None,
+        };
+        visit::walk_assignment_property(self, prop);
+
+        if let Some((key, value)) = renamed {
+            if let AssignmentProperty::Identifier { loc, span, ref mut init, .. } = *prop {
+                let init = init.take();
+                let value = AssignmentElement { loc, span, elem: Expression::Identifier(Box::new(value)), init };
+                *prop = AssignmentProperty::Property { loc, span, name: PropertyName::Identifier(key), puct: builder::colon(), value };
+            }
+        }
+    }
+
+    fn visit_jsx_element(&mut self, elem: &mut JSXElement) {
+        // NOTE: JSX 标签名引用的变量不做处理，应当先降级 JSX 再混淆
+        self.set_dynamic();
+        visit::walk_jsx_element(self, elem)
+    }
+
+    fn visit_jsx_fragment(&mut self, frag: &mut JSXFragment) {
+        self.set_dynamic();
+        visit::walk_jsx_children(self, &mut frag.children)
+    }
+}
+
+
+/// Renames local bindings ( and optionally properties ) to short names.
+///
+/// The original names are kept by source offset, pass them to `CodeGen::set_original_names`
+/// so that the source map `names` still refer to the original identifiers.
+#[derive(Debug, Default, Clone)]
+pub struct Mangler {
+    properties: Option<Regex>,
+    original_names: HashMap<usize, String>,
+}
+
+impl Mangler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also mangle the property names matching `pattern`.
+    pub fn set_properties(&mut self, pattern: Regex) {
+        self.properties = Some(pattern);
+    }
+
+    /// The original names of the renamed identifiers, by their source offset.
+    pub fn original_names(&self) -> &HashMap<usize, String> {
+        &self.original_names
+    }
+
+    pub fn mangle(&mut self, body: &mut Vec<Statement>) {
+        let global = Scope { parent: None, is_function: true, dynamic: false, bindings: Vec::new() };
+        let mut walker = ScopeWalker {
+            collecting: true,
+            scopes: vec![ global ],
+            references: Vec::new(),
+            current: 0,
+            next: 1,
+            properties: self.properties.as_ref(),
+            property_names: HashMap::new(),
+            original_names: &mut self.original_names,
+        };
+
+        walker.visit_statements(body);
+        walker.assign();
+
+        walker.collecting = false;
+        walker.next = 1;
+        walker.visit_statements(body);
+    }
+}
+
+
+#[test]
+fn test_mangle() {
+    use crate::toolshed::Arena;
+    use crate::ast::owned::ToArenaAst;
+    use crate::compiler::codegen::CodeGen;
+    use crate::compiler::sourcemap::SourceMap;
+    use crate::compiler::transform::{ parse_owned, print_owned, };
+
+    fn mangle(source: &str, properties: Option<&str>) -> String {
+        let mut body = parse_owned(source);
+        let mut mangler = Mangler::new();
+        if let Some(pattern) = properties {
+            mangler.set_properties(Regex::new(pattern).unwrap());
+        }
+        mangler.mangle(&mut body);
+        print_owned(&body)
+    }
+
+    assert_eq!(mangle("function f(value, index) { value * index; index; top }", None), "function f(b,a){b*a;a;top;}");
+    assert_eq!(mangle("function f(a, b) { (function g() { a; b; b; g; }) }", None), "function f(b,a){(function c(){b;a;a;c;});}");
+    assert_eq!(mangle("function f(a) { { function g() {} } g; g }", None), "function f(b){{function a(){}}a;a;}");
+    assert_eq!(mangle("x => x.value", None), "a=>a.value;");
+    assert_eq!(mangle("function f(a) { eval(a) }", None), "function f(a){eval(a);}");
+    assert_eq!(mangle("o._private = o._other + o._private + o.public", Some("^_")), "o.a=o.b+o.a+o.public;");
+
+    // function f(x) { ({ x }); }
+    let mut body = vec![
+        builder::function_decl("f", vec![ builder::ident_expr("x") ], vec![
+            builder::expr_stmt(builder::parenthesized(builder::object(vec![ ObjectProperty::Identifier(builder::ident("x")) ]))),
+        ]),
+    ];
+    Mangler::new().mangle(&mut body);
+    assert_eq!(print_owned(&body), "function f(a){({x:a});}");
+
+    // NOTE: source map 中记录原始的名字
+    let source = "function f(value) { value }";
+    let mut body = parse_owned(source);
+    let mut mangler = Mangler::new();
+    mangler.mangle(&mut body);
+
+    let arena = Arena::new();
+    let body = body.iter().map(|stmt| stmt.to_arena_ast(&arena)).collect::<Vec<_>>();
+    let mut source_map = SourceMap::new("dist/main.js");
+    let source_index = source_map.add_source("src/main.js", Some(source.to_string()));
+    let output = {
+        let mut codegen = CodeGen::with_source_map(Vec::new(), &mut source_map, source_index);
+        codegen.set_original_names(mangler.original_names());
+        codegen.set_minify(true);
+        codegen.gen_program(&body).unwrap();
+        String::from_utf8(codegen.into_inner()).unwrap()
+    };
+    assert_eq!(output, "function f(a){a;}");
+    assert_eq!(source_map.names(), &[ "f".to_string(), "value".to_string() ]);
+}
@@ -0,0 +1,85 @@
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::compiler::transform::bound_names;
+
+use std::collections::HashSet;
+
+mod constant_folding;
+mod dead_code;
+mod mangle;
+
+pub use self::constant_folding::{ Constant, fold_constants, };
+pub use self::dead_code::eliminate_dead_code;
+pub use self::mangle::Mangler;
+
+
+// NOTE: 两个 pass 互相产生新的机会（折叠出常量条件，删除后出现可折叠的表达式），
+//       所以需要重复执行直到不再变化。
+const MAX_ITERATIONS: usize = 100;
+
+/// Runs the optimization passes until the program reaches a fixed point.
+#[derive(Debug, Default, Clone)]
+pub struct Optimizer {
+    pure_calls: HashSet<usize>,
+}
+
+impl Optimizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Source offsets of the calls annotated with `/* @__PURE__ */`, see `Parser::pure_annotations`.
+    pub fn set_pure_calls<I: IntoIterator<Item=usize>>(&mut self, offsets: I) {
+        self.pure_calls = offsets.into_iter().collect();
+    }
+
+    pub fn optimize(&mut self, body: &mut Vec<Statement>) {
+        for _ in 0..MAX_ITERATIONS {
+            let folded = fold_constants(body);
+            let eliminated = eliminate_dead_code(body, &self.pure_calls);
+            if !folded && !eliminated {
+                break;
+            }
+        }
+    }
+}
+
+
+/// Names declared by `var` ( and sloppy mode function declarations in blocks ) inside `stmt`,
+/// not counting nested functions: removing `stmt` must keep these bindings.
+pub fn var_declared_names(stmt: &mut Statement) -> Vec<String> {
+    struct VarNames {
+        names: Vec<String>,
+    }
+
+    impl VisitMut for VarNames {
+        fn visit_statement(&mut self, stmt: &mut Statement) {
+            match *stmt {
+                Statement::Variable(ref inner) if inner.is_var() => {
+                    for declarator in inner.declarators.iter() {
+                        bound_names(&declarator.name, &mut self.names);
+                    }
+                },
+                Statement::Function(ref inner) => self.names.push(inner.name.name().to_string()),
+                _ => { },
+            }
+            visit::walk_statement(self, stmt)
+        }
+
+        fn visit_expression(&mut self, _expr: &mut Expression) { }
+        fn visit_function(&mut self, _func: &mut Function) { }
+        fn visit_class(&mut self, _class: &mut Class) { }
+    }
+
+    let mut visitor = VarNames { names: Vec::new() };
+    visitor.visit_statement(stmt);
+
+    let mut names = Vec::new();
+    for name in visitor.names {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    names
+}
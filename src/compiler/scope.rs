@@ -0,0 +1,1412 @@
+use crate::error::{ ErrorKind, Error, };
+use crate::lexer::span::{ Loc, Span, };
+use crate::lexer::token::Identifier;
+use crate::lexer::operator::{ PrefixOperator, AssignmentOperator, };
+use crate::ast::statement::{ Statement, VariableStatement, LexicalDeclarationKind, };
+use crate::ast::expression::{ Expression, ParenthesizedExpression, LiteralTemplateExpression, };
+use crate::ast::function::{ FunctionBody, ConciseBody, };
+use crate::ast::class::{ Class, MethodDefinition, };
+use crate::ast::pattern::{
+    PropertyName, ObjectProperty,
+    BindingPattern, BindingElement, BindingProperty, BindingRestElement,
+    AssignmentPattern, AssignmentProperty,
+};
+use crate::ast::jsx::{
+    JSXElement, JSXElementName, JSXAttribute, JSXAttributes,
+    JSXNormalAttributeInitializer, JSXChild, JSXChildren,
+};
+
+use std::collections::HashMap;
+
+
+// Scope Analysis
+// https://www.ecma-international.org/ecma-262/9.0/index.html#sec-static-semantics-vardeclarednames
+// https://www.ecma-international.org/ecma-262/9.0/index.html#sec-static-semantics-lexicallydeclarednames
+//
+// NOTE: 分析过程分两步：
+//      1. 进入作用域时，先声明该作用域内的所有绑定（var/function 提升到最近的函数作用域）。
+//      2. 遍历作用域内的代码，将每个 Identifier 解析到对应的绑定，找不到的视为全局变量。
+
+pub type ScopeId = usize;
+pub type BindingId = usize;
+pub type ReferenceId = usize;
+
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Goal {
+    Script,
+    Module,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum ScopeKind {
+    Global,
+    Module,
+    Function,
+    Block,
+    Catch,
+    Class,
+    With,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum DeclarationKind {
+    Var,
+    Let,
+    Const,
+    Function,
+    Class,
+    Param,
+    Import,
+}
+
+impl DeclarationKind {
+    /// Bindings that can be declared more than once in the same scope.
+    pub fn is_var_like(&self) -> bool {
+        match *self {
+            DeclarationKind::Var | DeclarationKind::Function | DeclarationKind::Param => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_lexical(&self) -> bool {
+        !self.is_var_like()
+    }
+
+    pub fn is_const(&self) -> bool {
+        match *self {
+            DeclarationKind::Const | DeclarationKind::Import => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum ReferenceKind {
+    Read,
+    Write,
+    ReadWrite,  // a += 1, a++
+}
+
+impl ReferenceKind {
+    pub fn is_read(&self) -> bool {
+        match *self {
+            ReferenceKind::Read | ReferenceKind::ReadWrite => true,
+            ReferenceKind::Write => false,
+        }
+    }
+
+    pub fn is_write(&self) -> bool {
+        match *self {
+            ReferenceKind::Write | ReferenceKind::ReadWrite => true,
+            ReferenceKind::Read => false,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    pub children: Vec<ScopeId>,
+    pub loc: Loc,
+    pub bindings: HashMap<String, BindingId>,
+    pub references: Vec<ReferenceId>,
+    pub is_arrow: bool,
+    // NOTE: 直接调用 `eval` 的作用域以及它的所有祖先作用域
+    pub has_direct_eval: bool,
+    // NOTE: 仅用于非箭头函数作用域
+    pub uses_this: bool,
+    pub uses_arguments: bool,
+}
+
+impl Scope {
+    fn new(kind: ScopeKind, parent: Option<ScopeId>, loc: Loc) -> Self {
+        Self {
+            kind,
+            parent,
+            children: Vec::new(),
+            loc,
+            bindings: HashMap::new(),
+            references: Vec::new(),
+            is_arrow: false,
+            has_direct_eval: false,
+            uses_this: false,
+            uses_arguments: false,
+        }
+    }
+
+    /// Scopes that receive `var` declarations.
+    pub fn is_var_scope(&self) -> bool {
+        match self.kind {
+            ScopeKind::Global | ScopeKind::Module | ScopeKind::Function => true,
+            _ => false,
+        }
+    }
+
+    /// Function scopes that bind their own `this` and `arguments`.
+    pub fn is_this_scope(&self) -> bool {
+        match self.kind {
+            ScopeKind::Global | ScopeKind::Module => true,
+            ScopeKind::Function => !self.is_arrow,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub name: String,
+    pub kind: DeclarationKind,
+    pub scope: ScopeId,
+    // NOTE: `var a; var a;` 会有多个声明位置
+    pub declarations: Vec<Loc>,
+    pub reads: Vec<ReferenceId>,
+    pub writes: Vec<ReferenceId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub loc: Loc,
+    pub span: Span,
+    pub kind: ReferenceKind,
+    pub scope: ScopeId,
+    // NOTE: `None` 表示全局变量（或者是函数内的 `arguments`）
+    pub binding: Option<BindingId>,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct ScopeTree {
+    scopes: Vec<Scope>,
+    bindings: Vec<Binding>,
+    references: Vec<Reference>,
+    free: Vec<ReferenceId>,
+    // NOTE: Identifier 的起始位置 => Binding
+    locations: HashMap<usize, BindingId>,
+}
+
+impl ScopeTree {
+    pub fn root(&self) -> ScopeId {
+        0
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id]
+    }
+
+    pub fn binding(&self, id: BindingId) -> &Binding {
+        &self.bindings[id]
+    }
+
+    pub fn reference(&self, id: ReferenceId) -> &Reference {
+        &self.references[id]
+    }
+
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+
+    /// References that resolve to no binding, ie. global variables.
+    pub fn free_references(&self) -> &[ReferenceId] {
+        &self.free
+    }
+
+    pub fn free_names(&self) -> Vec<&str> {
+        let mut names = self.free.iter()
+            .map(|id| self.references[*id].name.as_str())
+            .collect::<Vec<&str>>();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Look a name up starting from `scope`.
+    pub fn resolve(&self, scope: ScopeId, name: &str) -> Option<BindingId> {
+        let mut current = Some(scope);
+        while let Some(id) = current {
+            if let Some(binding) = self.scopes[id].bindings.get(name) {
+                return Some(*binding);
+            }
+            current = self.scopes[id].parent;
+        }
+
+        None
+    }
+
+    /// The binding declared or referenced by the identifier starting at `offset`.
+    pub fn binding_at(&self, offset: usize) -> Option<BindingId> {
+        self.locations.get(&offset).cloned()
+    }
+
+    /// Is `scope` ( or one of its ancestors ) a `with` scope ?
+    pub fn is_in_with(&self, scope: ScopeId) -> bool {
+        let mut current = Some(scope);
+        while let Some(id) = current {
+            if self.scopes[id].kind == ScopeKind::With {
+                return true;
+            }
+            current = self.scopes[id].parent;
+        }
+
+        false
+    }
+
+    /// Names in `scope` may be observed dynamically ( `eval` / `with` ), they must not be renamed.
+    pub fn is_dynamic(&self, scope: ScopeId) -> bool {
+        self.scopes[scope].has_direct_eval || self.is_in_with(scope)
+    }
+
+    /// The closest `var` scope.
+    pub fn var_scope(&self, scope: ScopeId) -> ScopeId {
+        let mut id = scope;
+        while !self.scopes[id].is_var_scope() {
+            id = self.scopes[id].parent.expect("Ooops ...");
+        }
+
+        id
+    }
+
+    /// The closest scope that binds `this`.
+    pub fn this_scope(&self, scope: ScopeId) -> ScopeId {
+        let mut id = scope;
+        while !self.scopes[id].is_this_scope() {
+            id = self.scopes[id].parent.expect("Ooops ...");
+        }
+
+        id
+    }
+}
+
+
+#[inline]
+fn ident_name(ident: &Identifier) -> String {
+    ident.cooked.unwrap_or(ident.raw).iter().collect::<String>()
+}
+
+#[inline]
+fn parenthesized_items<'a, 'ast>(expr: &'a ParenthesizedExpression<'ast>) -> &'a [Expression<'ast>] {
+    match expr.items {
+        [Expression::Comma(inner)] => inner.items,
+        items => items,
+    }
+}
+
+fn lexical_kind(stmt: &VariableStatement) -> DeclarationKind {
+    match stmt.kind {
+        LexicalDeclarationKind::Var => DeclarationKind::Var,
+        LexicalDeclarationKind::Let => DeclarationKind::Let,
+        LexicalDeclarationKind::Const => DeclarationKind::Const,
+    }
+}
+
+
+/// Build the scope tree of a Script or Module.
+pub fn analyze<'ast>(body: &[Statement<'ast>], goal: Goal) -> Result<ScopeTree, Error> {
+    let mut analyzer = ScopeAnalyzer::new(goal);
+    analyzer.program(body)?;
+    Ok(analyzer.tree)
+}
+
+
+pub struct ScopeAnalyzer {
+    tree: ScopeTree,
+    current: ScopeId,
+    goal: Goal,
+}
+
+impl ScopeAnalyzer {
+    pub fn new(goal: Goal) -> Self {
+        let tree = ScopeTree {
+            scopes: vec![ Scope::new(ScopeKind::Global, None, Loc::default()) ],
+            bindings: Vec::new(),
+            references: Vec::new(),
+            free: Vec::new(),
+            locations: HashMap::new(),
+        };
+
+        Self { tree, current: 0, goal }
+    }
+
+    pub fn into_tree(self) -> ScopeTree {
+        self.tree
+    }
+
+    pub fn current_scope(&self) -> ScopeId {
+        self.current
+    }
+
+    pub fn program<'ast>(&mut self, body: &[Statement<'ast>]) -> Result<(), Error> {
+        if self.goal == Goal::Module {
+            self.enter(ScopeKind::Module, Loc::default());
+        }
+
+        self.declare_hoisted(body)?;
+        self.declare_lexical(body)?;
+        for stmt in body.iter() {
+            self.statement(stmt)?;
+        }
+
+        if self.goal == Goal::Module {
+            self.exit();
+        }
+
+        Ok(())
+    }
+
+    /// Declare an import binding in the module scope, ie. `import { a as b } from "m";`
+    pub fn declare_import(&mut self, ident: &Identifier) -> Result<BindingId, Error> {
+        let scope = self.tree.var_scope(self.current);
+        self.declare(scope, ident, DeclarationKind::Import)
+    }
+
+    // ---------- scopes / bindings / references ----------
+
+    fn enter(&mut self, kind: ScopeKind, loc: Loc) -> ScopeId {
+        let id = self.tree.scopes.len();
+        self.tree.scopes.push(Scope::new(kind, Some(self.current), loc));
+        self.tree.scopes[self.current].children.push(id);
+        self.current = id;
+        id
+    }
+
+    fn exit(&mut self) {
+        self.current = self.tree.scopes[self.current].parent.expect("Ooops ...");
+    }
+
+    fn declare(&mut self, scope: ScopeId, ident: &Identifier, kind: DeclarationKind) -> Result<BindingId, Error> {
+        let name = ident_name(ident);
+
+        if let Some(id) = self.tree.scopes[scope].bindings.get(&name).cloned() {
+            let binding = &mut self.tree.bindings[id];
+            let redeclarable = binding.kind.is_var_like() && kind.is_var_like()
+                && self.tree.scopes[scope].is_var_scope();
+
+            if !redeclarable {
+                return Err(Error::new(ErrorKind::SyntaxError,
+                                      format!("Identifier '{}' has already been declared", name)));
+            }
+
+            if !binding.declarations.contains(&ident.loc) {
+                binding.declarations.push(ident.loc);
+            }
+            // NOTE: `var a; function a() {}` 最终是一个函数声明
+            if kind == DeclarationKind::Function {
+                binding.kind = kind;
+            }
+
+            self.tree.locations.insert(ident.loc.start, id);
+            return Ok(id);
+        }
+
+        let id = self.tree.bindings.len();
+        self.tree.bindings.push(Binding {
+            name: name.clone(),
+            kind: kind,
+            scope: scope,
+            declarations: vec![ ident.loc ],
+            reads: Vec::new(),
+            writes: Vec::new(),
+        });
+        self.tree.scopes[scope].bindings.insert(name, id);
+        self.tree.locations.insert(ident.loc.start, id);
+
+        Ok(id)
+    }
+
+    fn reference(&mut self, ident: &Identifier, kind: ReferenceKind) {
+        let name = ident_name(ident);
+        let scope = self.current;
+        let binding = self.tree.resolve(scope, &name);
+        let id = self.tree.references.len();
+
+        match binding {
+            Some(binding) => {
+                let binding_ref = &mut self.tree.bindings[binding];
+                if kind.is_read() {
+                    binding_ref.reads.push(id);
+                }
+                if kind.is_write() {
+                    binding_ref.writes.push(id);
+                }
+                self.tree.locations.insert(ident.loc.start, binding);
+            },
+            None => {
+                let this_scope = self.tree.this_scope(scope);
+                if name == "arguments" && self.tree.scopes[this_scope].kind == ScopeKind::Function {
+                    self.tree.scopes[this_scope].uses_arguments = true;
+                } else {
+                    self.tree.free.push(id);
+                }
+            },
+        }
+
+        self.tree.scopes[scope].references.push(id);
+        self.tree.references.push(Reference {
+            name,
+            loc: ident.loc,
+            span: ident.span,
+            kind,
+            scope,
+            binding,
+        });
+    }
+
+    fn mark_direct_eval(&mut self) {
+        let mut current = Some(self.current);
+        while let Some(id) = current {
+            self.tree.scopes[id].has_direct_eval = true;
+            current = self.tree.scopes[id].parent;
+        }
+    }
+
+    // ---------- declarations ----------
+
+    /// `var` and top level function declarations, hoisted to the current var scope.
+    fn declare_hoisted<'ast>(&mut self, body: &[Statement<'ast>]) -> Result<(), Error> {
+        for stmt in body.iter() {
+            self.hoist_var(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    fn hoist_var<'ast>(&mut self, stmt: &Statement<'ast>) -> Result<(), Error> {
+        match *stmt {
+            Statement::Variable(inner) => {
+                if inner.is_var() {
+                    let scope = self.tree.var_scope(self.current);
+                    for declarator in inner.declarators.iter() {
+                        self.declare_pattern(scope, &declarator.name, DeclarationKind::Var)?;
+                    }
+                }
+                Ok(())
+            },
+            Statement::Block(inner) => self.declare_hoisted(inner.body),
+            Statement::If(inner) => {
+                self.hoist_var(&inner.and_then)?;
+                self.hoist_var(&inner.or_else)
+            },
+            Statement::DoWhile(inner) => self.hoist_var(&inner.body),
+            Statement::While(inner) => self.hoist_var(&inner.body),
+            Statement::For(inner) => {
+                if let Some(ref init) = inner.init {
+                    self.hoist_var(init)?;
+                }
+                self.hoist_var(&inner.body)
+            },
+            Statement::ForIn(inner) => self.hoist_var(&inner.body),
+            Statement::ForOf(inner) => self.hoist_var(&inner.body),
+            Statement::ForAwaitOf(inner) => self.hoist_var(&inner.body),
+            Statement::With(inner) => self.hoist_var(&inner.then),
+            Statement::Switch(inner) => {
+                for clause in inner.clauses.iter() {
+                    self.hoist_var(&clause.body)?;
+                }
+                Ok(())
+            },
+            Statement::Labelled(inner) => self.hoist_var(&inner.item),
+            Statement::Try(inner) => {
+                self.declare_hoisted(inner.body.body)?;
+                if let Some(ref catch_body) = inner.catch_body {
+                    self.declare_hoisted(catch_body.body)?;
+                }
+                if let Some(ref finally) = inner.finally {
+                    self.declare_hoisted(finally.body)?;
+                }
+                Ok(())
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// let/const/class declarations, plus function declarations.
+    ///
+    /// Functions nested in blocks are block scoped, they can not be redeclared there.
+    fn declare_lexical<'ast>(&mut self, body: &[Statement<'ast>]) -> Result<(), Error> {
+        let scope = self.current;
+
+        for stmt in body.iter() {
+            match *stmt {
+                Statement::Variable(inner) if !inner.is_var() => {
+                    let kind = lexical_kind(inner);
+                    for declarator in inner.declarators.iter() {
+                        self.declare_pattern(scope, &declarator.name, kind)?;
+                    }
+                },
+                Statement::Function(inner) => {
+                    self.declare(scope, &inner.name, DeclarationKind::Function)?;
+                },
+                Statement::Class(inner) => {
+                    self.declare(scope, &inner.name, DeclarationKind::Class)?;
+                },
+                _ => { },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn declare_pattern<'ast>(&mut self, scope: ScopeId, target: &Expression<'ast>, kind: DeclarationKind) -> Result<(), Error> {
+        let mut names = Vec::new();
+        collect_bound_names(target, &mut names);
+        for ident in names.iter() {
+            self.declare(scope, ident, kind)?;
+        }
+
+        Ok(())
+    }
+
+    // ---------- statements ----------
+
+    fn statement<'ast>(&mut self, stmt: &Statement<'ast>) -> Result<(), Error> {
+        match *stmt {
+            Statement::Empty(_) | Statement::Debugger(_) => Ok(()),
+            Statement::Continue(_) | Statement::Break(_) => Ok(()),
+            Statement::Expression(inner) => self.expression(inner),
+            Statement::Variable(inner) => {
+                for declarator in inner.declarators.iter() {
+                    if let Some(ref init) = declarator.initializer {
+                        self.expression(init)?;
+                        self.binding_target(&declarator.name, true)?;
+                    } else {
+                        self.binding_target(&declarator.name, false)?;
+                    }
+                }
+                Ok(())
+            },
+            Statement::Function(inner) => {
+                let params = parenthesized_items(&inner.func.params);
+                self.function(None, params, FunctionBodyKind::Body(inner.func.body), false, inner.func.loc)
+            },
+            Statement::Class(inner) => self.class(None, &inner.class),
+            Statement::Block(inner) => self.block(inner.body, inner.loc),
+            Statement::If(inner) => {
+                self.expression(&inner.condition)?;
+                self.statement(&inner.and_then)?;
+                self.statement(&inner.or_else)
+            },
+            Statement::DoWhile(inner) => {
+                self.statement(&inner.body)?;
+                self.expression(&inner.condition)
+            },
+            Statement::While(inner) => {
+                self.expression(&inner.condition)?;
+                self.statement(&inner.body)
+            },
+            Statement::For(inner) => {
+                self.enter(ScopeKind::Block, inner.loc);
+                if let Some(ref init) = inner.init {
+                    self.declare_lexical(::std::slice::from_ref(init))?;
+                    self.statement(init)?;
+                }
+                if let Some(ref condition) = inner.condition {
+                    self.expression(condition)?;
+                }
+                if let Some(ref finally) = inner.finally {
+                    self.expression(finally)?;
+                }
+                self.statement(&inner.body)?;
+                self.exit();
+                Ok(())
+            },
+            Statement::ForIn(inner) => {
+                self.expression(&inner.right)?;
+                self.assignment_target(&inner.left, ReferenceKind::Write)?;
+                self.statement(&inner.body)
+            },
+            Statement::ForOf(inner) => {
+                self.expression(&inner.right)?;
+                self.assignment_target(&inner.left, ReferenceKind::Write)?;
+                self.statement(&inner.body)
+            },
+            Statement::ForAwaitOf(inner) => {
+                self.expression(&inner.right)?;
+                self.assignment_target(&inner.left, ReferenceKind::Write)?;
+                self.statement(&inner.body)
+            },
+            Statement::Return(inner) => match inner.value {
+                Some(ref value) => self.expression(value),
+                None => Ok(()),
+            },
+            Statement::With(inner) => {
+                self.expression(&inner.condition)?;
+                self.enter(ScopeKind::With, inner.loc);
+                self.statement(&inner.then)?;
+                self.exit();
+                Ok(())
+            },
+            Statement::Switch(inner) => {
+                self.expression(&inner.value)?;
+                self.enter(ScopeKind::Block, inner.loc);
+
+                // NOTE: 所有 case 子句共享同一个词法作用域
+                let mut body = Vec::new();
+                for clause in inner.clauses.iter() {
+                    match clause.body {
+                        Statement::Block(block) => body.extend_from_slice(block.body),
+                        stmt => body.push(stmt),
+                    }
+                }
+                self.declare_lexical(&body)?;
+
+                for clause in inner.clauses.iter() {
+                    if let Some(ref value) = clause.value {
+                        self.expression(value)?;
+                    }
+                    match clause.body {
+                        Statement::Block(block) => for stmt in block.body.iter() {
+                            self.statement(stmt)?;
+                        },
+                        ref stmt => self.statement(stmt)?,
+                    }
+                }
+
+                self.exit();
+                Ok(())
+            },
+            Statement::Labelled(inner) => self.statement(&inner.item),
+            Statement::Throw(inner) => self.expression(&inner.value),
+            Statement::Try(inner) => {
+                self.block(inner.body.body, inner.body.loc)?;
+
+                if let Some(ref catch_body) = inner.catch_body {
+                    let scope = self.enter(ScopeKind::Catch, catch_body.loc);
+                    if let Some(ref param) = inner.catch_parameter {
+                        self.declare_pattern(scope, param, DeclarationKind::Param)?;
+                        self.binding_target(param, false)?;
+                    }
+                    self.declare_lexical(catch_body.body)?;
+                    for stmt in catch_body.body.iter() {
+                        self.statement(stmt)?;
+                    }
+                    self.exit();
+                }
+
+                if let Some(ref finally) = inner.finally {
+                    self.block(finally.body, finally.loc)?;
+                }
+
+                Ok(())
+            },
+        }
+    }
+
+    fn block<'ast>(&mut self, body: &[Statement<'ast>], loc: Loc) -> Result<(), Error> {
+        self.enter(ScopeKind::Block, loc);
+        self.declare_lexical(body)?;
+        for stmt in body.iter() {
+            self.statement(stmt)?;
+        }
+        self.exit();
+
+        Ok(())
+    }
+
+    fn function<'ast>(&mut self,
+                      name: Option<&Identifier<'ast>>,
+                      params: &[Expression<'ast>],
+                      body: FunctionBodyKind<'ast>,
+                      is_arrow: bool,
+                      loc: Loc) -> Result<(), Error> {
+        let scope = self.enter(ScopeKind::Function, loc);
+        self.tree.scopes[scope].is_arrow = is_arrow;
+
+        if let Some(name) = name {
+            self.declare(scope, name, DeclarationKind::Function)?;
+        }
+
+        for param in params.iter() {
+            self.declare_pattern(scope, param, DeclarationKind::Param)?;
+        }
+
+        match body {
+            FunctionBodyKind::Body(body) => {
+                self.declare_hoisted(body)?;
+                self.declare_lexical(body)?;
+                for param in params.iter() {
+                    self.binding_target(param, false)?;
+                }
+                for stmt in body.iter() {
+                    self.statement(stmt)?;
+                }
+            },
+            FunctionBodyKind::Expr(expr) => {
+                for param in params.iter() {
+                    self.binding_target(param, false)?;
+                }
+                self.expression(&expr)?;
+            },
+        }
+
+        self.exit();
+        Ok(())
+    }
+
+    fn class<'ast>(&mut self, name: Option<&Identifier<'ast>>, class: &Class<'ast>) -> Result<(), Error> {
+        let scope = self.enter(ScopeKind::Class, class.loc);
+        if let Some(name) = name {
+            self.declare(scope, name, DeclarationKind::Class)?;
+        }
+
+        if let Some(ref heritage) = class.heritage {
+            self.expression(heritage)?;
+        }
+
+        for method in class.body.iter() {
+            self.method(&method.method)?;
+        }
+
+        self.exit();
+        Ok(())
+    }
+
+    fn method_name<'ast>(&mut self, name: &Expression<'ast>) -> Result<(), Error> {
+        match *name {
+            // NOTE: 非计算属性名
+            Expression::Identifier(_) | Expression::String(_) | Expression::Numeric(_) => Ok(()),
+            _ => self.expression(name),
+        }
+    }
+
+    fn method<'ast>(&mut self, method: &MethodDefinition<'ast>) -> Result<(), Error> {
+        match *method {
+            MethodDefinition::Method(ref inner) => {
+                self.method_name(&inner.name)?;
+                let params = parenthesized_items(&inner.params);
+                self.function(None, params, FunctionBodyKind::Body(inner.body), false, inner.loc)
+            },
+            MethodDefinition::Getter(ref inner) => {
+                self.method_name(&inner.name)?;
+                self.function(None, &[], FunctionBodyKind::Body(inner.body), false, inner.loc)
+            },
+            MethodDefinition::Setter(ref inner) => {
+                self.method_name(&inner.name)?;
+                let params = parenthesized_items(&inner.params);
+                self.function(None, params, FunctionBodyKind::Body(inner.body), false, inner.loc)
+            },
+        }
+    }
+
+    // ---------- patterns ----------
+
+    /// Visit a declaration target whose names are already declared,
+    /// records a write for each name when the declaration has an initializer.
+    fn binding_target<'ast>(&mut self, target: &Expression<'ast>, is_initialized: bool) -> Result<(), Error> {
+        let mut names = Vec::new();
+        let mut exprs = Vec::new();
+        collect_pattern(target, &mut names, &mut exprs);
+
+        for expr in exprs.iter() {
+            self.expression(expr)?;
+        }
+
+        // NOTE: 带有默认值的参数同样视为写入
+        let is_initialized = is_initialized || !exprs.is_empty();
+        if is_initialized {
+            for ident in names.iter() {
+                self.reference(ident, ReferenceKind::Write);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn assignment_target<'ast>(&mut self, target: &Expression<'ast>, kind: ReferenceKind) -> Result<(), Error> {
+        match *target {
+            Expression::Identifier(ident) => {
+                self.reference(ident, kind);
+                Ok(())
+            },
+            Expression::Member(_) => self.expression(target),
+            Expression::Parenthesized(inner) => {
+                for item in inner.items.iter() {
+                    self.assignment_target(item, kind)?;
+                }
+                Ok(())
+            },
+            Expression::Comma(inner) => {
+                for item in inner.items.iter() {
+                    self.assignment_target(item, kind)?;
+                }
+                Ok(())
+            },
+            Expression::Spread(inner) => self.assignment_target(&inner.item, kind),
+            Expression::Assignment(inner) => {
+                // NOTE: `[a = 1] = b` 中的默认值
+                self.expression(&inner.right)?;
+                self.assignment_target(&inner.left, kind)
+            },
+            Expression::ArrayLiteral(inner) => {
+                for elem in inner.elems.iter() {
+                    if let Some(ref elem) = *elem {
+                        self.assignment_target(elem, kind)?;
+                    }
+                }
+                Ok(())
+            },
+            Expression::ObjectLiteral(inner) => {
+                for prop in inner.properties.iter() {
+                    match *prop {
+                        ObjectProperty::Identifier(ref ident) => self.reference(ident, kind),
+                        ObjectProperty::Property { ref name, ref value, .. } => {
+                            self.property_name(name)?;
+                            self.assignment_target(value, kind)?;
+                        },
+                        ObjectProperty::Spread { ref target, .. } => self.assignment_target(target, kind)?,
+                        ObjectProperty::MethodDefinition(_) => { },
+                    }
+                }
+                Ok(())
+            },
+            Expression::AssignmentPattern(pattern) => {
+                match *pattern {
+                    AssignmentPattern::Array(ref inner) => {
+                        for elem in inner.elems.iter() {
+                            if let Some(ref elem) = *elem {
+                                if let Some(ref init) = elem.init {
+                                    self.expression(init)?;
+                                }
+                                self.assignment_target(&elem.elem, kind)?;
+                            }
+                        }
+                        if let Some(ref rest) = inner.rest_elem {
+                            self.assignment_target(rest, kind)?;
+                        }
+                    },
+                    AssignmentPattern::Object(ref inner) => {
+                        for prop in inner.properties.iter() {
+                            match *prop {
+                                AssignmentProperty::Identifier { ref name, ref init, .. } => {
+                                    if let Some(ref init) = *init {
+                                        self.expression(init)?;
+                                    }
+                                    self.reference(name, kind);
+                                },
+                                AssignmentProperty::Property { ref name, ref value, .. } => {
+                                    self.property_name(name)?;
+                                    if let Some(ref init) = value.init {
+                                        self.expression(init)?;
+                                    }
+                                    self.assignment_target(&value.elem, kind)?;
+                                },
+                                AssignmentProperty::Spread { ref target, .. } => {
+                                    self.assignment_target(target, kind)?;
+                                },
+                            }
+                        }
+                    },
+                }
+                Ok(())
+            },
+            Expression::BindingPattern(_) => {
+                let mut names = Vec::new();
+                let mut exprs = Vec::new();
+                collect_pattern(target, &mut names, &mut exprs);
+                for expr in exprs.iter() {
+                    self.expression(expr)?;
+                }
+                for ident in names.iter() {
+                    self.reference(ident, kind);
+                }
+                Ok(())
+            },
+            _ => self.expression(target),
+        }
+    }
+
+    fn property_name<'ast>(&mut self, name: &PropertyName<'ast>) -> Result<(), Error> {
+        match *name {
+            PropertyName::Computed(ref expr) => self.expression(expr),
+            _ => Ok(()),
+        }
+    }
+
+    // ---------- expressions ----------
+
+    fn expressions<'ast>(&mut self, exprs: &[Expression<'ast>]) -> Result<(), Error> {
+        for expr in exprs.iter() {
+            self.expression(expr)?;
+        }
+
+        Ok(())
+    }
+
+    fn template<'ast>(&mut self, template: &LiteralTemplateExpression<'ast>) -> Result<(), Error> {
+        self.expressions(template.bounds)
+    }
+
+    fn expression<'ast>(&mut self, expr: &Expression<'ast>) -> Result<(), Error> {
+        match *expr {
+            Expression::Identifier(ident) => {
+                self.reference(ident, ReferenceKind::Read);
+                Ok(())
+            },
+            Expression::This(_) => {
+                let scope = self.tree.this_scope(self.current);
+                self.tree.scopes[scope].uses_this = true;
+                Ok(())
+            },
+            Expression::Super(_)
+            | Expression::Null(_)
+            | Expression::Boolean(_)
+            | Expression::String(_)
+            | Expression::Numeric(_)
+            | Expression::RegularExpression(_)
+            | Expression::NewTarget(_) => Ok(()),
+            Expression::Template(inner) => self.template(inner),
+            Expression::TaggedTemplate(inner) => {
+                self.expression(&inner.tag)?;
+                self.template(&inner.template)
+            },
+            Expression::Spread(inner) => self.expression(&inner.item),
+            Expression::ArrayLiteral(inner) => {
+                for elem in inner.elems.iter() {
+                    if let Some(ref elem) = *elem {
+                        self.expression(elem)?;
+                    }
+                }
+                Ok(())
+            },
+            Expression::ObjectLiteral(inner) => {
+                for prop in inner.properties.iter() {
+                    match *prop {
+                        ObjectProperty::Identifier(ref ident) => self.reference(ident, ReferenceKind::Read),
+                        ObjectProperty::Property { ref name, ref value, .. } => {
+                            self.property_name(name)?;
+                            self.expression(value)?;
+                        },
+                        ObjectProperty::MethodDefinition(ref method) => self.method(method)?,
+                        ObjectProperty::Spread { ref target, .. } => self.expression(target)?,
+                    }
+                }
+                Ok(())
+            },
+            Expression::Function(inner) => {
+                let params = parenthesized_items(&inner.func.params);
+                self.function(inner.name.as_ref(), params, FunctionBodyKind::Body(inner.func.body), false, inner.func.loc)
+            },
+            Expression::ArrowFunction(inner) => {
+                let params = match inner.params {
+                    Expression::Parenthesized(ref params) => parenthesized_items(params),
+                    ref params => ::std::slice::from_ref(params),
+                };
+                let body = match inner.body {
+                    ConciseBody::Expr(expr) => FunctionBodyKind::Expr(expr),
+                    ConciseBody::Stmt(body) => FunctionBodyKind::Body(body),
+                };
+                self.function(None, params, body, true, inner.loc)
+            },
+            Expression::Class(inner) => self.class(inner.name.as_ref(), &inner.class),
+            Expression::Parenthesized(inner) => self.expressions(inner.items),
+            Expression::Comma(inner) => self.expressions(inner.items),
+            Expression::Member(inner) => {
+                self.expression(&inner.left)?;
+                if inner.computed {
+                    self.expression(&inner.right)?;
+                }
+                Ok(())
+            },
+            Expression::Call(inner) => {
+                if let Expression::Identifier(ident) = inner.callee {
+                    if ident_name(ident) == "eval" {
+                        self.mark_direct_eval();
+                    }
+                }
+                self.expression(&inner.callee)?;
+                self.expressions(inner.arguments.items)
+            },
+            Expression::New(inner) => {
+                self.expression(&inner.callee)?;
+                match inner.arguments {
+                    Some(ref arguments) => self.expressions(arguments.items),
+                    None => Ok(()),
+                }
+            },
+            Expression::Prefix(inner) => match inner.operator {
+                PrefixOperator::Increment | PrefixOperator::Decrement => {
+                    self.assignment_target(&inner.operand, ReferenceKind::ReadWrite)
+                },
+                _ => self.expression(&inner.operand),
+            },
+            Expression::Postfix(inner) => self.assignment_target(&inner.operand, ReferenceKind::ReadWrite),
+            Expression::Infix(inner) => {
+                self.expression(&inner.left)?;
+                self.expression(&inner.right)
+            },
+            Expression::Assignment(inner) => {
+                let kind = match inner.operator {
+                    AssignmentOperator::Assign => ReferenceKind::Write,
+                    _ => ReferenceKind::ReadWrite,
+                };
+                self.expression(&inner.right)?;
+                self.assignment_target(&inner.left, kind)
+            },
+            Expression::Conditional(inner) => {
+                self.expression(&inner.condition)?;
+                self.expression(&inner.and_then)?;
+                self.expression(&inner.or_else)
+            },
+            Expression::Yield(inner) => self.expression(&inner.item),
+            Expression::AssignmentPattern(_)
+            | Expression::BindingPattern(_) => self.assignment_target(expr, ReferenceKind::Write),
+            Expression::JSXFragment(inner) => self.jsx_children(&inner.children),
+            Expression::JSXElement(inner) => self.jsx_element(inner),
+        }
+    }
+
+    // ---------- JSX ----------
+
+    fn jsx_element_name<'ast>(&mut self, name: &JSXElementName<'ast>) {
+        // NOTE: 小写开头的标签名是内置元素（ `<div>` ），不是变量引用。
+        match *name {
+            JSXElementName::Identifier(ref ident) => {
+                match ident.raw.first() {
+                    Some(c) if c.is_lowercase() => { },
+                    _ => self.reference(ident, ReferenceKind::Read),
+                }
+            },
+            JSXElementName::MemberExpression(idents) => {
+                if let Some(ident) = idents.first() {
+                    self.reference(ident, ReferenceKind::Read);
+                }
+            },
+            JSXElementName::NamespacedName(_) => { },
+        }
+    }
+
+    fn jsx_attributes<'ast>(&mut self, attrs: &Option<JSXAttributes<'ast>>) -> Result<(), Error> {
+        let attrs = match *attrs {
+            Some(attrs) => attrs,
+            None => return Ok(()),
+        };
+
+        for attr in attrs.iter() {
+            match *attr {
+                JSXAttribute::Spread(ref expr) => self.expression(expr)?,
+                JSXAttribute::Normal(ref attr) => match attr.init {
+                    Some(JSXNormalAttributeInitializer::Assignment(ref expr)) => self.expression(expr)?,
+                    Some(JSXNormalAttributeInitializer::Element(ref elem)) => self.jsx_element(elem)?,
+                    Some(JSXNormalAttributeInitializer::Fragment(ref frag)) => self.jsx_children(&frag.children)?,
+                    _ => { },
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn jsx_children<'ast>(&mut self, children: &Option<JSXChildren<'ast>>) -> Result<(), Error> {
+        let children = match *children {
+            Some(children) => children,
+            None => return Ok(()),
+        };
+
+        for child in children.iter() {
+            match *child {
+                JSXChild::Text(_) => { },
+                JSXChild::Element(ref elem) => self.jsx_element(elem)?,
+                JSXChild::ChildExpression(ref exprs) => {
+                    if let Some(exprs) = *exprs {
+                        self.expressions(exprs)?;
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn jsx_element<'ast>(&mut self, elem: &JSXElement<'ast>) -> Result<(), Error> {
+        match *elem {
+            JSXElement::SelfClosing(ref inner) => {
+                self.jsx_element_name(&inner.name);
+                self.jsx_attributes(&inner.attrs)
+            },
+            JSXElement::Normal(ref inner) => {
+                self.jsx_element_name(&inner.opening.name);
+                self.jsx_attributes(&inner.opening.attrs)?;
+                self.jsx_children(&inner.children)
+            },
+        }
+    }
+}
+
+
+#[derive(Clone, Copy)]
+enum FunctionBodyKind<'ast> {
+    Body(FunctionBody<'ast>),
+    Expr(Expression<'ast>),
+}
+
+
+/// BoundNames of a binding target, ie. `a`, `[a, b = 1]`, `{ a, b: [c] }`, `...rest`.
+pub fn collect_bound_names<'ast>(target: &Expression<'ast>, names: &mut Vec<Identifier<'ast>>) {
+    let mut exprs = Vec::new();
+    collect_pattern(target, names, &mut exprs);
+}
+
+/// Collect the names bound by a target, and the expressions ( defaults, computed keys ) it evaluates.
+fn collect_pattern<'ast>(target: &Expression<'ast>, names: &mut Vec<Identifier<'ast>>, exprs: &mut Vec<Expression<'ast>>) {
+    match *target {
+        Expression::Identifier(ident) => names.push(*ident),
+        Expression::Assignment(inner) => {
+            collect_pattern(&inner.left, names, exprs);
+            exprs.push(inner.right);
+        },
+        Expression::Spread(inner) => collect_pattern(&inner.item, names, exprs),
+        Expression::Parenthesized(inner) => for item in inner.items.iter() {
+            collect_pattern(item, names, exprs);
+        },
+        Expression::Comma(inner) => for item in inner.items.iter() {
+            collect_pattern(item, names, exprs);
+        },
+        Expression::ArrayLiteral(inner) => for elem in inner.elems.iter() {
+            if let Some(ref elem) = *elem {
+                collect_pattern(elem, names, exprs);
+            }
+        },
+        Expression::ObjectLiteral(inner) => for prop in inner.properties.iter() {
+            match *prop {
+                ObjectProperty::Identifier(ident) => names.push(ident),
+                ObjectProperty::Property { ref name, ref value, .. } => {
+                    if let PropertyName::Computed(expr) = *name {
+                        exprs.push(expr);
+                    }
+                    collect_pattern(value, names, exprs);
+                },
+                ObjectProperty::Spread { ref target, .. } => collect_pattern(target, names, exprs),
+                ObjectProperty::MethodDefinition(_) => { },
+            }
+        },
+        Expression::BindingPattern(pattern) => collect_binding_pattern(pattern, names, exprs),
+        Expression::AssignmentPattern(pattern) => match *pattern {
+            AssignmentPattern::Array(ref inner) => {
+                for elem in inner.elems.iter() {
+                    if let Some(ref elem) = *elem {
+                        collect_pattern(&elem.elem, names, exprs);
+                        if let Some(init) = elem.init {
+                            exprs.push(init);
+                        }
+                    }
+                }
+                if let Some(ref rest) = inner.rest_elem {
+                    collect_pattern(rest, names, exprs);
+                }
+            },
+            AssignmentPattern::Object(ref inner) => {
+                for prop in inner.properties.iter() {
+                    match *prop {
+                        AssignmentProperty::Identifier { name, init, .. } => {
+                            names.push(name);
+                            if let Some(init) = init {
+                                exprs.push(init);
+                            }
+                        },
+                        AssignmentProperty::Property { ref name, ref value, .. } => {
+                            if let PropertyName::Computed(expr) = *name {
+                                exprs.push(expr);
+                            }
+                            collect_pattern(&value.elem, names, exprs);
+                            if let Some(init) = value.init {
+                                exprs.push(init);
+                            }
+                        },
+                        AssignmentProperty::Spread { ref target, .. } => collect_pattern(target, names, exprs),
+                    }
+                }
+            },
+        },
+        _ => { },
+    }
+}
+
+fn collect_binding_pattern<'ast>(pattern: &BindingPattern<'ast>, names: &mut Vec<Identifier<'ast>>, exprs: &mut Vec<Expression<'ast>>) {
+    match *pattern {
+        BindingPattern::Array(ref inner) => {
+            for elem in inner.elems.iter() {
+                if let Some(ref elem) = *elem {
+                    collect_binding_element(elem, names, exprs);
+                }
+            }
+            match inner.rest_elem {
+                Some(BindingRestElement::Identifier(ident)) => names.push(*ident),
+                Some(BindingRestElement::BindingPattern(pattern)) => collect_binding_pattern(pattern, names, exprs),
+                None => { },
+            }
+        },
+        BindingPattern::Object(ref inner) => {
+            for prop in inner.properties.iter() {
+                match *prop {
+                    BindingProperty::SingleNameBinding { name, init, .. } => {
+                        names.push(name);
+                        if let Some(init) = init {
+                            exprs.push(init);
+                        }
+                    },
+                    BindingProperty::Property { ref name, ref value, .. } => {
+                        if let PropertyName::Computed(expr) = *name {
+                            exprs.push(expr);
+                        }
+                        collect_binding_element(value, names, exprs);
+                    },
+                    BindingProperty::Spread { name, .. } => names.push(name),
+                }
+            }
+        },
+    }
+}
+
+fn collect_binding_element<'ast>(elem: &BindingElement<'ast>, names: &mut Vec<Identifier<'ast>>, exprs: &mut Vec<Expression<'ast>>) {
+    match *elem {
+        BindingElement::SingleNameBinding { name, init, .. } => {
+            names.push(name);
+            if let Some(init) = init {
+                exprs.push(init);
+            }
+        },
+        BindingElement::BindingPattern { ref pattern, init, .. } => {
+            collect_binding_pattern(pattern, names, exprs);
+            if let Some(init) = init {
+                exprs.push(init);
+            }
+        },
+    }
+}
+
+
+#[cfg(test)]
+fn parse_and_analyze(source: &str) -> Result<ScopeTree, Error> {
+    use crate::toolshed::Arena;
+    use crate::parser::Parser;
+
+    let arena = Arena::new();
+    let code = arena.alloc_vec(source.chars().collect::<Vec<char>>());
+    let mut parser = Parser::new(&arena, &code, "main.js");
+    parser.parse().unwrap();
+
+    analyze(&parser.body, Goal::Script)
+}
+
+#[cfg(test)]
+fn variable_statement<'ast>(arena: &'ast crate::toolshed::Arena,
+                            kind: LexicalDeclarationKind,
+                            name: &str,
+                            offset: usize) -> Statement<'ast> {
+    use crate::ast::statement::LexicalBinding;
+
+    let raw = arena.alloc_vec(name.chars().collect::<Vec<char>>());
+    let loc = Loc { start: offset, end: offset + name.len() };
+    let ident = Identifier { loc, span: Span::default(), raw, cooked: None };
+    let declarators = arena.alloc_vec(vec![
+        LexicalBinding { loc, span: Span::default(), name: Expression::Identifier(arena.alloc(ident)), initializer: None }
+    ]);
+
+    Statement::Variable(arena.alloc(VariableStatement { loc, span: Span::default(), kind, declarators }))
+}
+
+#[cfg(test)]
+fn identifier_statement<'ast>(arena: &'ast crate::toolshed::Arena, name: &str, offset: usize) -> Statement<'ast> {
+    let raw = arena.alloc_vec(name.chars().collect::<Vec<char>>());
+    let loc = Loc { start: offset, end: offset + name.len() };
+    let ident = Identifier { loc, span: Span::default(), raw, cooked: None };
+
+    Statement::Expression(arena.alloc(Expression::Identifier(arena.alloc(ident))))
+}
+
+#[test]
+fn test_resolve_function() {
+    let tree = parse_and_analyze("function f(a, b) { a; c = b; }").ok().unwrap();
+
+    let global = tree.scope(tree.root());
+    assert_eq!(global.kind, ScopeKind::Global);
+    let f = tree.binding(global.bindings["f"]);
+    assert_eq!(f.kind, DeclarationKind::Function);
+
+    let scope = tree.scope(global.children[0]);
+    assert_eq!(scope.kind, ScopeKind::Function);
+    let a = tree.binding(scope.bindings["a"]);
+    let b = tree.binding(scope.bindings["b"]);
+    assert_eq!(a.kind, DeclarationKind::Param);
+    assert_eq!((a.reads.len(), a.writes.len()), (1, 0));
+    assert_eq!((b.reads.len(), b.writes.len()), (1, 0));
+
+    assert_eq!(tree.free_names(), vec!["c"]);
+    let c = tree.reference(tree.free_references()[0]);
+    assert_eq!((c.kind, c.binding), (ReferenceKind::Write, None));
+}
+
+#[test]
+fn test_this_arguments_eval() {
+    let tree = parse_and_analyze("function f() { () => this; arguments; }").ok().unwrap();
+    let f = tree.scope(1);
+    assert!(f.uses_this && f.uses_arguments);
+    assert!(tree.scope(2).is_arrow);
+    assert!(tree.free_references().is_empty());
+
+    let tree = parse_and_analyze("function f() { eval(s); }").ok().unwrap();
+    assert!(tree.scope(1).has_direct_eval);
+    assert!(tree.is_dynamic(tree.root()));
+}
+
+#[test]
+fn test_hoisting_and_block_scope() {
+    use crate::toolshed::Arena;
+    use crate::ast::statement::BlockStatement;
+
+    let arena = Arena::new();
+
+    // x; { let x; x; } var x;
+    let block = BlockStatement {
+        loc: Loc { start: 3, end: 20 },
+        span: Span::default(),
+        body: arena.alloc_vec(vec![
+            variable_statement(&arena, LexicalDeclarationKind::Let, "x", 9),
+            identifier_statement(&arena, "x", 12),
+        ]),
+    };
+    let body = vec![
+        identifier_statement(&arena, "x", 0),
+        Statement::Block(arena.alloc(block)),
+        variable_statement(&arena, LexicalDeclarationKind::Var, "x", 25),
+    ];
+    assert!(body[2].is_hoistable());
+    assert!(!body[2].is_hoistable_declaration());
+
+    let tree = analyze(&body, Goal::Module).ok().unwrap();
+    let module = tree.scope(1);
+    assert_eq!(module.kind, ScopeKind::Module);
+    let var_x = module.bindings["x"];
+    let let_x = tree.scope(module.children[0]).bindings["x"];
+    assert_ne!(var_x, let_x);
+    assert_eq!(tree.binding_at(0), Some(var_x));
+    assert_eq!(tree.binding_at(12), Some(let_x));
+    assert_eq!(tree.binding(let_x).kind, DeclarationKind::Let);
+    assert!(tree.free_references().is_empty());
+
+    // var x; var x;
+    let body = vec![
+        variable_statement(&arena, LexicalDeclarationKind::Var, "x", 0),
+        variable_statement(&arena, LexicalDeclarationKind::Var, "x", 7),
+    ];
+    let tree = analyze(&body, Goal::Script).ok().unwrap();
+    assert_eq!(tree.bindings().len(), 1);
+    assert_eq!(tree.binding(0).declarations.len(), 2);
+
+    // let x; var x;
+    let body = vec![
+        variable_statement(&arena, LexicalDeclarationKind::Let, "x", 0),
+        variable_statement(&arena, LexicalDeclarationKind::Var, "x", 7),
+    ];
+    assert!(analyze(&body, Goal::Script).is_err());
+}
@@ -2,8 +2,11 @@ use crate::vlq;
 use crate::serde::ser::{ Serialize, SerializeStruct, Serializer, };
 
 use crate::lexer::span::{ Loc, Span, LineColumn, };
+use crate::error::{ ErrorKind, Error, };
 
 use std::io::{ Write, Cursor, };
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::path::{ Path, PathBuf, };
 
 // Source Map Revision 3 Proposal
@@ -11,7 +14,8 @@ use std::path::{ Path, PathBuf, };
 //      http://www.ruanyifeng.com/blog/2013/01/javascript_source_map.html
 // 
 // Tail
-// @ sourceMappingURL=/path/to/file.js.map
+// # sourceMappingURL=/path/to/file.js.map
+// # sourceMappingURL=data:application/json;charset=utf-8;base64,...
 // 
 // JSON Format:
 // {
@@ -38,7 +42,11 @@ const VERSION: u8      = 3u8;
 const COMMA: &[u8]     = b",";
 const SEMICOLON: &[u8] = b";";
 
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
 
+
+/// A single segment of the `mappings` field, all fields are absolute values.
+/// `SourceMap::add_pos` takes care of the delta encoding.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Position {
     pub dst_column: usize,          // A
@@ -50,37 +58,98 @@ pub struct Position {
 
 
 #[derive(Debug)]
-pub struct SourceMap<'a> {
+pub struct SourceMap {
     file: PathBuf,
     source_root: Option<PathBuf>,
     sources: Vec<PathBuf>,
-    sources_content: &'a [&'a str],
-    names: &'a [&'a [char]],
+    sources_content: Vec<Option<String>>,
+    names: Vec<String>,
+    names_index: HashMap<String, usize>,
     mappings: Cursor<Vec<u8>>,
+    // NOTE: 字段 A 在每一行开始时重置，其余字段相对于上一个 Segment 编码（跨行）。
+    prev_dst_column: usize,
+    prev_src_file_index: usize,
+    prev_src_line: usize,
+    prev_src_column: usize,
+    prev_ident_index: usize,
 }
 
-impl<'a> SourceMap<'a> {
-    
-
-    pub fn new<T>(dst_filepath: T,
-                  src_filepaths: Vec<PathBuf>,
-                  src_contents: &'a [&'a str],
-                  src_idents: &'a [&'a [char]],) -> Self
-    where
-        T: Into<PathBuf> 
-    {
+impl SourceMap {
+    pub fn new<T: Into<PathBuf>>(dst_filepath: T) -> Self {
         Self {
             file: dst_filepath.into(),
             source_root: None,
-            sources: src_filepaths,
-            sources_content: src_contents,
-            names: src_idents,
-            mappings: Cursor::new(Vec::with_capacity(src_idents.len() * 5 )),
+            sources: Vec::new(),
+            sources_content: Vec::new(),
+            names: Vec::new(),
+            names_index: HashMap::new(),
+            mappings: Cursor::new(Vec::new()),
+            prev_dst_column: 0,
+            prev_src_file_index: 0,
+            prev_src_line: 0,
+            prev_src_column: 0,
+            prev_ident_index: 0,
+        }
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    pub fn source_root(&self) -> Option<&Path> {
+        self.source_root.as_ref().map(|root| root.as_path())
+    }
+
+    pub fn set_source_root<T: Into<PathBuf>>(&mut self, source_root: T) {
+        self.source_root = Some(source_root.into());
+    }
+
+    pub fn sources(&self) -> &[PathBuf] {
+        &self.sources
+    }
+
+    pub fn sources_content(&self) -> &[Option<String>] {
+        &self.sources_content
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn mappings(&self) -> &[u8] {
+        self.mappings.get_ref()
+    }
+
+    /// Register a source file, returns its index in `sources`.
+    pub fn add_source<T: Into<PathBuf>>(&mut self, src_filepath: T, src_content: Option<String>) -> usize {
+        let src_filepath = src_filepath.into();
+
+        match self.sources.iter().position(|path| path == &src_filepath) {
+            Some(index) => index,
+            None => {
+                self.sources.push(src_filepath);
+                self.sources_content.push(src_content);
+                self.sources.len() - 1
+            }
+        }
+    }
+
+    /// Register an original identifier name, returns its index in `names`.
+    pub fn add_name(&mut self, name: &str) -> usize {
+        if let Some(index) = self.names_index.get(name) {
+            return *index;
         }
+
+        let index = self.names.len();
+        self.names.push(name.to_string());
+        self.names_index.insert(name.to_string(), index);
+
+        index
     }
 
     pub fn add_line(&mut self) {
         self.mappings.write(SEMICOLON);
+        self.prev_dst_column = 0;
     }
 
     pub fn add_pos(&mut self, pos: Position) {
@@ -93,25 +162,413 @@ impl<'a> SourceMap<'a> {
             self.mappings.write(COMMA);
         }
         
-        vlq::encode(pos.dst_column as i64, &mut self.mappings).expect("Ooops ...");
-        vlq::encode(pos.src_file_index as i64, &mut self.mappings).expect("Ooops ...");
-        vlq::encode(pos.src_line as i64, &mut self.mappings).expect("Ooops ...");
-        vlq::encode(pos.src_column as i64, &mut self.mappings).expect("Ooops ...");
+        let dst_column     = pos.dst_column as i64 - self.prev_dst_column as i64;
+        let src_file_index = pos.src_file_index as i64 - self.prev_src_file_index as i64;
+        let src_line       = pos.src_line as i64 - self.prev_src_line as i64;
+        let src_column     = pos.src_column as i64 - self.prev_src_column as i64;
+
+        vlq::encode(dst_column, &mut self.mappings).expect("Ooops ...");
+        vlq::encode(src_file_index, &mut self.mappings).expect("Ooops ...");
+        vlq::encode(src_line, &mut self.mappings).expect("Ooops ...");
+        vlq::encode(src_column, &mut self.mappings).expect("Ooops ...");
 
         if let Some(ident_index) = pos.ident_index {
-            vlq::encode(ident_index as i64, &mut self.mappings).expect("Ooops ...");
+            let delta = ident_index as i64 - self.prev_ident_index as i64;
+            vlq::encode(delta, &mut self.mappings).expect("Ooops ...");
+            self.prev_ident_index = ident_index;
         }
+
+        self.prev_dst_column = pos.dst_column;
+        self.prev_src_file_index = pos.src_file_index;
+        self.prev_src_line = pos.src_line;
+        self.prev_src_column = pos.src_column;
     }
 
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Ooops ...")
+    }
+
+    /// `//# sourceMappingURL=` pragma pointing at `<file>.map` next to the generated file.
     pub fn tail(&self) -> String {
-        format!("//@ sourceMappingURL=/path/to/file.js.map")
+        let filename = match self.file.file_name() {
+            Some(filename) => filename.to_string_lossy().to_string(),
+            None => self.file.to_string_lossy().to_string(),
+        };
+
+        format!("//# sourceMappingURL={}.map", filename)
+    }
+
+    /// `//# sourceMappingURL=` pragma with the whole map embedded as a data URL.
+    pub fn inline_tail(&self) -> String {
+        format!("//# sourceMappingURL=data:application/json;charset=utf-8;base64,{}", base64_encode(self.to_json().as_bytes()))
+    }
+
+
+    /// Parse a v3 source map, index maps ( `sections` ) are flattened into a regular map.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| Error::new(ErrorKind::SyntaxError, format!("invalid source map: {}", e)))?;
+
+        Self::from_value(&value)
+    }
+
+    fn from_value(value: &serde_json::Value) -> Result<Self, Error> {
+        match value.get("version").and_then(|v| v.as_u64()) {
+            Some(3) => { },
+            _ => return Err(Error::new(ErrorKind::SyntaxError, "unsupported source map version")),
+        }
+
+        let file = value.get("file").and_then(|v| v.as_str()).unwrap_or("");
+        let mut source_map = SourceMap::new(file);
+
+        if let Some(sections) = value.get("sections") {
+            let sections = sections.as_array()
+                .ok_or_else(|| Error::new(ErrorKind::SyntaxError, "source map `sections` must be an array"))?;
+
+            for section in sections.iter() {
+                let offset = section.get("offset");
+                let line_offset = offset.and_then(|v| v.get("line")).and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+                let column_offset = offset.and_then(|v| v.get("column")).and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+
+                // NOTE: `url` 形式的 section 需要额外的 IO，这里不处理。
+                let map = section.get("map")
+                    .ok_or_else(|| Error::new(ErrorKind::SyntaxError, "source map section without `map`"))?;
+                let map = SourceMap::from_value(map)?;
+
+                source_map.add_section(&map, line_offset, column_offset)?;
+            }
+
+            return Ok(source_map);
+        }
+
+        match value.get("sourceRoot").and_then(|v| v.as_str()) {
+            Some(root) if !root.is_empty() => source_map.set_source_root(root),
+            _ => { },
+        }
+
+        let sources = value.get("sources").and_then(|v| v.as_array())
+            .ok_or_else(|| Error::new(ErrorKind::SyntaxError, "source map without `sources`"))?;
+        let sources_content = value.get("sourcesContent").and_then(|v| v.as_array());
+        for (idx, source) in sources.iter().enumerate() {
+            let content = sources_content.and_then(|contents| contents.get(idx))
+                .and_then(|v| v.as_str())
+                .map(|content| content.to_string());
+            // NOTE: 这里不能去重，否则 `mappings` 里的索引会失效。
+            source_map.sources.push(PathBuf::from(source.as_str().unwrap_or("")));
+            source_map.sources_content.push(content);
+        }
+
+        if let Some(names) = value.get("names").and_then(|v| v.as_array()) {
+            for name in names.iter() {
+                let name = name.as_str().unwrap_or("");
+                source_map.names_index.entry(name.to_string()).or_insert(source_map.names.len());
+                source_map.names.push(name.to_string());
+            }
+        }
+
+        let mappings = value.get("mappings").and_then(|v| v.as_str())
+            .ok_or_else(|| Error::new(ErrorKind::SyntaxError, "source map without `mappings`"))?;
+        let mappings = decode_mappings(mappings.as_bytes())?;
+
+        for mapping in mappings.iter() {
+            if mapping.pos.src_file_index >= source_map.sources.len() {
+                return Err(Error::new(ErrorKind::SyntaxError, "source map `mappings` refers to an unknown source"));
+            }
+
+            match mapping.pos.ident_index {
+                Some(index) if index >= source_map.names.len() => {
+                    return Err(Error::new(ErrorKind::SyntaxError, "source map `mappings` refers to an unknown name"));
+                },
+                _ => { },
+            }
+        }
+
+        source_map.add_mappings(&mappings);
+        Ok(source_map)
+    }
+
+    /// `sourceRoot` joined with the source path.
+    pub fn resolve_source(&self, src_file_index: usize) -> PathBuf {
+        match self.source_root {
+            Some(ref root) => root.join(&self.sources[src_file_index]),
+            None => self.sources[src_file_index].clone(),
+        }
+    }
+
+    /// Decode `mappings`, segments come out in generated order.
+    pub fn decode(&self) -> Result<Vec<Mapping>, Error> {
+        decode_mappings(self.mappings.get_ref())
+    }
+
+    /// Append the mappings of `map`, whose generated code starts at `line`/`column` of this map's file.
+    /// Sections must be added in generated order.
+    pub fn add_section(&mut self, map: &SourceMap, line: usize, column: usize) -> Result<(), Error> {
+        let mut mappings = Vec::new();
+        for mut mapping in map.decode()? {
+            if mapping.dst_line == 0 {
+                mapping.pos.dst_column += column;
+            }
+            mapping.dst_line += line;
+            mapping.pos.src_file_index = self.add_source(map.resolve_source(mapping.pos.src_file_index),
+                                                         map.sources_content[mapping.pos.src_file_index].clone());
+            mapping.pos.ident_index = mapping.pos.ident_index.map(|index| self.add_name(&map.names[index]));
+            mappings.push(mapping);
+        }
+
+        self.add_mappings(&mappings);
+        Ok(())
+    }
+
+    /// Append mappings sorted in generated order, starting at the current line.
+    pub fn add_mappings(&mut self, mappings: &[Mapping]) {
+        let mut line = self.mappings.get_ref().iter().filter(|c| **c == SEMICOLON[0]).count();
+
+        for mapping in mappings.iter() {
+            while line < mapping.dst_line {
+                self.add_line();
+                line += 1;
+            }
+
+            self.add_pos(mapping.pos);
+        }
+    }
+}
+
+
+/// A decoded segment, `Position` plus the generated line it belongs to.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Mapping {
+    pub dst_line: usize,
+    pub pos: Position,
+}
+
+impl Mapping {
+    fn generated_cmp(&self, line: usize, column: usize) -> Ordering {
+        (self.dst_line, self.pos.dst_column).cmp(&(line, column))
+    }
+
+    fn original_cmp(&self, src_file_index: usize, line: usize, column: usize) -> Ordering {
+        (self.pos.src_file_index, self.pos.src_line, self.pos.src_column).cmp(&(src_file_index, line, column))
     }
 }
 
+fn decode_mappings(input: &[u8]) -> Result<Vec<Mapping>, Error> {
+    let invalid = |_| Error::new(ErrorKind::SyntaxError, "invalid VLQ in source map `mappings`");
+
+    let mut mappings = Vec::new();
+    let mut fields = [0i64; 5];
+
+    for (dst_line, line) in input.split(|c| *c == SEMICOLON[0]).enumerate() {
+        // NOTE: 字段 A 在每一行开始时重置。
+        fields[0] = 0;
+
+        for segment in line.split(|c| *c == COMMA[0]) {
+            if segment.is_empty() {
+                continue;
+            }
+
+            let mut bytes = segment.iter().cloned().peekable();
+            let mut count = 0;
+            while bytes.peek().is_some() {
+                if count == 5 {
+                    return Err(Error::new(ErrorKind::SyntaxError, "source map segment has too many fields"));
+                }
+                fields[count] += vlq::decode(&mut bytes).map_err(invalid)?;
+                count += 1;
+            }
+
+            if fields.iter().take(count).any(|field| *field < 0) {
+                return Err(Error::new(ErrorKind::SyntaxError, "source map segment has a negative field"));
+            }
+
+            match count {
+                // NOTE: 只有生成位置，没有对应的原始位置。
+                1 => continue,
+                4 | 5 => { },
+                _ => return Err(Error::new(ErrorKind::SyntaxError, "source map segment must have 1, 4 or 5 fields")),
+            }
+
+            mappings.push(Mapping {
+                dst_line: dst_line,
+                pos: Position {
+                    dst_column: fields[0] as usize,
+                    src_file_index: fields[1] as usize,
+                    src_line: fields[2] as usize,
+                    src_column: fields[3] as usize,
+                    ident_index: if count == 5 { Some(fields[4] as usize) } else { None },
+                },
+            });
+        }
+    }
+
+    Ok(mappings)
+}
+
+
+/// Query a decoded `SourceMap` in both directions.
+#[derive(Debug)]
+pub struct SourceMapConsumer {
+    source_map: SourceMap,
+    // NOTE: 按生成位置排序
+    generated: Vec<Mapping>,
+    // NOTE: `generated` 的下标，按原始位置排序
+    original: Vec<usize>,
+}
+
+impl SourceMapConsumer {
+    pub fn new(source_map: SourceMap) -> Result<Self, Error> {
+        let mut generated = source_map.decode()?;
+        generated.sort_by(|a, b| a.generated_cmp(b.dst_line, b.pos.dst_column));
+
+        let mut original = (0..generated.len()).collect::<Vec<usize>>();
+        original.sort_by(|a, b| {
+            let pos = generated[*b].pos;
+            generated[*a].original_cmp(pos.src_file_index, pos.src_line, pos.src_column)
+                .then_with(|| a.cmp(b))
+        });
+
+        Ok(Self { source_map, generated, original })
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Self::new(SourceMap::from_json(json)?)
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.generated
+    }
 
+    pub fn source_index<P: AsRef<Path>>(&self, path: P) -> Option<usize> {
+        let path = path.as_ref();
+        (0..self.source_map.sources.len())
+            .find(|idx| self.source_map.sources[*idx] == path || self.source_map.resolve_source(*idx) == path)
+    }
 
+    /// Generated → original, the closest segment at or before `column` on the same line.
+    pub fn original_position_for(&self, line: usize, column: usize) -> Option<Mapping> {
+        let index = match self.generated.binary_search_by(|m| m.generated_cmp(line, column)) {
+            Ok(mut index) => {
+                // NOTE: 同一位置可能有多个 Segment，取第一个。
+                while index > 0 && self.generated[index - 1].generated_cmp(line, column) == Ordering::Equal {
+                    index -= 1;
+                }
+                index
+            },
+            Err(0) => return None,
+            Err(index) => index - 1,
+        };
 
-impl<'a> Serialize for SourceMap<'a> {
+        let mapping = self.generated[index];
+        if mapping.dst_line == line { Some(mapping) } else { None }
+    }
+
+    /// Original → generated, the closest segment at or after `column` on the same original line.
+    pub fn generated_position_for(&self, src_file_index: usize, line: usize, column: usize) -> Option<Mapping> {
+        let index = match self.original.binary_search_by(|idx| {
+            self.generated[*idx].original_cmp(src_file_index, line, column)
+                .then(Ordering::Greater)
+        }) {
+            Ok(index) | Err(index) => index,
+        };
+
+        let mapping = self.generated[*self.original.get(index)?];
+        if mapping.pos.src_file_index == src_file_index && mapping.pos.src_line == line {
+            Some(mapping)
+        } else {
+            None
+        }
+    }
+}
+
+
+/// Compose `A → B` with `B → C` into `A → C`.
+///
+/// Segments of `b_to_c` pointing at the file described by `a_to_b` are traced back
+/// to the authored source, segments pointing at other sources are kept as-is.
+pub fn compose(a_to_b: &SourceMapConsumer, b_to_c: &SourceMapConsumer) -> SourceMap {
+    let outer = b_to_c.source_map();
+    let inner = a_to_b.source_map();
+
+    let mut source_map = SourceMap::new(outer.file.clone());
+    let mut mappings = Vec::with_capacity(b_to_c.generated.len());
+
+    for mapping in b_to_c.generated.iter() {
+        let src_file_index = mapping.pos.src_file_index;
+        let traceable = outer.sources.len() == 1
+            || outer.sources[src_file_index] == inner.file
+            || outer.resolve_source(src_file_index) == inner.file;
+
+        let pos = if traceable {
+            let original = match a_to_b.original_position_for(mapping.pos.src_line, mapping.pos.src_column) {
+                Some(original) => original,
+                // NOTE: 中间产物里没有对应位置，无法追溯到源文件。
+                None => continue,
+            };
+
+            let name = original.pos.ident_index.map(|index| &inner.names[index])
+                .or_else(|| mapping.pos.ident_index.map(|index| &outer.names[index]));
+
+            Position {
+                dst_column: mapping.pos.dst_column,
+                src_file_index: source_map.add_source(inner.resolve_source(original.pos.src_file_index),
+                                                      inner.sources_content[original.pos.src_file_index].clone()),
+                src_line: original.pos.src_line,
+                src_column: original.pos.src_column,
+                ident_index: name.map(|name| source_map.add_name(name)),
+            }
+        } else {
+            Position {
+                dst_column: mapping.pos.dst_column,
+                src_file_index: source_map.add_source(outer.resolve_source(src_file_index),
+                                                      outer.sources_content[src_file_index].clone()),
+                src_line: mapping.pos.src_line,
+                src_column: mapping.pos.src_column,
+                ident_index: mapping.pos.ident_index.map(|index| source_map.add_name(&outer.names[index])),
+            }
+        };
+
+        mappings.push(Mapping { dst_line: mapping.dst_line, pos });
+    }
+
+    source_map.add_mappings(&mappings);
+    source_map
+}
+
+
+fn base64_encode(input: &[u8]) -> String {
+    let mut output = String::with_capacity((input.len() + 2) / 3 * 4);
+
+    for chunk in input.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = if chunk.len() > 1 { chunk[1] as u32 } else { 0 };
+        let b2 = if chunk.len() > 2 { chunk[2] as u32 } else { 0 };
+        let n = (b0 << 16) | (b1 << 8) | b2;
+
+        output.push(BASE64_CHARS[(n >> 18) as usize & 63] as char);
+        output.push(BASE64_CHARS[(n >> 12) as usize & 63] as char);
+
+        if chunk.len() > 1 {
+            output.push(BASE64_CHARS[(n >> 6) as usize & 63] as char);
+        } else {
+            output.push('=');
+        }
+
+        if chunk.len() > 2 {
+            output.push(BASE64_CHARS[n as usize & 63] as char);
+        } else {
+            output.push('=');
+        }
+    }
+
+    output
+}
+
+
+impl Serialize for SourceMap {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
@@ -128,9 +585,7 @@ impl<'a> Serialize for SourceMap<'a> {
 
         s.serialize_field("sources", &self.sources)?;
         s.serialize_field("sourcesContent", &self.sources_content)?;
-            
-        let names = self.names.iter().map(|name| name.iter().collect::<String>()).collect::<Vec<String>>();
-        s.serialize_field("names", &names)?;
+        s.serialize_field("names", &self.names)?;
 
         let mappings = unsafe {
             String::from_utf8_unchecked(self.mappings.get_ref().to_owned())
@@ -146,26 +601,11 @@ impl<'a> Serialize for SourceMap<'a> {
 
 #[test]
 fn test_serialize() {
-    use crate::toolshed::{ Arena, };
     use crate::serde_json;
 
-    use std::io::{ Write, Cursor, };
-    use std::path::{Path, PathBuf};
-
-
-    let arena = Arena::new();
-
-    let file = "dist/main.js".into();
-    let source_root = None;
-    let sources = vec![];
-    let sources_content = arena.alloc_vec(vec![ arena.alloc_str("这是一份源代码:)") ]);
-
-    let names = arena.alloc_vec(vec![
-        arena.alloc_vec("let".chars().collect::<Vec<char>>()),
-    ]);
-    let mappings = Cursor::new(Vec::new());
-
-    let source_map = SourceMap { file, source_root, sources, sources_content, names, mappings };
+    let mut source_map = SourceMap::new("dist/main.js");
+    source_map.sources_content.push(Some("这是一份源代码:)".to_string()));
+    source_map.add_name("let");
 
     let res = serde_json::to_string(&source_map);
     assert_eq!(res.is_ok(), true);
@@ -173,4 +613,119 @@ fn test_serialize() {
     let res = res.unwrap();
     let json = r#"{"version":3,"file":"dist/main.js","sourceRoot":"","sources":[],"sourcesContent":["这是一份源代码:)"],"names":["let"],"mappings":""}"#;
     assert_eq!(res, json);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_delta_encoding() {
+    let mut source_map = SourceMap::new("out.js");
+    let src = source_map.add_source("a.js", None);
+    let name = source_map.add_name("foo");
+
+    source_map.add_pos(Position { dst_column: 0, src_file_index: src, src_line: 0, src_column: 0, ident_index: None });
+    source_map.add_pos(Position { dst_column: 4, src_file_index: src, src_line: 0, src_column: 6, ident_index: Some(name) });
+    source_map.add_line();
+    source_map.add_pos(Position { dst_column: 2, src_file_index: src, src_line: 1, src_column: 2, ident_index: Some(name) });
+
+    assert_eq!(source_map.mappings(), b"AAAA,IAAMA;EACJA");
+    assert_eq!(source_map.tail(), "//# sourceMappingURL=out.js.map");
+    assert_eq!(base64_encode(b"Man"), "TWFu");
+    assert_eq!(base64_encode(b"Ma"), "TWE=");
+}
+
+
+#[test]
+fn test_decode() {
+    let json = r#"{"version":3,"file":"out.js","sourceRoot":"","sources":["a.js"],"names":["foo"],"mappings":"AAAA,IAAMA;EACJA,G"}"#;
+    let source_map = SourceMap::from_json(json).ok().unwrap();
+    assert_eq!(source_map.mappings(), b"AAAA,IAAMA;EACJA");
+
+    let mappings = source_map.decode().ok().unwrap();
+    assert_eq!(mappings.len(), 3);
+    assert_eq!(mappings[2], Mapping {
+        dst_line: 1,
+        pos: Position { dst_column: 2, src_file_index: 0, src_line: 1, src_column: 2, ident_index: Some(0) },
+    });
+
+    assert!(SourceMap::from_json(r#"{"version":2,"sources":[],"mappings":""}"#).is_err());
+    assert!(SourceMap::from_json(r#"{"version":3,"sources":[],"mappings":"AAAA"}"#).is_err());
+    assert!(SourceMap::from_json(r#"{"version":3,"sources":["a.js"],"mappings":"A$"}"#).is_err());
+}
+
+#[test]
+fn test_index_map() {
+    let json = r#"{
+        "version": 3,
+        "file": "bundle.js",
+        "sections": [
+            { "offset": { "line": 0, "column": 0 }, "map": { "version": 3, "sources": ["a.js"], "names": ["a"], "mappings": "AAAAA" } },
+            { "offset": { "line": 2, "column": 4 }, "map": { "version": 3, "sources": ["b.js"], "names": ["b"], "mappings": "AAAAA;AACA" } }
+        ]
+    }"#;
+    let consumer = SourceMapConsumer::from_json(json).ok().unwrap();
+    let source_map = consumer.source_map();
+    assert_eq!(source_map.sources(), &[PathBuf::from("a.js"), PathBuf::from("b.js")]);
+    assert_eq!(source_map.names(), &["a".to_string(), "b".to_string()]);
+
+    let mapping = consumer.original_position_for(2, 4).unwrap();
+    assert_eq!((mapping.pos.src_file_index, mapping.pos.src_line, mapping.pos.src_column), (1, 0, 0));
+    assert_eq!(mapping.pos.ident_index, Some(1));
+
+    let mapping = consumer.original_position_for(3, 0).unwrap();
+    assert_eq!((mapping.pos.src_file_index, mapping.pos.src_line), (1, 1));
+}
+
+#[test]
+fn test_lookup() {
+    let mut source_map = SourceMap::new("out.js");
+    let src = source_map.add_source("a.js", None);
+    source_map.add_pos(Position { dst_column: 0, src_file_index: src, src_line: 0, src_column: 0, ident_index: None });
+    source_map.add_pos(Position { dst_column: 10, src_file_index: src, src_line: 3, src_column: 4, ident_index: None });
+    source_map.add_line();
+    source_map.add_pos(Position { dst_column: 2, src_file_index: src, src_line: 1, src_column: 0, ident_index: None });
+
+    let consumer = SourceMapConsumer::new(source_map).ok().unwrap();
+
+    assert_eq!(consumer.original_position_for(0, 5).unwrap().pos.src_line, 0);
+    assert_eq!(consumer.original_position_for(0, 10).unwrap().pos.src_line, 3);
+    assert_eq!(consumer.original_position_for(0, 99).unwrap().pos.src_line, 3);
+    assert_eq!(consumer.original_position_for(1, 1), None);
+    assert_eq!(consumer.original_position_for(1, 2).unwrap().pos.src_line, 1);
+
+    let mapping = consumer.generated_position_for(src, 3, 0).unwrap();
+    assert_eq!((mapping.dst_line, mapping.pos.dst_column), (0, 10));
+    let mapping = consumer.generated_position_for(src, 1, 0).unwrap();
+    assert_eq!((mapping.dst_line, mapping.pos.dst_column), (1, 2));
+    assert_eq!(consumer.generated_position_for(src, 2, 0), None);
+    assert_eq!(consumer.source_index("a.js"), Some(src));
+}
+
+#[test]
+fn test_compose() {
+    // a.js --(transform)--> b.js
+    let mut a_to_b = SourceMap::new("b.js");
+    let a = a_to_b.add_source("a.js", Some("let foo = 1;".to_string()));
+    let foo = a_to_b.add_name("foo");
+    a_to_b.add_line();
+    a_to_b.add_pos(Position { dst_column: 4, src_file_index: a, src_line: 0, src_column: 4, ident_index: Some(foo) });
+
+    // b.js --(minify)--> c.js
+    let mut b_to_c = SourceMap::new("c.js");
+    let b = b_to_c.add_source("b.js", None);
+    let x = b_to_c.add_name("x");
+    b_to_c.add_pos(Position { dst_column: 4, src_file_index: b, src_line: 1, src_column: 4, ident_index: Some(x) });
+    b_to_c.add_pos(Position { dst_column: 9, src_file_index: b, src_line: 0, src_column: 0, ident_index: None });
+
+    let a_to_b = SourceMapConsumer::new(a_to_b).ok().unwrap();
+    let b_to_c = SourceMapConsumer::new(b_to_c).ok().unwrap();
+    let a_to_c = compose(&a_to_b, &b_to_c);
+
+    assert_eq!(a_to_c.file(), Path::new("c.js"));
+    assert_eq!(a_to_c.sources(), &[PathBuf::from("a.js")]);
+    assert_eq!(a_to_c.sources_content(), &[Some("let foo = 1;".to_string())]);
+    assert_eq!(a_to_c.names(), &["foo".to_string()]);
+
+    let a_to_c = SourceMapConsumer::new(a_to_c).ok().unwrap();
+    assert_eq!(a_to_c.mappings().len(), 1);
+    let mapping = a_to_c.original_position_for(0, 4).unwrap();
+    assert_eq!((mapping.pos.src_line, mapping.pos.src_column, mapping.pos.ident_index), (0, 4, Some(0)));
+}
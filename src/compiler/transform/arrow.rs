@@ -0,0 +1,166 @@
+// Arrow functions → function expressions
+//
+//      function f() { return () => this.x + arguments[0]; }
+//
+//      function f() { var _this = this, _arguments = arguments; return function () { return _this.x + _arguments[0]; }; }
+//
+// NOTE: 箭头函数没有自己的 `this` 和 `arguments`，它们来自最近的非箭头函数（或者全局），
+//       在那里声明 `_this`/`_arguments` 并在箭头函数内部替换引用。
+
+use crate::error::Error;
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{ NameGenerator, directive_prologue, };
+
+use std::mem;
+
+
+#[derive(Default)]
+struct Context {
+    is_function: bool,
+    arrows: usize,
+    this: Option<String>,
+    arguments: Option<String>,
+}
+
+impl Context {
+    fn declarations(&self) -> Option<Statement> {
+        let mut declarators = Vec::new();
+        if let Some(ref name) = self.this {
+            declarators.push(builder::declarator(builder::ident_expr(name), Some(builder::this())));
+        }
+        if let Some(ref name) = self.arguments {
+            declarators.push(builder::declarator(builder::ident_expr(name), Some(builder::ident_expr("arguments"))));
+        }
+
+        if declarators.is_empty() {
+            None
+        } else {
+            Some(builder::variable(crate::ast::statement::LexicalDeclarationKind::Var, declarators))
+        }
+    }
+}
+
+struct ArrowLowering<'a> {
+    names: &'a mut NameGenerator,
+    context: Context,
+}
+
+impl<'a> ArrowLowering<'a> {
+    fn function_body<F: FnOnce(&mut Self)>(&mut self, walk: F) -> Option<Statement> {
+        let outer = mem::replace(&mut self.context, Context { is_function: true, ..Context::default() });
+        walk(self);
+        let inner = mem::replace(&mut self.context, outer);
+
+        inner.declarations()
+    }
+}
+
+fn prepend(body: &mut Vec<Statement>, decl: Option<Statement>) {
+    if let Some(decl) = decl {
+        // NOTE: 插入在指令序言之后，否则 `"use strict"` 不再是指令。
+        let index = directive_prologue(body);
+        body.insert(index, decl);
+    }
+}
+
+/// Arrow parameters: `x`, `(a, b)` or `(a, b)` parsed as one comma expression.
+pub(crate) fn arrow_params(params: Expression) -> Vec<Expression> {
+    match params {
+        Expression::Parenthesized(inner) => {
+            let mut items = inner.items;
+            if items.len() == 1 {
+                if let Expression::Comma(_) = items[0] {
+                    if let Expression::Comma(comma) = items.remove(0) {
+                        return comma.items;
+                    }
+                }
+            }
+            items
+        },
+        other => vec![ other ],
+    }
+}
+
+impl<'a> VisitMut for ArrowLowering<'a> {
+    fn visit_function(&mut self, func: &mut Function) {
+        let decl = self.function_body(|this| visit::walk_function(this, func));
+        prepend(&mut func.body, decl);
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        let decl = self.function_body(|this| visit::walk_method_definition(this, method));
+        match *method {
+            MethodDefinition::Method(ref mut inner) => prepend(&mut inner.body, decl),
+            MethodDefinition::Getter(ref mut inner) => prepend(&mut inner.body, decl),
+            MethodDefinition::Setter(ref mut inner) => prepend(&mut inner.body, decl),
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        match *expr {
+            Expression::This(_) if self.context.arrows > 0 => {
+                let names = &mut self.names;
+                let name = self.context.this.get_or_insert_with(|| names.fresh("this")).clone();
+                *expr = builder::ident_expr(&name);
+            },
+            Expression::Identifier(ref ident) if self.context.arrows > 0 && self.context.is_function && ident.name() == "arguments" => {
+                let names = &mut self.names;
+                let name = self.context.arguments.get_or_insert_with(|| names.fresh("arguments")).clone();
+                *expr = builder::ident_expr(&name);
+            },
+            Expression::ArrowFunction(_) => {
+                self.context.arrows += 1;
+                visit::walk_expression(self, expr);
+                self.context.arrows -= 1;
+
+                let arrow = match mem::replace(expr, builder::null()) {
+                    Expression::ArrowFunction(arrow) => *arrow,
+                    _ => unreachable!(),
+                };
+                let body = match arrow.body {
+                    ConciseBody::Expr(value) => vec![ builder::return_stmt(Some(value)) ],
+                    ConciseBody::Stmt(body) => body,
+                };
+
+                *expr = Expression::Function(Box::new(FunctionExpression {
+                    loc: arrow.loc,
+                    span: arrow.span,
+                    is_async: arrow.is_async,
+                    is_generator: false,
+                    name: None,
+                    func: Function { loc: arrow.loc, span: arrow.span, params: builder::paren(arrow_params(arrow.params)), body, },
+                }));
+            },
+            _ => visit::walk_expression(self, expr),
+        }
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, names: &mut NameGenerator) -> Result<(), Error> {
+    let mut pass = ArrowLowering { names, context: Context::default() };
+    pass.visit_statements(body);
+    prepend(body, pass.context.declarations());
+
+    Ok(())
+}
+
+
+#[test]
+fn test_arrow_lowering() {
+    use crate::compiler::transform::{ parse_owned, lower_and_print, };
+
+    assert_eq!(lower_and_print(parse_owned("x => x + 1")), "(function(x){return x+1;});");
+    assert_eq!(
+        lower_and_print(parse_owned("function f(_this) { (a, b) => () => this[a] + arguments[b] }")),
+        "function f(_this){var _this2=this,_arguments=arguments;(function(a,b){return function(){return _this2[a]+_arguments[b];};});}"
+    );
+    // NOTE: 全局作用域没有 `arguments`
+    assert_eq!(lower_and_print(parse_owned("() => this + arguments")), "var _this=this;(function(){return _this+arguments;});");
+    assert_eq!(
+        lower_and_print(parse_owned("function f() { \"use strict\"; g(() => this) }")),
+        "function f(){\"use strict\";var _this=this;g(function(){return _this;});}"
+    );
+}
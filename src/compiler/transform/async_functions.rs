@@ -0,0 +1,315 @@
+// Async functions → generators
+//
+//      async function f(a) { return await g(a); }
+//
+//      function f(a) { return _async(function* () { return yield g(a); }).apply(this, arguments); }
+//
+//      async function* f() { yield await g(); }
+//
+//      function f() { return _asyncGenerator(function* () { yield yield new _AwaitValue(g()); }).apply(this, arguments); }
+//
+//      for await (x of xs) body
+//
+//      for (var _iterator = _asyncIterator(xs), _step; !(_step = await _iterator.next()).done; ) { x = _step.value; body }
+//
+// NOTE: 参数仍然由外层函数接收，内层生成器通过闭包访问，`this`/`arguments` 通过 `apply` 传入。
+//       目标版本支持生成器时（ES2015+）生成器保持原样，否则接着由生成器降级处理。
+
+use crate::error::{ ErrorKind, Error, };
+use crate::lexer::operator::{ PrefixOperator, };
+use crate::ast::statement::LexicalDeclarationKind;
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{ NameGenerator, Helper, Helpers, };
+
+use std::mem;
+
+
+// NOTE: `await x` → `yield x`（异步生成器中为 `yield new _AwaitValue(x)`），不进入嵌套函数。
+struct AwaitRewriter<'a> {
+    await_value: Option<&'a str>,
+    error: Option<Error>,
+}
+
+impl<'a> VisitMut for AwaitRewriter<'a> {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        visit::walk_expression(self, expr);
+
+        match *expr {
+            Expression::Prefix(ref inner) if inner.operator == PrefixOperator::Await => { },
+            Expression::Yield(ref inner) if inner.star && self.await_value.is_some() => {
+                self.error = Some(Error::new(ErrorKind::SyntaxError, "'yield*' in async generators requires ES2018"));
+                return;
+            },
+            _ => return,
+        }
+
+        let operand = match mem::replace(expr, builder::null()) {
+            Expression::Prefix(inner) => inner.operand,
+            _ => unreachable!(),
+        };
+        let item = match self.await_value {
+            Some(name) => builder::new(builder::ident_expr(name), vec![ operand ]),
+            None => operand,
+        };
+        *expr = yield_expr(item);
+    }
+
+    fn visit_function(&mut self, _func: &mut Function) {
+
+    }
+
+    fn visit_arrow_function(&mut self, _arrow: &mut ArrowFunctionExpression) {
+
+    }
+
+    fn visit_class(&mut self, _class: &mut Class) {
+
+    }
+}
+
+fn yield_expr(item: Expression) -> Expression {
+    Expression::Yield(Box::new(YieldExpression { loc: Default::default(), span: Default::default(), star: false, item }))
+}
+
+
+// NOTE: 箭头函数没有自己的 `arguments`，只在用到时才传入外层的 `arguments`（全局作用域没有 `arguments`）。
+#[derive(Default)]
+struct ArgumentsFinder {
+    found: bool,
+}
+
+impl VisitMut for ArgumentsFinder {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        if let Expression::Identifier(ref ident) = *expr {
+            if ident.name() == "arguments" {
+                self.found = true;
+            }
+        }
+        visit::walk_expression(self, expr)
+    }
+
+    fn visit_function(&mut self, _func: &mut Function) {
+
+    }
+
+    fn visit_method_definition(&mut self, _method: &mut MethodDefinition) {
+
+    }
+}
+
+
+struct AsyncLowering<'a> {
+    names: &'a mut NameGenerator,
+    helpers: &'a mut Helpers,
+    // 降级 async 函数（ES2017 之前）
+    functions: bool,
+    // 降级 async 生成器和 `for await`（ES2018 之前）
+    generators: bool,
+    error: Option<Error>,
+}
+
+impl<'a> AsyncLowering<'a> {
+    fn should_lower(&self, is_async: bool, is_generator: bool) -> bool {
+        is_async && if is_generator { self.generators } else { self.functions }
+    }
+
+    // _async(function* () { body }).apply(this, arguments)
+    fn wrap(&mut self, mut body: Vec<Statement>, is_generator: bool, arguments: bool) -> Expression {
+        let (helper, await_value) = if is_generator {
+            (Helper::AsyncGenerator, Some(self.helpers.get(self.names, Helper::AwaitValue)))
+        } else {
+            (Helper::Async, None)
+        };
+        let helper = self.helpers.get(self.names, helper);
+
+        let mut rewriter = AwaitRewriter { await_value: await_value.as_ref().map(|name| name.as_str()), error: None };
+        rewriter.visit_statements(&mut body);
+        if let Some(error) = rewriter.error {
+            self.error = Some(error);
+        }
+
+        let generator = Expression::Function(Box::new(FunctionExpression {
+            loc: Default::default(),
+            span: Default::default(),
+            is_async: false,
+            is_generator: true,
+            name: None,
+            func: builder::function(vec![], body),
+        }));
+        let wrapped = builder::call(builder::ident_expr(&helper), vec![ generator ]);
+        if arguments {
+            builder::call(builder::member(wrapped, "apply"), vec![ builder::this(), builder::ident_expr("arguments") ])
+        } else {
+            builder::call(builder::member(wrapped, "call"), vec![ builder::this() ])
+        }
+    }
+
+    fn lower_function(&mut self, body: &mut Vec<Statement>, is_generator: bool) {
+        let inner = mem::replace(body, Vec::new());
+        let call = self.wrap(inner, is_generator, true);
+        *body = vec![ builder::return_stmt(Some(call)) ];
+    }
+
+    fn lower_arrow(&mut self, arrow: &mut ArrowFunctionExpression) {
+        let body = match mem::replace(&mut arrow.body, ConciseBody::Stmt(Vec::new())) {
+            ConciseBody::Expr(value) => vec![ builder::return_stmt(Some(value)) ],
+            ConciseBody::Stmt(body) => body,
+        };
+
+        let mut finder = ArgumentsFinder::default();
+        finder.visit_statements(&mut body.clone());
+
+        arrow.is_async = false;
+        arrow.body = ConciseBody::Expr(self.wrap(body, false, finder.found));
+    }
+
+    // for (var _iterator = _asyncIterator(right), _step; !(_step = await _iterator.next()).done; ) { left = _step.value; body }
+    fn lower_for_await(&mut self, stmt: ForAwaitOfStatement) -> Statement {
+        let ForAwaitOfStatement { left, right, body, .. } = stmt;
+
+        let helper = self.helpers.get(self.names, Helper::AsyncIterator);
+        let iterator = self.names.fresh("iterator");
+        let step = self.names.fresh("step");
+        let init = builder::variable(LexicalDeclarationKind::Var, vec![
+            builder::declarator(builder::ident_expr(&iterator), Some(builder::call(builder::ident_expr(&helper), vec![ right ]))),
+            builder::declarator(builder::ident_expr(&step), None),
+        ]);
+
+        let next = builder::call(builder::member(builder::ident_expr(&iterator), "next"), vec![]);
+        let result = builder::assign(builder::ident_expr(&step), builder::prefix(PrefixOperator::Await, next));
+        let condition = builder::prefix(PrefixOperator::Not, builder::member(builder::parenthesized(result), "done"));
+
+        let mut stmts = vec![ builder::expr_stmt(builder::assign(left, builder::member(builder::ident_expr(&step), "value"))) ];
+        match body {
+            Statement::Block(block) => stmts.extend(block.body),
+            other => stmts.push(other),
+        }
+
+        builder::for_stmt(Some(init), Some(condition), None, builder::block_stmt(stmts))
+    }
+}
+
+impl<'a> VisitMut for AsyncLowering<'a> {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        visit::walk_statement(self, stmt);
+
+        match *stmt {
+            Statement::Function(ref mut inner) if self.should_lower(inner.is_async, inner.is_generator) => {
+                self.lower_function(&mut inner.func.body, inner.is_generator);
+                inner.is_async = false;
+                inner.is_generator = false;
+            },
+            Statement::ForAwaitOf(_) if self.generators => {
+                if let Statement::ForAwaitOf(inner) = mem::replace(stmt, builder::empty()) {
+                    *stmt = self.lower_for_await(*inner);
+                }
+            },
+            _ => { },
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        visit::walk_expression(self, expr);
+
+        match *expr {
+            Expression::Function(ref mut inner) if self.should_lower(inner.is_async, inner.is_generator) => {
+                self.lower_function(&mut inner.func.body, inner.is_generator);
+                inner.is_async = false;
+                inner.is_generator = false;
+            },
+            Expression::ArrowFunction(ref mut inner) if self.should_lower(inner.is_async, false) => self.lower_arrow(inner),
+            _ => { },
+        }
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        visit::walk_method_definition(self, method);
+
+        if let MethodDefinition::Method(ref mut inner) = *method {
+            if self.should_lower(inner.is_async, inner.is_generator) {
+                self.lower_function(&mut inner.body, inner.is_generator);
+                inner.is_async = false;
+                inner.is_generator = false;
+            }
+        }
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, names: &mut NameGenerator, helpers: &mut Helpers, functions: bool, generators: bool) -> Result<(), Error> {
+    let mut pass = AsyncLowering { names, helpers, functions, generators, error: None };
+    pass.visit_statements(body);
+
+    match pass.error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+
+#[test]
+fn test_async_lowering() {
+    use crate::compiler::codegen::CodeGen;
+    use crate::lexer::operator::InfixOperator;
+    use crate::ast::owned::ToArenaAst;
+    use crate::toolshed::Arena;
+
+    // NOTE: 目标为 ES2015，生成器保持原样
+    let print = |mut body: Vec<Statement>| {
+        let mut names = NameGenerator::new();
+        let mut helpers = Helpers::new();
+        lower(&mut body, &mut names, &mut helpers, true, true).ok().unwrap();
+
+        let arena = Arena::new();
+        let body = body.iter().map(|stmt| stmt.to_arena_ast(&arena)).collect::<Vec<_>>();
+        let mut codegen = CodeGen::new(Vec::new());
+        codegen.set_minify(true);
+        codegen.gen_program(&body).unwrap();
+        String::from_utf8(codegen.into_inner()).unwrap()
+    };
+    let await_expr = |operand| builder::prefix(PrefixOperator::Await, operand);
+    let function = |is_generator, params, body| Statement::Function(Box::new(FunctionDeclaration {
+        loc: Default::default(),
+        span: Default::default(),
+        is_async: true,
+        is_generator,
+        name: builder::ident("f"),
+        func: builder::function(params, body),
+    }));
+
+    // async function f(a) { return 1 + await g(a); }
+    let value = builder::infix(builder::number(1), InfixOperator::Add, await_expr(builder::call(builder::ident_expr("g"), vec![ builder::ident_expr("a") ])));
+    assert_eq!(
+        print(vec![ function(false, vec![ builder::ident_expr("a") ], vec![ builder::return_stmt(Some(value)) ]) ]),
+        "function f(a){return _async(function*(){return 1+(yield g(a));}).apply(this,arguments);}"
+    );
+
+    // async x => await x
+    let arrow = Expression::ArrowFunction(Box::new(ArrowFunctionExpression {
+        loc: Default::default(),
+        span: Default::default(),
+        is_async: true,
+        params: builder::ident_expr("x"),
+        body: ConciseBody::Expr(await_expr(builder::ident_expr("x"))),
+    }));
+    assert_eq!(print(vec![ builder::expr_stmt(arrow) ]), "x=>_async(function*(){return yield x;}).call(this);");
+
+    // async function* f() { for await (x of xs) yield await x; }
+    let for_await = Statement::ForAwaitOf(Box::new(ForAwaitOfStatement {
+        loc: Default::default(),
+        span: Default::default(),
+        left: builder::ident_expr("x"),
+        right: builder::ident_expr("xs"),
+        body: builder::expr_stmt(yield_expr(await_expr(builder::ident_expr("x")))),
+    }));
+    assert_eq!(
+        print(vec![ function(true, vec![], vec![ for_await ]) ]),
+        concat!(
+            "function f(){return _asyncGenerator(function*(){",
+            "for(var _iterator=_asyncIterator(xs),_step;!(_step=(yield new _AwaitValue(_iterator.next()))).done;){x=_step.value;yield(yield new _AwaitValue(x));}",
+            "}).apply(this,arguments);}",
+        )
+    );
+}
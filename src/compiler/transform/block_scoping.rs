@@ -0,0 +1,771 @@
+// `let` / `const` → `var`
+//
+// NOTE: ES5 只有函数作用域，块级声明降级为 `var` 时需要处理两个问题：
+//
+//  1. 遮蔽：块内的声明与同一函数内其它位置出现的同名变量冲突时，重命名为唯一的名字。
+//
+//          { let x = 1; } x;           →   { var _x = 1; } x;
+//
+//  2. 闭包：循环体内的闭包捕获了循环中的块级变量时，每次迭代都需要一份新的绑定，
+//     把循环体提取为函数，控制流（`break`、`continue`、`return`）通过返回值传出。
+//
+//          for (let i = 0; i < 3; i++) { fns.push(() => i); }
+//
+//          { var _loop = (i) => { fns.push(() => i); }; for (var i = 0; i < 3; i++) { _loop(i); } }
+//
+//     `_loop` 保持为箭头函数，其中的 `this`、`arguments` 由箭头函数降级统一处理。
+
+use crate::error::Error;
+use crate::lexer::operator::{ PrefixOperator, PostfixOperator, InfixOperator, };
+use crate::ast::statement::LexicalDeclarationKind;
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{ NameGenerator, Renamer, bound_names, count_names, count_names_in_expression, has_suspension, };
+
+use std::mem;
+use std::collections::{ HashMap, HashSet, };
+
+
+fn is_loop(stmt: &Statement) -> bool {
+    match *stmt {
+        Statement::DoWhile(_) | Statement::While(_) | Statement::For(_)
+        | Statement::ForIn(_) | Statement::ForOf(_) | Statement::ForAwaitOf(_) => true,
+        _ => false,
+    }
+}
+
+fn loop_body(stmt: &mut Statement) -> Option<&mut Statement> {
+    match *stmt {
+        Statement::DoWhile(ref mut inner) => Some(&mut inner.body),
+        Statement::While(ref mut inner) => Some(&mut inner.body),
+        Statement::For(ref mut inner) => Some(&mut inner.body),
+        Statement::ForIn(ref mut inner) => Some(&mut inner.body),
+        Statement::ForOf(ref mut inner) => Some(&mut inner.body),
+        Statement::ForAwaitOf(ref mut inner) => Some(&mut inner.body),
+        _ => None,
+    }
+}
+
+fn lexical_names(stmt: &Statement, names: &mut Vec<String>) {
+    match *stmt {
+        Statement::Variable(ref inner) if !inner.is_var() => {
+            for declarator in inner.declarators.iter() {
+                bound_names(&declarator.name, names);
+            }
+        },
+        Statement::Class(ref inner) => names.push(inner.name.name().to_string()),
+        _ => { },
+    }
+}
+
+// NOTE: 循环中的 `let x;` 每次迭代都要重新初始化为 `undefined`
+fn make_var(stmt: &mut Statement, reset: bool) {
+    if let Statement::Variable(ref mut inner) = *stmt {
+        if inner.is_var() {
+            return;
+        }
+
+        inner.kind = LexicalDeclarationKind::Var;
+        if reset {
+            for declarator in inner.declarators.iter_mut() {
+                if declarator.initializer.is_none() {
+                    declarator.initializer = Some(builder::undefined());
+                }
+            }
+        }
+    }
+}
+
+fn merge(counts: &mut HashMap<String, usize>, other: HashMap<String, usize>) {
+    for (name, n) in other.into_iter() {
+        *counts.entry(name).or_insert(0) += n;
+    }
+}
+
+fn statement_counts(stmt: &mut Statement) -> HashMap<String, usize> {
+    let mut body = vec![ mem::replace(stmt, builder::empty()) ];
+    let counts = count_names(&mut body);
+    *stmt = body.remove(0);
+    counts
+}
+
+
+/// Does any closure inside the loop body reference one of `names` ?
+struct CaptureFinder<'a> {
+    names: &'a HashSet<String>,
+    depth: usize,
+    found: bool,
+}
+
+impl<'a> CaptureFinder<'a> {
+    fn check(&mut self, name: &str) {
+        if self.depth > 0 && self.names.contains(name) {
+            self.found = true;
+        }
+    }
+}
+
+impl<'a> VisitMut for CaptureFinder<'a> {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        if let Expression::Identifier(ref ident) = *expr {
+            self.check(ident.name());
+        }
+        visit::walk_expression(self, expr)
+    }
+
+    fn visit_object_property(&mut self, prop: &mut ObjectProperty) {
+        if let ObjectProperty::Identifier(ref ident) = *prop {
+            self.check(ident.name());
+        }
+        visit::walk_object_property(self, prop)
+    }
+
+    fn visit_function(&mut self, func: &mut Function) {
+        self.depth += 1;
+        visit::walk_function(self, func);
+        self.depth -= 1;
+    }
+
+    fn visit_arrow_function(&mut self, arrow: &mut ArrowFunctionExpression) {
+        self.depth += 1;
+        visit::walk_arrow_function(self, arrow);
+        self.depth -= 1;
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        self.depth += 1;
+        visit::walk_method_definition(self, method);
+        self.depth -= 1;
+    }
+}
+
+
+/// Is one of `names` assigned inside the loop body ?
+struct WriteFinder<'a> {
+    names: &'a [String],
+    written: HashSet<String>,
+}
+
+impl<'a> WriteFinder<'a> {
+    fn target(&mut self, expr: &Expression) {
+        if let Expression::Identifier(ref ident) = *expr {
+            if self.names.iter().any(|name| name == ident.name()) {
+                self.written.insert(ident.name().to_string());
+            }
+        }
+    }
+}
+
+impl<'a> VisitMut for WriteFinder<'a> {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        match *expr {
+            Expression::Assignment(ref inner) => self.target(&inner.left),
+            Expression::Prefix(ref inner) if inner.operator == PrefixOperator::Increment || inner.operator == PrefixOperator::Decrement => {
+                self.target(&inner.operand)
+            },
+            Expression::Postfix(ref inner) if inner.operator == PostfixOperator::Increment || inner.operator == PostfixOperator::Decrement => {
+                self.target(&inner.operand)
+            },
+            _ => { },
+        }
+        visit::walk_expression(self, expr)
+    }
+}
+
+
+/// `var` declarations inside the extracted loop body must stay visible after the loop:
+/// the declaration moves out, the initializer stays as an assignment.
+/// Turns `var` declarations into assignments, collecting the names to declare elsewhere.
+pub(crate) struct VarHoister<'a> {
+    pub keep: &'a HashSet<String>,
+    pub hoisted: Vec<String>,
+}
+
+impl<'a> VarHoister<'a> {
+    fn hoist(&mut self, stmt: &mut Statement) -> bool {
+        let assignments = match *stmt {
+            Statement::Variable(ref mut inner) if inner.is_var() => {
+                let hoistable = inner.declarators.iter().all(|declarator| match declarator.name {
+                    Expression::Identifier(ref ident) => !self.keep.contains(ident.name()),
+                    _ => false,
+                });
+                if !hoistable {
+                    return false;
+                }
+
+                let mut assignments = Vec::new();
+                for declarator in inner.declarators.drain(..) {
+                    let mut names = Vec::new();
+                    bound_names(&declarator.name, &mut names);
+                    self.hoisted.extend(names);
+                    if let Some(init) = declarator.initializer {
+                        assignments.push(builder::assign(declarator.name, init));
+                    }
+                }
+                assignments
+            },
+            _ => return false,
+        };
+
+        *stmt = if assignments.is_empty() {
+            builder::empty()
+        } else {
+            builder::expr_stmt(builder::comma(assignments))
+        };
+
+        true
+    }
+}
+
+impl<'a> VisitMut for VarHoister<'a> {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        if let Statement::For(ref mut inner) = *stmt {
+            let mut init_removed = false;
+            if let Some(ref mut init) = inner.init {
+                if self.hoist(init) {
+                    init_removed = match *init {
+                        Statement::Empty(_) => true,
+                        _ => false,
+                    };
+                }
+            }
+            if init_removed {
+                inner.init = None;
+            }
+            return visit::walk_statement(self, stmt);
+        }
+
+        if !self.hoist(stmt) {
+            visit::walk_statement(self, stmt)
+        }
+    }
+
+    fn visit_expression(&mut self, _expr: &mut Expression) {
+
+    }
+
+    fn visit_function(&mut self, _func: &mut Function) {
+
+    }
+
+    fn visit_class(&mut self, _class: &mut Class) {
+
+    }
+}
+
+
+#[derive(Default)]
+struct Exits {
+    // `break` of the extracted loop itself
+    breaks: bool,
+    // ( is_break, label ) of jumps to labels outside the loop
+    jumps: Vec<(bool, String)>,
+    returns: bool,
+}
+
+/// Turns the jumps out of the extracted loop body into `return`s of `_loop`.
+struct ExitRewriter<'a> {
+    label: Option<&'a str>,
+    sync: &'a [Statement],
+    loops: usize,
+    switches: usize,
+    labels: Vec<String>,
+    exits: Exits,
+}
+
+impl<'a> ExitRewriter<'a> {
+    fn exit(&self, value: Option<Expression>) -> Statement {
+        let ret = builder::return_stmt(value);
+        if self.sync.is_empty() {
+            return ret;
+        }
+
+        let mut body = self.sync.to_vec();
+        body.push(ret);
+        builder::block_stmt(body)
+    }
+
+    fn is_own_label(&self, label: &Identifier) -> bool {
+        self.label == Some(label.name())
+    }
+
+    fn is_inner_label(&self, label: &Identifier) -> bool {
+        self.labels.iter().any(|name| name == label.name())
+    }
+}
+
+impl<'a> VisitMut for ExitRewriter<'a> {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        let replacement = match *stmt {
+            Statement::Continue(ref inner) => match inner.label {
+                None if self.loops == 0 => Some(self.exit(None)),
+                Some(ref label) if self.is_own_label(label) => Some(self.exit(None)),
+                Some(ref label) if !self.is_inner_label(label) => {
+                    self.exits.jumps.push((false, label.name().to_string()));
+                    Some(self.exit(Some(builder::string(&format!("continue|{}", label.name())))))
+                },
+                _ => None,
+            },
+            Statement::Break(ref inner) => match inner.label {
+                None if self.loops == 0 && self.switches == 0 => {
+                    self.exits.breaks = true;
+                    Some(self.exit(Some(builder::string("break"))))
+                },
+                Some(ref label) if self.is_own_label(label) => {
+                    self.exits.breaks = true;
+                    Some(self.exit(Some(builder::string("break"))))
+                },
+                Some(ref label) if !self.is_inner_label(label) => {
+                    self.exits.jumps.push((true, label.name().to_string()));
+                    Some(self.exit(Some(builder::string(&format!("break|{}", label.name())))))
+                },
+                _ => None,
+            },
+            Statement::Return(ref mut inner) => {
+                // return x; → return { v: x };
+                self.exits.returns = true;
+                let value = inner.value.take().unwrap_or_else(builder::undefined);
+                Some(self.exit(Some(builder::object(vec![ builder::named_property("v", value) ]))))
+            },
+            _ => None,
+        };
+
+        if let Some(replacement) = replacement {
+            *stmt = replacement;
+            return;
+        }
+
+        match *stmt {
+            Statement::Labelled(ref mut inner) => {
+                self.labels.push(inner.label.name().to_string());
+                self.visit_statement(&mut inner.item);
+                self.labels.pop();
+            },
+            Statement::Switch(_) => {
+                self.switches += 1;
+                visit::walk_statement(self, stmt);
+                self.switches -= 1;
+            },
+            _ if is_loop(stmt) => {
+                self.loops += 1;
+                visit::walk_statement(self, stmt);
+                self.loops -= 1;
+            },
+            _ => visit::walk_statement(self, stmt),
+        }
+    }
+
+    // NOTE: 不进入嵌套函数，也不需要访问表达式
+    fn visit_expression(&mut self, _expr: &mut Expression) {
+
+    }
+}
+
+
+struct BlockScoping<'a> {
+    names: &'a mut NameGenerator,
+    // 每一层函数作用域中各个变量名出现的次数
+    functions: Vec<HashMap<String, usize>>,
+    // 当前循环体内（降级之后的）块级声明
+    lexicals: Vec<String>,
+}
+
+impl<'a> BlockScoping<'a> {
+    fn function_body(&mut self, mut counts: HashMap<String, usize>, body: &mut Vec<Statement>) {
+        merge(&mut counts, count_names(body));
+        self.functions.push(counts);
+        let lexicals = mem::replace(&mut self.lexicals, Vec::new());
+
+        self.visit_statements(body);
+        // NOTE: 函数顶层的声明不会与其它声明冲突，直接改为 `var`
+        for stmt in body.iter_mut() {
+            make_var(stmt, false);
+        }
+
+        self.lexicals = lexicals;
+        self.functions.pop();
+    }
+
+    fn params_counts(&mut self, params: &mut Vec<Expression>) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for param in params.iter_mut() {
+            self.visit_expression(param);
+            merge(&mut counts, count_names_in_expression(param));
+        }
+        counts
+    }
+
+    /// Picks a new name for every declaration of the scope that is also used outside of it.
+    fn renames(&mut self, declared: Vec<String>, inside: &HashMap<String, usize>) -> Vec<(String, String)> {
+        let mut renames = Vec::new();
+        for name in declared.into_iter() {
+            let inner = inside.get(&name).cloned().unwrap_or(0);
+            let total = self.functions.last().and_then(|counts| counts.get(&name)).cloned().unwrap_or(0);
+            if total <= inner {
+                continue;
+            }
+
+            let to = self.names.fresh(&name);
+            for counts in self.functions.iter_mut() {
+                if let Some(n) = counts.get_mut(&name) {
+                    *n = n.saturating_sub(inner);
+                }
+            }
+            renames.push((name, to));
+        }
+        renames
+    }
+
+    fn block_scope(&mut self, body: &mut Vec<Statement>) {
+        self.visit_statements(body);
+
+        let mut declared = Vec::new();
+        for stmt in body.iter() {
+            lexical_names(stmt, &mut declared);
+        }
+        if declared.is_empty() {
+            return;
+        }
+
+        let inside = count_names(body);
+        for (from, to) in self.renames(declared, &inside).iter() {
+            Renamer { from, to }.visit_statements(body);
+        }
+
+        for stmt in body.iter_mut() {
+            lexical_names(stmt, &mut self.lexicals);
+            make_var(stmt, true);
+        }
+    }
+
+    // NOTE: 所有 `case` 子句共享同一个作用域
+    fn switch_scope(&mut self, stmt: &mut Statement) {
+        let mut declared = Vec::new();
+        if let Statement::Switch(ref mut inner) = *stmt {
+            self.visit_expression(&mut inner.value);
+            for clause in inner.clauses.iter_mut() {
+                if let Some(ref mut value) = clause.value {
+                    self.visit_expression(value);
+                }
+                match clause.body {
+                    Statement::Block(ref mut block) => {
+                        self.visit_statements(&mut block.body);
+                        for item in block.body.iter() {
+                            lexical_names(item, &mut declared);
+                        }
+                    },
+                    ref mut body => {
+                        self.visit_statement(body);
+                        lexical_names(body, &mut declared);
+                    },
+                }
+            }
+        }
+        if declared.is_empty() {
+            return;
+        }
+
+        let inside = statement_counts(stmt);
+        for (from, to) in self.renames(declared, &inside).iter() {
+            Renamer { from, to }.visit_statement(stmt);
+        }
+
+        if let Statement::Switch(ref mut inner) = *stmt {
+            for clause in inner.clauses.iter_mut() {
+                match clause.body {
+                    Statement::Block(ref mut block) => {
+                        for item in block.body.iter_mut() {
+                            lexical_names(item, &mut self.lexicals);
+                            make_var(item, true);
+                        }
+                    },
+                    ref mut body => {
+                        lexical_names(body, &mut self.lexicals);
+                        make_var(body, true);
+                    },
+                }
+            }
+        }
+    }
+
+    /// Lowers the loop, returning the statements to put before it when its body was extracted.
+    fn loop_statement(&mut self, stmt: &mut Statement, label: Option<&str>) -> Option<Vec<Statement>> {
+        let outer = mem::replace(&mut self.lexicals, Vec::new());
+        visit::walk_statement(self, stmt);
+
+        // for (let i = 0; ...) 的声明作用域是整个 for 语句
+        let mut head = Vec::new();
+        if let Statement::For(ref inner) = *stmt {
+            if let Some(ref init) = inner.init {
+                lexical_names(init, &mut head);
+            }
+        }
+        if !head.is_empty() {
+            let inside = statement_counts(stmt);
+            for (from, to) in self.renames(head.clone(), &inside).iter() {
+                Renamer { from, to }.visit_statement(stmt);
+            }
+
+            head.clear();
+            if let Statement::For(ref mut inner) = *stmt {
+                if let Some(ref mut init) = inner.init {
+                    lexical_names(init, &mut head);
+                    make_var(init, false);
+                }
+            }
+        }
+
+        let body_lexicals = mem::replace(&mut self.lexicals, outer);
+        let names = head.iter().chain(body_lexicals.iter()).cloned().collect::<HashSet<String>>();
+        self.lexicals.extend(names.iter().cloned());
+        if names.is_empty() {
+            return None;
+        }
+
+        let body = loop_body(stmt)?;
+        let mut finder = CaptureFinder { names: &names, depth: 0, found: false };
+        finder.visit_statement(body);
+        // NOTE: 含有 `yield`/`await` 的循环体不能放进 `_loop` 函数，交给生成器降级处理
+        if !finder.found || has_suspension(body) {
+            return None;
+        }
+
+        let original = mem::replace(body, builder::empty());
+        let (prelude, new_body) = self.extract_body(original, &head, &names, label);
+        *body = new_body;
+
+        Some(prelude)
+    }
+
+    fn extract_body(&mut self, body: Statement, params: &[String], lexicals: &HashSet<String>, label: Option<&str>) -> (Vec<Statement>, Statement) {
+        let mut body = match body {
+            Statement::Block(block) => block.body,
+            other => vec![ other ],
+        };
+
+        let mut hoister = VarHoister { keep: lexicals, hoisted: Vec::new() };
+        hoister.visit_statements(&mut body);
+        let hoisted = hoister.hoisted;
+
+        // NOTE: 循环体修改了循环变量时，需要把新值同步回外面的循环变量
+        let mut writes = WriteFinder { names: params, written: HashSet::new() };
+        writes.visit_statements(&mut body);
+        let mut outs = Vec::new();
+        for param in params.iter() {
+            if writes.written.contains(param) {
+                outs.push((param.clone(), self.names.fresh(&format!("out_{}", param))));
+            }
+        }
+        let sync = outs.iter()
+            .map(|&(ref param, ref out)| builder::expr_stmt(builder::assign(builder::ident_expr(out), builder::ident_expr(param))))
+            .collect::<Vec<Statement>>();
+
+        let mut rewriter = ExitRewriter { label, sync: &sync, loops: 0, switches: 0, labels: Vec::new(), exits: Exits::default() };
+        rewriter.visit_statements(&mut body);
+        let exits = rewriter.exits;
+        body.extend(sync.iter().cloned());
+
+        let loop_name = self.names.fresh("loop");
+        let arrow = Expression::ArrowFunction(Box::new(ArrowFunctionExpression {
+            loc: Default::default(),
+            span: Default::default(),
+            is_async: false,
+            params: Expression::Parenthesized(Box::new(builder::paren(params.iter().map(|name| builder::ident_expr(name)).collect()))),
+            body: ConciseBody::Stmt(body),
+        }));
+
+        let mut prelude = Vec::new();
+        if !hoisted.is_empty() {
+            let declarators = hoisted.iter().map(|name| builder::declarator(builder::ident_expr(name), None)).collect();
+            prelude.push(builder::variable(LexicalDeclarationKind::Var, declarators));
+        }
+        prelude.push(builder::var(&loop_name, Some(arrow)));
+        if !outs.is_empty() {
+            let declarators = outs.iter().map(|&(_, ref out)| builder::declarator(builder::ident_expr(out), None)).collect();
+            prelude.push(builder::variable(LexicalDeclarationKind::Var, declarators));
+        }
+
+        let call = builder::call(builder::ident_expr(&loop_name), params.iter().map(|name| builder::ident_expr(name)).collect());
+        let sync_back = outs.iter()
+            .map(|&(ref param, ref out)| builder::expr_stmt(builder::assign(builder::ident_expr(param), builder::ident_expr(out))));
+
+        let mut new_body = Vec::new();
+        if !exits.breaks && !exits.returns && exits.jumps.is_empty() {
+            new_body.push(builder::expr_stmt(call));
+            new_body.extend(sync_back);
+            return (prelude, builder::block_stmt(new_body));
+        }
+
+        let ret = self.names.fresh("ret");
+        new_body.push(builder::var(&ret, Some(call)));
+        new_body.extend(sync_back);
+
+        let is = |value: &str| builder::infix(builder::ident_expr(&ret), InfixOperator::StrictEq, builder::string(value));
+        if exits.breaks {
+            new_body.push(builder::if_stmt(is("break"), builder::break_stmt(None), None));
+        }
+        let mut seen = HashSet::new();
+        for &(is_break, ref label) in exits.jumps.iter() {
+            if !seen.insert((is_break, label.clone())) {
+                continue;
+            }
+            if is_break {
+                new_body.push(builder::if_stmt(is(&format!("break|{}", label)), builder::break_stmt(Some(label)), None));
+            } else {
+                new_body.push(builder::if_stmt(is(&format!("continue|{}", label)), builder::continue_stmt(Some(label)), None));
+            }
+        }
+        if exits.returns {
+            // if (typeof _ret === "object") return _ret.v;
+            let condition = builder::infix(
+                builder::prefix(PrefixOperator::TypeOf, builder::ident_expr(&ret)),
+                InfixOperator::StrictEq,
+                builder::string("object"),
+            );
+            let value = builder::member(builder::ident_expr(&ret), "v");
+            new_body.push(builder::if_stmt(condition, builder::return_stmt(Some(value)), None));
+        }
+
+        (prelude, builder::block_stmt(new_body))
+    }
+}
+
+fn wrap(stmt: &mut Statement, mut prelude: Vec<Statement>) {
+    prelude.push(mem::replace(stmt, builder::empty()));
+    *stmt = builder::block_stmt(prelude);
+}
+
+impl<'a> VisitMut for BlockScoping<'a> {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        match *stmt {
+            Statement::Block(ref mut inner) => self.block_scope(&mut inner.body),
+            Statement::Try(ref mut inner) => {
+                self.block_scope(&mut inner.body.body);
+                if let Some(ref mut param) = inner.catch_parameter {
+                    self.visit_expression(param);
+                }
+                if let Some(ref mut catch_body) = inner.catch_body {
+                    self.block_scope(&mut catch_body.body);
+                }
+                if let Some(ref mut finally) = inner.finally {
+                    self.block_scope(&mut finally.body);
+                }
+            },
+            Statement::Switch(_) => self.switch_scope(stmt),
+            Statement::Labelled(ref mut inner) if is_loop(&inner.item) => {
+                let label = inner.label.name().to_string();
+                if let Some(prelude) = self.loop_statement(&mut inner.item, Some(&label)) {
+                    wrap(stmt, prelude);
+                }
+            },
+            _ if is_loop(stmt) => {
+                if let Some(prelude) = self.loop_statement(stmt, None) {
+                    wrap(stmt, prelude);
+                }
+            },
+            _ => visit::walk_statement(self, stmt),
+        }
+    }
+
+    fn visit_function(&mut self, func: &mut Function) {
+        let counts = self.params_counts(&mut func.params.items);
+        self.function_body(counts, &mut func.body);
+    }
+
+    fn visit_arrow_function(&mut self, arrow: &mut ArrowFunctionExpression) {
+        self.visit_expression(&mut arrow.params);
+        let counts = count_names_in_expression(&mut arrow.params);
+        match arrow.body {
+            ConciseBody::Expr(ref mut body) => self.visit_expression(body),
+            ConciseBody::Stmt(ref mut body) => self.function_body(counts, body),
+        }
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        match *method {
+            MethodDefinition::Method(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                let counts = self.params_counts(&mut inner.params.items);
+                self.function_body(counts, &mut inner.body);
+            },
+            MethodDefinition::Getter(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                self.function_body(HashMap::new(), &mut inner.body);
+            },
+            MethodDefinition::Setter(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                let counts = self.params_counts(&mut inner.params.items);
+                self.function_body(counts, &mut inner.body);
+            },
+        }
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, names: &mut NameGenerator) -> Result<(), Error> {
+    let mut pass = BlockScoping { names, functions: Vec::new(), lexicals: Vec::new() };
+    pass.function_body(HashMap::new(), body);
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+fn let_stmt(name: &str, init: Option<Expression>) -> Statement {
+    builder::variable(LexicalDeclarationKind::Let, vec![ builder::declarator(builder::ident_expr(name), init) ])
+}
+
+#[test]
+fn test_block_scoping_shadowing() {
+    use crate::compiler::transform::lower_and_print;
+
+    // let x = 1; { let x = 2; f(x); } { const x = 3; } f(x);
+    let body = vec![
+        let_stmt("x", Some(builder::number(1))),
+        builder::block_stmt(vec![
+            let_stmt("x", Some(builder::number(2))),
+            builder::expr_stmt(builder::call(builder::ident_expr("f"), vec![ builder::ident_expr("x") ])),
+        ]),
+        builder::block_stmt(vec![
+            builder::variable(LexicalDeclarationKind::Const, vec![ builder::declarator(builder::ident_expr("x"), Some(builder::number(3))) ]),
+        ]),
+        builder::expr_stmt(builder::call(builder::ident_expr("f"), vec![ builder::ident_expr("x") ])),
+    ];
+    assert_eq!(lower_and_print(body), "var x=1;{var _x=2;f(_x);}{var _x2=3;}f(x);");
+}
+
+#[test]
+fn test_block_scoping_loop_closure() {
+    use crate::compiler::transform::{ parse_owned, lower_and_print, };
+
+    // for (let i = 0; i < n; i++) { if (i) break; fns.push(() => i + this.k); }
+    let closure = parse_owned("fns.push(() => i + this.k)").remove(0);
+    let body = builder::block_stmt(vec![
+        builder::if_stmt(builder::ident_expr("i"), builder::break_stmt(None), None),
+        closure,
+    ]);
+    let for_stmt = Statement::For(Box::new(ForStatement {
+        loc: Default::default(),
+        span: Default::default(),
+        init: Some(let_stmt("i", Some(builder::number(0)))),
+        condition: Some(builder::infix(builder::ident_expr("i"), InfixOperator::Lt, builder::ident_expr("n"))),
+        finally: Some(builder::prefix(PrefixOperator::Increment, builder::ident_expr("i"))),
+        body,
+    }));
+
+    assert_eq!(
+        lower_and_print(vec![ for_stmt ]),
+        concat!(
+            "var _this=this;",
+            "{var _loop=function(i){if(i)return\"break\";fns.push(function(){return i+_this.k;});};",
+            "for(var i=0;i<n;++i){var _ret=_loop(i);if(_ret===\"break\")break;}}",
+        )
+    );
+}
@@ -0,0 +1,453 @@
+// Classes → constructor functions
+//
+//      class B extends A { constructor(x) { super(x); } m() { return super.m(); } static s() { } get g() { } }
+//
+//      var B = (function (_super) {
+//          function B(x) {
+//              if (!(this instanceof B)) throw new TypeError("Cannot call a class as a function");
+//              _super.call(this, x);
+//          }
+//          B.prototype = Object.create(_super && _super.prototype, { constructor: { value: B, writable: true, configurable: true } });
+//          if (_super) Object.setPrototypeOf ? Object.setPrototypeOf(B, _super) : B.__proto__ = _super;
+//          B.prototype.m = function () { return _super.prototype.m.call(this); };
+//          B.s = function () { };
+//          Object.defineProperty(B.prototype, "g", { get: function () { }, configurable: true });
+//          return B;
+//      })(A);
+//
+// NOTE: 方法通过赋值定义，因此是可枚举的（与 loose 模式一致）；访问器使用 `Object.defineProperty`，不可枚举。
+
+use crate::error::{ ErrorKind, Error, };
+use crate::lexer::operator::{ PrefixOperator, InfixOperator, AssignmentOperator, };
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{ NameGenerator, flatten_arguments, directive_prologue, };
+
+use std::mem;
+
+
+// NOTE: 方法名作为属性的两种形式：`target.name` 或者 `target[expr]`
+fn method_key(name: Expression) -> Result<String, Expression> {
+    match name {
+        Expression::Identifier(ident) => Ok(ident.name().to_string()),
+        other => Err(other),
+    }
+}
+
+fn key_member(target: Expression, key: &Result<String, Expression>) -> Expression {
+    match *key {
+        Ok(ref name) => builder::member(target, name),
+        Err(ref expr) => builder::computed_member(target, expr.clone()),
+    }
+}
+
+fn key_literal(key: &Result<String, Expression>) -> Expression {
+    match *key {
+        Ok(ref name) => builder::string(name),
+        Err(ref expr) => expr.clone(),
+    }
+}
+
+fn is_constructor(method: &ClassMethodDefinition) -> bool {
+    if method.is_static {
+        return false;
+    }
+
+    match method.method {
+        MethodDefinition::Method(ref inner) => match inner.name {
+            Expression::Identifier(ref ident) => ident.name() == "constructor",
+            Expression::String(ref lit) => lit.raw == "constructor",
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+
+/// Rewrites `super` inside one method body.
+struct SuperRewriter<'a> {
+    // `_super`, `None` when the class has no heritage
+    parent: Option<&'a str>,
+    is_static: bool,
+    error: Option<Error>,
+}
+
+impl<'a> SuperRewriter<'a> {
+    // NOTE: 没有 `extends` 时，`super.x` 指向 `Object.prototype` 或者 `Function.prototype`
+    fn home(&self) -> Expression {
+        match (self.parent, self.is_static) {
+            (Some(parent), false) => builder::member(builder::ident_expr(parent), "prototype"),
+            (Some(parent), true) => builder::ident_expr(parent),
+            (None, false) => builder::member(builder::ident_expr("Object"), "prototype"),
+            (None, true) => builder::member(builder::ident_expr("Function"), "prototype"),
+        }
+    }
+}
+
+fn is_super(expr: &Expression) -> bool {
+    match *expr {
+        Expression::Super(_) => true,
+        _ => false,
+    }
+}
+
+fn is_super_member(expr: &Expression) -> bool {
+    match *expr {
+        Expression::Member(ref inner) => is_super(&inner.left),
+        _ => false,
+    }
+}
+
+impl<'a> VisitMut for SuperRewriter<'a> {
+    // NOTE: 普通函数不能使用 `super`，对象字面量的方法有自己的 HomeObject
+    fn visit_function(&mut self, _func: &mut Function) {
+
+    }
+
+    fn visit_method_definition(&mut self, _method: &mut MethodDefinition) {
+
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        match *expr {
+            // super(...args) → _super.call(this, ...args)
+            Expression::Call(ref mut inner) if is_super(&inner.callee) => {
+                match self.parent {
+                    Some(parent) => {
+                        inner.callee = builder::member(builder::ident_expr(parent), "call");
+                        flatten_arguments(&mut inner.arguments);
+                        inner.arguments.items.insert(0, builder::this());
+                    },
+                    None => {
+                        self.error = Some(Error::new(ErrorKind::SyntaxError, "'super' keyword unexpected here"));
+                    },
+                }
+            },
+            // super.m(...args) → _super.prototype.m.call(this, ...args)
+            Expression::Call(ref mut inner) if is_super_member(&inner.callee) => {
+                if let Expression::Member(ref mut member) = inner.callee {
+                    member.left = self.home();
+                }
+                let callee = mem::replace(&mut inner.callee, builder::null());
+                inner.callee = builder::member(callee, "call");
+                flatten_arguments(&mut inner.arguments);
+                inner.arguments.items.insert(0, builder::this());
+            },
+            // super.x = v → this.x = v
+            Expression::Assignment(ref mut inner) if is_super_member(&inner.left) => {
+                if let Expression::Member(ref mut member) = inner.left {
+                    member.left = builder::this();
+                }
+            },
+            // super.x → _super.prototype.x
+            Expression::Member(ref mut inner) if is_super(&inner.left) => {
+                inner.left = self.home();
+            },
+            _ => { },
+        }
+
+        visit::walk_expression(self, expr)
+    }
+}
+
+
+enum Member {
+    Assignment(Statement),
+    Accessor {
+        is_static: bool,
+        key: Result<String, Expression>,
+        get: Option<Expression>,
+        set: Option<Expression>,
+    },
+}
+
+struct ClassLowering<'a> {
+    names: &'a mut NameGenerator,
+    error: Option<Error>,
+}
+
+impl<'a> ClassLowering<'a> {
+    fn rewrite_super(&mut self, body: &mut Vec<Statement>, parent: Option<&str>, is_static: bool) {
+        let mut rewriter = SuperRewriter { parent, is_static, error: None };
+        rewriter.visit_statements(body);
+        if let Some(err) = rewriter.error {
+            self.error.get_or_insert(err);
+        }
+    }
+
+    fn lower_class(&mut self, name: &str, class: Class) -> Expression {
+        let parent = class.heritage.as_ref().map(|_| self.names.fresh("super"));
+        let parent = parent.as_ref().map(|name| name.as_str());
+
+        let mut constructor = None;
+        let mut members: Vec<Member> = Vec::new();
+
+        for method in class.body.into_iter() {
+            if is_constructor(&method) {
+                if let MethodDefinition::Method(inner) = method.method {
+                    constructor = Some((inner.params, inner.body));
+                }
+                continue;
+            }
+
+            let is_static = method.is_static;
+            let target = if is_static {
+                builder::ident_expr(name)
+            } else {
+                builder::member(builder::ident_expr(name), "prototype")
+            };
+
+            match method.method {
+                MethodDefinition::Method(mut inner) => {
+                    self.rewrite_super(&mut inner.body, parent, is_static);
+                    let key = method_key(inner.name);
+                    let value = Expression::Function(Box::new(FunctionExpression {
+                        loc: inner.loc,
+                        span: inner.span,
+                        is_async: inner.is_async,
+                        is_generator: inner.is_generator,
+                        name: None,
+                        func: Function { loc: inner.loc, span: inner.span, params: inner.params, body: inner.body },
+                    }));
+                    members.push(Member::Assignment(builder::expr_stmt(builder::assign(key_member(target, &key), value))));
+                },
+                MethodDefinition::Getter(mut inner) => {
+                    self.rewrite_super(&mut inner.body, parent, is_static);
+                    let getter = Expression::Function(Box::new(FunctionExpression {
+                        loc: inner.loc,
+                        span: inner.span,
+                        is_async: false,
+                        is_generator: false,
+                        name: None,
+                        func: Function { loc: inner.loc, span: inner.span, params: builder::paren(vec![]), body: inner.body },
+                    }));
+                    add_accessor(&mut members, is_static, method_key(inner.name), Some(getter), None);
+                },
+                MethodDefinition::Setter(mut inner) => {
+                    self.rewrite_super(&mut inner.body, parent, is_static);
+                    let setter = Expression::Function(Box::new(FunctionExpression {
+                        loc: inner.loc,
+                        span: inner.span,
+                        is_async: false,
+                        is_generator: false,
+                        name: None,
+                        func: Function { loc: inner.loc, span: inner.span, params: inner.params, body: inner.body },
+                    }));
+                    add_accessor(&mut members, is_static, method_key(inner.name), None, Some(setter));
+                },
+            }
+        }
+
+        let (params, mut body) = match constructor {
+            Some((params, body)) => (params, body),
+            None => {
+                let mut body = Vec::new();
+                if let Some(parent) = parent {
+                    // _super.apply(this, arguments);
+                    let apply = builder::member(builder::ident_expr(parent), "apply");
+                    body.push(builder::expr_stmt(builder::call(apply, vec![ builder::this(), builder::ident_expr("arguments") ])));
+                }
+                (builder::paren(vec![]), body)
+            },
+        };
+        self.rewrite_super(&mut body, parent, false);
+
+        // if (!(this instanceof A)) throw new TypeError("Cannot call a class as a function");
+        let check = builder::if_stmt(
+            builder::prefix(PrefixOperator::Not, builder::parenthesized(
+                builder::infix(builder::this(), InfixOperator::InstanceOf, builder::ident_expr(name))
+            )),
+            builder::throw_stmt(builder::new(builder::ident_expr("TypeError"), vec![ builder::string("Cannot call a class as a function") ])),
+            None,
+        );
+        let index = directive_prologue(&body);
+        body.insert(index, check);
+
+        let mut stmts = vec![
+            Statement::Function(Box::new(FunctionDeclaration {
+                loc: class.loc,
+                span: class.span,
+                is_async: false,
+                is_generator: false,
+                name: builder::ident(name),
+                func: Function { loc: class.loc, span: class.span, params, body },
+            })),
+        ];
+
+        if let Some(parent) = parent {
+            stmts.extend(inherits(name, parent));
+        }
+
+        for member in members.into_iter() {
+            match member {
+                Member::Assignment(stmt) => stmts.push(stmt),
+                Member::Accessor { is_static, key, get, set } => {
+                    let target = if is_static {
+                        builder::ident_expr(name)
+                    } else {
+                        builder::member(builder::ident_expr(name), "prototype")
+                    };
+                    let mut descriptor = Vec::new();
+                    if let Some(get) = get {
+                        descriptor.push(builder::named_property("get", get));
+                    }
+                    if let Some(set) = set {
+                        descriptor.push(builder::named_property("set", set));
+                    }
+                    descriptor.push(builder::named_property("configurable", builder::boolean(true)));
+
+                    let define = builder::member(builder::ident_expr("Object"), "defineProperty");
+                    let args = vec![ target, key_literal(&key), builder::object(descriptor) ];
+                    stmts.push(builder::expr_stmt(builder::call(define, args)));
+                },
+            }
+        }
+
+        stmts.push(builder::return_stmt(Some(builder::ident_expr(name))));
+
+        match (parent, class.heritage) {
+            (Some(parent), Some(heritage)) => builder::iife(vec![ builder::ident_expr(parent) ], stmts, vec![ heritage ]),
+            _ => builder::iife(vec![], stmts, vec![]),
+        }
+    }
+}
+
+fn add_accessor(members: &mut Vec<Member>,
+                is_static: bool,
+                key: Result<String, Expression>,
+                get: Option<Expression>,
+                set: Option<Expression>) {
+    // NOTE: 同名的 getter 和 setter 合并为一个属性描述符
+    if let Ok(ref name) = key {
+        for member in members.iter_mut() {
+            if let Member::Accessor { is_static: other_static, key: Ok(ref other), get: ref mut other_get, set: ref mut other_set } = *member {
+                if other_static == is_static && other == name {
+                    if get.is_some() {
+                        *other_get = get;
+                    }
+                    if set.is_some() {
+                        *other_set = set;
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    members.push(Member::Accessor { is_static, key, get, set });
+}
+
+fn inherits(name: &str, parent: &str) -> Vec<Statement> {
+    // A.prototype = Object.create(_super && _super.prototype, { constructor: { value: A, writable: true, configurable: true } });
+    let descriptor = builder::object(vec![
+        builder::named_property("value", builder::ident_expr(name)),
+        builder::named_property("writable", builder::boolean(true)),
+        builder::named_property("configurable", builder::boolean(true)),
+    ]);
+    let proto = builder::infix(
+        builder::ident_expr(parent),
+        InfixOperator::And,
+        builder::member(builder::ident_expr(parent), "prototype"),
+    );
+    let create = builder::call(
+        builder::member(builder::ident_expr("Object"), "create"),
+        vec![ proto, builder::object(vec![ builder::named_property("constructor", descriptor) ]) ],
+    );
+    let prototype = builder::expr_stmt(builder::assign(builder::member(builder::ident_expr(name), "prototype"), create));
+
+    // if (_super) Object.setPrototypeOf ? Object.setPrototypeOf(A, _super) : A.__proto__ = _super;
+    let set_prototype_of = builder::member(builder::ident_expr("Object"), "setPrototypeOf");
+    let statics = builder::if_stmt(
+        builder::ident_expr(parent),
+        builder::expr_stmt(builder::conditional(
+            set_prototype_of.clone(),
+            builder::call(set_prototype_of, vec![ builder::ident_expr(name), builder::ident_expr(parent) ]),
+            builder::assign_op(
+                builder::member(builder::ident_expr(name), "__proto__"),
+                AssignmentOperator::Assign,
+                builder::ident_expr(parent),
+            ),
+        )),
+        None,
+    );
+
+    vec![ prototype, statics ]
+}
+
+impl<'a> VisitMut for ClassLowering<'a> {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        visit::walk_statement(self, stmt);
+
+        if let Statement::Class(_) = *stmt {
+            let decl = match mem::replace(stmt, builder::empty()) {
+                Statement::Class(decl) => *decl,
+                _ => unreachable!(),
+            };
+            let name = decl.name.name().to_string();
+            let value = self.lower_class(&name, decl.class);
+            *stmt = builder::var(&name, Some(value));
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        visit::walk_expression(self, expr);
+
+        if let Expression::Class(_) = *expr {
+            let class = match mem::replace(expr, builder::null()) {
+                Expression::Class(class) => *class,
+                _ => unreachable!(),
+            };
+            let name = match class.name {
+                Some(ref name) => name.name().to_string(),
+                None => self.names.fresh("class"),
+            };
+            *expr = self.lower_class(&name, class.class);
+        }
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, names: &mut NameGenerator) -> Result<(), Error> {
+    let mut pass = ClassLowering { names, error: None };
+    pass.visit_statements(body);
+
+    match pass.error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+
+#[test]
+fn test_class_lowering() {
+    use crate::compiler::transform::{ parse_owned, lower_and_print, };
+
+    assert_eq!(
+        lower_and_print(parse_owned("class A { constructor(x) { this.x = x } get x() { } static y() { } set x(v) { } }")),
+        concat!(
+            "var A=(function(){",
+            "function A(x){if(!(this instanceof A))throw new TypeError(\"Cannot call a class as a function\");this.x=x;}",
+            "Object.defineProperty(A.prototype,\"x\",{get:function(){},set:function(v){},configurable:true});",
+            "A.y=function(){};",
+            "return A;})();",
+        )
+    );
+
+    // NOTE: 解析器暂时不支持 `extends`，这里手动构造继承
+    let mut body = parse_owned("class B { constructor() { super(1) } m() { super.m(2) } }");
+    if let Statement::Class(ref mut decl) = body[0] {
+        decl.class.heritage = Some(builder::ident_expr("A"));
+    }
+    assert_eq!(
+        lower_and_print(body),
+        concat!(
+            "var B=(function(_super){",
+            "function B(){if(!(this instanceof B))throw new TypeError(\"Cannot call a class as a function\");_super.call(this,1);}",
+            "B.prototype=Object.create(_super&&_super.prototype,{constructor:{value:B,writable:true,configurable:true}});",
+            "if(_super)Object.setPrototypeOf?Object.setPrototypeOf(B,_super):B.__proto__=_super;",
+            "B.prototype.m=function(){_super.prototype.m.call(this,2);};",
+            "return B;})(A);",
+        )
+    );
+}
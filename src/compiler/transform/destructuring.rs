@@ -0,0 +1,826 @@
+// Destructuring, default and rest parameters → plain assignments
+//
+//      var { a, b: [c, d = 1], ...e } = obj;
+//
+//      var a = obj.a, _ref = obj.b, c = _ref[0], _ref2 = _ref[1], d = _ref2 === void 0 ? 1 : _ref2,
+//          e = _objectWithoutProperties(obj, ["a", "b"]);
+//
+//      function f(x = 1, { y }, ...z) { }
+//
+//      function f(x, _ref) { if (x === void 0) x = 1; var y = _ref.y; var z = Array.prototype.slice.call(arguments, 2); }
+//
+// NOTE: 数组模式按下标读取（与 loose 模式一致），只支持类数组的值，不会调用迭代器。
+//       `for-of` 同样降级为按下标遍历的 `for` 循环。
+//       目标版本支持解构（ES2015 - ES2017）时只降级包含对象剩余属性的模式：
+//
+//      var [{ a, ...b }] = xs;     →   var [_ref] = xs, a = _ref.a, b = _objectWithoutProperties(_ref, ["a"]);
+
+use crate::error::Error;
+use crate::lexer::operator::{ InfixOperator, PostfixOperator, AssignmentOperator, };
+use crate::ast::statement::LexicalDeclarationKind;
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{
+    NameGenerator, Helper, Helpers, Temporaries,
+    array_slice, flatten_arguments, count_names_in_expression,
+};
+use crate::compiler::transform::arrow::arrow_params;
+
+use std::mem;
+
+
+fn is_pattern(expr: &Expression) -> bool {
+    match *expr {
+        Expression::BindingPattern(_) | Expression::AssignmentPattern(_) => true,
+        _ => false,
+    }
+}
+
+fn is_array_pattern(expr: &Expression) -> bool {
+    match *expr {
+        Expression::BindingPattern(ref pattern) => match **pattern {
+            BindingPattern::Array(_) => true,
+            _ => false,
+        },
+        Expression::AssignmentPattern(ref pattern) => match **pattern {
+            AssignmentPattern::Array(_) => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn has_binding_object_rest(pattern: &BindingPattern) -> bool {
+    fn binding_element(elem: &BindingElement) -> bool {
+        match *elem {
+            BindingElement::SingleNameBinding { .. } => false,
+            BindingElement::BindingPattern { ref pattern, .. } => has_binding_object_rest(pattern),
+        }
+    }
+
+    match *pattern {
+        BindingPattern::Object(ref inner) => inner.properties.iter().any(|prop| match *prop {
+            BindingProperty::SingleNameBinding { .. } => false,
+            BindingProperty::Property { ref value, .. } => binding_element(value),
+            BindingProperty::Spread { .. } => true,
+        }),
+        BindingPattern::Array(ref inner) => {
+            inner.elems.iter().any(|elem| elem.as_ref().map(binding_element).unwrap_or(false))
+                || inner.rest_elem.as_ref().map(|rest| match **rest {
+                    BindingRestElement::Identifier(_) => false,
+                    BindingRestElement::BindingPattern(ref pattern) => has_binding_object_rest(pattern),
+                }).unwrap_or(false)
+        },
+    }
+}
+
+/// `{ ...rest }` anywhere in a pattern, a parameter ( `pattern = init`, `...pattern` ) included.
+fn has_object_rest(expr: &Expression) -> bool {
+    fn assignment_pattern(pattern: &AssignmentPattern) -> bool {
+        match *pattern {
+            AssignmentPattern::Object(ref inner) => inner.properties.iter().any(|prop| match *prop {
+                AssignmentProperty::Identifier { .. } => false,
+                AssignmentProperty::Property { ref value, .. } => has_object_rest(&value.elem),
+                AssignmentProperty::Spread { .. } => true,
+            }),
+            AssignmentPattern::Array(ref inner) => {
+                inner.elems.iter().any(|elem| elem.as_ref().map(|elem| has_object_rest(&elem.elem)).unwrap_or(false))
+                    || inner.rest_elem.as_ref().map(has_object_rest).unwrap_or(false)
+            },
+        }
+    }
+
+    match *expr {
+        Expression::BindingPattern(ref pattern) => has_binding_object_rest(pattern),
+        Expression::AssignmentPattern(ref pattern) => assignment_pattern(pattern),
+        Expression::Assignment(ref inner) => has_object_rest(&inner.left),
+        Expression::Spread(ref inner) => has_object_rest(&inner.item),
+        _ => false,
+    }
+}
+
+fn is_simple_param(expr: &Expression) -> bool {
+    match *expr {
+        Expression::Identifier(_) => true,
+        _ => false,
+    }
+}
+
+
+/// Lowers one pattern into `(target, value)` pairs, evaluated in order.
+struct Destructure<'a> {
+    names: &'a mut NameGenerator,
+    helpers: &'a mut Helpers,
+    pairs: Vec<(Expression, Expression)>,
+    temps: Vec<String>,
+    patterns: bool,
+}
+
+impl<'a> Destructure<'a> {
+    fn temp(&mut self, value: Expression) -> Expression {
+        let name = self.names.fresh("ref");
+        self.pairs.push((builder::ident_expr(&name), value));
+        self.temps.push(name.clone());
+        builder::ident_expr(&name)
+    }
+
+    // NOTE: 值会被读取多次，除非是 `this` 或者不会被模式本身修改的变量，否则先保存到临时变量
+    fn reference(&mut self, value: Expression, target: &mut Expression) -> Expression {
+        let reusable = match value {
+            Expression::This(_) => true,
+            Expression::Identifier(ref ident) => !count_names_in_expression(target).contains_key(ident.name()),
+            _ => false,
+        };
+
+        if reusable { value } else { self.temp(value) }
+    }
+
+    fn bind(&mut self, mut target: Expression, value: Expression) {
+        if !is_pattern(&target) || !(self.patterns || has_object_rest(&target)) {
+            self.pairs.push((target, value));
+            return;
+        }
+
+        // NOTE: 只降级对象剩余属性时，数组模式保持原样，其中包含对象剩余属性的元素换成临时变量
+        if !self.patterns && is_array_pattern(&target) {
+            let mut rests = Vec::new();
+            self.split_array_pattern(&mut target, &mut rests);
+            self.pairs.push((target, value));
+            for (pattern, temp) in rests.into_iter() {
+                self.bind(pattern, temp);
+            }
+            return;
+        }
+
+        let value = self.reference(value, &mut target);
+        match target {
+            Expression::BindingPattern(pattern) => self.binding_pattern(*pattern, value),
+            Expression::AssignmentPattern(pattern) => self.assignment_pattern(*pattern, value),
+            _ => unreachable!(),
+        }
+    }
+
+    // target = _ref === void 0 ? init : _ref
+    fn bind_default(&mut self, target: Expression, value: Expression, init: Option<Expression>) {
+        match init {
+            None => self.bind(target, value),
+            Some(init) => {
+                let value = self.temp(value);
+                let test = builder::infix(value.clone(), InfixOperator::StrictEq, builder::undefined());
+                self.bind(target, builder::conditional(test, init, value))
+            },
+        }
+    }
+
+    fn fresh_temp(&mut self) -> String {
+        let name = self.names.fresh("ref");
+        self.temps.push(name.clone());
+        name
+    }
+
+    /// `[{ ...a }, ...[{ ...b }]]` → `[_ref, ..._ref2]`, the replaced patterns are bound to the temporaries afterwards.
+    fn split_array_pattern(&mut self, target: &mut Expression, rests: &mut Vec<(Expression, Expression)>) {
+        match *target {
+            Expression::BindingPattern(ref mut pattern) => if let BindingPattern::Array(ref mut inner) = **pattern {
+                for elem in inner.elems.iter_mut() {
+                    let split = match *elem {
+                        Some(BindingElement::BindingPattern { ref pattern, .. }) => has_binding_object_rest(pattern),
+                        _ => false,
+                    };
+                    if !split {
+                        continue;
+                    }
+                    if let Some(BindingElement::BindingPattern { loc, span, pattern, init }) = elem.take() {
+                        let name = self.fresh_temp();
+                        rests.push((Expression::BindingPattern(Box::new(pattern)), builder::ident_expr(&name)));
+                        *elem = Some(BindingElement::SingleNameBinding { loc, span, name: builder::ident(&name), init });
+                    }
+                }
+
+                if let Some(ref mut rest) = inner.rest_elem {
+                    let split = match **rest {
+                        BindingRestElement::BindingPattern(ref pattern) => has_binding_object_rest(pattern),
+                        _ => false,
+                    };
+                    if split {
+                        let name = self.fresh_temp();
+                        if let BindingRestElement::BindingPattern(pattern) = mem::replace(&mut **rest, BindingRestElement::Identifier(builder::ident(&name))) {
+                            rests.push((Expression::BindingPattern(Box::new(pattern)), builder::ident_expr(&name)));
+                        }
+                    }
+                }
+            },
+            Expression::AssignmentPattern(ref mut pattern) => if let AssignmentPattern::Array(ref mut inner) = **pattern {
+                let elems = inner.elems.iter_mut().filter_map(|elem| elem.as_mut().map(|elem| &mut elem.elem));
+                for elem in elems.chain(inner.rest_elem.as_mut()) {
+                    if has_object_rest(elem) {
+                        let name = self.fresh_temp();
+                        rests.push((mem::replace(elem, builder::ident_expr(&name)), builder::ident_expr(&name)));
+                    }
+                }
+            },
+            _ => { },
+        }
+    }
+
+    /// `object.key`, recording the key for a following `...rest`.
+    fn property(&mut self, object: Expression, name: PropertyName, keys: &mut Option<Vec<Expression>>) -> Expression {
+        let (access, key) = match name {
+            PropertyName::Identifier(ident) => (builder::member(object, ident.name()), builder::string(ident.name())),
+            PropertyName::String(lit) => {
+                let key = Expression::String(Box::new(lit));
+                (builder::computed_member(object, key.clone()), key)
+            },
+            PropertyName::Numberic(lit) => {
+                let key = Expression::Numeric(Box::new(lit));
+                (builder::computed_member(object, key.clone()), builder::call(builder::ident_expr("String"), vec![ key ]))
+            },
+            PropertyName::Computed(expr) => {
+                if keys.is_none() {
+                    return builder::computed_member(object, expr);
+                }
+
+                // NOTE: 计算属性名只能求值一次
+                let key = self.temp(expr);
+                (builder::computed_member(object, key.clone()), builder::call(builder::ident_expr("String"), vec![ key ]))
+            },
+        };
+
+        if let Some(ref mut keys) = *keys {
+            keys.push(key);
+        }
+
+        access
+    }
+
+    fn object_rest(&mut self, object: Expression, keys: &Option<Vec<Expression>>) -> Expression {
+        let helper = self.helpers.get(self.names, Helper::ObjectWithoutProperties);
+        let keys = keys.clone().unwrap_or_default();
+        builder::call(builder::ident_expr(&helper), vec![ object, builder::array(keys) ])
+    }
+
+    fn binding_element(&mut self, elem: BindingElement, value: Expression) {
+        match elem {
+            BindingElement::SingleNameBinding { name, init, .. } => {
+                self.bind_default(Expression::Identifier(Box::new(name)), value, init)
+            },
+            BindingElement::BindingPattern { pattern, init, .. } => {
+                self.bind_default(Expression::BindingPattern(Box::new(pattern)), value, init)
+            },
+        }
+    }
+
+    fn binding_pattern(&mut self, pattern: BindingPattern, value: Expression) {
+        match pattern {
+            BindingPattern::Object(inner) => {
+                let has_rest = inner.properties.iter().any(|prop| match *prop {
+                    BindingProperty::Spread { .. } => true,
+                    _ => false,
+                });
+                let mut keys = if has_rest { Some(Vec::new()) } else { None };
+
+                for prop in inner.properties.into_iter() {
+                    match prop {
+                        BindingProperty::SingleNameBinding { name, init, .. } => {
+                            let access = self.property(value.clone(), PropertyName::Identifier(name.clone()), &mut keys);
+                            self.bind_default(Expression::Identifier(Box::new(name)), access, init);
+                        },
+                        BindingProperty::Property { name, value: elem, .. } => {
+                            let access = self.property(value.clone(), name, &mut keys);
+                            self.binding_element(elem, access);
+                        },
+                        BindingProperty::Spread { name, .. } => {
+                            let rest = self.object_rest(value.clone(), &keys);
+                            self.pairs.push((Expression::Identifier(Box::new(name)), rest));
+                        },
+                    }
+                }
+            },
+            BindingPattern::Array(inner) => {
+                let len = inner.elems.len();
+                for (index, elem) in inner.elems.into_iter().enumerate() {
+                    if let Some(elem) = elem {
+                        let access = builder::computed_member(value.clone(), builder::number(index as i64));
+                        self.binding_element(elem, access);
+                    }
+                }
+
+                if let Some(rest) = inner.rest_elem {
+                    let rest_value = array_slice(value, len);
+                    match *rest {
+                        BindingRestElement::Identifier(ident) => self.pairs.push((Expression::Identifier(Box::new(ident)), rest_value)),
+                        BindingRestElement::BindingPattern(pattern) => self.bind(Expression::BindingPattern(Box::new(pattern)), rest_value),
+                    }
+                }
+            },
+        }
+    }
+
+    fn assignment_pattern(&mut self, pattern: AssignmentPattern, value: Expression) {
+        match pattern {
+            AssignmentPattern::Object(inner) => {
+                let has_rest = inner.properties.iter().any(|prop| match *prop {
+                    AssignmentProperty::Spread { .. } => true,
+                    _ => false,
+                });
+                let mut keys = if has_rest { Some(Vec::new()) } else { None };
+
+                for prop in inner.properties.into_iter() {
+                    match prop {
+                        AssignmentProperty::Identifier { name, init, .. } => {
+                            let access = self.property(value.clone(), PropertyName::Identifier(name.clone()), &mut keys);
+                            self.bind_default(Expression::Identifier(Box::new(name)), access, init);
+                        },
+                        AssignmentProperty::Property { name, value: elem, .. } => {
+                            let access = self.property(value.clone(), name, &mut keys);
+                            self.bind_default(elem.elem, access, elem.init);
+                        },
+                        AssignmentProperty::Spread { target, .. } => {
+                            let rest = self.object_rest(value.clone(), &keys);
+                            self.bind(target, rest);
+                        },
+                    }
+                }
+            },
+            AssignmentPattern::Array(inner) => {
+                let len = inner.elems.len();
+                for (index, elem) in inner.elems.into_iter().enumerate() {
+                    if let Some(elem) = elem {
+                        let access = builder::computed_member(value.clone(), builder::number(index as i64));
+                        self.bind_default(elem.elem, access, elem.init);
+                    }
+                }
+
+                if let Some(rest) = inner.rest_elem {
+                    self.bind(rest, array_slice(value, len));
+                }
+            },
+        }
+    }
+}
+
+
+struct DestructuringLowering<'a> {
+    names: &'a mut NameGenerator,
+    helpers: &'a mut Helpers,
+    temps: Temporaries,
+    patterns: bool,
+}
+
+impl<'a> DestructuringLowering<'a> {
+    /// Every pattern when lowering to ES5, otherwise only the patterns with an object rest.
+    fn should_lower(&self, target: &Expression) -> bool {
+        is_pattern(target) && (self.patterns || has_object_rest(target))
+    }
+
+    fn should_lower_assignment(&self, expr: &Expression) -> bool {
+        match *expr {
+            Expression::Assignment(ref inner) => inner.operator == AssignmentOperator::Assign && self.should_lower(&inner.left),
+            _ => false,
+        }
+    }
+
+    fn destructure(&mut self, target: Expression, value: Expression) -> Destructure<'_> {
+        let mut destructure = Destructure { names: self.names, helpers: self.helpers, pairs: Vec::new(), temps: Vec::new(), patterns: self.patterns };
+        destructure.bind(target, value);
+        destructure
+    }
+
+    /// `var <target> = value;`
+    fn declare(&mut self, target: Expression, value: Expression) -> Statement {
+        let declarators = self.destructure(target, value).pairs.into_iter()
+            .map(|(name, value)| builder::declarator(name, Some(value)))
+            .collect();
+        builder::variable(LexicalDeclarationKind::Var, declarators)
+    }
+
+    /// `<target> = value` as a comma expression, temporaries go to the enclosing function.
+    fn assign(&mut self, mut target: Expression, value: Expression, keep_value: bool) -> Expression {
+        let (pairs, temps) = {
+            let mut destructure = Destructure { names: self.names, helpers: self.helpers, pairs: Vec::new(), temps: Vec::new(), patterns: self.patterns };
+            // NOTE: 赋值表达式的值是右侧的值
+            let value = destructure.reference(value, &mut target);
+            destructure.bind(target, value.clone());
+            let mut pairs = destructure.pairs.into_iter().map(|(left, right)| builder::assign(left, right)).collect::<Vec<_>>();
+            if keep_value {
+                pairs.push(value);
+            }
+            (pairs, destructure.temps)
+        };
+
+        for name in temps.into_iter() {
+            self.temps.declare(name);
+        }
+
+        if keep_value {
+            builder::parenthesized(builder::comma(pairs))
+        } else {
+            builder::comma(pairs)
+        }
+    }
+
+    fn lower_declarations(&mut self, stmt: &mut VariableStatement) {
+        let declarators = mem::replace(&mut stmt.declarators, Vec::new());
+        for declarator in declarators.into_iter() {
+            match declarator {
+                LexicalBinding { ref name, initializer: Some(_), .. } if self.should_lower(name) => {
+                    let value = declarator.initializer.unwrap();
+                    let pairs = self.destructure(declarator.name, value).pairs;
+                    stmt.declarators.extend(pairs.into_iter().map(|(name, value)| builder::declarator(name, Some(value))));
+                },
+                other => stmt.declarators.push(other),
+            }
+        }
+    }
+
+    fn lower_params(&mut self, params: &mut ParenthesizedExpression, body: &mut Vec<Statement>) {
+        flatten_arguments(params);
+        let lowered = if self.patterns {
+            !params.items.iter().all(is_simple_param)
+        } else {
+            params.items.iter().any(has_object_rest)
+        };
+        if !lowered {
+            return;
+        }
+
+        // NOTE: 只降级对象剩余属性时，从第一个包含对象剩余属性的参数开始降级，
+        //       之后的默认值可能引用它绑定的名字。
+        let mut kept = !self.patterns;
+        let mut prologue = Vec::new();
+        let items = mem::replace(&mut params.items, Vec::new());
+        for (index, param) in items.into_iter().enumerate() {
+            kept = kept && !has_object_rest(&param);
+            if kept {
+                params.items.push(param);
+                continue;
+            }
+
+            match param {
+                Expression::Identifier(_) => params.items.push(param),
+                // NOTE: 剩余参数保持原样，箭头函数没有自己的 `arguments`
+                Expression::Spread(mut inner) if !self.patterns => {
+                    if is_pattern(&inner.item) {
+                        let param = builder::ident_expr(&self.names.fresh("ref"));
+                        let pattern = mem::replace(&mut inner.item, param.clone());
+                        prologue.push(self.declare(pattern, param));
+                    }
+                    params.items.push(Expression::Spread(inner));
+                },
+                // ...rest → var rest = Array.prototype.slice.call(arguments, index);
+                Expression::Spread(inner) => {
+                    let value = array_slice(builder::ident_expr("arguments"), index);
+                    prologue.push(self.declare(inner.item, value));
+                },
+                // x = init → if (x === void 0) x = init;
+                Expression::Assignment(inner) => {
+                    let AssignmentExpression { left, right, .. } = *inner;
+                    let param = match left {
+                        Expression::Identifier(_) => left.clone(),
+                        _ => builder::ident_expr(&self.names.fresh("ref")),
+                    };
+
+                    let test = builder::infix(param.clone(), InfixOperator::StrictEq, builder::undefined());
+                    prologue.push(builder::if_stmt(test, builder::expr_stmt(builder::assign(param.clone(), right)), None));
+                    if is_pattern(&left) {
+                        prologue.push(self.declare(left, param.clone()));
+                    }
+                    params.items.push(param);
+                },
+                pattern => {
+                    let param = builder::ident_expr(&self.names.fresh("ref"));
+                    prologue.push(self.declare(pattern, param.clone()));
+                    params.items.push(param);
+                },
+            }
+        }
+
+        prologue.extend(body.drain(..));
+        *body = prologue;
+    }
+
+    fn function_body(&mut self, body: &mut Vec<Statement>) {
+        self.temps.enter();
+        self.visit_statements(body);
+        self.temps.leave(body);
+    }
+
+    // for (left of right) body
+    //
+    // for (var _i = 0, _ref = right; _i < _ref.length; _i++) { left = _ref[_i]; body }
+    fn lower_for_of(&mut self, stmt: ForOfStatement) -> Statement {
+        let ForOfStatement { left, right, body, .. } = stmt;
+
+        let index = self.names.fresh("i");
+        let array = self.names.fresh("ref");
+        let init = builder::variable(LexicalDeclarationKind::Var, vec![
+            builder::declarator(builder::ident_expr(&index), Some(builder::number(0))),
+            builder::declarator(builder::ident_expr(&array), Some(right)),
+        ]);
+        let condition = builder::infix(builder::ident_expr(&index), InfixOperator::Lt, builder::member(builder::ident_expr(&array), "length"));
+        let update = builder::postfix(PostfixOperator::Increment, builder::ident_expr(&index));
+
+        let element = builder::computed_member(builder::ident_expr(&array), builder::ident_expr(&index));
+        let body = self.loop_head(left, element, body);
+
+        builder::for_stmt(Some(init), Some(condition), Some(update), body)
+    }
+
+    // for (<pattern> in right) body → for (_ref in right) { <pattern> = _ref; body }
+    fn lower_loop_left(&mut self, left: &mut Expression, body: &mut Statement) {
+        let name = self.names.fresh("ref");
+        self.temps.declare(name.clone());
+        let key = builder::ident_expr(&name);
+        let pattern = mem::replace(left, key.clone());
+        let inner = mem::replace(body, builder::empty());
+        *body = self.loop_head(pattern, key, inner);
+    }
+
+    fn loop_head(&mut self, left: Expression, value: Expression, body: Statement) -> Statement {
+        let head = if is_pattern(&left) {
+            builder::expr_stmt(self.assign(left, value, false))
+        } else {
+            builder::expr_stmt(builder::assign(left, value))
+        };
+
+        let mut stmts = vec![ head ];
+        match body {
+            Statement::Block(block) => stmts.extend(block.body),
+            other => stmts.push(other),
+        }
+        builder::block_stmt(stmts)
+    }
+}
+
+impl<'a> VisitMut for DestructuringLowering<'a> {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        // NOTE: 表达式语句不需要赋值表达式的值
+        if let Statement::Expression(ref mut inner) = *stmt {
+            if self.should_lower_assignment(inner) {
+                visit::walk_expression(self, inner);
+                if let Expression::Assignment(assignment) = mem::replace(&mut **inner, builder::null()) {
+                    let AssignmentExpression { left, right, .. } = *assignment;
+                    **inner = self.assign(left, right, false);
+                }
+                return;
+            }
+        }
+
+        visit::walk_statement(self, stmt);
+
+        match *stmt {
+            Statement::Variable(ref mut inner) => self.lower_declarations(inner),
+            Statement::ForIn(ref mut inner) if self.should_lower(&inner.left) => {
+                self.lower_loop_left(&mut inner.left, &mut inner.body);
+            },
+            // NOTE: 目标版本支持 `for-of` 时只改写左侧的模式
+            Statement::ForOf(ref mut inner) if !self.patterns => {
+                if self.should_lower(&inner.left) {
+                    self.lower_loop_left(&mut inner.left, &mut inner.body);
+                }
+            },
+            Statement::ForOf(_) => {
+                if let Statement::ForOf(inner) = mem::replace(stmt, builder::empty()) {
+                    *stmt = self.lower_for_of(*inner);
+                }
+            },
+            Statement::Try(ref mut inner) => {
+                // catch (<pattern>) { } → catch (_ref) { var <pattern> = _ref; }
+                let has_pattern = inner.catch_parameter.as_ref().map(|param| self.should_lower(param)).unwrap_or(false);
+                if has_pattern {
+                    let param = builder::ident_expr(&self.names.fresh("ref"));
+                    let pattern = mem::replace(inner.catch_parameter.as_mut().unwrap(), param.clone());
+                    let decl = self.declare(pattern, param);
+                    if let Some(ref mut catch_body) = inner.catch_body {
+                        catch_body.body.insert(0, decl);
+                    }
+                }
+            },
+            _ => { },
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        visit::walk_expression(self, expr);
+
+        if self.should_lower_assignment(expr) {
+            if let Expression::Assignment(assignment) = mem::replace(expr, builder::null()) {
+                let AssignmentExpression { left, right, .. } = *assignment;
+                *expr = self.assign(left, right, true);
+            }
+        }
+    }
+
+    fn visit_function(&mut self, func: &mut Function) {
+        self.lower_params(&mut func.params, &mut func.body);
+        self.function_body(&mut func.body);
+    }
+
+    // NOTE: 降级到 ES5 时箭头函数已经被降级，这里只会遇到 ES2015 - ES2017 的箭头函数
+    fn visit_arrow_function(&mut self, arrow: &mut ArrowFunctionExpression) {
+        if arrow_params(arrow.params.clone()).iter().any(has_object_rest) {
+            let mut params = builder::paren(arrow_params(mem::replace(&mut arrow.params, builder::null())));
+            let mut body = match mem::replace(&mut arrow.body, ConciseBody::Stmt(Vec::new())) {
+                ConciseBody::Expr(value) => vec![ builder::return_stmt(Some(value)) ],
+                ConciseBody::Stmt(stmts) => stmts,
+            };
+            self.lower_params(&mut params, &mut body);
+            arrow.params = Expression::Parenthesized(Box::new(params));
+            arrow.body = ConciseBody::Stmt(body);
+        }
+
+        self.temps.enter();
+        match arrow.body {
+            ConciseBody::Expr(ref mut body) => self.visit_expression(body),
+            ConciseBody::Stmt(ref mut body) => self.visit_statements(body),
+        }
+        self.temps.leave_arrow(&mut arrow.body);
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        match *method {
+            MethodDefinition::Method(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                self.lower_params(&mut inner.params, &mut inner.body);
+                self.function_body(&mut inner.body);
+            },
+            MethodDefinition::Getter(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                self.function_body(&mut inner.body);
+            },
+            MethodDefinition::Setter(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                self.lower_params(&mut inner.params, &mut inner.body);
+                self.function_body(&mut inner.body);
+            },
+        }
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, names: &mut NameGenerator, helpers: &mut Helpers, patterns: bool) -> Result<(), Error> {
+    let mut pass = DestructuringLowering { names, helpers, temps: Temporaries::default(), patterns };
+    pass.function_body(body);
+
+    Ok(())
+}
+
+
+#[test]
+fn test_destructuring_lowering() {
+    use crate::version::ECMAScriptVersion;
+    use crate::compiler::transform::{ Transformer, lower_and_print, print_owned, assert_reparse, };
+
+    let single = |name: &str, init: Option<Expression>| BindingElement::SingleNameBinding {
+        loc: Default::default(),
+        span: Default::default(),
+        name: builder::ident(name),
+        init,
+    };
+    let array = |elems: Vec<Option<BindingElement>>| BindingPattern::Array(ArrayBindingPattern {
+        loc: Default::default(),
+        span: Default::default(),
+        elems,
+        rest_elem: None,
+    });
+
+    // var { a, b: [c, d = 1], ...e } = obj;
+    let pattern = BindingPattern::Object(ObjectBindingPattern {
+        loc: Default::default(),
+        span: Default::default(),
+        properties: vec![
+            BindingProperty::SingleNameBinding { loc: Default::default(), span: Default::default(), name: builder::ident("a"), init: None },
+            BindingProperty::Property {
+                loc: Default::default(),
+                span: Default::default(),
+                name: PropertyName::Identifier(builder::ident("b")),
+                puct: builder::colon(),
+                value: BindingElement::BindingPattern {
+                    loc: Default::default(),
+                    span: Default::default(),
+                    pattern: array(vec![ Some(single("c", None)), Some(single("d", Some(builder::number(1)))) ]),
+                    init: None,
+                },
+            },
+            BindingProperty::Spread { loc: Default::default(), span: Default::default(), puct: builder::colon(), name: builder::ident("e") },
+        ],
+    });
+    let declaration = builder::variable(LexicalDeclarationKind::Var, vec![
+        builder::declarator(Expression::BindingPattern(Box::new(pattern)), Some(builder::ident_expr("obj"))),
+    ]);
+
+    // function f(x = 1, [y], ...z) { [x, y] = [y, x]; }
+    let swap = Expression::AssignmentPattern(Box::new(AssignmentPattern::Array(ArrayAssignmentPattern {
+        loc: Default::default(),
+        span: Default::default(),
+        elems: vec![ "x", "y" ].into_iter().map(|name| Some(AssignmentElement {
+            loc: Default::default(),
+            span: Default::default(),
+            elem: builder::ident_expr(name),
+            init: None,
+        })).collect(),
+        rest_elem: None,
+    })));
+    let params = vec![
+        builder::assign(builder::ident_expr("x"), builder::number(1)),
+        Expression::BindingPattern(Box::new(array(vec![ Some(single("y", None)) ]))),
+        Expression::Spread(Box::new(SpreadExpression { loc: Default::default(), span: Default::default(), item: builder::ident_expr("z") })),
+    ];
+    let body = vec![
+        builder::expr_stmt(builder::assign(swap, builder::array(vec![ builder::ident_expr("y"), builder::ident_expr("x") ]))),
+    ];
+    let function = builder::function_decl("f", params, body);
+
+    assert_eq!(
+        lower_and_print(vec![ declaration, function ]),
+        concat!(
+            "function _objectWithoutProperties(source,excluded){var target={},key;for(key in source){",
+            "if(excluded.indexOf(key)>=0)continue;if(!Object.prototype.hasOwnProperty.call(source,key))continue;",
+            "target[key]=source[key];}return target;}",
+            "var a=obj.a,_ref=obj.b,c=_ref[0],_ref2=_ref[1],d=_ref2===void 0?1:_ref2,e=_objectWithoutProperties(obj,[\"a\",\"b\"]);",
+            "function f(x,_ref3){var _ref4;if(x===void 0)x=1;var y=_ref3[0];var z=Array.prototype.slice.call(arguments,2);",
+            "_ref4=[y,x],x=_ref4[0],y=_ref4[1];}",
+        )
+    );
+
+    // var [a, , b] = xs; function g([y], ...z) { [y, z] = [z, y]; }
+    let swap = Expression::AssignmentPattern(Box::new(AssignmentPattern::Array(ArrayAssignmentPattern {
+        loc: Default::default(),
+        span: Default::default(),
+        elems: vec![ "y", "z" ].into_iter().map(|name| Some(AssignmentElement {
+            loc: Default::default(),
+            span: Default::default(),
+            elem: builder::ident_expr(name),
+            init: None,
+        })).collect(),
+        rest_elem: None,
+    })));
+    let declaration = builder::variable(LexicalDeclarationKind::Var, vec![
+        builder::declarator(Expression::BindingPattern(Box::new(array(vec![ Some(single("a", None)), None, Some(single("b", None)) ]))), Some(builder::ident_expr("xs"))),
+    ]);
+    let params = vec![
+        Expression::BindingPattern(Box::new(array(vec![ Some(single("y", None)) ]))),
+        Expression::Spread(Box::new(SpreadExpression { loc: Default::default(), span: Default::default(), item: builder::ident_expr("z") })),
+    ];
+    let body = vec![
+        builder::expr_stmt(builder::assign(swap, builder::array(vec![ builder::ident_expr("z"), builder::ident_expr("y") ]))),
+    ];
+    let output = lower_and_print(vec![ declaration, builder::function_decl("g", params, body) ]);
+    assert_eq!(output, "var a=xs[0],b=xs[2];function g(_ref){var _ref2;var y=_ref[0];var z=Array.prototype.slice.call(arguments,1);_ref2=[z,y],y=_ref2[0],z=_ref2[1];}");
+    // NOTE: 降级的结果可以再次解析
+    assert_reparse(&output);
+
+    // NOTE: ES2015 - ES2017 只降级包含对象剩余属性的模式
+    let rest = |names: Vec<&str>, rest: &str| BindingPattern::Object(ObjectBindingPattern {
+        loc: Default::default(),
+        span: Default::default(),
+        properties: names.into_iter()
+            .map(|name| BindingProperty::SingleNameBinding { loc: Default::default(), span: Default::default(), name: builder::ident(name), init: None })
+            .chain(Some(BindingProperty::Spread { loc: Default::default(), span: Default::default(), puct: builder::colon(), name: builder::ident(rest) }))
+            .collect(),
+    });
+    let declare = |pattern: BindingPattern, value: &str| builder::variable(LexicalDeclarationKind::Var, vec![
+        builder::declarator(Expression::BindingPattern(Box::new(pattern)), Some(builder::ident_expr(value))),
+    ]);
+    let nested = array(vec![
+        Some(single("x", None)),
+        Some(BindingElement::BindingPattern { loc: Default::default(), span: Default::default(), pattern: rest(vec![], "r"), init: None }),
+    ]);
+    // ({ a, ...b }, ...c) => b
+    let arrow = Expression::ArrowFunction(Box::new(ArrowFunctionExpression {
+        loc: Default::default(),
+        span: Default::default(),
+        is_async: false,
+        params: builder::parenthesized(builder::comma(vec![
+            Expression::BindingPattern(Box::new(rest(vec![ "a" ], "b"))),
+            Expression::Spread(Box::new(SpreadExpression { loc: Default::default(), span: Default::default(), item: builder::ident_expr("c") })),
+        ])),
+        body: ConciseBody::Expr(builder::ident_expr("b")),
+    }));
+    let mut body = vec![
+        declare(rest(vec![ "a" ], "e"), "obj"),
+        declare(nested, "xs"),
+        declare(array(vec![ Some(single("y", None)) ]), "ys"),
+        builder::expr_stmt(builder::parenthesized(arrow)),
+    ];
+    Transformer::new(ECMAScriptVersion::ES2015).transform_program(&mut body).ok().unwrap();
+    assert_eq!(
+        print_owned(&body),
+        concat!(
+            "function _objectWithoutProperties(source,excluded){var target={},key;for(key in source){",
+            "if(excluded.indexOf(key)>=0)continue;if(!Object.prototype.hasOwnProperty.call(source,key))continue;",
+            "target[key]=source[key];}return target;}",
+            "var a=obj.a,e=_objectWithoutProperties(obj,[\"a\"]);",
+            "var[x,_ref]=xs,r=_objectWithoutProperties(_ref,[]);",
+            "var[y]=ys;",
+            "((_ref2,...c)=>{var a=_ref2.a,b=_objectWithoutProperties(_ref2,[\"a\"]);return b;});",
+        )
+    );
+}
@@ -0,0 +1,150 @@
+// Exponentiation operator → `Math.pow`
+//
+//      a ** b          →   Math.pow(a, b)
+//      a **= b         →   a = Math.pow(a, b)
+//      o().p **= b     →   (_ref = o()).p = Math.pow(_ref.p, b)
+
+use crate::error::Error;
+use crate::lexer::operator::{ InfixOperator, AssignmentOperator, };
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{ NameGenerator, Temporaries, memoize_reference, };
+
+use std::mem;
+
+
+fn pow(base: Expression, exponent: Expression) -> Expression {
+    builder::call(builder::member(builder::ident_expr("Math"), "pow"), vec![ base, exponent ])
+}
+
+
+struct ExponentLowering<'a> {
+    names: &'a mut NameGenerator,
+    temps: Temporaries,
+}
+
+impl<'a> ExponentLowering<'a> {
+    fn function_body(&mut self, body: &mut Vec<Statement>) {
+        self.temps.enter();
+        self.visit_statements(body);
+        self.temps.leave(body);
+    }
+}
+
+impl<'a> VisitMut for ExponentLowering<'a> {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        visit::walk_expression(self, expr);
+
+        match *expr {
+            Expression::Infix(ref inner) if inner.operator == InfixOperator::Pow => { },
+            Expression::Assignment(ref inner) if inner.operator == AssignmentOperator::PowAssign => { },
+            _ => return,
+        }
+
+        *expr = match mem::replace(expr, builder::null()) {
+            Expression::Infix(inner) => pow(inner.left, inner.right),
+            Expression::Assignment(inner) => {
+                let AssignmentExpression { left, right, .. } = *inner;
+                let (target, value) = memoize_reference(left, self.names, &mut self.temps);
+                builder::assign(target, pow(value, right))
+            },
+            _ => unreachable!(),
+        };
+    }
+
+    fn visit_function(&mut self, func: &mut Function) {
+        for param in func.params.items.iter_mut() {
+            self.visit_expression(param);
+        }
+        self.function_body(&mut func.body);
+    }
+
+    fn visit_arrow_function(&mut self, arrow: &mut ArrowFunctionExpression) {
+        self.visit_expression(&mut arrow.params);
+        self.temps.enter();
+        match arrow.body {
+            ConciseBody::Expr(ref mut body) => self.visit_expression(body),
+            ConciseBody::Stmt(ref mut body) => self.visit_statements(body),
+        }
+        self.temps.leave_arrow(&mut arrow.body);
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        match *method {
+            MethodDefinition::Method(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                for param in inner.params.items.iter_mut() {
+                    self.visit_expression(param);
+                }
+                self.function_body(&mut inner.body);
+            },
+            MethodDefinition::Getter(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                self.function_body(&mut inner.body);
+            },
+            MethodDefinition::Setter(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                for param in inner.params.items.iter_mut() {
+                    self.visit_expression(param);
+                }
+                self.function_body(&mut inner.body);
+            },
+        }
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, names: &mut NameGenerator) -> Result<(), Error> {
+    let mut pass = ExponentLowering { names, temps: Temporaries::default() };
+    pass.function_body(body);
+
+    Ok(())
+}
+
+
+#[test]
+fn test_exponent_lowering() {
+    use crate::toolshed::Arena;
+    use crate::parser::Parser;
+    use crate::compiler::transform::{ parse_owned, lower_and_print, assert_reparse, };
+
+    assert_eq!(lower_and_print(parse_owned("a ** b * c")), "Math.pow(a,b)*c;");
+    assert_eq!(lower_and_print(parse_owned("a **= 2")), "a=Math.pow(a,2);");
+    assert_eq!(lower_and_print(parse_owned("o.p[k] **= 2")), "var _ref;(_ref=o.p)[k]=Math.pow(_ref[k],2);");
+    assert_eq!(lower_and_print(parse_owned("o[f()] **= 2")), "var _ref;o[_ref=f()]=Math.pow(o[_ref],2);");
+    // NOTE: `**` 是右结合的
+    assert_eq!(lower_and_print(parse_owned("2 ** 3 ** 2")), "Math.pow(2,Math.pow(3,2));");
+    assert_eq!(lower_and_print(parse_owned("x = (2 ** 3) ** 2")), "x=Math.pow((Math.pow(2,3)),2);");
+    assert_eq!(lower_and_print(parse_owned("a * b ** c ** d")), "a*Math.pow(b,Math.pow(c,d));");
+    assert_eq!(lower_and_print(parse_owned("x = (-2) ** 2")), "x=Math.pow((-2),2);");
+    // NOTE: 右操作数不能吞掉后面的逗号
+    assert_eq!(lower_and_print(parse_owned("x = 2 ** 3, y")), "x=Math.pow(2,3),y;");
+    assert_eq!(lower_and_print(parse_owned("(o.a.v **= 2, o.a.v)")), "var _ref;((_ref=o.a).v=Math.pow(_ref.v,2),o.a.v);");
+    assert_eq!(lower_and_print(parse_owned("f(a ** 2, b **= 2, c)")), "f(Math.pow(a,2),b=Math.pow(b,2),c);");
+
+    // NOTE: 降级的结果可以再次解析
+    for &source in [
+        "o.p[k] **= 2", "o[f()] **= 2", "(o.a.v **= 2, o.a.v)",
+        "f(a ** 2, b **= 2, c)", "x = [2 ** 10, 2 ** -1]", "a * b ** c ** d",
+    ].iter() {
+        assert_reparse(&lower_and_print(parse_owned(source)));
+    }
+
+    // NOTE: `-2 ** 2` 是语法错误
+    let parse = |source: &str| {
+        let arena = Arena::new();
+        let code = arena.alloc_vec(source.chars().collect::<Vec<char>>());
+        let mut parser = Parser::new(&arena, &code, "main.js");
+        parser.parse().map(|_| ())
+    };
+    assert!(parse("-2 ** 2").is_err());
+    assert!(parse("typeof a ** 2").is_err());
+    assert!(parse("2 ** -2").is_ok());
+}
@@ -0,0 +1,919 @@
+// Generators → state machines
+//
+//      function* g(a) { var x = yield a; try { yield x; } finally { f(); } }
+//
+//      function g(a) {
+//          var x;
+//          return _generator(function (_context) {
+//              switch (_context.label) {
+//                  case 0: _context.label = 1; return [4, a];
+//                  case 1: x = _context.sent(); _context.label = 2;
+//                  case 2: _context.trys.push([2, 0, 4, 5]); _context.label = 3; return [4, x];
+//                  case 3: _context.sent(); return [3, 5];
+//                  case 4: f(); return [7];
+//                  case 5: return [2];
+//              }
+//          }, this);
+//      }
+//
+// NOTE: 函数体被切分成 `switch` 的各个 case，每个 `yield` 之后开始一个新的 case，
+//       局部变量提升到外层函数中，跨越 `yield` 的中间值保存在临时变量 `_t` 中。
+//       不含 `yield` 的语句原样输出，只改写其中的 `return`/`break`/`continue`。
+//       指令的含义见 `helpers::generator`。
+
+use crate::error::{ ErrorKind, Error, };
+use crate::lexer::operator::{ PrefixOperator, InfixOperator, PostfixOperator, };
+use crate::ast::statement::LexicalDeclarationKind;
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{ NameGenerator, Helper, Helpers, Renamer, count_names, flatten_arguments, has_suspension, has_suspension_in_expression, };
+use crate::compiler::transform::block_scoping::VarHoister;
+
+use std::collections::HashSet;
+use std::mem;
+
+
+const OP_RETURN: i64 = 2;
+const OP_JUMP: i64 = 3;
+const OP_YIELD: i64 = 4;
+const OP_YIELD_STAR: i64 = 5;
+const OP_END_FINALLY: i64 = 7;
+
+// NOTE: label 在创建时还不知道对应哪个 case（case 按照 `mark` 的顺序编号），
+//       先用无法出现在源代码中的标识符 `%n` 占位，生成结束后统一替换。
+type Label = usize;
+
+fn placeholder(label: Label) -> Expression {
+    builder::ident_expr(&format!("%{}", label))
+}
+
+// return [op, value];
+fn instruction(op: i64, value: Option<Expression>) -> Statement {
+    let mut items = vec![ builder::number(op) ];
+    items.extend(value);
+    builder::return_stmt(Some(builder::array(items)))
+}
+
+fn jump(label: Label) -> Statement {
+    instruction(OP_JUMP, Some(placeholder(label)))
+}
+
+fn not(expr: Expression) -> Expression {
+    builder::prefix(PrefixOperator::Not, expr)
+}
+
+fn assign_to(name: &str, value: Expression) -> Statement {
+    builder::expr_stmt(builder::assign(builder::ident_expr(name), value))
+}
+
+// NOTE: 不需要保存到临时变量的值：它们的值不会被 `yield` 之后的代码改变。
+fn is_constant(expr: &Expression) -> bool {
+    match *expr {
+        Expression::This(_) | Expression::Null(_) | Expression::Boolean(_)
+        | Expression::String(_) | Expression::Numeric(_) => true,
+        _ => false,
+    }
+}
+
+fn is_loop(stmt: &Statement) -> bool {
+    match *stmt {
+        Statement::While(_) | Statement::DoWhile(_) | Statement::For(_)
+        | Statement::ForIn(_) | Statement::ForOf(_) | Statement::ForAwaitOf(_) => true,
+        _ => false,
+    }
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::new(ErrorKind::SyntaxError, format!("{} containing 'yield' can not be lowered to ES5", what))
+}
+
+
+/// `break`/`continue` target of a loop, `switch` or labelled statement containing `yield`.
+struct Target {
+    labels: Vec<String>,
+    break_to: Label,
+    continue_to: Option<Label>,
+}
+
+
+/// Rewrites the exits of a statement without `yield`:
+/// `return v` → `return [2, v]`, jumps to compiled targets → `return [3, label]`.
+struct ExitRewriter<'a> {
+    targets: &'a [Target],
+    loops: usize,
+    switches: usize,
+    labels: Vec<String>,
+}
+
+impl<'a> ExitRewriter<'a> {
+    fn is_inner_label(&self, label: &Identifier) -> bool {
+        self.labels.iter().any(|name| name == label.name())
+    }
+
+    fn find(&self, label: &Option<Identifier>, is_continue: bool) -> Option<Label> {
+        let mut targets = self.targets.iter().rev();
+        let target = match *label {
+            Some(ref label) => targets.find(|target| target.labels.iter().any(|name| name == label.name())),
+            None if is_continue => targets.find(|target| target.continue_to.is_some()),
+            None => targets.next(),
+        };
+
+        target.and_then(|target| if is_continue { target.continue_to } else { Some(target.break_to) })
+    }
+}
+
+impl<'a> VisitMut for ExitRewriter<'a> {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        let exit = match *stmt {
+            Statement::Return(ref mut inner) => Some(instruction(OP_RETURN, inner.value.take())),
+            Statement::Break(ref inner) => {
+                let is_inner = match inner.label {
+                    Some(ref label) => self.is_inner_label(label),
+                    None => self.loops + self.switches > 0,
+                };
+                if is_inner { None } else { self.find(&inner.label, false).map(jump) }
+            },
+            Statement::Continue(ref inner) => {
+                let is_inner = match inner.label {
+                    Some(ref label) => self.is_inner_label(label),
+                    None => self.loops > 0,
+                };
+                if is_inner { None } else { self.find(&inner.label, true).map(jump) }
+            },
+            Statement::Labelled(ref mut inner) => {
+                self.labels.push(inner.label.name().to_string());
+                self.visit_statement(&mut inner.item);
+                self.labels.pop();
+                return;
+            },
+            Statement::Switch(_) => {
+                self.switches += 1;
+                visit::walk_statement(self, stmt);
+                self.switches -= 1;
+                return;
+            },
+            _ if is_loop(stmt) => {
+                self.loops += 1;
+                visit::walk_statement(self, stmt);
+                self.loops -= 1;
+                return;
+            },
+            _ => None,
+        };
+
+        match exit {
+            Some(exit) => *stmt = exit,
+            None => visit::walk_statement(self, stmt),
+        }
+    }
+
+    fn visit_expression(&mut self, _expr: &mut Expression) {
+
+    }
+}
+
+
+// NOTE: 把 label 的占位符替换为 case 的序号。
+struct LabelPatcher<'a> {
+    cases: &'a [Option<usize>],
+}
+
+impl<'a> VisitMut for LabelPatcher<'a> {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        let case = match *expr {
+            Expression::Identifier(ref ident) if ident.raw.starts_with('%') => {
+                let label = ident.raw[1..].parse::<usize>().unwrap();
+                self.cases[label].unwrap_or(0)
+            },
+            _ => return visit::walk_expression(self, expr),
+        };
+        *expr = builder::number(case as i64);
+    }
+}
+
+
+/// Emits the body of a generator function as the cases of the state machine.
+struct Emitter<'a> {
+    names: &'a mut NameGenerator,
+    context: String,
+    cases: Vec<Vec<Statement>>,
+    // label → case
+    labels: Vec<Option<usize>>,
+    targets: Vec<Target>,
+    // labels of the loop about to be emitted
+    loop_labels: Vec<String>,
+    vars: Vec<String>,
+    functions: Vec<Statement>,
+}
+
+impl<'a> Emitter<'a> {
+    fn context(&self, field: &str) -> Expression {
+        builder::member(builder::ident_expr(&self.context), field)
+    }
+
+    fn sent(&self) -> Expression {
+        builder::call(self.context("sent"), vec![])
+    }
+
+    fn emit(&mut self, stmt: Statement) {
+        self.cases.last_mut().unwrap().push(stmt);
+    }
+
+    fn label(&mut self) -> Label {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn falls_through(&self) -> bool {
+        match self.cases.last().and_then(|case| case.last()) {
+            Some(&Statement::Return(_)) | Some(&Statement::Throw(_)) => false,
+            _ => true,
+        }
+    }
+
+    /// Starts a new case at `label`. Falling into it updates `_context.label`,
+    /// the runtime relies on it to find the enclosing `try`.
+    fn mark(&mut self, label: Label) {
+        let current = self.cases.len() - 1;
+        if self.cases[current].is_empty() {
+            self.labels[label] = Some(current);
+            return;
+        }
+
+        if self.falls_through() {
+            let update = builder::expr_stmt(builder::assign(self.context("label"), builder::number(current as i64 + 1)));
+            self.emit(update);
+        }
+        self.cases.push(Vec::new());
+        self.labels[label] = Some(current + 1);
+    }
+
+    fn temp(&mut self) -> String {
+        let name = self.names.fresh("t");
+        self.vars.push(name.clone());
+        name
+    }
+
+    fn spill(&mut self, value: Expression) -> Expression {
+        if is_constant(&value) {
+            return value;
+        }
+
+        let name = self.temp();
+        self.emit(assign_to(&name, value));
+        builder::ident_expr(&name)
+    }
+
+    // _context.label = N; return [4, value]; case N: _context.sent()
+    fn suspend(&mut self, op: i64, value: Expression) -> Expression {
+        let resume = self.label();
+        let update = builder::expr_stmt(builder::assign(self.context("label"), placeholder(resume)));
+        self.emit(update);
+        self.emit(instruction(op, Some(value)));
+        self.mark(resume);
+
+        self.sent()
+    }
+
+    /// Evaluates `items` in order; the ones before the last `yield` are saved to temporaries.
+    fn expressions(&mut self, mut items: Vec<Expression>) -> Result<Vec<Expression>, Error> {
+        let last = match items.iter_mut().rposition(|item| has_suspension_in_expression(item)) {
+            Some(last) => last,
+            None => return Ok(items),
+        };
+
+        let mut values = Vec::with_capacity(items.len());
+        for (index, item) in items.into_iter().enumerate() {
+            if index < last {
+                let value = self.expression(item)?;
+                values.push(self.spill(value));
+            } else if index == last {
+                values.push(self.expression(item)?);
+            } else {
+                values.push(item);
+            }
+        }
+
+        Ok(values)
+    }
+
+    // a, b, c → a; b; c
+    fn sequence(&mut self, mut items: Vec<Expression>) -> Result<Expression, Error> {
+        let last = match items.pop() {
+            Some(last) => last,
+            None => return Ok(builder::undefined()),
+        };
+        for item in items.into_iter() {
+            let value = self.expression(item)?;
+            if !is_constant(&value) {
+                self.emit(builder::expr_stmt(value));
+            }
+        }
+
+        self.expression(last)
+    }
+
+    // a && b → _t = a; if (!_t) goto end; _t = b; end:
+    fn logical(&mut self, left: Expression, operator: InfixOperator, right: Expression) -> Result<Expression, Error> {
+        let name = self.temp();
+        let end = self.label();
+
+        let left = self.expression(left)?;
+        self.emit(assign_to(&name, left));
+        let test = match operator {
+            InfixOperator::And => not(builder::ident_expr(&name)),
+            _ => builder::ident_expr(&name),
+        };
+        self.emit(builder::if_stmt(test, jump(end), None));
+        let right = self.expression(right)?;
+        self.emit(assign_to(&name, right));
+        self.mark(end);
+
+        Ok(builder::ident_expr(&name))
+    }
+
+    fn conditional(&mut self, expr: ConditionalExpression) -> Result<Expression, Error> {
+        let name = self.temp();
+        let or_else = self.label();
+        let end = self.label();
+
+        let condition = self.expression(expr.condition)?;
+        self.emit(builder::if_stmt(not(condition), jump(or_else), None));
+        let value = self.expression(expr.and_then)?;
+        self.emit(assign_to(&name, value));
+        self.emit(jump(end));
+        self.mark(or_else);
+        let value = self.expression(expr.or_else)?;
+        self.emit(assign_to(&name, value));
+        self.mark(end);
+
+        Ok(builder::ident_expr(&name))
+    }
+
+    // `o.m`, `o[k]`: the object and the key are evaluated before `rest`.
+    fn member(&mut self, member: MemberExpression, rest: Vec<Expression>) -> Result<(Expression, Vec<Expression>), Error> {
+        let MemberExpression { loc, span, left, right, computed, optional } = member;
+
+        let mut items = vec![ left ];
+        if computed {
+            items.push(right.clone());
+        }
+        items.extend(rest);
+
+        let mut values = self.expressions(items)?.into_iter();
+        let left = values.next().unwrap();
+        let right = if computed { values.next().unwrap() } else { right };
+        let member = Expression::Member(Box::new(MemberExpression { loc, span, left, right, computed, optional }));
+
+        Ok((member, values.collect()))
+    }
+
+    fn expression(&mut self, mut expr: Expression) -> Result<Expression, Error> {
+        if !has_suspension_in_expression(&mut expr) {
+            return Ok(expr);
+        }
+
+        let value = match expr {
+            Expression::Yield(inner) => {
+                let YieldExpression { star, item, .. } = *inner;
+                let value = self.expression(item)?;
+                self.suspend(if star { OP_YIELD_STAR } else { OP_YIELD }, value)
+            },
+            Expression::Parenthesized(inner) => {
+                let value = self.sequence(inner.items)?;
+                builder::parenthesized(value)
+            },
+            Expression::Comma(inner) => self.sequence(inner.items)?,
+            Expression::Infix(inner) => {
+                let InfixExpression { left, operator, right, .. } = *inner;
+                match operator {
+                    InfixOperator::And | InfixOperator::Or => self.logical(left, operator, right)?,
+                    _ => {
+                        let mut values = self.expressions(vec![ left, right ])?;
+                        let right = values.pop().unwrap();
+                        let left = values.pop().unwrap();
+                        builder::infix(left, operator, right)
+                    },
+                }
+            },
+            Expression::Conditional(inner) => self.conditional(*inner)?,
+            Expression::Assignment(inner) => {
+                let AssignmentExpression { left, operator, right, .. } = *inner;
+                match left {
+                    Expression::Identifier(_) => {
+                        let right = self.expression(right)?;
+                        builder::assign_op(left, operator, right)
+                    },
+                    Expression::Member(member) => {
+                        let (left, mut rest) = self.member(*member, vec![ right ])?;
+                        builder::assign_op(left, operator, rest.pop().unwrap())
+                    },
+                    _ => return Err(unsupported("destructuring assignment")),
+                }
+            },
+            Expression::Member(inner) => self.member(*inner, vec![])?.0,
+            Expression::Call(inner) => {
+                let mut inner = *inner;
+                flatten_arguments(&mut inner.arguments);
+                let arguments = mem::replace(&mut inner.arguments.items, Vec::new());
+                let (callee, arguments) = match inner.callee {
+                    // NOTE: 保存对象而不是方法，调用时的 `this` 不变
+                    Expression::Member(member) => self.member(*member, arguments)?,
+                    callee => {
+                        let mut items = vec![ callee ];
+                        items.extend(arguments);
+                        let mut values = self.expressions(items)?;
+                        let callee = values.remove(0);
+                        (callee, values)
+                    },
+                };
+                inner.callee = callee;
+                inner.arguments.items = arguments;
+                Expression::Call(Box::new(inner))
+            },
+            Expression::New(inner) => {
+                let mut inner = *inner;
+                let mut items = vec![ inner.callee ];
+                if let Some(ref mut arguments) = inner.arguments {
+                    flatten_arguments(arguments);
+                    items.extend(arguments.items.drain(..));
+                }
+                let mut values = self.expressions(items)?;
+                inner.callee = values.remove(0);
+                if let Some(ref mut arguments) = inner.arguments {
+                    arguments.items = values;
+                }
+                Expression::New(Box::new(inner))
+            },
+            Expression::Prefix(mut inner) => {
+                let operand = mem::replace(&mut inner.operand, builder::null());
+                inner.operand = self.expression(operand)?;
+                Expression::Prefix(inner)
+            },
+            Expression::Postfix(mut inner) => {
+                let operand = mem::replace(&mut inner.operand, builder::null());
+                inner.operand = self.expression(operand)?;
+                Expression::Postfix(inner)
+            },
+            Expression::Spread(mut inner) => {
+                let item = mem::replace(&mut inner.item, builder::null());
+                inner.item = self.expression(item)?;
+                Expression::Spread(inner)
+            },
+            Expression::ArrayLiteral(mut inner) => {
+                let elems = mem::replace(&mut inner.elems, Vec::new());
+                let holes = elems.iter().map(Option::is_none).collect::<Vec<bool>>();
+                let mut values = self.expressions(elems.into_iter().flatten().collect())?.into_iter();
+                inner.elems = holes.into_iter().map(|hole| if hole { None } else { values.next() }).collect();
+                Expression::ArrayLiteral(inner)
+            },
+            Expression::ObjectLiteral(mut inner) => {
+                let mut values = Vec::new();
+                for prop in inner.properties.iter_mut() {
+                    match *prop {
+                        ObjectProperty::Property { name: PropertyName::Computed(_), .. } => return Err(unsupported("computed property name")),
+                        ObjectProperty::Property { ref mut value, .. } => values.push(mem::replace(value, builder::null())),
+                        ObjectProperty::Spread { ref mut target, .. } => values.push(mem::replace(target, builder::null())),
+                        _ => { },
+                    }
+                }
+                let mut values = self.expressions(values)?.into_iter();
+                for prop in inner.properties.iter_mut() {
+                    match *prop {
+                        ObjectProperty::Property { ref mut value, .. } => *value = values.next().unwrap(),
+                        ObjectProperty::Spread { ref mut target, .. } => *target = values.next().unwrap(),
+                        _ => { },
+                    }
+                }
+                Expression::ObjectLiteral(inner)
+            },
+            Expression::Template(mut inner) => {
+                let bounds = mem::replace(&mut inner.bounds, Vec::new());
+                inner.bounds = self.expressions(bounds)?;
+                Expression::Template(inner)
+            },
+            _ => return Err(unsupported("expression")),
+        };
+
+        Ok(value)
+    }
+
+    fn statements(&mut self, body: Vec<Statement>) -> Result<(), Error> {
+        for stmt in body.into_iter() {
+            self.statement(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    fn take_loop_labels(&mut self) -> Vec<String> {
+        mem::replace(&mut self.loop_labels, Vec::new())
+    }
+
+    fn loop_body(&mut self, labels: Vec<String>, break_to: Label, continue_to: Label, body: Statement) -> Result<(), Error> {
+        self.targets.push(Target { labels, break_to, continue_to: Some(continue_to) });
+        self.statement(body)?;
+        self.targets.pop();
+
+        Ok(())
+    }
+
+    fn statement(&mut self, mut stmt: Statement) -> Result<(), Error> {
+        if !has_suspension(&mut stmt) {
+            match stmt {
+                Statement::Empty(_) => { },
+                Statement::Function(_) => self.functions.push(stmt),
+                _ => {
+                    let mut rewriter = ExitRewriter { targets: &self.targets, loops: 0, switches: 0, labels: Vec::new() };
+                    rewriter.visit_statement(&mut stmt);
+                    self.emit(stmt);
+                },
+            }
+            return Ok(());
+        }
+
+        match stmt {
+            Statement::Expression(inner) => {
+                let value = self.expression(*inner)?;
+                if !is_constant(&value) {
+                    self.emit(builder::expr_stmt(value));
+                }
+            },
+            Statement::Block(inner) => self.statements(inner.body)?,
+            Statement::Return(inner) => {
+                let value = match inner.value {
+                    Some(value) => Some(self.expression(value)?),
+                    None => None,
+                };
+                self.emit(instruction(OP_RETURN, value));
+            },
+            Statement::Throw(inner) => {
+                let value = self.expression(inner.value)?;
+                self.emit(builder::throw_stmt(value));
+            },
+            Statement::If(inner) => {
+                let IfStatement { condition, and_then, or_else, .. } = *inner;
+                let has_else = match or_else {
+                    Statement::Empty(_) => false,
+                    _ => true,
+                };
+                let end = self.label();
+                let else_label = if has_else { self.label() } else { end };
+
+                let condition = self.expression(condition)?;
+                self.emit(builder::if_stmt(not(condition), jump(else_label), None));
+                self.statement(and_then)?;
+                if has_else {
+                    self.emit(jump(end));
+                    self.mark(else_label);
+                    self.statement(or_else)?;
+                }
+                self.mark(end);
+            },
+            Statement::While(inner) => {
+                let labels = self.take_loop_labels();
+                let head = self.label();
+                let end = self.label();
+
+                self.mark(head);
+                let condition = self.expression(inner.condition)?;
+                self.emit(builder::if_stmt(not(condition), jump(end), None));
+                self.loop_body(labels, end, head, inner.body)?;
+                self.emit(jump(head));
+                self.mark(end);
+            },
+            Statement::DoWhile(inner) => {
+                let labels = self.take_loop_labels();
+                let head = self.label();
+                let next = self.label();
+                let end = self.label();
+
+                self.mark(head);
+                self.loop_body(labels, end, next, inner.body)?;
+                self.mark(next);
+                let condition = self.expression(inner.condition)?;
+                self.emit(builder::if_stmt(condition, jump(head), None));
+                self.mark(end);
+            },
+            Statement::For(inner) => {
+                let ForStatement { init, condition, finally, body, .. } = *inner;
+                let labels = self.take_loop_labels();
+                let head = self.label();
+                let next = self.label();
+                let end = self.label();
+
+                if let Some(init) = init {
+                    self.statement(init)?;
+                }
+                self.mark(head);
+                if let Some(condition) = condition {
+                    let condition = self.expression(condition)?;
+                    self.emit(builder::if_stmt(not(condition), jump(end), None));
+                }
+                self.loop_body(labels, end, next, body)?;
+                self.mark(next);
+                if let Some(finally) = finally {
+                    let value = self.expression(finally)?;
+                    self.emit(builder::expr_stmt(value));
+                }
+                self.emit(jump(head));
+                self.mark(end);
+            },
+            // NOTE: 先取出所有的键，之后逐个赋值给 `left`
+            //
+            //      _t = []; for (_t2 in object) _t.push(_t2); _t3 = 0;
+            //      head: if (!(_t3 < _t.length)) goto end; left = _t[_t3]; body;
+            //      next: _t3++; goto head;
+            //      end:
+            Statement::ForIn(inner) => {
+                let ForInStatement { left, right, body, .. } = *inner;
+                let labels = self.take_loop_labels();
+                let keys = self.temp();
+                let key = self.temp();
+                let index = self.temp();
+                let head = self.label();
+                let next = self.label();
+                let end = self.label();
+
+                let object = self.expression(right)?;
+                self.emit(assign_to(&keys, builder::array(vec![])));
+                let push = builder::call(builder::member(builder::ident_expr(&keys), "push"), vec![ builder::ident_expr(&key) ]);
+                self.emit(builder::for_in_stmt(builder::ident_expr(&key), object, builder::expr_stmt(push)));
+                self.emit(assign_to(&index, builder::number(0)));
+
+                self.mark(head);
+                let length = builder::member(builder::ident_expr(&keys), "length");
+                self.emit(builder::if_stmt(not(builder::infix(builder::ident_expr(&index), InfixOperator::Lt, length)), jump(end), None));
+                let value = builder::computed_member(builder::ident_expr(&keys), builder::ident_expr(&index));
+                self.statement(builder::expr_stmt(builder::assign(left, value)))?;
+                self.loop_body(labels, end, next, body)?;
+                self.mark(next);
+                self.emit(builder::expr_stmt(builder::postfix(PostfixOperator::Increment, builder::ident_expr(&index))));
+                self.emit(jump(head));
+                self.mark(end);
+            },
+            // switch (value) { case a: A; default: B; }
+            //
+            //      _t = value; if (_t === a) goto case_a; goto default;
+            //      case_a: A; default: B; end:
+            Statement::Switch(inner) => {
+                let SwitchStatement { value, clauses, .. } = *inner;
+                let end = self.label();
+                let labels = clauses.iter().map(|_| self.label()).collect::<Vec<Label>>();
+
+                let value = self.expression(value)?;
+                let value = self.spill(value);
+                let mut default = None;
+                let mut bodies = Vec::with_capacity(clauses.len());
+                for (clause, &label) in clauses.into_iter().zip(labels.iter()) {
+                    match clause.value {
+                        Some(test) => {
+                            let test = self.expression(test)?;
+                            self.emit(builder::if_stmt(builder::infix(value.clone(), InfixOperator::StrictEq, test), jump(label), None));
+                        },
+                        None => default = Some(label),
+                    }
+                    bodies.push(clause.body);
+                }
+                self.emit(jump(default.unwrap_or(end)));
+
+                self.targets.push(Target { labels: Vec::new(), break_to: end, continue_to: None });
+                for (body, label) in bodies.into_iter().zip(labels.into_iter()) {
+                    self.mark(label);
+                    self.statement(body)?;
+                }
+                self.targets.pop();
+                self.mark(end);
+            },
+            Statement::Labelled(inner) => {
+                let mut labels = vec![ inner.label.name().to_string() ];
+                let mut item = inner.item;
+                while let Statement::Labelled(inner) = item {
+                    labels.push(inner.label.name().to_string());
+                    item = inner.item;
+                }
+
+                if is_loop(&item) {
+                    self.loop_labels = labels;
+                    self.statement(item)?;
+                } else {
+                    let end = self.label();
+                    self.targets.push(Target { labels, break_to: end, continue_to: None });
+                    self.statement(item)?;
+                    self.targets.pop();
+                    self.mark(end);
+                }
+            },
+            // begin: _context.trys.push([begin, catch, finally, end]); body; goto end;
+            // catch: e = _context.sent(); ...; goto end;
+            // finally: ...; endfinally;
+            // end:
+            Statement::Try(inner) => {
+                let TryStatement { body, catch_parameter, catch_body, finally, .. } = *inner;
+                let begin = self.label();
+                let catch_label = catch_body.as_ref().map(|_| self.label());
+                let finally_label = finally.as_ref().map(|_| self.label());
+                let end = self.label();
+
+                self.mark(begin);
+                let entry = builder::array(vec![
+                    placeholder(begin),
+                    catch_label.map(placeholder).unwrap_or_else(|| builder::number(0)),
+                    finally_label.map(placeholder).unwrap_or_else(|| builder::number(0)),
+                    placeholder(end),
+                ]);
+                let push = builder::call(builder::member(self.context("trys"), "push"), vec![ entry ]);
+                self.emit(builder::expr_stmt(push));
+                self.statements(body.body)?;
+                self.emit(jump(end));
+
+                if let (Some(label), Some(catch_body)) = (catch_label, catch_body) {
+                    self.mark(label);
+                    let mut body = catch_body.body;
+                    match catch_parameter {
+                        // NOTE: catch 参数提升到外层函数后可能与其它变量重名，改为新的名字
+                        Some(Expression::Identifier(param)) => {
+                            let name = self.names.fresh(param.name());
+                            Renamer { from: param.name(), to: &name }.visit_statements(&mut body);
+                            self.vars.push(name.clone());
+                            let sent = self.sent();
+                            self.emit(assign_to(&name, sent));
+                        },
+                        Some(_) => return Err(unsupported("catch clause with a destructuring parameter")),
+                        None => { },
+                    }
+                    self.statements(body)?;
+                    self.emit(jump(end));
+                }
+                if let (Some(label), Some(finally)) = (finally_label, finally) {
+                    self.mark(label);
+                    self.statements(finally.body)?;
+                    self.emit(instruction(OP_END_FINALLY, None));
+                }
+                self.mark(end);
+            },
+            Statement::Variable(_) => return Err(unsupported("declaration")),
+            Statement::With(_) => return Err(unsupported("'with' statement")),
+            _ => return Err(unsupported("statement")),
+        }
+
+        Ok(())
+    }
+
+    // switch (_context.label) { case 0: ... case 1: ... }
+    fn finish(mut self) -> (Statement, Vec<String>, Vec<Statement>) {
+        if self.falls_through() {
+            self.emit(instruction(OP_RETURN, None));
+        }
+
+        let label = self.context("label");
+        let clauses = mem::replace(&mut self.cases, Vec::new()).into_iter()
+            .enumerate()
+            .map(|(index, body)| (Some(builder::number(index as i64)), body))
+            .collect();
+        let mut switch = builder::switch_stmt(label, clauses);
+        LabelPatcher { cases: &self.labels }.visit_statement(&mut switch);
+
+        (switch, self.vars, self.functions)
+    }
+}
+
+
+struct GeneratorLowering<'a> {
+    names: &'a mut NameGenerator,
+    helpers: &'a mut Helpers,
+    error: Option<Error>,
+}
+
+impl<'a> GeneratorLowering<'a> {
+    //  var <vars>; <functions>
+    //  return _generator(function (_context) { switch (_context.label) { ... } }, this);
+    fn lower_body(&mut self, body: &mut Vec<Statement>) {
+        let mut statements = mem::replace(body, Vec::new());
+
+        // NOTE: 状态机是一个新的函数，`arguments` 要从外层传进去
+        let arguments = self.names.fresh("arguments");
+        Renamer { from: "arguments", to: &arguments }.visit_statements(&mut statements);
+        let uses_arguments = count_names(&mut statements).contains_key(&arguments);
+
+        let keep = HashSet::new();
+        let mut hoister = VarHoister { keep: &keep, hoisted: Vec::new() };
+        hoister.visit_statements(&mut statements);
+
+        let helper = self.helpers.get(self.names, Helper::Generator);
+        let context = self.names.fresh("context");
+        let mut emitter = Emitter {
+            names: self.names,
+            context: context.clone(),
+            cases: vec![ Vec::new() ],
+            labels: Vec::new(),
+            targets: Vec::new(),
+            loop_labels: Vec::new(),
+            vars: hoister.hoisted,
+            functions: Vec::new(),
+        };
+        if let Err(error) = emitter.statements(statements) {
+            self.error = Some(error);
+            return;
+        }
+        let (switch, vars, functions) = emitter.finish();
+
+        let mut seen = HashSet::new();
+        let mut declarators = vars.into_iter()
+            .filter(|name| seen.insert(name.clone()))
+            .map(|name| builder::declarator(builder::ident_expr(&name), None))
+            .collect::<Vec<_>>();
+        if uses_arguments {
+            declarators.push(builder::declarator(builder::ident_expr(&arguments), Some(builder::ident_expr("arguments"))));
+        }
+        if !declarators.is_empty() {
+            body.push(builder::variable(LexicalDeclarationKind::Var, declarators));
+        }
+        body.extend(functions);
+
+        let state_machine = builder::function_expr(None, vec![ builder::ident_expr(&context) ], vec![ switch ]);
+        let generator = builder::call(builder::ident_expr(&helper), vec![ state_machine, builder::this() ]);
+        body.push(builder::return_stmt(Some(generator)));
+    }
+}
+
+impl<'a> VisitMut for GeneratorLowering<'a> {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        visit::walk_statement(self, stmt);
+
+        if let Statement::Function(ref mut inner) = *stmt {
+            if inner.is_generator {
+                self.lower_body(&mut inner.func.body);
+                inner.is_generator = false;
+            }
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        visit::walk_expression(self, expr);
+
+        if let Expression::Function(ref mut inner) = *expr {
+            if inner.is_generator {
+                self.lower_body(&mut inner.func.body);
+                inner.is_generator = false;
+            }
+        }
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        visit::walk_method_definition(self, method);
+
+        if let MethodDefinition::Method(ref mut inner) = *method {
+            if inner.is_generator {
+                self.lower_body(&mut inner.body);
+                inner.is_generator = false;
+            }
+        }
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, names: &mut NameGenerator, helpers: &mut Helpers) -> Result<(), Error> {
+    let mut pass = GeneratorLowering { names, helpers, error: None };
+    pass.visit_statements(body);
+
+    match pass.error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+
+#[test]
+fn test_generator_lowering() {
+    use crate::compiler::transform::{ parse_owned, lower_and_print, };
+
+    // function* g(a) { x = yield a; try { yield x } finally { f() } }
+    let try_finally = builder::try_stmt(
+        vec![ builder::expr_stmt(Expression::Yield(Box::new(YieldExpression {
+            loc: Default::default(),
+            span: Default::default(),
+            star: false,
+            item: builder::ident_expr("x"),
+        }))) ],
+        None,
+        Some(vec![ builder::expr_stmt(builder::call(builder::ident_expr("f"), vec![])) ]),
+    );
+    let mut body = parse_owned("function* g(a) { x = yield a }");
+    if let Statement::Function(ref mut inner) = body[0] {
+        inner.func.body.push(try_finally);
+    }
+
+    let output = lower_and_print(body);
+    assert!(output.ends_with(concat!(
+        "function g(a){return _generator(function(_context){switch(_context.label){",
+        "case 0:_context.label=1;return[4,a];",
+        "case 1:x=_context.sent();_context.label=2;",
+        "case 2:_context.trys.push([2,0,4,5]);_context.label=3;return[4,x];",
+        "case 3:_context.sent();return[3,5];",
+        "case 4:f();return[7];",
+        "case 5:return[2];",
+        "}},this);}",
+    )), "{}", output);
+}
@@ -0,0 +1,672 @@
+// Runtime helpers
+//
+// NOTE: 降级后的代码需要的少量辅助函数，按需生成并放在程序的开头，不依赖任何外部运行时。
+//       辅助函数的名字同样来自 `NameGenerator`，不会与源代码中的名字冲突。
+
+use crate::lexer::operator::{ PrefixOperator, InfixOperator, PostfixOperator, };
+use crate::ast::statement::LexicalDeclarationKind;
+use crate::ast::owned::*;
+use crate::ast::builder;
+use crate::compiler::transform::NameGenerator;
+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Helper {
+    /// `_extends(target, ...sources)`, `Object.assign` with an ES5 fallback.
+    Extends,
+    /// `_objectWithoutProperties(source, excluded)`, own enumerable properties of `source` not in `excluded`.
+    ObjectWithoutProperties,
+    /// `_values(iterable)`, an iterator over an iterable or an array-like value.
+    Values,
+    /// `_toConsumableArray(iterable)`, a new array of the values of an iterable or an array-like value.
+    ToConsumableArray,
+    /// `_generator(body, thisArg)`, the generator object driving a state machine.
+    Generator,
+    /// `_async(fn)`, runs the generator function `fn` as an async function.
+    Async,
+    /// `new _AwaitValue(value)`, an `await` inside an async generator.
+    AwaitValue,
+    /// `_asyncGenerator(fn)`, runs the generator function `fn` as an async generator.
+    AsyncGenerator,
+    /// `_asyncIterator(iterable)`, the iterator `for await` loops over.
+    AsyncIterator,
+    /// `_taggedTemplateLiteral(strings, raw)`, the frozen strings array passed to a template tag.
+    TaggedTemplateLiteral,
+}
+
+impl Helper {
+    fn base_name(&self) -> &'static str {
+        match *self {
+            Helper::Extends => "extends",
+            Helper::ObjectWithoutProperties => "objectWithoutProperties",
+            Helper::Values => "values",
+            Helper::ToConsumableArray => "toConsumableArray",
+            Helper::Generator => "generator",
+            Helper::Async => "async",
+            Helper::AwaitValue => "AwaitValue",
+            Helper::AsyncGenerator => "asyncGenerator",
+            Helper::AsyncIterator => "asyncIterator",
+            Helper::TaggedTemplateLiteral => "taggedTemplateLiteral",
+        }
+    }
+
+    fn requires(&self) -> &'static [Helper] {
+        match *self {
+            Helper::Generator | Helper::AsyncIterator | Helper::ToConsumableArray => &[ Helper::Values ],
+            Helper::AsyncGenerator => &[ Helper::AwaitValue ],
+            _ => &[],
+        }
+    }
+}
+
+/// The helpers used so far, in the order they were first requested.
+#[derive(Debug, Default)]
+pub struct Helpers {
+    used: Vec<(Helper, String)>,
+}
+
+impl Helpers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.used.is_empty()
+    }
+
+    /// The name to call `helper` by.
+    pub fn get(&mut self, names: &mut NameGenerator, helper: Helper) -> String {
+        if let Some(name) = self.name(helper) {
+            return name.to_string();
+        }
+
+        for required in helper.requires().iter() {
+            self.get(names, *required);
+        }
+
+        let name = names.fresh(helper.base_name());
+        self.used.push((helper, name.clone()));
+        name
+    }
+
+    fn name(&self, helper: Helper) -> Option<&str> {
+        self.used.iter().find(|&&(used, _)| used == helper).map(|&(_, ref name)| name.as_str())
+    }
+
+    fn required(&self, helper: Helper) -> &str {
+        self.name(helper).expect("required helper")
+    }
+
+    pub fn declarations(&self) -> Vec<Statement> {
+        self.used.iter()
+            .map(|&(helper, ref name)| match helper {
+                Helper::Extends => extends(name),
+                Helper::ObjectWithoutProperties => object_without_properties(name),
+                Helper::Values => values(name),
+                Helper::ToConsumableArray => to_consumable_array(name, self.required(Helper::Values)),
+                Helper::Generator => generator(name, self.required(Helper::Values)),
+                Helper::Async => async_function(name),
+                Helper::AwaitValue => await_value(name),
+                Helper::AsyncGenerator => async_generator(name, self.required(Helper::AwaitValue)),
+                Helper::AsyncIterator => async_iterator(name, self.required(Helper::Values)),
+                Helper::TaggedTemplateLiteral => tagged_template_literal(name),
+            })
+            .collect()
+    }
+}
+
+
+fn id(name: &str) -> Expression {
+    builder::ident_expr(name)
+}
+
+fn num(value: i64) -> Expression {
+    builder::number(value)
+}
+
+fn index(object: Expression, index: Expression) -> Expression {
+    builder::computed_member(object, index)
+}
+
+fn eq(left: Expression, right: Expression) -> Expression {
+    builder::infix(left, InfixOperator::StrictEq, right)
+}
+
+fn stmt(expr: Expression) -> Statement {
+    builder::expr_stmt(expr)
+}
+
+fn set(left: Expression, right: Expression) -> Statement {
+    builder::expr_stmt(builder::assign(left, right))
+}
+
+fn vars(declarators: Vec<(&str, Option<Expression>)>) -> Statement {
+    let declarators = declarators.into_iter().map(|(name, init)| builder::declarator(id(name), init)).collect();
+    builder::variable(LexicalDeclarationKind::Var, declarators)
+}
+
+fn ret(value: Expression) -> Statement {
+    builder::return_stmt(Some(value))
+}
+
+fn func(params: &[&str], body: Vec<Statement>) -> Expression {
+    builder::function_expr(None, params.iter().map(|name| id(name)).collect(), body)
+}
+
+fn iter_result(value: Expression, done: Expression) -> Expression {
+    builder::object(vec![ builder::named_property("value", value), builder::named_property("done", done) ])
+}
+
+// typeof Symbol === "function" && Symbol.<name>
+fn well_known_symbol(name: &str) -> Expression {
+    let has_symbol = eq(builder::prefix(PrefixOperator::TypeOf, id("Symbol")), builder::string("function"));
+    builder::infix(has_symbol, InfixOperator::And, builder::member(id("Symbol"), name))
+}
+
+// if (typeof Symbol === "function" && Symbol.<name>) target[Symbol.<name>] = function () { return this; };
+fn self_iterable(target: &str, symbol: &str) -> Statement {
+    builder::if_stmt(
+        well_known_symbol(symbol),
+        set(index(id(target), builder::member(id("Symbol"), symbol)), func(&[], vec![ ret(builder::this()) ])),
+        None,
+    )
+}
+
+// { next: function (value) { return <resume>(0, value); }, "throw": ..., "return": ... }
+fn resumable(resume: &str, make_op: fn(i64, Expression) -> Vec<Expression>) -> Expression {
+    let method = |kind: i64, param: &str| func(&[ param ], vec![ ret(builder::call(id(resume), make_op(kind, id(param)))) ]);
+    builder::object(vec![
+        builder::named_property("next", method(0, "value")),
+        builder::named_property("throw", method(1, "error")),
+        builder::named_property("return", method(2, "value")),
+    ])
+}
+
+
+// Object.prototype.hasOwnProperty.call(source, key)
+fn has_own_property(source: &str, key: &str) -> Expression {
+    let method = builder::member(builder::member(builder::ident_expr("Object"), "prototype"), "hasOwnProperty");
+    builder::call(builder::member(method, "call"), vec![ builder::ident_expr(source), builder::ident_expr(key) ])
+}
+
+// target[key] = source[key];
+fn copy_property(target: &str, source: &str, key: &str) -> Statement {
+    builder::expr_stmt(builder::assign(
+        builder::computed_member(builder::ident_expr(target), builder::ident_expr(key)),
+        builder::computed_member(builder::ident_expr(source), builder::ident_expr(key)),
+    ))
+}
+
+//  var _extends = Object.assign || function (target) {
+//      for (var i = 1; i < arguments.length; i++) {
+//          var source = arguments[i], key;
+//          for (key in source) if (Object.prototype.hasOwnProperty.call(source, key)) target[key] = source[key];
+//      }
+//      return target;
+//  };
+fn extends(name: &str) -> Statement {
+    let copy = builder::for_in_stmt(
+        builder::ident_expr("key"),
+        builder::ident_expr("source"),
+        builder::if_stmt(has_own_property("source", "key"), copy_property("target", "source", "key"), None),
+    );
+    let body = builder::block_stmt(vec![
+        builder::variable(LexicalDeclarationKind::Var, vec![
+            builder::declarator(builder::ident_expr("source"), Some(builder::computed_member(builder::ident_expr("arguments"), builder::ident_expr("i")))),
+            builder::declarator(builder::ident_expr("key"), None),
+        ]),
+        copy,
+    ]);
+    let each = builder::for_stmt(
+        Some(builder::var("i", Some(builder::number(1)))),
+        Some(builder::infix(builder::ident_expr("i"), InfixOperator::Lt, builder::member(builder::ident_expr("arguments"), "length"))),
+        Some(builder::postfix(PostfixOperator::Increment, builder::ident_expr("i"))),
+        body,
+    );
+    let fallback = builder::function_expr(None, vec![ builder::ident_expr("target") ], vec![
+        each,
+        builder::return_stmt(Some(builder::ident_expr("target"))),
+    ]);
+
+    let assign = builder::member(builder::ident_expr("Object"), "assign");
+    builder::var(name, Some(builder::infix(assign, InfixOperator::Or, fallback)))
+}
+
+//  function _objectWithoutProperties(source, excluded) {
+//      var target = {}, key;
+//      for (key in source) {
+//          if (excluded.indexOf(key) >= 0) continue;
+//          if (!Object.prototype.hasOwnProperty.call(source, key)) continue;
+//          target[key] = source[key];
+//      }
+//      return target;
+//  }
+fn object_without_properties(name: &str) -> Statement {
+    let index_of = builder::call(builder::member(builder::ident_expr("excluded"), "indexOf"), vec![ builder::ident_expr("key") ]);
+    let body = builder::block_stmt(vec![
+        builder::if_stmt(builder::infix(index_of, InfixOperator::GtEq, builder::number(0)), builder::continue_stmt(None), None),
+        builder::if_stmt(builder::prefix(PrefixOperator::Not, has_own_property("source", "key")), builder::continue_stmt(None), None),
+        copy_property("target", "source", "key"),
+    ]);
+
+    builder::function_decl(name, vec![ builder::ident_expr("source"), builder::ident_expr("excluded") ], vec![
+        builder::variable(LexicalDeclarationKind::Var, vec![
+            builder::declarator(builder::ident_expr("target"), Some(builder::object(vec![]))),
+            builder::declarator(builder::ident_expr("key"), None),
+        ]),
+        builder::for_in_stmt(builder::ident_expr("key"), builder::ident_expr("source"), body),
+        builder::return_stmt(Some(builder::ident_expr("target"))),
+    ])
+}
+
+
+//  function _values(o) {
+//      var method = typeof Symbol === "function" && Symbol.iterator && o[Symbol.iterator], i = 0;
+//      if (method) return method.call(o);
+//      return { next: function () { return { done: i >= o.length, value: o[i++] }; } };
+//  }
+fn values(name: &str) -> Statement {
+    let method = builder::infix(well_known_symbol("iterator"), InfixOperator::And, index(id("o"), builder::member(id("Symbol"), "iterator")));
+    let next = func(&[], vec![ ret(builder::object(vec![
+        builder::named_property("done", builder::infix(id("i"), InfixOperator::GtEq, builder::member(id("o"), "length"))),
+        builder::named_property("value", index(id("o"), builder::postfix(PostfixOperator::Increment, id("i")))),
+    ])) ]);
+
+    builder::function_decl(name, vec![ id("o") ], vec![
+        vars(vec![ ("method", Some(method)), ("i", Some(num(0))) ]),
+        builder::if_stmt(id("method"), ret(builder::call(builder::member(id("method"), "call"), vec![ id("o") ])), None),
+        ret(builder::object(vec![ builder::named_property("next", next) ])),
+    ])
+}
+
+//  function _toConsumableArray(o) {
+//      var iterator = _values(o), result = [], step;
+//      while (!(step = iterator.next()).done) result.push(step.value);
+//      return result;
+//  }
+fn to_consumable_array(name: &str, values: &str) -> Statement {
+    let step = builder::parenthesized(builder::assign(id("step"), builder::call(builder::member(id("iterator"), "next"), vec![])));
+    let push = stmt(builder::call(builder::member(id("result"), "push"), vec![ builder::member(id("step"), "value") ]));
+
+    builder::function_decl(name, vec![ id("o") ], vec![
+        vars(vec![
+            ("iterator", Some(builder::call(id(values), vec![ id("o") ]))),
+            ("result", Some(builder::array(vec![]))),
+            ("step", None),
+        ]),
+        builder::while_stmt(builder::prefix(PrefixOperator::Not, builder::member(step, "done")), push),
+        ret(id("result")),
+    ])
+}
+
+// NOTE: 状态机协议，`body` 返回的指令：
+//
+//      [2, value]  return              [3, label]  跳转（经过 finally）
+//      [4, value]  yield               [5, value]  yield*
+//      [7]         finally 结束        异常        直接抛出
+//
+//      `context.trys` 中的每一项为 `[try, catch, finally, end]` 四个 label（没有的为 0），
+//      label 按照源代码顺序编号，因此当前 label 与它们比较即可知道处于哪个区域。
+//      [0, value]、[1, error] 为 `next`、`throw` 的恢复指令，[6, error] 为执行中抛出的异常。
+//
+//  function _generator(body, thisArg) {
+//      var context = { label: 0, op: null, trys: [], pending: [] }, state = 0, delegate = null;
+//      context.sent = function () { if (context.op[0] === 1) throw context.op[1]; return context.op[1]; };
+//      function run(op) { ... }
+//      function resume(op) { ... }
+//      var generator = { next: ..., "throw": ..., "return": ... };
+//      if (typeof Symbol === "function" && Symbol.iterator) generator[Symbol.iterator] = function () { return this; };
+//      return generator;
+//  }
+fn generator(name: &str, values: &str) -> Statement {
+    let op = |n: i64| index(id("op"), num(n));
+    let kind = |n: i64| eq(id("kind"), num(n));
+    let context = |field: &str| builder::member(id("context"), field);
+    let op_array = |kind: Expression, value: Expression| builder::array(vec![ kind, value ]);
+
+    let sent = func(&[], vec![
+        builder::if_stmt(eq(index(context("op"), num(0)), num(1)), builder::throw_stmt(index(context("op"), num(1))), None),
+        ret(index(context("op"), num(1))),
+    ]);
+
+    // if (delegate) { ... }
+    let method = builder::conditional(
+        eq(op(0), num(0)),
+        builder::string("next"),
+        builder::conditional(eq(op(0), num(1)), builder::string("throw"), builder::string("return")),
+    );
+    let delegate = builder::if_stmt(id("delegate"), builder::block_stmt(vec![
+        vars(vec![ ("method", Some(method)) ]),
+        builder::if_stmt(index(id("delegate"), id("method")), builder::block_stmt(vec![
+            builder::try_stmt(
+                vec![ vars(vec![ ("result", Some(builder::call(index(id("delegate"), id("method")), vec![ op(1) ]))) ]) ],
+                Some((id("e"), vec![
+                    set(id("delegate"), builder::null()),
+                    set(id("op"), op_array(num(6), id("e"))),
+                    builder::continue_stmt(None),
+                ])),
+                None,
+            ),
+            builder::if_stmt(builder::prefix(PrefixOperator::Not, builder::member(id("result"), "done")), builder::block_stmt(vec![
+                set(id("state"), num(1)),
+                ret(id("result")),
+            ]), None),
+            set(id("op"), op_array(
+                builder::conditional(eq(op(0), num(2)), num(2), num(0)),
+                builder::member(id("result"), "value"),
+            )),
+        ]), None),
+        set(id("delegate"), builder::null()),
+        builder::continue_stmt(None),
+    ]), None);
+
+    let route = builder::block_stmt(vec![
+        vars(vec![ ("t", Some(index(context("trys"), builder::infix(builder::member(context("trys"), "length"), InfixOperator::Sub, num(1))))) ]),
+        builder::if_stmt(
+            builder::infix(builder::prefix(PrefixOperator::Not, id("t")), InfixOperator::And, builder::infix(id("kind"), InfixOperator::StrictNeq, num(3))),
+            builder::block_stmt(vec![
+                set(id("state"), num(3)),
+                builder::if_stmt(kind(6), builder::throw_stmt(op(1)), None),
+                ret(iter_result(op(1), builder::boolean(true))),
+            ]),
+            None,
+        ),
+        builder::if_stmt(
+            // !t || kind === 3 && op[1] > t[0] && op[1] < t[3]
+            builder::infix(
+                builder::prefix(PrefixOperator::Not, id("t")),
+                InfixOperator::Or,
+                builder::infix(
+                    builder::infix(kind(3), InfixOperator::And, builder::infix(op(1), InfixOperator::Gt, index(id("t"), num(0)))),
+                    InfixOperator::And,
+                    builder::infix(op(1), InfixOperator::Lt, index(id("t"), num(3))),
+                ),
+            ),
+            set(context("label"), op(1)),
+            Some(builder::if_stmt(
+                builder::infix(
+                    builder::infix(kind(6), InfixOperator::And, index(id("t"), num(1))),
+                    InfixOperator::And,
+                    builder::infix(context("label"), InfixOperator::Lt, index(id("t"), num(1))),
+                ),
+                builder::block_stmt(vec![ set(context("label"), index(id("t"), num(1))), set(context("op"), id("op")) ]),
+                Some(builder::if_stmt(
+                    builder::infix(index(id("t"), num(2)), InfixOperator::And, builder::infix(context("label"), InfixOperator::Lt, index(id("t"), num(2)))),
+                    builder::block_stmt(vec![
+                        set(context("label"), index(id("t"), num(2))),
+                        stmt(builder::call(builder::member(context("pending"), "push"), vec![ id("op") ])),
+                    ]),
+                    Some(builder::block_stmt(vec![
+                        builder::if_stmt(index(id("t"), num(2)), stmt(builder::call(builder::member(context("pending"), "pop"), vec![])), None),
+                        stmt(builder::call(builder::member(context("trys"), "pop"), vec![])),
+                        builder::continue_stmt(None),
+                    ])),
+                )),
+            )),
+        ),
+    ]);
+
+    let run = builder::function_decl("run", vec![ id("op") ], vec![
+        builder::while_stmt(num(1), builder::block_stmt(vec![
+            delegate,
+            vars(vec![ ("kind", Some(op(0))) ]),
+            builder::if_stmt(kind(4), builder::block_stmt(vec![
+                set(id("state"), num(1)),
+                ret(iter_result(op(1), builder::boolean(false))),
+            ]), None),
+            builder::if_stmt(kind(5), builder::block_stmt(vec![
+                set(id("delegate"), builder::call(id(values), vec![ op(1) ])),
+                set(id("op"), op_array(num(0), builder::undefined())),
+                builder::continue_stmt(None),
+            ]), None),
+            builder::if_stmt(kind(7), builder::block_stmt(vec![
+                set(id("op"), builder::call(builder::member(context("pending"), "pop"), vec![])),
+                stmt(builder::call(builder::member(context("trys"), "pop"), vec![])),
+                builder::continue_stmt(None),
+            ]), None),
+            builder::if_stmt(
+                builder::infix(kind(0), InfixOperator::Or, kind(1)),
+                set(context("op"), id("op")),
+                Some(route),
+            ),
+            builder::try_stmt(
+                vec![ set(id("op"), builder::call(builder::member(id("body"), "call"), vec![ id("thisArg"), id("context") ])) ],
+                Some((id("e"), vec![ set(id("op"), op_array(num(6), id("e"))) ])),
+                None,
+            ),
+        ])),
+    ]);
+
+    let resume = builder::function_decl("resume", vec![ id("op") ], vec![
+        builder::if_stmt(
+            eq(id("state"), num(2)),
+            builder::throw_stmt(builder::new(id("TypeError"), vec![ builder::string("Generator is already running") ])),
+            None,
+        ),
+        builder::if_stmt(
+            builder::infix(
+                eq(id("state"), num(3)),
+                InfixOperator::Or,
+                builder::infix(eq(id("state"), num(0)), InfixOperator::And, builder::infix(op(0), InfixOperator::StrictNeq, num(0))),
+            ),
+            builder::block_stmt(vec![
+                set(id("state"), num(3)),
+                builder::if_stmt(eq(op(0), num(1)), builder::throw_stmt(op(1)), None),
+                ret(iter_result(builder::conditional(eq(op(0), num(2)), op(1), builder::undefined()), builder::boolean(true))),
+            ]),
+            None,
+        ),
+        set(id("state"), num(2)),
+        builder::try_stmt(
+            vec![ ret(builder::call(id("run"), vec![ id("op") ])) ],
+            Some((id("e"), vec![ set(id("state"), num(3)), builder::throw_stmt(id("e")) ])),
+            None,
+        ),
+    ]);
+
+    builder::function_decl(name, vec![ id("body"), id("thisArg") ], vec![
+        vars(vec![
+            ("context", Some(builder::object(vec![
+                builder::named_property("label", num(0)),
+                builder::named_property("op", builder::null()),
+                builder::named_property("trys", builder::array(vec![])),
+                builder::named_property("pending", builder::array(vec![])),
+            ]))),
+            ("state", Some(num(0))),
+            ("delegate", Some(builder::null())),
+        ]),
+        set(context("sent"), sent),
+        run,
+        resume,
+        vars(vec![ ("generator", Some(resumable("resume", |kind, value| vec![ builder::array(vec![ num(kind), value ]) ]))) ]),
+        self_iterable("generator", "iterator"),
+        ret(id("generator")),
+    ])
+}
+
+//  function _async(fn) {
+//      return function () {
+//          var self = this, args = arguments;
+//          return new Promise(function (resolve, reject) {
+//              var generator = fn.apply(self, args);
+//              function step(kind, value) {
+//                  try { var result = kind === 0 ? generator.next(value) : generator["throw"](value); }
+//                  catch (e) { reject(e); return; }
+//                  if (result.done) resolve(result.value);
+//                  else Promise.resolve(result.value).then(function (value) { step(0, value); }, function (error) { step(1, error); });
+//              }
+//              step(0, void 0);
+//          });
+//      };
+//  }
+fn async_function(name: &str) -> Statement {
+    let resume = builder::conditional(
+        eq(id("kind"), num(0)),
+        builder::call(builder::member(id("generator"), "next"), vec![ id("value") ]),
+        builder::call(index(id("generator"), builder::string("throw")), vec![ id("value") ]),
+    );
+    let step = builder::function_decl("step", vec![ id("kind"), id("value") ], vec![
+        builder::try_stmt(
+            vec![ vars(vec![ ("result", Some(resume)) ]) ],
+            Some((id("e"), vec![ stmt(builder::call(id("reject"), vec![ id("e") ])), builder::return_stmt(None) ])),
+            None,
+        ),
+        builder::if_stmt(
+            builder::member(id("result"), "done"),
+            stmt(builder::call(id("resolve"), vec![ builder::member(id("result"), "value") ])),
+            Some(stmt(then(builder::member(id("result"), "value"), "step", "step"))),
+        ),
+    ]);
+    let executor = func(&[ "resolve", "reject" ], vec![
+        vars(vec![ ("generator", Some(builder::call(builder::member(id("fn"), "apply"), vec![ id("self"), id("args") ]))) ]),
+        step,
+        stmt(builder::call(id("step"), vec![ num(0), builder::undefined() ])),
+    ]);
+
+    builder::function_decl(name, vec![ id("fn") ], vec![
+        ret(func(&[], vec![
+            vars(vec![ ("self", Some(builder::this())), ("args", Some(id("arguments"))) ]),
+            ret(builder::new(id("Promise"), vec![ executor ])),
+        ])),
+    ])
+}
+
+// Promise.resolve(value).then(function (value) { fulfilled(0, value); }, function (error) { rejected(1, error); })
+fn then(value: Expression, fulfilled: &str, rejected: &str) -> Expression {
+    let resolved = builder::call(builder::member(id("Promise"), "resolve"), vec![ value ]);
+    builder::call(builder::member(resolved, "then"), vec![
+        func(&[ "value" ], vec![ stmt(builder::call(id(fulfilled), vec![ num(0), id("value") ])) ]),
+        func(&[ "error" ], vec![ stmt(builder::call(id(rejected), vec![ num(1), id("error") ])) ]),
+    ])
+}
+
+//  function _AwaitValue(value) { this.value = value; }
+fn await_value(name: &str) -> Statement {
+    builder::function_decl(name, vec![ id("value") ], vec![ set(builder::member(builder::this(), "value"), id("value")) ])
+}
+
+//  function _asyncGenerator(fn) {
+//      return function () {
+//          var generator = fn.apply(this, arguments), queue = [], running = false;
+//          function send(kind, value) { ... }
+//          function resume(kind, value) { ... }
+//          function settle(kind, value) { ... }
+//          var iterator = { next: ..., "throw": ..., "return": ... };
+//          if (typeof Symbol === "function" && Symbol.asyncIterator) iterator[Symbol.asyncIterator] = function () { return this; };
+//          return iterator;
+//      };
+//  }
+fn async_generator(name: &str, await_value: &str) -> Statement {
+    // queue.push([kind, value, resolve, reject]); if (!running) resume(kind, value);
+    let send = builder::function_decl("send", vec![ id("kind"), id("value") ], vec![
+        ret(builder::new(id("Promise"), vec![ func(&[ "resolve", "reject" ], vec![
+            stmt(builder::call(builder::member(id("queue"), "push"), vec![
+                builder::array(vec![ id("kind"), id("value"), id("resolve"), id("reject") ]),
+            ])),
+            builder::if_stmt(
+                builder::prefix(PrefixOperator::Not, id("running")),
+                stmt(builder::call(id("resume"), vec![ id("kind"), id("value") ])),
+                None,
+            ),
+        ]) ])),
+    ]);
+
+    let method = builder::conditional(
+        eq(id("kind"), num(0)),
+        builder::string("next"),
+        builder::conditional(eq(id("kind"), num(1)), builder::string("throw"), builder::string("return")),
+    );
+    let resume = builder::function_decl("resume", vec![ id("kind"), id("value") ], vec![
+        set(id("running"), builder::boolean(true)),
+        builder::try_stmt(
+            vec![ vars(vec![ ("result", Some(builder::call(index(id("generator"), method), vec![ id("value") ]))) ]) ],
+            Some((id("e"), vec![ stmt(builder::call(id("settle"), vec![ num(3), id("e") ])), builder::return_stmt(None) ])),
+            None,
+        ),
+        builder::if_stmt(
+            builder::infix(builder::member(id("result"), "value"), InfixOperator::InstanceOf, id(await_value)),
+            builder::block_stmt(vec![
+                stmt(then(builder::member(builder::member(id("result"), "value"), "value"), "resume", "resume")),
+                builder::return_stmt(None),
+            ]),
+            None,
+        ),
+        stmt(builder::call(id("settle"), vec![ num(2), id("result") ])),
+    ]);
+
+    // request[kind](value): 2 resolve, 3 reject
+    let settle = builder::function_decl("settle", vec![ id("kind"), id("value") ], vec![
+        vars(vec![ ("request", Some(builder::call(builder::member(id("queue"), "shift"), vec![]))) ]),
+        stmt(builder::call(index(id("request"), id("kind")), vec![ id("value") ])),
+        builder::if_stmt(
+            builder::member(id("queue"), "length"),
+            stmt(builder::call(id("resume"), vec![ index(index(id("queue"), num(0)), num(0)), index(index(id("queue"), num(0)), num(1)) ])),
+            Some(set(id("running"), builder::boolean(false))),
+        ),
+    ]);
+
+    builder::function_decl(name, vec![ id("fn") ], vec![
+        ret(func(&[], vec![
+            vars(vec![
+                ("generator", Some(builder::call(builder::member(id("fn"), "apply"), vec![ builder::this(), id("arguments") ]))),
+                ("queue", Some(builder::array(vec![]))),
+                ("running", Some(builder::boolean(false))),
+            ]),
+            send,
+            resume,
+            settle,
+            vars(vec![ ("iterator", Some(resumable("send", |kind, value| vec![ num(kind), value ]))) ]),
+            self_iterable("iterator", "asyncIterator"),
+            ret(id("iterator")),
+        ])),
+    ])
+}
+
+//  function _asyncIterator(o) {
+//      var method = typeof Symbol === "function" && Symbol.asyncIterator && o[Symbol.asyncIterator];
+//      if (method) return method.call(o);
+//      var iterator = _values(o);
+//      return { next: function (value) {
+//          var result = iterator.next(value);
+//          return Promise.resolve(result.value).then(function (value) { return { value: value, done: result.done }; });
+//      } };
+//  }
+fn async_iterator(name: &str, values: &str) -> Statement {
+    let method = builder::infix(well_known_symbol("asyncIterator"), InfixOperator::And, index(id("o"), builder::member(id("Symbol"), "asyncIterator")));
+    let resolved = builder::call(builder::member(id("Promise"), "resolve"), vec![ builder::member(id("result"), "value") ]);
+    let next = func(&[ "value" ], vec![
+        vars(vec![ ("result", Some(builder::call(builder::member(id("iterator"), "next"), vec![ id("value") ]))) ]),
+        ret(builder::call(builder::member(resolved, "then"), vec![
+            func(&[ "value" ], vec![ ret(iter_result(id("value"), builder::member(id("result"), "done"))) ]),
+        ])),
+    ]);
+
+    builder::function_decl(name, vec![ id("o") ], vec![
+        vars(vec![ ("method", Some(method)) ]),
+        builder::if_stmt(id("method"), ret(builder::call(builder::member(id("method"), "call"), vec![ id("o") ])), None),
+        vars(vec![ ("iterator", Some(builder::call(id(values), vec![ id("o") ]))) ]),
+        ret(builder::object(vec![ builder::named_property("next", next) ])),
+    ])
+}
+
+
+//  function _taggedTemplateLiteral(strings, raw) {
+//      if (!raw) raw = strings.slice(0);
+//      return Object.freeze(Object.defineProperties(strings, { raw: { value: Object.freeze(raw) } }));
+//  }
+fn tagged_template_literal(name: &str) -> Statement {
+    let freeze = |value: Expression| builder::call(builder::member(id("Object"), "freeze"), vec![ value ]);
+    let raw = builder::object(vec![
+        builder::named_property("raw", builder::object(vec![ builder::named_property("value", freeze(id("raw"))) ])),
+    ]);
+    let define = builder::call(builder::member(id("Object"), "defineProperties"), vec![ id("strings"), raw ]);
+
+    builder::function_decl(name, vec![ id("strings"), id("raw") ], vec![
+        builder::if_stmt(
+            builder::prefix(PrefixOperator::Not, id("raw")),
+            set(id("raw"), builder::call(builder::member(id("strings"), "slice"), vec![ num(0) ])),
+            None,
+        ),
+        ret(freeze(define)),
+    ])
+}
@@ -0,0 +1,555 @@
+// JSX → function calls
+//
+// Classic runtime ( `pragma` / `pragmaFrag` ):
+//
+//      <div id="a" {...b}>x</div>      →   React.createElement("div", _extends({ id: "a" }, b), "x")
+//      <Foo.Bar key={k} />             →   React.createElement(Foo.Bar, { key: k })
+//      <>x{y}</>                       →   React.createElement(React.Fragment, null, "x", y)
+//
+// Automatic runtime ( `jsx` / `jsxs` from `<importSource>/jsx-runtime` ):
+//
+//      <div key={k} id="a">x</div>     →   _jsx("div", { id: "a", children: "x" }, k)
+//      <>x{y}</>                       →   _jsxs(_Fragment, { children: ["x", y] })
+//      <div {...p} key={k} />          →   _createElement("div", _extends({}, p, { key: k }))
+//
+// NOTE: AST 中还没有模块声明，自动运行时的导入以 `var _jsx = require("react/jsx-runtime").jsx;`
+//       的形式声明在程序开头。
+//       展开属性之后出现的 `key` 不能从 props 中提取出来（展开的对象中可能也有 `key`），
+//       与 Babel 一样退回到 `importSource` 导出的 `createElement`。
+
+use crate::error::Error;
+use crate::version::ECMAScriptVersion;
+use crate::ast::jsx::{ CREATE_JSX_ELEMENT, CREATE_JSX_FRAGMENT, };
+use crate::ast::statement::LexicalDeclarationKind;
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{ NameGenerator, Helper, Helpers, directive_prologue, };
+
+use std::mem;
+
+
+pub const DEFAULT_IMPORT_SOURCE: &str = "react";
+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum JsxRuntime {
+    /// `pragma(type, props, ...children)`
+    Classic,
+    /// `jsx(type, { ...props, children }, key)` imported from `<import_source>/jsx-runtime`
+    Automatic,
+}
+
+/// Options of the JSX transform.
+///
+/// `pragma` and `pragma_frag` are only used by the classic runtime, `import_source` only by the automatic one.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct JsxOptions {
+    pub runtime: JsxRuntime,
+    /// `React.createElement`, `h`, `preact.h`, ...
+    pub pragma: String,
+    /// `React.Fragment`, `Fragment`, ...
+    pub pragma_frag: String,
+    /// `react`, `preact`, ...
+    pub import_source: String,
+}
+
+impl Default for JsxOptions {
+    fn default() -> Self {
+        Self {
+            runtime: JsxRuntime::Classic,
+            pragma: CREATE_JSX_ELEMENT.to_string(),
+            pragma_frag: CREATE_JSX_FRAGMENT.to_string(),
+            import_source: DEFAULT_IMPORT_SOURCE.to_string(),
+        }
+    }
+}
+
+
+/// `React.createElement` → `React.createElement` ( member expressions ), `h` → `h`
+fn pragma(name: &str) -> Expression {
+    let mut parts = name.split('.');
+    let head = match parts.next() {
+        Some("this") => builder::this(),
+        Some(head) => builder::ident_expr(head),
+        None => builder::ident_expr(name),
+    };
+
+    parts.fold(head, |object, part| builder::member(object, part))
+}
+
+fn is_identifier_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' || c == '$' => { },
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+// NOTE: 小写字母开头的名字（以及 `foo-bar` 这样的自定义元素）是内置的标签，作为字符串传递。
+fn is_tag_name(name: &str) -> bool {
+    name.chars().next().map(|c| c.is_ascii_lowercase()).unwrap_or(false) || !is_identifier_name(name)
+}
+
+fn entity(name: &str) -> Option<char> {
+    if name.starts_with("#x") || name.starts_with("#X") {
+        return u32::from_str_radix(&name[2..], 16).ok().and_then(::std::char::from_u32);
+    }
+    if name.starts_with('#') {
+        return name[1..].parse::<u32>().ok().and_then(::std::char::from_u32);
+    }
+
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => None,
+    }
+}
+
+/// Decodes the character references of a JSX text or attribute string: `&amp;`, `&#123;`, `&#x7B;`, `&nbsp;`, ...
+fn decode_entities(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        value.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| entity(&rest[1..end]).map(|c| (c, end)));
+        match decoded {
+            Some((c, end)) => {
+                value.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                value.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    value.push_str(rest);
+
+    value
+}
+
+/// The whitespace rules of React:
+/// lines are trimmed ( except the leading whitespace of the first line and the trailing whitespace of the last line ),
+/// lines consisting only of whitespace are removed, and the remaining lines are joined by a single space.
+pub fn clean_text(raw: &str) -> String {
+    let raw = raw.replace("\r\n", "\n").replace('\r', "\n");
+    let lines = raw.split('\n').collect::<Vec<&str>>();
+    let last_non_empty = lines.iter().rposition(|line| line.chars().any(|c| c != ' ' && c != '\t')).unwrap_or(0);
+
+    let mut text = String::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let mut line = line.replace('\t', " ");
+        if idx > 0 {
+            line = line.trim_start_matches(' ').to_string();
+        }
+        if idx < lines.len() - 1 {
+            line = line.trim_end_matches(' ').to_string();
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        text.push_str(&line);
+        if idx != last_non_empty {
+            text.push(' ');
+        }
+    }
+
+    decode_entities(&text)
+}
+
+fn element_type(name: JSXElementName) -> Expression {
+    match name {
+        JSXElementName::Identifier(ref ident) if ident.name() == "this" => builder::this(),
+        JSXElementName::Identifier(ref ident) if is_tag_name(ident.name()) => builder::string_value(ident.name()),
+        JSXElementName::Identifier(ident) => Expression::Identifier(Box::new(ident)),
+        JSXElementName::NamespacedName(inner) => builder::string_value(&format!("{}:{}", inner.namespace.name(), inner.name.name())),
+        JSXElementName::MemberExpression(idents) => {
+            let mut idents = idents.into_iter();
+            let head = match idents.next() {
+                Some(ref ident) if ident.name() == "this" => builder::this(),
+                Some(ident) => Expression::Identifier(Box::new(ident)),
+                None => builder::null(),
+            };
+            idents.fold(head, |object, ident| builder::member(object, ident.name()))
+        },
+    }
+}
+
+fn attribute_name(name: &JSXNormalAttributeName) -> String {
+    match *name {
+        JSXNormalAttributeName::Identifier(ref ident) => ident.name().to_string(),
+        JSXNormalAttributeName::NamespacedName(ref inner) => format!("{}:{}", inner.namespace.name(), inner.name.name()),
+    }
+}
+
+fn property(name: &str, value: Expression) -> ObjectProperty {
+    if is_identifier_name(name) {
+        builder::named_property(name, value)
+    } else {
+        builder::property(PropertyName::String(LiteralString { loc: Default::default(), span: Default::default(), raw: name.to_string(), cooked: None }), value)
+    }
+}
+
+
+enum Prop {
+    Property(ObjectProperty),
+    Spread(Expression),
+}
+
+struct JsxLowering<'a> {
+    options: &'a JsxOptions,
+    target: ECMAScriptVersion,
+    names: &'a mut NameGenerator,
+    helpers: &'a mut Helpers,
+    /// `(local, module, export)`
+    imports: Vec<(String, String, &'static str)>,
+}
+
+impl<'a> JsxLowering<'a> {
+    /// The local binding of `export` imported from `module`.
+    fn import(&mut self, module: String, export: &'static str) -> Expression {
+        let local = match self.imports.iter().find(|&&(_, ref m, e)| *m == module && e == export) {
+            Some(&(ref local, _, _)) => local.clone(),
+            None => {
+                let local = self.names.fresh(export);
+                self.imports.push((local.clone(), module, export));
+                local
+            },
+        };
+
+        builder::ident_expr(&local)
+    }
+
+    fn runtime(&mut self, export: &'static str) -> Expression {
+        let module = format!("{}/jsx-runtime", self.options.import_source);
+        self.import(module, export)
+    }
+
+    fn attribute_value(&mut self, init: Option<JSXNormalAttributeInitializer>) -> Expression {
+        match init {
+            None => builder::boolean(true),
+            Some(JSXNormalAttributeInitializer::Identifier(ident)) => {
+                // NOTE: 属性字符串中的换行连同后面的空白替换为一个空格
+                let mut value = String::new();
+                let mut lines = ident.raw.split('\n');
+                if let Some(first) = lines.next() {
+                    value.push_str(first);
+                }
+                for line in lines {
+                    let trimmed = line.trim_start();
+                    if trimmed.len() < line.len() {
+                        value.push(' ');
+                    } else {
+                        value.push('\n');
+                    }
+                    value.push_str(trimmed);
+                }
+                builder::string_value(&decode_entities(&value))
+            },
+            Some(JSXNormalAttributeInitializer::Assignment(mut expr)) => {
+                self.visit_expression(&mut expr);
+                expr
+            },
+            Some(JSXNormalAttributeInitializer::Element(elem)) => self.element(elem),
+            Some(JSXNormalAttributeInitializer::Fragment(frag)) => self.fragment(frag),
+        }
+    }
+
+    fn children(&mut self, children: Option<Vec<JSXChild>>) -> Vec<Expression> {
+        let mut items = Vec::new();
+        for child in children.unwrap_or_default() {
+            match child {
+                JSXChild::Text(text) => {
+                    let text = clean_text(&text.raw);
+                    if !text.is_empty() {
+                        items.push(builder::string_value(&text));
+                    }
+                },
+                JSXChild::Element(elem) => items.push(self.element(elem)),
+                JSXChild::ChildExpression(Some(mut exprs)) => {
+                    if exprs.is_empty() {
+                        continue;
+                    }
+                    for expr in exprs.iter_mut() {
+                        self.visit_expression(expr);
+                    }
+                    items.push(builder::comma(exprs));
+                },
+                JSXChild::ChildExpression(None) => { },
+            }
+        }
+
+        items
+    }
+
+    /// `{ a: 1, ...b }`, or `_extends({ a: 1 }, b)` when the target does not support object spread.
+    fn object(&mut self, props: Vec<Prop>) -> Option<Expression> {
+        if props.is_empty() {
+            return None;
+        }
+
+        let has_spread = props.iter().any(|prop| match *prop { Prop::Spread(_) => true, _ => false });
+        if !has_spread || self.target >= ECMAScriptVersion::ES2018 {
+            let properties = props.into_iter().map(|prop| match prop {
+                Prop::Property(property) => property,
+                Prop::Spread(target) => ObjectProperty::Spread { puct: builder::colon(), target },
+            }).collect();
+            return Some(builder::object(properties));
+        }
+
+        // NOTE: 第一个参数必须是新的对象，不能修改展开的对象。
+        let mut arguments = Vec::new();
+        let mut current = Vec::new();
+        for prop in props {
+            match prop {
+                Prop::Property(property) => current.push(property),
+                Prop::Spread(target) => {
+                    if !current.is_empty() || arguments.is_empty() {
+                        arguments.push(builder::object(mem::replace(&mut current, Vec::new())));
+                    }
+                    arguments.push(target);
+                },
+            }
+        }
+        if !current.is_empty() {
+            arguments.push(builder::object(current));
+        }
+
+        let helper = self.helpers.get(self.names, Helper::Extends);
+        Some(builder::call(builder::ident_expr(&helper), arguments))
+    }
+
+    fn props(&mut self, attrs: Option<Vec<JSXAttribute>>) -> Vec<(Option<String>, Prop)> {
+        let mut props = Vec::new();
+        for attr in attrs.unwrap_or_default() {
+            match attr {
+                JSXAttribute::Spread(mut expr) => {
+                    self.visit_expression(&mut expr);
+                    props.push((None, Prop::Spread(expr)));
+                },
+                JSXAttribute::Normal(attr) => {
+                    let name = attribute_name(&attr.name);
+                    let value = self.attribute_value(attr.init);
+                    props.push((Some(name.clone()), Prop::Property(property(&name, value))));
+                },
+            }
+        }
+
+        props
+    }
+
+    fn create(&mut self, name: JSXElementName, attrs: Option<Vec<JSXAttribute>>, children: Option<Vec<JSXChild>>) -> Expression {
+        let kind = element_type(name);
+        let props = self.props(attrs);
+
+        match self.options.runtime {
+            JsxRuntime::Classic => {
+                let callee = pragma(&self.options.pragma);
+                self.classic(callee, kind, props, children)
+            },
+            JsxRuntime::Automatic => self.automatic(kind, props, children),
+        }
+    }
+
+    // pragma(type, props, ...children)
+    fn classic(&mut self, callee: Expression, kind: Expression, props: Vec<(Option<String>, Prop)>, children: Option<Vec<JSXChild>>) -> Expression {
+        let props = self.object(props.into_iter().map(|(_, prop)| prop).collect());
+        let mut arguments = vec![ kind, props.unwrap_or_else(builder::null) ];
+        arguments.extend(self.children(children));
+
+        builder::call(callee, arguments)
+    }
+
+    // jsx(type, { ...props, children }, key)
+    fn automatic(&mut self, kind: Expression, props: Vec<(Option<String>, Prop)>, children: Option<Vec<JSXChild>>) -> Expression {
+        let key_after_spread = props.iter()
+            .skip_while(|&&(ref name, _)| name.is_some())
+            .any(|&(ref name, _)| name.as_ref().map(|name| name == "key").unwrap_or(false));
+        if key_after_spread {
+            let module = self.options.import_source.clone();
+            let callee = self.import(module, "createElement");
+            return self.classic(callee, kind, props, children);
+        }
+
+        let mut key = None;
+        let mut rest = Vec::new();
+        for (name, prop) in props {
+            match (name, prop) {
+                (Some(ref name), Prop::Property(ObjectProperty::Property { value, .. })) if name == "key" => key = Some(value),
+                (_, prop) => rest.push(prop),
+            }
+        }
+
+        let mut children = self.children(children);
+        let export = if children.len() > 1 { "jsxs" } else { "jsx" };
+        match children.len() {
+            0 => { },
+            1 => rest.push(Prop::Property(builder::named_property("children", children.remove(0)))),
+            _ => rest.push(Prop::Property(builder::named_property("children", builder::array(children)))),
+        }
+
+        let callee = self.runtime(export);
+        let props = self.object(rest).unwrap_or_else(|| builder::object(Vec::new()));
+        let mut arguments = vec![ kind, props ];
+        arguments.extend(key);
+
+        builder::call(callee, arguments)
+    }
+
+    fn element(&mut self, elem: JSXElement) -> Expression {
+        match elem {
+            JSXElement::SelfClosing(inner) => self.create(inner.name, inner.attrs, None),
+            JSXElement::Normal(inner) => self.create(inner.opening.name, inner.opening.attrs, inner.children),
+        }
+    }
+
+    fn fragment(&mut self, frag: JSXFragment) -> Expression {
+        match self.options.runtime {
+            JsxRuntime::Classic => {
+                let callee = pragma(&self.options.pragma);
+                let kind = pragma(&self.options.pragma_frag);
+                self.classic(callee, kind, Vec::new(), frag.children)
+            },
+            JsxRuntime::Automatic => {
+                let kind = self.runtime("Fragment");
+                self.automatic(kind, Vec::new(), frag.children)
+            },
+        }
+    }
+}
+
+impl<'a> VisitMut for JsxLowering<'a> {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        match *expr {
+            Expression::JSXElement(_) | Expression::JSXFragment(_) => { },
+            _ => return visit::walk_expression(self, expr),
+        }
+
+        *expr = match mem::replace(expr, builder::null()) {
+            Expression::JSXElement(elem) => self.element(*elem),
+            Expression::JSXFragment(frag) => self.fragment(*frag),
+            _ => unreachable!(),
+        };
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, options: &JsxOptions, target: ECMAScriptVersion, names: &mut NameGenerator, helpers: &mut Helpers) -> Result<(), Error> {
+    let mut pass = JsxLowering { options, target, names, helpers, imports: Vec::new() };
+    pass.visit_statements(body);
+
+    if !pass.imports.is_empty() {
+        let declarators = pass.imports.iter().map(|&(ref local, ref module, export)| {
+            let module = builder::call(builder::ident_expr("require"), vec![ builder::string_value(module) ]);
+            builder::declarator(builder::ident_expr(local), Some(builder::member(module, export)))
+        }).collect();
+        let index = directive_prologue(body);
+        body.insert(index, builder::variable(LexicalDeclarationKind::Var, declarators));
+    }
+
+    Ok(())
+}
+
+
+#[test]
+fn test_jsx_lowering() {
+    use crate::compiler::transform::{ Transformer, print_owned, };
+
+    fn name(name: &str) -> JSXElementName {
+        JSXElementName::Identifier(builder::ident(name))
+    }
+    fn attr(name: &str, init: Option<JSXNormalAttributeInitializer>) -> JSXAttribute {
+        JSXAttribute::Normal(JSXNormalAttribute { loc: Default::default(), span: Default::default(), name: JSXNormalAttributeName::Identifier(builder::ident(name)), init })
+    }
+    fn text(raw: &str) -> JSXChild {
+        JSXChild::Text(LiteralString { loc: Default::default(), span: Default::default(), raw: raw.to_string(), cooked: None })
+    }
+    fn element(tag: &str, attrs: Vec<JSXAttribute>, children: Vec<JSXChild>) -> JSXElement {
+        let opening = JSXOpeningElement { loc: Default::default(), span: Default::default(), name: name(tag), attrs: Some(attrs) };
+        let closing = JSXClosingElement { loc: Default::default(), span: Default::default(), name: name(tag) };
+        JSXElement::Normal(JSXNormalElement { loc: Default::default(), span: Default::default(), opening, children: Some(children), closing })
+    }
+    fn lower(options: JsxOptions, target: ECMAScriptVersion, expr: Expression) -> String {
+        let mut body = vec![ builder::expr_stmt(expr) ];
+        let mut transformer = Transformer::new(target);
+        transformer.set_jsx(options);
+        transformer.transform_program(&mut body).ok().unwrap();
+        print_owned(&body)
+    }
+
+    // <ul class="list" {...props} key={k}>
+    //     <li>Hello,&nbsp;
+    //         world</li>
+    //     {items}
+    //     <Foo bar />
+    // </ul>
+    let ul = element("ul", vec![
+        attr("class", Some(JSXNormalAttributeInitializer::Identifier(builder::ident("list")))),
+        JSXAttribute::Spread(builder::ident_expr("props")),
+        attr("key", Some(JSXNormalAttributeInitializer::Assignment(builder::ident_expr("k")))),
+    ], vec![
+        text("\n    "),
+        JSXChild::Element(element("li", vec![], vec![ text("Hello,&nbsp;\n        world") ])),
+        text("\n    "),
+        JSXChild::ChildExpression(Some(vec![ builder::ident_expr("items") ])),
+        text("\n    "),
+        JSXChild::Element(JSXElement::SelfClosing(JSXSelfClosingElement {
+            loc: Default::default(), span: Default::default(), name: name("Foo"), attrs: Some(vec![ attr("bar", None) ]),
+        })),
+        text("\n"),
+    ]);
+    let ul = Expression::JSXElement(Box::new(ul));
+
+    let preact = JsxOptions { pragma: "h".to_string(), pragma_frag: "Fragment".to_string(), ..JsxOptions::default() };
+    assert_eq!(
+        lower(preact.clone(), ECMAScriptVersion::ES2018, ul.clone()),
+        "h(\"ul\",{class:\"list\",...props,key:k},h(\"li\",null,\"Hello,\u{a0} world\"),items,h(Foo,{bar:true}));"
+    );
+    // NOTE: 对象展开是 ES2018 的语法
+    assert_eq!(
+        lower(preact.clone(), ECMAScriptVersion::ES2015, ul.clone()),
+        concat!(
+            "var _extends=Object.assign||function(target){for(var i=1;i<arguments.length;i++){var source=arguments[i],key;",
+            "for(key in source)if(Object.prototype.hasOwnProperty.call(source,key))target[key]=source[key];}return target;};",
+            "h(\"ul\",_extends({class:\"list\"},props,{key:k}),h(\"li\",null,\"Hello,\u{a0} world\"),items,h(Foo,{bar:true}));",
+        )
+    );
+    assert_eq!(
+        lower(preact, ECMAScriptVersion::ES5, Expression::JSXFragment(Box::new(JSXFragment {
+            loc: Default::default(), span: Default::default(), children: Some(vec![ text("a "), JSXChild::ChildExpression(None) ]),
+        }))),
+        "h(Fragment,null,\"a \");"
+    );
+
+    let automatic = JsxOptions { runtime: JsxRuntime::Automatic, import_source: "preact".to_string(), ..JsxOptions::default() };
+    let li = element("li", vec![ attr("key", Some(JSXNormalAttributeInitializer::Assignment(builder::ident_expr("k")))) ], vec![ text("x") ]);
+    assert_eq!(
+        lower(automatic.clone(), ECMAScriptVersion::ES2018, Expression::JSXElement(Box::new(li))),
+        "var _jsx=require(\"preact/jsx-runtime\").jsx;_jsx(\"li\",{children:\"x\"},k);"
+    );
+
+    let mut body = vec![ builder::expr_stmt(builder::string("use strict")), builder::expr_stmt(Expression::JSXElement(Box::new(element("br", vec![], vec![])))) ];
+    let mut transformer = Transformer::new(ECMAScriptVersion::ES2018);
+    transformer.set_jsx(automatic.clone());
+    transformer.transform_program(&mut body).ok().unwrap();
+    assert_eq!(print_owned(&body), "\"use strict\";var _jsx=require(\"preact/jsx-runtime\").jsx;_jsx(\"br\",{});");
+    assert_eq!(
+        lower(automatic, ECMAScriptVersion::ES2018, ul),
+        concat!(
+            "var _createElement=require(\"preact\").createElement,_jsx=require(\"preact/jsx-runtime\").jsx;",
+            "_createElement(\"ul\",{class:\"list\",...props,key:k},_jsx(\"li\",{children:\"Hello,\u{a0} world\"}),items,_jsx(Foo,{bar:true}));"
+        )
+    );
+}
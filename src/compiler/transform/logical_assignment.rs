@@ -0,0 +1,135 @@
+// Logical assignment → short-circuit evaluation
+//
+//      a ||= b         →   a || (a = b)
+//      a &&= b         →   a && (a = b)
+//      a ??= b         →   a ?? (a = b)
+//      o().p ||= b     →   (_ref = o()).p || (_ref.p = b)
+//
+// NOTE: 与 `a = a || b` 不同，只有在短路失败时才会赋值（不会触发 setter）。
+//       产生的 `??` 在目标版本不支持时接着由 nullish coalescing 降级处理。
+
+use crate::error::Error;
+use crate::lexer::operator::{ InfixOperator, AssignmentOperator, };
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{ NameGenerator, Temporaries, memoize_reference, };
+
+use std::mem;
+
+
+fn logical_operator(operator: AssignmentOperator) -> Option<InfixOperator> {
+    match operator {
+        AssignmentOperator::AndAssign => Some(InfixOperator::And),
+        AssignmentOperator::OrAssign => Some(InfixOperator::Or),
+        AssignmentOperator::NullishAssign => Some(InfixOperator::NullishCoalescing),
+        _ => None,
+    }
+}
+
+
+struct LogicalAssignmentLowering<'a> {
+    names: &'a mut NameGenerator,
+    temps: Temporaries,
+}
+
+impl<'a> LogicalAssignmentLowering<'a> {
+    fn function_body(&mut self, body: &mut Vec<Statement>) {
+        self.temps.enter();
+        self.visit_statements(body);
+        self.temps.leave(body);
+    }
+}
+
+impl<'a> VisitMut for LogicalAssignmentLowering<'a> {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        visit::walk_expression(self, expr);
+
+        let operator = match *expr {
+            Expression::Assignment(ref inner) => match logical_operator(inner.operator) {
+                Some(operator) => operator,
+                None => return,
+            },
+            _ => return,
+        };
+
+        if let Expression::Assignment(inner) = mem::replace(expr, builder::null()) {
+            let AssignmentExpression { left, right, .. } = *inner;
+            let (target, value) = memoize_reference(left, self.names, &mut self.temps);
+            *expr = builder::infix(target, operator, builder::assign(value, right));
+        }
+    }
+
+    fn visit_function(&mut self, func: &mut Function) {
+        for param in func.params.items.iter_mut() {
+            self.visit_expression(param);
+        }
+        self.function_body(&mut func.body);
+    }
+
+    fn visit_arrow_function(&mut self, arrow: &mut ArrowFunctionExpression) {
+        self.visit_expression(&mut arrow.params);
+        self.temps.enter();
+        match arrow.body {
+            ConciseBody::Expr(ref mut body) => self.visit_expression(body),
+            ConciseBody::Stmt(ref mut body) => self.visit_statements(body),
+        }
+        self.temps.leave_arrow(&mut arrow.body);
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        match *method {
+            MethodDefinition::Method(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                for param in inner.params.items.iter_mut() {
+                    self.visit_expression(param);
+                }
+                self.function_body(&mut inner.body);
+            },
+            MethodDefinition::Getter(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                self.function_body(&mut inner.body);
+            },
+            MethodDefinition::Setter(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                for param in inner.params.items.iter_mut() {
+                    self.visit_expression(param);
+                }
+                self.function_body(&mut inner.body);
+            },
+        }
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, names: &mut NameGenerator) -> Result<(), Error> {
+    let mut pass = LogicalAssignmentLowering { names, temps: Temporaries::default() };
+    pass.function_body(body);
+
+    Ok(())
+}
+
+
+#[test]
+fn test_logical_assignment_lowering() {
+    use crate::compiler::transform::{ parse_owned, lower_and_print, assert_reparse, };
+
+    assert_eq!(lower_and_print(parse_owned("a ||= b")), "a||(a=b);");
+    assert_eq!(lower_and_print(parse_owned("o.p.q &&= b")), "var _ref;(_ref=o.p).q&&(_ref.q=b);");
+    assert_eq!(lower_and_print(parse_owned("a ??= b")), "a!==null&&a!==void 0?a:a=b;");
+    assert_eq!(
+        lower_and_print(parse_owned("f(a ||= 1, o.p.q &&= 2, c ??= 3)")),
+        "var _ref;f(a||(a=1),(_ref=o.p).q&&(_ref.q=2),c!==null&&c!==void 0?c:c=3);"
+    );
+
+    // NOTE: 降级的结果可以再次解析
+    for &source in [ "a ||= b", "o.p.q &&= b", "a ??= b", "f(a ||= 1, o.p.q &&= 2, c ??= 3)", "x = [a ??= b, c]" ].iter() {
+        assert_reparse(&lower_and_print(parse_owned(source)));
+    }
+}
@@ -0,0 +1,812 @@
+use crate::toolshed::Arena;
+use crate::version::ECMAScriptVersion;
+use crate::error::{ ErrorKind, Error, };
+use crate::ast::statement::{ Statement, };
+use crate::ast::expression::{ Expression, };
+use crate::ast::owned::{ self, ToOwnedAst, ToArenaAst, };
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::lexer::operator::PrefixOperator;
+use crate::compiler::scope::Goal;
+use crate::compiler::bytecode::CodeObject;
+
+use std::io::{ self, Write, };
+use std::collections::{ HashMap, HashSet, };
+
+mod helpers;
+mod block_scoping;
+mod class;
+mod arrow;
+mod destructuring;
+mod spread;
+mod async_functions;
+mod generator;
+mod template;
+mod exponent;
+mod optional_catch;
+mod optional_chaining;
+mod logical_assignment;
+mod numeric_separator;
+mod jsx;
+
+pub use self::helpers::{ Helper, Helpers, };
+pub use self::jsx::{ JsxOptions, JsxRuntime, };
+
+
+// transpiler
+pub trait Transform<'ast> {
+    type Item;
+
+    fn transform(&self, arena: &'ast Arena, target: ECMAScriptVersion) -> Result<Self::Item, Error>;
+}
+
+impl<'ast> Transform<'ast> for [Statement<'ast>] {
+    type Item = Vec<Statement<'ast>>;
+
+    fn transform(&self, arena: &'ast Arena, target: ECMAScriptVersion) -> Result<Self::Item, Error> {
+        let mut body = self.to_owned_ast();
+        Transformer::new(target).transform_program(&mut body)?;
+
+        Ok(body.iter().map(|stmt| stmt.to_arena_ast(arena)).collect())
+    }
+}
+
+impl<'ast> Transform<'ast> for Statement<'ast> {
+    type Item = Statement<'ast>;
+
+    fn transform(&self, arena: &'ast Arena, target: ECMAScriptVersion) -> Result<Self::Item, Error> {
+        let mut body = vec![ self.to_owned_ast() ];
+        Transformer::new(target).transform_program(&mut body)?;
+
+        // NOTE: 降级可能会产生多条语句（例如 `var _this = this;`）
+        if body.len() == 1 {
+            Ok(body[0].to_arena_ast(arena))
+        } else {
+            Ok(builder::block_stmt(body).to_arena_ast(arena))
+        }
+    }
+}
+
+impl<'ast> Transform<'ast> for Expression<'ast> {
+    type Item = Expression<'ast>;
+
+    fn transform(&self, arena: &'ast Arena, target: ECMAScriptVersion) -> Result<Self::Item, Error> {
+        let mut body = vec![ builder::expr_stmt(self.to_owned_ast()) ];
+        Transformer::new(target).transform_program(&mut body)?;
+
+        let last = match body.pop() {
+            Some(owned::Statement::Expression(expr)) => *expr,
+            _ => return Err(Error::new(ErrorKind::InternalError, "expression lowered into a statement")),
+        };
+
+        if body.is_empty() {
+            return Ok(last.to_arena_ast(arena));
+        }
+
+        // NOTE: 辅助声明无法放在表达式里面，使用 `(function () { ...; return expr; }).call(this)` 包裹。
+        body.push(builder::return_stmt(Some(last)));
+        let func = builder::parenthesized(builder::function_expr(None, vec![], body));
+        let expr = builder::call(builder::member(func, "call"), vec![ builder::this() ]);
+
+        Ok(expr.to_arena_ast(arena))
+    }
+}
+
+
+/// Lowers a program to `target`, running the passes the target lacks support for.
+///
+/// The passes run on the owned AST (`ast::owned`) and share one `NameGenerator`,
+/// so every temporary they introduce is unique across the whole program.
+pub struct Transformer {
+    target: ECMAScriptVersion,
+    names: NameGenerator,
+    helpers: Helpers,
+    jsx: JsxOptions,
+}
+
+impl Transformer {
+    pub fn new(target: ECMAScriptVersion) -> Self {
+        Self { target, names: NameGenerator::new(), helpers: Helpers::new(), jsx: JsxOptions::default() }
+    }
+
+    pub fn target(&self) -> ECMAScriptVersion {
+        self.target
+    }
+
+    pub fn set_jsx(&mut self, options: JsxOptions) {
+        self.jsx = options;
+    }
+
+    pub fn transform_program(&mut self, body: &mut Vec<owned::Statement>) -> Result<(), Error> {
+        self.names.reserve_all(count_names(body).into_iter().map(|(name, _)| name));
+
+        // NOTE: 任何版本都不支持 JSX，它产生的对象展开接着按目标版本降级。
+        jsx::lower(body, &self.jsx, self.target, &mut self.names, &mut self.helpers)?;
+
+        // NOTE: 先降级较新的语法，`&&=`、`||=`、`??=` 产生的 `??` 接着由 nullish coalescing 降级处理。
+        if self.target < ECMAScriptVersion::ES2021 {
+            numeric_separator::lower(body)?;
+            logical_assignment::lower(body, &mut self.names)?;
+        }
+        if self.target < ECMAScriptVersion::ES2020 {
+            optional_chaining::lower(body, &mut self.names)?;
+        }
+        if self.target < ECMAScriptVersion::ES2019 {
+            optional_catch::lower(body, &mut self.names)?;
+        }
+        if self.target < ECMAScriptVersion::ES2016 {
+            exponent::lower(body, &mut self.names)?;
+        }
+
+        if self.target < ECMAScriptVersion::ES2015 {
+            template::lower(body, &mut self.names, &mut self.helpers)?;
+            // NOTE: 顺序很重要：块级作用域产生的 `_loop` 是箭头函数，类降级会改写 `super`，
+            //       两者产生的 `this`/`arguments` 最后统一由箭头函数降级处理。
+            //       解构和展开在箭头函数降级之后进行，此时剩余参数可以直接使用函数自己的 `arguments`。
+            //       async 函数先变成生成器，最后和普通的生成器一起变成状态机。
+            block_scoping::lower(body, &mut self.names)?;
+            class::lower(body, &mut self.names)?;
+            arrow::lower(body, &mut self.names)?;
+            async_functions::lower(body, &mut self.names, &mut self.helpers, true, true)?;
+            destructuring::lower(body, &mut self.names, &mut self.helpers, true)?;
+            spread::lower(body, &mut self.names, &mut self.helpers, true)?;
+            generator::lower(body, &mut self.names, &mut self.helpers)?;
+        } else if self.target < ECMAScriptVersion::ES2018 {
+            let functions = self.target < ECMAScriptVersion::ES2017;
+            async_functions::lower(body, &mut self.names, &mut self.helpers, functions, true)?;
+            // NOTE: 对象的剩余属性和展开属性是 ES2018 的语法
+            destructuring::lower(body, &mut self.names, &mut self.helpers, false)?;
+            spread::lower(body, &mut self.names, &mut self.helpers, false)?;
+        }
+
+        if !self.helpers.is_empty() {
+            // NOTE: 辅助函数放在指令序言之后，`"use strict"` 同样作用于它们。
+            let index = directive_prologue(body);
+            let declarations = self.helpers.declarations();
+            body.splice(index..index, declarations);
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Hands out identifiers that collide neither with the program nor with each other.
+#[derive(Debug, Default)]
+pub struct NameGenerator {
+    used: HashSet<String>,
+}
+
+impl NameGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reserve<S: Into<String>>(&mut self, name: S) {
+        self.used.insert(name.into());
+    }
+
+    pub fn reserve_all<I: IntoIterator<Item=String>>(&mut self, names: I) {
+        self.used.extend(names);
+    }
+
+    pub fn is_used(&self, name: &str) -> bool {
+        self.used.contains(name)
+    }
+
+    /// `_base`, `_base2`, `_base3`, ...
+    pub fn fresh(&mut self, base: &str) -> String {
+        let base = format!("_{}", base.trim_start_matches('_'));
+        let mut name = base.clone();
+        let mut n = 2;
+        while self.used.contains(&name) {
+            name = format!("{}{}", base, n);
+            n += 1;
+        }
+
+        self.used.insert(name.clone());
+        name
+    }
+}
+
+
+/// Temporaries of the enclosing functions, declared by one `var` at the top of each body.
+#[derive(Debug, Default)]
+pub struct Temporaries {
+    scopes: Vec<Vec<String>>,
+}
+
+impl Temporaries {
+    pub fn enter(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    pub fn declare(&mut self, name: String) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(name);
+        }
+    }
+
+    pub fn leave(&mut self, body: &mut Vec<owned::Statement>) {
+        let scope = self.scopes.pop().unwrap_or_default();
+        if scope.is_empty() {
+            return;
+        }
+
+        let mut declarators = scope.iter().map(|name| builder::declarator(builder::ident_expr(name), None)).collect::<Vec<_>>();
+
+        // NOTE: 与前一个 pass 产生的临时变量声明合并：`var _ref2, _ref;`
+        let index = directive_prologue(body);
+        if let Some(owned::Statement::Variable(ref mut inner)) = body.get_mut(index) {
+            let is_temps = inner.kind == crate::ast::statement::LexicalDeclarationKind::Var
+                && inner.declarators.iter().all(|declarator| declarator.initializer.is_none());
+            if is_temps {
+                declarators.extend(inner.declarators.drain(..));
+                inner.declarators = declarators;
+                return;
+            }
+        }
+
+        body.insert(index, builder::variable(crate::ast::statement::LexicalDeclarationKind::Var, declarators));
+    }
+
+    /// `leave` for an arrow function: an expression body becomes `{ var ...; return expr; }`.
+    pub fn leave_arrow(&mut self, body: &mut owned::ConciseBody) {
+        if self.scopes.last().map(|scope| scope.is_empty()).unwrap_or(true) {
+            self.scopes.pop();
+            return;
+        }
+
+        let mut stmts = match ::std::mem::replace(body, owned::ConciseBody::Stmt(Vec::new())) {
+            owned::ConciseBody::Expr(value) => vec![ builder::return_stmt(Some(value)) ],
+            owned::ConciseBody::Stmt(stmts) => stmts,
+        };
+        self.leave(&mut stmts);
+        *body = owned::ConciseBody::Stmt(stmts);
+    }
+}
+
+
+/// `Array.prototype.slice.call(value, start)`: an array copy of an array-like value.
+pub fn array_slice(value: owned::Expression, start: usize) -> owned::Expression {
+    let slice = builder::member(builder::member(builder::ident_expr("Array"), "prototype"), "slice");
+    let mut arguments = vec![ value ];
+    if start > 0 {
+        arguments.push(builder::number(start as i64));
+    }
+
+    builder::call(builder::member(slice, "call"), arguments)
+}
+
+
+/// Evaluates `value` once and refers to it twice: `(first, second)` is `(_ref = value, _ref)`,
+/// or `(value, value)` when reading `value` again has no side effects ( `this`, identifiers, literals ).
+pub fn memoize(value: owned::Expression, names: &mut NameGenerator, temps: &mut Temporaries) -> (owned::Expression, owned::Expression) {
+    match value {
+        owned::Expression::This(_) | owned::Expression::Identifier(_)
+        | owned::Expression::Null(_) | owned::Expression::Boolean(_)
+        | owned::Expression::String(_) | owned::Expression::Numeric(_) => (value.clone(), value),
+        _ => {
+            let name = names.fresh("ref");
+            temps.declare(name.clone());
+            (builder::assign(builder::ident_expr(&name), value), builder::ident_expr(&name))
+        },
+    }
+}
+
+/// Memoizes the object and the computed key of an assignment target, so it can be read and then written:
+/// `o().p` → `((_ref = o()).p, _ref.p)`, `o[k()]` → `(o[_ref = k()], o[_ref])`.
+pub fn memoize_reference(target: owned::Expression, names: &mut NameGenerator, temps: &mut Temporaries) -> (owned::Expression, owned::Expression) {
+    let owned::MemberExpression { loc, span, left, right, computed, optional } = match target {
+        owned::Expression::Member(member) => *member,
+        other => return (other.clone(), other),
+    };
+
+    let (left, left2) = match left {
+        owned::Expression::Super(_) => (left.clone(), left),
+        _ => memoize(left, names, temps),
+    };
+    let (right, right2) = match right {
+        owned::Expression::String(_) | owned::Expression::Numeric(_) => (right.clone(), right),
+        _ if !computed => (right.clone(), right),
+        _ => memoize(right, names, temps),
+    };
+
+    let first = owned::MemberExpression { loc, span, left, right, computed, optional };
+    let second = owned::MemberExpression { loc: Default::default(), span: Default::default(), left: left2, right: right2, computed, optional };
+
+    (owned::Expression::Member(Box::new(first)), owned::Expression::Member(Box::new(second)))
+}
+
+
+/// NOTE: 参数列表被解析为 `ParenthesizedExpression`，其唯一的元素可能是 `CommaExpression`，
+///       在增删参数之前先展开。
+pub fn flatten_arguments(arguments: &mut owned::ParenthesizedExpression) {
+    if arguments.items.len() == 1 {
+        if let owned::Expression::Comma(_) = arguments.items[0] {
+            if let owned::Expression::Comma(comma) = arguments.items.remove(0) {
+                arguments.items = comma.items;
+            }
+        }
+    }
+}
+
+/// Length of the directive prologue ( `"use strict";` ... ) at the start of `body`,
+/// statements inserted at the top of a body go after it.
+pub fn directive_prologue(body: &[owned::Statement]) -> usize {
+    body.iter()
+        .take_while(|stmt| match *stmt {
+            owned::Statement::Expression(ref expr) => match **expr {
+                owned::Expression::String(_) => true,
+                _ => false,
+            },
+            _ => false,
+        })
+        .count()
+}
+
+
+/// Names bound by a declaration target: `a`, `[a, { b: c }]`, ...
+pub fn bound_names(target: &owned::Expression, names: &mut Vec<String>) {
+    use crate::ast::owned::*;
+
+    fn binding_pattern(pattern: &BindingPattern, names: &mut Vec<String>) {
+        match *pattern {
+            BindingPattern::Object(ref inner) => {
+                for prop in inner.properties.iter() {
+                    match *prop {
+                        BindingProperty::SingleNameBinding { ref name, .. } => names.push(name.name().to_string()),
+                        BindingProperty::Property { ref value, .. } => binding_element(value, names),
+                        BindingProperty::Spread { ref name, .. } => names.push(name.name().to_string()),
+                    }
+                }
+            },
+            BindingPattern::Array(ref inner) => {
+                for elem in inner.elems.iter() {
+                    if let Some(ref elem) = *elem {
+                        binding_element(elem, names);
+                    }
+                }
+                match inner.rest_elem.as_ref().map(|rest| &**rest) {
+                    Some(BindingRestElement::Identifier(ref ident)) => names.push(ident.name().to_string()),
+                    Some(BindingRestElement::BindingPattern(ref pattern)) => binding_pattern(pattern, names),
+                    None => { },
+                }
+            },
+        }
+    }
+
+    fn binding_element(elem: &BindingElement, names: &mut Vec<String>) {
+        match *elem {
+            BindingElement::SingleNameBinding { ref name, .. } => names.push(name.name().to_string()),
+            BindingElement::BindingPattern { ref pattern, .. } => binding_pattern(pattern, names),
+        }
+    }
+
+    match *target {
+        Expression::Identifier(ref ident) => names.push(ident.name().to_string()),
+        Expression::BindingPattern(ref pattern) => binding_pattern(pattern, names),
+        Expression::AssignmentPattern(ref pattern) => match **pattern {
+            AssignmentPattern::Object(ref inner) => {
+                for prop in inner.properties.iter() {
+                    match *prop {
+                        AssignmentProperty::Identifier { ref name, .. } => names.push(name.name().to_string()),
+                        AssignmentProperty::Property { ref value, .. } => bound_names(&value.elem, names),
+                        AssignmentProperty::Spread { ref target, .. } => bound_names(target, names),
+                    }
+                }
+            },
+            AssignmentPattern::Array(ref inner) => {
+                for elem in inner.elems.iter() {
+                    if let Some(ref elem) = *elem {
+                        bound_names(&elem.elem, names);
+                    }
+                }
+                if let Some(ref rest) = inner.rest_elem {
+                    bound_names(rest, names);
+                }
+            },
+        },
+        // `a = 1` ( default value ) or `...a` ( rest parameter )
+        Expression::Assignment(ref inner) => bound_names(&inner.left, names),
+        Expression::Spread(ref inner) => bound_names(&inner.item, names),
+        Expression::Comma(ref inner) => {
+            for item in inner.items.iter() {
+                bound_names(item, names);
+            }
+        },
+        _ => { },
+    }
+}
+
+/// Names bound by a parameter list.
+pub fn param_names(params: &[owned::Expression]) -> Vec<String> {
+    let mut names = Vec::new();
+    for param in params.iter() {
+        bound_names(param, &mut names);
+    }
+
+    names
+}
+
+
+// NOTE: 统计变量名（引用以及绑定）出现的次数，属性名不计算在内。
+#[derive(Default)]
+struct NameCounter {
+    counts: HashMap<String, usize>,
+}
+
+impl NameCounter {
+    fn add(&mut self, name: &str) {
+        *self.counts.entry(name.to_string()).or_insert(0) += 1;
+    }
+}
+
+impl VisitMut for NameCounter {
+    fn visit_expression(&mut self, expr: &mut owned::Expression) {
+        if let owned::Expression::Identifier(ref ident) = *expr {
+            self.add(ident.name());
+        }
+        visit::walk_expression(self, expr)
+    }
+
+    fn visit_binding_identifier(&mut self, ident: &mut owned::Identifier) {
+        self.add(ident.name());
+    }
+
+    fn visit_object_property(&mut self, prop: &mut owned::ObjectProperty) {
+        if let owned::ObjectProperty::Identifier(ref ident) = *prop {
+            self.add(ident.name());
+        }
+        visit::walk_object_property(self, prop)
+    }
+
+    fn visit_binding_property(&mut self, prop: &mut owned::BindingProperty) {
+        if let owned::BindingProperty::SingleNameBinding { ref name, .. } = *prop {
+            self.add(name.name());
+        }
+        visit::walk_binding_property(self, prop)
+    }
+
+    fn visit_assignment_property(&mut self, prop: &mut owned::AssignmentProperty) {
+        if let owned::AssignmentProperty::Identifier { ref name, .. } = *prop {
+            self.add(name.name());
+        }
+        visit::walk_assignment_property(self, prop)
+    }
+}
+
+/// How many times each variable name occurs ( as a reference or a binding ) in `body`.
+pub fn count_names(body: &mut Vec<owned::Statement>) -> HashMap<String, usize> {
+    let mut counter = NameCounter::default();
+    counter.visit_statements(body);
+    counter.counts
+}
+
+pub fn count_names_in_expression(expr: &mut owned::Expression) -> HashMap<String, usize> {
+    let mut counter = NameCounter::default();
+    counter.visit_expression(expr);
+    counter.counts
+}
+
+
+// NOTE: 收集函数作用域内声明的名字（不进入嵌套函数）
+#[derive(Default)]
+struct VarCollector {
+    names: Vec<String>,
+}
+
+impl VisitMut for VarCollector {
+    fn visit_statement(&mut self, stmt: &mut owned::Statement) {
+        match *stmt {
+            owned::Statement::Variable(ref inner) => {
+                for declarator in inner.declarators.iter() {
+                    bound_names(&declarator.name, &mut self.names);
+                }
+            },
+            owned::Statement::Function(ref inner) => {
+                self.names.push(inner.name.name().to_string());
+                return;
+            },
+            owned::Statement::Class(ref inner) => {
+                self.names.push(inner.name.name().to_string());
+                return;
+            },
+            _ => { },
+        }
+        visit::walk_statement(self, stmt)
+    }
+
+    fn visit_expression(&mut self, _expr: &mut owned::Expression) {
+
+    }
+}
+
+/// Names declared in a function body ( `var`, `let`, `const`, functions and classes ),
+/// without looking into nested functions.
+pub fn declared_names(body: &mut Vec<owned::Statement>) -> Vec<String> {
+    let mut collector = VarCollector::default();
+    collector.visit_statements(body);
+    collector.names
+}
+
+
+// NOTE: 查找 `yield`、`await` 和 `for await`（不进入嵌套函数）
+#[derive(Default)]
+struct SuspensionFinder {
+    found: bool,
+}
+
+impl VisitMut for SuspensionFinder {
+    fn visit_statement(&mut self, stmt: &mut owned::Statement) {
+        if let owned::Statement::ForAwaitOf(_) = *stmt {
+            self.found = true;
+        }
+        if !self.found {
+            visit::walk_statement(self, stmt)
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &mut owned::Expression) {
+        match *expr {
+            owned::Expression::Yield(_) => self.found = true,
+            owned::Expression::Prefix(ref inner) if inner.operator == PrefixOperator::Await => self.found = true,
+            _ => { },
+        }
+        if !self.found {
+            visit::walk_expression(self, expr)
+        }
+    }
+
+    fn visit_function(&mut self, _func: &mut owned::Function) {
+
+    }
+
+    fn visit_arrow_function(&mut self, _arrow: &mut owned::ArrowFunctionExpression) {
+
+    }
+
+    fn visit_class(&mut self, _class: &mut owned::Class) {
+
+    }
+}
+
+/// Whether `stmt` can suspend the function it is in: a `yield`, an `await` or a `for await`.
+pub fn has_suspension(stmt: &mut owned::Statement) -> bool {
+    let mut finder = SuspensionFinder::default();
+    finder.visit_statement(stmt);
+    finder.found
+}
+
+pub fn has_suspension_in_expression(expr: &mut owned::Expression) -> bool {
+    let mut finder = SuspensionFinder::default();
+    finder.visit_expression(expr);
+    finder.found
+}
+
+
+/// Renames every variable `from` to `to`, leaving alone functions and catch clauses
+/// that declare their own `from`. Shorthand properties are expanded: `{ x }` → `{ x: _x }`.
+pub struct Renamer<'a> {
+    pub from: &'a str,
+    pub to: &'a str,
+}
+
+impl<'a> Renamer<'a> {
+    fn rename(&self, ident: &mut owned::Identifier) {
+        if ident.name() == self.from {
+            ident.raw = self.to.to_string();
+            ident.cooked = None;
+        }
+    }
+
+    fn shadows(&self, params: &[owned::Expression], body: &mut Vec<owned::Statement>) -> bool {
+        param_names(params).iter().any(|name| name == self.from)
+            || declared_names(body).iter().any(|name| name == self.from)
+            || self.from == "arguments"
+    }
+}
+
+impl<'a> VisitMut for Renamer<'a> {
+    fn visit_expression(&mut self, expr: &mut owned::Expression) {
+        match *expr {
+            owned::Expression::Identifier(ref mut ident) => self.rename(ident),
+            owned::Expression::Function(ref mut inner) => {
+                let own_name = inner.name.as_ref().map(|name| name.name() == self.from).unwrap_or(false);
+                if !own_name && !self.shadows(&inner.func.params.items, &mut inner.func.body) {
+                    self.visit_function(&mut inner.func);
+                }
+            },
+            owned::Expression::Class(ref mut inner) => {
+                let own_name = inner.name.as_ref().map(|name| name.name() == self.from).unwrap_or(false);
+                if !own_name {
+                    self.visit_class(&mut inner.class);
+                }
+            },
+            _ => visit::walk_expression(self, expr),
+        }
+    }
+
+    fn visit_statement(&mut self, stmt: &mut owned::Statement) {
+        match *stmt {
+            owned::Statement::Function(ref mut inner) => {
+                self.rename(&mut inner.name);
+                if !self.shadows(&inner.func.params.items, &mut inner.func.body) {
+                    self.visit_function(&mut inner.func);
+                }
+            },
+            owned::Statement::Try(ref mut inner) => {
+                self.visit_statements(&mut inner.body.body);
+                let mut shadowed = false;
+                if let Some(ref mut param) = inner.catch_parameter {
+                    let mut names = Vec::new();
+                    bound_names(param, &mut names);
+                    shadowed = names.iter().any(|name| name == self.from);
+                    if !shadowed {
+                        self.visit_expression(param);
+                    }
+                }
+                if let Some(ref mut catch_body) = inner.catch_body {
+                    if !shadowed {
+                        self.visit_statements(&mut catch_body.body);
+                    }
+                }
+                if let Some(ref mut finally) = inner.finally {
+                    self.visit_statements(&mut finally.body);
+                }
+            },
+            _ => visit::walk_statement(self, stmt),
+        }
+    }
+
+    fn visit_arrow_function(&mut self, arrow: &mut owned::ArrowFunctionExpression) {
+        let params = match arrow.params {
+            owned::Expression::Parenthesized(ref inner) => param_names(&inner.items),
+            ref other => param_names(::std::slice::from_ref(other)),
+        };
+        let declared = match arrow.body {
+            owned::ConciseBody::Stmt(ref mut body) => declared_names(body),
+            owned::ConciseBody::Expr(_) => vec![],
+        };
+        if params.iter().chain(declared.iter()).all(|name| name != self.from) {
+            visit::walk_arrow_function(self, arrow)
+        }
+    }
+
+    fn visit_method_definition(&mut self, method: &mut owned::MethodDefinition) {
+        let shadowed = match *method {
+            owned::MethodDefinition::Method(ref mut inner) => self.shadows(&inner.params.items, &mut inner.body),
+            owned::MethodDefinition::Getter(ref mut inner) => self.shadows(&[], &mut inner.body),
+            owned::MethodDefinition::Setter(ref mut inner) => self.shadows(&inner.params.items, &mut inner.body),
+        };
+        if !shadowed {
+            visit::walk_method_definition(self, method)
+        }
+    }
+
+    fn visit_binding_identifier(&mut self, ident: &mut owned::Identifier) {
+        self.rename(ident)
+    }
+
+    fn visit_object_property(&mut self, prop: &mut owned::ObjectProperty) {
+        let expand = match *prop {
+            owned::ObjectProperty::Identifier(ref ident) => ident.name() == self.from,
+            _ => false,
+        };
+        if expand {
+            let key = owned::PropertyName::Identifier(builder::ident(self.from));
+            *prop = builder::property(key, builder::ident_expr(self.to));
+            return;
+        }
+        visit::walk_object_property(self, prop)
+    }
+
+    fn visit_binding_property(&mut self, prop: &mut owned::BindingProperty) {
+        let replacement = match *prop {
+            owned::BindingProperty::SingleNameBinding { loc, span, ref name, ref init } if name.name() == self.from => {
+                Some(owned::BindingProperty::Property {
+                    loc,
+                    span,
+                    name: owned::PropertyName::Identifier(name.clone()),
+                    puct: builder::colon(),
+                    value: owned::BindingElement::SingleNameBinding { loc, span, name: builder::ident(self.to), init: init.clone() },
+                })
+            },
+            _ => None,
+        };
+        if let Some(replacement) = replacement {
+            *prop = replacement;
+        }
+        visit::walk_binding_property(self, prop)
+    }
+
+    fn visit_assignment_property(&mut self, prop: &mut owned::AssignmentProperty) {
+        let replacement = match *prop {
+            owned::AssignmentProperty::Identifier { loc, span, ref name, ref init } if name.name() == self.from => {
+                Some(owned::AssignmentProperty::Property {
+                    loc,
+                    span,
+                    name: owned::PropertyName::Identifier(name.clone()),
+                    puct: builder::colon(),
+                    value: owned::AssignmentElement { loc, span, elem: builder::ident_expr(self.to), init: init.clone() },
+                })
+            },
+            _ => None,
+        };
+        if let Some(replacement) = replacement {
+            *prop = replacement;
+        }
+        visit::walk_assignment_property(self, prop)
+    }
+}
+
+pub trait ByteCodeGen {
+    fn byte_code_gen(&self, goal: Goal) -> Result<CodeObject, Error>;
+}
+
+pub trait ToSourceCode {
+    fn source_code_gen<W: Write>(&self, output: &mut W);
+}
+
+pub trait DebugSourceCodeGen {
+    fn debug_source_code_gen<W: Write>(&self, output: &mut W);
+}
+
+
+/// Lowers `body` to ES5 and prints it, for the pass tests.
+#[cfg(test)]
+fn lower_and_print(mut body: Vec<owned::Statement>) -> String {
+    Transformer::new(ECMAScriptVersion::ES5).transform_program(&mut body).ok().unwrap();
+    print_owned(&body)
+}
+
+/// Parses the printed output of a pass again, the output must print the same.
+#[cfg(test)]
+fn assert_reparse(output: &str) {
+    assert_eq!(print_owned(&parse_owned(output)), output);
+}
+
+#[cfg(test)]
+pub(crate) fn print_owned(body: &[owned::Statement]) -> String {
+    use crate::compiler::codegen::CodeGen;
+
+    let arena = Arena::new();
+    let body = body.iter().map(|stmt| stmt.to_arena_ast(&arena)).collect::<Vec<Statement>>();
+    let mut codegen = CodeGen::new(Vec::new());
+    codegen.set_minify(true);
+    codegen.gen_program(&body).unwrap();
+
+    String::from_utf8(codegen.into_inner()).unwrap()
+}
+
+#[cfg(test)]
+pub(crate) fn parse_owned(source: &str) -> Vec<owned::Statement> {
+    use crate::parser::Parser;
+
+    let arena = Arena::new();
+    let code = arena.alloc_vec(source.chars().collect::<Vec<char>>());
+    let mut parser = Parser::new(&arena, &code, "main.js");
+    parser.parse().ok().unwrap();
+
+    parser.body.as_slice().to_owned_ast()
+}
+
+#[test]
+fn test_directive_prologue() {
+    let helpers = concat!(
+        "function _values(o){var method=typeof Symbol===\"function\"&&Symbol.iterator&&o[Symbol.iterator],i=0;",
+        "if(method)return method.call(o);return{next:function(){return{done:i>=o.length,value:o[i++]};}};}",
+        "function _toConsumableArray(o){var iterator=_values(o),result=[],step;",
+        "while(!(step=iterator.next()).done)result.push(step.value);return result;}",
+    );
+
+    // NOTE: 辅助函数和临时变量都放在 `"use strict"` 之后
+    assert_eq!(
+        lower_and_print(parse_owned("\"use strict\"; a.b.m(...xs)")),
+        format!("\"use strict\";{}var _ref;(_ref=a.b).m.apply(_ref,_toConsumableArray(xs));", helpers)
+    );
+    assert_eq!(
+        lower_and_print(parse_owned("function f() { \"use strict\"; a.b.m(...xs) }")),
+        format!("{}function f(){{\"use strict\";var _ref;(_ref=a.b).m.apply(_ref,_toConsumableArray(xs));}}", helpers)
+    );
+}
@@ -0,0 +1,38 @@
+// Numeric separators
+//
+//      1_000_000       →   1000000
+//      0xFF_FF         →   0xFFFF
+
+use crate::error::Error;
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+
+
+struct NumericSeparatorLowering;
+
+impl VisitMut for NumericSeparatorLowering {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        if let Expression::Numeric(ref mut inner) = *expr {
+            inner.raw.retain(|c| c != '_');
+        }
+        visit::walk_expression(self, expr)
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>) -> Result<(), Error> {
+    NumericSeparatorLowering.visit_statements(body);
+
+    Ok(())
+}
+
+
+#[test]
+fn test_numeric_separator_lowering() {
+    use crate::compiler::transform::{ parse_owned, lower_and_print, assert_reparse, };
+
+    let output = lower_and_print(parse_owned("f(1_000, 0xff_ff, 1_000.5, [1e1_0, 0b1010_1010])"));
+    assert_eq!(output, "f(1000,0xffff,1000.5,[1e10,0b10101010]);");
+    // NOTE: 降级的结果可以再次解析
+    assert_reparse(&output);
+}
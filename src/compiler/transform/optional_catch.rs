@@ -0,0 +1,45 @@
+// Optional catch binding → an unused parameter
+//
+//      try { f() } catch { g() }     →   try { f() } catch (_unused) { g() }
+
+use crate::error::Error;
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::NameGenerator;
+
+
+struct OptionalCatchLowering<'a> {
+    names: &'a mut NameGenerator,
+}
+
+impl<'a> VisitMut for OptionalCatchLowering<'a> {
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        visit::walk_statement(self, stmt);
+
+        if let Statement::Try(ref mut inner) = *stmt {
+            if inner.catch_body.is_some() && inner.catch_parameter.is_none() {
+                inner.catch_parameter = Some(builder::ident_expr(&self.names.fresh("unused")));
+            }
+        }
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, names: &mut NameGenerator) -> Result<(), Error> {
+    let mut pass = OptionalCatchLowering { names };
+    pass.visit_statements(body);
+
+    Ok(())
+}
+
+
+#[test]
+fn test_optional_catch_lowering() {
+    use crate::compiler::transform::{ parse_owned, lower_and_print, assert_reparse, };
+
+    let output = lower_and_print(parse_owned("try { f(a, b) } catch { g() } try { f() } catch (e) { g(e) } finally { h() }"));
+    assert_eq!(output, "try{f(a,b);}catch(_unused){g();}try{f();}catch(e){g(e);}finally{h();}");
+    // NOTE: 降级的结果可以再次解析
+    assert_reparse(&output);
+}
@@ -0,0 +1,282 @@
+// Optional chaining and nullish coalescing → conditional expressions
+//
+//      a?.b.c              →   a === null || a === void 0 ? void 0 : a.b.c
+//      f()?.[k]            →   (_ref = f()) === null || _ref === void 0 ? void 0 : _ref[k]
+//      o.m?.(x)            →   (_ref = o.m) === null || _ref === void 0 ? void 0 : _ref.call(o, x)
+//      delete a?.b         →   a === null || a === void 0 ? true : delete a.b
+//      a ?? b              →   a !== null && a !== void 0 ? a : b
+//
+// NOTE: 一条链从最外层（最靠近顶端）的 `?.` 处断开，左边的对象单独降级（它可能还包含 `?.`），
+//       左边短路得到 `undefined` 时外层的检查同样会短路，因此整条链一起短路。
+//       括号会结束一条链：`(a?.b).c` 中的 `.c` 不会被短路。
+
+use crate::error::Error;
+use crate::lexer::operator::{ PrefixOperator, InfixOperator, };
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{ NameGenerator, Temporaries, memoize, flatten_arguments, };
+
+use std::mem;
+
+
+/// Whether the member/call chain `expr` contains a `?.`.
+fn is_optional_chain(expr: &Expression) -> bool {
+    match *expr {
+        Expression::Member(ref inner) => inner.optional || is_optional_chain(&inner.left),
+        Expression::Call(ref inner) => inner.optional || is_optional_chain(&inner.callee),
+        _ => false,
+    }
+}
+
+// first === null || second === void 0
+fn is_nullish(first: Expression, second: Expression) -> Expression {
+    builder::infix(
+        builder::infix(first, InfixOperator::StrictEq, builder::null()),
+        InfixOperator::Or,
+        builder::infix(second, InfixOperator::StrictEq, builder::undefined()),
+    )
+}
+
+// first !== null && second !== void 0
+fn is_not_nullish(first: Expression, second: Expression) -> Expression {
+    builder::infix(
+        builder::infix(first, InfixOperator::StrictNeq, builder::null()),
+        InfixOperator::And,
+        builder::infix(second, InfixOperator::StrictNeq, builder::undefined()),
+    )
+}
+
+
+struct OptionalChainingLowering<'a> {
+    names: &'a mut NameGenerator,
+    temps: Temporaries,
+}
+
+impl<'a> OptionalChainingLowering<'a> {
+    fn function_body(&mut self, body: &mut Vec<Statement>) {
+        self.temps.enter();
+        self.visit_statements(body);
+        self.temps.leave(body);
+    }
+
+    fn operand(&mut self, mut expr: Expression) -> Expression {
+        if is_optional_chain(&expr) {
+            self.lower_chain(expr, false)
+        } else {
+            self.visit_expression(&mut expr);
+            expr
+        }
+    }
+
+    /// `test ? void 0 : chain`, with the outermost `?.` of the chain turned into a plain access.
+    fn lower_chain(&mut self, mut chain: Expression, is_delete: bool) -> Expression {
+        let test = self.split(&mut chain).expect("optional chain");
+
+        if is_delete {
+            builder::conditional(test, builder::boolean(true), builder::prefix(PrefixOperator::Delete, chain))
+        } else {
+            builder::conditional(test, builder::undefined(), chain)
+        }
+    }
+
+    // NOTE: 从链的顶端向下查找最外层的 `?.`，把它左边的对象换成可以重复读取的引用，返回短路的检查；
+    //       途中的计算属性名和参数按普通的表达式处理。
+    fn split(&mut self, expr: &mut Expression) -> Option<Expression> {
+        match *expr {
+            Expression::Member(ref mut inner) => {
+                if inner.computed {
+                    self.visit_expression(&mut inner.right);
+                }
+                if !inner.optional {
+                    return self.split(&mut inner.left);
+                }
+
+                inner.optional = false;
+                let object = mem::replace(&mut inner.left, builder::null());
+                let object = self.operand(object);
+                let (first, second) = memoize(object, self.names, &mut self.temps);
+                inner.left = second.clone();
+
+                Some(is_nullish(first, second))
+            },
+            Expression::Call(ref mut inner) => {
+                for item in inner.arguments.items.iter_mut() {
+                    self.visit_expression(item);
+                }
+                if !inner.optional {
+                    return self.split(&mut inner.callee);
+                }
+
+                inner.optional = false;
+                let (callee, this) = match mem::replace(&mut inner.callee, builder::null()) {
+                    // NOTE: `o.m?.()` 需要保留 `this`：`(_ref = o.m) ... _ref.call(o)`
+                    Expression::Member(member) => {
+                        let (callee, this) = self.method(*member);
+                        (callee, Some(this))
+                    },
+                    other => (self.operand(other), None),
+                };
+
+                let (first, second) = memoize(callee, self.names, &mut self.temps);
+                match this {
+                    Some(this) => {
+                        inner.callee = builder::member(second.clone(), "call");
+                        flatten_arguments(&mut inner.arguments);
+                        inner.arguments.items.insert(0, this);
+                    },
+                    None => inner.callee = second.clone(),
+                }
+
+                Some(is_nullish(first, second))
+            },
+            _ => None,
+        }
+    }
+
+    /// `(method, this)` of an optional call `o.m?.()` whose callee is a member expression.
+    fn method(&mut self, mut member: MemberExpression) -> (Expression, Expression) {
+        if member.computed {
+            self.visit_expression(&mut member.right);
+        }
+
+        let object = mem::replace(&mut member.left, builder::null());
+        let (first, this) = match object {
+            Expression::Super(_) => (object, builder::this()),
+            object => {
+                let object = self.operand(object);
+                memoize(object, self.names, &mut self.temps)
+            },
+        };
+
+        // o?.m?.()
+        if member.optional {
+            member.optional = false;
+            member.left = this.clone();
+            let test = is_nullish(first, this.clone());
+            return (builder::conditional(test, builder::undefined(), Expression::Member(Box::new(member))), this);
+        }
+
+        member.left = first;
+        (Expression::Member(Box::new(member)), this)
+    }
+
+    fn lower_nullish(&mut self, left: Expression, right: Expression) -> Expression {
+        let (first, second) = memoize(left, self.names, &mut self.temps);
+        builder::conditional(is_not_nullish(first, second.clone()), second, right)
+    }
+}
+
+impl<'a> VisitMut for OptionalChainingLowering<'a> {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        match *expr {
+            Expression::Member(_) | Expression::Call(_) if is_optional_chain(expr) => {
+                let chain = mem::replace(expr, builder::null());
+                *expr = self.lower_chain(chain, false);
+                return;
+            },
+            Expression::Prefix(ref inner) if inner.operator == PrefixOperator::Delete && is_optional_chain(&inner.operand) => {
+                if let Expression::Prefix(inner) = mem::replace(expr, builder::null()) {
+                    *expr = self.lower_chain(inner.operand, true);
+                }
+                return;
+            },
+            _ => { },
+        }
+
+        visit::walk_expression(self, expr);
+
+        match *expr {
+            Expression::Infix(ref inner) if inner.operator == InfixOperator::NullishCoalescing => { },
+            _ => return,
+        }
+        if let Expression::Infix(inner) = mem::replace(expr, builder::null()) {
+            let InfixExpression { left, right, .. } = *inner;
+            *expr = self.lower_nullish(left, right);
+        }
+    }
+
+    fn visit_function(&mut self, func: &mut Function) {
+        for param in func.params.items.iter_mut() {
+            self.visit_expression(param);
+        }
+        self.function_body(&mut func.body);
+    }
+
+    fn visit_arrow_function(&mut self, arrow: &mut ArrowFunctionExpression) {
+        self.visit_expression(&mut arrow.params);
+        self.temps.enter();
+        match arrow.body {
+            ConciseBody::Expr(ref mut body) => self.visit_expression(body),
+            ConciseBody::Stmt(ref mut body) => self.visit_statements(body),
+        }
+        self.temps.leave_arrow(&mut arrow.body);
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        match *method {
+            MethodDefinition::Method(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                for param in inner.params.items.iter_mut() {
+                    self.visit_expression(param);
+                }
+                self.function_body(&mut inner.body);
+            },
+            MethodDefinition::Getter(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                self.function_body(&mut inner.body);
+            },
+            MethodDefinition::Setter(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                for param in inner.params.items.iter_mut() {
+                    self.visit_expression(param);
+                }
+                self.function_body(&mut inner.body);
+            },
+        }
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, names: &mut NameGenerator) -> Result<(), Error> {
+    let mut pass = OptionalChainingLowering { names, temps: Temporaries::default() };
+    pass.function_body(body);
+
+    Ok(())
+}
+
+
+#[test]
+fn test_optional_chaining_lowering() {
+    use crate::compiler::transform::{ parse_owned, lower_and_print, assert_reparse, };
+
+    assert_eq!(lower_and_print(parse_owned("a?.b.c")), "a===null||a===void 0?void 0:a.b.c;");
+    assert_eq!(
+        lower_and_print(parse_owned("a.b?.c?.[d]")),
+        "var _ref,_ref2;(_ref2=(_ref=a.b)===null||_ref===void 0?void 0:_ref.c)===null||_ref2===void 0?void 0:_ref2[d];"
+    );
+    assert_eq!(
+        lower_and_print(parse_owned("a.b.m?.(x)")),
+        "var _ref,_ref2;(_ref2=(_ref=a.b).m)===null||_ref2===void 0?void 0:_ref2.call(_ref,x);"
+    );
+    assert_eq!(lower_and_print(parse_owned("f?.()")), "f===null||f===void 0?void 0:f();");
+    assert_eq!(lower_and_print(parse_owned("a.b ?? c")), "var _ref;(_ref=a.b)!==null&&_ref!==void 0?_ref:c;");
+    assert_eq!(
+        lower_and_print(parse_owned("f(a?.b, o.m?.(x, y), [c ?? 1, d ?? 2])")),
+        "var _ref;f(a===null||a===void 0?void 0:a.b,(_ref=o.m)===null||_ref===void 0?void 0:_ref.call(o,x,y),[c!==null&&c!==void 0?c:1,d!==null&&d!==void 0?d:2]);"
+    );
+
+    // NOTE: 降级的结果可以再次解析
+    for &source in [
+        "a?.b.c", "a.b?.c?.[d]", "a.b.m?.(x)", "f?.()", "a.b ?? c",
+        "f(a?.b, o.m?.(x, y), [c ?? 1, d ?? 2])",
+    ].iter() {
+        assert_reparse(&lower_and_print(parse_owned(source)));
+    }
+}
@@ -0,0 +1,367 @@
+// Spread → `concat` / `apply` / `_extends`
+//
+//      [a, ...b]           →   [a].concat(_toConsumableArray(b))
+//      f(a, ...b)          →   f.apply(void 0, [a].concat(_toConsumableArray(b)))
+//      o.m(...b)           →   o.m.apply(o, _toConsumableArray(b))
+//      new F(...b)         →   new (Function.prototype.bind.apply(F, [null].concat(_toConsumableArray(b))))()
+//      { a: 1, ...b }      →   _extends({ a: 1 }, b)
+//
+// NOTE: 展开的值通过迭代器读取（`Set`、`Map`、生成器 ...），没有 `Symbol.iterator` 时按类数组处理。
+//       `x.call(a, ...b)` 同样使用 `apply`，`call` 不一定是 `Function.prototype.call`。
+//       目标版本支持展开语法（ES2015 - ES2017）时只降级对象展开。
+
+use crate::error::Error;
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{ NameGenerator, Helper, Helpers, Temporaries, flatten_arguments, };
+
+use std::mem;
+
+
+fn is_spread(expr: &Expression) -> bool {
+    match *expr {
+        Expression::Spread(_) => true,
+        _ => false,
+    }
+}
+
+fn has_spread(items: &[Expression]) -> bool {
+    items.iter().any(is_spread)
+}
+
+/// `[a, b, ...c, d]` → `[a, b].concat(_toConsumableArray(c), [d])`, `to_array` is the name of `_toConsumableArray`.
+fn concat(items: Vec<Option<Expression>>, to_array: &str) -> Expression {
+    let mut segments = Vec::new();
+    let mut elems = Vec::new();
+    for item in items.into_iter() {
+        match item {
+            Some(Expression::Spread(spread)) => {
+                if !elems.is_empty() {
+                    segments.push(array_literal(mem::replace(&mut elems, Vec::new())));
+                }
+                segments.push(builder::call(builder::ident_expr(to_array), vec![ spread.item ]));
+            },
+            other => elems.push(other),
+        }
+    }
+    if !elems.is_empty() {
+        segments.push(array_literal(elems));
+    }
+
+    if segments.is_empty() {
+        return array_literal(Vec::new());
+    }
+
+    let first = segments.remove(0);
+    if segments.is_empty() {
+        return first;
+    }
+
+    let first = match first {
+        Expression::ArrayLiteral(_) => first,
+        // NOTE: 第一个元素是展开时，从空数组开始，`concat` 的接收者必须是数组
+        _ => {
+            segments.insert(0, first);
+            array_literal(Vec::new())
+        },
+    };
+
+    builder::call(builder::member(first, "concat"), segments)
+}
+
+fn has_object_spread(object: &ObjectLiteral) -> bool {
+    object.properties.iter().any(|prop| match *prop {
+        ObjectProperty::Spread { .. } => true,
+        _ => false,
+    })
+}
+
+fn array_literal(elems: Vec<Option<Expression>>) -> Expression {
+    Expression::ArrayLiteral(Box::new(ArrayLiteral { loc: Default::default(), span: Default::default(), elems }))
+}
+
+fn is_reusable(expr: &Expression) -> bool {
+    match *expr {
+        Expression::This(_) | Expression::Identifier(_) => true,
+        _ => false,
+    }
+}
+
+
+struct SpreadLowering<'a> {
+    names: &'a mut NameGenerator,
+    helpers: &'a mut Helpers,
+    temps: Temporaries,
+    iterables: bool,
+}
+
+impl<'a> SpreadLowering<'a> {
+    fn function_body(&mut self, body: &mut Vec<Statement>) {
+        self.temps.enter();
+        self.visit_statements(body);
+        self.temps.leave(body);
+    }
+
+    fn to_array(&mut self) -> String {
+        self.helpers.get(self.names, Helper::ToConsumableArray)
+    }
+
+    fn lower_call(&mut self, call: &mut CallExpression) {
+        flatten_arguments(&mut call.arguments);
+        if !has_spread(&call.arguments.items) {
+            return;
+        }
+
+        let items = mem::replace(&mut call.arguments.items, Vec::new());
+        let callee = mem::replace(&mut call.callee, builder::null());
+
+        let (callee, this) = match callee {
+            // o.m(...args) → o.m.apply(o, args)
+            Expression::Member(mut member) => {
+                let this = if is_reusable(&member.left) {
+                    member.left.clone()
+                } else {
+                    // (_ref = o()).m.apply(_ref, args)
+                    let name = self.names.fresh("ref");
+                    self.temps.declare(name.clone());
+                    let object = mem::replace(&mut member.left, builder::null());
+                    member.left = builder::parenthesized(builder::assign(builder::ident_expr(&name), object));
+                    builder::ident_expr(&name)
+                };
+                (Expression::Member(member), this)
+            },
+            other => (other, builder::undefined()),
+        };
+
+        let to_array = self.to_array();
+        let arguments = concat(items.into_iter().map(Some).collect(), &to_array);
+        call.callee = builder::member(callee, "apply");
+        call.arguments.items = vec![ this, arguments ];
+    }
+
+    // new F(...args) → new (Function.prototype.bind.apply(F, [null].concat(args)))()
+    fn lower_new(&mut self, expr: &mut NewExpression) {
+        let mut arguments = match expr.arguments.take() {
+            Some(arguments) => arguments,
+            None => return,
+        };
+        flatten_arguments(&mut arguments);
+        if !has_spread(&arguments.items) {
+            expr.arguments = Some(arguments);
+            return;
+        }
+
+        let mut items = vec![ Some(builder::null()) ];
+        items.extend(arguments.items.into_iter().map(Some));
+
+        let bind = builder::member(builder::member(builder::member(builder::ident_expr("Function"), "prototype"), "bind"), "apply");
+        let callee = mem::replace(&mut expr.callee, builder::null());
+        let to_array = self.to_array();
+        expr.callee = builder::parenthesized(builder::call(bind, vec![ callee, concat(items, &to_array) ]));
+        arguments.items = Vec::new();
+        expr.arguments = Some(arguments);
+    }
+
+    // { a, ...b, c } → _extends({ a }, b, { c })
+    fn lower_object(&mut self, properties: Vec<ObjectProperty>) -> Expression {
+        let mut arguments = Vec::new();
+        let mut current = Vec::new();
+        for prop in properties.into_iter() {
+            match prop {
+                ObjectProperty::Spread { target, .. } => {
+                    if !current.is_empty() || arguments.is_empty() {
+                        arguments.push(builder::object(mem::replace(&mut current, Vec::new())));
+                    }
+                    arguments.push(target);
+                },
+                other => current.push(other),
+            }
+        }
+        if !current.is_empty() {
+            arguments.push(builder::object(current));
+        }
+
+        let helper = self.helpers.get(self.names, Helper::Extends);
+        builder::call(builder::ident_expr(&helper), arguments)
+    }
+}
+
+/// The parser reads `new F(...args)` as `new` applied to the call `F(...args)`,
+/// it is rewritten to `new F` with the arguments before the call is lowered as an ordinary call.
+fn normalize_new(expr: &mut NewExpression) {
+    if expr.arguments.is_some() {
+        return;
+    }
+    let spread = match expr.callee {
+        Expression::Call(ref mut call) if !call.optional => {
+            flatten_arguments(&mut call.arguments);
+            has_spread(&call.arguments.items)
+        },
+        _ => false,
+    };
+    if !spread {
+        return;
+    }
+
+    if let Expression::Call(call) = mem::replace(&mut expr.callee, builder::null()) {
+        let call = *call;
+        expr.callee = call.callee;
+        expr.arguments = Some(call.arguments);
+    }
+}
+
+impl<'a> VisitMut for SpreadLowering<'a> {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        if !self.iterables {
+            visit::walk_expression(self, expr);
+            if let Expression::ObjectLiteral(ref mut inner) = *expr {
+                if has_object_spread(inner) {
+                    let properties = mem::replace(&mut inner.properties, Vec::new());
+                    *expr = self.lower_object(properties);
+                }
+            }
+            return;
+        }
+
+        if let Expression::New(ref mut inner) = *expr {
+            normalize_new(inner);
+        }
+        visit::walk_expression(self, expr);
+
+        let object = match *expr {
+            Expression::ArrayLiteral(ref mut inner) => {
+                let has_spread = inner.elems.iter().any(|elem| elem.as_ref().map(is_spread).unwrap_or(false));
+                if has_spread {
+                    let elems = mem::replace(&mut inner.elems, Vec::new());
+                    let to_array = self.to_array();
+                    *expr = concat(elems, &to_array);
+                }
+                return;
+            },
+            Expression::Call(ref mut inner) => return self.lower_call(inner),
+            Expression::New(ref mut inner) => return self.lower_new(inner),
+            Expression::ObjectLiteral(ref mut inner) if has_object_spread(inner) => {
+                mem::replace(&mut inner.properties, Vec::new())
+            },
+            _ => return,
+        };
+
+        *expr = self.lower_object(object);
+    }
+
+    fn visit_function(&mut self, func: &mut Function) {
+        for param in func.params.items.iter_mut() {
+            self.visit_expression(param);
+        }
+        self.function_body(&mut func.body);
+    }
+
+    fn visit_method_definition(&mut self, method: &mut MethodDefinition) {
+        match *method {
+            MethodDefinition::Method(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                self.function_body(&mut inner.body);
+            },
+            MethodDefinition::Getter(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                self.function_body(&mut inner.body);
+            },
+            MethodDefinition::Setter(ref mut inner) => {
+                if visit::is_computed_method_name(&inner.name) {
+                    self.visit_expression(&mut inner.name);
+                }
+                self.function_body(&mut inner.body);
+            },
+        }
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, names: &mut NameGenerator, helpers: &mut Helpers, iterables: bool) -> Result<(), Error> {
+    let mut pass = SpreadLowering { names, helpers, temps: Temporaries::default(), iterables };
+    pass.function_body(body);
+
+    Ok(())
+}
+
+
+#[test]
+fn test_spread_lowering() {
+    use crate::version::ECMAScriptVersion;
+    use crate::compiler::transform::{ Transformer, parse_owned, lower_and_print, print_owned, assert_reparse, };
+
+    let helpers = concat!(
+        "function _values(o){var method=typeof Symbol===\"function\"&&Symbol.iterator&&o[Symbol.iterator],i=0;",
+        "if(method)return method.call(o);return{next:function(){return{done:i>=o.length,value:o[i++]};}};}",
+        "function _toConsumableArray(o){var iterator=_values(o),result=[],step;",
+        "while(!(step=iterator.next()).done)result.push(step.value);return result;}",
+    );
+    let lower = |source: &str| {
+        let output = lower_and_print(parse_owned(source));
+        assert!(output.starts_with(helpers), "{}", output);
+        output[helpers.len()..].to_string()
+    };
+
+    assert_eq!(lower("f(a, ...b)"), "f.apply(void 0,[a].concat(_toConsumableArray(b)));");
+    assert_eq!(lower("a.b.m(d, ...c)"), "var _ref;(_ref=a.b).m.apply(_ref,[d].concat(_toConsumableArray(c)));");
+    // NOTE: `call` 不一定是 `Function.prototype.call`
+    assert_eq!(lower("rpc.call(\"m\", ...args)"), "rpc.call.apply(rpc,[\"m\"].concat(_toConsumableArray(args)));");
+    assert_eq!(lower("new F(...xs)"), "new (Function.prototype.bind.apply(F,[null].concat(_toConsumableArray(xs))))();");
+    assert_eq!(
+        lower("new a.F(x, ...xs, y)"),
+        "new (Function.prototype.bind.apply(a.F,[null,x].concat(_toConsumableArray(xs),[y])))();"
+    );
+    assert_eq!(lower("x = [...a, 1]"), "x=[].concat(_toConsumableArray(a),[1]);");
+    assert_eq!(lower("f(a, ...b, c, ...d)"), "f.apply(void 0,[a].concat(_toConsumableArray(b),[c],_toConsumableArray(d)));");
+    assert_eq!(lower("x = [a, ...b, c, ...d]"), "x=[a].concat(_toConsumableArray(b),[c],_toConsumableArray(d));");
+    assert_eq!(lower("f(...a + b, c)"), "f.apply(void 0,[].concat(_toConsumableArray(a+b),[c]));");
+
+    // NOTE: 降级的结果可以再次解析
+    for &source in [
+        "f(a, ...b)", "a.b.m(d, ...c)", "new a.F(x, ...xs, y)",
+        "f(a, ...b, c, ...d)", "x = [a, ...b, c, ...d]", "f(...a + b, c)",
+    ].iter() {
+        assert_reparse(&lower(source));
+    }
+
+    // ({ ...a, b: 1 });
+    let body = vec![
+        builder::expr_stmt(builder::parenthesized(builder::object(vec![
+            ObjectProperty::Spread { puct: builder::colon(), target: builder::ident_expr("a") },
+            builder::named_property("b", builder::number(1)),
+        ]))),
+    ];
+    assert_eq!(
+        lower_and_print(body),
+        concat!(
+            "var _extends=Object.assign||function(target){for(var i=1;i<arguments.length;i++){var source=arguments[i],key;",
+            "for(key in source)if(Object.prototype.hasOwnProperty.call(source,key))target[key]=source[key];}return target;};",
+            "(_extends({},a,{b:1}));",
+        )
+    );
+
+    // NOTE: ES2015 - ES2017 只降级对象展开
+    let mut body = vec![
+        builder::expr_stmt(builder::call(builder::ident_expr("f"), vec![
+            Expression::Spread(Box::new(SpreadExpression { loc: Default::default(), span: Default::default(), item: builder::ident_expr("xs") })),
+            builder::object(vec![
+                builder::named_property("b", builder::number(1)),
+                ObjectProperty::Spread { puct: builder::colon(), target: builder::ident_expr("a") },
+            ]),
+        ])),
+    ];
+    Transformer::new(ECMAScriptVersion::ES2015).transform_program(&mut body).ok().unwrap();
+    assert_eq!(
+        print_owned(&body),
+        concat!(
+            "var _extends=Object.assign||function(target){for(var i=1;i<arguments.length;i++){var source=arguments[i],key;",
+            "for(key in source)if(Object.prototype.hasOwnProperty.call(source,key))target[key]=source[key];}return target;};",
+            "f(...xs,_extends({b:1},a));",
+        )
+    );
+}
@@ -0,0 +1,155 @@
+// Template literals → string concatenation
+//
+//      `a${b}c`        →   "a".concat(b, "c")
+//      tag`a${b}\n`    →   tag(_templateObject || (_templateObject = _taggedTemplateLiteral(["a", "\n"], ["a", "\\n"])), b)
+//
+// NOTE: 使用 `concat` 而不是 `+`，与模板字符串一样对插值调用 `ToString`（而不是 `valueOf`）。
+//       同一处标签模板每次得到的是同一个 strings 数组，缓存在程序开头声明的变量中。
+
+use crate::error::Error;
+use crate::lexer::operator::InfixOperator;
+use crate::ast::statement::LexicalDeclarationKind;
+use crate::ast::owned::*;
+use crate::ast::visit::{ self, VisitMut, };
+use crate::ast::builder;
+use crate::compiler::transform::{ NameGenerator, Helper, Helpers, directive_prologue, };
+
+use std::mem;
+
+
+/// `"a".concat(b, "c")`
+fn concat(template: LiteralTemplateExpression) -> Expression {
+    let LiteralTemplateExpression { strings, bounds, .. } = template;
+
+    let mut strings = strings.into_iter();
+    let head = match strings.next() {
+        Some(head) => builder::string(&head.raw),
+        None => builder::string(""),
+    };
+    if bounds.is_empty() {
+        return head;
+    }
+
+    let mut arguments = Vec::new();
+    // NOTE: 最后一个插值之后的空字符串可能不存在
+    for bound in bounds.into_iter() {
+        arguments.push(bound);
+        match strings.next() {
+            Some(ref string) if !string.raw.is_empty() => arguments.push(builder::string(&string.raw)),
+            _ => { },
+        }
+    }
+
+    builder::call(builder::member(head, "concat"), arguments)
+}
+
+// NOTE: raw 数组中的元素是模板的原始文本，反斜杠本身需要转义。
+fn raw_string(string: &LiteralString) -> Expression {
+    builder::string(&string.raw.replace('\\', "\\\\"))
+}
+
+
+struct TemplateLowering<'a> {
+    names: &'a mut NameGenerator,
+    helpers: &'a mut Helpers,
+    objects: Vec<String>,
+}
+
+impl<'a> TemplateLowering<'a> {
+    // tag(_templateObject || (_templateObject = _taggedTemplateLiteral(strings, raw)), ...bounds)
+    fn tagged(&mut self, tagged: TaggedTemplateExpression) -> Expression {
+        let TaggedTemplateExpression { loc, span, tag, mut template } = tagged;
+
+        // NOTE: strings 数组总是比插值多一个元素
+        while template.strings.len() <= template.bounds.len() {
+            template.strings.push(LiteralString { loc: Default::default(), span: Default::default(), raw: String::new(), cooked: None });
+        }
+
+        let helper = self.helpers.get(self.names, Helper::TaggedTemplateLiteral);
+        let object = self.names.fresh("templateObject");
+        self.objects.push(object.clone());
+
+        let cooked = template.strings.iter().map(|string| builder::string(&string.raw)).collect();
+        let mut arguments = vec![ builder::array(cooked) ];
+        if template.strings.iter().any(|string| string.raw.contains('\\')) {
+            arguments.push(builder::array(template.strings.iter().map(raw_string).collect()));
+        }
+
+        let create = builder::call(builder::ident_expr(&helper), arguments);
+        let cached = builder::infix(builder::ident_expr(&object), InfixOperator::Or, builder::assign(builder::ident_expr(&object), create));
+
+        let mut items = vec![ cached ];
+        items.extend(template.bounds);
+
+        Expression::Call(Box::new(CallExpression { loc, span, callee: tag, arguments: builder::paren(items), optional: false }))
+    }
+}
+
+impl<'a> VisitMut for TemplateLowering<'a> {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        visit::walk_expression(self, expr);
+
+        match *expr {
+            Expression::Template(_) | Expression::TaggedTemplate(_) => { },
+            _ => return,
+        }
+
+        *expr = match mem::replace(expr, builder::null()) {
+            Expression::Template(template) => concat(*template),
+            Expression::TaggedTemplate(tagged) => self.tagged(*tagged),
+            _ => unreachable!(),
+        };
+    }
+}
+
+
+pub fn lower(body: &mut Vec<Statement>, names: &mut NameGenerator, helpers: &mut Helpers) -> Result<(), Error> {
+    let mut pass = TemplateLowering { names, helpers, objects: Vec::new() };
+    pass.visit_statements(body);
+
+    if !pass.objects.is_empty() {
+        let declarators = pass.objects.iter().map(|name| builder::declarator(builder::ident_expr(name), None)).collect();
+        let index = directive_prologue(body);
+        body.insert(index, builder::variable(LexicalDeclarationKind::Var, declarators));
+    }
+
+    Ok(())
+}
+
+
+#[test]
+fn test_template_lowering() {
+    use crate::compiler::transform::{ parse_owned, lower_and_print, assert_reparse, };
+
+    assert_eq!(lower_and_print(parse_owned("`a${b}c${d}`")), "\"a\".concat(b,\"c\",d);");
+    assert_eq!(lower_and_print(parse_owned("`${a}`")), "\"\".concat(a);");
+    assert_eq!(lower_and_print(parse_owned("`${a}${b}c`")), "\"\".concat(a,b,\"c\");");
+    assert_eq!(lower_and_print(parse_owned("`a\"b`")), "\"a\\\"b\";");
+    assert_eq!(
+        lower_and_print(parse_owned("tag`a${b}\\n` + tag`${c}`")),
+        concat!(
+            "function _taggedTemplateLiteral(strings,raw){if(!raw)raw=strings.slice(0);",
+            "return Object.freeze(Object.defineProperties(strings,{raw:{value:Object.freeze(raw)}}));}",
+            "var _templateObject,_templateObject2;",
+            "tag(_templateObject||(_templateObject=_taggedTemplateLiteral([\"a\",\"\\n\"],[\"a\",\"\\\\n\"])),b)",
+            "+tag(_templateObject2||(_templateObject2=_taggedTemplateLiteral([\"\",\"\"])),c);",
+        )
+    );
+    assert_eq!(
+        lower_and_print(parse_owned("\"use strict\"; tag`a`")),
+        concat!(
+            "\"use strict\";function _taggedTemplateLiteral(strings,raw){if(!raw)raw=strings.slice(0);",
+            "return Object.freeze(Object.defineProperties(strings,{raw:{value:Object.freeze(raw)}}));}",
+            "var _templateObject;tag(_templateObject||(_templateObject=_taggedTemplateLiteral([\"a\"])));",
+        )
+    );
+
+    assert_eq!(
+        lower_and_print(parse_owned("f(`a${b}`, `${c}d${e + 1}`, [`${f}`, g])")),
+        "f(\"a\".concat(b),\"\".concat(c,\"d\",e+1),[\"\".concat(f),g]);"
+    );
+    // NOTE: 降级的结果可以再次解析
+    for &source in [ "`a${b}c${d}`", "`a\"b`", "f(`a${b}`, `${c}d${e + 1}`, [`${f}`, g])" ].iter() {
+        assert_reparse(&lower_and_print(parse_owned(source)));
+    }
+}
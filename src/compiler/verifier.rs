@@ -0,0 +1,434 @@
+// Bytecode verifier
+//
+//      0000  GetLocal slot=0           depth 0 -> 1
+//      0002  JumpIfFalse offset=3      depth 1 -> 0    ; -> 0010
+//      0007  Int value=1               depth 0 -> 1
+//      0009  Return
+//      0010  Undefined                 depth 0 -> 1
+//      0011  Return
+//
+// NOTE: 解释器假设字节码是正确的（操作数栈不会下溢，局部变量、常量、捕获变量的索引都有效），
+//       从缓存文件或者其它不可信的来源加载的字节码在执行之前都需要经过检查：
+//          * 指令可以完整解码，跳转目标、异常处理的范围与目标都在指令的边界上
+//          * 任意一条路径到达同一条指令时，操作数栈的深度都相同，并且不会下溢
+//          * 代码不会执行到末尾之后（每条路径都以 `Return`、`Throw`、`ThrowError` 或者跳转结束）
+//          * 常量的索引有效并且类型正确，局部变量、Upvalue 的索引在范围之内
+//          * 内层函数捕获的变量在外层函数中存在，函数嵌套的深度不超过 `MAX_FUNCTION_NESTING`
+//       检查失败时返回 `InternalError`。
+
+use crate::error::{ ErrorKind, Error, };
+use crate::compiler::bytecode::{ CodeObject, Constant, Capture, Instruction, MAX_FUNCTION_NESTING, error_kind_from_u8, };
+
+
+/// Check the code object and its nested functions.
+pub fn verify(code: &CodeObject) -> Result<(), Error> {
+    Verifier::new(code, None)?.verify(0)
+}
+
+/// The stack effect of an instruction, the number of values it pops and pushes.
+pub fn stack_effect(instruction: Instruction) -> (u32, u32) {
+    use self::Instruction::*;
+
+    match instruction {
+        Nop | Debugger | NewCell(_) | DeclareName(_) | Jump(_) | LeaveWith | InitialYield => (0, 0),
+        ThrowError(..) => (0, 0),
+
+        Undefined | Null | True | False | Hole | Int(_) | Const(_) | This | NewTarget
+        | Closure(_) | RegExp(_) | TemplateObject(_) | Object | GetLocal(_) | GetCell(_)
+        | GetUpvalue(_) | GetName(_) | TypeofName(_) | DeleteName(_) | Arguments | Rest(_)
+        | Callee => (0, 1),
+
+        Array(count) => (count, 1),
+
+        Pop | JumpIfTrue(_) | JumpIfFalse(_) | JumpIfNullish(_) | JumpIfNotNullish(_)
+        | Return | Throw | EnterWith | IteratorClose => (1, 0),
+        Dup => (1, 2),
+        Dup2 => (2, 4),
+        Swap => (2, 2),
+        Rot3 => (3, 3),
+
+        SetLocal(_) | SetCell(_) | SetUpvalue(_) | CheckHole(_) | SetName(_)
+        | GetNamed(..) | DeleteNamed(_) | GetSuper | ArrayHole
+        | Neg | Plus | Not | BitNot | TypeOf | Inc | Dec | ToNumeric | ToPropertyKey | ToString
+        | GetIterator | GetAsyncIterator | ForInEnumerate | Yield | Await | SuperCallSpread => (1, 1),
+
+        SetNamed(..) | GetKeyed | DeleteKeyed | SetSuper | DefineNamed(_) | CopyDataProperties
+        | SetPrototype | ArrayPush | ArraySpread | NewSpread
+        | Add | Sub | Mul | Div | Mod | Exp | Shl | Shr | UShr | BitAnd | BitOr | BitXor
+        | Eq | Ne | StrictEq | StrictNe | Lt | Le | Gt | Ge | In | InstanceOf => (2, 1),
+
+        SetKeyed | DefineField | DefineMethod(_) | DefineGetter(_) | DefineSetter(_) | CallSpread => (3, 1),
+        Class(_) => (2, 2),
+        IteratorNext => (1, 3),
+
+        Call(argc) => (argc.saturating_add(2), 1),
+        New(argc) => (argc.saturating_add(1), 1),
+        SuperCall(argc) => (argc, 1),
+    }
+}
+
+
+struct Verifier<'a> {
+    code: &'a CodeObject,
+    instructions: Vec<(u32, Instruction)>,
+    /// Stack depth before each instruction, `None` for unreachable instructions.
+    depths: Vec<Option<u32>>,
+    worklist: Vec<usize>,
+}
+
+impl<'a> Verifier<'a> {
+    /// `parent` is the number of local slots and captures of the enclosing function.
+    fn new(code: &'a CodeObject, parent: Option<(u32, usize)>) -> Result<Self, Error> {
+        for capture in code.captures.iter() {
+            let ok = match (*capture, parent) {
+                (Capture::Local(slot), Some((local_count, _))) => slot < local_count,
+                (Capture::Upvalue(index), Some((_, capture_count))) => (index as usize) < capture_count,
+                (_, None) => false,
+            };
+            if !ok {
+                return Err(invalid(code, format!("invalid capture {:?}", capture)));
+            }
+        }
+
+        let instructions = code.instructions().map_err(|error| invalid(code, error.message()))?;
+        let depths = vec![ None; instructions.len() ];
+
+        Ok(Verifier { code, instructions, depths, worklist: Vec::new() })
+    }
+
+    fn local_count(&self) -> u32 {
+        std::cmp::max(self.code.local_count, self.code.param_count)
+    }
+
+    fn error<S: Into<String>>(&self, message: S) -> Error {
+        invalid(self.code, message)
+    }
+
+    /// Index of the instruction at `offset`.
+    fn boundary(&self, offset: u32) -> Option<usize> {
+        self.instructions.binary_search_by_key(&offset, |&(start, _)| start).ok()
+    }
+
+    /// `depth` is the nesting depth of the function.
+    fn verify(mut self, depth: usize) -> Result<(), Error> {
+        if depth > MAX_FUNCTION_NESTING {
+            return Err(self.error("functions are nested too deeply"));
+        }
+        if self.instructions.is_empty() {
+            return Err(self.error("the code is empty"));
+        }
+        // NOTE: VM 为每个缓存分配空间，每条 GetNamed / SetNamed 指令至少占 3 个字节
+        if self.code.cache_count as usize > self.code.code.len() / 3 {
+            return Err(self.error(format!("too many inline caches: {}", self.code.cache_count)));
+        }
+
+        for index in 0..self.instructions.len() {
+            self.operands(index)?;
+        }
+        self.handlers()?;
+        self.lines()?;
+        self.stack()?;
+
+        let parent = (self.local_count(), self.code.captures.len());
+        for constant in self.code.constants.iter() {
+            if let Constant::Function(ref function) = *constant {
+                Verifier::new(function, Some(parent))?.verify(depth + 1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn constant(&self, offset: u32, index: u32, expected: &str) -> Result<(), Error> {
+        let ok = match (self.code.constants.get(index as usize), expected) {
+            (Some(&Constant::Number(_)), "value") | (Some(&Constant::String(_)), "value")
+            | (Some(&Constant::BigInt(_)), "value") => true,
+            (Some(&Constant::String(_)), "string") => true,
+            (Some(&Constant::Function(_)), "function") => true,
+            (Some(&Constant::RegExp { .. }), "regexp") => true,
+            (Some(&Constant::Template { .. }), "template") => true,
+            _ => false,
+        };
+
+        if ok {
+            Ok(())
+        } else {
+            Err(self.error(format!("constant {} at {} is not a {}", index, offset, expected)))
+        }
+    }
+
+    fn operands(&self, index: usize) -> Result<(), Error> {
+        let (offset, instruction) = self.instructions[index];
+        match instruction {
+            Instruction::Const(index) => self.constant(offset, index, "value")?,
+            Instruction::Closure(index) => self.constant(offset, index, "function")?,
+            Instruction::RegExp(index) => self.constant(offset, index, "regexp")?,
+            Instruction::TemplateObject(index) => self.constant(offset, index, "template")?,
+            Instruction::CheckHole(name) | Instruction::GetName(name) | Instruction::SetName(name)
+            | Instruction::TypeofName(name) | Instruction::DeleteName(name) | Instruction::DeclareName(name)
+            | Instruction::DeleteNamed(name) | Instruction::DefineNamed(name) | Instruction::Class(name) => {
+                self.constant(offset, name, "string")?
+            },
+            Instruction::GetNamed(name, cache) | Instruction::SetNamed(name, cache) => {
+                self.constant(offset, name, "string")?;
+                if cache >= self.code.cache_count {
+                    return Err(self.error(format!("inline cache {} at {} out of range", cache, offset)));
+                }
+            },
+            Instruction::ThrowError(kind, message) => {
+                if error_kind_from_u8(kind).is_none() {
+                    return Err(self.error(format!("invalid error kind {} at {}", kind, offset)));
+                }
+                self.constant(offset, message, "string")?;
+            },
+            Instruction::GetLocal(slot) | Instruction::SetLocal(slot) | Instruction::NewCell(slot)
+            | Instruction::GetCell(slot) | Instruction::SetCell(slot) => {
+                if slot >= self.local_count() {
+                    return Err(self.error(format!("local slot {} at {} out of range", slot, offset)));
+                }
+            },
+            Instruction::GetUpvalue(index) | Instruction::SetUpvalue(index) => {
+                if index as usize >= self.code.captures.len() {
+                    return Err(self.error(format!("upvalue {} at {} out of range", index, offset)));
+                }
+            },
+            _ => { },
+        }
+
+        if let Some(target) = self.code.jump_target(offset)? {
+            if self.boundary(target).is_none() {
+                return Err(self.error(format!("jump at {} to {} is not an instruction boundary", offset, target)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handlers(&self) -> Result<(), Error> {
+        let len = self.code.code.len() as u32;
+        for handler in self.code.handlers.iter() {
+            let ok = handler.start < handler.end
+                && self.boundary(handler.start).is_some()
+                && (handler.end == len || self.boundary(handler.end).is_some())
+                && self.boundary(handler.target).is_some();
+            if !ok {
+                return Err(self.error(format!("invalid handler {:?}", handler)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn lines(&self) -> Result<(), Error> {
+        let len = self.code.code.len() as u32;
+        let entries = self.code.lines.entries();
+        let ordered = entries.windows(2).all(|pair| pair[0].0 < pair[1].0);
+        if !ordered || entries.iter().any(|&(offset, loc)| offset >= len || loc.start > loc.end) {
+            return Err(self.error("invalid line table"));
+        }
+
+        Ok(())
+    }
+
+    fn enter(&mut self, index: usize, depth: u32) -> Result<(), Error> {
+        match self.depths[index] {
+            None => {
+                self.depths[index] = Some(depth);
+                self.worklist.push(index);
+                Ok(())
+            },
+            Some(expected) if expected == depth => Ok(()),
+            Some(expected) => {
+                let offset = self.instructions[index].0;
+                Err(self.error(format!("inconsistent stack depth at {}: {} and {}", offset, expected, depth)))
+            },
+        }
+    }
+
+    fn stack(&mut self) -> Result<(), Error> {
+        self.enter(0, 0)?;
+        for handler in self.code.handlers.iter() {
+            let target = self.boundary(handler.target).unwrap();
+            self.enter(target, handler.stack_depth.saturating_add(1))?;
+        }
+
+        while let Some(index) = self.worklist.pop() {
+            let depth = self.depths[index].unwrap();
+            let (offset, instruction) = self.instructions[index];
+            let (pops, pushes) = stack_effect(instruction);
+            if depth < pops {
+                return Err(self.error(format!("stack underflow at {}", offset)));
+            }
+
+            // NOTE: 异常处理只会截断操作数栈，不能增加栈的深度
+            let covered = self.code.handlers.iter()
+                .filter(|handler| handler.start <= offset && offset < handler.end)
+                .any(|handler| depth - pops < handler.stack_depth);
+            if covered {
+                return Err(self.error(format!("stack depth at {} is below its handler", offset)));
+            }
+
+            let after = (depth - pops).checked_add(pushes)
+                .ok_or_else(|| self.error(format!("stack overflow at {}", offset)))?;
+
+            if let Some(target) = self.code.jump_target(offset)? {
+                let target = self.boundary(target).unwrap();
+                self.enter(target, after)?;
+            }
+
+            match instruction {
+                Instruction::Jump(_) | Instruction::Return | Instruction::Throw | Instruction::ThrowError(..) => { },
+                _ if index + 1 == self.instructions.len() => {
+                    return Err(self.error(format!("the instruction at {} falls off the end of the code", offset)));
+                },
+                _ => self.enter(index + 1, after)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid<S: Into<String>>(code: &CodeObject, message: S) -> Error {
+    Error::new(ErrorKind::InternalError, format!("invalid bytecode in function '{}': {}", code.name, message.into()))
+}
+
+
+#[test]
+fn test_verify() {
+    use crate::compiler::bytecode::Handler;
+    use crate::lexer::span::Loc;
+    use crate::ast::builder::*;
+
+    let loc = Loc::default();
+    let compile = |body| {
+        use crate::toolshed::Arena;
+        use crate::ast::owned::{ Statement, ToArenaAst, };
+        use crate::compiler::scope::Goal;
+
+        let arena = Arena::new();
+        let body: Vec<Statement> = body;
+        let body = body.iter().map(|stmt| stmt.to_arena_ast(&arena)).collect::<Vec<_>>();
+        crate::compiler::bytecodegen::compile(&body, Goal::Script).unwrap()
+    };
+
+    // NOTE: 编译器生成的代码都能通过检查
+    let inner = function_expr(None, vec![], vec![ return_stmt(Some(ident_expr("x"))) ]);
+    let finally = vec![ expr_stmt(call(ident_expr("f"), vec![])) ];
+    let body = vec![ if_stmt(ident_expr("b"), break_stmt(None), None), throw_stmt(string_value("e")) ];
+    let code = compile(vec![
+        function_decl("outer", vec![ ident_expr("a") ], vec![ var("x", Some(number(1))), return_stmt(Some(inner)) ]),
+        while_stmt(ident_expr("a"), block_stmt(vec![
+            try_stmt(body, Some((ident_expr("e"), vec![ expr_stmt(ident_expr("e")) ])), Some(finally)),
+        ])),
+        for_in_stmt(ident_expr("k"), object(vec![ named_property("a", number(1)) ]), empty()),
+        switch_stmt(ident_expr("a"), vec![ (Some(number(1)), vec![ break_stmt(None) ]), (None, vec![]) ]),
+        expr_stmt(conditional(ident_expr("a"), array(vec![ number(1), string_value("s") ]), new(ident_expr("F"), vec![]))),
+    ]);
+    assert_eq!(verify(&code), Ok(()));
+
+    let valid = {
+        let mut code = CodeObject::new("f");
+        code.local_count = 1;
+        code.emit(Instruction::GetLocal(0), loc);
+        let jump = code.emit(Instruction::JumpIfFalse(0), loc);
+        code.emit(Instruction::Int(1), loc);
+        code.emit(Instruction::Return, loc);
+        let target = code.emit(Instruction::Undefined, loc);
+        code.emit(Instruction::Return, loc);
+        code.patch_jump(jump, target);
+        code
+    };
+    assert_eq!(verify(&valid), Ok(()));
+
+    let check = |code: &CodeObject, message: &str| {
+        let error = verify(code).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InternalError);
+        assert!(error.message().contains(message), "{:?} does not contain {:?}", error.message(), message);
+    };
+
+    // 跳转到指令的中间
+    let mut code = valid.clone();
+    code.code[3..7].copy_from_slice(&(-2i32).to_le_bytes());
+    check(&code, "jump at 2 to 5 is not an instruction boundary");
+
+    // 两条路径的栈深度不同
+    let mut code = valid.clone();
+    code.code.truncate(7);
+    code.emit(Instruction::Int(1), loc);
+    code.emit(Instruction::Int(2), loc);
+    code.emit(Instruction::Undefined, loc);
+    code.emit(Instruction::Return, loc);
+    code.patch_jump(2, 9);
+    check(&code, "inconsistent stack depth");
+
+    let mut code = CodeObject::new("f");
+    code.emit(Instruction::Pop, loc);
+    code.emit(Instruction::Undefined, loc);
+    code.emit(Instruction::Return, loc);
+    check(&code, "stack underflow at 0");
+
+    let mut code = CodeObject::new("f");
+    code.emit(Instruction::Undefined, loc);
+    check(&code, "falls off the end");
+
+    let mut code = valid.clone();
+    code.local_count = 0;
+    check(&code, "local slot 0 at 0 out of range");
+
+    let mut code = CodeObject::new("f");
+    code.emit(Instruction::Const(0), loc);
+    code.emit(Instruction::Return, loc);
+    check(&code, "constant 0 at 0 is not a value");
+    code.add_constant(Constant::Function(Box::new(valid.clone())));
+    check(&code, "constant 0 at 0 is not a value");
+
+    let mut code = CodeObject::new("f");
+    code.emit(Instruction::GetUpvalue(0), loc);
+    code.emit(Instruction::Return, loc);
+    check(&code, "upvalue 0 at 0 out of range");
+
+    let mut code = CodeObject::new("f");
+    let name = code.add_string("x");
+    code.emit(Instruction::Object, loc);
+    code.emit(Instruction::GetNamed(name, 0), loc);
+    code.emit(Instruction::Return, loc);
+    check(&code, "inline cache 0 at 1 out of range");
+    code.cache();
+    assert_eq!(verify(&code), Ok(()));
+    code.cache_count = u32::max_value();
+    check(&code, "too many inline caches");
+
+    let mut code = valid.clone();
+    code.handlers.push(Handler { start: 0, end: 8, target: 1, stack_depth: 0 });
+    check(&code, "invalid handler");
+    let mut code = valid.clone();
+    code.handlers.push(Handler { start: 2, end: 7, target: 10, stack_depth: 1 });
+    check(&code, "below its handler");
+
+    // 内层函数捕获了外层函数中不存在的变量
+    let mut inner = valid.clone();
+    inner.captures.push(Capture::Local(1));
+    let mut code = valid.clone();
+    code.add_constant(Constant::Function(Box::new(inner)));
+    check(&code, "invalid capture Local(1)");
+
+    // 嵌套过深的函数
+    let nested = |depth: usize| {
+        let mut code = valid.clone();
+        for _ in 0..depth {
+            let mut outer = valid.clone();
+            outer.add_constant(Constant::Function(Box::new(code)));
+            code = outer;
+        }
+        code
+    };
+    assert_eq!(verify(&nested(MAX_FUNCTION_NESTING)), Ok(()));
+    check(&nested(MAX_FUNCTION_NESTING + 1), "functions are nested too deeply");
+
+    check(&CodeObject::new("f"), "the code is empty");
+    let mut code = valid.clone();
+    code.code.pop();
+    code.code.push(0xff);
+    check(&code, "unknown opcode");
+}
@@ -98,6 +98,41 @@ impl Error {
             self.line = line;
         }
     }
+
+    /// Set the stack infomation of the character at `offset` in `source`.
+    pub fn set_location<F: Into<String>>(&mut self, filename: F, source: &str, offset: usize) {
+        let source = source.chars().collect::<Vec<char>>();
+        let offset = std::cmp::min(offset, source.len());
+
+        let is_line_terminator = |c: char| c == '\n' || c == '\r' || c == '\u{2028}' || c == '\u{2029}';
+
+        let mut line_number = 0;
+        let mut line_start = 0;
+        for (index, c) in source[..offset].iter().enumerate() {
+            match *c {
+                '\r' if source.get(index + 1) == Some(&'\n') => { },
+                c if is_line_terminator(c) => {
+                    line_number += 1;
+                    line_start = index + 1;
+                },
+                _ => { },
+            }
+        }
+        let line_end = source[line_start..].iter()
+            .position(|c| is_line_terminator(*c))
+            .map(|end| line_start + end)
+            .unwrap_or_else(|| source.len());
+        let column_number = offset - line_start;
+
+        // NOTE: 与词法分析器的错误行格式相同
+        let code_line = source[line_start..line_end].iter().collect::<String>();
+        let prefix_width = format!("{}", line_number).len() + 1;
+        let prefix = " ".repeat(prefix_width);
+        let line = format!("{}|\n{:<width$}| {}\n{}| {}^", prefix, line_number + 1, code_line, prefix,
+            " ".repeat(column_number), width=prefix_width);
+
+        self.set_stack(filename, line_number, column_number, Some(line));
+    }
 }
 
 impl fmt::Debug for Error {
@@ -109,7 +144,10 @@ impl fmt::Debug for Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let _ = writeln!(f, "{}: {}", self.kind, self.message);
-        let _ = writeln!(f, " --> {}:{}:{}", self.filename(), self.line_number() + 1, self.column_number());
+        // NOTE: 执行已编译的代码时没有源码，错误不带位置信息
+        if let (Some(filename), Some(line_number), Some(column_number)) = (&self.filename, self.line_number, self.column_number) {
+            let _ = writeln!(f, " --> {}:{}:{}", filename, line_number + 1, column_number);
+        }
 
         match self.line {
             Some(ref line) => {
@@ -121,3 +159,14 @@ impl fmt::Display for Error {
         Ok(())
     }
 }
+
+
+#[test]
+fn test_error_location() {
+    let mut error = Error::new(ErrorKind::TypeError, "null has no properties");
+    assert_eq!(format!("{}", error), "TypeError: null has no properties\n");
+
+    error.set_location("<script>", "a;\r\nb;\n  null.x;", 9);
+    assert_eq!((error.filename(), error.line_number(), error.column_number()), ("<script>", 2, 2));
+    assert_eq!(format!("{}", error), "TypeError: null has no properties\n --> <script>:3:2\n  |\n3 |   null.x;\n  |   ^");
+}
@@ -93,6 +93,10 @@ pub struct Lexer<'ast> {
     token_start_line_offset: usize,
     token_start_line: usize,
     token_start_column: usize,
+
+    // NOTE: `/* @__PURE__ */` 注释之后的第一个 Token 的起始位置
+    pub pure_annotations: Vec<usize>,
+    pure_pending: bool,
 }
 
 impl<'ast> Lexer<'ast> {
@@ -113,6 +117,9 @@ impl<'ast> Lexer<'ast> {
             token_start_line_offset: 0,
             token_start_line: 0,
             token_start_column: 0,
+
+            pure_annotations: Vec::new(),
+            pure_pending: false,
         }
     }
 
@@ -458,7 +465,7 @@ impl<'ast> Lexer<'ast> {
 
         loop {
             match self.character() {
-                '0' ... '9' => {
+                '0' ... '9' | '_' => {
                     if let Err(_) = self.bump() {
                         break;
                     }
@@ -490,7 +497,7 @@ impl<'ast> Lexer<'ast> {
 
         loop {
             match self.character() {
-                '0' ... '9' => {
+                '0' ... '9' | '_' => {
                     if let Err(_) = self.bump() {
                         break;
                     }
@@ -534,7 +541,7 @@ impl<'ast> Lexer<'ast> {
 
                         loop {
                             match self.character() {
-                                '0' | '1' => {
+                                '0' | '1' | '_' => {
                                     match self.bump() {
                                         Ok(_) => { },
                                         Err(_) => {
@@ -553,7 +560,7 @@ impl<'ast> Lexer<'ast> {
 
                         loop {
                             match self.character() {
-                                '0' ... '7' => {
+                                '0' ... '7' | '_' => {
                                     match self.bump() {
                                         Ok(_) => { },
                                         Err(_) => {
@@ -572,7 +579,7 @@ impl<'ast> Lexer<'ast> {
 
                         loop {
                             match self.character() {
-                                '0' ... '9' | 'a' ... 'f' | 'A' ... 'F' => {
+                                '0' ... '9' | 'a' ... 'f' | 'A' ... 'F' | '_' => {
                                     match self.bump() {
                                         Ok(_) => { },
                                         Err(_) => {
@@ -602,7 +609,7 @@ impl<'ast> Lexer<'ast> {
                     loop {
                         let c = self.character();
                         match c {
-                            '0' ... '9' => {
+                            '0' ... '9' | '_' => {
                                 if let Err(_) = self.bump() {
                                     break;
                                 }
@@ -785,7 +792,27 @@ impl<'ast> Lexer<'ast> {
             ',' => bump_with_punct!(Comma),
             ':' => bump_with_punct!(Colon),
             ';' => bump_with_punct!(Semicolon),
-            '?' => bump_with_punct!(Question),
+            '?' => {
+                bump_or_with_punct!(Question);
+
+                match self.character() {
+                    '?' => {
+                        bump_or_with_punct!(Nullish);
+
+                        match self.character() {
+                            '=' => {
+                                bump_with_punct!(NullishAssign);
+                            },
+                            _ => Ok(Some(punct!(Nullish)))
+                        }
+                    },
+                    // NOTE: `a?.5:b` 是条件表达式
+                    '.' if !self.source.get(self.offset + 1).map(|c| c.is_ascii_digit()).unwrap_or(false) => {
+                        bump_with_punct!(QuestionDot);
+                    },
+                    _ => Ok(Some(punct!(Question)))
+                }
+            },
             '.' => {
                 bump_or_with_punct!(Dot);
                 
@@ -822,6 +849,7 @@ impl<'ast> Lexer<'ast> {
                     '*' => {
                         // /*
                         bump_or_with_error!(self, UnexpectedEOF);
+                        let start = self.offset;
 
                         loop {
                             let c = self.character();
@@ -843,6 +871,11 @@ impl<'ast> Lexer<'ast> {
                             }
                         }
                         
+                        let value = self.source[start..self.offset - 1].iter().collect::<String>();
+                        match value.trim() {
+                            "@__PURE__" | "#__PURE__" => self.pure_pending = true,
+                            _ => { },
+                        }
                         let _ = self.bump();
 
                         return self.consume();
@@ -914,6 +947,16 @@ impl<'ast> Lexer<'ast> {
                 bump_or_with_punct!(BitOr);
 
                 match self.character() {
+                    '|' => {
+                        bump_or_with_punct!(Or);
+
+                        match self.character() {
+                            '=' => {
+                                bump_with_punct!(OrAssign);
+                            },
+                            _ => Ok(Some(punct!(Or)))
+                        }
+                    },
                     '=' => {
                         bump_with_punct!(BitOrAssign);
                     },
@@ -924,6 +967,16 @@ impl<'ast> Lexer<'ast> {
                 bump_or_with_punct!(BitAnd);
 
                 match self.character() {
+                    '&' => {
+                        bump_or_with_punct!(And);
+
+                        match self.character() {
+                            '=' => {
+                                bump_with_punct!(AndAssign);
+                            },
+                            _ => Ok(Some(punct!(And)))
+                        }
+                    },
                     '=' => {
                         bump_with_punct!(BitAndAssign);
                     },
@@ -1238,8 +1291,21 @@ impl<'ast> Lexer<'ast> {
         Ok((LiteralString { loc, span, raw, cooked }, is_end))
     }
 
-    #[inline]
     pub fn consume(&mut self) -> Result<Option<Token<'ast>>, Error> {
+        let token = self.consume_token()?;
+        match token {
+            Some(Token::LineTerminator) | None => { },
+            Some(_) => if self.pure_pending {
+                self.pure_pending = false;
+                self.pure_annotations.push(self.token_start_offset);
+            },
+        }
+
+        Ok(token)
+    }
+
+    #[inline]
+    fn consume_token(&mut self) -> Result<Option<Token<'ast>>, Error> {
         loop {
             if self.eof() {
                 return Ok(None);
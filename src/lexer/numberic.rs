@@ -76,8 +76,39 @@ pub fn from_chars_radix(digits: &[char], radix: u32, pre_offset: usize) -> Resul
 
 
 
+// NOTE: 数字分隔符（ES2021）只能出现在两个数字之间，例如 `1_000`、`0xff_ff`，
+//       `1__0`、`1_`、`0x_1`、`1_.5`、`0_1` 都是非法的。
+#[inline]
+fn strip_separators(input: &[char]) -> Result<Vec<char>, ParseNumbericError> {
+    let radix = match (input.get(0), input.get(1)) {
+        (Some('0'), Some('b')) | (Some('0'), Some('B')) => BINARY,
+        (Some('0'), Some('o')) | (Some('0'), Some('O')) => OCTAL,
+        (Some('0'), Some('x')) | (Some('0'), Some('X')) => HEX,
+        _ => 10,
+    };
+    let is_digit = |c: Option<&char>| c.map(|c| c.is_digit(radix)).unwrap_or(false);
+
+    for (idx, c) in input.iter().enumerate() {
+        if *c != '_' {
+            continue;
+        }
+
+        let leading_zero = radix == 10 && idx == 1 && input[0] == '0';
+        if idx == 0 || leading_zero || !is_digit(input.get(idx - 1)) || !is_digit(input.get(idx + 1)) {
+            return Err(ParseNumbericError::new(NumbericErrorKind::InvalidDigit, idx));
+        }
+    }
+
+    Ok(input.iter().cloned().filter(|c| *c != '_').collect())
+}
+
 #[inline]
 pub fn parse_numberic(input: &[char]) -> Result<Numberic, ParseNumbericError> {
+    if input.contains(&'_') {
+        let digits = strip_separators(input)?;
+        return parse_numberic(&digits);
+    }
+
     let input_len = input.len();
     let mut idx = 0usize;
 
@@ -161,7 +192,8 @@ pub fn parse_numberic(input: &[char]) -> Result<Numberic, ParseNumbericError> {
                 Ok(float) => {
                     let fract = float.fract();
                     // TODO: 计算 EPSILON ? std::f64::EPSILON
-                    if fract < 0.0 || fract > 0.0 {
+                    // NOTE: 超出 i64 精确表示范围的整数（`1e21`）保留为浮点数
+                    if fract < 0.0 || fract > 0.0 || float > 9007199254740991.0 {
                         return Ok(Numberic::F64(float.into()));
                     } else {
                         // int
@@ -235,3 +267,21 @@ fn test_parse_int() {
     assert_eq!(parse_numberic(&f("0x69")), Ok(Numberic::I64(105)));
 }
 
+
+#[test]
+fn test_parse_separator() {
+    let f = |s: &str| -> Vec<char> {
+        s.chars().collect::<Vec<char>>()
+    };
+
+    assert_eq!(parse_numberic(&f("1_000_000")), Ok(Numberic::I64(1000000)));
+    assert_eq!(parse_numberic(&f("0b1010_1010")), Ok(Numberic::I64(170)));
+    assert_eq!(parse_numberic(&f("0xff_ff")), Ok(Numberic::I64(65535)));
+    assert_eq!(parse_numberic(&f("1_0.2_5")), Ok(Numberic::F64( 10.25f64.into() )));
+
+    assert_eq!(parse_numberic(&f("1__0")).is_err(), true);
+    assert_eq!(parse_numberic(&f("1_")).is_err(), true);
+    assert_eq!(parse_numberic(&f("0x_1")).is_err(), true);
+    assert_eq!(parse_numberic(&f("1_.5")).is_err(), true);
+    assert_eq!(parse_numberic(&f("0_1")).is_err(), true);
+}
@@ -40,6 +40,7 @@ pub enum InfixOperator {
     BitUShr,  // >>>
     And,      // &&
     Or,       // ||
+    NullishCoalescing, // ??
     BitAnd,   // &
     BitXor,   // ^
     BitOr,    // |
@@ -73,7 +74,7 @@ impl InfixOperator {
             BitXor => 8,
             BitOr => 7,
             And => 6,
-            Or => 5,
+            Or | NullishCoalescing => 5,
         }
     }
 }
@@ -100,5 +101,9 @@ pub enum AssignmentOperator {
     BitShlAssign,   //  <<=
     BitShrAssign,   //  >>=
     BitUShrAssign,  // >>>=
+
+    AndAssign,      //  &&=
+    OrAssign,       //  ||=
+    NullishAssign,  //  ??=
 }
 
@@ -36,6 +36,7 @@ pub const PUNCT_LTEQ: &[char]          = &[ '<', '=', ];
 pub enum PunctuatorKind {
     Colon,          // :
     Question,       // ?
+    QuestionDot,    // ?.
     Semicolon,      // ;
     Comma,          // ,
     Dot,            // .
@@ -56,6 +57,7 @@ pub enum PunctuatorKind {
     Not,            //  ! , unary operator
     And,            // &&
     Or,             // ||
+    Nullish,        // ??
 
     // Binary operators
     Add,            //  + , Maybe unary operator
@@ -86,6 +88,9 @@ pub enum PunctuatorKind {
     BitShlAssign,   // <<=
     BitShrAssign,   // >>=
     BitUShrAssign,  // >>>=
+    AndAssign,      // &&=
+    OrAssign,       // ||=
+    NullishAssign,  // ??=
 
     // compare operator
     Eq,             // ==
@@ -106,6 +111,7 @@ impl FromStr for PunctuatorKind {
 
         match s {
             "?" => Ok(Question),
+            "?." => Ok(QuestionDot),
             "." => Ok(Dot),
             ";" => Ok(Semicolon),
             ":" => Ok(Colon),
@@ -124,6 +130,7 @@ impl FromStr for PunctuatorKind {
             "!" => Ok(Not),
             "&&" => Ok(And),
             "||" => Ok(Or),
+            "??" => Ok(Nullish),
             "+" => Ok(Add),
             "-" => Ok(Sub),
             "*" => Ok(Mul),
@@ -149,6 +156,9 @@ impl FromStr for PunctuatorKind {
             "<<=" => Ok(BitShlAssign),
             ">>=" => Ok(BitShrAssign),
             ">>>=" => Ok(BitUShrAssign),
+            "&&=" => Ok(AndAssign),
+            "||=" => Ok(OrAssign),
+            "??=" => Ok(NullishAssign),
             "==" => Ok(Eq),
             "===" => Ok(StrictEq),
             ">" => Ok(Gt),
@@ -20,6 +20,14 @@ pub struct Loc {
     pub end: usize,
 }
 
+impl Loc {
+    /// Nodes synthesized by the compiler carry a default `Loc`,
+    /// they have no counterpart in the original source.
+    pub fn is_dummy(&self) -> bool {
+        self.start == 0 && self.end == 0
+    }
+}
+
 impl fmt::Debug for LineColumn {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}:{:?}", self.line + 1, self.column)
@@ -18,7 +18,9 @@ extern crate serde_json;
 extern crate vlq;
 extern crate time;
 extern crate num;
+extern crate regex;
 extern crate rustc_hash;
+extern crate fnv;
 
 
 pub mod rc_ref;
@@ -35,5 +37,6 @@ pub mod lexer;
 
 pub mod parser;
 pub mod compiler;
+pub mod bundler;
 pub mod vm;
 
@@ -29,6 +29,7 @@ use crate::ast::expression::{
     CallExpression, 
 };
 use crate::ast::function::{ FunctionExpression, Function, ArrowFunctionExpression, ConciseBody, };
+use crate::ast::pattern::ArrayLiteral;
 
 // 运算符优先级
 // https://developer.mozilla.org/zh-CN/docs/Web/JavaScript/Reference/Operators/Operator_Precedence#Table
@@ -57,6 +58,7 @@ pub fn punctuator_to_infix_op(punct: PunctuatorKind) -> InfixOperator {
         PunctuatorKind::BitUShr => InfixOperator::BitUShr,
         PunctuatorKind::And => InfixOperator::And,
         PunctuatorKind::Or => InfixOperator::Or,
+        PunctuatorKind::Nullish => InfixOperator::NullishCoalescing,
         PunctuatorKind::BitAnd => InfixOperator::BitAnd,
         PunctuatorKind::BitXor => InfixOperator::BitXor,
         PunctuatorKind::BitOr => InfixOperator::BitOr,
@@ -90,10 +92,38 @@ pub fn punctuator_to_assignment_op(punct: PunctuatorKind) -> AssignmentOperator
         PunctuatorKind::BitShlAssign => AssignmentOperator::BitShlAssign,
         PunctuatorKind::BitShrAssign => AssignmentOperator::BitShrAssign,
         PunctuatorKind::BitUShrAssign => AssignmentOperator::BitUShrAssign,
+
+        PunctuatorKind::AndAssign => AssignmentOperator::AndAssign,
+        PunctuatorKind::OrAssign => AssignmentOperator::OrAssign,
+        PunctuatorKind::NullishAssign => AssignmentOperator::NullishAssign,
         _ => unreachable!(),
     }
 }
 
+#[inline]
+fn is_mixed_nullish(operator: InfixOperator, operand: &Expression) -> bool {
+    let is_logical = |op: InfixOperator| op == InfixOperator::And || op == InfixOperator::Or;
+
+    match *operand {
+        Expression::Infix(inner) => {
+            (operator == InfixOperator::NullishCoalescing && is_logical(inner.operator))
+            || (is_logical(operator) && inner.operator == InfixOperator::NullishCoalescing)
+        },
+        _ => false,
+    }
+}
+
+#[inline]
+fn is_unary(expr: &Expression) -> bool {
+    match *expr {
+        Expression::Prefix(inner) => match inner.operator {
+            PrefixOperator::Increment | PrefixOperator::Decrement => false,
+            _ => true,
+        },
+        _ => false,
+    }
+}
+
 #[inline]
 pub fn keyword_to_infix_op(keyword: KeywordKind) -> InfixOperator {
     match keyword {
@@ -136,6 +166,8 @@ impl<'ast> Parser<'ast> {
                         let mut span = punct.span;
                         let mut items: Vec<Expression<'ast>> = Vec::new();
                         let op_precedence = 20i8;
+                        // NOTE: 括号内是完整的表达式（包括逗号表达式），与外层的优先级无关
+                        let item_precedence = -1i8;
 
                         let mut is_first: bool = true;
                         loop {
@@ -155,7 +187,7 @@ impl<'ast> Parser<'ast> {
                                                 return Err(self.unexpected_token(token2));
                                             }
 
-                                            let item = self.parse_expression(token2, precedence)?;
+                                            let item = self.parse_expression(token2, item_precedence)?;
                                             items.push(item);
                                             
                                             is_first = false;
@@ -167,7 +199,7 @@ impl<'ast> Parser<'ast> {
                                         return Err(self.unexpected_token(token2));
                                     }
 
-                                    let item = self.parse_expression(token2, precedence)?;
+                                    let item = self.parse_expression(token2, item_precedence)?;
                                     items.push(item);
                                     
                                     is_first = false;
@@ -179,6 +211,11 @@ impl<'ast> Parser<'ast> {
                         let item = ParenthesizedExpression { loc, span, items: elems };
                         Expression::Parenthesized(self.alloc(item))
                     },
+                    PunctuatorKind::LBracket => {
+                        // ArrayLiteral
+                        // [
+                        self.parse_bracket_expression(token)?
+                    },
                     PunctuatorKind::Div => {
                         let item = self.parse_literal_regular_expression()?;
                         Expression::RegularExpression(self.arena.alloc(item))
@@ -519,9 +556,9 @@ impl<'ast> Parser<'ast> {
                                     Token::Punctuator(punct) => {
                                         match punct.kind {
                                             PunctuatorKind::Comma => {
-                                                // FIXME: 需要处理优先级问题
-                                                // if precedence >= op_precedence {
-                                                if left_expr.precedence() >= op_precedence {
+                                                // NOTE: 数组元素、调用参数以及运算符的右操作数都不能吞掉后面的逗号，
+                                                //       `[1 + 2, 3]` 不能被解析为 `[1 + (2, 3)]`
+                                                if precedence >= op_precedence {
                                                     self.token.push(token3);
                                                     return Ok(left_expr);
                                                 }
@@ -563,8 +600,16 @@ impl<'ast> Parser<'ast> {
                             let mut loc = left_expr.loc();
                             let mut span = left_expr.span();
 
+                            // NOTE: `a || b ? c : d` 即 `(a || b) ? c : d`，二元与一元运算符的操作数不能吞掉 `?`
+                            if op_precedence <= precedence {
+                                self.token.push(token2);
+                                return Ok(left_expr);
+                            }
+
+                            // NOTE: 条件表达式是右结合的：`a ? b : c ? d : e` 即 `a ? b : (c ? d : e)`
+                            let branch_precedence = op_precedence - 1;
                             let token3 = self.token2()?;
-                            let and_then = self.parse_expression(token3, op_precedence)?;
+                            let and_then = self.parse_expression(token3, branch_precedence)?;
                             let mut token4 = self.token2()?;
                             
                             loop {
@@ -588,7 +633,7 @@ impl<'ast> Parser<'ast> {
                                 }
                             }
 
-                            let or_else = self.parse_expression(token4, op_precedence)?;
+                            let or_else = self.parse_expression(token4, branch_precedence)?;
                             
                             loc.end = or_else.loc().end;
                             span.end = or_else.span().end;
@@ -643,9 +688,9 @@ impl<'ast> Parser<'ast> {
 
                             left_expr = self.parse_member_expression(left_expr, token2)?;
                         },
-                        PunctuatorKind::LBracket => {
+                        PunctuatorKind::LBracket | PunctuatorKind::QuestionDot => {
                             // MemberAccessor
-                            // [
+                            // [ ?.
                             left_expr = self.parse_member_expression(left_expr, token2)?;
                         },
                         PunctuatorKind::LParen => {
@@ -667,8 +712,10 @@ impl<'ast> Parser<'ast> {
                                     return Err(self.unexpected_token(token2))
                                 },
                             };
+                            loc.end = arguments.loc.end;
+                            span.end = arguments.span.end;
 
-                            let item = CallExpression { loc, span, callee, arguments, };
+                            let item = CallExpression { loc, span, callee, arguments, optional: false, };
                             left_expr = Expression::Call(self.arena.alloc(item));
                         },
 
@@ -676,7 +723,7 @@ impl<'ast> Parser<'ast> {
                         PunctuatorKind::Add | PunctuatorKind::Sub | PunctuatorKind::Mul | PunctuatorKind::Div
                         | PunctuatorKind::Rem | PunctuatorKind::Pow
                         | PunctuatorKind::BitShl | PunctuatorKind::BitShr | PunctuatorKind::BitUShr
-                        | PunctuatorKind::And | PunctuatorKind::Or
+                        | PunctuatorKind::And | PunctuatorKind::Or | PunctuatorKind::Nullish
                         | PunctuatorKind::BitAnd | PunctuatorKind::BitXor | PunctuatorKind::BitOr
                         | PunctuatorKind::Gt | PunctuatorKind::Lt | PunctuatorKind::GtEq | PunctuatorKind::LtEq
                         | PunctuatorKind::Eq | PunctuatorKind::Neq | PunctuatorKind::StrictEq | PunctuatorKind::StrictNeq => {
@@ -691,8 +738,20 @@ impl<'ast> Parser<'ast> {
                                 return Ok(left_expr);
                             }
 
+                            // NOTE: `-a ** b` 有歧义，一元表达式（`++a`、`--a` 除外）不能作为 `**` 的左操作数
+                            if operator == InfixOperator::Pow && is_unary(&left_expr) {
+                                return Err(self.unexpected_token(token2));
+                            }
+
+                            // NOTE: `**` 是右结合的：`a ** b ** c` 即 `a ** (b ** c)`
+                            let right_precedence = if operator == InfixOperator::Pow { op_precedence - 1 } else { op_precedence };
                             let token3 = self.token2()?;
-                            let right_expr = self.parse_expression(token3, op_precedence)?;
+                            let right_expr = self.parse_expression(token3, right_precedence)?;
+
+                            // NOTE: `a ?? b || c` 与 `a || b ?? c` 必须加括号
+                            if is_mixed_nullish(operator, &left_expr) || is_mixed_nullish(operator, &right_expr) {
+                                return Err(self.unexpected_token(token2));
+                            }
 
                             loc.end = right_expr.loc().end;
                             span.end = right_expr.span().end;
@@ -705,7 +764,8 @@ impl<'ast> Parser<'ast> {
                         PunctuatorKind::Assign | PunctuatorKind::AddAssign | PunctuatorKind::SubAssign | PunctuatorKind::MulAssign 
                         | PunctuatorKind::DivAssign | PunctuatorKind::RemAssign | PunctuatorKind::PowAssign 
                         | PunctuatorKind::BitAndAssign | PunctuatorKind::BitOrAssign | PunctuatorKind::BitXorAssign 
-                        | PunctuatorKind::BitShlAssign | PunctuatorKind::BitShrAssign | PunctuatorKind::BitUShrAssign => {
+                        | PunctuatorKind::BitShlAssign | PunctuatorKind::BitShrAssign | PunctuatorKind::BitUShrAssign
+                        | PunctuatorKind::AndAssign | PunctuatorKind::OrAssign | PunctuatorKind::NullishAssign => {
                             let op_precedence = 3i8;
 
                             let operator = punctuator_to_assignment_op(punct.kind);
@@ -803,9 +863,8 @@ impl<'ast> Parser<'ast> {
         loop {
             let (lit_str, is_end) = self.lexer.read_literal_template_string()?;
             
-            if lit_str.raw.len() > 0 {
-                strings.push(lit_str);
-            }
+            // NOTE: 空字符串也要保留，`strings[i]` 总是在 `bounds[i]` 之前：`${a}b` 即 ["", "b"]
+            strings.push(lit_str);
 
             if is_end {
                 break;
@@ -898,7 +957,7 @@ impl<'ast> Parser<'ast> {
                                     let left = left_expr;
                                     let right = right_expr;
                                     let computed = false;
-                                    let item = MemberExpression { loc, span, left, right, computed, };
+                                    let item = MemberExpression { loc, span, left, right, computed, optional: false, };
 
                                     left_expr = Expression::Member(self.alloc(item));
                                 },
@@ -907,27 +966,7 @@ impl<'ast> Parser<'ast> {
                         },
                         PunctuatorKind::LBracket => {
                             // [
-                            let token2 = self.token2()?;
-
-                            let right_expr = self.parse_expression(token2, -1i8)?;
-
-                            loop {
-                                let end_token = self.token2()?;
-                                match end_token {
-                                    Token::LineTerminator => continue,
-                                    Token::Punctuator(punct) => match punct.kind {
-                                        PunctuatorKind::RBracket => {
-                                            break;
-                                        },
-                                        _ => {
-                                            return Err(self.unexpected_token(end_token));
-                                        }
-                                    },
-                                    _ => {
-                                        return Err(self.unexpected_token(end_token));
-                                    }
-                                }
-                            }
+                            let right_expr = self.parse_computed_property()?;
                             
                             let mut loc = left_expr.loc();
                             let mut span = left_expr.span();
@@ -938,10 +977,56 @@ impl<'ast> Parser<'ast> {
                             let left = left_expr;
                             let right = right_expr;
                             let computed = true;
-                            let item = MemberExpression { loc, span, left, right, computed, };
+                            let item = MemberExpression { loc, span, left, right, computed, optional: false, };
 
                             left_expr = Expression::Member(self.alloc(item));
                         },
+                        PunctuatorKind::QuestionDot => {
+                            // ?.
+                            let token2 = self.token2()?;
+
+                            let mut loc = left_expr.loc();
+                            let mut span = left_expr.span();
+
+                            match token2 {
+                                Token::Identifier(ident) => {
+                                    // a?.b
+                                    let right = Expression::Identifier(self.arena.alloc(ident));
+
+                                    loc.end = right.loc().end;
+                                    span.end = right.span().end;
+
+                                    let item = MemberExpression { loc, span, left: left_expr, right, computed: false, optional: true, };
+                                    left_expr = Expression::Member(self.alloc(item));
+                                },
+                                Token::Punctuator(punct2) if punct2.kind == PunctuatorKind::LBracket => {
+                                    // a?.[b]
+                                    let right = self.parse_computed_property()?;
+
+                                    loc.end = right.loc().end;
+                                    span.end = right.span().end;
+
+                                    let item = MemberExpression { loc, span, left: left_expr, right, computed: true, optional: true, };
+                                    left_expr = Expression::Member(self.alloc(item));
+                                },
+                                Token::Punctuator(punct2) if punct2.kind == PunctuatorKind::LParen => {
+                                    // a?.(b)
+                                    let arguments = match self.parse_expression(token2, op_precedence)? {
+                                        Expression::Parenthesized(inner) => *inner,
+                                        _ => {
+                                            return Err(self.unexpected_token(token2))
+                                        },
+                                    };
+
+                                    loc.end = arguments.loc.end;
+                                    span.end = arguments.span.end;
+
+                                    let item = CallExpression { loc, span, callee: left_expr, arguments, optional: true, };
+                                    left_expr = Expression::Call(self.arena.alloc(item));
+                                },
+                                _ => return Err(self.unexpected_token(token2)),
+                            }
+                        },
                         _ => {
                             self.token.push(token);
                             break;
@@ -967,6 +1052,32 @@ impl<'ast> Parser<'ast> {
         Ok(left_expr)
     }
 
+    // [ Expression ]
+    fn parse_computed_property(&mut self) -> Result<Expression<'ast>, Error> {
+        let token = self.token2()?;
+        let property = self.parse_expression(token, -1i8)?;
+
+        loop {
+            let end_token = self.token2()?;
+            match end_token {
+                Token::LineTerminator => continue,
+                Token::Punctuator(punct) => match punct.kind {
+                    PunctuatorKind::RBracket => {
+                        break;
+                    },
+                    _ => {
+                        return Err(self.unexpected_token(end_token));
+                    }
+                },
+                _ => {
+                    return Err(self.unexpected_token(end_token));
+                }
+            }
+        }
+
+        Ok(property)
+    }
+
     pub fn parse_new_expression(&mut self, token: Token<'ast>) -> Result<Expression<'ast>, Error> {
         let (mut loc, mut span) = match token {
             Token::Keyword(kw) => {
@@ -1076,6 +1187,39 @@ impl<'ast> Parser<'ast> {
             _ => unreachable!(),
         };
 
-        unimplemented!()
+        let mut elems: Vec<Option<Expression<'ast>>> = Vec::new();
+        // NOTE: 逗号之后期待一个元素，连续的逗号表示空位（`[a, , b]`）
+        let mut expect_elem = true;
+        loop {
+            let token2 = self.token2()?;
+            match token2 {
+                Token::LineTerminator => continue,
+                Token::Punctuator(punct) if punct.kind == PunctuatorKind::RBracket => {
+                    // ]
+                    loc.end = punct.loc.end;
+                    span.end = punct.span.end;
+                    break;
+                },
+                Token::Punctuator(punct) if punct.kind == PunctuatorKind::Comma => {
+                    if expect_elem {
+                        elems.push(None);
+                    }
+                    expect_elem = true;
+                },
+                _ => {
+                    if !expect_elem {
+                        return Err(self.unexpected_token(token2));
+                    }
+                    // NOTE: 元素是 AssignmentExpression ，在 `,` 或 `]` 处停止
+                    let elem = self.parse_expression(token2, 1i8)?;
+                    elems.push(Some(elem));
+                    expect_elem = false;
+                },
+            }
+        }
+
+        let elems = self.arena.alloc_vec(elems);
+        let item = ArrayLiteral { loc, span, elems };
+        Ok(Expression::ArrayLiteral(self.alloc(item)))
     }
-}
\ No newline at end of file
+}
@@ -17,6 +17,7 @@ use crate::ast::statement::{
 use crate::ast::expression::{
     Expression, LiteralTemplateExpression,
 };
+use crate::ast::node::NodeTable;
 
 use self::ParserErrorKind::*;
 
@@ -36,6 +37,8 @@ pub struct Parser<'ast> {
 
     pub body: Vec<Statement<'ast>>,
     pub tokens: Vec<Token<'ast>>,
+    // NOTE: parse 完成之后才会填充
+    pub nodes: NodeTable<'ast>,
 
     pub errors: Vec<Error>,
 }
@@ -47,9 +50,15 @@ impl<'ast> Parser<'ast> {
         let body = vec![];
         let token = Vec::with_capacity(1);
         let tokens = vec![];
+        let nodes = NodeTable::default();
         let errors = vec![];
         
-        Self { arena, lexer, body, token, tokens, errors, }
+        Self { arena, lexer, body, token, tokens, nodes, errors, }
+    }
+
+    /// Source offsets of the expressions annotated with `/* @__PURE__ */`.
+    pub fn pure_annotations(&self) -> &[usize] {
+        &self.lexer.pure_annotations
     }
     
     #[inline]
@@ -222,12 +231,13 @@ impl<'ast> Parser<'ast> {
                     },
                     PunctuatorKind::LParen => {
                         // (
-                        let expr = self.parse_expression(token, 20i8)?;
+                        let expr = self.parse_expression(token, expr_precedence)?;
                         Ok(Statement::Expression(self.alloc(expr)))
                     },
                     PunctuatorKind::LBracket => {
                         // [
-                        unimplemented!()
+                        let expr = self.parse_expression(token, expr_precedence)?;
+                        Ok(Statement::Expression(self.alloc(expr)))
                     },
                     PunctuatorKind::LBrace => {
                         // Block
@@ -267,6 +277,8 @@ impl<'ast> Parser<'ast> {
             }
         }
 
+        self.nodes = NodeTable::new(&self.body);
+
         Ok(())
     }
 }
@@ -18,6 +18,7 @@ use crate::ast::statement::{
     EmptyStatement, DebuggerStatement,
     BlockStatement,
     VariableStatement, LexicalDeclarationKind, LexicalBinding,
+    TryStatement,
 };
 use crate::ast::expression::{
     Expression, LiteralTemplateExpression,
@@ -93,6 +94,12 @@ impl<'ast> Parser<'ast> {
                         let func_decl = self.parse_function_declaration(token)?;
                         return Ok(Statement::Function(self.alloc(func_decl)));
                     },
+                    KeywordKind::Var | KeywordKind::Let | KeywordKind::Const => {
+                        return self.parse_variable_statement(token);
+                    },
+                    KeywordKind::Try => {
+                        return self.parse_try_statement(token);
+                    },
                     KeywordKind::Debugger => {
                         let loc =  kw.loc;
                         let span = kw.span;
@@ -112,14 +119,163 @@ impl<'ast> Parser<'ast> {
         }
     }
 
-    fn parse_lexical_binding(&mut self) -> Result<Vec<LexicalBinding>, Error> {
-        unimplemented!()
+    fn parse_lexical_binding(&mut self) -> Result<Vec<LexicalBinding<'ast>>, Error> {
+        let mut bindings: Vec<LexicalBinding<'ast>> = vec![];
+        loop {
+            // NOTE: 目前只支持标识符，还不支持解构模式
+            let token = self.token4()?;
+            let ident = match token {
+                Token::Identifier(ident) => ident,
+                _ => return Err(self.unexpected_token(token)),
+            };
+            let mut loc = ident.loc;
+            let mut span = ident.span;
+            let name = Expression::Identifier(self.alloc(ident));
+
+            let mut initializer = None;
+            let mut token2 = self.token()?;
+            match token2 {
+                Some(Token::Punctuator(punct)) if punct.kind == PunctuatorKind::Assign => {
+                    // =
+                    // NOTE: 初始值是 AssignmentExpression ，在 `,` 处停止
+                    let token3 = self.token2()?;
+                    let expr = self.parse_expression(token3, 1i8)?;
+                    loc.end = expr.loc().end;
+                    span.end = expr.span().end;
+                    initializer = Some(expr);
+                    token2 = self.token()?;
+                },
+                _ => { },
+            }
+
+            bindings.push(LexicalBinding { loc, span, name, initializer });
+
+            match token2 {
+                Some(Token::Punctuator(punct)) if punct.kind == PunctuatorKind::Comma => continue,
+                Some(token2) => {
+                    self.token.push(token2);
+                    break;
+                },
+                None => break,
+            }
+        }
+
+        Ok(bindings)
     }
     
     /// var/let/const
     pub fn parse_variable_statement(&mut self, token: Token<'ast>) -> Result<Statement<'ast>, Error> {
         // var/let/const
-        unimplemented!()
+        let (kind, mut loc, mut span) = match token {
+            Token::Keyword(kw) => {
+                let kind = match kw.kind {
+                    KeywordKind::Var => LexicalDeclarationKind::Var,
+                    KeywordKind::Let => LexicalDeclarationKind::Let,
+                    KeywordKind::Const => LexicalDeclarationKind::Const,
+                    _ => unreachable!(),
+                };
+                (kind, kw.loc, kw.span)
+            },
+            _ => unreachable!(),
+        };
+
+        let bindings = self.parse_lexical_binding()?;
+        if let Some(binding) = bindings.last() {
+            loc.end = binding.loc.end;
+            span.end = binding.span.end;
+        }
+
+        let declarators = self.arena.alloc_vec(bindings);
+        let item = VariableStatement { loc, span, kind, declarators };
+        Ok(Statement::Variable(self.alloc(item)))
+    }
+
+    /// try/catch/finally
+    pub fn parse_try_statement(&mut self, token: Token<'ast>) -> Result<Statement<'ast>, Error> {
+        let (mut loc, mut span) = match token {
+            Token::Keyword(kw) => (kw.loc, kw.span),
+            _ => unreachable!(),
+        };
+
+        let body = self.parse_clause_block()?;
+        loc.end = body.loc.end;
+        span.end = body.span.end;
+
+        let mut catch_parameter = None;
+        let mut catch_body = None;
+        if self.parse_optional_keyword(KeywordKind::Catch)? {
+            let mut token2 = self.token4()?;
+            match token2 {
+                Token::Punctuator(punct) if punct.kind == PunctuatorKind::LParen => {
+                    // NOTE: 目前只支持标识符，还不支持解构模式
+                    let token3 = self.token4()?;
+                    match token3 {
+                        Token::Identifier(ident) => catch_parameter = Some(Expression::Identifier(self.alloc(ident))),
+                        _ => return Err(self.unexpected_token(token3)),
+                    }
+                    let token4 = self.token4()?;
+                    match token4 {
+                        Token::Punctuator(punct) if punct.kind == PunctuatorKind::RParen => { },
+                        _ => return Err(self.unexpected_token(token4)),
+                    }
+                },
+                // NOTE: ES2019 允许省略 catch 的参数：`catch { }`
+                _ => self.token.push(token2),
+            }
+
+            let block = self.parse_clause_block()?;
+            loc.end = block.loc.end;
+            span.end = block.span.end;
+            catch_body = Some(block);
+        }
+
+        let mut finally = None;
+        if self.parse_optional_keyword(KeywordKind::Finally)? {
+            let block = self.parse_clause_block()?;
+            loc.end = block.loc.end;
+            span.end = block.span.end;
+            finally = Some(block);
+        }
+
+        if catch_body.is_none() && finally.is_none() {
+            // NOTE: `try { }` 之后必须有 catch 或 finally 子句
+            let token2 = self.token2()?;
+            return Err(self.unexpected_token(token2));
+        }
+
+        let item = TryStatement { loc, span, body, catch_parameter, catch_body, finally };
+        Ok(Statement::Try(self.alloc(item)))
+    }
+
+    /// The `{ }` of `try`, `catch` and `finally`.
+    fn parse_clause_block(&mut self) -> Result<BlockStatement<'ast>, Error> {
+        let token = self.token4()?;
+        match token {
+            Token::Punctuator(punct) if punct.kind == PunctuatorKind::LBrace => self.parse_block_statement(token),
+            _ => Err(self.unexpected_token(token)),
+        }
+    }
+
+    /// Consumes the next token when it is the keyword `kind`.
+    fn parse_optional_keyword(&mut self, kind: KeywordKind) -> Result<bool, Error> {
+        loop {
+            let token = match self.token()? {
+                Some(token) => token,
+                None => return Ok(false),
+            };
+            let token = match token {
+                Token::LineTerminator => continue,
+                Token::Identifier(ident) => ident.to_keyword_or_literal().unwrap_or(token),
+                _ => token,
+            };
+            match token {
+                Token::Keyword(kw) if kw.kind == kind => return Ok(true),
+                _ => {
+                    self.token.push(token);
+                    return Ok(false);
+                },
+            }
+        }
     }
 
     pub fn parse_async_statement(&mut self, token: Token<'ast>) -> Result<Statement<'ast>, Error> {
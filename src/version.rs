@@ -34,10 +34,16 @@ impl ECMAScriptVersion {
     pub const ES2017: ECMAScriptVersion = ECMAScriptVersion { major: 8, minor: 0, micro: 0 };
     // ECMAScript 2018 (ES2018): https://www.ecma-international.org/ecma-262/9.0/index.html
     pub const ES2018: ECMAScriptVersion = ECMAScriptVersion { major: 9, minor: 0, micro: 0 };
+    // ECMAScript 2019 (ES2019): https://www.ecma-international.org/ecma-262/10.0/index.html
+    pub const ES2019: ECMAScriptVersion = ECMAScriptVersion { major: 10, minor: 0, micro: 0 };
+    // ECMAScript 2020 (ES2020): https://www.ecma-international.org/ecma-262/11.0/index.html
+    pub const ES2020: ECMAScriptVersion = ECMAScriptVersion { major: 11, minor: 0, micro: 0 };
+    // ECMAScript 2021 (ES2021): https://www.ecma-international.org/ecma-262/12.0/index.html
+    pub const ES2021: ECMAScriptVersion = ECMAScriptVersion { major: 12, minor: 0, micro: 0 };
 
     pub const ESNEXT: ECMAScriptVersion = ECMAScriptVersion { major: MAX_U16, minor: MAX_U16, micro: MAX_U16 };
 
-    pub const LATEST: ECMAScriptVersion = Self::ES2018;
+    pub const LATEST: ECMAScriptVersion = Self::ES2021;
 }
 
 impl ECMAScriptVersion {
@@ -49,6 +55,9 @@ impl ECMAScriptVersion {
             Self::ES2016 => Date { year: 2016, month:  6, day: 0 },
             Self::ES2017 => Date { year: 2017, month:  6, day: 0 },
             Self::ES2018 => Date { year: 2018, month:  6, day: 0 },
+            Self::ES2019 => Date { year: 2019, month:  6, day: 0 },
+            Self::ES2020 => Date { year: 2020, month:  6, day: 0 },
+            Self::ES2021 => Date { year: 2021, month:  6, day: 0 },
             Self::ESNEXT => Date { year: MAX_U16, month:  12, day: 30 },
             _ => unreachable!(),
         }
@@ -76,6 +85,9 @@ impl fmt::Display for ECMAScriptVersion {
             Self::ES2016 => write!(f, "ES{}(ES7.0)", self.published_at().year),
             Self::ES2017 => write!(f, "ES{}(ES8.0)", self.published_at().year),
             Self::ES2018 => write!(f, "ES{}(ES9.0)", self.published_at().year),
+            Self::ES2019 => write!(f, "ES{}(ES10.0)", self.published_at().year),
+            Self::ES2020 => write!(f, "ES{}(ES11.0)", self.published_at().year),
+            Self::ES2021 => write!(f, "ES{}(ES12.0)", self.published_at().year),
             Self::ESNEXT => write!(f, "ESNEXT"),
             _ => unreachable!(),
         }
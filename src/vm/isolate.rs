@@ -1,31 +1,94 @@
-use crate::error::Error;
+use crate::toolshed::Arena;
+use crate::error::{ ErrorKind, Error, };
 use crate::rc_ref::RcRef;
+use crate::parser::Parser;
+use crate::compiler::scope::Goal;
+use crate::compiler::bytecode::CodeObject;
+use crate::compiler::bytecodegen;
+use crate::compiler::cache;
 use crate::vm::value::Value;
 use crate::vm::value::symbol::{ SymbolRegistry, SymbolRegistryRef, };
 use crate::vm::scope::{ Scope, ScopeRef, };
 
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
 use std::rc::{ Rc, };
 use std::cell::{ Cell, Ref, RefMut, RefCell, };
 
 
 pub type IsolateRef = RcRef<Isolate>;
 
+/// Runs compiled code objects, implemented by the interpreter in `crates/vm`.
+pub trait Executor: fmt::Debug {
+    fn execute(&mut self, code: CodeObject) -> Result<Value, Error>;
+
+    /// Run `code` compiled from `source`, runtime errors are located in the source.
+    fn execute_source(&mut self, code: CodeObject, _filename: &str, _source: &str) -> Result<Value, Error> {
+        self.execute(code)
+    }
+
+    fn heap_stats(&self) -> HeapStats {
+        HeapStats::default()
+    }
+}
+
+/// Memory usage and collections of the heap of an `Executor`.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct HeapStats {
+    // NOTE: 堆上各类值的数量，包含还没有被回收的垃圾
+    pub strings: usize,
+    pub bigints: usize,
+    pub objects: usize,
+    pub functions: usize,
+    pub environments: usize,
+    pub cells: usize,
+    /// Bytes of the values on the heap.
+    pub heap_bytes: usize,
+    /// Bytes of the values in the young generation, they are collected by minor collections.
+    pub young_bytes: usize,
+    /// Bytes allocated since the heap was created.
+    pub allocated_bytes: u64,
+    pub minor_collections: usize,
+    pub major_collections: usize,
+    pub last_pause: Duration,
+    pub max_pause: Duration,
+    pub total_pause: Duration,
+}
+
+impl HeapStats {
+    pub fn values(&self) -> usize {
+        self.strings + self.bigints + self.objects + self.functions + self.environments + self.cells
+    }
+
+    pub fn collections(&self) -> usize {
+        self.minor_collections + self.major_collections
+    }
+}
+
 #[derive(Debug)]
 pub struct Isolate {
     scope_ref: Option<ScopeRef>, // Global
     symbol_register_ref: SymbolRegistryRef,
+    executor: RefCell<Box<dyn Executor>>,
 }
 
 impl Isolate {
-    pub fn new() -> Self {
+    /// An isolate runs scripts with `executor`, the interpreter is `vm::Vm` in `crates/vm`.
+    pub fn new<E: Executor + 'static>(executor: E) -> Self {
         let symbol_register_ref = SymbolRegistryRef::new(SymbolRegistry::new());
         
         Isolate {
             scope_ref: None,
             symbol_register_ref: symbol_register_ref,
+            executor: RefCell::new(Box::new(executor)),
         }
     }
 
+    pub fn set_executor<E: Executor + 'static>(&self, executor: E) {
+        *self.executor.borrow_mut() = Box::new(executor);
+    }
+
     pub fn set_global_scope(&mut self, scope_ref: ScopeRef) {
         self.scope_ref = Some(scope_ref);
     }
@@ -41,22 +104,96 @@ impl Isolate {
         &self.symbol_register_ref
     }
     
+    // NOTE: 编译不需要执行器，`esc` 等工具直接使用 `Isolate::compile`
+    pub fn compile(source: &str, filename: &str, goal: Goal) -> Result<CodeObject, Error> {
+        let arena = Arena::new();
+        let source = arena.alloc_vec(source.chars().collect::<Vec<char>>());
+        let filename = arena.alloc_str(filename);
+
+        let mut parser = Parser::new(&arena, &source, &filename);
+        parser.parse()?;
+
+        bytecodegen::compile(&parser.body, goal)
+    }
+
+    /// Load the code object from the cache file when it was compiled from the same source,
+    /// otherwise compile the source and write the cache file.
+    pub fn compile_cached<P: AsRef<Path>>(source: &str, filename: &str, goal: Goal, cache_path: P) -> Result<CodeObject, Error> {
+        let cache_path = cache_path.as_ref();
+        if cache_path.exists() {
+            match cache::read(cache_path, source, goal) {
+                Ok(code) => return Ok(code),
+                Err(error) => debug!("recompile {}: {}", filename, error.message()),
+            }
+        }
+
+        let code = Isolate::compile(source, filename, goal)?;
+        if let Err(error) = cache::write(cache_path, &code, source, goal) {
+            warn!("{}", error.message());
+        }
+
+        Ok(code)
+    }
+
+    pub fn execute(&self, code: CodeObject) -> Result<Value, Error> {
+        self.executor.borrow_mut().execute(code)
+    }
+
+    /// Statistics of the heap of the executor.
+    pub fn heap_stats(&self) -> HeapStats {
+        self.executor.borrow().heap_stats()
+    }
+
     pub fn run_script(&self, script: &str) -> Result<Value, Error> {
-        unimplemented!()
+        let code = Isolate::compile(script, "<script>", Goal::Script)?;
+        self.executor.borrow_mut().execute_source(code, "<script>", script)
+    }
+
+    pub fn run_script_cached<P: AsRef<Path>>(&self, script: &str, cache_path: P) -> Result<Value, Error> {
+        let code = Isolate::compile_cached(script, "<script>", Goal::Script, cache_path)?;
+        self.executor.borrow_mut().execute_source(code, "<script>", script)
     }
 
     pub fn run_module(&self, module: &str) -> Result<Value, Error> {
-        unimplemented!()
+        let code = Isolate::compile(module, "<module>", Goal::Module)?;
+        self.executor.borrow_mut().execute_source(code, "<module>", module)
     }
 }
 
 
 #[test]
 fn test_isolate() {
-    let mut isolate_ref = IsolateRef::new(Isolate::new());
+    // NOTE: 不执行代码的执行器，只用来构造 Isolate
+    #[derive(Debug)]
+    struct Noop;
+
+    impl Executor for Noop {
+        fn execute(&mut self, _code: CodeObject) -> Result<Value, Error> {
+            Ok(Value::Undefined(crate::vm::value::Undefined))
+        }
+    }
+
+    let isolate_ref = IsolateRef::new(Isolate::new(Noop));
 
     let global_scope_ref = ScopeRef::new(Scope::new(isolate_ref.clone(), None));
     
     isolate_ref.borrow_mut().set_global_scope(global_scope_ref);
 
-}
\ No newline at end of file
+    assert!(isolate_ref.borrow().run_script("1 + 1").is_ok());
+    assert_eq!(isolate_ref.borrow().heap_stats(), HeapStats::default());
+}
+
+#[test]
+fn test_isolate_compile() {
+    // NOTE: 解释器在 crates/vm 中（`run_script` 的测试见 `vm::test_run_script`），这里只检查编译阶段
+    assert!(Isolate::compile("1 + 1", "<script>", Goal::Script).is_ok());
+    assert_eq!(Isolate::compile("1 +", "<script>", Goal::Script).unwrap_err().kind(), ErrorKind::SyntaxError);
+
+    let cache_path = std::env::temp_dir().join(format!("ecmascript-isolate-{}.esbc", std::process::id()));
+    let code = Isolate::compile_cached("1 + 1", "<script>", Goal::Script, &cache_path).unwrap();
+    assert_eq!(cache::read(&cache_path, "1 + 1", Goal::Script), Ok(code.clone()));
+    assert_eq!(Isolate::compile_cached("1 + 1", "<script>", Goal::Script, &cache_path), Ok(code));
+    let code = Isolate::compile_cached("2 * 3", "<script>", Goal::Script, &cache_path).unwrap();
+    assert_eq!(cache::read(&cache_path, "2 * 3", Goal::Script), Ok(code));
+    std::fs::remove_file(&cache_path).unwrap();
+}
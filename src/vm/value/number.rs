@@ -54,7 +54,8 @@ impl Number {
         self.clone().into()
     }
     pub fn toInt32(&self) -> i32 {
-        self.clone().into()
+        // NOTE: 对 2^32 取模后解释为有符号整数，与 `as i32` 的饱和转换不同。
+        self.toUint32() as i32
     }
     pub fn toUint8(&self) -> u8 {
         self.clone().into()
@@ -63,7 +64,11 @@ impl Number {
         self.clone().into()
     }
     pub fn toUint32(&self) -> u32 {
-        self.clone().into()
+        if !self.0.is_finite() {
+            return 0;
+        }
+
+        self.0.trunc().rem_euclid(4294967296.0f64) as u32
     }
     
     pub fn toUint8Clamp(&self) -> u8 {
@@ -270,8 +275,6 @@ impl Number {
         // If base < 0 and base is finite and exponent is finite and exponent is not an integer, the result is NaN.
         let integer_part = exponent.0.trunc();
         let fractional_part = exponent.0 - integer_part;
-        
-        assert_eq!(fractional_part >= 0.0, true);
 
         let exponent_is_integer = fractional_part == 0.0f64;
 
@@ -281,7 +284,12 @@ impl Number {
                 return f64::NAN.into();
         }
 
-        unreachable!()
+        self.0.powf(exponent.0).into()
+    }
+
+    /// Unsigned Right Shift Operator ( >>> )
+    pub fn unsigned_shr(&self, rhs: &Number) -> Number {
+        ( self.toUint32() >> (rhs.toUint32() & 0x1F) ).into()
     }
 
     pub fn unary_plus(&self) -> Number {
@@ -375,14 +383,14 @@ impl ops::Shl for Number {
         // NOTE:
         // Let shiftCount be the result of masking out all but the least significant 5 bits of rnum, that is, compute rnum & 0x1F.
         // Return the result of left shifting lnum by shiftCount bits. The result is a signed 32-bit integer. 
-        ( self.toInt32() << rhs.toUint32() ).into()
+        self.toInt32().wrapping_shl(rhs.toUint32() & 0x1F).into()
     }
 }
 impl<'a> ops::Shl for &'a Number {
     type Output = Number;
     
     fn shl(self, rhs: &'a Number) -> Self::Output {
-        ( self.toInt32() << rhs.toUint32() ).into()
+        self.toInt32().wrapping_shl(rhs.toUint32() & 0x1F).into()
     }
 }
 
@@ -393,17 +401,16 @@ impl ops::Shr for Number {
         // NOTE:
         // Let shiftCount be the result of masking out all but the least significant 5 bits of rnum, that is, compute rnum & 0x1F.
         // Return the result of left shifting lnum by shiftCount bits. The result is a signed 32-bit integer. 
-        ( self.toInt32() >> rhs.toUint32() ).into()
+        ( self.toInt32() >> (rhs.toUint32() & 0x1F) ).into()
     }
 }
 impl<'a> ops::Shr for &'a Number {
     type Output = Number;
     
     fn shr(self, rhs: &'a Number) -> Self::Output {
-        ( self.toInt32() >> rhs.toUint32() ).into()
+        ( self.toInt32() >> (rhs.toUint32() & 0x1F) ).into()
     }
 }
-// TODO: Unsigned Right Shift Operator ( >>> )
 
 impl ops::Neg for Number {
     type Output = Number;
@@ -493,8 +500,36 @@ impl fmt::Display for Number {
             } else {
                 write!(f, "Infinity")
             }
+        } else if self.0 == 0.0f64 {
+            // NOTE: -0 的字符串形式也是 "0"
+            write!(f, "0")
+        } else if self.0 < 0.0f64 {
+            write!(f, "-{}", Number(-self.0))
         } else {
-            write!(f, "{}", self.0)
+            // https://www.ecma-international.org/ecma-262/8.0/#sec-tostring-applied-to-the-number-type
+            // NOTE: `{:e}` 给出能够还原该值的最短的十进制数字，再按规范决定小数点和指数的位置。
+            let repr = format!("{:e}", self.0);
+            let mut parts = repr.splitn(2, 'e');
+            let digits = parts.next().unwrap_or("0").replace('.', "");
+            let exponent = parts.next().and_then(|e| e.parse::<i32>().ok()).unwrap_or(0);
+
+            let k = digits.len() as i32;
+            let n = exponent + 1;
+
+            if k <= n && n <= 21 {
+                write!(f, "{}{}", digits, "0".repeat((n - k) as usize))
+            } else if 0 < n && n <= 21 {
+                write!(f, "{}.{}", &digits[..n as usize], &digits[n as usize..])
+            } else if -6 < n && n <= 0 {
+                write!(f, "0.{}{}", "0".repeat((-n) as usize), digits)
+            } else {
+                let sign = if n - 1 < 0 { '-' } else { '+' };
+                if k == 1 {
+                    write!(f, "{}e{}{}", digits, sign, (n - 1).abs())
+                } else {
+                    write!(f, "{}.{}e{}{}", &digits[..1], &digits[1..], sign, (n - 1).abs())
+                }
+            }
         }
     }
 }
@@ -658,34 +693,56 @@ impl From<Boolean> for Number {
         rust_bool.into()
     }
 }
+impl<'a> From<&'a str> for Number {
+    fn from(s: &'a str) -> Self {
+        // https://www.ecma-international.org/ecma-262/8.0/#sec-tonumber-applied-to-the-string-type
+        use std::f64::{ NAN, INFINITY, NEG_INFINITY, };
+
+        let is_whitespace = |c: char| c.is_whitespace() || c == '\u{feff}';
+        let s = s.trim_matches(is_whitespace);
+        if s.is_empty() {
+            return 0.0f64.into();
+        }
+
+        let radix = match s.get(..2) {
+            Some("0x") | Some("0X") => 16,
+            Some("0o") | Some("0O") => 8,
+            Some("0b") | Some("0B") => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            let digits = &s[2..];
+            if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+                return NAN.into();
+            }
+            return digits.chars().fold(0.0f64, |n, c| n * radix as f64 + c.to_digit(radix).unwrap_or(0) as f64).into();
+        }
+
+        match s {
+            "Infinity" | "+Infinity" => return INFINITY.into(),
+            "-Infinity" => return NEG_INFINITY.into(),
+            _ => { },
+        }
+
+        // NOTE: Rust 还接受 `inf`、`NaN` 这样的写法
+        let is_decimal = s.chars().all(|c| c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || c == '+' || c == '-');
+        if !is_decimal {
+            return NAN.into();
+        }
+
+        s.parse::<f64>().unwrap_or(NAN).into()
+    }
+}
 impl From<String> for Number {
     fn from(s: String) -> Self {
-        // NOTE: Should parse ECMAScript Number KEYWORDS
-        //       -Infinity
-        //       +Infinity
-        //       Infinity
-        //       -NaN
-        //       +NaN
-        //       NaN
-        use std::f64::NAN;
-
         let rust_s: ::std::string::String = s.into();
-        rust_s.parse::<f64>().unwrap_or(NAN).into()
+        Number::from(rust_s.as_str())
     }
 }
 impl<'a> From<&'a String> for Number {
     fn from(s: &'a String) -> Self {
-        // NOTE: Should parse ECMAScript Number KEYWORDS
-        //       -Infinity
-        //       +Infinity
-        //       Infinity
-        //       -NaN
-        //       +NaN
-        //       NaN
-        use std::f64::NAN;
-
-        let rust_s: ::std::string::String = s.to_string().into();
-        rust_s.parse::<f64>().unwrap_or(NAN).into()
+        let rust_s: ::std::string::String = s.to_string();
+        Number::from(rust_s.as_str())
     }
 }
 impl From<Object> for Number {
@@ -805,6 +862,35 @@ fn test_to_fixed() {
 
 #[test]
 fn test_pow() {
-    // assert_eq!(Number(2.0f64).pow(&Number(2.00f64)), Number(4.928400000000001f64) );
+    use std::f64;
+
+    assert_eq!(Number(2.0f64).pow(&Number(10.0f64)), Number(1024.0f64));
+    assert_eq!(Number(4.0f64).pow(&Number(-0.5f64)), Number(0.5f64));
+    assert_eq!(Number(-8.0f64).pow(&Number(1.0f64 / 3.0f64)), Number(f64::NAN));
+    assert_eq!(Number(f64::NAN).pow(&Number(0.0f64)), Number(1.0f64));
+}
+
+#[test]
+fn test_number_to_string() {
+    use std::f64;
+
+    assert_eq!(Number(0.1f64 + 0.2f64).to_string(), "0.30000000000000004");
+    assert_eq!(Number(1e21f64).to_string(), "1e+21");
+    assert_eq!(Number(123456789012345680000.0f64).to_string(), "123456789012345680000");
+    assert_eq!(Number(1.5e-7f64).to_string(), "1.5e-7");
+    assert_eq!(Number(0.000001f64).to_string(), "0.000001");
+    assert_eq!(Number(-0.0f64).to_string(), "0");
+    assert_eq!(Number(-42.0f64).to_string(), "-42");
+    assert_eq!(Number(f64::NAN).to_string(), "NaN");
+
+    assert_eq!(Number::from(" 0x1F\n"), Number(31.0f64));
+    assert_eq!(Number::from(""), Number(0.0f64));
+    assert_eq!(Number::from("-Infinity"), Number(f64::NEG_INFINITY));
+    assert_eq!(Number::from("inf"), Number(f64::NAN));
+
+    assert_eq!(Number(4294967297.0f64).toInt32(), 1);
+    assert_eq!(Number(2147483648.0f64).toInt32(), -2147483648);
+    assert_eq!(Number(-1.0f64).unsigned_shr(&Number(0.0f64)), Number(4294967295.0f64));
+    assert_eq!(Number(1.0f64) << Number(33.0f64), Number(2.0f64));
 }
 